@@ -1,48 +1,121 @@
 /// Alert threshold evaluation and webhook dispatch.
 ///
 /// Evaluates configurable thresholds against current metrics:
-///   - CPU temperature > X°C
+///   - CPU temperature ≥ X°C (warning) / ≥ Y°C (critical), with hysteresis
 ///   - Packet loss > X%
 ///   - Latency p95 > X ms
 ///   - Client disconnected for > X seconds
 ///   - MIDI device unplugged
+///   - No MIDI received from any input for > X seconds (while clients are
+///     connected and MIDI has been seen before)
 ///   - Standby host unreachable
 ///   - Disk space < X%
 ///
 /// Alert lifecycle: pending → active → resolved
 /// Dispatch: dashboard banner + WebSocket push + optional HTTP webhook
+/// (sent when an alert triggers and again when it clears, with retries)
+/// + optional email for alerts at or above a severity (see `email`)
+///
+/// Operators can acknowledge an active alert (it stays listed but stops
+/// notifying, including if it flaps and re-fires within `ACK_HOLD_SECS`)
+/// or snooze it (hidden from the active list until the snooze ends; if
+/// it is still active then, it notifies again).
 
 use std::collections::HashMap;
 use std::sync::Mutex;
 
 use serde::{Deserialize, Serialize};
 
+use crate::email::EmailBatcher;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlertConfig {
+    /// Critical thermal alert at or above this temperature
     pub cpu_temp_max_c: f32,
+    /// Warning thermal alert at or above this temperature
+    #[serde(default = "default_cpu_temp_warn_c")]
+    pub cpu_temp_warn_c: f32,
+    /// How far the temperature must fall below a threshold to clear it
+    #[serde(default = "default_cpu_temp_hysteresis_c")]
+    pub cpu_temp_hysteresis_c: f32,
     pub packet_loss_max_percent: f32,
     pub latency_p95_max_ms: f32,
     pub client_disconnect_max_secs: u64,
     pub disk_free_min_mb: u64,
+    /// Alert after this long without any incoming MIDI (0 = off)
+    #[serde(default = "default_midi_silence_max_secs")]
+    pub midi_silence_max_secs: u64,
+    /// Clients this deployment should have; each missing one lowers the
+    /// health score (0 = not configured)
+    #[serde(default)]
+    pub expected_clients: u32,
     pub webhook_url: Option<String>,
     pub webhook_enabled: bool,
+    /// JSON body template for the webhook (see `webhook_payload`); the
+    /// default payload is sent when unset
+    #[serde(default)]
+    pub webhook_template: Option<String>,
+    /// SMTP notifications; off when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<crate::email::EmailConfig>,
 }
 
 impl Default for AlertConfig {
     fn default() -> Self {
         Self {
             cpu_temp_max_c: 80.0,
+            cpu_temp_warn_c: default_cpu_temp_warn_c(),
+            cpu_temp_hysteresis_c: default_cpu_temp_hysteresis_c(),
             packet_loss_max_percent: 1.0,
             latency_p95_max_ms: 10.0,
             client_disconnect_max_secs: 30,
             disk_free_min_mb: 100,
+            midi_silence_max_secs: default_midi_silence_max_secs(),
+            expected_clients: 0,
             webhook_url: None,
             webhook_enabled: false,
+            webhook_template: None,
+            email: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+fn default_cpu_temp_warn_c() -> f32 { 70.0 }
+fn default_cpu_temp_hysteresis_c() -> f32 { 5.0 }
+fn default_midi_silence_max_secs() -> u64 { 120 }
+
+/// Thermal alert level. Moving up happens as soon as a threshold is
+/// reached; moving down only once the temperature is `hysteresis` below
+/// it, so a Pi hovering at the threshold doesn't flap.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThermalLevel {
+    #[default]
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl ThermalLevel {
+    /// The level after reading `temp_c`, given the current level.
+    pub fn next(self, temp_c: f32, config: &AlertConfig) -> Self {
+        let (warn, crit, hyst) = (config.cpu_temp_warn_c, config.cpu_temp_max_c, config.cpu_temp_hysteresis_c);
+        if temp_c >= crit {
+            return ThermalLevel::Critical;
+        }
+        let above_warn = match self {
+            ThermalLevel::Normal => temp_c >= warn,
+            ThermalLevel::Warning | ThermalLevel::Critical => temp_c >= warn - hyst,
+        };
+        match self {
+            ThermalLevel::Critical if temp_c >= crit - hyst => ThermalLevel::Critical,
+            _ if above_warn => ThermalLevel::Warning,
+            _ => ThermalLevel::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum AlertSeverity {
     Warning,
     Critical,
@@ -64,6 +137,23 @@ pub struct Alert {
     pub triggered_at: u64,  // Unix timestamp
     pub resolved_at: Option<u64>,
     pub source: String, // "cpu_temp", "packet_loss", etc.
+    /// An operator has seen it; no further notifications
+    #[serde(default)]
+    pub acknowledged: bool,
+    /// Hidden from the active list until this time (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<u64>,
+}
+
+/// An acknowledged alert that clears and re-fires within this many seconds
+/// comes back acknowledged instead of notifying again
+const ACK_HOLD_SECS: u64 = 600;
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
 }
 
 pub struct AlertManager {
@@ -71,6 +161,12 @@ pub struct AlertManager {
     active_alerts: Mutex<HashMap<String, Alert>>,
     history: Mutex<Vec<Alert>>,
     alert_counter: Mutex<u32>,
+    thermal_level: Mutex<ThermalLevel>,
+    /// When MIDI was last seen coming in (None until the first message)
+    last_midi_activity: Mutex<Option<u64>>,
+    email: EmailBatcher,
+    /// Sources whose acknowledged alert resolved, and when
+    acked_sources: Mutex<HashMap<String, u64>>,
 }
 
 impl AlertManager {
@@ -80,24 +176,43 @@ impl AlertManager {
             active_alerts: Mutex::new(HashMap::new()),
             history: Mutex::new(Vec::new()),
             alert_counter: Mutex::new(0),
+            thermal_level: Mutex::new(ThermalLevel::Normal),
+            last_midi_activity: Mutex::new(None),
+            email: EmailBatcher::new(),
+            acked_sources: Mutex::new(HashMap::new()),
         }
     }
 
     /// Evaluate current metrics against thresholds and fire/resolve alerts
     pub fn evaluate(&self, metrics: &EvalMetrics) {
+        self.evaluate_at(metrics, now_secs());
+    }
+
+    fn evaluate_at(&self, metrics: &EvalMetrics, now: u64) {
         let config = self.config.lock().unwrap().clone();
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        self.expire_snoozes(&config, now);
 
-        // CPU temperature
+        // CPU temperature: one alert per level, so a warning resolves when
+        // it escalates to critical and comes back when it cools down to warning
+        let thermal = {
+            let mut level = self.thermal_level.lock().unwrap();
+            *level = level.next(metrics.cpu_temp_c, &config);
+            *level
+        };
+        self.check_threshold(
+            &config,
+            "cpu_temp_warning",
+            thermal == ThermalLevel::Warning,
+            AlertSeverity::Warning,
+            format!("CPU temperature {:.1}°C reached {:.0}°C", metrics.cpu_temp_c, config.cpu_temp_warn_c),
+            now,
+        );
         self.check_threshold(
             &config,
             "cpu_temp",
-            metrics.cpu_temp_c > config.cpu_temp_max_c,
+            thermal == ThermalLevel::Critical,
             AlertSeverity::Critical,
-            format!("CPU temperature {:.1}°C exceeds {:.0}°C", metrics.cpu_temp_c, config.cpu_temp_max_c),
+            format!("CPU temperature {:.1}°C reached {:.0}°C", metrics.cpu_temp_c, config.cpu_temp_max_c),
             now,
         );
 
@@ -131,6 +246,28 @@ impl AlertManager {
             now,
         );
 
+        // MIDI silence: no input on any controller. Device presence is the
+        // midi_device alert; this catches devices that are present but quiet
+        // (a dead hub, both controllers gone idle mid-show).
+        let silent_secs = {
+            let mut last = self.last_midi_activity.lock().unwrap();
+            if metrics.midi_messages_per_sec > 0.0 {
+                *last = Some(now);
+            }
+            last.map(|t| now.saturating_sub(t))
+        };
+        if config.midi_silence_max_secs > 0 {
+            let silent_secs = silent_secs.unwrap_or(0);
+            self.check_threshold(
+                &config,
+                "midi_silence",
+                metrics.client_count > 0 && silent_secs >= config.midi_silence_max_secs,
+                AlertSeverity::Warning,
+                format!("No MIDI input for {}s", silent_secs),
+                now,
+            );
+        }
+
         // Standby host unreachable
         self.check_threshold(
             &config,
@@ -170,6 +307,13 @@ impl AlertManager {
             if !active.contains_key(source) {
                 let mut counter = self.alert_counter.lock().unwrap();
                 *counter += 1;
+                // A recently acknowledged alert flapping back stays acknowledged
+                let acknowledged = self
+                    .acked_sources
+                    .lock()
+                    .unwrap()
+                    .remove(source)
+                    .is_some_and(|resolved_at| now.saturating_sub(resolved_at) < ACK_HOLD_SECS);
                 let alert = Alert {
                     id: format!("alert-{}", *counter),
                     severity,
@@ -179,6 +323,8 @@ impl AlertManager {
                     triggered_at: now,
                     resolved_at: None,
                     source: source.to_string(),
+                    acknowledged,
+                    snoozed_until: None,
                 };
                 active.insert(source.to_string(), alert.clone());
 
@@ -192,11 +338,8 @@ impl AlertManager {
                     }
                 }
 
-                // Dispatch webhook if configured and enabled
-                if config.webhook_enabled {
-                    if let Some(ref url) = config.webhook_url {
-                        dispatch_webhook(url.clone(), alert);
-                    }
+                if !acknowledged {
+                    self.notify(config, alert, AlertEvent::Triggered);
                 }
             }
         } else {
@@ -206,15 +349,137 @@ impl AlertManager {
                 alert.resolved_at = Some(now);
 
                 if let Ok(mut hist) = self.history.lock() {
-                    hist.push(alert);
+                    hist.push(alert.clone());
+                }
+
+                if alert.acknowledged {
+                    self.acked_sources.lock().unwrap().insert(source.to_string(), now);
+                } else {
+                    self.notify(config, alert, AlertEvent::Cleared);
                 }
             }
         }
     }
 
-    /// Get all currently active alerts
+    /// Send an alert event to the configured sinks (webhook; email for
+    /// newly triggered alerts).
+    fn notify(&self, config: &AlertConfig, alert: Alert, event: AlertEvent) {
+        if event == AlertEvent::Triggered {
+            if let Some(ref email) = config.email {
+                self.email.push(email, &alert);
+            }
+        }
+        dispatch_webhook(config, alert, event);
+    }
+
+    /// Un-snooze alerts whose snooze has run out. One still active is
+    /// treated as firing again and notifies (unless acknowledged).
+    fn expire_snoozes(&self, config: &AlertConfig, now: u64) {
+        let expired: Vec<Alert> = {
+            let mut active = self.active_alerts.lock().unwrap();
+            active
+                .values_mut()
+                .filter(|a| a.snoozed_until.is_some_and(|until| until <= now))
+                .map(|a| {
+                    a.snoozed_until = None;
+                    a.clone()
+                })
+                .collect()
+        };
+        for alert in expired {
+            if !alert.acknowledged {
+                self.notify(config, alert, AlertEvent::Triggered);
+            }
+        }
+    }
+
+    /// Acknowledge an active alert by id. None if no active alert has it.
+    pub fn acknowledge(&self, id: &str) -> Option<Alert> {
+        let mut active = self.active_alerts.lock().unwrap();
+        let alert = active.values_mut().find(|a| a.id == id)?;
+        alert.acknowledged = true;
+        Some(alert.clone())
+    }
+
+    /// Hide an active alert for `minutes`. None if no active alert has the id.
+    pub fn snooze(&self, id: &str, minutes: u64) -> Option<Alert> {
+        self.snooze_at(id, minutes, now_secs())
+    }
+
+    fn snooze_at(&self, id: &str, minutes: u64, now: u64) -> Option<Alert> {
+        let mut active = self.active_alerts.lock().unwrap();
+        let alert = active.values_mut().find(|a| a.id == id)?;
+        alert.snoozed_until = Some(now + minutes * 60);
+        Some(alert.clone())
+    }
+
+    /// Set or clear a condition detected outside the admin panel (e.g. a
+    /// host reporting split brain). Fires and resolves like the built-in
+    /// threshold alerts, keyed by `source`.
+    pub fn report(&self, source: &str, active: bool, severity: AlertSeverity, message: String) {
+        let config = self.config.lock().unwrap().clone();
+        self.check_threshold(&config, source, active, severity, message, now_secs());
+    }
+
+    /// Record a one-off event (e.g. an operator action) in the alert history.
+    /// It never becomes active: there is no condition to resolve later.
+    pub fn record_event(&self, source: &str, severity: AlertSeverity, message: String) -> Alert {
+        let config = self.config.lock().unwrap().clone();
+        let now = now_secs();
+
+        let mut counter = self.alert_counter.lock().unwrap();
+        *counter += 1;
+        let alert = Alert {
+            id: format!("alert-{}", *counter),
+            severity,
+            state: AlertState::Resolved,
+            title: format!("{} event", source.replace('_', " ")),
+            message,
+            triggered_at: now,
+            resolved_at: Some(now),
+            source: source.to_string(),
+            acknowledged: false,
+            snoozed_until: None,
+        };
+        drop(counter);
+
+        if let Ok(mut hist) = self.history.lock() {
+            hist.push(alert.clone());
+            let len = hist.len();
+            if len > 1000 {
+                hist.drain(0..len - 1000);
+            }
+        }
+
+        dispatch_webhook(&config, alert.clone(), AlertEvent::Recorded);
+        alert
+    }
+
+    /// Get all currently active alerts, except snoozed ones
     pub fn active_alerts(&self) -> Vec<Alert> {
-        self.active_alerts.lock().unwrap().values().cloned().collect()
+        self.active_alerts_at(now_secs())
+    }
+
+    fn active_alerts_at(&self, now: u64) -> Vec<Alert> {
+        self.active_alerts
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|a| a.snoozed_until.is_none_or(|until| until <= now))
+            .cloned()
+            .collect()
+    }
+
+    /// Active alerts currently hidden by a snooze
+    pub fn snoozed_alerts(&self) -> Vec<Alert> {
+        let now = now_secs();
+        self.active_alerts
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|a| a.snoozed_until.is_some_and(|until| until > now))
+            .cloned()
+            .collect()
     }
 
     /// Get alert history (most recent first)
@@ -236,11 +501,19 @@ impl AlertManager {
     pub fn get_config(&self) -> AlertConfig {
         self.config.lock().unwrap().clone()
     }
+
+    /// Current thermal alert level
+    pub fn thermal_level(&self) -> ThermalLevel {
+        *self.thermal_level.lock().unwrap()
+    }
 }
 
 /// Metrics snapshot for alert evaluation
 pub struct EvalMetrics {
     pub cpu_temp_c: f32,
+    /// Incoming MIDI messages per second, across all inputs
+    pub midi_messages_per_sec: f32,
+    pub client_count: u32,
     pub packet_loss_percent: f32,
     pub latency_p95_ms: f32,
     pub midi_device_connected: bool,
@@ -248,57 +521,439 @@ pub struct EvalMetrics {
     pub disk_free_mb: u64,
 }
 
+/// Webhook delivery attempts per alert event (the first try plus retries)
+const WEBHOOK_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry; doubled after each failed attempt
+const WEBHOOK_RETRY_BASE: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// What happened to an alert, as sent to the webhook.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertEvent {
+    /// The alert became active
+    Triggered,
+    /// The alert's condition went away
+    Cleared,
+    /// A one-off event from `record_event`
+    Recorded,
+}
+
+impl AlertEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            AlertEvent::Triggered => "triggered",
+            AlertEvent::Cleared => "cleared",
+            AlertEvent::Recorded => "recorded",
+        }
+    }
+}
+
+/// The JSON body POSTed for an alert event. Without a template it is
+/// `{event, id, severity, title, message, timestamp, source}`. A template
+/// is JSON text in which `{{event}}`, `{{id}}`, `{{severity}}`,
+/// `{{title}}`, `{{message}}`, `{{timestamp}}` and `{{source}}` are
+/// replaced (JSON-escaped, so they can sit inside strings), e.g. for Slack:
+/// `{"text": "[{{severity}}] {{title}} {{event}}: {{message}}"}`.
+pub fn webhook_payload(alert: &Alert, event: AlertEvent, template: Option<&str>) -> Result<serde_json::Value, String> {
+    let severity = match alert.severity {
+        AlertSeverity::Warning => "warning",
+        AlertSeverity::Critical => "critical",
+    };
+    let timestamp = match event {
+        AlertEvent::Cleared => alert.resolved_at.unwrap_or(alert.triggered_at),
+        _ => alert.triggered_at,
+    };
+
+    let Some(template) = template.filter(|t| !t.trim().is_empty()) else {
+        return Ok(serde_json::json!({
+            "event": event,
+            "id": alert.id,
+            "severity": severity,
+            "title": alert.title,
+            "message": alert.message,
+            "timestamp": timestamp,
+            "source": alert.source,
+        }));
+    };
+
+    let escape = |value: &str| {
+        let quoted = serde_json::to_string(value).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    };
+    let body = template
+        .replace("{{event}}", event.as_str())
+        .replace("{{id}}", &escape(&alert.id))
+        .replace("{{severity}}", severity)
+        .replace("{{title}}", &escape(&alert.title))
+        .replace("{{message}}", &escape(&alert.message))
+        .replace("{{timestamp}}", &timestamp.to_string())
+        .replace("{{source}}", &escape(&alert.source));
+    serde_json::from_str(&body).map_err(|e| format!("webhook template is not valid JSON: {}", e))
+}
+
+/// Check that a webhook template renders to valid JSON.
+pub fn validate_webhook_template(template: Option<&str>) -> Result<(), String> {
+    let sample = Alert {
+        id: "alert-0".to_string(),
+        severity: AlertSeverity::Warning,
+        state: AlertState::Active,
+        title: "test \"alert\"".to_string(),
+        message: "test message".to_string(),
+        triggered_at: 0,
+        resolved_at: None,
+        source: "test".to_string(),
+        acknowledged: false,
+        snoozed_until: None,
+    };
+    webhook_payload(&sample, AlertEvent::Triggered, template).map(|_| ())
+}
+
 /// Fire-and-forget webhook delivery.
 ///
-/// Spawns a detached tokio task that POSTs the alert as JSON to the
-/// given URL. Delivery failures are logged but never block the
-/// evaluation loop.
-fn dispatch_webhook(url: String, alert: Alert) {
+/// Spawns a detached tokio task that POSTs the alert event to the given
+/// URL, retrying with exponential backoff on errors and non-2xx replies.
+/// Delivery failures are logged but never block the evaluation loop.
+fn dispatch_webhook(config: &AlertConfig, alert: Alert, event: AlertEvent) {
+    if !config.webhook_enabled {
+        return;
+    }
+    let Some(url) = config.webhook_url.clone() else {
+        return;
+    };
+    let payload = match webhook_payload(&alert, event, config.webhook_template.as_deref()) {
+        Ok(payload) => payload,
+        Err(e) => {
+            // Better an unformatted message than none
+            tracing::warn!(alert_id = %alert.id, error = %e, "falling back to the default webhook payload");
+            match webhook_payload(&alert, event, None) {
+                Ok(payload) => payload,
+                Err(_) => return,
+            }
+        }
+    };
     tokio::spawn(async move {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build();
+        deliver_webhook(&url, &alert.id, &payload, WEBHOOK_RETRY_BASE).await;
+    });
+}
 
-        let client = match client {
-            Ok(c) => c,
-            Err(e) => {
-                tracing::warn!(error = %e, "failed to build webhook HTTP client");
-                return;
-            }
-        };
+/// POST `payload` to `url`, retrying up to `WEBHOOK_ATTEMPTS` times.
+/// Returns whether it was delivered.
+async fn deliver_webhook(url: &str, alert_id: &str, payload: &serde_json::Value, retry_base: std::time::Duration) -> bool {
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to build webhook HTTP client");
+            return false;
+        }
+    };
 
-        match client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .json(&alert)
-            .send()
-            .await
-        {
+    let mut delay = retry_base;
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        match client.post(url).json(payload).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                tracing::info!(
+                    alert_id = %alert_id,
+                    url = %url,
+                    status = %resp.status(),
+                    "webhook delivered"
+                );
+                return true;
+            }
             Ok(resp) => {
-                if resp.status().is_success() {
-                    tracing::info!(
-                        alert_id = %alert.id,
-                        url = %url,
-                        status = %resp.status(),
-                        "webhook delivered"
-                    );
-                } else {
-                    tracing::warn!(
-                        alert_id = %alert.id,
-                        url = %url,
-                        status = %resp.status(),
-                        "webhook returned non-success status"
-                    );
-                }
+                tracing::warn!(
+                    alert_id = %alert_id,
+                    url = %url,
+                    status = %resp.status(),
+                    attempt,
+                    "webhook returned non-success status"
+                );
             }
             Err(e) => {
                 tracing::warn!(
-                    alert_id = %alert.id,
+                    alert_id = %alert_id,
                     url = %url,
                     error = %e,
+                    attempt,
                     "webhook delivery failed"
                 );
             }
         }
-    });
+        if attempt < WEBHOOK_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    tracing::warn!(alert_id = %alert_id, url = %url, "webhook given up after {} attempts", WEBHOOK_ATTEMPTS);
+    false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thermal_level_has_hysteresis() {
+        // Warning at 70, critical at 80, 5°C hysteresis
+        let config = AlertConfig::default();
+        let mut level = ThermalLevel::Normal;
+        let mut walk = |temp: f32| {
+            level = level.next(temp, &config);
+            level
+        };
+
+        assert_eq!(walk(69.9), ThermalLevel::Normal);
+        assert_eq!(walk(70.0), ThermalLevel::Warning);
+        // Hovering just under the threshold doesn't clear it
+        assert_eq!(walk(68.0), ThermalLevel::Warning);
+        assert_eq!(walk(70.5), ThermalLevel::Warning);
+        assert_eq!(walk(80.0), ThermalLevel::Critical);
+        assert_eq!(walk(76.0), ThermalLevel::Critical);
+        // Below critical - hysteresis but still warm: back to warning
+        assert_eq!(walk(74.9), ThermalLevel::Warning);
+        assert_eq!(walk(65.1), ThermalLevel::Warning);
+        assert_eq!(walk(64.9), ThermalLevel::Normal);
+        // A sudden drop from critical goes straight to normal
+        assert_eq!(walk(85.0), ThermalLevel::Critical);
+        assert_eq!(walk(40.0), ThermalLevel::Normal);
+    }
+
+    #[test]
+    fn thermal_alerts_follow_the_level() {
+        let manager = AlertManager::new();
+        let metrics = |cpu_temp_c: f32| EvalMetrics {
+            cpu_temp_c,
+            midi_messages_per_sec: 0.0,
+            client_count: 0,
+            packet_loss_percent: 0.0,
+            latency_p95_ms: 0.0,
+            midi_device_connected: true,
+            standby_host_healthy: true,
+            disk_free_mb: 10_000,
+        };
+        let sources = |manager: &AlertManager| {
+            let mut sources: Vec<String> = manager.active_alerts().into_iter().map(|a| a.source).collect();
+            sources.sort();
+            sources
+        };
+
+        manager.evaluate(&metrics(72.0));
+        assert_eq!(sources(&manager), vec!["cpu_temp_warning"]);
+        manager.evaluate(&metrics(81.0));
+        assert_eq!(sources(&manager), vec!["cpu_temp"]);
+        manager.evaluate(&metrics(77.0));
+        assert_eq!(sources(&manager), vec!["cpu_temp"]);
+        manager.evaluate(&metrics(50.0));
+        assert!(sources(&manager).is_empty());
+        assert_eq!(manager.thermal_level(), ThermalLevel::Normal);
+    }
+
+    #[test]
+    fn midi_silence_alert_sets_and_clears() {
+        let manager = AlertManager::new();
+        let metrics = |midi_messages_per_sec: f32| EvalMetrics {
+            cpu_temp_c: 50.0,
+            midi_messages_per_sec,
+            client_count: 2,
+            packet_loss_percent: 0.0,
+            latency_p95_ms: 0.0,
+            midi_device_connected: true,
+            standby_host_healthy: true,
+            disk_free_mb: 10_000,
+        };
+        let silent = |manager: &AlertManager| manager.active_alerts().iter().any(|a| a.source == "midi_silence");
+
+        // Quiet since startup: nothing has played yet, so no show to miss
+        manager.evaluate_at(&metrics(0.0), 1_000);
+        manager.evaluate_at(&metrics(0.0), 1_500);
+        assert!(!silent(&manager));
+
+        manager.evaluate_at(&metrics(40.0), 2_000);
+        manager.evaluate_at(&metrics(0.0), 2_119);
+        assert!(!silent(&manager));
+        manager.evaluate_at(&metrics(0.0), 2_120);
+        assert!(silent(&manager));
+
+        // Activity resumes: cleared on the next evaluation
+        manager.evaluate_at(&metrics(12.0), 2_130);
+        assert!(!silent(&manager));
+        let history = manager.alert_history(10);
+        assert_eq!(history[0].source, "midi_silence");
+        assert_eq!(history[0].state, AlertState::Resolved);
+    }
+
+    #[test]
+    fn reported_condition_fires_once_and_resolves() {
+        let manager = AlertManager::new();
+        let active = |manager: &AlertManager| manager.active_alerts().iter().filter(|a| a.source == "split_brain_host_2").count();
+
+        for _ in 0..3 {
+            manager.report("split_brain_host_2", true, AlertSeverity::Critical, "host 1 also claims primary".into());
+        }
+        assert_eq!(active(&manager), 1);
+        assert_eq!(manager.active_alerts()[0].severity, AlertSeverity::Critical);
+
+        manager.report("split_brain_host_2", false, AlertSeverity::Critical, String::new());
+        assert_eq!(active(&manager), 0);
+        let history = manager.alert_history(10);
+        assert_eq!(history[0].state, AlertState::Resolved);
+        assert_eq!(history[0].message, "host 1 also claims primary");
+    }
+
+    /// A local webhook receiver that answers with `statuses` in turn (then
+    /// 200) and forwards each body it gets. Returns its URL.
+    async fn mock_webhook(statuses: Vec<u16>) -> (String, tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>) {
+        use axum::http::StatusCode;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let statuses = std::sync::Arc::new(Mutex::new(statuses.into_iter()));
+        let app = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                let tx = tx.clone();
+                let statuses = statuses.clone();
+                async move {
+                    let _ = tx.send(body);
+                    let status = statuses.lock().unwrap().next().unwrap_or(200);
+                    StatusCode::from_u16(status).unwrap()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/hook", addr), rx)
+    }
+
+    #[tokio::test]
+    async fn webhook_sends_triggered_then_cleared() {
+        let (url, mut rx) = mock_webhook(vec![]).await;
+        let manager = AlertManager::new();
+        manager.update_config(AlertConfig {
+            webhook_url: Some(url),
+            webhook_enabled: true,
+            ..AlertConfig::default()
+        });
+        let metrics = |disk_free_mb: u64| EvalMetrics {
+            cpu_temp_c: 50.0,
+            midi_messages_per_sec: 0.0,
+            client_count: 0,
+            packet_loss_percent: 0.0,
+            latency_p95_ms: 0.0,
+            midi_device_connected: true,
+            standby_host_healthy: true,
+            disk_free_mb,
+        };
+
+        manager.evaluate_at(&metrics(50), 1_000);
+        let triggered = rx.recv().await.unwrap();
+        assert_eq!(triggered["event"], "triggered");
+        assert_eq!(triggered["severity"], "warning");
+        assert_eq!(triggered["title"], "disk space alert");
+        assert_eq!(triggered["timestamp"], 1_000);
+        assert!(triggered["id"].as_str().unwrap().starts_with("alert-"));
+        assert!(triggered["message"].as_str().unwrap().contains("50MB free"));
+
+        manager.evaluate_at(&metrics(5_000), 1_060);
+        let cleared = rx.recv().await.unwrap();
+        assert_eq!(cleared["event"], "cleared");
+        assert_eq!(cleared["id"], triggered["id"]);
+        assert_eq!(cleared["timestamp"], 1_060);
+    }
+
+    #[tokio::test]
+    async fn webhook_template_and_retry() {
+        let alert = Alert {
+            id: "alert-7".to_string(),
+            severity: AlertSeverity::Critical,
+            state: AlertState::Active,
+            title: "cpu temp alert".to_string(),
+            message: "CPU temperature 81.0°C reached \"80\"°C".to_string(),
+            triggered_at: 1_000,
+            resolved_at: None,
+            source: "cpu_temp".to_string(),
+            acknowledged: false,
+            snoozed_until: None,
+        };
+        let slack = r#"{"text": "[{{severity}}] {{title}} {{event}}: {{message}}"}"#;
+        let payload = webhook_payload(&alert, AlertEvent::Triggered, Some(slack)).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({ "text": "[critical] cpu temp alert triggered: CPU temperature 81.0°C reached \"80\"°C" })
+        );
+        assert!(validate_webhook_template(Some("{\"text\": {{title}}}")).is_err());
+
+        // Two failures, then delivered on the third attempt
+        let (url, mut rx) = mock_webhook(vec![500, 503]).await;
+        assert!(deliver_webhook(&url, &alert.id, &payload, std::time::Duration::from_millis(5)).await);
+        for _ in 0..3 {
+            assert_eq!(rx.recv().await.unwrap(), payload);
+        }
+    }
+
+    fn disk_metrics(disk_free_mb: u64) -> EvalMetrics {
+        EvalMetrics {
+            cpu_temp_c: 50.0,
+            midi_messages_per_sec: 0.0,
+            client_count: 0,
+            packet_loss_percent: 0.0,
+            latency_p95_ms: 0.0,
+            midi_device_connected: true,
+            standby_host_healthy: true,
+            disk_free_mb,
+        }
+    }
+
+    #[tokio::test]
+    async fn acknowledged_alert_does_not_renotify() {
+        let (url, mut rx) = mock_webhook(vec![]).await;
+        let manager = AlertManager::new();
+        manager.update_config(AlertConfig {
+            webhook_url: Some(url),
+            webhook_enabled: true,
+            ..AlertConfig::default()
+        });
+
+        manager.evaluate_at(&disk_metrics(50), 1_000);
+        let triggered = rx.recv().await.unwrap();
+        let id = triggered["id"].as_str().unwrap().to_string();
+        assert!(manager.acknowledge(&id).unwrap().acknowledged);
+        assert!(manager.acknowledge("alert-999").is_none());
+
+        // Flaps: clears and re-fires a minute later, still visible and acknowledged
+        manager.evaluate_at(&disk_metrics(5_000), 1_030);
+        manager.evaluate_at(&disk_metrics(50), 1_090);
+        let active = manager.active_alerts_at(1_090);
+        assert_eq!(active.len(), 1);
+        assert!(active[0].acknowledged);
+
+        // After the hold time a new occurrence notifies again
+        manager.evaluate_at(&disk_metrics(5_000), 1_100);
+        manager.evaluate_at(&disk_metrics(50), 1_100 + ACK_HOLD_SECS);
+        let resent = rx.recv().await.unwrap();
+        assert_eq!(resent["event"], "triggered");
+        assert_ne!(resent["id"], id.as_str());
+        assert!(rx.try_recv().is_err(), "nothing was sent for the acknowledged flap");
+    }
+
+    #[test]
+    fn snooze_hides_until_it_expires() {
+        let manager = AlertManager::new();
+        manager.evaluate_at(&disk_metrics(50), 1_000);
+        let id = manager.active_alerts_at(1_000)[0].id.clone();
+
+        assert_eq!(manager.snooze_at(&id, 5, 1_000).unwrap().snoozed_until, Some(1_300));
+        assert!(manager.active_alerts_at(1_000).is_empty());
+        assert!(manager.active_alerts_at(1_299).is_empty());
+
+        // Still firing once the snooze runs out: back in the active list
+        manager.evaluate_at(&disk_metrics(50), 1_300);
+        let active = manager.active_alerts_at(1_300);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, id);
+        assert_eq!(active[0].snoozed_until, None);
+    }
+}
+
@@ -1,15 +1,21 @@
-use axum::extract::State;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use serde::Deserialize;
 use serde_json::{json, Value};
 
+use crate::alerting::AlertSeverity;
 use crate::state::AppState;
 
 pub async fn get_alerts(State(state): State<AppState>) -> Json<Value> {
     let active = state.inner.alert_manager.active_alerts();
+    let snoozed = state.inner.alert_manager.snoozed_alerts();
     let history = state.inner.alert_manager.alert_history(100);
 
     Json(json!({
         "active_alerts": active,
+        "snoozed_alerts": snoozed,
         "alert_history": history,
     }))
 }
@@ -23,6 +29,72 @@ pub async fn update_alert_config(
     State(state): State<AppState>,
     Json(config): Json<crate::alerting::AlertConfig>,
 ) -> Json<Value> {
+    if let Err(e) = crate::alerting::validate_webhook_template(config.webhook_template.as_deref()) {
+        return Json(json!({ "success": false, "error": e }));
+    }
     state.inner.alert_manager.update_config(config);
     Json(json!({ "success": true }))
 }
+
+#[derive(Deserialize)]
+pub struct ReportRequest {
+    /// Condition name, e.g. "split_brain_host_2"; one active alert per source
+    pub source: String,
+    /// true while the condition holds, false once it has cleared
+    pub active: bool,
+    pub severity: AlertSeverity,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// POST /api/alerts/report — set or clear a condition a host detected
+/// itself (split brain), so it alerts like the admin's own checks.
+pub async fn report_alert(State(state): State<AppState>, Json(req): Json<ReportRequest>) -> Response {
+    if req.source.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "success": false, "error": "source is required" })),
+        )
+            .into_response();
+    }
+    state.inner.alert_manager.report(&req.source, req.active, req.severity, req.message);
+    Json(json!({ "success": true })).into_response()
+}
+
+fn unknown_alert(id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "success": false, "error": format!("No active alert '{}'", id) })),
+    )
+        .into_response()
+}
+
+/// POST /api/alerts/:id/ack — keep the alert listed but stop notifying about it.
+pub async fn ack_alert(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.inner.alert_manager.acknowledge(&id) {
+        Some(alert) => Json(json!({ "success": true, "alert": alert })).into_response(),
+        None => unknown_alert(&id),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SnoozeQuery {
+    pub minutes: Option<u64>,
+}
+
+/// POST /api/alerts/:id/snooze?minutes=N — hide the alert for N minutes
+/// (default 15, at most a day).
+pub async fn snooze_alert(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<SnoozeQuery>,
+) -> Response {
+    let minutes = query.minutes.unwrap_or(15);
+    if !(1..=1440).contains(&minutes) {
+        return Json(json!({ "success": false, "error": "minutes must be 1-1440" })).into_response();
+    }
+    match state.inner.alert_manager.snooze(&id, minutes) {
+        Some(alert) => Json(json!({ "success": true, "alert": alert })).into_response(),
+        None => unknown_alert(&id),
+    }
+}
@@ -0,0 +1,304 @@
+/// Fleet-wide client commands.
+///
+/// Commands ride the same channel as focus claim/release: they are queued
+/// per client and handed out in the response to that client's next
+/// heartbeat; the client returns acks with its following heartbeat.
+/// `POST /api/clients/command` fans one command out to every matching client
+/// and waits for the acks so the operator gets a single collated answer.
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::Json;
+use midi_protocol::client_command::{ClientCommand, CommandAck, QueuedCommand};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::state::AppState;
+
+/// Default time to wait for acks (a little over two client heartbeats)
+const DEFAULT_COMMAND_TIMEOUT_MS: u64 = 12_000;
+const MAX_COMMAND_TIMEOUT_MS: u64 = 60_000;
+
+/// Queue a command for each of `client_ids`. Returns the command id.
+pub async fn queue_command(state: &AppState, client_ids: &[u32], command: ClientCommand) -> u64 {
+    let id = state.inner.next_command_id.fetch_add(1, Ordering::Relaxed);
+
+    state.inner.command_acks.write().await.insert(id, HashMap::new());
+    let mut queues = state.inner.client_commands.write().await;
+    for client_id in client_ids {
+        queues.entry(*client_id).or_default().push(QueuedCommand {
+            id,
+            command: command.clone(),
+        });
+    }
+    id
+}
+
+/// Take the commands waiting for a client (called from its heartbeat).
+pub async fn take_pending(state: &AppState, client_id: u32) -> Vec<QueuedCommand> {
+    state
+        .inner
+        .client_commands
+        .write()
+        .await
+        .remove(&client_id)
+        .unwrap_or_default()
+}
+
+/// Store acks reported by a client. Acks for commands nobody is waiting on
+/// any more (timed out) are ignored.
+pub async fn record_acks(state: &AppState, client_id: u32, acks: Vec<CommandAck>) {
+    if acks.is_empty() {
+        return;
+    }
+    let mut in_flight = state.inner.command_acks.write().await;
+    for ack in acks {
+        if let Some(results) = in_flight.get_mut(&ack.id) {
+            results.insert(client_id, ack);
+        }
+    }
+    drop(in_flight);
+    state.inner.command_ack_notify.notify_waiters();
+}
+
+/// Wait until every client in `client_ids` has acked `command_id` or the
+/// timeout expires, then stop tracking the command. Clients that did not
+/// pick the command up in time have it withdrawn from their queue.
+pub async fn collect_acks(
+    state: &AppState,
+    command_id: u64,
+    client_ids: &[u32],
+    timeout: Duration,
+) -> HashMap<u32, CommandAck> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        // Register for wake-up before checking, so an ack landing in between isn't missed
+        let notified = state.inner.command_ack_notify.notified();
+        {
+            let in_flight = state.inner.command_acks.read().await;
+            let acked = in_flight.get(&command_id).map_or(0, |r| r.len());
+            if acked >= client_ids.len() {
+                break;
+            }
+        }
+        if tokio::time::timeout_at(deadline, notified).await.is_err() {
+            break;
+        }
+    }
+
+    let results = state
+        .inner
+        .command_acks
+        .write()
+        .await
+        .remove(&command_id)
+        .unwrap_or_default();
+
+    let mut queues = state.inner.client_commands.write().await;
+    for client_id in client_ids {
+        if let Some(queue) = queues.get_mut(client_id) {
+            queue.retain(|c| c.id != command_id);
+            if queue.is_empty() {
+                queues.remove(client_id);
+            }
+        }
+    }
+
+    results
+}
+
+#[derive(Deserialize)]
+pub struct BulkCommandRequest {
+    pub command: ClientCommand,
+    /// Only send to these client IDs (default: every registered client)
+    #[serde(default)]
+    pub clients: Option<Vec<u32>>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// POST /api/clients/command — send one command to many clients and collate acks.
+pub async fn send_bulk_command(
+    State(state): State<AppState>,
+    Json(req): Json<BulkCommandRequest>,
+) -> Json<Value> {
+    // Clients only ack after a heartbeat round trip; catch what they
+    // would refuse before queueing
+    if let ClientCommand::SetPipeline { pipeline } = &req.command {
+        if let Err(e) = pipeline.validate_for_client() {
+            return Json(json!({ "success": false, "error": e }));
+        }
+    }
+
+    let targets: Vec<(u32, String)> = state
+        .inner
+        .clients
+        .read()
+        .await
+        .iter()
+        .filter(|c| req.clients.as_ref().is_none_or(|ids| ids.contains(&c.id)))
+        .map(|c| (c.id, c.hostname.clone()))
+        .collect();
+
+    if targets.is_empty() {
+        return Json(json!({ "success": false, "error": "No matching clients" }));
+    }
+
+    let client_ids: Vec<u32> = targets.iter().map(|(id, _)| *id).collect();
+    let timeout = Duration::from_millis(
+        req.timeout_ms
+            .unwrap_or(DEFAULT_COMMAND_TIMEOUT_MS)
+            .min(MAX_COMMAND_TIMEOUT_MS),
+    );
+
+    let command_id = queue_command(&state, &client_ids, req.command).await;
+    info!(command_id, clients = client_ids.len(), "Bulk client command queued");
+
+    let acks = collect_acks(&state, command_id, &client_ids, timeout).await;
+
+    let results: Vec<Value> = targets
+        .iter()
+        .map(|(id, hostname)| match acks.get(id) {
+            Some(ack) if ack.success => json!({ "client_id": id, "hostname": hostname, "status": "ok" }),
+            Some(ack) => json!({
+                "client_id": id,
+                "hostname": hostname,
+                "status": "error",
+                "error": ack.error,
+            }),
+            None => json!({ "client_id": id, "hostname": hostname, "status": "timeout" }),
+        })
+        .collect();
+    let succeeded = acks.values().filter(|a| a.success).count();
+
+    Json(json!({
+        "success": succeeded == targets.len(),
+        "command_id": command_id,
+        "total": targets.len(),
+        "succeeded": succeeded,
+        "results": results,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::status::{client_heartbeat, register_client};
+    use axum::extract::Path;
+
+    async fn register(state: &AppState, id: u32) {
+        let body = serde_json::from_value(json!({ "id": id, "hostname": format!("client-{}", id) })).unwrap();
+        let _ = register_client(State(state.clone()), Json(body)).await;
+    }
+
+    async fn heartbeat(state: &AppState, id: u32, acks: Vec<CommandAck>) -> Vec<QueuedCommand> {
+        let body = serde_json::from_value(json!({ "command_acks": acks })).unwrap();
+        let Json(resp) = client_heartbeat(State(state.clone()), Path(id), Json(body)).await;
+        serde_json::from_value(resp["commands"].clone()).unwrap()
+    }
+
+    /// Mock client: heartbeat until a command arrives, then ack it.
+    async fn mock_client(state: AppState, id: u32, fail: bool) {
+        loop {
+            let commands = heartbeat(&state, id, Vec::new()).await;
+            if !commands.is_empty() {
+                let acks = commands
+                    .iter()
+                    .map(|c| if fail { CommandAck::failed(c.id, "device not ready") } else { CommandAck::ok(c.id) })
+                    .collect();
+                heartbeat(&state, id, acks).await;
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn broadcast_reaches_clients_and_collates_acks() {
+        let state = AppState::new(String::new());
+        for id in [1, 2, 3] {
+            register(&state, id).await;
+        }
+
+        let bulk = tokio::spawn(send_bulk_command(
+            State(state.clone()),
+            Json(BulkCommandRequest {
+                command: ClientCommand::AllNotesOff,
+                clients: None,
+                timeout_ms: Some(5_000),
+            }),
+        ));
+
+        tokio::join!(
+            mock_client(state.clone(), 1, false),
+            mock_client(state.clone(), 2, false),
+            mock_client(state.clone(), 3, true),
+        );
+
+        let Json(resp) = bulk.await.unwrap();
+        assert_eq!(resp["total"], 3);
+        assert_eq!(resp["succeeded"], 2);
+        assert_eq!(resp["success"], false);
+        let status: HashMap<u64, String> = resp["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| (r["client_id"].as_u64().unwrap(), r["status"].as_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(status[&1], "ok");
+        assert_eq!(status[&2], "ok");
+        assert_eq!(status[&3], "error");
+        assert!(state.inner.command_acks.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn filter_limits_targets_and_missing_acks_time_out() {
+        let state = AppState::new(String::new());
+        for id in [1, 2] {
+            register(&state, id).await;
+        }
+
+        let Json(resp) = send_bulk_command(
+            State(state.clone()),
+            Json(BulkCommandRequest {
+                command: ClientCommand::AllNotesOff,
+                clients: Some(vec![2]),
+                timeout_ms: Some(20),
+            }),
+        )
+        .await;
+
+        assert_eq!(resp["total"], 1);
+        assert_eq!(resp["results"][0]["client_id"], 2);
+        assert_eq!(resp["results"][0]["status"], "timeout");
+        // The unanswered command is withdrawn, and client 1 never got one
+        assert!(heartbeat(&state, 2, Vec::new()).await.is_empty());
+        assert!(heartbeat(&state, 1, Vec::new()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pipeline_with_host_only_settings_is_not_queued() {
+        let state = AppState::new(String::new());
+        register(&state, 1).await;
+
+        let mut pipeline = midi_protocol::pipeline::PipelineConfig::default();
+        pipeline.mono[0] = true;
+        let Json(resp) = send_bulk_command(
+            State(state.clone()),
+            Json(BulkCommandRequest {
+                command: ClientCommand::SetPipeline { pipeline: Box::new(pipeline) },
+                clients: None,
+                timeout_ms: Some(20),
+            }),
+        )
+        .await;
+
+        assert_eq!(resp["success"], false);
+        assert!(resp["error"].as_str().unwrap().contains("mono"));
+        assert!(heartbeat(&state, 1, Vec::new()).await.is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use axum::extract::State;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -53,6 +55,9 @@ pub struct NetworkConfig {
     pub control_port: u16,
     #[serde(default = "default_interface")]
     pub interface: String,
+    /// Must match the hosts' `network.psk` for the sniffer to read a sealed stream
+    #[serde(default)]
+    pub psk: String,
 }
 
 fn default_multicast_group() -> String { "239.69.83.1".to_string() }
@@ -105,12 +110,14 @@ impl Default for MidiConfig {
 pub fn load_config(path: &str) -> anyhow::Result<MidinetConfig> {
     let contents = std::fs::read_to_string(path)?;
     let config: MidinetConfig = toml::from_str(&contents)?;
+    config.pipeline.validate().map_err(|e| anyhow::anyhow!("[pipeline] {}", e))?;
     Ok(config)
 }
 
 /// Save a MidinetConfig to a TOML file on disk.
 /// Preserves sections not managed by the admin (e.g. [host], [network], [heartbeat])
 /// by reading the existing file first and merging our sections into it.
+/// The file is replaced via a temp file and rename, never written in place.
 pub fn save_config(path: &str, config: &MidinetConfig) -> anyhow::Result<()> {
     if let Some(parent) = std::path::Path::new(path).parent() {
         if !parent.as_os_str().is_empty() {
@@ -135,14 +142,17 @@ pub fn save_config(path: &str, config: &MidinetConfig) -> anyhow::Result<()> {
     }
 
     let contents = toml::to_string_pretty(&base)?;
-    std::fs::write(path, contents)?;
+    replace_config_file(path, &contents)?;
     Ok(())
 }
 
 /// Assemble a MidinetConfig from current in-memory state.
 pub async fn build_config_from_state(state: &AppState) -> MidinetConfig {
     let pipeline = state.inner.pipeline_config.read().await.clone();
-    let failover = state.inner.failover_config.read().await.clone();
+    let mut failover = state.inner.failover_config.read().await.clone();
+    // The live active host is the source of truth for the persisted override
+    let active_host = state.inner.failover_state.read().await.active_host.clone();
+    failover.active_host = (active_host != "primary").then_some(active_host);
     let alert_config = state.inner.alert_manager.get_config();
     let osc_state = state.inner.osc_port_state.read().await;
     let active_device = state.inner.active_device.read().await;
@@ -175,26 +185,342 @@ pub async fn persist_config(state: &AppState) -> Result<(), String> {
     Ok(())
 }
 
+/// `?persist=` on the mutating config/settings/pipeline endpoints.
+/// `persist=false` applies the change in memory only, so a restart reverts it.
+#[derive(Debug, Default, Deserialize)]
+pub struct PersistQuery {
+    pub persist: Option<bool>,
+}
+
+impl PersistQuery {
+    /// Whether to write to disk, given the endpoint's default.
+    pub fn persist_or(&self, default: bool) -> bool {
+        self.persist.unwrap_or(default)
+    }
+}
+
+/// `persist_config` unless the request asked for an in-memory-only change.
+pub async fn persist_if(state: &AppState, persist: bool) -> Result<(), String> {
+    if !persist {
+        info!("Configuration change applied in memory only (persist=false)");
+        return Ok(());
+    }
+    persist_config(state).await
+}
+
 /// GET /api/config — return the full current configuration.
 pub async fn get_config(State(state): State<AppState>) -> Json<Value> {
     let config = build_config_from_state(&state).await;
     Json(json!({ "config": config }))
 }
 
-/// PUT /api/config — update in-memory state and persist to disk.
+/// Check a config file without applying it: it must be valid TOML, parse
+/// as a `MidinetConfig`, and pass the same checks as the settings API.
+/// Returns every problem found, not just the first.
+pub fn dry_run_config(contents: &str) -> Result<MidinetConfig, Vec<String>> {
+    let config: MidinetConfig = toml::from_str(contents).map_err(|e| vec![format!("Invalid config: {}", e)])?;
+
+    let mut errors: Vec<String> = crate::api::settings::validate_failover(&config.failover)
+        .into_iter()
+        .map(|e| format!("failover: {}", e))
+        .collect();
+    if let Some(ref osc) = config.osc {
+        if let Err(msg) = crate::api::settings::validate_port(osc.listen_port) {
+            errors.push(format!("osc.listen_port: {}", msg));
+        }
+    }
+    if let Err(msg) = config.pipeline.validate() {
+        errors.push(format!("pipeline: {}", msg));
+    }
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Replace the config file with `contents` via a temp file and rename,
+/// so a failed write never leaves a half-written config behind.
+pub(crate) fn replace_config_file(path: &str, contents: &str) -> std::io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// GET /api/config/download — the raw TOML file on disk, as an attachment.
+pub async fn download_config(State(state): State<AppState>) -> Response {
+    let config_path = state.inner.config_path.read().await.clone();
+    match std::fs::read_to_string(&config_path) {
+        Ok(contents) => {
+            let filename = std::path::Path::new(&config_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "midinet.toml".to_string());
+            (
+                [
+                    (header::CONTENT_TYPE, "application/toml".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+                ],
+                contents,
+            )
+                .into_response()
+        }
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "success": false,
+                "error": format!("Failed to read {}: {}", config_path, e),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /api/config/upload — replace the config file with the request body
+/// (raw TOML) and apply it. Nothing is written unless it passes `dry_run_config`.
+pub async fn upload_config(State(state): State<AppState>, body: String) -> Json<Value> {
+    let config = match dry_run_config(&body) {
+        Ok(config) => config,
+        Err(errors) => {
+            warn!(errors = errors.len(), "Rejected uploaded configuration");
+            return Json(json!({
+                "success": false,
+                "error": "Config failed validation; the file on disk was not changed",
+                "errors": errors,
+            }));
+        }
+    };
+
+    let config_path = state.inner.config_path.read().await.clone();
+    if let Err(e) = replace_config_file(&config_path, &body) {
+        warn!(path = %config_path, error = %e, "Failed to write uploaded configuration");
+        return Json(json!({ "success": false, "error": format!("Failed to save: {}", e) }));
+    }
+    state.apply_config(config).await;
+
+    info!(path = %config_path, bytes = body.len(), "Uploaded configuration saved and applied");
+    Json(json!({ "success": true, "bytes": body.len() }))
+}
+
+/// The config a fresh admin would end up with after loading `path`,
+/// i.e. what a restart would apply. Defaults fill anything the file omits.
+async fn config_as_loaded(path: &str) -> anyhow::Result<MidinetConfig> {
+    let fresh = AppState::new(path.to_string());
+    fresh.apply_config(load_config(path)?).await;
+    Ok(build_config_from_state(&fresh).await)
+}
+
+/// Top-level sections whose values differ between two configs.
+fn drifted_sections(effective: &MidinetConfig, on_disk: &MidinetConfig) -> Vec<String> {
+    let (Ok(Value::Object(a)), Ok(Value::Object(b))) =
+        (serde_json::to_value(effective), serde_json::to_value(on_disk))
+    else {
+        return Vec::new();
+    };
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|k| a.get(*k) != b.get(*k))
+        .cloned()
+        .collect()
+}
+
+/// GET /api/config/effective — the configuration currently applied in memory,
+/// next to what the file on disk would apply, with the sections that differ.
+pub async fn get_effective_config(State(state): State<AppState>) -> Json<Value> {
+    let effective = build_config_from_state(&state).await;
+    let config_path = state.inner.config_path.read().await.clone();
+
+    match config_as_loaded(&config_path).await {
+        Ok(on_disk) => {
+            let drifted = drifted_sections(&effective, &on_disk);
+            Json(json!({
+                "config_path": config_path,
+                "in_sync": drifted.is_empty(),
+                "drifted_sections": drifted,
+                "effective": effective,
+                "on_disk": on_disk,
+            }))
+        }
+        Err(e) => Json(json!({
+            "config_path": config_path,
+            "in_sync": false,
+            "effective": effective,
+            "on_disk": null,
+            "disk_error": e.to_string(),
+        })),
+    }
+}
+
+/// PUT /api/config — update in-memory state and persist to disk
+/// (`?persist=false` to apply only).
 pub async fn put_config(
     State(state): State<AppState>,
+    Query(query): Query<PersistQuery>,
     Json(config): Json<MidinetConfig>,
 ) -> Json<Value> {
+    if let Err(e) = config.pipeline.validate() {
+        return Json(json!({ "success": false, "error": format!("pipeline: {}", e) }));
+    }
+
     // Apply all config via the shared method
     state.apply_config(config).await;
 
-    // Persist to disk
-    match persist_config(&state).await {
-        Ok(()) => Json(json!({ "success": true })),
+    let persist = query.persist_or(true);
+    match persist_if(&state, persist).await {
+        Ok(()) => Json(json!({ "success": true, "persisted": persist })),
         Err(e) => Json(json!({
             "success": false,
             "error": format!("Config applied in memory but failed to save: {}", e)
         })),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn effective_reflects_runtime_change_but_disk_does_not() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-effective-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+
+        let state = AppState::new(path.clone());
+        persist_config(&state).await.unwrap();
+
+        let Json(before) = get_effective_config(State(state.clone())).await;
+        assert_eq!(before["in_sync"], true);
+
+        // Runtime-only pipeline change
+        let mut pipeline = state.inner.pipeline_config.read().await.clone();
+        pipeline.transpose[0] = 12;
+        let query = PersistQuery { persist: Some(false) };
+        let _ = crate::api::pipeline::update_pipeline(State(state.clone()), Query(query), Json(pipeline)).await;
+
+        let Json(after) = get_effective_config(State(state.clone())).await;
+        assert_eq!(after["effective"]["pipeline"]["transpose"][0], 12);
+        assert_eq!(after["on_disk"]["pipeline"]["transpose"][0], 0);
+        assert_eq!(after["in_sync"], false);
+        assert_eq!(after["drifted_sections"], json!(["pipeline"]));
+        assert_eq!(load_config(&path).unwrap().pipeline.transpose[0], 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn non_persisting_put_changes_effective_but_not_disk() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-persist-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+
+        let state = AppState::new(path.clone());
+        persist_config(&state).await.unwrap();
+        let disk_before = std::fs::read_to_string(&path).unwrap();
+
+        let mut config = build_config_from_state(&state).await;
+        config.alerts.cpu_temp_max_c = 65.0;
+        let Json(resp) = put_config(
+            State(state.clone()),
+            Query(PersistQuery { persist: Some(false) }),
+            Json(config.clone()),
+        )
+        .await;
+        assert_eq!(resp["success"], true);
+        assert_eq!(resp["persisted"], false);
+
+        let Json(effective) = get_effective_config(State(state.clone())).await;
+        assert_eq!(effective["effective"]["alerts"]["cpu_temp_max_c"], 65.0);
+        assert_eq!(effective["drifted_sections"], json!(["alerts"]));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), disk_before);
+
+        // Default still persists
+        let Json(resp) = put_config(State(state.clone()), Query(PersistQuery::default()), Json(config)).await;
+        assert_eq!(resp["persisted"], true);
+        assert_eq!(load_config(&path).unwrap().alerts.cpu_temp_max_c, 65.0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn download_returns_the_file_as_an_attachment() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-download-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+        let contents = "# backup me\n[host]\nid = 1\n\n[pipeline]\ntranspose = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]\n";
+        std::fs::write(&path, contents).unwrap();
+
+        let resp = download_config(State(AppState::new(path))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"midinet.toml\""
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, contents.as_bytes());
+
+        let resp = download_config(State(AppState::new(dir.join("missing.toml").to_string_lossy().into_owned()))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn upload_rejects_invalid_config_without_replacing() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-upload-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+
+        let state = AppState::new(path.clone());
+        persist_config(&state).await.unwrap();
+        let disk_before = std::fs::read_to_string(&path).unwrap();
+
+        // Not TOML at all, then TOML that fails the range checks
+        for bad in ["[failover\nlockout_seconds = ", "[failover]\nswitch_back_policy = \"sometimes\"\n[osc]\nlisten_port = 80\n"] {
+            let Json(resp) = upload_config(State(state.clone()), bad.to_string()).await;
+            assert_eq!(resp["success"], false);
+            assert!(!resp["errors"].as_array().unwrap().is_empty());
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), disk_before);
+        }
+        assert_eq!(dry_run_config("[failover]\nswitch_back_policy = \"sometimes\"\n[osc]\nlisten_port = 80\n").unwrap_err().len(), 2);
+        // Rejected the same way PUT /api/pipeline would reject it
+        let errors = dry_run_config("[pipeline]\nvelocity_min = 100\nvelocity_max = 20\n").unwrap_err();
+        assert!(errors[0].starts_with("pipeline: velocity_min"), "{:?}", errors);
+
+        // A valid upload is written verbatim and applied
+        let good = "# uploaded\n[host]\nid = 2\n\n[failover]\nlockout_seconds = 30\n";
+        let Json(resp) = upload_config(State(state.clone()), good.to_string()).await;
+        assert_eq!(resp["success"], true);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), good);
+        assert_eq!(state.inner.failover_config.read().await.lockout_seconds, 30);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn velocity_curves_load_in_the_host_syntax() {
+        use midi_protocol::pipeline::VelocityCurve;
+        let contents = "[pipeline]\nvelocity_curve = \"s_curve\"\n\
+                        feedback_velocity_curve = { compressor = { threshold = 90, ratio = 3.0, makeup = 0 } }\n";
+        let config = dry_run_config(contents).unwrap();
+        assert_eq!(config.pipeline.velocity_curve, VelocityCurve::SCurve);
+        let compressor = VelocityCurve::Compressor { threshold: 90, ratio: 3.0, makeup: 0 };
+        assert_eq!(config.pipeline.feedback_velocity_curve, compressor);
+
+        // Saved the way the host reads it back
+        let saved = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<MidinetConfig>(&saved).unwrap().pipeline, config.pipeline);
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_reported_not_fatal() {
+        let state = AppState::new("/nonexistent/midinet-effective.toml".to_string());
+        let Json(resp) = get_effective_config(State(state)).await;
+        assert_eq!(resp["in_sync"], false);
+        assert!(resp["disk_error"].is_string());
+        assert!(resp["effective"]["pipeline"].is_object());
+    }
+}
@@ -1,11 +1,18 @@
-use axum::extract::State;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use midi_protocol::failover::FailoverCause;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use tracing::info;
 
+use crate::api::config::persist_config;
 use crate::state::AppState;
 
 pub async fn get_failover_state(State(state): State<AppState>) -> Json<Value> {
     let fs = state.inner.failover_state.read().await;
+    let designated_primary = *state.inner.designated_primary.read().await;
     Json(json!({
         "active_host": fs.active_host,
         "auto_enabled": fs.auto_enabled,
@@ -15,6 +22,8 @@ pub async fn get_failover_state(State(state): State<AppState>) -> Json<Value> {
         "lockout_seconds": fs.lockout_seconds,
         "confirmation_mode": fs.confirmation_mode,
         "history": fs.history,
+        // Hosts poll this to follow `PUT /api/hosts/:id/promote`
+        "designated_primary": designated_primary,
     }))
 }
 
@@ -36,29 +45,344 @@ pub async fn trigger_failover_switch(State(state): State<AppState>) -> Json<Valu
         to_host: new_host.to_string(),
         trigger: "api".to_string(),
         duration_ms: 0,
+        cause: FailoverCause::Manual,
+        detail: "admin API".to_string(),
     };
 
     fs.active_host = new_host.to_string();
     fs.failover_count += 1;
-    fs.last_failover = Some(event.clone());
-    fs.history.push(event);
+    fs.record(event);
+
+    // A manual switch overrides any pinned primary
+    if state.inner.designated_primary.write().await.take().is_some() {
+        info!("Manual failover switch cleared the pinned primary");
+    }
+
+    let active_host = fs.active_host.clone();
+    let failover_count = fs.failover_count;
+    drop(fs);
+
+    // Persist so the operator's choice of host survives a restart
+    if let Err(e) = persist_config(&state).await {
+        return Json(json!({
+            "success": false,
+            "error": format!("Switched in memory but config save failed: {}", e),
+            "active_host": active_host,
+        }));
+    }
 
     Json(json!({
         "success": true,
-        "active_host": fs.active_host,
-        "failover_count": fs.failover_count,
+        "active_host": active_host,
+        "failover_count": failover_count,
     }))
 }
 
+/// PUT /api/hosts/:id/promote — pin one host as primary and the rest as
+/// standby. Hosts pick the pin up from `GET /api/failover`; the admin's
+/// view changes now. Refused inside the failover lockout.
+pub async fn promote_host(State(state): State<AppState>, Path(id): Path<u8>) -> Response {
+    let mut fs = state.inner.failover_state.write().await;
+    let mut hosts = state.inner.hosts.write().await;
+    let Some(to_host) = hosts.iter().find(|h| h.id == id).map(|h| h.name.clone()) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "success": false, "error": format!("No host with id {}", id) })),
+        )
+            .into_response();
+    };
+    let mut designated = state.inner.designated_primary.write().await;
+
+    let settled = *designated == Some(id)
+        && hosts.iter().all(|h| (h.role == "primary") == (h.id == id));
+    if !settled {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let since_last = fs.last_failover.as_ref().map(|e| now.saturating_sub(e.timestamp));
+        if let Some(elapsed) = since_last.filter(|&s| s < fs.lockout_seconds) {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!({
+                    "success": false,
+                    "error": format!("Failover locked out for another {}s", fs.lockout_seconds - elapsed),
+                })),
+            )
+                .into_response();
+        }
+
+        let from_host = hosts
+            .iter()
+            .find(|h| h.role == "primary")
+            .map(|h| h.name.clone())
+            .unwrap_or_default();
+        for host in hosts.iter_mut() {
+            host.role = if host.id == id { "primary" } else { "standby" }.to_string();
+        }
+        *designated = Some(id);
+
+        let event = crate::state::FailoverEvent {
+            timestamp: now,
+            from_host,
+            to_host,
+            trigger: "api".to_string(),
+            duration_ms: 0,
+            cause: FailoverCause::Manual,
+            detail: format!("host {} promoted", id),
+        };
+        fs.failover_count += 1;
+        fs.record(event);
+        info!(host_id = id, "Host promoted to primary, others to standby");
+    }
+
+    let roles: Vec<Value> = hosts.iter().map(|h| json!({ "id": h.id, "role": h.role })).collect();
+    Json(json!({ "success": true, "designated_primary": id, "hosts": roles })).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct FailoverReport {
+    pub host_id: u8,
+    pub event: ReportedSwitch,
+}
+
+/// One switch as the host's failover log records it (roles, not hosts)
+#[derive(Deserialize)]
+pub struct ReportedSwitch {
+    pub timestamp: u64,
+    pub from_host: String,
+    pub to_host: String,
+    pub cause: FailoverCause,
+    #[serde(default)]
+    pub detail: String,
+}
+
+/// POST /api/failover/report — a host switched roles on its own (heartbeat
+/// miss, OSC/MIDI trigger, switch-back, split brain) or followed a pin;
+/// log it with its cause. Only promotions count as failovers, so a
+/// handover reported by both hosts counts once.
+pub async fn report_failover(State(state): State<AppState>, Json(report): Json<FailoverReport>) -> Json<Value> {
+    let ReportedSwitch { timestamp, from_host, to_host, cause, detail } = report.event;
+    let name = state
+        .inner
+        .hosts
+        .read()
+        .await
+        .iter()
+        .find(|h| h.id == report.host_id)
+        .map(|h| h.name.clone())
+        .unwrap_or_else(|| format!("host-{}", report.host_id));
+    let trigger = match cause {
+        FailoverCause::Manual => "api",
+        FailoverCause::Osc => "osc",
+        FailoverCause::Midi => "midi",
+        FailoverCause::HeartbeatMiss | FailoverCause::HostLoad | FailoverCause::SwitchBack | FailoverCause::SplitBrain => "auto",
+    };
+    let promoted = to_host == "primary";
+    let event = crate::state::FailoverEvent {
+        timestamp,
+        from_host: format!("{}: {}", name, from_host),
+        to_host: format!("{}: {}", name, to_host),
+        trigger: trigger.to_string(),
+        duration_ms: 0,
+        cause,
+        detail,
+    };
+    info!(host_id = report.host_id, cause = cause.as_str(), to = %event.to_host, "Host reported a failover switch");
+
+    // Its role changed: a pending shadow promotion has done its job
+    state.inner.shadow_promotions.write().await.remove(&report.host_id);
+
+    let mut fs = state.inner.failover_state.write().await;
+    if promoted {
+        fs.failover_count += 1;
+    }
+    fs.record(event);
+    Json(json!({ "success": true }))
+}
+
 pub async fn set_auto_failover(
     State(state): State<AppState>,
     Json(body): Json<serde_json::Value>,
 ) -> Json<Value> {
-    if let Some(enabled) = body.get("enabled").and_then(|v| v.as_bool()) {
-        let mut fs = state.inner.failover_state.write().await;
-        fs.auto_enabled = enabled;
-        Json(json!({ "success": true, "auto_enabled": enabled }))
-    } else {
-        Json(json!({ "error": "Missing 'enabled' field" }))
+    let Some(enabled) = body.get("enabled").and_then(|v| v.as_bool()) else {
+        return Json(json!({ "error": "Missing 'enabled' field" }));
+    };
+
+    state.inner.failover_state.write().await.auto_enabled = enabled;
+    state.inner.failover_config.write().await.auto_enabled = enabled;
+
+    if let Err(e) = persist_config(&state).await {
+        return Json(json!({
+            "success": false,
+            "error": format!("Auto-failover set in memory but config save failed: {}", e),
+            "auto_enabled": enabled,
+        }));
+    }
+
+    Json(json!({ "success": true, "auto_enabled": enabled }))
+}
+
+/// GET /api/shadow/:host_id — polled by a shadow host waiting for promotion.
+pub async fn get_shadow_status(State(state): State<AppState>, Path(host_id): Path<u8>) -> Json<Value> {
+    let promote = state.inner.shadow_promotions.read().await.contains(&host_id);
+    Json(json!({ "host_id": host_id, "promote": promote }))
+}
+
+/// POST /api/shadow/:host_id/promote — tell a shadow host to start broadcasting.
+pub async fn promote_shadow(State(state): State<AppState>, Path(host_id): Path<u8>) -> Json<Value> {
+    let newly = state.inner.shadow_promotions.write().await.insert(host_id);
+    if newly {
+        info!(host_id, "Shadow host promotion requested");
+    }
+    Json(json!({ "success": true, "host_id": host_id, "promote": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::config::load_config;
+
+    fn temp_config_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("midinet.toml").to_string_lossy().into_owned()
+    }
+
+    /// Simulate a restart: fresh state, config loaded from disk.
+    async fn restart(path: &str) -> AppState {
+        let state = AppState::new(path.to_string());
+        state.apply_config(load_config(path).unwrap()).await;
+        state
+    }
+
+    #[tokio::test]
+    async fn auto_failover_off_survives_restart() {
+        let path = temp_config_path("auto-off");
+        let state = AppState::new(path.clone());
+
+        let Json(resp) = set_auto_failover(State(state), Json(json!({ "enabled": false }))).await;
+        assert_eq!(resp["success"], true);
+
+        let state = restart(&path).await;
+        assert!(!state.inner.failover_state.read().await.auto_enabled);
+        assert!(!state.inner.failover_config.read().await.auto_enabled);
+
+        let _ = std::fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn manual_switch_survives_restart() {
+        let path = temp_config_path("switch");
+        let state = AppState::new(path.clone());
+
+        let Json(resp) = trigger_failover_switch(State(state.clone())).await;
+        assert_eq!(resp["active_host"], "standby");
+        let Json(fs) = get_failover_state(State(state.clone())).await;
+        assert_eq!(fs["history"][0]["cause"], "manual");
+        assert_eq!(restart(&path).await.inner.failover_state.read().await.active_host, "standby");
+
+        // Switching back clears the override
+        let _ = trigger_failover_switch(State(state)).await;
+        let state = restart(&path).await;
+        assert_eq!(state.inner.failover_state.read().await.active_host, "primary");
+        assert!(state.inner.failover_config.read().await.active_host.is_none());
+
+        let _ = std::fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn host_switches_land_in_the_history_with_their_cause() {
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        *state.inner.hosts.write().await = vec![host(1, "primary"), host(2, "standby")];
+        let report = |host_id: u8, from: &str, to: &str, cause: &str| {
+            serde_json::from_value::<FailoverReport>(json!({
+                "host_id": host_id,
+                "event": { "timestamp": 100, "from_host": from, "to_host": to, "cause": cause, "detail": "no primary heartbeat for 12ms" },
+            }))
+            .unwrap()
+        };
+
+        let _ = report_failover(State(state.clone()), Json(report(2, "standby", "primary", "heartbeat_miss"))).await;
+        let _ = report_failover(State(state.clone()), Json(report(2, "primary", "standby", "switch_back"))).await;
+
+        let Json(fs) = get_failover_state(State(state)).await;
+        assert_eq!(fs["failover_count"], 1, "only the promotion counts");
+        assert_eq!(fs["history"][0]["cause"], "heartbeat_miss");
+        assert_eq!(fs["history"][0]["trigger"], "auto");
+        assert_eq!(fs["history"][0]["to_host"], "host-2: primary");
+        assert_eq!(fs["history"][0]["detail"], "no primary heartbeat for 12ms");
+        assert_eq!(fs["last_failover"]["cause"], "switch_back");
+    }
+
+    #[tokio::test]
+    async fn shadow_promotion_is_reported_to_that_host_only() {
+        let state = AppState::new(String::new());
+        let Json(resp) = get_shadow_status(State(state.clone()), Path(3)).await;
+        assert_eq!(resp["promote"], false);
+
+        let Json(resp) = promote_shadow(State(state.clone()), Path(3)).await;
+        assert_eq!(resp["success"], true);
+
+        let Json(resp) = get_shadow_status(State(state.clone()), Path(3)).await;
+        assert_eq!(resp["promote"], true);
+        let Json(resp) = get_shadow_status(State(state.clone()), Path(2)).await;
+        assert_eq!(resp["promote"], false);
+
+        // Once host 3 reports its new role the promotion is forgotten
+        let report = serde_json::from_value::<FailoverReport>(json!({
+            "host_id": 3,
+            "event": { "timestamp": 100, "from_host": "standby", "to_host": "primary", "cause": "manual" },
+        }))
+        .unwrap();
+        let _ = report_failover(State(state.clone()), Json(report)).await;
+        let Json(resp) = get_shadow_status(State(state.clone()), Path(3)).await;
+        assert_eq!(resp["promote"], false);
+        assert!(state.inner.shadow_promotions.read().await.is_empty());
+    }
+
+    fn host(id: u8, role: &str) -> crate::state::HostInfo {
+        serde_json::from_value(json!({
+            "id": id, "name": format!("host-{}", id), "role": role, "ip": format!("10.0.0.{}", id),
+            "uptime_seconds": 0, "device_name": "", "midi_active": true, "heartbeat_ok": true,
+            "last_heartbeat_ms": 0,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn promoting_host_3_of_3_pins_it_and_demotes_the_rest() {
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        *state.inner.hosts.write().await = vec![host(1, "primary"), host(2, "standby"), host(3, "standby")];
+        state.inner.failover_state.write().await.lockout_seconds = 60;
+
+        let resp = promote_host(State(state.clone()), Path(3)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let roles: Vec<_> = state.inner.hosts.read().await.iter().map(|h| (h.id, h.role.clone())).collect();
+        assert_eq!(roles, [(1, "standby".into()), (2, "standby".into()), (3, "primary".into())]);
+        let Json(fs) = get_failover_state(State(state.clone())).await;
+        assert_eq!(fs["designated_primary"], 3);
+        assert_eq!(fs["failover_count"], 1);
+        assert_eq!(fs["last_failover"]["from_host"], "host-1");
+        assert_eq!(fs["last_failover"]["to_host"], "host-3");
+
+        // Promoting the settled host again is a no-op, not a lockout
+        let resp = promote_host(State(state.clone()), Path(3)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(state.inner.failover_state.read().await.failover_count, 1);
+
+        // Moving the pin again inside the lockout is refused
+        let resp = promote_host(State(state.clone()), Path(1)).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(*state.inner.designated_primary.read().await, Some(3));
+
+        let resp = promote_host(State(state.clone()), Path(9)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // A manual switch takes over from the pin, so hosts stop following it
+        let _ = trigger_failover_switch(State(state.clone())).await;
+        let Json(fs) = get_failover_state(State(state)).await;
+        assert!(fs["designated_primary"].is_null());
     }
 }
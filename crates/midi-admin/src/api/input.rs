@@ -3,11 +3,21 @@
 /// GET  /api/input-redundancy          — Full input redundancy state
 /// POST /api/input-redundancy/switch   — Manual input controller switch
 /// POST /api/input-redundancy/auto     — Toggle auto-switch on failure
+/// PUT  /api/input-redundancy/:index/enable — Enable/disable one input
+///
+/// Enable/disable is sent to the hosts as an `InputEnablePacket` on the
+/// control multicast group; the host's input mux applies it.
+
+use std::net::SocketAddr;
 
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use tokio::net::UdpSocket;
+
+use midi_protocol::multicast;
+use midi_protocol::packets::InputEnablePacket;
 
 use crate::state::{AppState, InputSwitchEvent};
 
@@ -22,10 +32,12 @@ pub async fn get_input_redundancy(State(state): State<AppState>) -> Json<Value>
         "primary": {
             "health": ir.primary_health,
             "device": ir.primary_device,
+            "enabled": ir.input_enabled[0],
         },
         "secondary": {
             "health": ir.secondary_health,
             "device": ir.secondary_device,
+            "enabled": ir.input_enabled[1],
         },
         "switch_count": ir.switch_count,
         "activity_timeout_s": ir.activity_timeout_s,
@@ -57,6 +69,16 @@ pub async fn trigger_input_switch(State(state): State<AppState>) -> Json<Value>
         &ir.secondary_health
     };
 
+    if !ir.input_enabled[target as usize] {
+        return Json(json!({
+            "success": false,
+            "error": format!(
+                "Cannot switch to {} — input is disabled",
+                if target == 0 { "primary" } else { "secondary" }
+            )
+        }));
+    }
+
     if target_health != "active" {
         return Json(json!({
             "success": false,
@@ -119,3 +141,131 @@ pub async fn set_auto_switch(
         "auto_switch_enabled": ir.auto_switch_enabled,
     }))
 }
+
+#[derive(Deserialize)]
+pub struct InputEnableRequest {
+    pub enabled: bool,
+}
+
+/// PUT /api/input-redundancy/:index/enable
+/// Mute or unmute one input controller without removing it from the config.
+pub async fn set_input_enabled(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+    Json(req): Json<InputEnableRequest>,
+) -> Json<Value> {
+    let mut ir = state.inner.input_redundancy.write().await;
+    let Some(flag) = ir.input_enabled.get_mut(index) else {
+        return Json(json!({
+            "success": false,
+            "error": format!("No input {} (0 = primary, 1 = secondary)", index)
+        }));
+    };
+
+    let (group, port) = match state.inner.network_config.read().await.as_ref() {
+        Some(net) => (net.control_group.clone(), net.control_port),
+        None => (
+            midi_protocol::DEFAULT_CONTROL_GROUP.to_string(),
+            midi_protocol::DEFAULT_CONTROL_PORT,
+        ),
+    };
+    if let Err(e) = send_input_enable(&group, port, index as u8, req.enabled).await {
+        return Json(json!({
+            "success": false,
+            "error": format!("Failed to send input enable request: {}", e)
+        }));
+    }
+    *flag = req.enabled;
+
+    tracing::info!(input = index, enabled = req.enabled, group = %group, "Input {}",
+        if req.enabled { "enabled" } else { "disabled" });
+
+    Json(json!({
+        "success": true,
+        "index": index,
+        "enabled": req.enabled,
+    }))
+}
+
+async fn send_input_enable(group: &str, port: u16, index: u8, enabled: bool) -> anyhow::Result<()> {
+    let group = multicast::parse_group(group)?;
+    let socket = UdpSocket::bind(multicast::bind_addr(group, 0)).await?;
+    // IPv6 multicast already defaults to a hop limit of 1
+    if group.is_ipv4() {
+        socket.set_multicast_ttl_v4(1)?;
+    }
+
+    let packet = InputEnablePacket {
+        index,
+        enabled,
+        timestamp_us: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64,
+    };
+    let mut buf = [0u8; InputEnablePacket::SIZE];
+    packet.serialize(&mut buf);
+
+    socket.send_to(&buf, SocketAddr::new(group, port)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn disabled_input_is_reported_and_excluded_from_switching() {
+        let group: std::net::Ipv4Addr = "239.69.83.246".parse().unwrap();
+        let listener = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        listener.join_multicast_v4(&group, &std::net::Ipv4Addr::UNSPECIFIED).unwrap();
+        listener.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let state = AppState::new(String::new());
+        *state.inner.network_config.write().await = Some(crate::api::config::NetworkConfig {
+            multicast_group: "239.69.83.1".to_string(),
+            data_port: 5004,
+            control_group: group.to_string(),
+            control_port: port,
+            interface: String::new(),
+            psk: String::new(),
+        });
+        {
+            let mut ir = state.inner.input_redundancy.write().await;
+            ir.enabled = true;
+            ir.primary_health = "active".to_string();
+            ir.secondary_health = "active".to_string();
+        }
+
+        let Json(resp) = set_input_enabled(
+            State(state.clone()),
+            Path(1),
+            Json(InputEnableRequest { enabled: false }),
+        )
+        .await;
+        assert_eq!(resp["success"], true);
+
+        // The host's input mux hears about it on the control group
+        let mut buf = [0u8; 64];
+        let len = listener.recv(&mut buf).unwrap();
+        let request = InputEnablePacket::deserialize(&buf[..len]).unwrap();
+        assert_eq!((request.index, request.enabled), (1, false));
+
+        let Json(ir) = get_input_redundancy(State(state.clone())).await;
+        assert_eq!(ir["primary"]["enabled"], true);
+        assert_eq!(ir["secondary"]["enabled"], false);
+
+        let Json(resp) = trigger_input_switch(State(state.clone())).await;
+        assert_eq!(resp["success"], false);
+        assert!(resp["error"].as_str().unwrap().contains("disabled"));
+
+        let Json(resp) = set_input_enabled(State(state.clone()), Path(2), Json(InputEnableRequest { enabled: true })).await;
+        assert_eq!(resp["success"], false);
+
+        // Re-enabled: the switch goes through
+        let _ = set_input_enabled(State(state.clone()), Path(1), Json(InputEnableRequest { enabled: true })).await;
+        let Json(resp) = trigger_input_switch(State(state)).await;
+        assert_eq!(resp["active_input"], 1);
+    }
+}
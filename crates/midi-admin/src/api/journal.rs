@@ -0,0 +1,166 @@
+/// API endpoint exposing what each host thinks is sounding.
+///
+/// GET /api/journal — per-host MIDI state: active notes with velocities,
+/// notes held by the pedals, CC values, program, pitch bend and channel
+/// pressure. Only non-default values are listed, and channels with nothing
+/// to report are left out.
+///
+/// The admin panel has no access to a host's memory, so the MIDI sniffer
+/// mirrors each host's `MidiState` from its data stream the same way a
+/// shadow host does: a journal replaces the state, MIDI in between is
+/// applied on top. Hosts journal every 100ms, so this trails the host by
+/// at most that much.
+
+use std::collections::BTreeMap;
+
+use axum::extract::State;
+use axum::Json;
+use midi_protocol::journal::decode_journal;
+use midi_protocol::midi_state::{MidiState, NUM_CCS, NUM_CHANNELS, NUM_NOTES};
+use midi_protocol::packets::MidiDataPacket;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::state::AppState;
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct NoteValue {
+    pub note: u8,
+    pub velocity: u8,
+}
+
+/// Non-default state of one channel.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ChannelJournal {
+    /// 1-16
+    pub channel: u8,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<NoteValue>,
+    /// Key released but still sounding under sustain/sostenuto
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pedal_held: Vec<NoteValue>,
+    /// CC number → value, non-zero only
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub cc: BTreeMap<u8, u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch_bend: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_pressure: Option<u8>,
+}
+
+/// Channels of `state` that differ from the power-on default.
+pub fn channel_journals(state: &MidiState) -> Vec<ChannelJournal> {
+    let notes = |values: &[u8; NUM_NOTES]| -> Vec<NoteValue> {
+        (0..NUM_NOTES)
+            .filter(|&n| values[n] > 0)
+            .map(|n| NoteValue { note: n as u8, velocity: values[n] })
+            .collect()
+    };
+
+    (0..NUM_CHANNELS)
+        .map(|ch| {
+            let c = &state.channels[ch];
+            ChannelJournal {
+                channel: ch as u8 + 1,
+                notes: notes(&c.notes),
+                pedal_held: notes(&c.pedal_held),
+                cc: (0..NUM_CCS).filter(|&n| c.cc[n] != 0).map(|n| (n as u8, c.cc[n])).collect(),
+                program: (c.program != 0).then_some(c.program),
+                pitch_bend: (c.pitch_bend != 8192).then_some(c.pitch_bend),
+                channel_pressure: (c.channel_pressure != 0).then_some(c.channel_pressure),
+            }
+        })
+        .filter(|c| {
+            !c.notes.is_empty()
+                || !c.pedal_held.is_empty()
+                || !c.cc.is_empty()
+                || c.program.is_some()
+                || c.pitch_bend.is_some()
+                || c.channel_pressure.is_some()
+        })
+        .collect()
+}
+
+/// Apply one sniffed data packet to the mirrored state of its host.
+pub fn mirror_packet(states: &mut BTreeMap<u8, MidiState>, packet: &MidiDataPacket) {
+    let state = states.entry(packet.host_id).or_default();
+    // The host snapshots its journal after applying the packet's MIDI
+    match packet.journal.as_deref().and_then(decode_journal) {
+        Some(snapshot) => *state = snapshot,
+        None => {
+            state.process_message(&packet.midi_data);
+        }
+    }
+}
+
+/// GET /api/journal
+pub async fn get_journal(State(state): State<AppState>) -> Json<Value> {
+    let states = state.inner.host_midi_states.read().await;
+    let hosts: Vec<Value> = states
+        .iter()
+        .map(|(host_id, midi_state)| {
+            json!({
+                "host_id": host_id,
+                "active_notes": midi_state.active_note_count(),
+                "channels": channel_journals(midi_state),
+            })
+        })
+        .collect();
+    Json(json!({ "hosts": hosts }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(host_id: u8, midi_data: Vec<u8>, journal: Option<Vec<u8>>) -> MidiDataPacket {
+        MidiDataPacket { sequence: 0, timestamp_us: 0, host_id, midi_data, journal }
+    }
+
+    #[test]
+    fn only_non_default_channels_and_values_are_listed() {
+        let mut state = MidiState::new();
+        state.process_message(&[0x90, 60, 100]);
+        state.process_message(&[0xB0, 7, 90]);
+        state.process_message(&[0xC3, 5]);
+
+        let journals = channel_journals(&state);
+        assert_eq!(journals.len(), 2);
+        assert_eq!(journals[0].channel, 1);
+        assert_eq!(journals[0].notes, vec![NoteValue { note: 60, velocity: 100 }]);
+        assert_eq!(journals[0].cc, BTreeMap::from([(7, 90)]));
+        assert_eq!(journals[0].program, None);
+        assert_eq!(journals[1].channel, 4);
+        assert_eq!(journals[1].program, Some(5));
+
+        let json = serde_json::to_value(&journals[1]).unwrap();
+        assert_eq!(json, json!({ "channel": 4, "program": 5 }));
+    }
+
+    #[tokio::test]
+    async fn endpoint_reports_each_hosts_mirrored_state() {
+        let state = AppState::new(String::new());
+        {
+            let mut states = state.inner.host_midi_states.write().await;
+            mirror_packet(&mut states, &packet(1, vec![0x90, 60, 100], None));
+            mirror_packet(&mut states, &packet(1, vec![0x91, 64, 80], None));
+
+            // A journal replaces what the stream built up
+            let mut snapshot = MidiState::new();
+            snapshot.process_message(&[0x90, 48, 70]);
+            let journal = midi_protocol::journal::encode_journal(&snapshot);
+            mirror_packet(&mut states, &packet(2, vec![0x90, 48, 70], Some(journal)));
+        }
+
+        let Json(resp) = get_journal(State(state)).await;
+        let hosts = resp["hosts"].as_array().unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0]["host_id"], 1);
+        assert_eq!(hosts[0]["active_notes"], 2);
+        assert_eq!(hosts[0]["channels"][1]["channel"], 2);
+        assert_eq!(hosts[0]["channels"][1]["notes"][0], json!({ "note": 64, "velocity": 80 }));
+        assert_eq!(hosts[1]["channels"], json!([{ "channel": 1, "notes": [{ "note": 48, "velocity": 70 }] }]));
+    }
+}
@@ -1,8 +1,13 @@
+use std::fmt::Write;
+
 use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::Deserialize;
 use serde_json::{json, Value};
 
+use crate::api::program_names::program_label;
 use crate::state::AppState;
 
 pub async fn get_system_metrics(State(state): State<AppState>) -> Json<Value> {
@@ -15,12 +20,26 @@ pub async fn get_system_metrics(State(state): State<AppState>) -> Json<Value> {
         "disk_free_mb": sys.disk_free_mb,
         "network_tx_bytes": sys.network_tx_bytes,
         "network_rx_bytes": sys.network_rx_bytes,
+        "throttled": sys.throttled,
+        "thermal_level": state.inner.alert_manager.thermal_level(),
         "uptime_seconds": state.uptime_secs(),
     }))
 }
 
 pub async fn get_midi_metrics(State(state): State<AppState>) -> Json<Value> {
     let midi = state.inner.midi_metrics.read().await;
+    let names = state.inner.program_names.read().await;
+    // Last Program Change per channel, with its label
+    let programs: Vec<Value> = (0..16u8)
+        .filter_map(|ch| midi.programs[ch as usize].map(|program| (ch + 1, program)))
+        .map(|(channel, program)| {
+            json!({
+                "channel": channel,
+                "program": program,
+                "label": program_label(&names, channel, program),
+            })
+        })
+        .collect();
     Json(json!({
         "messages_in_per_sec": midi.messages_in_per_sec,
         "messages_out_per_sec": midi.messages_out_per_sec,
@@ -30,6 +49,8 @@ pub async fn get_midi_metrics(State(state): State<AppState>) -> Json<Value> {
         "active_notes": midi.active_notes,
         "dropped_messages": midi.dropped_messages,
         "peak_burst_rate": midi.peak_burst_rate,
+        "programs": programs,
+        "per_channel": midi.per_channel,
     }))
 }
 
@@ -37,7 +58,8 @@ pub async fn get_midi_metrics(State(state): State<AppState>) -> Json<Value> {
 pub struct MetricsQuery {
     /// Number of recent samples (default: 60 = last minute)
     pub count: Option<usize>,
-    /// Time range: "1h", "6h", "24h", "7d"
+    /// Time range: "1h", "6h", "24h", "7d", or "30d" / "1y" (hourly
+    /// aggregates, which begin where the 1-minute rows end)
     pub range: Option<String>,
 }
 
@@ -64,6 +86,13 @@ pub async fn get_metrics_history(
                     "resolution": "1min",
                 }));
             }
+            "30d" | "1y" => {
+                let span = if range == "30d" { 30 * 86400 } else { 365 * 86400 };
+                return Json(json!({
+                    "aggregates": state.inner.metrics_store.query_hourly(now - span, now),
+                    "resolution": "1h",
+                }));
+            }
             _ => now - 3600,
         };
         state.inner.metrics_store.query_range(from, now)
@@ -77,3 +106,173 @@ pub async fn get_metrics_history(
         "resolution": "1s",
     }))
 }
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// "prometheus" or "csv"
+    pub format: Option<String>,
+    /// CSV range start (Unix seconds, default: 7 days ago)
+    pub from: Option<u64>,
+    /// CSV range end (Unix seconds, default: now)
+    pub to: Option<u64>,
+}
+
+/// GET /api/metrics/export?format=prometheus — current gauges in the
+/// Prometheus text format. ?format=csv — the 1-minute history as CSV.
+pub async fn export_metrics(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    match query.format.as_deref() {
+        Some("prometheus") => (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+            render_prometheus(&state).await,
+        )
+            .into_response(),
+        Some("csv") => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let from = query.from.unwrap_or(now.saturating_sub(604800));
+            let to = query.to.unwrap_or(now);
+            let samples = state.inner.metrics_store.query_history(from, to);
+            (
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                    (header::CONTENT_DISPOSITION, "attachment; filename=\"midinet-metrics.csv\""),
+                ],
+                render_csv(&samples),
+            )
+                .into_response()
+        }
+        _ => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "success": false, "error": "format must be prometheus or csv" })),
+        )
+            .into_response(),
+    }
+}
+
+/// Escape a Prometheus label value
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+async fn render_prometheus(state: &AppState) -> String {
+    let sys = state.inner.system_status.read().await.clone();
+    let midi_in = state.inner.midi_metrics.read().await.messages_in_per_sec;
+    let failovers = state.inner.failover_state.read().await.failover_count;
+    let clients = state.inner.clients.read().await.clone();
+    let alerts = state.inner.alert_manager.active_alerts().len();
+    let avg_loss = if clients.is_empty() {
+        0.0
+    } else {
+        clients.iter().map(|c| c.packet_loss_percent).sum::<f32>() / clients.len() as f32
+    };
+
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(out, "{}{} {}", name, labels, value);
+        }
+    };
+    metric("midinet_midi_messages_per_second", "gauge", "Incoming MIDI messages per second", vec![(String::new(), midi_in as f64)]);
+    metric("midinet_packet_loss_percent", "gauge", "Average packet loss reported by clients", vec![(String::new(), avg_loss as f64)]);
+    metric(
+        "midinet_client_packet_loss_percent",
+        "gauge",
+        "Packet loss reported by each client",
+        clients
+            .iter()
+            .map(|c| (format!("{{client=\"{}\",hostname=\"{}\"}}", c.id, label(&c.hostname)), c.packet_loss_percent as f64))
+            .collect(),
+    );
+    metric("midinet_cpu_percent", "gauge", "Admin host CPU usage", vec![(String::new(), sys.cpu_percent as f64)]);
+    metric("midinet_cpu_temperature_celsius", "gauge", "Admin host CPU temperature", vec![(String::new(), sys.cpu_temp_c as f64)]);
+    metric("midinet_clients_connected", "gauge", "Connected clients", vec![(String::new(), clients.len() as f64)]);
+    metric("midinet_failovers_total", "counter", "Failovers since the admin panel started", vec![(String::new(), failovers as f64)]);
+    metric("midinet_health_score", "gauge", "System health score (0-100)", vec![(String::new(), sys.health_score as f64)]);
+    metric("midinet_active_alerts", "gauge", "Active (not snoozed) alerts", vec![(String::new(), alerts as f64)]);
+    out
+}
+
+fn render_csv(samples: &[crate::metrics_store::MetricsSample]) -> String {
+    let mut out = String::from(
+        "timestamp,cpu_percent,cpu_temp_c,memory_used_mb,midi_messages_per_sec,midi_bytes_per_sec,active_notes,\
+         packet_loss_percent,latency_p50_ms,latency_p95_ms,latency_p99_ms,client_count,network_tx_bytes,network_rx_bytes\n",
+    );
+    for s in samples {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            s.timestamp, s.cpu_percent, s.cpu_temp_c, s.memory_used_mb, s.midi_messages_per_sec,
+            s.midi_bytes_per_sec, s.active_notes, s.packet_loss_percent, s.latency_p50_ms,
+            s.latency_p95_ms, s.latency_p99_ms, s.client_count, s.network_tx_bytes, s.network_rx_bytes,
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prometheus_export_is_valid_exposition_text() {
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        state.inner.midi_metrics.write().await.messages_in_per_sec = 42.5;
+        state.inner.failover_state.write().await.failover_count = 3;
+        state.inner.clients.write().await.push(
+            serde_json::from_value(json!({
+                "id": 7, "ip": "10.0.0.7", "hostname": "foh \"mac\"", "os": "macos",
+                "connected_since": 0, "last_heartbeat_ms": 0, "latency_ms": 1.5,
+                "packet_loss_percent": 0.25,
+            }))
+            .unwrap(),
+        );
+
+        let resp = export_metrics(
+            State(state),
+            Query(ExportQuery { format: Some("prometheus".to_string()), from: None, to: None }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+
+        // Every sample is `name{labels} value` with a declared TYPE
+        let mut typed = std::collections::HashSet::new();
+        let mut samples = std::collections::HashMap::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(kind == "gauge" || kind == "counter", "{}", line);
+                typed.insert(name.to_string());
+            } else if !line.starts_with('#') {
+                let (series, value) = line.rsplit_once(' ').unwrap();
+                let name = series.split('{').next().unwrap();
+                assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'), "{}", line);
+                assert!(typed.contains(name), "{} has no TYPE", name);
+                samples.insert(series.to_string(), value.parse::<f64>().unwrap());
+            }
+        }
+
+        for name in [
+            "midinet_midi_messages_per_second",
+            "midinet_packet_loss_percent",
+            "midinet_cpu_percent",
+            "midinet_cpu_temperature_celsius",
+            "midinet_clients_connected",
+            "midinet_failovers_total",
+        ] {
+            assert!(samples.contains_key(name), "missing {}", name);
+        }
+        assert_eq!(samples["midinet_midi_messages_per_second"], 42.5);
+        assert_eq!(samples["midinet_failovers_total"], 3.0);
+        assert_eq!(samples["midinet_clients_connected"], 1.0);
+        assert_eq!(samples[r#"midinet_client_packet_loss_percent{client="7",hostname="foh \"mac\""}"#], 0.25);
+    }
+}
@@ -1,11 +1,16 @@
 pub mod alerts;
+pub mod commands;
 pub mod config;
 pub mod devices;
 pub mod failover;
 pub mod focus;
 pub mod input;
+pub mod journal;
 pub mod metrics;
+pub mod panic;
 pub mod pipeline;
+pub mod program_names;
+pub mod record;
 pub mod settings;
 pub mod status;
 pub mod system;
@@ -13,15 +18,17 @@ pub mod system;
 use axum::{
     body::Body,
     extract::State,
-    http::{header, Request, StatusCode, Uri},
+    handler::Handler,
+    http::{header, Method, Request, StatusCode, Uri},
     middleware::{self, Next},
     response::{IntoResponse, Response},
-    routing::{delete, get, post, put},
-    Extension, Router,
+    routing::{get, on, MethodFilter, MethodRouter},
+    Extension, Json, Router,
 };
 use rust_embed::Embed;
+use serde::Serialize;
 
-use crate::auth::{require_auth, ApiToken};
+use crate::auth::{require_auth, ApiToken, PublicMetrics};
 use crate::state::AppState;
 use crate::websocket;
 
@@ -111,57 +118,152 @@ async fn count_api_requests(
     resp
 }
 
-pub fn build_router(state: AppState, api_token: Option<String>) -> Router {
-    // API routes with request counting middleware
-    let api_routes = Router::new()
+/// One entry in the API route table. The table drives both the router and
+/// the self-describing `GET /api` index, so the two cannot drift apart.
+struct ApiRoute {
+    endpoint: ApiEndpoint,
+    handler: MethodRouter<AppState>,
+}
+
+/// Public description of an API endpoint, as listed by `GET /api`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiEndpoint {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub description: &'static str,
+}
+
+fn endpoint<H, T>(
+    method: Method,
+    path: &'static str,
+    description: &'static str,
+    handler: H,
+) -> ApiRoute
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    let filter = MethodFilter::try_from(method.clone()).expect("standard HTTP method");
+    ApiRoute {
+        endpoint: ApiEndpoint {
+            method: method_name(&method),
+            path,
+            description,
+        },
+        handler: on(filter, handler),
+    }
+}
+
+fn method_name(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::PATCH => "PATCH",
+        _ => "OTHER",
+    }
+}
+
+fn api_route_table() -> Vec<ApiRoute> {
+    vec![
+        // API index
+        endpoint(Method::GET, "/api", "This listing of available endpoints", || async {
+            Json(api_index())
+        }),
         // System status
-        .route("/api/status", get(status::get_status))
-        .route("/api/hosts", get(status::get_hosts))
-        .route("/api/clients", get(status::get_clients))
+        endpoint(Method::GET, "/api/status", "Overall system status: hosts, clients, failover and health", status::get_status),
+        endpoint(Method::GET, "/api/hosts", "Discovered hosts and their roles", status::get_hosts),
+        endpoint(Method::GET, "/api/clients", "Connected clients and their health", status::get_clients),
         // MIDI devices
-        .route("/api/devices", get(devices::list_devices))
-        .route("/api/devices/activity", get(devices::get_device_activity))
-        .route("/api/devices/:id/identify", post(devices::identify_device).delete(devices::cancel_identify))
-        .route("/api/devices/:id/activity", post(devices::report_device_activity))
+        endpoint(Method::GET, "/api/devices", "MIDI devices attached to the hosts", devices::list_devices),
+        endpoint(Method::GET, "/api/devices/activity", "Recent per-device MIDI activity", devices::get_device_activity),
+        endpoint(Method::POST, "/api/devices/:id/identify", "Flash a device so it can be identified", devices::identify_device),
+        endpoint(Method::DELETE, "/api/devices/:id/identify", "Cancel a running identify", devices::cancel_identify),
+        endpoint(Method::POST, "/api/devices/:id/activity", "Report MIDI activity for a device", devices::report_device_activity),
         // MIDI pipeline
-        .route("/api/pipeline", get(pipeline::get_pipeline).put(pipeline::update_pipeline))
+        endpoint(Method::GET, "/api/pipeline", "Current MIDI processing pipeline", pipeline::get_pipeline),
+        endpoint(Method::PUT, "/api/pipeline", "Replace and persist the MIDI processing pipeline (?persist=false to apply only)", pipeline::update_pipeline),
+        endpoint(Method::GET, "/api/pipeline/presets", "Saved pipeline presets and the active one", pipeline::list_presets),
+        endpoint(Method::POST, "/api/pipeline/presets/:name", "Save the current pipeline as a named preset", pipeline::save_preset),
+        endpoint(Method::POST, "/api/pipeline/presets/:name/apply", "Apply a saved pipeline preset (?persist=false to apply only)", pipeline::apply_preset),
+        endpoint(Method::GET, "/api/program-names", "Program Change labels per channel", program_names::get_program_names),
+        endpoint(Method::PUT, "/api/program-names", "Replace and persist the Program Change labels", program_names::put_program_names),
         // Metrics
-        .route("/api/metrics/system", get(metrics::get_system_metrics))
-        .route("/api/metrics/midi", get(metrics::get_midi_metrics))
-        .route("/api/metrics/history", get(metrics::get_metrics_history))
+        endpoint(Method::GET, "/api/metrics/system", "Host CPU, memory and temperature", metrics::get_system_metrics),
+        endpoint(Method::GET, "/api/metrics/midi", "MIDI throughput and latency", metrics::get_midi_metrics),
+        endpoint(Method::GET, "/api/metrics/history", "Historical metrics samples", metrics::get_metrics_history),
+        endpoint(Method::GET, "/api/metrics/export", "Current gauges (?format=prometheus) or history (?format=csv)", metrics::export_metrics),
+        endpoint(Method::GET, "/api/journal", "Each host's MIDI state: sounding notes, CCs, program, bend, pressure", journal::get_journal),
         // Focus
-        .route("/api/focus", get(focus::get_focus))
+        endpoint(Method::GET, "/api/focus", "Which client currently holds feedback focus", focus::get_focus),
         // Failover
-        .route("/api/failover", get(failover::get_failover_state))
-        .route("/api/failover/switch", post(failover::trigger_failover_switch))
-        .route("/api/failover/auto", put(failover::set_auto_failover))
+        endpoint(Method::GET, "/api/failover", "Failover state and history", failover::get_failover_state),
+        endpoint(Method::POST, "/api/failover/switch", "Manually switch the active host", failover::trigger_failover_switch),
+        endpoint(Method::PUT, "/api/failover/auto", "Enable or disable automatic failover", failover::set_auto_failover),
+        endpoint(Method::POST, "/api/failover/report", "Log a role switch a host made, with its cause", failover::report_failover),
+        endpoint(Method::GET, "/api/shadow/:host_id", "Whether a shadow host has been asked to go live", failover::get_shadow_status),
+        endpoint(Method::POST, "/api/shadow/:host_id/promote", "Promote a shadow host to broadcasting", failover::promote_shadow),
+        // Panic
+        endpoint(Method::POST, "/api/panic", "All Notes Off + All Sound Off (?channel=1-16, default all)", panic::trigger_panic),
+        // Recording
+        endpoint(Method::POST, "/api/record", "Start or stop recording host input to a MIDI file (?action=start|stop)", record::trigger_record),
         // Input redundancy
-        .route("/api/input-redundancy", get(input::get_input_redundancy))
-        .route("/api/input-redundancy/switch", post(input::trigger_input_switch))
-        .route("/api/input-redundancy/auto", post(input::set_auto_switch))
+        endpoint(Method::GET, "/api/input-redundancy", "Dual-controller input redundancy state", input::get_input_redundancy),
+        endpoint(Method::POST, "/api/input-redundancy/switch", "Switch the active input controller", input::trigger_input_switch),
+        endpoint(Method::POST, "/api/input-redundancy/auto", "Enable or disable automatic input switching", input::set_auto_switch),
+        endpoint(Method::PUT, "/api/input-redundancy/:index/enable", "Enable or disable one input controller", input::set_input_enabled),
         // Fleet management
-        .route("/api/clients/register", post(status::register_client))
-        .route("/api/clients/:id/heartbeat", post(status::client_heartbeat))
-        .route("/api/hosts/:id/role", put(status::set_host_role))
-        .route("/api/clients/:id/focus", put(status::set_client_focus))
-        .route("/api/clients/add", post(status::add_client_manual))
-        .route("/api/clients/:id", delete(status::remove_client))
+        endpoint(Method::POST, "/api/clients/register", "Register a client with the admin panel", status::register_client),
+        endpoint(Method::POST, "/api/clients/:id/heartbeat", "Client health heartbeat", status::client_heartbeat),
+        endpoint(Method::PUT, "/api/hosts/:id/role", "Set a host's role (primary/standby)", status::set_host_role),
+        endpoint(Method::PUT, "/api/hosts/:id/promote", "Pin a host as primary and the others as standby", failover::promote_host),
+        endpoint(Method::PUT, "/api/clients/:id/focus", "Give or take feedback focus for a client", status::set_client_focus),
+        endpoint(Method::POST, "/api/clients/add", "Manually add a client", status::add_client_manual),
+        endpoint(Method::POST, "/api/clients/command", "Send a command to all (or selected) clients and collect acks", commands::send_bulk_command),
+        endpoint(Method::DELETE, "/api/clients/:id", "Remove a client", status::remove_client),
         // Alerts
-        .route("/api/alerts", get(alerts::get_alerts))
-        .route("/api/alerts/config", get(alerts::get_alert_config).put(alerts::update_alert_config))
+        endpoint(Method::GET, "/api/alerts", "Active alerts", alerts::get_alerts),
+        endpoint(Method::GET, "/api/alerts/config", "Alert thresholds and webhook settings", alerts::get_alert_config),
+        endpoint(Method::PUT, "/api/alerts/config", "Update alert thresholds and webhook settings", alerts::update_alert_config),
+        endpoint(Method::POST, "/api/alerts/report", "Set or clear a condition reported by a host (e.g. split brain)", alerts::report_alert),
+        endpoint(Method::POST, "/api/alerts/:id/ack", "Acknowledge an active alert (no more notifications)", alerts::ack_alert),
+        endpoint(Method::POST, "/api/alerts/:id/snooze", "Hide an active alert for ?minutes=N", alerts::snooze_alert),
         // Config
-        .route("/api/config", get(config::get_config).put(config::put_config))
+        endpoint(Method::GET, "/api/config", "Full MIDInet configuration", config::get_config),
+        endpoint(Method::GET, "/api/config/effective", "Running config vs the file on disk, with drifted sections", config::get_effective_config),
+        endpoint(Method::GET, "/api/config/download", "Download the config file on disk as TOML", config::download_config),
+        endpoint(Method::POST, "/api/config/upload", "Validate and replace the config file with an uploaded TOML", config::upload_config),
+        endpoint(Method::PUT, "/api/config", "Replace and persist the MIDInet configuration (?persist=false to apply only)", config::put_config),
         // System management
-        .route("/api/system/update-check", get(system::check_update))
-        .route("/api/system/update", post(system::run_update))
-        .route("/api/system/update-status", get(system::update_status))
+        endpoint(Method::GET, "/api/system/update-check", "Check for a newer MIDInet version", system::check_update),
+        endpoint(Method::POST, "/api/system/update", "Start a MIDInet update", system::run_update),
+        endpoint(Method::GET, "/api/system/update-status", "Progress of a running update", system::update_status),
         // Settings
-        .route("/api/settings", get(settings::get_settings))
-        .route("/api/settings/midi-device", put(settings::set_midi_device))
-        .route("/api/settings/osc-port", put(settings::set_osc_port))
-        .route("/api/settings/failover", put(settings::set_failover))
-        .route("/api/settings/presets", get(settings::list_presets))
-        .route("/api/settings/preset", post(settings::apply_preset))
+        endpoint(Method::GET, "/api/settings", "Current settings", settings::get_settings),
+        endpoint(Method::PUT, "/api/settings/midi-device", "Select the host MIDI input device", settings::set_midi_device),
+        endpoint(Method::PUT, "/api/settings/osc-port", "Change the OSC monitor port", settings::set_osc_port),
+        endpoint(Method::PUT, "/api/settings/failover", "Update failover settings", settings::set_failover),
+        endpoint(Method::GET, "/api/settings/presets", "Available settings presets", settings::list_presets),
+        endpoint(Method::POST, "/api/settings/preset", "Apply a settings preset", settings::apply_preset),
+    ]
+}
+
+/// All registered API endpoints with their methods and descriptions.
+pub fn api_index() -> Vec<ApiEndpoint> {
+    api_route_table().into_iter().map(|r| r.endpoint).collect()
+}
+
+/// Build the `/api/*` router from the route table.
+fn api_router() -> Router<AppState> {
+    api_route_table()
+        .into_iter()
+        .fold(Router::new(), |router, r| router.route(r.endpoint.path, r.handler))
+}
+
+pub fn build_router(state: AppState, api_token: Option<String>, public_metrics: bool) -> Router {
+    // API routes with request counting middleware
+    let api_routes = api_router()
         // Count API requests for traffic monitor
         .layer(middleware::from_fn_with_state(state.clone(), count_api_requests));
 
@@ -177,8 +279,50 @@ pub fn build_router(state: AppState, api_token: Option<String>) -> Router {
         // Auth middleware (only checks /api/* paths, static + ws are exempt)
         .layer(middleware::from_fn(require_auth))
         .layer(Extension(ApiToken(api_token)))
+        .layer(Extension(PublicMetrics(public_metrics)))
         // Static files (fallback for everything else)
         .fallback(static_handler)
         // State
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has(index: &[ApiEndpoint], method: &str, path: &str) -> bool {
+        index.iter().any(|e| e.method == method && e.path == path)
+    }
+
+    #[test]
+    fn index_lists_known_routes_with_methods() {
+        let index = api_index();
+        assert!(has(&index, "GET", "/api"));
+        assert!(has(&index, "GET", "/api/status"));
+        assert!(has(&index, "POST", "/api/failover/switch"));
+        assert!(has(&index, "POST", "/api/panic"));
+        assert!(has(&index, "POST", "/api/record"));
+        assert!(has(&index, "POST", "/api/clients/command"));
+        assert!(has(&index, "PUT", "/api/config"));
+        assert!(has(&index, "GET", "/api/config/effective"));
+        assert!(has(&index, "POST", "/api/config/upload"));
+        assert!(has(&index, "DELETE", "/api/clients/:id"));
+        assert!(!has(&index, "GET", "/api/failover/switch"));
+        assert!(index.iter().all(|e| !e.description.is_empty()));
+    }
+
+    #[test]
+    fn index_has_no_duplicate_entries() {
+        let index = api_index();
+        let mut seen = std::collections::HashSet::new();
+        for e in &index {
+            assert!(seen.insert((e.method, e.path)), "duplicate {} {}", e.method, e.path);
+        }
+    }
+
+    #[test]
+    fn route_table_builds_a_router() {
+        // Panics on conflicting registrations (same path + method twice)
+        let _router = api_router();
+    }
+}
@@ -0,0 +1,133 @@
+/// API endpoint for MIDI panic (All Notes Off + All Sound Off).
+///
+/// POST /api/panic             — panic on all 16 channels
+/// POST /api/panic?channel=N   — panic on one channel (1-16) only
+///
+/// The request is sent as a `PanicPacket` on the control multicast group;
+/// hosts broadcast the CCs in-sequence and clear the channel's held notes.
+/// Every panic is recorded in the alert history with who asked for it:
+/// the optional `?by=` name (the CLI sends the local user) and the
+/// caller's address.
+
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Query, State};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::UdpSocket;
+use tracing::info;
+
+use midi_protocol::multicast;
+use midi_protocol::packets::PanicPacket;
+
+use crate::alerting::AlertSeverity;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct PanicQuery {
+    /// MIDI channel 1-16 (omit for all channels)
+    pub channel: Option<u8>,
+    /// Who triggered the panic, for the alert log
+    pub by: Option<String>,
+}
+
+/// "alice (10.0.0.5)", "10.0.0.5", "alice" or "unknown".
+fn trigger_source(by: Option<&str>, addr: Option<SocketAddr>) -> String {
+    let by = by.map(str::trim).filter(|s| !s.is_empty());
+    match (by, addr) {
+        (Some(by), Some(addr)) => format!("{} ({})", by, addr.ip()),
+        (Some(by), None) => by.to_string(),
+        (None, Some(addr)) => addr.ip().to_string(),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+/// POST /api/panic
+pub async fn trigger_panic(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Query(params): Query<PanicQuery>,
+) -> Json<Value> {
+    let channel = match params.channel {
+        Some(ch @ 1..=16) => Some(ch - 1),
+        Some(ch) => return Json(json!({ "error": format!("Invalid channel {} (must be 1-16)", ch) })),
+        None => None,
+    };
+
+    let (group, port) = match state.inner.network_config.read().await.as_ref() {
+        Some(net) => (net.control_group.clone(), net.control_port),
+        None => (
+            midi_protocol::DEFAULT_CONTROL_GROUP.to_string(),
+            midi_protocol::DEFAULT_CONTROL_PORT,
+        ),
+    };
+
+    match send_panic(&group, port, channel).await {
+        Ok(()) => {
+            let source = trigger_source(params.by.as_deref(), peer.map(|ConnectInfo(addr)| addr));
+            let target = match params.channel {
+                Some(ch) => format!("channel {}", ch),
+                None => "all channels".to_string(),
+            };
+            state.inner.alert_manager.record_event(
+                "midi_panic",
+                AlertSeverity::Warning,
+                format!("MIDI panic on {} triggered by {}", target, source),
+            );
+            info!(channel = ?params.channel, group = %group, by = %source, "MIDI panic sent");
+            Json(json!({
+                "success": true,
+                "channel": params.channel,
+            }))
+        }
+        Err(e) => Json(json!({ "error": format!("Failed to send panic: {}", e) })),
+    }
+}
+
+async fn send_panic(group: &str, port: u16, channel: Option<u8>) -> anyhow::Result<()> {
+    let group = multicast::parse_group(group)?;
+    let socket = UdpSocket::bind(multicast::bind_addr(group, 0)).await?;
+    // IPv6 multicast already defaults to a hop limit of 1
+    if group.is_ipv4() {
+        socket.set_multicast_ttl_v4(1)?;
+    }
+
+    let packet = PanicPacket {
+        channel,
+        timestamp_us: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64,
+    };
+    let mut buf = [0u8; PanicPacket::SIZE];
+    packet.serialize(&mut buf);
+
+    socket.send_to(&buf, SocketAddr::new(group, port)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{AlertManager, AlertState};
+
+    #[test]
+    fn panic_event_records_who_triggered_it() {
+        let addr: SocketAddr = "10.0.0.5:51234".parse().unwrap();
+        assert_eq!(trigger_source(Some("alice"), Some(addr)), "alice (10.0.0.5)");
+        assert_eq!(trigger_source(Some("  "), Some(addr)), "10.0.0.5");
+        assert_eq!(trigger_source(None, None), "unknown");
+
+        let alerts = AlertManager::new();
+        alerts.record_event("midi_panic", AlertSeverity::Warning, "MIDI panic on all channels triggered by alice".into());
+
+        // Logged, but not left standing as an active alert
+        assert!(alerts.active_alerts().is_empty());
+        let history = alerts.alert_history(10);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].source, "midi_panic");
+        assert_eq!(history[0].state, AlertState::Resolved);
+        assert!(history[0].message.contains("alice"));
+    }
+}
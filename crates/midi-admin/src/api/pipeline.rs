@@ -1,19 +1,256 @@
-use axum::extract::State;
+use std::collections::BTreeMap;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde_json::{json, Value};
+use tracing::{info, warn};
 
-use crate::state::AppState;
+use crate::api::config::{persist_if, replace_config_file, PersistQuery};
+use crate::state::{AppState, PipelineConfig};
 
 pub async fn get_pipeline(State(state): State<AppState>) -> Json<Value> {
     let pipeline = state.inner.pipeline_config.read().await;
-    Json(json!({ "pipeline": *pipeline }))
+    let active_preset = state.inner.active_pipeline_preset.read().await;
+    Json(json!({ "pipeline": *pipeline, "active_preset": *active_preset }))
 }
 
+/// PUT /api/pipeline — applies in memory and writes the `[pipeline]` section
+/// of the shared config file, which the host loads at boot
+/// (`?persist=false` to apply only).
 pub async fn update_pipeline(
     State(state): State<AppState>,
+    Query(query): Query<PersistQuery>,
     Json(config): Json<crate::state::PipelineConfig>,
 ) -> Json<Value> {
-    let mut pipeline = state.inner.pipeline_config.write().await;
-    *pipeline = config;
-    Json(json!({ "success": true }))
+    if let Err(e) = config.validate() {
+        return Json(json!({ "success": false, "error": e }));
+    }
+    *state.inner.pipeline_config.write().await = config;
+    // Clear active preset (manual change)
+    *state.inner.active_pipeline_preset.write().await = None;
+
+    let persist = query.persist_or(true);
+    if let Err(e) = persist_if(&state, persist).await {
+        return Json(json!({
+            "success": false,
+            "error": format!("Pipeline applied in memory but config save failed: {}", e)
+        }));
+    }
+    Json(json!({ "success": true, "persisted": persist }))
+}
+
+// ── Presets ──
+
+/// Where pipeline presets are stored: `pipeline_presets.json` in the
+/// directory of the config file.
+pub fn presets_path(config_path: &str) -> std::path::PathBuf {
+    std::path::Path::new(config_path).with_file_name("pipeline_presets.json")
+}
+
+/// Load the pipeline presets stored next to the config file into state.
+/// A missing file just means no presets have been saved yet.
+pub async fn load_presets(state: &AppState) -> anyhow::Result<usize> {
+    let path = presets_path(&state.inner.config_path.read().await);
+    let presets: BTreeMap<String, PipelineConfig> = match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let count = presets.len();
+    *state.inner.pipeline_presets.write().await = presets;
+    Ok(count)
+}
+
+/// Write all pipeline presets to disk via a temp file and rename.
+async fn save_presets(state: &AppState) -> Result<(), String> {
+    let path = presets_path(&state.inner.config_path.read().await);
+    let contents = serde_json::to_string_pretty(&*state.inner.pipeline_presets.read().await)
+        .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+    }
+    replace_config_file(&path.to_string_lossy(), &contents).map_err(|e| {
+        warn!(path = %path.display(), error = %e, "Failed to save pipeline presets");
+        format!("Failed to save: {}", e)
+    })
+}
+
+/// GET /api/pipeline/presets — saved pipeline presets and the active one.
+pub async fn list_presets(State(state): State<AppState>) -> Json<Value> {
+    let presets: Vec<Value> = state
+        .inner
+        .pipeline_presets
+        .read()
+        .await
+        .iter()
+        .map(|(name, pipeline)| json!({ "name": name, "pipeline": pipeline }))
+        .collect();
+    let active_preset = state.inner.active_pipeline_preset.read().await;
+
+    Json(json!({ "presets": presets, "active_preset": *active_preset }))
+}
+
+/// POST /api/pipeline/presets/:name — save the current pipeline under `name`,
+/// replacing any preset of that name.
+pub async fn save_preset(State(state): State<AppState>, Path(name): Path<String>) -> Json<Value> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Json(json!({ "success": false, "error": "preset name cannot be empty" }));
+    }
+
+    let pipeline = state.inner.pipeline_config.read().await.clone();
+    let replaced = state
+        .inner
+        .pipeline_presets
+        .write()
+        .await
+        .insert(name.clone(), pipeline)
+        .is_some();
+
+    if let Err(e) = save_presets(&state).await {
+        return Json(json!({
+            "success": false,
+            "error": format!("Preset saved in memory but could not be written: {}", e)
+        }));
+    }
+    *state.inner.active_pipeline_preset.write().await = Some(name.clone());
+
+    info!(preset = %name, replaced, "Pipeline preset saved");
+    Json(json!({ "success": true, "name": name, "replaced": replaced }))
+}
+
+/// POST /api/pipeline/presets/:name/apply — make a saved preset the active
+/// pipeline. Persists like `PUT /api/pipeline` (`?persist=false` to apply only).
+pub async fn apply_preset(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<PersistQuery>,
+) -> Response {
+    let preset = state.inner.pipeline_presets.read().await.get(&name).cloned();
+    let Some(pipeline) = preset else {
+        let available: Vec<String> = state.inner.pipeline_presets.read().await.keys().cloned().collect();
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "success": false,
+                "error": format!("Unknown pipeline preset '{}'. Available: {:?}", name, available)
+            })),
+        )
+            .into_response();
+    };
+
+    *state.inner.pipeline_config.write().await = pipeline;
+    *state.inner.active_pipeline_preset.write().await = Some(name.clone());
+
+    let persist = query.persist_or(true);
+    if let Err(e) = persist_if(&state, persist).await {
+        return Json(json!({
+            "success": false,
+            "error": format!("Preset applied in memory but config save failed: {}", e)
+        }))
+        .into_response();
+    }
+
+    info!(preset = %name, "Pipeline preset applied");
+    Json(json!({ "success": true, "preset": name, "persisted": persist })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn inverted_velocity_range_is_rejected() {
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        let mut config = state.inner.pipeline_config.read().await.clone();
+        config.velocity_min = 100;
+        config.velocity_max = 20;
+
+        let Json(resp) = update_pipeline(State(state.clone()), Query(PersistQuery::default()), Json(config)).await;
+        assert_eq!(resp["success"], false);
+        assert!(resp["error"].as_str().unwrap().contains("velocity_min"));
+        assert_eq!(state.inner.pipeline_config.read().await.velocity_min, 1, "rejected config must not be applied");
+    }
+
+    #[tokio::test]
+    async fn updated_pipeline_survives_a_reload() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-pipeline-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+        // Sections owned by the host are kept as they are
+        std::fs::write(&path, "[host]\nid = 2\n\n[pipeline]\ntranspose = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]\n").unwrap();
+
+        let state = AppState::new(path.clone());
+        let mut config = state.inner.pipeline_config.read().await.clone();
+        config.transpose[0] = 12;
+        config.velocity_curve = midi_protocol::pipeline::VelocityCurve::SCurve;
+        config.feedback_velocity_curve =
+            midi_protocol::pipeline::VelocityCurve::Compressor { threshold: 90, ratio: 3.0, makeup: 4 };
+        config.mono[2] = true;
+        config.cc_remap = vec![midi_protocol::pipeline::CcRemap { channel: 0, from: 21, to: 71 }];
+        config.harmonize.insert("1".to_string(), vec![0, 4, 7]);
+        config.message_filter.sysex = false;
+
+        let Json(resp) = update_pipeline(State(state.clone()), Query(PersistQuery::default()), Json(config.clone())).await;
+        assert_eq!(resp["success"], true);
+        assert_eq!(resp["persisted"], true);
+
+        let reloaded = crate::api::config::load_config(&path).unwrap();
+        assert_eq!(reloaded.pipeline, config);
+        assert_eq!(*state.inner.pipeline_config.read().await, config);
+        let on_disk: toml::Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk["host"]["id"].as_integer(), Some(2));
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists(), "temp file is renamed into place");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn preset_save_list_apply_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-presets-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+
+        let state = AppState::new(path.clone());
+        let mut act_one = state.inner.pipeline_config.read().await.clone();
+        act_one.transpose[0] = -12;
+        act_one.channel_remap[1] = 3;
+        *state.inner.pipeline_config.write().await = act_one.clone();
+        let Json(resp) = save_preset(State(state.clone()), Path("act-one".to_string())).await;
+        assert_eq!(resp["success"], true);
+
+        // Switch to a manual pipeline, then back to the preset
+        let Json(resp) = update_pipeline(State(state.clone()), Query(PersistQuery { persist: Some(false) }), Json(PipelineConfig::default())).await;
+        assert_eq!(resp["success"], true);
+        assert_eq!(*state.inner.active_pipeline_preset.read().await, None);
+
+        // A fresh admin loads the presets from disk
+        let reloaded = AppState::new(path.clone());
+        assert_eq!(load_presets(&reloaded).await.unwrap(), 1);
+        let Json(list) = list_presets(State(reloaded.clone())).await;
+        assert_eq!(list["presets"][0]["name"], "act-one");
+        assert_eq!(list["presets"][0]["pipeline"]["transpose"][0], -12);
+
+        let resp = apply_preset(State(reloaded.clone()), Path("act-one".to_string()), Query(PersistQuery::default())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(*reloaded.inner.pipeline_config.read().await, act_one);
+        assert_eq!(reloaded.inner.active_pipeline_preset.read().await.as_deref(), Some("act-one"));
+        assert_eq!(crate::api::config::load_config(&path).unwrap().pipeline, act_one);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn applying_an_unknown_preset_is_not_found() {
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        let resp = apply_preset(State(state.clone()), Path("missing".to_string()), Query(PersistQuery::default())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(*state.inner.pipeline_config.read().await, PipelineConfig::default());
+    }
 }
@@ -0,0 +1,164 @@
+/// Human labels for Program Change numbers, per channel.
+///
+/// GET /api/program-names — the label map
+/// PUT /api/program-names — replace it (saved to `program_names.json` next
+/// to the config file)
+///
+/// Labels are metadata for the dashboard only: they are resolved where the
+/// admin panel reports program changes (`/api/metrics/midi`, the traffic
+/// sniffer) and never touch the MIDI stream.
+
+use std::collections::BTreeMap;
+
+use axum::extract::State;
+use axum::Json;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::api::config::replace_config_file;
+use crate::state::AppState;
+
+/// Channel (1-16) → program number (0-127) → label.
+pub type ProgramNames = BTreeMap<u8, BTreeMap<u8, String>>;
+
+/// The label for `program` on `channel` (1-16), or the program number
+/// itself when it has no label.
+pub fn program_label(names: &ProgramNames, channel: u8, program: u8) -> String {
+    names
+        .get(&channel)
+        .and_then(|programs| programs.get(&program))
+        .cloned()
+        .unwrap_or_else(|| program.to_string())
+}
+
+/// Reject channels outside 1-16, programs above 127 and empty labels.
+pub fn validate_program_names(names: &ProgramNames) -> Result<(), String> {
+    for (&channel, programs) in names {
+        if !(1..=16).contains(&channel) {
+            return Err(format!("channel {} must be 1-16", channel));
+        }
+        for (&program, label) in programs {
+            if program > 127 {
+                return Err(format!("channel {}: program {} must be 0-127", channel, program));
+            }
+            if label.trim().is_empty() {
+                return Err(format!("channel {}: program {} has an empty label", channel, program));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where program labels are stored: `program_names.json` in the directory
+/// of the config file.
+pub fn program_names_path(config_path: &str) -> std::path::PathBuf {
+    std::path::Path::new(config_path).with_file_name("program_names.json")
+}
+
+/// Load the program labels stored next to the config file into state.
+/// A missing file just means no labels have been set yet.
+pub async fn load_program_names(state: &AppState) -> anyhow::Result<usize> {
+    let path = program_names_path(&state.inner.config_path.read().await);
+    let names: ProgramNames = match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProgramNames::new(),
+        Err(e) => return Err(e.into()),
+    };
+    validate_program_names(&names).map_err(|e| anyhow::anyhow!(e))?;
+    let count = names.values().map(|programs| programs.len()).sum();
+    *state.inner.program_names.write().await = names;
+    Ok(count)
+}
+
+/// Write the program labels to disk via a temp file and rename.
+fn save_program_names(config_path: &str, names: &ProgramNames) -> Result<(), String> {
+    let path = program_names_path(config_path);
+    let contents = serde_json::to_string_pretty(names)
+        .map_err(|e| format!("Failed to serialize program names: {}", e))?;
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+    }
+    replace_config_file(&path.to_string_lossy(), &contents).map_err(|e| {
+        warn!(path = %path.display(), error = %e, "Failed to save program names");
+        format!("Failed to save: {}", e)
+    })
+}
+
+/// GET /api/program-names
+pub async fn get_program_names(State(state): State<AppState>) -> Json<Value> {
+    let names = state.inner.program_names.read().await;
+    Json(json!({ "program_names": *names }))
+}
+
+/// PUT /api/program-names — replace the whole label map and save it.
+pub async fn put_program_names(
+    State(state): State<AppState>,
+    Json(names): Json<ProgramNames>,
+) -> Json<Value> {
+    if let Err(e) = validate_program_names(&names) {
+        return Json(json!({ "success": false, "error": e }));
+    }
+
+    let config_path = state.inner.config_path.read().await.clone();
+    if let Err(e) = save_program_names(&config_path, &names) {
+        return Json(json!({ "success": false, "error": e }));
+    }
+    let count: usize = names.values().map(|programs| programs.len()).sum();
+    *state.inner.program_names.write().await = names;
+
+    info!(labels = count, "Program names updated");
+    Json(json!({ "success": true, "labels": count }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unmapped_program_falls_back_to_its_number() {
+        let mut names = ProgramNames::new();
+        names.entry(1).or_default().insert(4, "Verse Pad".to_string());
+
+        assert_eq!(program_label(&names, 1, 4), "Verse Pad");
+        assert_eq!(program_label(&names, 1, 5), "5");
+        assert_eq!(program_label(&names, 2, 4), "4", "labels are per channel");
+    }
+
+    #[test]
+    fn out_of_range_entries_are_rejected() {
+        let mut names = ProgramNames::new();
+        names.entry(17).or_default().insert(0, "Piano".to_string());
+        assert!(validate_program_names(&names).unwrap_err().contains("1-16"));
+
+        let mut names = ProgramNames::new();
+        names.entry(1).or_default().insert(128, "Piano".to_string());
+        assert!(validate_program_names(&names).unwrap_err().contains("0-127"));
+    }
+
+    #[tokio::test]
+    async fn program_names_survive_a_reload() {
+        let dir = std::env::temp_dir().join(format!("midinet-admin-program-names-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("midinet.toml").to_string_lossy().into_owned();
+
+        let mut names = ProgramNames::new();
+        names.entry(1).or_default().insert(0, "Grand Piano".to_string());
+        names.entry(10).or_default().insert(25, "Brush Kit".to_string());
+
+        let state = AppState::new(path.clone());
+        let Json(resp) = put_program_names(State(state.clone()), Json(names.clone())).await;
+        assert_eq!(resp["success"], true);
+        assert_eq!(resp["labels"], 2);
+
+        let reloaded = AppState::new(path.clone());
+        assert_eq!(load_program_names(&reloaded).await.unwrap(), 2);
+        assert_eq!(*reloaded.inner.program_names.read().await, names);
+        let Json(listed) = get_program_names(State(reloaded)).await;
+        assert_eq!(listed["program_names"]["10"]["25"], "Brush Kit");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
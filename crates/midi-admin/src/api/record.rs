@@ -0,0 +1,99 @@
+/// API endpoint for the hosts' input recorders.
+///
+/// POST /api/record?action=start  — start a new recording on every host
+/// POST /api/record?action=stop   — stop and close the current recording
+///
+/// The request is sent as a `RecordPacket` on the control multicast group.
+/// Hosts with `[recording] enabled = true` write what arrives through their
+/// input to a Standard MIDI File; other hosts ignore it.
+
+use std::net::SocketAddr;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::UdpSocket;
+use tracing::info;
+
+use midi_protocol::multicast;
+use midi_protocol::packets::RecordPacket;
+
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct RecordQuery {
+    /// "start" or "stop"
+    pub action: String,
+}
+
+fn parse_action(action: &str) -> Option<bool> {
+    match action.trim().to_ascii_lowercase().as_str() {
+        "start" => Some(true),
+        "stop" => Some(false),
+        _ => None,
+    }
+}
+
+/// POST /api/record
+pub async fn trigger_record(
+    State(state): State<AppState>,
+    Query(params): Query<RecordQuery>,
+) -> Json<Value> {
+    let Some(start) = parse_action(&params.action) else {
+        return Json(json!({ "error": format!("Invalid action '{}' (must be start or stop)", params.action) }));
+    };
+
+    let (group, port) = match state.inner.network_config.read().await.as_ref() {
+        Some(net) => (net.control_group.clone(), net.control_port),
+        None => (
+            midi_protocol::DEFAULT_CONTROL_GROUP.to_string(),
+            midi_protocol::DEFAULT_CONTROL_PORT,
+        ),
+    };
+
+    match send_record(&group, port, start).await {
+        Ok(()) => {
+            info!(start, group = %group, "Record request sent");
+            Json(json!({
+                "success": true,
+                "recording": start,
+            }))
+        }
+        Err(e) => Json(json!({ "error": format!("Failed to send record request: {}", e) })),
+    }
+}
+
+async fn send_record(group: &str, port: u16, start: bool) -> anyhow::Result<()> {
+    let group = multicast::parse_group(group)?;
+    let socket = UdpSocket::bind(multicast::bind_addr(group, 0)).await?;
+    // IPv6 multicast already defaults to a hop limit of 1
+    if group.is_ipv4() {
+        socket.set_multicast_ttl_v4(1)?;
+    }
+
+    let packet = RecordPacket {
+        start,
+        timestamp_us: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64,
+    };
+    let mut buf = [0u8; RecordPacket::SIZE];
+    packet.serialize(&mut buf);
+
+    socket.send_to(&buf, SocketAddr::new(group, port)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_start_and_stop_are_actions() {
+        assert_eq!(parse_action("start"), Some(true));
+        assert_eq!(parse_action(" STOP "), Some(false));
+        assert_eq!(parse_action("pause"), None);
+    }
+}
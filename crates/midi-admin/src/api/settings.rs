@@ -1,10 +1,10 @@
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tracing::info;
 
-use crate::api::config::persist_config;
+use crate::api::config::{persist_if, PersistQuery};
 use crate::state::{
     AppState, FailoverSettings, FailoverTriggerSettings, HeartbeatSettings, MidiDeviceStatus,
     MidiTriggerSettings, OscTriggerSettings,
@@ -36,12 +36,14 @@ fn builtin_presets() -> Vec<Preset> {
                 auto_enabled: true,
                 switch_back_policy: "manual".to_string(),
                 lockout_seconds: 10,
+                switch_back_delay_s: 10,
                 confirmation_mode: "confirm".to_string(),
                 heartbeat: HeartbeatSettings {
                     interval_ms: 5,
                     miss_threshold: 5,
                 },
                 triggers: FailoverTriggerSettings::default(),
+                active_host: None,
             },
         },
         Preset {
@@ -52,12 +54,14 @@ fn builtin_presets() -> Vec<Preset> {
                 auto_enabled: true,
                 switch_back_policy: "manual".to_string(),
                 lockout_seconds: 3,
+                switch_back_delay_s: 10,
                 confirmation_mode: "immediate".to_string(),
                 heartbeat: HeartbeatSettings {
                     interval_ms: 2,
                     miss_threshold: 2,
                 },
                 triggers: FailoverTriggerSettings::default(),
+                active_host: None,
             },
         },
         Preset {
@@ -68,6 +72,7 @@ fn builtin_presets() -> Vec<Preset> {
                 auto_enabled: true,
                 switch_back_policy: "auto".to_string(),
                 lockout_seconds: 2,
+                switch_back_delay_s: 5,
                 confirmation_mode: "immediate".to_string(),
                 heartbeat: HeartbeatSettings {
                     interval_ms: 3,
@@ -83,6 +88,7 @@ fn builtin_presets() -> Vec<Preset> {
                         ..OscTriggerSettings::default()
                     },
                 },
+                active_host: None,
             },
         },
     ]
@@ -112,6 +118,7 @@ pub struct SetFailoverRequest {
     pub auto_enabled: Option<bool>,
     pub switch_back_policy: Option<String>,
     pub lockout_seconds: Option<u64>,
+    pub switch_back_delay_s: Option<u64>,
     pub confirmation_mode: Option<String>,
     pub heartbeat: Option<HeartbeatSettings>,
     pub triggers: Option<FailoverTriggerSettings>,
@@ -124,6 +131,44 @@ pub struct ApplyPresetRequest {
 
 // ── Validation helpers ──
 
+/// Hard limits on failover settings. Shared by the settings API and the
+/// config file dry run so both accept exactly the same values.
+pub(crate) fn validate_failover(settings: &FailoverSettings) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if settings.switch_back_policy != "manual" && settings.switch_back_policy != "auto" {
+        errors.push("switch_back_policy must be 'manual' or 'auto'".to_string());
+    }
+    if settings.lockout_seconds > 300 {
+        errors.push("lockout_seconds must be 0-300".to_string());
+    }
+    if settings.switch_back_delay_s == 0 || settings.switch_back_delay_s > 600 {
+        errors.push("switch_back_delay_s must be 1-600".to_string());
+    }
+    if settings.confirmation_mode != "immediate" && settings.confirmation_mode != "confirm" {
+        errors.push("confirmation_mode must be 'immediate' or 'confirm'".to_string());
+    }
+    if settings.heartbeat.interval_ms == 0 || settings.heartbeat.interval_ms > 1000 {
+        errors.push("heartbeat.interval_ms must be 1-1000".to_string());
+    }
+    if settings.heartbeat.miss_threshold == 0 || settings.heartbeat.miss_threshold > 20 {
+        errors.push("heartbeat.miss_threshold must be 1-20".to_string());
+    }
+    if settings.triggers.midi.channel == 0 || settings.triggers.midi.channel > 16 {
+        errors.push("MIDI trigger channel must be 1-16".to_string());
+    }
+    if settings.triggers.midi.note > 127 {
+        errors.push("MIDI trigger note must be 0-127".to_string());
+    }
+    if settings.triggers.osc.enabled {
+        if let Err(msg) = validate_port(settings.triggers.osc.listen_port) {
+            errors.push(format!("OSC trigger port: {}", msg));
+        }
+    }
+
+    errors
+}
+
 fn validate_failover_warnings(settings: &FailoverSettings) -> Vec<String> {
     let mut warnings = Vec::new();
 
@@ -164,7 +209,7 @@ fn validate_failover_warnings(settings: &FailoverSettings) -> Vec<String> {
     warnings
 }
 
-fn validate_port(port: u16) -> Result<(), String> {
+pub(crate) fn validate_port(port: u16) -> Result<(), String> {
     if port < 1024 {
         return Err("Port must be 1024 or higher (privileged ports require root).".into());
     }
@@ -208,8 +253,10 @@ pub async fn get_settings(State(state): State<AppState>) -> Json<Value> {
 /// PUT /api/settings/midi-device — assign a MIDI device to active or backup role.
 pub async fn set_midi_device(
     State(state): State<AppState>,
+    Query(query): Query<PersistQuery>,
     Json(req): Json<SetMidiDeviceRequest>,
 ) -> Json<Value> {
+    let persist = query.persist_or(true);
     let device_id = req.device_id.trim().to_string();
     let role = req.role.trim().to_lowercase();
 
@@ -228,11 +275,11 @@ pub async fn set_midi_device(
             ir.enabled = false;
         }
         *state.inner.active_preset.write().await = None;
-        if let Err(e) = persist_config(&state).await {
+        if let Err(e) = persist_if(&state, persist).await {
             return Json(json!({ "success": false, "error": format!("Config save failed: {}", e) }));
         }
         info!("Backup MIDI device cleared via settings API");
-        return Json(json!({ "success": true, "role": "backup", "device": Value::Null, "note": "Backup device cleared." }));
+        return Json(json!({ "success": true, "role": "backup", "device": Value::Null, "persisted": persist, "note": "Backup device cleared." }));
     }
 
     if device_id.is_empty() {
@@ -306,7 +353,7 @@ pub async fn set_midi_device(
     *state.inner.active_preset.write().await = None;
 
     // Persist to disk
-    if let Err(e) = persist_config(&state).await {
+    if let Err(e) = persist_if(&state, persist).await {
         return Json(json!({
             "success": false,
             "error": format!("Device selected but config save failed: {}", e)
@@ -320,15 +367,22 @@ pub async fn set_midi_device(
         "role": role,
         "device": config_value,
         "status": if role == "active" { "switching" } else { "assigned" },
-        "note": "Device change persisted. The host daemon will pick up the new device on its next config reload."
+        "persisted": persist,
+        "note": if persist {
+            "Device change persisted. The host daemon will pick up the new device on its next config reload."
+        } else {
+            "Device change applied in memory only; the config file is unchanged."
+        }
     }))
 }
 
 /// PUT /api/settings/osc-port — change the OSC monitor listen port.
 pub async fn set_osc_port(
     State(state): State<AppState>,
+    Query(query): Query<PersistQuery>,
     Json(req): Json<SetOscPortRequest>,
 ) -> Json<Value> {
+    let persist = query.persist_or(true);
     // Validate
     if let Err(msg) = validate_port(req.port) {
         return Json(json!({ "success": false, "error": msg }));
@@ -350,7 +404,7 @@ pub async fn set_osc_port(
     }
 
     // Persist
-    if let Err(e) = persist_config(&state).await {
+    if let Err(e) = persist_if(&state, persist).await {
         return Json(json!({
             "success": false,
             "error": format!("Port changed but config save failed: {}", e)
@@ -362,15 +416,18 @@ pub async fn set_osc_port(
     Json(json!({
         "success": true,
         "port": req.port,
-        "status": "listening"
+        "status": "listening",
+        "persisted": persist,
     }))
 }
 
 /// PUT /api/settings/failover — update failover settings with validation.
 pub async fn set_failover(
     State(state): State<AppState>,
+    Query(query): Query<PersistQuery>,
     Json(req): Json<SetFailoverRequest>,
 ) -> Json<Value> {
+    let persist = query.persist_or(true);
     // Merge with current settings (partial update)
     let mut settings = state.inner.failover_config.read().await.clone();
 
@@ -378,48 +435,26 @@ pub async fn set_failover(
         settings.auto_enabled = v;
     }
     if let Some(v) = req.switch_back_policy {
-        if v != "manual" && v != "auto" {
-            return Json(json!({ "success": false, "error": "switch_back_policy must be 'manual' or 'auto'" }));
-        }
         settings.switch_back_policy = v;
     }
     if let Some(v) = req.lockout_seconds {
-        if v > 300 {
-            return Json(json!({ "success": false, "error": "lockout_seconds must be 0-300" }));
-        }
         settings.lockout_seconds = v;
     }
+    if let Some(v) = req.switch_back_delay_s {
+        settings.switch_back_delay_s = v;
+    }
     if let Some(v) = req.confirmation_mode {
-        if v != "immediate" && v != "confirm" {
-            return Json(json!({ "success": false, "error": "confirmation_mode must be 'immediate' or 'confirm'" }));
-        }
         settings.confirmation_mode = v;
     }
     if let Some(v) = req.heartbeat {
-        if v.interval_ms == 0 || v.interval_ms > 1000 {
-            return Json(json!({ "success": false, "error": "heartbeat.interval_ms must be 1-1000" }));
-        }
-        if v.miss_threshold == 0 || v.miss_threshold > 20 {
-            return Json(json!({ "success": false, "error": "heartbeat.miss_threshold must be 1-20" }));
-        }
         settings.heartbeat = v;
     }
     if let Some(v) = req.triggers {
-        // Validate MIDI trigger channel 1-16
-        if v.midi.channel == 0 || v.midi.channel > 16 {
-            return Json(json!({ "success": false, "error": "MIDI trigger channel must be 1-16" }));
-        }
-        if v.midi.note > 127 {
-            return Json(json!({ "success": false, "error": "MIDI trigger note must be 0-127" }));
-        }
-        // Validate OSC trigger port if enabled
-        if v.osc.enabled {
-            if let Err(msg) = validate_port(v.osc.listen_port) {
-                return Json(json!({ "success": false, "error": format!("OSC trigger port: {}", msg) }));
-            }
-        }
         settings.triggers = v;
     }
+    if let Some(error) = validate_failover(&settings).into_iter().next() {
+        return Json(json!({ "success": false, "error": error }));
+    }
 
     let warnings = validate_failover_warnings(&settings);
 
@@ -438,7 +473,7 @@ pub async fn set_failover(
     *state.inner.active_preset.write().await = None;
 
     // Persist
-    if let Err(e) = persist_config(&state).await {
+    if let Err(e) = persist_if(&state, persist).await {
         return Json(json!({
             "success": false,
             "error": format!("Settings applied in memory but config save failed: {}", e)
@@ -451,6 +486,7 @@ pub async fn set_failover(
         "success": true,
         "failover": settings,
         "warnings": warnings,
+        "persisted": persist,
     }))
 }
 
@@ -474,8 +510,10 @@ pub async fn list_presets(State(_state): State<AppState>) -> Json<Value> {
 /// POST /api/settings/preset — apply a named preset.
 pub async fn apply_preset(
     State(state): State<AppState>,
+    Query(query): Query<PersistQuery>,
     Json(req): Json<ApplyPresetRequest>,
 ) -> Json<Value> {
+    let persist = query.persist_or(true);
     let presets = builtin_presets();
     let preset = match presets.iter().find(|p| p.id == req.preset) {
         Some(p) => p.clone(),
@@ -503,7 +541,7 @@ pub async fn apply_preset(
     *state.inner.active_preset.write().await = Some(preset.id.to_string());
 
     // Persist
-    if let Err(e) = persist_config(&state).await {
+    if let Err(e) = persist_if(&state, persist).await {
         return Json(json!({
             "success": false,
             "error": format!("Preset applied in memory but config save failed: {}", e)
@@ -517,5 +555,6 @@ pub async fn apply_preset(
         "preset": preset.id,
         "name": preset.name,
         "failover": preset.failover,
+        "persisted": persist,
     }))
 }
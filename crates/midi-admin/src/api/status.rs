@@ -1,10 +1,15 @@
+use std::collections::VecDeque;
+
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use tracing::info;
 
-use crate::state::{AppState, ClientInfo};
+use midi_protocol::client_command::CommandAck;
+
+use crate::api::commands;
+use crate::state::{AppState, ClientInfo, LossTrend};
 
 pub async fn get_status(State(state): State<AppState>) -> Json<Value> {
     let sys = state.inner.system_status.read().await;
@@ -21,10 +26,12 @@ pub async fn get_status(State(state): State<AppState>) -> Json<Value> {
         "build_time": midi_protocol::BUILD_TIME,
         "uptime_seconds": state.uptime_secs(),
         "health_score": sys.health_score,
+        "health_breakdown": sys.health,
         "cpu_percent": sys.cpu_percent,
         "cpu_temp_c": sys.cpu_temp_c,
         "memory_used_mb": sys.memory_used_mb,
         "active_host": failover.active_host,
+        "failover_count": failover.failover_count,
         "connected_clients": clients.len(),
         "midi_messages_per_sec": midi.messages_in_per_sec,
         "active_alerts": alerts.len(),
@@ -36,9 +43,60 @@ pub async fn get_hosts(State(state): State<AppState>) -> Json<Value> {
     Json(json!({ "hosts": *hosts }))
 }
 
+/// GET /api/clients — connected clients, plus a loss table ranking them
+/// by the packet loss they report, worst first.
 pub async fn get_clients(State(state): State<AppState>) -> Json<Value> {
     let clients = state.inner.clients.read().await;
-    Json(json!({ "clients": *clients }))
+    let mut loss: Vec<&ClientInfo> = clients.iter().filter(|c| !c.manual).collect();
+    loss.sort_by(|a, b| b.packet_loss_percent.total_cmp(&a.packet_loss_percent));
+    let loss: Vec<Value> = loss
+        .into_iter()
+        .map(|c| {
+            json!({
+                "id": c.id,
+                "hostname": c.hostname,
+                "packet_loss_percent": c.packet_loss_percent,
+                "max_sequence_gap": c.max_sequence_gap,
+                "packets_out_of_order": c.packets_out_of_order,
+                "loss_trend": c.loss_trend,
+            })
+        })
+        .collect();
+    Json(json!({ "clients": *clients, "loss": loss }))
+}
+
+/// Heartbeats kept per client for the loss trend (1 minute at the 5s heartbeat)
+const LOSS_TREND_WINDOW: usize = 12;
+
+/// Change in mean loss (percentage points) between the older and newer half
+/// of the window that counts as a trend rather than noise
+const LOSS_TREND_THRESHOLD: f32 = 0.5;
+
+/// Add a heartbeat's loss report to the client's window and re-classify its trend.
+fn record_loss_sample(client: &mut ClientInfo, loss_percent: f32) {
+    if client.loss_history.len() == LOSS_TREND_WINDOW {
+        client.loss_history.pop_front();
+    }
+    client.loss_history.push_back(loss_percent);
+    client.loss_trend = classify_loss_trend(client.loss_history.make_contiguous());
+}
+
+/// Compare the mean loss of the newer half of `samples` (oldest first) with
+/// the older half. Too few samples to tell count as stable.
+pub fn classify_loss_trend(samples: &[f32]) -> LossTrend {
+    if samples.len() < 4 {
+        return LossTrend::Stable;
+    }
+    let (older, newer) = samples.split_at(samples.len() / 2);
+    let mean = |s: &[f32]| s.iter().sum::<f32>() / s.len() as f32;
+    let delta = mean(newer) - mean(older);
+    if delta > LOSS_TREND_THRESHOLD {
+        LossTrend::Degrading
+    } else if delta < -LOSS_TREND_THRESHOLD {
+        LossTrend::Improving
+    } else {
+        LossTrend::Stable
+    }
 }
 
 // ── Fleet management endpoints ──
@@ -60,6 +118,10 @@ pub struct RegisterClientBody {
     pub connection_state: String,
     #[serde(default)]
     pub git_hash: String,
+    #[serde(default)]
+    pub max_sequence_gap: u64,
+    #[serde(default)]
+    pub packets_out_of_order: u64,
 }
 
 /// POST /api/clients/register — client self-registers on startup
@@ -79,6 +141,8 @@ pub async fn register_client(
         existing.device_ready = body.device_ready;
         existing.connection_state = body.connection_state;
         existing.git_hash = body.git_hash;
+        existing.max_sequence_gap = body.max_sequence_gap;
+        existing.packets_out_of_order = body.packets_out_of_order;
         existing.last_heartbeat_ms = now_ms;
     } else {
         // New ID — check if a stale entry from the same machine exists.
@@ -113,9 +177,14 @@ pub async fn register_client(
             device_ready: body.device_ready,
             midi_rate_in: 0.0,
             midi_rate_out: 0.0,
+            device_send_latency_us: 0,
             connection_state: body.connection_state,
             git_hash: body.git_hash,
             manual: false,
+            max_sequence_gap: body.max_sequence_gap,
+            packets_out_of_order: body.packets_out_of_order,
+            loss_history: VecDeque::new(),
+            loss_trend: LossTrend::Stable,
         });
     }
 
@@ -133,6 +202,8 @@ pub struct ClientHeartbeatBody {
     #[serde(default)]
     pub midi_rate_out: f32,
     #[serde(default)]
+    pub device_send_latency_us: u32,
+    #[serde(default)]
     pub device_ready: bool,
     #[serde(default)]
     pub device_name: String,
@@ -140,6 +211,15 @@ pub struct ClientHeartbeatBody {
     pub connection_state: String,
     #[serde(default)]
     pub git_hash: String,
+    /// Largest sequence gap the client has seen (cumulative max)
+    #[serde(default)]
+    pub max_sequence_gap: u64,
+    /// Packets the client received out of order (cumulative)
+    #[serde(default)]
+    pub packets_out_of_order: u64,
+    /// Results of commands handed out with earlier heartbeat responses
+    #[serde(default)]
+    pub command_acks: Vec<CommandAck>,
 }
 
 /// POST /api/clients/:id/heartbeat — periodic health update from client
@@ -155,8 +235,12 @@ pub async fn client_heartbeat(
         client.last_heartbeat_ms = now_ms;
         client.latency_ms = body.latency_ms;
         client.packet_loss_percent = body.packet_loss_percent;
+        client.max_sequence_gap = body.max_sequence_gap;
+        client.packets_out_of_order = body.packets_out_of_order;
+        record_loss_sample(client, body.packet_loss_percent);
         client.midi_rate_in = body.midi_rate_in;
         client.midi_rate_out = body.midi_rate_out;
+        client.device_send_latency_us = body.device_send_latency_us;
         client.device_ready = body.device_ready;
         if !body.device_name.is_empty() {
             client.device_name = body.device_name;
@@ -167,6 +251,10 @@ pub async fn client_heartbeat(
         if !body.git_hash.is_empty() {
             client.git_hash = body.git_hash;
         }
+        drop(clients);
+
+        commands::record_acks(&state, id, body.command_acks).await;
+        let pending = commands::take_pending(&state, id).await;
 
         // Include focus command based on designated_focus
         let designated = *state.inner.designated_focus.read().await;
@@ -176,7 +264,12 @@ pub async fn client_heartbeat(
             None => None,
         };
 
-        Json(json!({ "success": true, "focus_command": focus_cmd, "host_git_hash": midi_protocol::GIT_HASH }))
+        Json(json!({
+            "success": true,
+            "focus_command": focus_cmd,
+            "commands": pending,
+            "host_git_hash": midi_protocol::GIT_HASH,
+        }))
     } else {
         Json(json!({ "success": false, "error": "Client not registered" }))
     }
@@ -281,9 +374,14 @@ pub async fn add_client_manual(
         device_ready: false,
         midi_rate_in: 0.0,
         midi_rate_out: 0.0,
+        device_send_latency_us: 0,
         connection_state: "manual".to_string(),
         git_hash: String::new(),
         manual: true,
+        max_sequence_gap: 0,
+        packets_out_of_order: 0,
+        loss_history: VecDeque::new(),
+        loss_trend: LossTrend::Stable,
     });
 
     Json(json!({ "success": true, "id": id }))
@@ -311,3 +409,49 @@ fn epoch_ms() -> u64 {
         .unwrap_or_default()
         .as_millis() as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loss_trend_follows_the_newer_half_of_the_window() {
+        assert_eq!(classify_loss_trend(&[0.0, 0.1, 0.0, 0.2, 1.5, 2.0, 2.5, 3.0]), LossTrend::Degrading);
+        assert_eq!(classify_loss_trend(&[4.0, 3.5, 3.0, 2.0, 0.5, 0.2, 0.0, 0.0]), LossTrend::Improving);
+        assert_eq!(classify_loss_trend(&[1.0, 1.2, 0.9, 1.1, 1.0, 1.3, 0.8, 1.1]), LossTrend::Stable);
+    }
+
+    #[test]
+    fn short_loss_history_is_stable() {
+        assert_eq!(classify_loss_trend(&[]), LossTrend::Stable);
+        assert_eq!(classify_loss_trend(&[0.0, 10.0, 20.0]), LossTrend::Stable);
+    }
+
+    #[tokio::test]
+    async fn heartbeats_build_the_loss_trend() {
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        let body: RegisterClientBody = serde_json::from_value(json!({
+            "id": 7,
+            "ip": "10.0.0.7",
+            "hostname": "stage-left",
+        }))
+        .unwrap();
+        let _ = register_client(State(state.clone()), Json(body)).await;
+
+        for loss in [0.0, 0.0, 0.0, 2.0, 3.0, 4.0] {
+            let heartbeat: ClientHeartbeatBody = serde_json::from_value(json!({
+                "packet_loss_percent": loss,
+                "max_sequence_gap": 5,
+                "packets_out_of_order": 2,
+            }))
+            .unwrap();
+            let _ = client_heartbeat(State(state.clone()), Path(7), Json(heartbeat)).await;
+        }
+
+        let Json(resp) = get_clients(State(state)).await;
+        assert_eq!(resp["clients"][0]["loss_trend"], "degrading");
+        assert_eq!(resp["loss"][0]["id"], 7);
+        assert_eq!(resp["loss"][0]["max_sequence_gap"], 5);
+        assert_eq!(resp["loss"][0]["packets_out_of_order"], 2);
+    }
+}
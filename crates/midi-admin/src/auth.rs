@@ -2,7 +2,9 @@
 ///
 /// When an API token is configured (via --api-token), all /api/* requests
 /// must include `Authorization: Bearer <token>`. Static files and WebSocket
-/// upgrades are exempt so the dashboard remains accessible.
+/// upgrades are exempt so the dashboard remains accessible. With
+/// --public-metrics, the Prometheus export is exempt too, so a scraper
+/// doesn't need the token.
 
 use axum::{
     body::Body,
@@ -15,9 +17,22 @@ use axum::{
 #[derive(Clone)]
 pub struct ApiToken(pub Option<String>);
 
+/// Whether `/api/metrics/export?format=prometheus` skips authentication.
+#[derive(Clone)]
+pub struct PublicMetrics(pub bool);
+
+fn is_prometheus_export(req: &Request<Body>) -> bool {
+    req.uri().path() == "/api/metrics/export"
+        && req
+            .uri()
+            .query()
+            .is_some_and(|q| q.split('&').any(|pair| pair == "format=prometheus"))
+}
+
 /// Axum middleware: reject /api/* requests without a valid bearer token.
 pub async fn require_auth(
     token: axum::extract::Extension<ApiToken>,
+    axum::extract::Extension(PublicMetrics(public_metrics)): axum::extract::Extension<PublicMetrics>,
     req: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
@@ -32,7 +47,10 @@ pub async fn require_auth(
     let path = req.uri().path();
 
     // Static files and WebSocket upgrades are exempt
-    if !path.starts_with("/api/") {
+    if path != "/api" && !path.starts_with("/api/") {
+        return Ok(next.run(req).await);
+    }
+    if public_metrics && is_prometheus_export(&req) {
         return Ok(next.run(req).await);
     }
 
@@ -2,18 +2,27 @@
 ///
 /// Periodically samples system metrics (CPU, memory, temperature, disk)
 /// using the `sysinfo` crate, updates shared state, records to the
-/// metrics store, and evaluates alert thresholds.
+/// metrics store, and evaluates alert thresholds. On a Raspberry Pi the
+/// firmware throttling flags are read with `vcgencmd get_throttled`.
 ///
 /// Runs on a 1-second interval, spawned as a tokio task from main.
 
+use std::net::SocketAddr;
 use std::time::Duration;
 
+use midi_protocol::multicast;
+use midi_protocol::packets::{FocusAction, FocusPacket};
 use sysinfo::{Disks, System};
-use tracing::debug;
+use tokio::net::UdpSocket;
+use tracing::{debug, info, warn};
 
 use crate::alerting::EvalMetrics;
+use crate::health::{HealthInputs, HealthScore};
 use crate::metrics_store::MetricsSample;
-use crate::state::{AppState, MidiDeviceInfo};
+use crate::state::{AppState, ClientInfo, MidiDeviceInfo, ThrottleStatus};
+
+/// Seconds between `vcgencmd get_throttled` reads
+const THROTTLE_POLL_SECS: u64 = 5;
 
 /// Run the metrics collection loop. This function never returns under
 /// normal operation — it should be spawned as a background tokio task.
@@ -27,9 +36,13 @@ pub async fn run(state: AppState) {
     sys.refresh_all();
 
     let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut tick: u64 = 0;
+    // Cleared once vcgencmd turns out to be missing (not a Pi)
+    let mut vcgencmd_available = cfg!(target_os = "linux");
 
     loop {
         interval.tick().await;
+        tick += 1;
 
         // --- Refresh system info ---
         sys.refresh_cpu_usage();
@@ -55,6 +68,19 @@ pub async fn run(state: AppState) {
                 .unwrap_or(0.0)
         };
 
+        // --- Pi throttling flags ---
+        let throttled = if vcgencmd_available && tick % THROTTLE_POLL_SECS == 1 {
+            match read_throttled().await {
+                Ok(status) => status,
+                Err(_) => {
+                    vcgencmd_available = false;
+                    None
+                }
+            }
+        } else {
+            state.inner.system_status.read().await.throttled
+        };
+
         // --- Memory ---
         let memory_total_mb = sys.total_memory() / (1024 * 1024);
         let memory_used_mb = sys.used_memory() / (1024 * 1024);
@@ -96,9 +122,9 @@ pub async fn run(state: AppState) {
             let devices = state.inner.devices.read().await;
             devices.iter().any(|d| d.connected)
         };
-        let standby_host_healthy = {
+        let (standby_host_healthy, last_failover) = {
             let failover = state.inner.failover_state.read().await;
-            failover.standby_healthy
+            (failover.standby_healthy, failover.last_failover.as_ref().map(|e| e.timestamp))
         };
 
         // --- Timestamp for this tick ---
@@ -108,51 +134,17 @@ pub async fn run(state: AppState) {
             .as_secs();
 
         // --- Compute health score ---
-        let health_score = {
-            let mut score: i32 = 100;
-
-            // Primary: latency p95
-            if avg_latency_p95 > 20.0 {
-                score -= 30;
-            } else if avg_latency_p95 > 10.0 {
-                score -= 15;
-            } else if avg_latency_p95 > 5.0 {
-                score -= 5;
-            }
-
-            // Primary: packet loss
-            if avg_packet_loss > 5.0 {
-                score -= 30;
-            } else if avg_packet_loss > 1.0 {
-                score -= 15;
-            } else if avg_packet_loss > 0.5 {
-                score -= 5;
-            }
-
-            // Primary: no clients connected
-            if client_count == 0 {
-                score -= 5;
-            }
-
-            // Secondary: CPU temperature
-            if cpu_temp_c > 80.0 {
-                score -= 15;
-            } else if cpu_temp_c > 70.0 {
-                score -= 5;
-            }
-
-            // Secondary: MIDI device disconnected
-            if !midi_device_connected {
-                score -= 20;
-            }
-
-            // Secondary: disk space low
-            if disk_free_mb < 100 {
-                score -= 5;
-            }
-
-            score.clamp(0, 100) as u8
-        };
+        let health = HealthScore::compute(&HealthInputs {
+            latency_p95_ms: avg_latency_p95,
+            packet_loss_percent: avg_packet_loss,
+            midi_device_connected,
+            cpu_temp_c,
+            cpu_percent,
+            client_count,
+            expected_clients: state.inner.alert_manager.get_config().expected_clients,
+            secs_since_failover: last_failover.map(|t| now.saturating_sub(t)),
+            disk_free_mb,
+        });
 
         // --- Update SystemStatus ---
         {
@@ -162,7 +154,9 @@ pub async fn run(state: AppState) {
             status.memory_used_mb = memory_used_mb;
             status.memory_total_mb = memory_total_mb;
             status.disk_free_mb = disk_free_mb;
-            status.health_score = health_score;
+            status.throttled = throttled;
+            status.health_score = health.score;
+            status.health = health;
         }
 
         let sample = MetricsSample {
@@ -196,6 +190,8 @@ pub async fn run(state: AppState) {
         // --- Evaluate alert thresholds ---
         let eval = EvalMetrics {
             cpu_temp_c,
+            midi_messages_per_sec: midi_msgs_per_sec,
+            client_count,
             packet_loss_percent: avg_packet_loss,
             latency_p95_ms: avg_latency_p95,
             midi_device_connected,
@@ -252,21 +248,118 @@ pub async fn run(state: AppState) {
         }
 
         // --- Clean up stale clients (no heartbeat for 30s) ---
-        {
-            let now_ms = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as u64;
-            let mut clients = state.inner.clients.write().await;
-            let before = clients.len();
-            clients.retain(|c| c.manual || now_ms.saturating_sub(c.last_heartbeat_ms) < 30_000);
-            if clients.len() < before {
-                debug!("Removed {} stale client(s)", before - clients.len());
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        expire_stale_clients(&state, now_ms).await;
+    }
+}
+
+/// Clients silent this long are dropped
+const CLIENT_TIMEOUT_MS: u64 = 30_000;
+
+/// Drop clients whose heartbeats stopped. Each one is released from
+/// focus with a Release on the control group, as if it had sent it
+/// itself: the host drops focus if that client holds it (so it isn't stuck
+/// on a client that crashed), and the control sniffer updates the admin's
+/// mirror from the same packet. A focus designation is cleared here.
+pub async fn expire_stale_clients(state: &AppState, now_ms: u64) {
+    let expired: Vec<u32> = {
+        let mut clients = state.inner.clients.write().await;
+        let (stale, live): (Vec<ClientInfo>, Vec<ClientInfo>) = std::mem::take(&mut *clients)
+            .into_iter()
+            .partition(|c| !c.manual && now_ms.saturating_sub(c.last_heartbeat_ms) >= CLIENT_TIMEOUT_MS);
+        *clients = live;
+        stale.into_iter().map(|c| c.id).collect()
+    };
+    if expired.is_empty() {
+        return;
+    }
+    debug!("Removed {} stale client(s)", expired.len());
+
+    if let Some(holder) = &state.inner.focus_state.read().await.holder {
+        if expired.contains(&holder.client_id) {
+            info!(client_id = holder.client_id, "Releasing focus: holder's heartbeat timed out");
+        }
+    }
+    let (group, port) = match state.inner.network_config.read().await.as_ref() {
+        Some(net) => (net.control_group.clone(), net.control_port),
+        None => (
+            midi_protocol::DEFAULT_CONTROL_GROUP.to_string(),
+            midi_protocol::DEFAULT_CONTROL_PORT,
+        ),
+    };
+    if let Err(e) = send_focus_releases(&group, port, &expired).await {
+        warn!(error = %e, "Failed to release focus for timed-out clients");
+    }
+
+    let mut designated = state.inner.designated_focus.write().await;
+    if let Some(id) = designated.take_if(|id| expired.contains(id)) {
+        info!(client_id = id, "Focus designation cleared: client's heartbeat timed out");
+    }
+}
+
+async fn send_focus_releases(group: &str, port: u16, client_ids: &[u32]) -> anyhow::Result<()> {
+    let group = multicast::parse_group(group)?;
+    let socket = UdpSocket::bind(multicast::bind_addr(group, 0)).await?;
+    if group.is_ipv4() {
+        socket.set_multicast_ttl_v4(1)?;
+    }
+    let timestamp_us = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64;
+    let mut buf = [0u8; FocusPacket::SIZE];
+    for &client_id in client_ids {
+        FocusPacket { action: FocusAction::Release, client_id, sequence: 0, timestamp_us }.serialize(&mut buf);
+        socket.send_to(&buf, SocketAddr::new(group, port)).await?;
+    }
+    Ok(())
+}
+
+/// Metrics DB upkeep: downsample old rows every hour, vacuum once a day.
+/// Never returns; spawned from main next to `run`.
+pub async fn run_retention(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(3600));
+    let mut runs: u64 = 0;
+    loop {
+        interval.tick().await;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        match state.inner.metrics_store.apply_retention(now) {
+            Ok(stats) => debug!(
+                collapsed = stats.rows_collapsed,
+                expired = stats.aggregates_expired,
+                "metrics retention applied"
+            ),
+            Err(e) => tracing::warn!(error = %e, "metrics retention failed"),
+        }
+        if runs.is_multiple_of(24) {
+            if let Err(e) = state.inner.metrics_store.vacuum() {
+                tracing::warn!(error = %e, "metrics vacuum failed");
             }
         }
+        runs += 1;
     }
 }
 
+/// Run `vcgencmd get_throttled`. Err when the tool can't be run at all;
+/// Ok(None) when its output isn't understood.
+async fn read_throttled() -> std::io::Result<Option<ThrottleStatus>> {
+    let output = tokio::process::Command::new("vcgencmd").arg("get_throttled").output().await?;
+    Ok(parse_throttled(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `throttled=0x50005` into its flags.
+fn parse_throttled(output: &str) -> Option<ThrottleStatus> {
+    let hex = output.trim().strip_prefix("throttled=")?;
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    u32::from_str_radix(hex, 16).ok().map(ThrottleStatus::from_bits)
+}
+
 /// Scan for MIDI devices via /proc/asound (Linux) or return empty (other OS).
 #[cfg(target_os = "linux")]
 fn scan_midi_devices() -> Vec<MidiDeviceInfo> {
@@ -363,3 +456,82 @@ fn scan_midi_devices() -> Vec<MidiDeviceInfo> {
 fn scan_midi_devices() -> Vec<MidiDeviceInfo> {
     Vec::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttled_output_is_decoded() {
+        let status = parse_throttled("throttled=0x50005\n").unwrap();
+        assert_eq!(status.raw, 0x50005);
+        assert!(status.under_voltage && status.throttled);
+        assert!(!status.freq_capped && !status.soft_temp_limit);
+        assert!(status.under_voltage_occurred && status.throttled_occurred);
+        assert!(!status.soft_temp_limit_occurred);
+
+        assert_eq!(parse_throttled("throttled=0x0"), Some(ThrottleStatus::default()));
+        assert_eq!(parse_throttled("error=1"), None);
+    }
+
+    fn client(id: u32, last_heartbeat_ms: u64) -> ClientInfo {
+        ClientInfo {
+            id,
+            ip: format!("10.0.0.{}", id),
+            hostname: String::new(),
+            os: "linux".to_string(),
+            connected_since: 0,
+            last_heartbeat_ms,
+            latency_ms: 0.0,
+            packet_loss_percent: 0.0,
+            device_name: String::new(),
+            device_ready: false,
+            midi_rate_in: 0.0,
+            midi_rate_out: 0.0,
+            device_send_latency_us: 0,
+            connection_state: String::new(),
+            git_hash: String::new(),
+            manual: false,
+            max_sequence_gap: 0,
+            packets_out_of_order: 0,
+            loss_history: Default::default(),
+            loss_trend: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn holder_timeout_releases_focus() {
+        let group: std::net::Ipv4Addr = "239.69.83.247".parse().unwrap();
+        let listener = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        listener.join_multicast_v4(&group, &std::net::Ipv4Addr::UNSPECIFIED).unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let state = AppState::new("/nonexistent/midinet.toml".to_string());
+        *state.inner.network_config.write().await = Some(crate::api::config::NetworkConfig {
+            multicast_group: "239.69.83.1".to_string(),
+            data_port: 5004,
+            control_group: group.to_string(),
+            control_port: port,
+            interface: String::new(),
+            psk: String::new(),
+        });
+        state.inner.clients.write().await.extend([client(7, 1_000), client(8, 40_000)]);
+        *state.inner.designated_focus.write().await = Some(7);
+
+        // Still inside the timeout: nothing changes
+        expire_stale_clients(&state, 30_999).await;
+        assert_eq!(state.inner.clients.read().await.len(), 2);
+
+        expire_stale_clients(&state, 45_000).await;
+        let clients: Vec<u32> = state.inner.clients.read().await.iter().map(|c| c.id).collect();
+        assert_eq!(clients, [8]);
+        assert_eq!(*state.inner.designated_focus.read().await, None);
+
+        // The host hears a Release from the timed-out client
+        let mut buf = [0u8; 64];
+        let len = listener.recv(&mut buf).unwrap();
+        let release = FocusPacket::deserialize(&buf[..len]).unwrap();
+        assert_eq!((release.action, release.client_id), (FocusAction::Release, 7));
+    }
+}
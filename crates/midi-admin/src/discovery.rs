@@ -117,6 +117,9 @@ async fn handle_resolved(state: &AppState, info: &mdns_sd::ServiceInfo) {
     // Upsert into hosts list
     let mut hosts = state.inner.hosts.write().await;
     if let Some(existing) = hosts.iter_mut().find(|h| h.id == host_id) {
+        if existing.role != host.role {
+            state.inner.shadow_promotions.write().await.remove(&host_id);
+        }
         existing.name = host.name;
         existing.role = host.role;
         existing.ip = host.ip;
@@ -131,5 +134,12 @@ async fn handle_resolved(state: &AppState, info: &mdns_sd::ServiceInfo) {
 async fn handle_removed(state: &AppState, fullname: &str) {
     info!(name = %fullname, "MIDInet host removed from network");
     let mut hosts = state.inner.hosts.write().await;
-    hosts.retain(|h| h.name != fullname);
+    let mut promotions = state.inner.shadow_promotions.write().await;
+    hosts.retain(|h| {
+        let keep = h.name != fullname;
+        if !keep {
+            promotions.remove(&h.id);
+        }
+        keep
+    });
 }
@@ -0,0 +1,273 @@
+/// Email alert notifications over SMTP.
+///
+/// Alerts at or above the configured severity (critical by default) are
+/// collected for `coalesce_secs` after the first one arrives and then sent
+/// as a single email, so a burst of failures is one message rather than
+/// one per alert.
+///
+/// The SMTP client is deliberately small: plain SMTP with no TLS and no
+/// authentication, aimed at a venue's local relay that accepts mail from
+/// this host (port 25 on a trusted network). Providers that require AUTH
+/// need such a relay in front of them.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::alerting::{Alert, AlertSeverity};
+
+/// Give up on an SMTP exchange that stalls this long
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    pub from: String,
+    pub recipients: Vec<String>,
+    /// Least severe alert that is emailed
+    #[serde(default = "default_min_severity")]
+    pub min_severity: AlertSeverity,
+    /// How long to collect alerts into one email after the first
+    #[serde(default = "default_coalesce_secs")]
+    pub coalesce_secs: u64,
+}
+
+fn default_smtp_port() -> u16 { 25 }
+fn default_min_severity() -> AlertSeverity { AlertSeverity::Critical }
+fn default_coalesce_secs() -> u64 { 30 }
+
+/// Collects triggered alerts into batches and mails each batch once its
+/// coalescing window closes.
+#[derive(Default)]
+pub struct EmailBatcher {
+    pending: Arc<Mutex<Vec<Alert>>>,
+}
+
+impl EmailBatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a newly triggered alert. The first alert of a batch starts the
+    /// window; everything queued before it closes goes in the same email.
+    pub fn push(&self, config: &EmailConfig, alert: &Alert) {
+        if !config.enabled || config.recipients.is_empty() || alert.severity < config.min_severity {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        pending.push(alert.clone());
+        if pending.len() > 1 {
+            return;
+        }
+
+        let pending = self.pending.clone();
+        let config = config.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(config.coalesce_secs)).await;
+            let batch = std::mem::take(&mut *pending.lock().unwrap());
+            let (subject, body) = compose(&batch);
+            match send_mail(&config, &subject, &body).await {
+                Ok(()) => tracing::info!(alerts = batch.len(), host = %config.smtp_host, "alert email sent"),
+                Err(e) => tracing::warn!(alerts = batch.len(), host = %config.smtp_host, error = %e, "alert email failed"),
+            }
+        });
+    }
+}
+
+/// Subject and plain-text body for a batch of alerts.
+fn compose(batch: &[Alert]) -> (String, String) {
+    let subject = match batch {
+        [alert] => format!("[MIDInet] {:?}: {}", alert.severity, alert.title),
+        _ => format!("[MIDInet] {} alerts", batch.len()),
+    };
+    let body = batch
+        .iter()
+        .map(|a| format!("{:?}: {}\n  {}\n  id {}, at {} (unix)\n", a.severity, a.title, a.message, a.id, a.triggered_at))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (subject, body)
+}
+
+/// Deliver one message over SMTP.
+pub async fn send_mail(config: &EmailConfig, subject: &str, body: &str) -> anyhow::Result<()> {
+    tokio::time::timeout(SMTP_TIMEOUT, smtp_session(config, subject, body))
+        .await
+        .map_err(|_| anyhow::anyhow!("SMTP timed out"))?
+}
+
+async fn smtp_session(config: &EmailConfig, subject: &str, body: &str) -> anyhow::Result<()> {
+    let stream = TcpStream::connect((config.smtp_host.as_str(), config.smtp_port)).await?;
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+
+    expect(&mut read, 220).await?;
+    command(&mut write, &mut read, "EHLO midinet", 250).await?;
+    command(&mut write, &mut read, &format!("MAIL FROM:<{}>", config.from), 250).await?;
+    for rcpt in &config.recipients {
+        command(&mut write, &mut read, &format!("RCPT TO:<{}>", rcpt), 250).await?;
+    }
+    command(&mut write, &mut read, "DATA", 354).await?;
+
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        config.from,
+        config.recipients.join(", "),
+        subject,
+    );
+    for line in body.lines() {
+        // Dot-stuffing: a leading '.' would otherwise end the message early
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    write.write_all(message.as_bytes()).await?;
+    expect(&mut read, 250).await?;
+
+    let _ = command(&mut write, &mut read, "QUIT", 221).await;
+    Ok(())
+}
+
+async fn command<W, R>(write: &mut W, read: &mut R, line: &str, code: u16) -> anyhow::Result<()>
+where
+    W: AsyncWriteExt + Unpin,
+    R: AsyncBufReadExt + Unpin,
+{
+    write.write_all(format!("{}\r\n", line).as_bytes()).await?;
+    expect(read, code).await
+}
+
+/// Read one (possibly multi-line) SMTP reply and check its code.
+async fn expect<R: AsyncBufReadExt + Unpin>(read: &mut R, code: u16) -> anyhow::Result<()> {
+    loop {
+        let mut line = String::new();
+        if read.read_line(&mut line).await? == 0 {
+            anyhow::bail!("SMTP server closed the connection");
+        }
+        let got: u16 = line.get(..3).and_then(|c| c.parse().ok()).unwrap_or(0);
+        if got != code {
+            anyhow::bail!("SMTP server replied {:?}, expected {}", line.trim_end(), code);
+        }
+        // "250-..." continues, "250 ..." is the last line
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{AlertConfig, AlertManager, EvalMetrics};
+
+    /// A bare-bones SMTP server that accepts every command and forwards
+    /// each message body it receives. Returns its port.
+    async fn mock_smtp() -> (u16, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 mock ESMTP\r\n").await.unwrap();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let reply: &[u8] = match line.as_str() {
+                            l if l.starts_with("EHLO") => b"250-mock\r\n250 8BITMIME\r\n",
+                            "DATA" => {
+                                write.write_all(b"354 go ahead\r\n").await.unwrap();
+                                let mut message = String::new();
+                                while let Ok(Some(line)) = lines.next_line().await {
+                                    if line == "." {
+                                        break;
+                                    }
+                                    message.push_str(&line);
+                                    message.push('\n');
+                                }
+                                let _ = tx.send(message);
+                                b"250 queued\r\n"
+                            }
+                            "QUIT" => {
+                                let _ = write.write_all(b"221 bye\r\n").await;
+                                break;
+                            }
+                            _ => b"250 ok\r\n",
+                        };
+                        write.write_all(reply).await.unwrap();
+                    }
+                });
+            }
+        });
+        (port, rx)
+    }
+
+    #[tokio::test]
+    async fn burst_of_critical_alerts_sends_one_email() {
+        let (port, mut rx) = mock_smtp().await;
+        let manager = AlertManager::new();
+        manager.update_config(AlertConfig {
+            email: Some(EmailConfig {
+                enabled: true,
+                smtp_host: "127.0.0.1".to_string(),
+                smtp_port: port,
+                from: "midinet@venue.local".to_string(),
+                recipients: vec!["ops@venue.local".to_string()],
+                min_severity: AlertSeverity::Critical,
+                coalesce_secs: 1,
+            }),
+            ..AlertConfig::default()
+        });
+        let metrics = |cpu_temp_c: f32, midi_device_connected: bool| EvalMetrics {
+            cpu_temp_c,
+            midi_messages_per_sec: 0.0,
+            client_count: 0,
+            packet_loss_percent: 0.0,
+            latency_p95_ms: 0.0,
+            midi_device_connected,
+            standby_host_healthy: false,
+            disk_free_mb: 10_000,
+        };
+
+        // Device unplugged (critical) and standby down (warning, below the
+        // floor), then the CPU overheats (critical) inside the window
+        manager.evaluate(&metrics(50.0, false));
+        manager.evaluate(&metrics(85.0, false));
+
+        let message = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(message.contains("Subject: [MIDInet] 2 alerts"));
+        assert!(message.contains("midi device alert"));
+        assert!(message.contains("cpu temp alert"));
+        assert!(!message.contains("standby host"));
+
+        // Nothing else goes out for the same burst
+        assert!(tokio::time::timeout(Duration::from_millis(1500), rx.recv()).await.is_err());
+    }
+
+    #[test]
+    fn credentials_in_an_old_config_are_dropped() {
+        let config: EmailConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "smtp_host": "relay.venue.local",
+            "username": "ops",
+            "password": "secret",
+            "from": "midinet@venue.local",
+            "recipients": ["ops@venue.local"],
+        }))
+        .unwrap();
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert!(json.get("username").is_none());
+        assert!(json.get("password").is_none());
+    }
+}
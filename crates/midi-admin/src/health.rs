@@ -0,0 +1,212 @@
+/// System health score with a per-component breakdown.
+///
+/// The score starts at 100 and each component takes off up to its weight:
+///
+///   - latency           30  (client p95 > 5 / 10 / 20 ms)
+///   - packet_loss       30  (average client loss > 0.5 / 1 / 5 %)
+///   - midi_device       20  (no MIDI device connected)
+///   - cpu_temp          15  (> 70 / 80 °C)
+///   - clients           10  (share of `expected_clients` missing)
+///   - cpu               10  (> 75 / 90 %)
+///   - failover_recency  10  (a failover within the last 10 min / 1 min)
+///   - disk               5  (< 100 MB free)
+///
+/// With no `expected_clients` configured, `clients` only takes off 5 when
+/// nobody is connected. Computed by the collector every second;
+/// `/api/status` reports the result as `health_score` and the breakdown
+/// as `health_breakdown`.
+
+use serde::{Deserialize, Serialize};
+
+/// Everything the score is computed from, sampled by the collector.
+#[derive(Debug, Clone, Default)]
+pub struct HealthInputs {
+    pub latency_p95_ms: f32,
+    pub packet_loss_percent: f32,
+    pub midi_device_connected: bool,
+    pub cpu_temp_c: f32,
+    pub cpu_percent: f32,
+    pub client_count: u32,
+    /// Clients the deployment should have (0 = not configured)
+    pub expected_clients: u32,
+    /// Seconds since the last failover, None if there hasn't been one
+    pub secs_since_failover: Option<u64>,
+    pub disk_free_mb: u64,
+}
+
+/// One named contribution to the score.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthComponent {
+    pub name: String,
+    /// Most points this component can take off
+    pub weight: u8,
+    /// Points it takes off right now (0..=weight)
+    pub penalty: u8,
+    /// The measured value, as text, e.g. "2.3% loss"
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthScore {
+    /// 100 minus the sum of penalties, floored at 0
+    pub score: u8,
+    pub components: Vec<HealthComponent>,
+}
+
+impl Default for HealthScore {
+    fn default() -> Self {
+        Self { score: 100, components: Vec::new() }
+    }
+}
+
+impl HealthScore {
+    pub fn compute(inputs: &HealthInputs) -> Self {
+        let component = |name: &str, weight: u8, penalty: u8, detail: String| HealthComponent {
+            name: name.to_string(),
+            weight,
+            penalty: penalty.min(weight),
+            detail,
+        };
+
+        let latency = match inputs.latency_p95_ms {
+            ms if ms > 20.0 => 30,
+            ms if ms > 10.0 => 15,
+            ms if ms > 5.0 => 5,
+            _ => 0,
+        };
+        let loss = match inputs.packet_loss_percent {
+            pct if pct > 5.0 => 30,
+            pct if pct > 1.0 => 15,
+            pct if pct > 0.5 => 5,
+            _ => 0,
+        };
+        let device = if inputs.midi_device_connected { 0 } else { 20 };
+        let temp = match inputs.cpu_temp_c {
+            c if c > 80.0 => 15,
+            c if c > 70.0 => 5,
+            _ => 0,
+        };
+        let (clients, clients_detail) = if inputs.expected_clients > 0 {
+            let missing = inputs.expected_clients.saturating_sub(inputs.client_count);
+            let penalty = (10 * missing).div_ceil(inputs.expected_clients) as u8;
+            (penalty, format!("{} of {} expected", inputs.client_count, inputs.expected_clients))
+        } else {
+            let penalty = if inputs.client_count == 0 { 5 } else { 0 };
+            (penalty, format!("{} connected", inputs.client_count))
+        };
+        let cpu = match inputs.cpu_percent {
+            pct if pct > 90.0 => 10,
+            pct if pct > 75.0 => 5,
+            _ => 0,
+        };
+        let (failover, failover_detail) = match inputs.secs_since_failover {
+            Some(secs) if secs < 60 => (10, format!("{}s ago", secs)),
+            Some(secs) if secs < 600 => (5, format!("{}s ago", secs)),
+            Some(secs) => (0, format!("{}s ago", secs)),
+            None => (0, "never".to_string()),
+        };
+        let disk = if inputs.disk_free_mb < 100 { 5 } else { 0 };
+
+        let components = vec![
+            component("latency", 30, latency, format!("p95 {:.1}ms", inputs.latency_p95_ms)),
+            component("packet_loss", 30, loss, format!("{:.2}% loss", inputs.packet_loss_percent)),
+            component(
+                "midi_device",
+                20,
+                device,
+                if inputs.midi_device_connected { "connected" } else { "disconnected" }.to_string(),
+            ),
+            component("cpu_temp", 15, temp, format!("{:.1}°C", inputs.cpu_temp_c)),
+            component("clients", 10, clients, clients_detail),
+            component("cpu", 10, cpu, format!("{:.0}%", inputs.cpu_percent)),
+            component("failover_recency", 10, failover, failover_detail),
+            component("disk", 5, disk, format!("{}MB free", inputs.disk_free_mb)),
+        ];
+
+        let penalties: u32 = components.iter().map(|c| c.penalty as u32).sum();
+        Self {
+            score: 100u32.saturating_sub(penalties) as u8,
+            components,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy() -> HealthInputs {
+        HealthInputs {
+            latency_p95_ms: 2.0,
+            packet_loss_percent: 0.0,
+            midi_device_connected: true,
+            cpu_temp_c: 55.0,
+            cpu_percent: 20.0,
+            client_count: 4,
+            expected_clients: 4,
+            secs_since_failover: None,
+            disk_free_mb: 10_000,
+        }
+    }
+
+    fn penalty(score: &HealthScore, name: &str) -> u8 {
+        score.components.iter().find(|c| c.name == name).unwrap().penalty
+    }
+
+    #[test]
+    fn healthy_system_scores_100() {
+        let score = HealthScore::compute(&healthy());
+        assert_eq!(score.score, 100);
+        assert_eq!(score.components.len(), 8);
+        assert!(score.components.iter().all(|c| c.penalty == 0));
+    }
+
+    #[test]
+    fn known_degradations_add_up() {
+        // 2% loss (15), 72°C (5), 3 of 4 clients (3), failover 30s ago (10)
+        let score = HealthScore::compute(&HealthInputs {
+            packet_loss_percent: 2.0,
+            cpu_temp_c: 72.0,
+            client_count: 3,
+            secs_since_failover: Some(30),
+            ..healthy()
+        });
+        assert_eq!(score.score, 67);
+        assert_eq!(penalty(&score, "packet_loss"), 15);
+        assert_eq!(penalty(&score, "cpu_temp"), 5);
+        assert_eq!(penalty(&score, "clients"), 3);
+        assert_eq!(penalty(&score, "failover_recency"), 10);
+
+        // An old failover only costs 5, then nothing
+        let recent = HealthScore::compute(&HealthInputs { secs_since_failover: Some(300), ..healthy() });
+        assert_eq!(recent.score, 95);
+        let old = HealthScore::compute(&HealthInputs { secs_since_failover: Some(3600), ..healthy() });
+        assert_eq!(old.score, 100);
+    }
+
+    #[test]
+    fn without_expected_clients_only_an_empty_network_counts() {
+        let inputs = HealthInputs { expected_clients: 0, client_count: 1, ..healthy() };
+        assert_eq!(HealthScore::compute(&inputs).score, 100);
+
+        let inputs = HealthInputs { expected_clients: 0, client_count: 0, ..healthy() };
+        assert_eq!(HealthScore::compute(&inputs).score, 95);
+    }
+
+    #[test]
+    fn score_floors_at_zero() {
+        let score = HealthScore::compute(&HealthInputs {
+            latency_p95_ms: 50.0,
+            packet_loss_percent: 10.0,
+            midi_device_connected: false,
+            cpu_temp_c: 85.0,
+            cpu_percent: 95.0,
+            client_count: 0,
+            expected_clients: 4,
+            secs_since_failover: Some(5),
+            disk_free_mb: 10,
+        });
+        assert_eq!(score.score, 0);
+        assert!(score.components.iter().all(|c| c.penalty == c.weight));
+    }
+}
@@ -3,6 +3,8 @@ pub mod alerting;
 pub mod auth;
 pub mod collector;
 pub mod discovery;
+pub mod email;
+pub mod health;
 pub mod metrics_store;
 pub mod midi_sniffer;
 pub mod osc_listener;
@@ -26,10 +28,22 @@ struct Args {
     #[arg(long, default_value = "metrics.db")]
     metrics_db: String,
 
+    /// Days of 1-minute metrics to keep before downsampling to hourly
+    #[arg(long, default_value = "7")]
+    metrics_full_days: u64,
+
+    /// Days of hourly metric aggregates to keep
+    #[arg(long, default_value = "365")]
+    metrics_hourly_days: u64,
+
     /// API bearer token (if set, /api/* routes require Authorization header)
     #[arg(long, env = "MIDINET_API_TOKEN")]
     api_token: Option<String>,
 
+    /// Serve /api/metrics/export?format=prometheus without the API token
+    #[arg(long)]
+    public_metrics: bool,
+
     /// Path to MIDInet TOML configuration file
     #[arg(short, long, default_value = "midinet.toml")]
     config: String,
@@ -76,10 +90,27 @@ async fn main() -> anyhow::Result<()> {
         info!(path = %args.config, "No config file found, using defaults");
     }
 
+    // Load saved pipeline presets (kept next to the config file)
+    match api::pipeline::load_presets(&state).await {
+        Ok(count) => info!(count, "Loaded pipeline presets"),
+        Err(e) => tracing::warn!(error = %e, "Failed to load pipeline presets (starting with none)"),
+    }
+    match api::program_names::load_program_names(&state).await {
+        Ok(count) => info!(count, "Loaded program names"),
+        Err(e) => tracing::warn!(error = %e, "Failed to load program names (starting with none)"),
+    }
+
     // Initialize metrics database
     if let Err(e) = state.inner.metrics_store.init_db(&args.metrics_db) {
         tracing::warn!("Failed to init metrics DB: {} (continuing without persistence)", e);
     }
+    state.inner.metrics_store.set_retention(metrics_store::RetentionPolicy {
+        full_resolution_secs: args.metrics_full_days * 86400,
+        aggregate_secs: args.metrics_hourly_days * 86400,
+    });
+
+    // Spawn metrics retention (downsample hourly, vacuum daily)
+    tokio::spawn(collector::run_retention(state.clone()));
 
     // Spawn background metrics collector
     tokio::spawn(collector::run(state.clone()));
@@ -95,6 +126,7 @@ async fn main() -> anyhow::Result<()> {
             net.multicast_group.clone(),
             net.data_port,
             net.interface.clone(),
+            net.psk.clone(),
         ));
     }
 
@@ -123,7 +155,7 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Build the router with shared state and optional auth
-    let app = api::build_router(state, args.api_token.clone());
+    let app = api::build_router(state, args.api_token.clone(), args.public_metrics);
 
     if args.api_token.is_some() {
         info!("API authentication enabled (bearer token required for /api/*)");
@@ -132,7 +164,7 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&args.listen).await?;
     info!(addr = %args.listen, "Admin panel listening");
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
 
     Ok(())
 }
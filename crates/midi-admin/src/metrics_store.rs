@@ -1,11 +1,15 @@
 /// Metrics storage: in-memory ring buffer for 24h at 1s resolution,
-/// SQLite for 7-day retention at 1-minute resolution.
+/// SQLite at 1-minute resolution for a recent window (7 days by default)
+/// and hourly min/max/avg aggregates beyond that (1 year by default).
 ///
 /// Architecture:
 ///   - `record()` pushes a sample into the ring buffer
 ///   - Every 60s, the oldest 60 samples are averaged and written to SQLite
 ///   - `query_recent()` reads from the ring buffer (fast, in-memory)
-///   - `query_history()` reads from SQLite (7-day range)
+///   - `query_history()` reads 1-minute rows from SQLite
+///   - `apply_retention()` (run hourly from main) collapses 1-minute rows
+///     older than the window into `metrics_1h` and drops expired
+///     aggregates; `vacuum()` (daily) gives the freed pages back
 
 use std::collections::VecDeque;
 use std::sync::Mutex;
@@ -15,6 +19,63 @@ use serde::{Deserialize, Serialize};
 /// Maximum samples in the ring buffer: 24h × 3600s/h = 86400 samples
 const RING_BUFFER_CAPACITY: usize = 86400;
 
+/// Metrics kept in the hourly aggregates, each as `<name>_min/_max/_avg`
+const AGGREGATED_METRICS: [&str; 7] = [
+    "cpu_percent",
+    "cpu_temp_c",
+    "memory_used_mb",
+    "midi_messages_per_sec",
+    "packet_loss_percent",
+    "latency_p95_ms",
+    "client_count",
+];
+
+/// How long SQLite keeps each resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// 1-minute rows younger than this are kept as they are
+    pub full_resolution_secs: u64,
+    /// Hourly aggregates younger than this are kept
+    pub aggregate_secs: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            full_resolution_secs: 7 * 86400,
+            aggregate_secs: 365 * 86400,
+        }
+    }
+}
+
+/// What one `apply_retention()` pass did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionStats {
+    /// 1-minute rows folded into hourly aggregates and deleted
+    pub rows_collapsed: usize,
+    /// Hourly aggregates past the retention window, deleted
+    pub aggregates_expired: usize,
+}
+
+/// Min/max/avg of one metric over an hour.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct Aggregate {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+/// One hour of downsampled metrics.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HourlyAggregate {
+    /// Start of the hour (Unix seconds)
+    pub hour: u64,
+    /// 1-minute rows that went into it
+    pub samples: u32,
+    /// Keyed by metric name (see `AGGREGATED_METRICS`)
+    pub metrics: std::collections::BTreeMap<String, Aggregate>,
+}
+
 /// A single metrics sample (recorded every second)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricsSample {
@@ -62,6 +123,7 @@ pub struct MetricsStore {
     db: Mutex<Option<rusqlite::Connection>>,
     /// Counter for SQLite write interval (every 60 samples)
     sample_counter: Mutex<u32>,
+    retention: Mutex<RetentionPolicy>,
 }
 
 impl MetricsStore {
@@ -70,9 +132,14 @@ impl MetricsStore {
             ring_buffer: Mutex::new(VecDeque::with_capacity(RING_BUFFER_CAPACITY)),
             db: Mutex::new(None),
             sample_counter: Mutex::new(0),
+            retention: Mutex::new(RetentionPolicy::default()),
         }
     }
 
+    pub fn set_retention(&self, policy: RetentionPolicy) {
+        *self.retention.lock().unwrap() = policy;
+    }
+
     /// Initialize SQLite database for long-term storage
     pub fn init_db(&self, path: &str) -> Result<(), String> {
         let conn = rusqlite::Connection::open(path)
@@ -96,15 +163,23 @@ impl MetricsStore {
                 network_rx_bytes INTEGER
             );
 
-            -- Auto-cleanup: delete rows older than 7 days on each insert
-            CREATE TRIGGER IF NOT EXISTS cleanup_old_metrics
-            AFTER INSERT ON metrics_1min
-            BEGIN
-                DELETE FROM metrics_1min
-                WHERE timestamp < NEW.timestamp - 604800;
-            END;"
+            -- Old rows are now downsampled by apply_retention() instead
+            DROP TRIGGER IF EXISTS cleanup_old_metrics;"
         ).map_err(|e| format!("Failed to create metrics table: {}", e))?;
 
+        let columns: Vec<String> = AGGREGATED_METRICS
+            .iter()
+            .map(|m| format!("{m}_min REAL, {m}_max REAL, {m}_avg REAL"))
+            .collect();
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS metrics_1h (
+                hour INTEGER PRIMARY KEY,
+                samples INTEGER,
+                {}
+            );",
+            columns.join(",\n                ")
+        )).map_err(|e| format!("Failed to create hourly metrics table: {}", e))?;
+
         if let Ok(mut db) = self.db.lock() {
             *db = Some(conn);
         }
@@ -205,6 +280,113 @@ impl MetricsStore {
         }
     }
 
+    /// Fold 1-minute rows older than the full-resolution window into hourly
+    /// aggregates, then drop aggregates older than their window. Only whole
+    /// hours are folded, so each hour is aggregated once.
+    pub fn apply_retention(&self, now: u64) -> Result<RetentionStats, String> {
+        let policy = *self.retention.lock().unwrap();
+        let cutoff = now.saturating_sub(policy.full_resolution_secs);
+        let cutoff = cutoff - cutoff % 3600;
+        let expire_before = now.saturating_sub(policy.aggregate_secs);
+
+        let mut db_guard = self.db.lock().map_err(|_| "metrics DB lock poisoned".to_string())?;
+        let Some(conn) = db_guard.as_mut() else {
+            return Ok(RetentionStats::default());
+        };
+
+        let names: Vec<String> = AGGREGATED_METRICS
+            .iter()
+            .map(|m| format!("{m}_min, {m}_max, {m}_avg"))
+            .collect();
+        let selects: Vec<String> = AGGREGATED_METRICS
+            .iter()
+            .map(|m| format!("MIN({m}), MAX({m}), AVG({m})"))
+            .collect();
+        // Should an hour already exist (e.g. the clock went back), merge into it
+        let merges: Vec<String> = AGGREGATED_METRICS
+            .iter()
+            .map(|m| {
+                format!(
+                    "{m}_min = MIN({m}_min, excluded.{m}_min), \
+                     {m}_max = MAX({m}_max, excluded.{m}_max), \
+                     {m}_avg = ({m}_avg * samples + excluded.{m}_avg * excluded.samples) / (samples + excluded.samples)"
+                )
+            })
+            .collect();
+        let fold = format!(
+            "INSERT INTO metrics_1h (hour, samples, {})
+             SELECT (timestamp / 3600) * 3600 AS h, COUNT(*), {}
+             FROM metrics_1min WHERE timestamp < ?1 GROUP BY h
+             ON CONFLICT(hour) DO UPDATE SET {}, samples = samples + excluded.samples",
+            names.join(", "),
+            selects.join(", "),
+            merges.join(", "),
+        );
+
+        let tx = conn.transaction().map_err(|e| format!("Failed to start retention: {}", e))?;
+        tx.execute(&fold, rusqlite::params![cutoff])
+            .map_err(|e| format!("Failed to downsample metrics: {}", e))?;
+        let rows_collapsed = tx
+            .execute("DELETE FROM metrics_1min WHERE timestamp < ?1", rusqlite::params![cutoff])
+            .map_err(|e| format!("Failed to delete downsampled metrics: {}", e))?;
+        let aggregates_expired = tx
+            .execute("DELETE FROM metrics_1h WHERE hour < ?1", rusqlite::params![expire_before])
+            .map_err(|e| format!("Failed to expire hourly metrics: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to commit retention: {}", e))?;
+
+        Ok(RetentionStats { rows_collapsed, aggregates_expired })
+    }
+
+    /// Reclaim the space freed by retention.
+    pub fn vacuum(&self) -> Result<(), String> {
+        if let Ok(db_guard) = self.db.lock() {
+            if let Some(ref conn) = *db_guard {
+                conn.execute_batch("VACUUM").map_err(|e| format!("Failed to vacuum metrics DB: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Query hourly aggregates from SQLite
+    pub fn query_hourly(&self, from_ts: u64, to_ts: u64) -> Vec<HourlyAggregate> {
+        let Ok(db_guard) = self.db.lock() else {
+            return Vec::new();
+        };
+        let Some(ref conn) = *db_guard else {
+            return Vec::new();
+        };
+        let names: Vec<String> = AGGREGATED_METRICS
+            .iter()
+            .map(|m| format!("{m}_min, {m}_max, {m}_avg"))
+            .collect();
+        let mut stmt = match conn.prepare(&format!(
+            "SELECT hour, samples, {} FROM metrics_1h WHERE hour >= ?1 AND hour <= ?2 ORDER BY hour ASC",
+            names.join(", ")
+        )) {
+            Ok(s) => s,
+            Err(_) => return Vec::new(),
+        };
+        let rows = stmt.query_map(rusqlite::params![from_ts, to_ts], |row| {
+            let mut metrics = std::collections::BTreeMap::new();
+            for (i, name) in AGGREGATED_METRICS.iter().enumerate() {
+                let col = 2 + i * 3;
+                metrics.insert(
+                    name.to_string(),
+                    Aggregate {
+                        min: row.get::<_, Option<f64>>(col)?.unwrap_or(0.0),
+                        max: row.get::<_, Option<f64>>(col + 1)?.unwrap_or(0.0),
+                        avg: row.get::<_, Option<f64>>(col + 2)?.unwrap_or(0.0),
+                    },
+                );
+            }
+            Ok(HourlyAggregate { hour: row.get(0)?, samples: row.get(1)?, metrics })
+        });
+        match rows {
+            Ok(mapped) => mapped.filter_map(|r| r.ok()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     /// Flush averaged data to SQLite (called every 60 samples)
     fn flush_to_sqlite(&self) {
         let samples = self.query_recent(60);
@@ -230,6 +412,11 @@ impl MetricsStore {
             network_rx_bytes: samples.last().map(|s| s.network_rx_bytes).unwrap_or(0),
         };
 
+        self.insert_minute(&avg);
+    }
+
+    /// Write one 1-minute row
+    fn insert_minute(&self, avg: &MetricsSample) {
         if let Ok(db_guard) = self.db.lock() {
             if let Some(ref conn) = *db_guard {
                 let _ = conn.execute(
@@ -263,3 +450,59 @@ impl MetricsStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minute_rows(store: &MetricsStore) -> usize {
+        let db = store.db.lock().unwrap();
+        db.as_ref()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM metrics_1min", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn old_rows_collapse_into_hourly_aggregates() {
+        let store = MetricsStore::new();
+        store.init_db(":memory:").unwrap();
+        store.set_retention(RetentionPolicy {
+            full_resolution_secs: 2 * 86400,
+            aggregate_secs: 3 * 86400,
+        });
+
+        // Four days of 1-minute rows; the CPU climbs 0..59 within each hour
+        let start = 1_700_000_000 / 3600 * 3600;
+        let now = start + 4 * 86400;
+        for ts in (start..now).step_by(60) {
+            store.insert_minute(&MetricsSample {
+                timestamp: ts,
+                cpu_percent: ((ts - start) % 3600 / 60) as f32,
+                client_count: 4,
+                ..MetricsSample::default()
+            });
+        }
+        assert_eq!(minute_rows(&store), 4 * 1440);
+
+        let stats = store.apply_retention(now).unwrap();
+        // Two days stay at full resolution, the older two are folded,
+        // and the first day of aggregates is already past its 3 days
+        assert_eq!(stats.rows_collapsed, 2 * 1440);
+        assert_eq!(stats.aggregates_expired, 24);
+        assert_eq!(minute_rows(&store), 2 * 1440);
+
+        let hourly = store.query_hourly(0, now);
+        assert_eq!(hourly.len(), 24);
+        assert_eq!(hourly[0].hour, start + 86400);
+        assert_eq!(hourly[0].samples, 60);
+        let cpu = hourly[0].metrics["cpu_percent"];
+        assert_eq!((cpu.min, cpu.max, cpu.avg), (0.0, 59.0, 29.5));
+        assert_eq!(hourly[0].metrics["client_count"].avg, 4.0);
+
+        // Nothing more to do until time moves on, and the row count stays bounded
+        assert_eq!(store.apply_retention(now).unwrap(), RetentionStats::default());
+        store.vacuum().unwrap();
+        assert_eq!(minute_rows(&store) + store.query_hourly(0, now).len(), 2 * 1440 + 24);
+    }
+}
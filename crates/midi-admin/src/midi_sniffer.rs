@@ -1,22 +1,31 @@
 /// Lightweight multicast MIDI sniffer.
 ///
 /// Joins the host's multicast group and counts incoming MIDI data packets
-/// to populate the admin panel's MIDI metrics (messages/sec, bytes/sec, etc.).
+/// to populate the admin panel's MIDI metrics (messages/sec, bytes/sec, etc.),
+/// and mirrors each host's MIDI state for `GET /api/journal`.
 /// Runs as a background tokio task spawned from main.
 
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::Ipv4Addr;
 use std::time::{Duration, Instant};
 
-use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 use tracing::{info, warn};
 
+use midi_protocol::activity::ChannelActivity;
+use midi_protocol::interface::{self, InterfaceFallback};
+use midi_protocol::multicast::{self, MulticastInterface};
+use midi_protocol::crypto::{decode_data_packet, PacketCipher};
+
+use crate::api::journal::mirror_packet;
+use crate::api::program_names::program_label;
 use crate::state::AppState;
 
 /// Run the multicast MIDI sniffer. Joins `multicast_group:data_port`,
 /// counts MIDI packets, and updates `state.midi_metrics` once per second.
-pub async fn run(state: AppState, multicast_group: String, data_port: u16, interface: String) {
-    let group: Ipv4Addr = match multicast_group.parse() {
+/// `psk` must match the hosts' `network.psk` when the stream is sealed.
+pub async fn run(state: AppState, multicast_group: String, data_port: u16, interface: String, psk: String) {
+    let cipher = PacketCipher::from_psk(&psk);
+    let group = match multicast::parse_group(&multicast_group) {
         Ok(g) => g,
         Err(e) => {
             warn!(group = %multicast_group, error = %e, "Invalid multicast group, MIDI sniffer disabled");
@@ -24,17 +33,13 @@ pub async fn run(state: AppState, multicast_group: String, data_port: u16, inter
         }
     };
 
-    let iface: Ipv4Addr = if interface == "0.0.0.0" || interface.is_empty() {
-        Ipv4Addr::UNSPECIFIED
-    } else {
-        resolve_interface_ip(&interface).unwrap_or(Ipv4Addr::UNSPECIFIED)
-    };
+    let iface = sniffer_interface(&interface);
 
-    let bind_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, data_port);
+    let bind_addr = multicast::bind_addr(group, data_port);
 
     // Use socket2 to set SO_REUSEADDR + SO_REUSEPORT *before* bind,
     // allowing port sharing with midi-host on the same machine.
-    let raw = match Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)) {
+    let raw = match multicast::new_socket(group) {
         Ok(s) => s,
         Err(e) => {
             warn!(error = %e, "MIDI sniffer failed to create socket");
@@ -53,6 +58,10 @@ pub async fn run(state: AppState, multicast_group: String, data_port: u16, inter
         warn!(addr = %bind_addr, error = %e, "MIDI sniffer failed to bind");
         return;
     }
+    if let Err(e) = multicast::join(&raw, group, MulticastInterface::from(iface)) {
+        warn!(group = %group, iface = %iface, error = %e, "MIDI sniffer failed to join multicast");
+        return;
+    }
 
     let std_socket: std::net::UdpSocket = raw.into();
     let socket = match UdpSocket::from_std(std_socket) {
@@ -63,11 +72,6 @@ pub async fn run(state: AppState, multicast_group: String, data_port: u16, inter
         }
     };
 
-    if let Err(e) = socket.join_multicast_v4(group, iface) {
-        warn!(group = %group, iface = %iface, error = %e, "MIDI sniffer failed to join multicast");
-        return;
-    }
-
     info!(group = %group, port = data_port, "MIDI sniffer listening on multicast");
 
     let mut buf = [0u8; 2048];
@@ -75,6 +79,8 @@ pub async fn run(state: AppState, multicast_group: String, data_port: u16, inter
     let mut byte_count: u64 = 0;
     let mut total_messages: u64 = 0;
     let mut active_notes: u32 = 0;
+    let mut programs: [Option<u8>; 16] = [None; 16];
+    let mut channel_activity = ChannelActivity::new();
     let mut tick = Instant::now();
 
     loop {
@@ -82,43 +88,61 @@ pub async fn run(state: AppState, multicast_group: String, data_port: u16, inter
             result = socket.recv_from(&mut buf) => {
                 match result {
                     Ok((len, _addr)) => {
-                        // Verify MIDI data packet magic: "MDMI"
-                        if len >= 18 && &buf[0..4] == b"MDMI" {
-                            msg_count += 1;
-                            total_messages += 1;
-
-                            // Extract MIDI payload length from header (bytes 16..18, big-endian u16)
-                            let midi_len = u16::from_be_bytes([buf[16], buf[17]]) as usize;
-                            byte_count += midi_len as u64;
-
-                            // Count active notes from raw MIDI data (offset 18..)
-                            if len >= 18 + midi_len {
-                                let midi_data = &buf[18..18 + midi_len];
-                                let prev = active_notes;
-                                count_active_notes(midi_data, &mut active_notes);
-                                // Push active_notes to shared state immediately for snappy UI
-                                if active_notes != prev {
-                                    state.inner.midi_metrics.write().await.active_notes = active_notes;
-                                }
-                            }
+                        // Opened with the PSK on a sealed stream; packets that
+                        // fail to decode or authenticate are not counted
+                        let Ok(packet) = decode_data_packet(cipher.as_ref(), &buf[..len]) else {
+                            continue;
+                        };
+                        msg_count += 1;
+                        total_messages += 1;
+
+                        mirror_packet(&mut *state.inner.host_midi_states.write().await, &packet);
+                        // Live stream for /ws/midi (`midinet tail`)
+                        if !packet.midi_data.is_empty() && state.inner.midi_stream_tx.receiver_count() > 0 {
+                            let _ = state.inner.midi_stream_tx.send(
+                                serde_json::json!({
+                                    "host_id": packet.host_id,
+                                    "seq": packet.sequence,
+                                    "timestamp_us": packet.timestamp_us,
+                                    "data": packet.midi_data,
+                                }).to_string(),
+                            );
+                        }
+
+                        let midi_data = &packet.midi_data[..];
+                        byte_count += midi_data.len() as u64;
 
-                            // Log to traffic sniffer for real-time display
-                            if midi_len > 0 && len >= 18 + midi_len {
-                                let midi_data = &buf[18..18 + midi_len];
-                                let desc = describe_midi(midi_data);
-                                let now_s = std::time::SystemTime::now()
-                                    .duration_since(std::time::UNIX_EPOCH)
-                                    .unwrap_or_default()
-                                    .as_secs();
-                                let _ = state.inner.traffic_log_tx.send(
-                                    serde_json::json!({
-                                        "ch": "midi",
-                                        "ts": now_s,
-                                        "msg": desc,
-                                    }).to_string(),
-                                );
-                                state.inner.traffic_counters.midi_packets_in.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let prev = active_notes;
+                        count_active_notes(midi_data, &mut active_notes);
+                        channel_activity.record(midi_data);
+                        // Push active_notes to shared state immediately for snappy UI
+                        if active_notes != prev {
+                            state.inner.midi_metrics.write().await.active_notes = active_notes;
+                        }
+                        if track_program_changes(midi_data, &mut programs) {
+                            state.inner.midi_metrics.write().await.programs = programs;
+                        }
+
+                        // Log to traffic sniffer for real-time display
+                        if !midi_data.is_empty() {
+                            let mut desc = describe_midi(midi_data);
+                            let now_s = std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs();
+                            let mut entry = serde_json::json!({
+                                "ch": "midi",
+                                "ts": now_s,
+                            });
+                            // Label program changes for the operator (display only)
+                            if let Some((channel, program)) = program_change(midi_data) {
+                                let label = program_label(&*state.inner.program_names.read().await, channel, program);
+                                desc = format!("{} ({})", desc, label);
+                                entry["program_label"] = label.into();
                             }
+                            entry["msg"] = desc.into();
+                            let _ = state.inner.traffic_log_tx.send(entry.to_string());
+                            state.inner.traffic_counters.midi_packets_in.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                         }
                     }
                     Err(e) => {
@@ -130,12 +154,14 @@ pub async fn run(state: AppState, multicast_group: String, data_port: u16, inter
             _ = tokio::time::sleep(Duration::from_millis(250).saturating_sub(tick.elapsed())) => {
                 // Update rate metrics every 250ms for responsive UI
                 let elapsed = tick.elapsed().as_secs_f32().max(0.001);
+                channel_activity.roll(tick.elapsed());
                 {
                     let mut metrics = state.inner.midi_metrics.write().await;
                     metrics.messages_in_per_sec = msg_count as f32 / elapsed;
                     metrics.bytes_in_per_sec = (byte_count as f32 / elapsed) as u64;
                     metrics.total_messages = total_messages;
                     metrics.active_notes = active_notes;
+                    metrics.per_channel = channel_activity.meters();
                 }
                 msg_count = 0;
                 byte_count = 0;
@@ -188,6 +214,46 @@ fn count_active_notes(data: &[u8], active_notes: &mut u32) {
     }
 }
 
+/// Record every Program Change in `data` into `programs` (index 0-15).
+/// Returns whether any was found.
+fn track_program_changes(data: &[u8], programs: &mut [Option<u8>; 16]) -> bool {
+    let mut found = false;
+    let mut i = 0;
+    while i < data.len() {
+        let status = data[i];
+        if status & 0x80 == 0 {
+            i += 1;
+            continue;
+        }
+        match status & 0xF0 {
+            0xC0 => {
+                if i + 1 < data.len() {
+                    programs[(status & 0x0F) as usize] = Some(data[i + 1]);
+                    found = true;
+                }
+                i += 2;
+            }
+            0xD0 => { i += 2; }
+            0x80 | 0x90 | 0xA0 | 0xB0 | 0xE0 => { i += 3; }
+            _ => {
+                if status == 0xF0 {
+                    while i < data.len() && data[i] != 0xF7 { i += 1; }
+                }
+                i += 1;
+            }
+        }
+    }
+    found
+}
+
+/// Channel (1-16) and program of the first message in `data`, if it is a Program Change.
+fn program_change(data: &[u8]) -> Option<(u8, u8)> {
+    match data {
+        [status, program, ..] if status & 0xF0 == 0xC0 => Some(((status & 0x0F) + 1, *program)),
+        _ => None,
+    }
+}
+
 /// Produce a human-readable description of the first MIDI message in the buffer.
 fn describe_midi(data: &[u8]) -> String {
     if data.is_empty() {
@@ -215,7 +281,7 @@ fn describe_midi(data: &[u8]) -> String {
 /// monitors focus claims/acks and feedback MIDI packets, and updates the
 /// admin panel's focus state + traffic sniffer.
 pub async fn run_control(state: AppState, control_group: String, control_port: u16, interface: String) {
-    let group: Ipv4Addr = match control_group.parse() {
+    let group = match multicast::parse_group(&control_group) {
         Ok(g) => g,
         Err(e) => {
             warn!(group = %control_group, error = %e, "Invalid control group, control sniffer disabled");
@@ -223,15 +289,11 @@ pub async fn run_control(state: AppState, control_group: String, control_port: u
         }
     };
 
-    let iface: Ipv4Addr = if interface == "0.0.0.0" || interface.is_empty() {
-        Ipv4Addr::UNSPECIFIED
-    } else {
-        resolve_interface_ip(&interface).unwrap_or(Ipv4Addr::UNSPECIFIED)
-    };
+    let iface = sniffer_interface(&interface);
 
-    let bind_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, control_port);
+    let bind_addr = multicast::bind_addr(group, control_port);
 
-    let raw = match Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)) {
+    let raw = match multicast::new_socket(group) {
         Ok(s) => s,
         Err(e) => {
             warn!(error = %e, "Control sniffer failed to create socket");
@@ -250,6 +312,10 @@ pub async fn run_control(state: AppState, control_group: String, control_port: u
         warn!(addr = %bind_addr, error = %e, "Control sniffer failed to bind");
         return;
     }
+    if let Err(e) = multicast::join(&raw, group, MulticastInterface::from(iface)) {
+        warn!(group = %group, iface = %iface, error = %e, "Control sniffer failed to join multicast");
+        return;
+    }
 
     let std_socket: std::net::UdpSocket = raw.into();
     let socket = match UdpSocket::from_std(std_socket) {
@@ -260,11 +326,6 @@ pub async fn run_control(state: AppState, control_group: String, control_port: u
         }
     };
 
-    if let Err(e) = socket.join_multicast_v4(group, iface) {
-        warn!(group = %group, iface = %iface, error = %e, "Control sniffer failed to join multicast");
-        return;
-    }
-
     info!(group = %group, port = control_port, "Control sniffer listening (focus + feedback MIDI)");
 
     let mut buf = [0u8; 2048];
@@ -349,29 +410,12 @@ pub async fn run_control(state: AppState, control_group: String, control_port: u
     }
 }
 
-/// Try to resolve a network interface name (e.g. "eth0") to its IPv4 address.
-fn resolve_interface_ip(name: &str) -> Option<Ipv4Addr> {
-    // Try parsing as IP first
-    if let Ok(ip) = name.parse::<Ipv4Addr>() {
-        return Some(ip);
-    }
-    // On Linux, read from /sys/class/net/<name>/... or use getifaddrs
-    #[cfg(target_os = "linux")]
-    {
-        use std::process::Command;
-        let output = Command::new("ip")
-            .args(["-4", "-o", "addr", "show", name])
-            .output()
-            .ok()?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        // Parse "2: eth0    inet 192.168.2.23/24 ..."
-        for part in stdout.split_whitespace() {
-            if let Some(ip_str) = part.split('/').next() {
-                if let Ok(ip) = ip_str.parse::<Ipv4Addr>() {
-                    return Some(ip);
-                }
-            }
-        }
+/// The IPv4 address to join on for `interface` (a name or an address),
+/// falling back to any interface when it can't be found.
+fn sniffer_interface(name: &str) -> Ipv4Addr {
+    let resolved = interface::resolve(name, InterfaceFallback::Any);
+    if let Some(e) = &resolved.error {
+        warn!("Sniffer interface: {}", e);
     }
-    None
+    resolved.addr
 }
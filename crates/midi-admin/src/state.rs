@@ -2,13 +2,15 @@
 /// Collects metrics, status, and configuration from the system.
 /// All fields are thread-safe for use with axum's State extractor.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
+use midi_protocol::client_command::{CommandAck, QueuedCommand};
+use midi_protocol::midi_state::MidiState;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, Notify, RwLock};
 
 use crate::alerting::AlertManager;
 use crate::metrics_store::MetricsStore;
@@ -31,6 +33,9 @@ pub struct InputRedundancyState {
     pub primary_device: String,
     /// Device name/path of secondary controller
     pub secondary_device: String,
+    /// Per-input enable flag (0 = primary, 1 = secondary). A disabled input
+    /// stays configured but is muted and never a switch target.
+    pub input_enabled: [bool; 2],
     /// Total input switch count since startup
     pub switch_count: u64,
     /// Activity timeout in seconds (0 = disabled)
@@ -52,6 +57,7 @@ impl Default for InputRedundancyState {
             secondary_health: "unknown".to_string(),
             primary_device: String::new(),
             secondary_device: String::new(),
+            input_enabled: [true; 2],
             switch_count: 0,
             activity_timeout_s: 0,
             auto_switch_enabled: true,
@@ -81,12 +87,19 @@ pub struct FailoverSettings {
     pub switch_back_policy: String,
     #[serde(default = "default_lockout")]
     pub lockout_seconds: u64,
+    /// Steady primary heartbeats required before an auto switch-back
+    #[serde(default = "default_switch_back_delay")]
+    pub switch_back_delay_s: u64,
     #[serde(default = "default_confirmation_mode")]
     pub confirmation_mode: String,
     #[serde(default)]
     pub heartbeat: HeartbeatSettings,
     #[serde(default)]
     pub triggers: FailoverTriggerSettings,
+    /// Operator-selected active host, persisted so a manual switch survives
+    /// a restart. Only written while it differs from "primary".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_host: Option<String>,
 }
 
 impl Default for FailoverSettings {
@@ -95,9 +108,11 @@ impl Default for FailoverSettings {
             auto_enabled: true,
             switch_back_policy: "manual".to_string(),
             lockout_seconds: 5,
+            switch_back_delay_s: 10,
             confirmation_mode: "immediate".to_string(),
             heartbeat: HeartbeatSettings::default(),
             triggers: FailoverTriggerSettings::default(),
+            active_host: None,
         }
     }
 }
@@ -229,6 +244,7 @@ pub struct DeviceActivity {
 fn default_true() -> bool { true }
 fn default_switch_back_policy() -> String { "manual".to_string() }
 fn default_lockout() -> u64 { 5 }
+fn default_switch_back_delay() -> u64 { 10 }
 fn default_confirmation_mode() -> String { "immediate".to_string() }
 fn default_heartbeat_interval() -> u64 { 3 }
 fn default_miss_threshold() -> u8 { 3 }
@@ -256,6 +272,12 @@ pub struct AppStateInner {
     pub failover_state: RwLock<FailoverState>,
     pub focus_state: RwLock<FocusInfo>,
     pub pipeline_config: RwLock<PipelineConfig>,
+    /// Named pipeline presets, kept in `pipeline_presets.json` next to the config file
+    pub pipeline_presets: RwLock<BTreeMap<String, PipelineConfig>>,
+    /// Pipeline preset last applied (None = custom / manual pipeline)
+    pub active_pipeline_preset: RwLock<Option<String>>,
+    /// Program Change labels per channel, kept in `program_names.json` next to the config file
+    pub program_names: RwLock<crate::api::program_names::ProgramNames>,
     pub metrics_store: MetricsStore,
     pub alert_manager: AlertManager,
     /// Connected WebSocket clients count
@@ -268,6 +290,8 @@ pub struct AppStateInner {
     pub traffic_rates: RwLock<TrafficRates>,
     /// Broadcast channel for per-message traffic log (sniffer panel)
     pub traffic_log_tx: broadcast::Sender<String>,
+    /// Broadcast channel for sniffed MIDI data, one event per packet (streamed to /ws/midi)
+    pub midi_stream_tx: broadcast::Sender<String>,
     // ── Settings state ──
     /// Full failover configuration (superset of FailoverState's configurable fields)
     pub failover_config: RwLock<FailoverSettings>,
@@ -291,8 +315,21 @@ pub struct AppStateInner {
     pub designated_primary: RwLock<Option<u8>>,
     /// Designated focus client ID (user-selected focus holder)
     pub designated_focus: RwLock<Option<u32>>,
+    /// Commands waiting for each client's next heartbeat: client_id → queue
+    pub client_commands: RwLock<HashMap<u32, Vec<QueuedCommand>>>,
+    /// Acks for in-flight commands: command id → (client_id → ack)
+    pub command_acks: RwLock<HashMap<u64, HashMap<u32, CommandAck>>>,
+    /// Wakes bulk command requests waiting on acks
+    pub command_ack_notify: Notify,
+    pub next_command_id: AtomicU64,
     /// Broadcast channel for update log lines (streamed to /ws/update)
     pub update_log_tx: broadcast::Sender<String>,
+    /// Network config from the shared host TOML (control group for panic etc.)
+    pub network_config: RwLock<Option<crate::api::config::NetworkConfig>>,
+    /// Shadow host IDs asked to go live (picked up by the host's next poll)
+    pub shadow_promotions: RwLock<HashSet<u8>>,
+    /// Each host's MIDI state, mirrored from its data stream by the sniffer
+    pub host_midi_states: RwLock<BTreeMap<u8, MidiState>>,
 }
 
 impl AppState {
@@ -311,6 +348,9 @@ impl AppState {
                 failover_state: RwLock::new(FailoverState::default()),
                 focus_state: RwLock::new(FocusInfo::default()),
                 pipeline_config: RwLock::new(PipelineConfig::default()),
+                pipeline_presets: RwLock::new(BTreeMap::new()),
+                active_pipeline_preset: RwLock::new(None),
+                program_names: RwLock::new(BTreeMap::new()),
                 metrics_store: MetricsStore::new(),
                 alert_manager: AlertManager::new(),
                 ws_client_count: RwLock::new(0),
@@ -318,6 +358,7 @@ impl AppState {
                 traffic_counters: TrafficCounters::new(),
                 traffic_rates: RwLock::new(TrafficRates::default()),
                 traffic_log_tx: broadcast::channel(512).0,
+                midi_stream_tx: broadcast::channel(512).0,
                 failover_config: RwLock::new(FailoverSettings::default()),
                 osc_port_state: RwLock::new(OscPortState::default()),
                 osc_restart_tx,
@@ -329,7 +370,14 @@ impl AppState {
                 identify_requests: RwLock::new(HashMap::new()),
                 designated_primary: RwLock::new(None),
                 designated_focus: RwLock::new(None),
+                client_commands: RwLock::new(HashMap::new()),
+                command_acks: RwLock::new(HashMap::new()),
+                command_ack_notify: Notify::new(),
+                next_command_id: AtomicU64::new(1),
                 update_log_tx: broadcast::channel(256).0,
+                network_config: RwLock::new(None),
+                shadow_promotions: RwLock::new(HashSet::new()),
+                host_midi_states: RwLock::new(BTreeMap::new()),
             }),
         }
     }
@@ -344,6 +392,9 @@ impl AppState {
             failover.auto_enabled = config.failover.auto_enabled;
             failover.lockout_seconds = config.failover.lockout_seconds;
             failover.confirmation_mode = config.failover.confirmation_mode.clone();
+            if let Some(ref host) = config.failover.active_host {
+                failover.active_host = host.clone();
+            }
         }
 
         // Apply the full failover settings
@@ -365,6 +416,11 @@ impl AppState {
             }
         }
 
+        // Network config only comes from the TOML file (never from the API)
+        if let Some(network) = config.network {
+            *self.inner.network_config.write().await = Some(network);
+        }
+
         self.inner.alert_manager.update_config(config.alerts);
     }
 
@@ -376,6 +432,8 @@ impl AppState {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemStatus {
     pub health_score: u8,
+    /// What the health score is made of
+    pub health: crate::health::HealthScore,
     pub cpu_percent: f32,
     pub cpu_temp_c: f32,
     pub memory_used_mb: u64,
@@ -383,12 +441,47 @@ pub struct SystemStatus {
     pub disk_free_mb: u64,
     pub network_tx_bytes: u64,
     pub network_rx_bytes: u64,
+    /// Raspberry Pi firmware throttling flags (None off a Pi)
+    pub throttled: Option<ThrottleStatus>,
+}
+
+/// Decoded `vcgencmd get_throttled` bits. The first four are the current
+/// state; the `_occurred` ones latch until reboot.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThrottleStatus {
+    pub raw: u32,
+    pub under_voltage: bool,
+    pub freq_capped: bool,
+    pub throttled: bool,
+    pub soft_temp_limit: bool,
+    pub under_voltage_occurred: bool,
+    pub freq_capped_occurred: bool,
+    pub throttled_occurred: bool,
+    pub soft_temp_limit_occurred: bool,
+}
+
+impl ThrottleStatus {
+    pub fn from_bits(raw: u32) -> Self {
+        let bit = |n: u32| raw & (1 << n) != 0;
+        Self {
+            raw,
+            under_voltage: bit(0),
+            freq_capped: bit(1),
+            throttled: bit(2),
+            soft_temp_limit: bit(3),
+            under_voltage_occurred: bit(16),
+            freq_capped_occurred: bit(17),
+            throttled_occurred: bit(18),
+            soft_temp_limit_occurred: bit(19),
+        }
+    }
 }
 
 impl Default for SystemStatus {
     fn default() -> Self {
         Self {
             health_score: 100,
+            health: crate::health::HealthScore::default(),
             cpu_percent: 0.0,
             cpu_temp_c: 0.0,
             memory_used_mb: 0,
@@ -396,6 +489,7 @@ impl Default for SystemStatus {
             disk_free_mb: 0,
             network_tx_bytes: 0,
             network_rx_bytes: 0,
+            throttled: None,
         }
     }
 }
@@ -437,6 +531,9 @@ pub struct ClientInfo {
     pub midi_rate_in: f32,
     #[serde(default)]
     pub midi_rate_out: f32,
+    /// Client's virtual device send p99 in µs (driver latency)
+    #[serde(default)]
+    pub device_send_latency_us: u32,
     #[serde(default)]
     pub connection_state: String,
     /// Git hash of the client binary (reported via heartbeat)
@@ -445,6 +542,27 @@ pub struct ClientInfo {
     /// True if this client was manually added by the operator (not self-registered)
     #[serde(default)]
     pub manual: bool,
+    /// Largest run of data packets the client has seen missing at once
+    #[serde(default)]
+    pub max_sequence_gap: u64,
+    /// Data packets the client received out of order
+    #[serde(default)]
+    pub packets_out_of_order: u64,
+    /// Recent `packet_loss_percent` reports, oldest first
+    #[serde(skip)]
+    pub loss_history: VecDeque<f32>,
+    /// Direction of the client's packet loss over `loss_history`
+    #[serde(default)]
+    pub loss_trend: LossTrend,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LossTrend {
+    Improving,
+    #[default]
+    Stable,
+    Degrading,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -469,6 +587,12 @@ pub struct MidiMetrics {
     pub active_notes: u32,
     pub dropped_messages: u64,
     pub peak_burst_rate: f32,
+    /// Last Program Change seen per channel (index 0-15)
+    #[serde(default)]
+    pub programs: [Option<u8>; 16],
+    /// Messages and messages/sec per channel, over the same window as the aggregate rate
+    #[serde(default)]
+    pub per_channel: Vec<midi_protocol::activity::ChannelMeter>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -498,6 +622,19 @@ impl Default for FailoverState {
     }
 }
 
+impl FailoverState {
+    /// Log a switch as the latest, keeping the newest `MAX_FAILOVER_HISTORY`
+    pub fn record(&mut self, event: FailoverEvent) {
+        self.last_failover = Some(event.clone());
+        self.history.push(event);
+        let excess = self.history.len().saturating_sub(MAX_FAILOVER_HISTORY);
+        self.history.drain(..excess);
+    }
+}
+
+/// Switches kept in `FailoverState::history`
+pub const MAX_FAILOVER_HISTORY: usize = 100;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FailoverEvent {
     pub timestamp: u64,
@@ -505,6 +642,12 @@ pub struct FailoverEvent {
     pub to_host: String,
     pub trigger: String, // "auto", "api", "midi", "osc"
     pub duration_ms: u32,
+    /// Why the switch happened
+    #[serde(default)]
+    pub cause: midi_protocol::failover::FailoverCause,
+    /// Free-form context for the cause
+    #[serde(default)]
+    pub detail: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -533,14 +676,54 @@ pub struct FocusHistoryEntry {
     pub timestamp: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PipelineConfig {
     pub channel_filter: [bool; 16],
     pub message_filter: MessageFilter,
     pub channel_remap: [u8; 16],
     pub transpose: [i8; 16],
-    pub velocity_curve: String,
+    /// Controller number remaps (source channel index 0-15, CC from → to)
+    pub cc_remap: Vec<midi_protocol::pipeline::CcRemap>,
+    pub velocity_curve: midi_protocol::pipeline::VelocityCurve,
+    /// Velocity curve for feedback MIDI returning to the controllers (applied by the host)
+    pub feedback_velocity_curve: midi_protocol::pipeline::VelocityCurve,
+    /// Note On velocities are rescaled onto velocity_min..=velocity_max after the curve
+    pub velocity_min: u8,
+    pub velocity_max: u8,
+    /// Rewrite every note release (0x80 or Note On velocity 0) into note_off_form
+    pub normalize_note_off: bool,
+    pub note_off_form: midi_protocol::pipeline::NoteOffForm,
     pub sysex_passthrough: bool,
+    /// Note debounce applied by the host (0 = off)
+    #[serde(default)]
+    pub min_note_duration_ms: u64,
+    /// Hold notes merged by channel remap until every source releases them (applied by the host)
+    pub merge_note_refcount: bool,
+    /// Max Control Changes per second per controller, 0 = off (applied by the host)
+    pub cc_throttle_hz: u16,
+    /// Note latch per channel: notes sustain until the next Note On (applied by the host)
+    pub latch: [bool; 16],
+    /// Monophonic mode per channel, last-note priority (applied by the host)
+    pub mono: [bool; 16],
+    /// Mono channels return to the last key still held on release (applied by the host)
+    pub mono_retrigger: bool,
+    /// Chord offsets (semitones) keyed by channel "1"-"16", e.g. [0, 4, 7] (applied by the host)
+    pub harmonize: std::collections::BTreeMap<String, Vec<i8>>,
+    /// MPE zone kept free of channel remap/filtering (applied by the host)
+    pub mpe_zone: Option<midi_protocol::pipeline::MpeZone>,
+    /// Keyboard split zones (note range on a source channel → target channel)
+    pub zones: Vec<midi_protocol::pipeline::Zone>,
+}
+
+impl PipelineConfig {
+    /// Reject settings the pipeline can't apply.
+    pub fn validate(&self) -> Result<(), String> {
+        use midi_protocol::pipeline::{validate_velocity_curve, validate_velocity_range};
+        validate_velocity_range(self.velocity_min, self.velocity_max)?;
+        validate_velocity_curve(&self.velocity_curve).map_err(|e| format!("velocity_curve: {}", e))?;
+        validate_velocity_curve(&self.feedback_velocity_curve).map_err(|e| format!("feedback_velocity_curve: {}", e))
+    }
 }
 
 impl Default for PipelineConfig {
@@ -550,22 +733,44 @@ impl Default for PipelineConfig {
             message_filter: MessageFilter::default(),
             channel_remap: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
             transpose: [0; 16],
-            velocity_curve: "linear".to_string(),
+            cc_remap: Vec::new(),
+            velocity_curve: Default::default(),
+            feedback_velocity_curve: Default::default(),
+            velocity_min: 1,
+            velocity_max: 127,
+            normalize_note_off: false,
+            note_off_form: Default::default(),
             sysex_passthrough: true,
+            min_note_duration_ms: 0,
+            merge_note_refcount: false,
+            cc_throttle_hz: 0,
+            latch: [false; 16],
+            mono: [false; 16],
+            mono_retrigger: false,
+            harmonize: Default::default(),
+            mpe_zone: None,
+            zones: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MessageFilter {
     pub note_on: bool,
     pub note_off: bool,
     pub control_change: bool,
     pub program_change: bool,
     pub pitch_bend: bool,
+    /// Both kinds of aftertouch; the two below block just one
     pub aftertouch: bool,
+    pub channel_pressure: bool,
+    pub poly_aftertouch: bool,
     pub sysex: bool,
+    /// Clock and Start/Continue/Stop
     pub clock: bool,
+    /// Active sensing and reset
+    pub realtime: bool,
 }
 
 impl Default for MessageFilter {
@@ -577,8 +782,11 @@ impl Default for MessageFilter {
             program_change: true,
             pitch_bend: true,
             aftertouch: true,
+            channel_pressure: true,
+            poly_aftertouch: true,
             sysex: true,
             clock: true,
+            realtime: true,
         }
     }
 }
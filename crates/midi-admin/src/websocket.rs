@@ -2,7 +2,7 @@
 ///
 /// Channels:
 ///   /ws/status   — System status + metrics pushed every 1s
-///   /ws/midi     — Real-time MIDI message stream
+///   /ws/midi     — Real-time MIDI data, one event per sniffed packet
 ///   /ws/logs     — Log stream (filtered by severity)
 ///   /ws/alerts   — Alert notifications
 ///   /ws/traffic  — Live traffic log for sniffer panel
@@ -149,7 +149,8 @@ async fn handle_status_ws(mut socket: WebSocket, state: AppState) {
     debug!("WebSocket status client disconnected");
 }
 
-/// Handler for /ws/midi — real-time MIDI message stream
+/// Handler for /ws/midi — real-time MIDI stream from the multicast sniffer.
+/// Each event is `{"host_id", "seq", "timestamp_us", "data": [bytes]}`.
 pub async fn ws_midi_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
@@ -161,19 +162,33 @@ async fn handle_midi_ws(mut socket: WebSocket, state: AppState) {
     info!("WebSocket MIDI client connected");
     log_ws_event(&state, "midi client connected");
 
-    // For now, keep the connection alive.
-    // In production, this would subscribe to a broadcast channel
-    // that the MIDI receiver pushes messages into.
+    let mut rx = state.inner.midi_stream_tx.subscribe();
+
     loop {
-        match socket.recv().await {
-            Some(Ok(Message::Close(_))) | None => break,
-            Some(Ok(Message::Ping(data))) => {
-                if socket.send(Message::Pong(data)).await.is_err() {
-                    break;
+        tokio::select! {
+            result = rx.recv() => {
+                match result {
+                    Ok(msg) => {
+                        if socket.send(Message::Text(msg.into())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        debug!("midi stream lagged by {n} packets");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
             }
-            _ => {
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(Message::Ping(data))) => {
+                        if socket.send(Message::Pong(data)).await.is_err() {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
             }
         }
     }
@@ -1,3 +1,6 @@
+mod tail;
+mod watch;
+
 use clap::{Parser, Subcommand};
 use serde_json::Value;
 
@@ -10,12 +13,20 @@ struct Args {
     /// Admin panel URL
     #[arg(short, long, default_value = "http://localhost:8080", global = true)]
     url: String,
+
+    /// Print the API's JSON instead of formatted text
+    #[arg(long, global = true)]
+    json: bool,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Show system status
-    Status,
+    Status {
+        /// Re-poll every N seconds (default 2), highlighting what changed
+        #[arg(long, value_name = "SECS", num_args = 0..=1, default_missing_value = "2")]
+        watch: Option<u64>,
+    },
     /// List connected hosts
     Hosts,
     /// List connected clients
@@ -31,241 +42,500 @@ enum Commands {
         #[arg(long)]
         status: bool,
     },
+    /// Make a specific host primary (the others go to standby)
+    Promote {
+        /// Host ID to promote
+        host_id: u8,
+    },
     /// Show MIDI metrics
     Metrics {
         /// Show system metrics instead of MIDI
         #[arg(long)]
         system: bool,
+        /// Also show a bar of activity per MIDI channel
+        #[arg(long)]
+        channels: bool,
     },
     /// Show alerts
     Alerts,
-    /// Show MIDI pipeline config
-    Pipeline,
+    /// Show MIDI pipeline config, or change which message types pass
+    Pipeline {
+        /// Block a message type (repeatable)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(FILTER_TYPES))]
+        block: Vec<String>,
+        /// Let a blocked message type through again (repeatable)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(FILTER_TYPES))]
+        allow: Vec<String>,
+    },
     /// Input redundancy (dual-controller) status or manual switch
     Input {
         /// Trigger manual input switch (swap active controller)
         #[arg(long)]
         switch: bool,
     },
+    /// Show what each host thinks is sounding (active notes per channel)
+    Journal,
+    /// MIDI panic: All Notes Off + All Sound Off
+    Panic {
+        /// Only this MIDI channel (1-16); default is all channels
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: Option<u8>,
+    },
+    /// Stream live MIDI from the hosts until Ctrl+C (reconnects if dropped)
+    Tail {
+        /// Only this MIDI channel (1-16); system messages are hidden
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: Option<u8>,
+        /// Print each message as raw hex bytes
+        #[arg(long)]
+        raw: bool,
+    },
 }
 
+/// Keys of the pipeline's `message_filter`
+const FILTER_TYPES: [&str; 11] = [
+    "note_on",
+    "note_off",
+    "control_change",
+    "program_change",
+    "pitch_bend",
+    "aftertouch",
+    "channel_pressure",
+    "poly_aftertouch",
+    "sysex",
+    "clock",
+    "realtime",
+];
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
     let client = reqwest::Client::new();
     let base = args.url.trim_end_matches('/');
+    let json = args.json;
 
     match args.command {
-        Commands::Status => {
-            let resp: Value = client
-                .get(format!("{}/api/status", base))
-                .send().await?
-                .json().await?;
-            println!("MIDInet Status");
-            println!("══════════════════════════════");
-            println!("  Version:      {}", resp["version"].as_str().unwrap_or("?"));
-            println!("  Health:       {}/100", resp["health_score"]);
-            println!("  Uptime:       {}s", resp["uptime_seconds"]);
-            println!("  Active host:  {}", resp["active_host"]);
-            println!("  Clients:      {}", resp["connected_clients"]);
-            println!("  MIDI msg/s:   {}", resp["midi_messages_per_sec"]);
-            println!("  CPU:          {}%", resp["cpu_percent"]);
-            println!("  Alerts:       {}", resp["active_alerts"]);
+        Commands::Status { watch: Some(interval) } => {
+            watch::run(&client, base, interval.max(1), json).await?;
+        }
+        Commands::Status { watch: None } => {
+            let resp = get(&client, base, "/api/status").await?;
+            output(&resp, json, print_status);
         }
         Commands::Hosts => {
-            let resp: Value = client
-                .get(format!("{}/api/hosts", base))
-                .send().await?
-                .json().await?;
-            println!("Hosts");
-            println!("══════════════════════════════");
-            if let Some(hosts) = resp["hosts"].as_array() {
-                if hosts.is_empty() {
-                    println!("  No hosts discovered");
-                }
-                for host in hosts {
-                    println!("  {} [{}] {} — {} (uptime: {}s)",
-                        host["name"], host["role"], host["ip"],
-                        host["device_name"], host["uptime_seconds"]);
-                }
-            }
+            let resp = get(&client, base, "/api/hosts").await?;
+            output(&resp, json, print_hosts);
         }
         Commands::Clients => {
-            let resp: Value = client
-                .get(format!("{}/api/clients", base))
-                .send().await?
-                .json().await?;
-            println!("Clients");
-            println!("══════════════════════════════");
-            if let Some(clients) = resp["clients"].as_array() {
-                if clients.is_empty() {
-                    println!("  No clients connected");
-                }
-                for c in clients {
-                    println!("  #{} {} ({}) — latency: {}ms, loss: {}%",
-                        c["id"], c["ip"], c["os"],
-                        c["latency_ms"], c["packet_loss_percent"]);
-                }
-            }
+            let resp = get(&client, base, "/api/clients").await?;
+            output(&resp, json, print_clients);
         }
         Commands::Focus { client_id } => {
             if let Some(_id) = client_id {
-                println!("Focus assignment via CLI not yet implemented");
+                let resp = serde_json::json!({
+                    "success": false,
+                    "error": "Focus assignment via CLI not yet implemented",
+                });
+                output(&resp, json, |resp| println!("{}", resp["error"].as_str().unwrap_or_default()));
             } else {
-                let resp: Value = client
-                    .get(format!("{}/api/focus", base))
-                    .send().await?
-                    .json().await?;
-                println!("Focus");
-                println!("══════════════════════════════");
-                if resp["focus_holder"].is_null() {
-                    println!("  No client holds focus");
-                } else {
-                    println!("  Holder: client #{}", resp["focus_holder"]["client_id"]);
-                    println!("  Since:  {}", resp["focus_holder"]["since"]);
-                }
+                let resp = get(&client, base, "/api/focus").await?;
+                output(&resp, json, print_focus);
             }
         }
         Commands::Failover { status } => {
             if status {
-                let resp: Value = client
-                    .get(format!("{}/api/failover", base))
-                    .send().await?
-                    .json().await?;
-                println!("Failover");
-                println!("══════════════════════════════");
-                println!("  Active host:   {}", resp["active_host"]);
-                println!("  Auto-failover: {}", resp["auto_enabled"]);
-                println!("  Standby OK:    {}", resp["standby_healthy"]);
-                println!("  Total events:  {}", resp["failover_count"]);
-                println!("  Lockout:       {}s", resp["lockout_seconds"]);
+                let resp = get(&client, base, "/api/failover").await?;
+                output(&resp, json, print_failover);
             } else {
-                println!("Triggering manual failover...");
+                if !json {
+                    println!("Triggering manual failover...");
+                }
                 let resp: Value = client
                     .post(format!("{}/api/failover/switch", base))
                     .send().await?
                     .json().await?;
+                output(&resp, json, print_failover_switch);
+            }
+        }
+        Commands::Promote { host_id } => {
+            let resp: Value = client
+                .put(format!("{}/api/hosts/{}/promote", base, host_id))
+                .send().await?
+                .json().await?;
+            output(&resp, json, |resp| {
                 if resp["success"].as_bool().unwrap_or(false) {
-                    println!("  Failover triggered. Active host: {}", resp["active_host"]);
+                    println!("Host {} is now primary", host_id);
+                    print_host_roles(resp);
                 } else {
-                    println!("  Failover failed: {}", resp.get("error").unwrap_or(&Value::Null));
+                    println!("Promote failed: {}", resp.get("error").unwrap_or(&Value::Null));
                 }
-            }
+            });
         }
-        Commands::Metrics { system } => {
+        Commands::Metrics { system, channels } => {
             if system {
-                let resp: Value = client
-                    .get(format!("{}/api/metrics/system", base))
-                    .send().await?
-                    .json().await?;
-                println!("System Metrics");
-                println!("══════════════════════════════");
-                println!("  CPU:        {}%", resp["cpu_percent"]);
-                println!("  CPU temp:   {}°C", resp["cpu_temp_c"]);
-                println!("  Memory:     {}MB / {}MB", resp["memory_used_mb"], resp["memory_total_mb"]);
-                println!("  Disk free:  {}MB", resp["disk_free_mb"]);
-                println!("  Network TX: {} bytes", resp["network_tx_bytes"]);
-                println!("  Network RX: {} bytes", resp["network_rx_bytes"]);
+                let resp = get(&client, base, "/api/metrics/system").await?;
+                output(&resp, json, print_system_metrics);
             } else {
-                let resp: Value = client
-                    .get(format!("{}/api/metrics/midi", base))
-                    .send().await?
-                    .json().await?;
-                println!("MIDI Metrics");
-                println!("══════════════════════════════");
-                println!("  In:         {} msg/s", resp["messages_in_per_sec"]);
-                println!("  Out:        {} msg/s", resp["messages_out_per_sec"]);
-                println!("  Bytes in:   {}/s", resp["bytes_in_per_sec"]);
-                println!("  Bytes out:  {}/s", resp["bytes_out_per_sec"]);
-                println!("  Total msgs: {}", resp["total_messages"]);
-                println!("  Active notes: {}", resp["active_notes"]);
-                println!("  Dropped:    {}", resp["dropped_messages"]);
-                println!("  Peak burst: {} msg/s", resp["peak_burst_rate"]);
+                let resp = get(&client, base, "/api/metrics/midi").await?;
+                output(&resp, json, |resp| print_midi_metrics(resp, channels));
             }
         }
         Commands::Alerts => {
-            let resp: Value = client
-                .get(format!("{}/api/alerts", base))
-                .send().await?
-                .json().await?;
-            println!("Alerts");
-            println!("══════════════════════════════");
-            if let Some(active) = resp["active_alerts"].as_array() {
-                if active.is_empty() {
-                    println!("  No active alerts");
+            let resp = get(&client, base, "/api/alerts").await?;
+            output(&resp, json, print_alerts);
+        }
+        Commands::Pipeline { block, allow } => {
+            let resp = get(&client, base, "/api/pipeline").await?;
+            let mut pipeline = resp.get("pipeline").cloned().unwrap_or(Value::Null);
+
+            let mut update = None;
+            if !block.is_empty() || !allow.is_empty() {
+                for (types, pass) in [(&block, false), (&allow, true)] {
+                    for t in types {
+                        pipeline["message_filter"][t.as_str()] = Value::Bool(pass);
+                    }
                 }
-                for a in active {
-                    println!("  [{:?}] {} — {}", a["severity"], a["title"], a["message"]);
+                let resp: Value = client
+                    .put(format!("{}/api/pipeline", base))
+                    .json(&pipeline)
+                    .send().await?
+                    .json().await?;
+                if !resp["success"].as_bool().unwrap_or(false) {
+                    output(&resp, json, |resp| {
+                        println!("Pipeline update failed: {}", resp.get("error").unwrap_or(&Value::Null));
+                    });
+                    return Ok(());
                 }
+                update = Some(resp);
             }
-        }
-        Commands::Pipeline => {
-            let resp: Value = client
-                .get(format!("{}/api/pipeline", base))
-                .send().await?
-                .json().await?;
-            println!("Pipeline Config");
-            println!("══════════════════════════════");
-            if let Some(p) = resp.get("pipeline") {
-                println!("  Velocity curve:  {}", p["velocity_curve"]);
-                println!("  SysEx passthrough: {}", p["sysex_passthrough"]);
-                println!("  Channel filter:  {:?}", p["channel_filter"]);
+
+            // Normalized: the resulting pipeline, plus the update response if one was made
+            let mut resp = serde_json::json!({ "pipeline": pipeline });
+            if let Some(update) = update {
+                resp["update"] = update;
             }
+            output(&resp, json, print_pipeline);
         }
         Commands::Input { switch } => {
             if switch {
-                println!("Triggering manual input switch...");
+                if !json {
+                    println!("Triggering manual input switch...");
+                }
                 let resp: Value = client
                     .post(format!("{}/api/input-redundancy/switch", base))
                     .send().await?
                     .json().await?;
+                output(&resp, json, print_input_switch);
+            } else {
+                let resp = get(&client, base, "/api/input-redundancy").await?;
+                output(&resp, json, print_input);
+            }
+        }
+        Commands::Journal => {
+            let resp = get(&client, base, "/api/journal").await?;
+            output(&resp, json, print_journal);
+        }
+        Commands::Panic { channel } => {
+            let mut req = client.post(format!("{}/api/panic", base));
+            if let Some(ch) = channel {
+                req = req.query(&[("channel", ch)]);
+            }
+            // Recorded in the admin alert log
+            if let Ok(user) = std::env::var("USER").or_else(|_| std::env::var("USERNAME")) {
+                req = req.query(&[("by", user)]);
+            }
+            let resp: Value = req.send().await?.json().await?;
+            output(&resp, json, |resp| {
+                let target = match channel {
+                    Some(ch) => format!("channel {}", ch),
+                    None => "all channels".to_string(),
+                };
                 if resp["success"].as_bool().unwrap_or(false) {
-                    println!("  Switch complete. Active: {} (input {})",
-                        resp["active_label"], resp["active_input"]);
-                    println!("  Total switches: {}", resp["switch_count"]);
+                    println!("Panic sent on {}", target);
                 } else {
-                    println!("  Switch failed: {}", resp.get("error").unwrap_or(&Value::Null));
+                    println!("Panic failed: {}", resp.get("error").unwrap_or(&Value::Null));
                 }
-            } else {
-                let resp: Value = client
-                    .get(format!("{}/api/input-redundancy", base))
-                    .send().await?
-                    .json().await?;
-                println!("Input Redundancy");
-                println!("══════════════════════════════");
-                let enabled = resp["enabled"].as_bool().unwrap_or(false);
-                println!("  Enabled:     {}", if enabled { "yes" } else { "no" });
-
-                if enabled {
-                    println!("  Active:      {} (input {})",
-                        resp["active_label"], resp["active_input"]);
-                    println!("  Primary:     {} [{}]",
-                        resp["primary"]["device"], resp["primary"]["health"]);
-                    println!("  Secondary:   {} [{}]",
-                        resp["secondary"]["device"], resp["secondary"]["health"]);
-                    println!("  Switches:    {}", resp["switch_count"]);
-
-                    let timeout = resp["activity_timeout_s"].as_u64().unwrap_or(0);
-                    if timeout > 0 {
-                        println!("  Activity TO: {}s", timeout);
-                    } else {
-                        println!("  Activity TO: disabled");
-                    }
+            });
+        }
+        Commands::Tail { channel, raw } => {
+            tail::run(base, channel, raw, json).await?;
+        }
+    }
 
-                    if let Some(last) = resp.get("last_switch") {
-                        if !last.is_null() {
-                            println!("  Last switch: {} → {} ({})",
-                                if last["from_input"].as_u64() == Some(0) { "primary" } else { "secondary" },
-                                if last["to_input"].as_u64() == Some(0) { "primary" } else { "secondary" },
-                                last["trigger"]);
-                        }
-                    }
-                } else {
-                    println!("  (no secondary device configured)");
-                }
+    Ok(())
+}
+
+/// GET an admin API path and parse the JSON body.
+async fn get(client: &reqwest::Client, base: &str, path: &str) -> anyhow::Result<Value> {
+    Ok(client.get(format!("{}{}", base, path)).send().await?.json().await?)
+}
+
+/// Print `resp` as pretty JSON with `--json`, otherwise as text via `text`.
+fn output(resp: &Value, json: bool, text: impl FnOnce(&Value)) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(resp).unwrap_or_default());
+    } else {
+        text(resp);
+    }
+}
+
+// ── Text formatting ──
+
+fn print_status(resp: &Value) {
+    println!("MIDInet Status");
+    println!("══════════════════════════════");
+    for (_, line) in status_lines(resp) {
+        println!("{}", line);
+    }
+}
+
+/// The status body lines, each with the API field it shows.
+fn status_lines(resp: &Value) -> Vec<(&'static str, String)> {
+    vec![
+        ("version", format!("  Version:      {}", resp["version"].as_str().unwrap_or("?"))),
+        ("health_score", format!("  Health:       {}/100", resp["health_score"])),
+        ("uptime_seconds", format!("  Uptime:       {}s", resp["uptime_seconds"])),
+        ("active_host", format!("  Active host:  {}", resp["active_host"])),
+        ("failover_count", format!("  Failovers:    {}", resp["failover_count"])),
+        ("connected_clients", format!("  Clients:      {}", resp["connected_clients"])),
+        ("midi_messages_per_sec", format!("  MIDI msg/s:   {}", resp["midi_messages_per_sec"])),
+        ("cpu_percent", format!("  CPU:          {}%", resp["cpu_percent"])),
+        ("active_alerts", format!("  Alerts:       {}", resp["active_alerts"])),
+    ]
+}
+
+fn print_hosts(resp: &Value) {
+    println!("Hosts");
+    println!("══════════════════════════════");
+    if let Some(hosts) = resp["hosts"].as_array() {
+        if hosts.is_empty() {
+            println!("  No hosts discovered");
+        }
+        for host in hosts {
+            println!("  {} [{}] {} — {} (uptime: {}s)",
+                host["name"], host["role"], host["ip"],
+                host["device_name"], host["uptime_seconds"]);
+        }
+    }
+}
+
+fn print_host_roles(resp: &Value) {
+    for host in resp["hosts"].as_array().into_iter().flatten() {
+        println!("  host {} [{}]", host["id"], host["role"].as_str().unwrap_or("?"));
+    }
+}
+
+fn print_clients(resp: &Value) {
+    println!("Clients");
+    println!("══════════════════════════════");
+    if let Some(clients) = resp["clients"].as_array() {
+        if clients.is_empty() {
+            println!("  No clients connected");
+        }
+        for c in clients {
+            println!("  #{} {} ({}) — latency: {}ms, loss: {}%",
+                c["id"], c["ip"], c["os"],
+                c["latency_ms"], c["packet_loss_percent"]);
+        }
+    }
+}
+
+fn print_focus(resp: &Value) {
+    println!("Focus");
+    println!("══════════════════════════════");
+    if resp["focus_holder"].is_null() {
+        println!("  No client holds focus");
+    } else {
+        println!("  Holder: client #{}", resp["focus_holder"]["client_id"]);
+        println!("  Since:  {}", resp["focus_holder"]["since"]);
+    }
+}
+
+fn print_failover(resp: &Value) {
+    println!("Failover");
+    println!("══════════════════════════════");
+    println!("  Active host:   {}", resp["active_host"]);
+    println!("  Auto-failover: {}", resp["auto_enabled"]);
+    println!("  Standby OK:    {}", resp["standby_healthy"]);
+    println!("  Total events:  {}", resp["failover_count"]);
+    println!("  Lockout:       {}s", resp["lockout_seconds"]);
+}
+
+fn print_failover_switch(resp: &Value) {
+    if resp["success"].as_bool().unwrap_or(false) {
+        println!("  Failover triggered. Active host: {}", resp["active_host"]);
+    } else {
+        println!("  Failover failed: {}", resp.get("error").unwrap_or(&Value::Null));
+    }
+}
+
+fn print_system_metrics(resp: &Value) {
+    println!("System Metrics");
+    println!("══════════════════════════════");
+    println!("  CPU:        {}%", resp["cpu_percent"]);
+    println!("  CPU temp:   {}°C", resp["cpu_temp_c"]);
+    println!("  Memory:     {}MB / {}MB", resp["memory_used_mb"], resp["memory_total_mb"]);
+    println!("  Disk free:  {}MB", resp["disk_free_mb"]);
+    println!("  Network TX: {} bytes", resp["network_tx_bytes"]);
+    println!("  Network RX: {} bytes", resp["network_rx_bytes"]);
+}
+
+fn print_midi_metrics(resp: &Value, channels: bool) {
+    println!("MIDI Metrics");
+    println!("══════════════════════════════");
+    println!("  In:         {} msg/s", resp["messages_in_per_sec"]);
+    println!("  Out:        {} msg/s", resp["messages_out_per_sec"]);
+    println!("  Bytes in:   {}/s", resp["bytes_in_per_sec"]);
+    println!("  Bytes out:  {}/s", resp["bytes_out_per_sec"]);
+    println!("  Total msgs: {}", resp["total_messages"]);
+    println!("  Active notes: {}", resp["active_notes"]);
+    println!("  Dropped:    {}", resp["dropped_messages"]);
+    println!("  Peak burst: {} msg/s", resp["peak_burst_rate"]);
+
+    if channels {
+        let meters = resp["per_channel"].as_array().cloned().unwrap_or_default();
+        let rate = |m: &Value| m["per_sec"].as_f64().unwrap_or(0.0);
+        let peak = meters.iter().map(rate).fold(0.0, f64::max);
+        println!();
+        println!("Per Channel");
+        println!("══════════════════════════════");
+        for m in &meters {
+            println!(
+                "  Ch {:>2}  {:<20} {:>7.1} msg/s",
+                m["channel"],
+                channel_bar(rate(m), peak, 20),
+                rate(m),
+            );
+        }
+    }
+}
+
+fn print_alerts(resp: &Value) {
+    println!("Alerts");
+    println!("══════════════════════════════");
+    if let Some(active) = resp["active_alerts"].as_array() {
+        if active.is_empty() {
+            println!("  No active alerts");
+        }
+        for a in active {
+            println!("  [{:?}] {} — {}", a["severity"], a["title"], a["message"]);
+        }
+    }
+}
+
+fn print_pipeline(resp: &Value) {
+    if let Some(update) = resp.get("update") {
+        if update["persisted"].as_bool().unwrap_or(false) {
+            println!("Pipeline updated and saved");
+        } else {
+            println!("Pipeline updated (in memory, not persisted)");
+        }
+    }
+
+    println!("Pipeline Config");
+    println!("══════════════════════════════");
+    let p = &resp["pipeline"];
+    if !p.is_null() {
+        println!("  Velocity curve:  {}", p["velocity_curve"]);
+        println!("  SysEx passthrough: {}", p["sysex_passthrough"]);
+        println!("  Channel filter:  {:?}", p["channel_filter"]);
+        let blocked: Vec<&str> = FILTER_TYPES
+            .into_iter()
+            .filter(|t| p["message_filter"][*t].as_bool() == Some(false))
+            .collect();
+        if blocked.is_empty() {
+            println!("  Blocked types:   none");
+        } else {
+            println!("  Blocked types:   {}", blocked.join(", "));
+        }
+    }
+}
+
+fn print_input_switch(resp: &Value) {
+    if resp["success"].as_bool().unwrap_or(false) {
+        println!("  Switch complete. Active: {} (input {})",
+            resp["active_label"], resp["active_input"]);
+        println!("  Total switches: {}", resp["switch_count"]);
+    } else {
+        println!("  Switch failed: {}", resp.get("error").unwrap_or(&Value::Null));
+    }
+}
+
+fn print_input(resp: &Value) {
+    println!("Input Redundancy");
+    println!("══════════════════════════════");
+    let enabled = resp["enabled"].as_bool().unwrap_or(false);
+    println!("  Enabled:     {}", if enabled { "yes" } else { "no" });
+
+    if enabled {
+        println!("  Active:      {} (input {})",
+            resp["active_label"], resp["active_input"]);
+        println!("  Primary:     {} [{}]",
+            resp["primary"]["device"], resp["primary"]["health"]);
+        println!("  Secondary:   {} [{}]",
+            resp["secondary"]["device"], resp["secondary"]["health"]);
+        println!("  Switches:    {}", resp["switch_count"]);
+
+        let timeout = resp["activity_timeout_s"].as_u64().unwrap_or(0);
+        if timeout > 0 {
+            println!("  Activity TO: {}s", timeout);
+        } else {
+            println!("  Activity TO: disabled");
+        }
+
+        if let Some(last) = resp.get("last_switch") {
+            if !last.is_null() {
+                println!("  Last switch: {} → {} ({})",
+                    if last["from_input"].as_u64() == Some(0) { "primary" } else { "secondary" },
+                    if last["to_input"].as_u64() == Some(0) { "primary" } else { "secondary" },
+                    last["trigger"]);
             }
         }
+    } else {
+        println!("  (no secondary device configured)");
     }
+}
 
-    Ok(())
+fn print_journal(resp: &Value) {
+    println!("MIDI Journal");
+    println!("══════════════════════════════");
+    let hosts = resp["hosts"].as_array().cloned().unwrap_or_default();
+    if hosts.is_empty() {
+        println!("  No host streams seen yet");
+    }
+    for host in &hosts {
+        println!("  Host {} — {} active notes", host["host_id"], host["active_notes"]);
+        for ch in host["channels"].as_array().into_iter().flatten() {
+            let notes = |key: &str| -> Vec<String> {
+                ch[key].as_array().into_iter().flatten()
+                    .map(|n| format!("{}({})", note_name(n["note"].as_u64().unwrap_or(0)), n["velocity"]))
+                    .collect()
+            };
+            let (active, held) = (notes("notes"), notes("pedal_held"));
+            if active.is_empty() && held.is_empty() {
+                continue;
+            }
+            print!("    Ch {:>2}: {}", ch["channel"], active.join(" "));
+            if !held.is_empty() {
+                print!("  [pedal: {}]", held.join(" "));
+            }
+            println!();
+        }
+    }
+}
+
+/// Note number as a name, middle C (60) = C4.
+fn note_name(note: u64) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[(note % 12) as usize], note as i64 / 12 - 1)
+}
+
+/// A bar of up to `width` blocks for `value`, scaled so `peak` fills it.
+/// Any activity at all shows at least one block.
+fn channel_bar(value: f64, peak: f64, width: usize) -> String {
+    if value <= 0.0 || peak <= 0.0 {
+        return String::new();
+    }
+    let blocks = ((value / peak) * width as f64).round().clamp(1.0, width as f64) as usize;
+    "█".repeat(blocks)
 }
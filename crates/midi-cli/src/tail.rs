@@ -0,0 +1,193 @@
+/// `midinet tail` — live MIDI from the admin panel's `/ws/midi` stream.
+///
+/// Each websocket event carries one sniffed data packet
+/// (`{"host_id", "seq", "timestamp_us", "data": [bytes]}`); its MIDI is
+/// split into messages and printed one per line (or, with `--json`, each
+/// event is printed as one compact JSON line). The connection is
+/// re-established whenever it drops, until Ctrl+C.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::note_name;
+
+/// Longest wait between reconnect attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Stream `/ws/midi` from the admin panel at `base` (http:// or https://)
+/// until Ctrl+C.
+pub async fn run(base: &str, channel: Option<u8>, raw: bool, json: bool) -> anyhow::Result<()> {
+    let url = format!("{}/ws/midi", base.replacen("http", "ws", 1));
+    tokio::select! {
+        _ = stream(&url, channel, raw, json) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    Ok(())
+}
+
+/// Connect, print, and reconnect with backoff whenever the stream ends.
+async fn stream(url: &str, channel: Option<u8>, raw: bool, json: bool) {
+    let mut delay = Duration::from_millis(500);
+    loop {
+        match tokio_tungstenite::connect_async(url).await {
+            Ok((mut ws, _)) => {
+                eprintln!("Connected to {} (Ctrl+C to stop)", url);
+                delay = Duration::from_millis(500);
+                while let Some(msg) = ws.next().await {
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            if let Ok(event) = serde_json::from_str::<Value>(&text) {
+                                if json {
+                                    print_event_json(&event, channel);
+                                } else {
+                                    print_event(&event, channel, raw);
+                                }
+                            }
+                        }
+                        Ok(Message::Close(_)) | Err(_) => break,
+                        _ => {}
+                    }
+                }
+                eprintln!("Connection lost, reconnecting...");
+            }
+            Err(e) => {
+                eprintln!("Cannot connect to {}: {} (retrying in {:.1}s)", url, e, delay.as_secs_f32());
+            }
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+fn print_event(event: &Value, channel: Option<u8>, raw: bool) {
+    let data = event_data(event);
+    for msg in split_messages(&data) {
+        let msg_channel = message_channel(msg);
+        if channel.is_some() && msg_channel != channel {
+            continue;
+        }
+        let line = if raw {
+            msg.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+        } else {
+            describe(msg)
+        };
+        println!("host {}  {}", event["host_id"], line);
+    }
+}
+
+/// The event as one JSON line, skipped when `channel` is set and none of
+/// its messages are on that channel.
+fn print_event_json(event: &Value, channel: Option<u8>) {
+    if let Some(channel) = channel {
+        let data = event_data(event);
+        if !split_messages(&data).iter().any(|msg| message_channel(msg) == Some(channel)) {
+            return;
+        }
+    }
+    println!("{}", event);
+}
+
+fn event_data(event: &Value) -> Vec<u8> {
+    event["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|b| b.as_u64().map(|b| b as u8))
+        .collect()
+}
+
+/// Split a packet's MIDI into whole messages, skipping stray data bytes.
+fn split_messages(data: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let status = data[i];
+        if status & 0x80 == 0 {
+            i += 1;
+            continue;
+        }
+        let len = match status {
+            0xF0 => data[i..].iter().position(|&b| b == 0xF7).map_or(data.len() - i, |end| end + 1),
+            0xF1 | 0xF3 => 2,
+            0xF2 => 3,
+            0xF4..=0xFF => 1,
+            _ => match status & 0xF0 {
+                0xC0 | 0xD0 => 2,
+                _ => 3,
+            },
+        };
+        let end = (i + len).min(data.len());
+        messages.push(&data[i..end]);
+        i = end;
+    }
+    messages
+}
+
+/// MIDI channel (1-16) of a channel message; None for system messages.
+fn message_channel(msg: &[u8]) -> Option<u8> {
+    match msg.first() {
+        Some(&status) if status < 0xF0 => Some((status & 0x0F) + 1),
+        _ => None,
+    }
+}
+
+/// One message as text, e.g. `ch  1  Note On    C4 (60) vel 100`.
+fn describe(msg: &[u8]) -> String {
+    let status = msg[0];
+    let byte = |i: usize| msg.get(i).copied().unwrap_or(0);
+    let note = |i: usize| format!("{} ({})", note_name(byte(i) as u64), byte(i));
+    if status >= 0xF0 {
+        return match status {
+            0xF0 => format!("SysEx      {} bytes", msg.len()),
+            0xF1 => format!("MTC QF     {:02X}", byte(1)),
+            0xF2 => format!("Song Pos   {}", (byte(2) as u16) << 7 | byte(1) as u16),
+            0xF3 => format!("Song Sel   {}", byte(1)),
+            0xF6 => "Tune Request".to_string(),
+            0xF8 => "Clock".to_string(),
+            0xFA => "Start".to_string(),
+            0xFB => "Continue".to_string(),
+            0xFC => "Stop".to_string(),
+            0xFE => "Active Sensing".to_string(),
+            0xFF => "Reset".to_string(),
+            _ => format!("System     {:02X}", status),
+        };
+    }
+    let ch = (status & 0x0F) + 1;
+    let body = match status & 0xF0 {
+        0x90 if byte(2) > 0 => format!("Note On    {} vel {}", note(1), byte(2)),
+        0x80 | 0x90 => format!("Note Off   {} vel {}", note(1), byte(2)),
+        0xA0 => format!("Poly AT    {} {}", note(1), byte(2)),
+        0xB0 => format!("CC {:<7} {}", byte(1), byte(2)),
+        0xC0 => format!("Program    {}", byte(1)),
+        0xD0 => format!("Pressure   {}", byte(1)),
+        _ => format!("Pitch Bend {}", (byte(2) as u16) << 7 | byte(1) as u16),
+    };
+    format!("ch {:>2}  {}", ch, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_is_split_into_messages() {
+        let data = [0x90, 60, 100, 0xF8, 0xF0, 0x7E, 0x7F, 0xF7, 0xC1, 5, 0x42];
+        let messages = split_messages(&data);
+        assert_eq!(messages, vec![&[0x90, 60, 100][..], &[0xF8], &[0xF0, 0x7E, 0x7F, 0xF7], &[0xC1, 5]]);
+        assert_eq!(message_channel(messages[0]), Some(1));
+        assert_eq!(message_channel(messages[1]), None);
+        assert_eq!(message_channel(messages[3]), Some(2));
+    }
+
+    #[test]
+    fn messages_are_described_with_note_names() {
+        assert_eq!(describe(&[0x90, 60, 100]), "ch  1  Note On    C4 (60) vel 100");
+        assert_eq!(describe(&[0x9F, 61, 0]), "ch 16  Note Off   C#4 (61) vel 0");
+        assert_eq!(describe(&[0xB2, 7, 127]), "ch  3  CC 7       127");
+        assert_eq!(describe(&[0xE0, 0, 64]), "ch  1  Pitch Bend 8192");
+        assert_eq!(describe(&[0xFA]), "Start");
+    }
+}
@@ -0,0 +1,155 @@
+/// `midinet status --watch` — re-poll `/api/status` on an interval.
+///
+/// Each poll clears the screen and redraws the status, coloring fields that
+/// changed since the previous poll: red when things got worse (health score
+/// dropped, a failover happened, more alerts), green when they recovered.
+/// If the admin panel can't be reached the last status stays on screen
+/// under a "reconnecting" line and polling carries on. With `--json` each
+/// poll is printed as one compact JSON line instead.
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::{get, status_lines};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/// How a field moved between two status snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Worse,
+    Better,
+}
+
+/// Fields of two `/api/status` snapshots worth highlighting, with the
+/// direction they moved. Counters that only grow (uptime, totals) are
+/// left out; a restart would otherwise read as a change every time.
+pub fn status_changes(prev: &Value, cur: &Value) -> Vec<(&'static str, Change)> {
+    let num = |v: &Value, key: &str| v[key].as_f64();
+    let mut changes = Vec::new();
+
+    if let (Some(a), Some(b)) = (num(prev, "health_score"), num(cur, "health_score")) {
+        if b < a {
+            changes.push(("health_score", Change::Worse));
+        } else if b > a {
+            changes.push(("health_score", Change::Better));
+        }
+    }
+    if let (Some(a), Some(b)) = (num(prev, "failover_count"), num(cur, "failover_count")) {
+        if b > a {
+            changes.push(("failover_count", Change::Worse));
+        }
+    }
+    if prev["active_host"] != cur["active_host"] && !prev["active_host"].is_null() {
+        changes.push(("active_host", Change::Worse));
+    }
+    if let (Some(a), Some(b)) = (num(prev, "active_alerts"), num(cur, "active_alerts")) {
+        if b > a {
+            changes.push(("active_alerts", Change::Worse));
+        } else if b < a {
+            changes.push(("active_alerts", Change::Better));
+        }
+    }
+    if let (Some(a), Some(b)) = (num(prev, "connected_clients"), num(cur, "connected_clients")) {
+        if b < a {
+            changes.push(("connected_clients", Change::Worse));
+        } else if b > a {
+            changes.push(("connected_clients", Change::Better));
+        }
+    }
+    changes
+}
+
+/// Poll `/api/status` every `interval_secs` until Ctrl+C.
+pub async fn run(client: &reqwest::Client, base: &str, interval_secs: u64, json: bool) -> anyhow::Result<()> {
+    tokio::select! {
+        _ = poll(client, base, Duration::from_secs(interval_secs), json) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    Ok(())
+}
+
+async fn poll(client: &reqwest::Client, base: &str, interval: Duration, json: bool) {
+    let mut last: Option<Value> = None;
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match get(client, base, "/api/status").await {
+            Ok(resp) if json => {
+                println!("{}", resp);
+                last = Some(resp);
+            }
+            Ok(resp) => {
+                let changes = last.as_ref().map(|prev| status_changes(prev, &resp)).unwrap_or_default();
+                draw(&resp, &changes, interval);
+                last = Some(resp);
+            }
+            Err(e) if json => eprintln!("Cannot reach {}: {} (reconnecting)", base, e),
+            Err(e) => {
+                match &last {
+                    Some(prev) => draw(prev, &[], interval),
+                    None => print!("{}", CLEAR),
+                }
+                println!();
+                println!("{}  Admin panel unreachable, reconnecting... ({}){}", YELLOW, e, RESET);
+            }
+        }
+    }
+}
+
+fn draw(resp: &Value, changes: &[(&'static str, Change)], interval: Duration) {
+    print!("{}", CLEAR);
+    println!("MIDInet Status (every {}s, Ctrl+C to stop)", interval.as_secs());
+    println!("══════════════════════════════");
+    for (field, line) in status_lines(resp) {
+        match changes.iter().find(|(f, _)| *f == field) {
+            Some((_, Change::Worse)) => println!("{}{}{}", RED, line, RESET),
+            Some((_, Change::Better)) => println!("{}{}{}", GREEN, line, RESET),
+            None => println!("{}", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn health_drop_and_failover_are_flagged_worse() {
+        let prev = json!({ "health_score": 100, "failover_count": 2, "active_host": "primary", "active_alerts": 0, "uptime_seconds": 10 });
+        let cur = json!({ "health_score": 73, "failover_count": 3, "active_host": "standby", "active_alerts": 1, "uptime_seconds": 12 });
+
+        let changes = status_changes(&prev, &cur);
+        assert_eq!(
+            changes,
+            vec![
+                ("health_score", Change::Worse),
+                ("failover_count", Change::Worse),
+                ("active_host", Change::Worse),
+                ("active_alerts", Change::Worse),
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery_is_flagged_better_and_steady_state_is_quiet() {
+        let prev = json!({ "health_score": 73, "failover_count": 3, "active_alerts": 2, "connected_clients": 4 });
+        let cur = json!({ "health_score": 95, "failover_count": 3, "active_alerts": 0, "connected_clients": 5 });
+        assert_eq!(
+            status_changes(&prev, &cur),
+            vec![
+                ("health_score", Change::Better),
+                ("active_alerts", Change::Better),
+                ("connected_clients", Change::Better),
+            ]
+        );
+
+        assert!(status_changes(&cur, &cur).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+//! Integration tests for `--json` output.
+//!
+//! Each test serves a canned admin API response from a throwaway HTTP
+//! server on localhost, runs the real `midi-cli` binary against it and
+//! checks that stdout is valid JSON carrying the API's fields.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::Command;
+
+use serde_json::Value;
+
+/// Serve `body` as the JSON response to a single request; returns the base URL.
+fn serve_once(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        // Read up to the blank line ending the request headers
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+            line.clear();
+        }
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+fn run_json(base: &str, command: &str) -> Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_midi-cli"))
+        .args(["--url", base, "--json", command])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON")
+}
+
+#[test]
+fn status_json_is_valid() {
+    let base = serve_once(
+        r#"{"version":"0.1.0","health_score":98,"uptime_seconds":42,"active_host":1,"connected_clients":3,"midi_messages_per_sec":120,"cpu_percent":12.5,"active_alerts":0}"#,
+    );
+    let resp = run_json(&base, "status");
+    assert_eq!(resp["health_score"], 98);
+    assert_eq!(resp["connected_clients"], 3);
+}
+
+#[test]
+fn hosts_json_is_valid() {
+    let base = serve_once(
+        r#"{"hosts":[{"id":1,"name":"stage-left","role":"primary","ip":"10.0.0.2","device_name":"Keystation","uptime_seconds":600}]}"#,
+    );
+    let resp = run_json(&base, "hosts");
+    assert_eq!(resp["hosts"][0]["name"], "stage-left");
+    assert_eq!(resp["hosts"].as_array().unwrap().len(), 1);
+}
@@ -2,10 +2,14 @@
 ///
 /// Discovers the admin panel URL from mDNS host metadata, registers this
 /// client, and sends periodic heartbeat updates with health metrics.
+/// Heartbeat responses carry commands from the admin (focus, fleet-wide
+/// commands); their acks go back with the next heartbeat.
 
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
+use midi_protocol::client_command::{ClientCommand, CommandAck, QueuedCommand};
 use serde_json::json;
 use tracing::{debug, info, warn};
 
@@ -49,6 +53,8 @@ pub async fn run(state: Arc<ClientState>) {
         "device_ready": *state.device_ready.read().await,
         "connection_state": connection_state_str(&state).await,
         "git_hash": midi_protocol::GIT_HASH,
+        "max_sequence_gap": state.health.counters.max_sequence_gap.load(Ordering::Relaxed),
+        "packets_out_of_order": state.health.counters.packets_out_of_order.load(Ordering::Relaxed),
     });
 
     match http.post(format!("{}/api/clients/register", admin_url))
@@ -66,6 +72,7 @@ pub async fn run(state: Arc<ClientState>) {
 
     // Periodic heartbeat (re-registers automatically if admin has lost track)
     let mut interval = tokio::time::interval(Duration::from_secs(5));
+    let mut command_acks: Vec<CommandAck> = Vec::new();
     loop {
         interval.tick().await;
 
@@ -75,10 +82,14 @@ pub async fn run(state: Arc<ClientState>) {
             "packet_loss_percent": snapshot.packet_loss_percent,
             "midi_rate_in": snapshot.midi_rate_in,
             "midi_rate_out": snapshot.midi_rate_out,
+            "device_send_latency_us": snapshot.device_send_latency_us,
             "device_ready": snapshot.device_ready,
             "device_name": snapshot.device_name,
             "connection_state": format!("{:?}", snapshot.connection_state).to_lowercase(),
             "git_hash": midi_protocol::GIT_HASH,
+            "max_sequence_gap": snapshot.max_sequence_gap,
+            "packets_out_of_order": snapshot.packets_out_of_order,
+            "command_acks": command_acks,
         });
 
         match http.post(format!("{}/api/clients/{}/heartbeat", admin_url, state.client_id))
@@ -87,6 +98,8 @@ pub async fn run(state: Arc<ClientState>) {
             .await
         {
             Ok(resp) => {
+                command_acks.clear();
+
                 // Check if admin panel has lost our registration (e.g. after restart)
                 if let Ok(resp_body) = resp.json::<serde_json::Value>().await {
                     if resp_body.get("success") == Some(&json!(false)) {
@@ -100,6 +113,8 @@ pub async fn run(state: Arc<ClientState>) {
                             "device_ready": snapshot.device_ready,
                             "connection_state": format!("{:?}", snapshot.connection_state).to_lowercase(),
                             "git_hash": midi_protocol::GIT_HASH,
+                            "max_sequence_gap": snapshot.max_sequence_gap,
+                            "packets_out_of_order": snapshot.packets_out_of_order,
                         });
                         let _ = http.post(format!("{}/api/clients/register", admin_url))
                             .json(&register_body)
@@ -122,6 +137,18 @@ pub async fn run(state: Arc<ClientState>) {
                         }
                         _ => {}
                     }
+
+                    // Fleet commands; ack on an immediate extra heartbeat
+                    if let Some(commands) = resp_body.get("commands") {
+                        let commands: Vec<QueuedCommand> =
+                            serde_json::from_value(commands.clone()).unwrap_or_default();
+                        for cmd in &commands {
+                            command_acks.push(execute_command(&state, cmd).await);
+                        }
+                        if !commands.is_empty() {
+                            interval.reset_immediately();
+                        }
+                    }
                 }
             }
             Err(e) => {
@@ -131,6 +158,34 @@ pub async fn run(state: Arc<ClientState>) {
     }
 }
 
+/// Run one admin command against this client.
+async fn execute_command(state: &ClientState, cmd: &QueuedCommand) -> CommandAck {
+    match &cmd.command {
+        ClientCommand::AllNotesOff => {
+            if !*state.device_ready.read().await {
+                return CommandAck::failed(cmd.id, "virtual device not ready");
+            }
+            let panic = midi_protocol::midi_state::panic_messages(None);
+            let device = state.virtual_device.read().await;
+            match state.health.device_send_latency.time(|| device.send(&panic)) {
+                Ok(()) => {
+                    info!(command_id = cmd.id, "All Notes Off sent (admin command)");
+                    CommandAck::ok(cmd.id)
+                }
+                Err(e) => CommandAck::failed(cmd.id, e.to_string()),
+            }
+        }
+        ClientCommand::SetPipeline { pipeline } => {
+            if let Err(e) = pipeline.validate_for_client() {
+                return CommandAck::failed(cmd.id, e);
+            }
+            *state.pipeline_config.write().await = (**pipeline).clone();
+            info!(command_id = cmd.id, "Pipeline config replaced (admin command)");
+            CommandAck::ok(cmd.id)
+        }
+    }
+}
+
 async fn connection_state_str(state: &ClientState) -> String {
     let active = state.active_host_id.read().await;
     let ready = *state.device_ready.read().await;
@@ -7,12 +7,18 @@
 ///
 /// Also provides:
 /// - `run_http_discovery()` — polls admin API when mDNS unavailable
+/// - `run_seed_discovery()` — polls a static JSON host list (VPN/routed links)
 /// - `run_broadcast_discovery()` — UDP broadcast, zero-config, works on all LANs
+///
+/// Every source stores hosts through `track_host()`, which keeps the list
+/// within `network.max_discovered_hosts` by evicting the least recently
+/// seen host. Hosts on `network.host_allowlist` (and the active host) are
+/// never evicted; with `network.host_allowlist_only` nothing else is kept.
 
 use std::collections::HashSet;
 use std::net::{IpAddr, Ipv4Addr, SocketAddrV4};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use mdns_sd::{ServiceDaemon, ServiceEvent};
 use serde::Deserialize;
@@ -24,7 +30,66 @@ use midi_protocol::packets::{DiscoverRequest, DiscoverResponse};
 use midi_protocol::{DEFAULT_DISCOVERY_PORT, MDNS_SERVICE_TYPE, PROTOCOL_VERSION};
 
 use crate::health::TaskPulse;
-use crate::{ClientState, DiscoveredHost};
+use crate::{ClientState, DiscoveredHost, NetworkSection};
+
+// ── Discovered-host list ─────────────────────────────────────────────────
+
+/// What `track_host()` did with a host report.
+#[derive(Debug, PartialEq, Eq)]
+enum Tracked {
+    New,
+    Updated,
+    /// Not on the allowlist, or the list is full of hosts that can't be evicted
+    Ignored,
+}
+
+/// Whether `host` matches an entry of `network.host_allowlist`, by full
+/// name, mDNS instance name or multicast group.
+fn is_allowlisted(host: &DiscoveredHost, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|entry| {
+        host.name == *entry
+            || host.name.split('.').next() == Some(entry.as_str())
+            || host.multicast_group == *entry
+    })
+}
+
+/// Upsert `host` into `hosts`, evicting the least recently seen evictable
+/// host if the list is full. `active` is never evicted.
+fn track_host(
+    hosts: &mut Vec<DiscoveredHost>,
+    host: DiscoveredHost,
+    net: &NetworkSection,
+    active: Option<u8>,
+) -> Tracked {
+    let allowlisted = is_allowlisted(&host, &net.host_allowlist);
+    if net.host_allowlist_only && !allowlisted {
+        return Tracked::Ignored;
+    }
+    if let Some(existing) = hosts.iter_mut().find(|h| h.id == host.id) {
+        *existing = host;
+        return Tracked::Updated;
+    }
+
+    if net.max_discovered_hosts > 0 && hosts.len() >= net.max_discovered_hosts {
+        let victim = hosts
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| Some(h.id) != active && !is_allowlisted(h, &net.host_allowlist))
+            .min_by_key(|(_, h)| h.last_seen)
+            .map(|(i, _)| i);
+        match victim {
+            Some(i) => {
+                let evicted = hosts.remove(i);
+                debug!(host_id = evicted.id, name = %evicted.name, "Discovered-host list full, evicted least recently seen host");
+            }
+            // Only allowlisted hosts left: they win over a newcomer
+            None if !allowlisted => return Tracked::Ignored,
+            None => {}
+        }
+    }
+    hosts.push(host);
+    Tracked::New
+}
 
 pub async fn run(state: Arc<ClientState>, pulse: TaskPulse) -> anyhow::Result<()> {
     let mdns = ServiceDaemon::new()?;
@@ -137,6 +202,7 @@ async fn handle_service_resolved(
         device_name: device_name.clone(),
         protocol_version,
         admin_url: admin_url.clone(),
+        last_seen: Instant::now(),
     };
 
     info!(
@@ -155,7 +221,14 @@ async fn handle_service_resolved(
     // ── Version compatibility check ───────────────────────────────────
 
     if let Some(ver) = protocol_version {
-        if ver != midi_protocol::PROTOCOL_VERSION {
+        if (midi_protocol::MIN_PROTOCOL_VERSION..midi_protocol::PROTOCOL_VERSION).contains(&ver) {
+            info!(
+                host_id = host_id,
+                host_version = ver,
+                our_version = midi_protocol::PROTOCOL_VERSION,
+                "Host uses an older protocol version — assuming its 3ms heartbeat"
+            );
+        } else if ver != midi_protocol::PROTOCOL_VERSION {
             warn!(
                 host_id = host_id,
                 host_version = ver,
@@ -167,13 +240,11 @@ async fn handle_service_resolved(
 
     // ── Store in discovered hosts list ────────────────────────────────
 
-    {
-        let mut hosts = state.discovered_hosts.write().await;
-        if let Some(existing) = hosts.iter_mut().find(|h| h.id == host_id) {
-            *existing = discovered;
-        } else {
-            hosts.push(discovered);
-        }
+    let active = *state.active_host_id.read().await;
+    let tracked = track_host(&mut *state.discovered_hosts.write().await, discovered, &state.config.network, active);
+    if tracked == Tracked::Ignored {
+        debug!(host_id = host_id, "Host not tracked (allowlist / discovered-host limit)");
+        return;
     }
 
     // ── Set active host if none selected yet ──────────────────────────
@@ -235,13 +306,17 @@ async fn handle_service_resolved(
 
 // ── HTTP-based host discovery (fallback when mDNS unavailable) ──────────
 
-/// Host info as returned by the admin panel's `GET /api/hosts` endpoint.
+/// Host info as returned by the admin panel's `GET /api/hosts` endpoint,
+/// or listed in a seed file.
 #[derive(Debug, Deserialize)]
 struct HttpHostInfo {
     id: u8,
+    #[serde(default)]
     name: String,
+    #[serde(default = "default_seed_role")]
     role: String,
     ip: String,
+    #[serde(default)]
     device_name: String,
     #[serde(default)]
     multicast_group: String,
@@ -249,11 +324,101 @@ struct HttpHostInfo {
     data_port: u16,
 }
 
+/// A seed file may leave the role out: the host is then neither assumed
+/// primary nor standby, and heartbeats decide which one is active.
+fn default_seed_role() -> String { "unknown".to_string() }
+
 #[derive(Debug, Deserialize)]
 struct HostsResponse {
     hosts: Vec<HttpHostInfo>,
 }
 
+impl HttpHostInfo {
+    /// The record to store in `discovered_hosts`, or None for an unparseable IP.
+    fn to_discovered(&self, admin_url: Option<&str>) -> Option<DiscoveredHost> {
+        let ip_addr: IpAddr = self.ip.parse().ok()?;
+        Some(DiscoveredHost {
+            id: self.id,
+            name: self.name.clone(),
+            role: self.role.clone(),
+            addresses: HashSet::from([ip_addr]),
+            multicast_group: if self.multicast_group.is_empty() {
+                midi_protocol::DEFAULT_PRIMARY_GROUP.to_string()
+            } else {
+                self.multicast_group.clone()
+            },
+            data_port: if self.data_port == 0 {
+                midi_protocol::DEFAULT_DATA_PORT
+            } else {
+                self.data_port
+            },
+            control_group: None,
+            device_name: self.device_name.clone(),
+            protocol_version: None,
+            admin_url: admin_url.map(str::to_string),
+            last_seen: Instant::now(),
+        })
+    }
+}
+
+/// Merge one host learned over HTTP into the client state: upsert it into
+/// `discovered_hosts`, select it as active if appropriate, and take its
+/// device name when it is the active host. `source` is only for logging.
+async fn apply_http_host(state: &ClientState, host: &HttpHostInfo, discovered: DiscoveredHost, source: &str) {
+    // Upsert into discovered hosts
+    let active = *state.active_host_id.read().await;
+    match track_host(&mut *state.discovered_hosts.write().await, discovered, &state.config.network, active) {
+        Tracked::New => info!(
+            host_id = host.id,
+            name = %host.name,
+            role = %host.role,
+            ip = %host.ip,
+            device = %host.device_name,
+            "Discovered MIDInet host via {}", source
+        ),
+        Tracked::Updated => {}
+        Tracked::Ignored => return,
+    }
+
+    // Set active host if none selected yet
+    {
+        let mut active = state.active_host_id.write().await;
+        match *active {
+            None => {
+                *active = Some(host.id);
+                info!(
+                    host_id = host.id,
+                    role = %host.role,
+                    "Selected as active host ({} discovery)", source
+                );
+            }
+            Some(current) if current != host.id && host.role == "primary" => {
+                info!(
+                    previous = current,
+                    new = host.id,
+                    "Primary host discovered via {}, switching active host", source
+                );
+                *active = Some(host.id);
+            }
+            _ => {}
+        }
+    }
+
+    // Populate device identity
+    let active_id = state.active_host_id.read().await.unwrap_or(1);
+    if host.id == active_id && !host.device_name.is_empty() {
+        let mut identity = state.identity.write().await;
+        if !identity.is_valid() || identity.name != host.device_name {
+            info!(
+                device = %host.device_name,
+                host_id = host.id,
+                "Updating device identity from {}-discovered host", source
+            );
+            identity.name = host.device_name.clone();
+        }
+    }
+}
+
 /// Poll the admin panel API for host information. This is a fallback for
 /// networks where multicast/mDNS doesn't work. Runs indefinitely, polling
 /// every 10 seconds.
@@ -294,90 +459,67 @@ pub async fn run_http_discovery(state: Arc<ClientState>, admin_url: String) {
         }
 
         for host in &body.hosts {
-            let ip_addr: IpAddr = match host.ip.parse() {
-                Ok(a) => a,
-                Err(_) => continue,
-            };
-
-            let mut addresses = HashSet::new();
-            addresses.insert(ip_addr);
-
-            let discovered = DiscoveredHost {
-                id: host.id,
-                name: host.name.clone(),
-                role: host.role.clone(),
-                addresses,
-                multicast_group: if host.multicast_group.is_empty() {
-                    midi_protocol::DEFAULT_PRIMARY_GROUP.to_string()
-                } else {
-                    host.multicast_group.clone()
-                },
-                data_port: if host.data_port == 0 {
-                    midi_protocol::DEFAULT_DATA_PORT
-                } else {
-                    host.data_port
-                },
-                control_group: None,
-                device_name: host.device_name.clone(),
-                protocol_version: None,
-                admin_url: Some(admin_url.clone()),
-            };
-
-            // Upsert into discovered hosts
-            {
-                let mut hosts = state.discovered_hosts.write().await;
-                if let Some(existing) = hosts.iter_mut().find(|h| h.id == host.id) {
-                    *existing = discovered;
-                } else {
-                    info!(
-                        host_id = host.id,
-                        name = %host.name,
-                        role = %host.role,
-                        ip = %host.ip,
-                        device = %host.device_name,
-                        "Discovered MIDInet host via HTTP"
-                    );
-                    hosts.push(discovered);
-                }
+            if let Some(discovered) = host.to_discovered(Some(&admin_url)) {
+                apply_http_host(&state, host, discovered, "HTTP").await;
             }
+        }
+    }
+}
 
-            // Set active host if none selected yet
-            {
-                let mut active = state.active_host_id.write().await;
-                match *active {
-                    None => {
-                        *active = Some(host.id);
-                        info!(
-                            host_id = host.id,
-                            role = %host.role,
-                            "Selected as active host (HTTP discovery)"
-                        );
-                    }
-                    Some(current) if current != host.id && host.role == "primary" => {
-                        info!(
-                            previous = current,
-                            new = host.id,
-                            "Primary host discovered via HTTP, switching active host"
-                        );
-                        *active = Some(host.id);
-                    }
-                    _ => {}
-                }
-            }
+// ── Seed-list discovery (static JSON, for routed/VPN links) ─────────────
 
-            // Populate device identity
-            let active_id = state.active_host_id.read().await.unwrap_or(1);
-            if host.id == active_id {
-                let mut identity = state.identity.write().await;
-                if !identity.is_valid() || identity.name != host.device_name {
-                    info!(
-                        device = %host.device_name,
-                        host_id = host.id,
-                        "Updating device identity from HTTP-discovered host"
-                    );
-                    identity.name = host.device_name.clone();
-                }
+/// Parse a seed file: either `{"hosts": [...]}` (the admin `/api/hosts`
+/// shape) or a bare array of host records. Only `id` and `ip` are required.
+fn parse_seed(body: &str) -> serde_json::Result<Vec<HttpHostInfo>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seed {
+        Wrapped(HostsResponse),
+        Bare(Vec<HttpHostInfo>),
+    }
+    Ok(match serde_json::from_str(body)? {
+        Seed::Wrapped(r) => r.hosts,
+        Seed::Bare(hosts) => hosts,
+    })
+}
+
+/// Fetch the seed URL and parse it into host records.
+async fn fetch_seed(http: &reqwest::Client, url: &str) -> anyhow::Result<Vec<(HttpHostInfo, DiscoveredHost)>> {
+    let body = http.get(url).send().await?.error_for_status()?.text().await?;
+    Ok(parse_seed(&body)?
+        .into_iter()
+        .filter_map(|h| {
+            let discovered = h.to_discovered(None)?;
+            Some((h, discovered))
+        })
+        .collect())
+}
+
+/// Poll a static host list at `seed_url` every `interval`. For installs
+/// where multicast and broadcast can't cross the network (VPNs, routed
+/// cloud links) and there is no admin panel to ask: any web server or
+/// object store serving a JSON file will do.
+pub async fn run_seed_discovery(state: Arc<ClientState>, seed_url: String, interval: Duration) {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap_or_default();
+
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        let hosts = match fetch_seed(&http, &seed_url).await {
+            Ok(hosts) => hosts,
+            Err(e) => {
+                debug!(url = %seed_url, error = %e, "Seed discovery: failed to fetch host list");
+                continue;
             }
+        };
+
+        for (host, discovered) in hosts {
+            apply_http_host(&state, &host, discovered, "seed list").await;
         }
     }
 }
@@ -476,31 +618,33 @@ async fn handle_discover_response(state: &Arc<ClientState>, resp: &DiscoverRespo
         device_name: resp.device_name.clone(),
         protocol_version: Some(resp.protocol_version),
         admin_url: Some(admin_url),
+        last_seen: Instant::now(),
     };
 
     // Upsert into discovered hosts
     {
+        let active = *state.active_host_id.read().await;
         let mut hosts = state.discovered_hosts.write().await;
-        if let Some(existing) = hosts.iter_mut().find(|h| h.id == resp.host_id) {
-            // Only log on first discovery, not updates
-            if existing.addresses != discovered.addresses {
-                info!(
-                    host_id = resp.host_id,
-                    ip = %ip_addr,
-                    device = %resp.device_name,
-                    "Discovered MIDInet host via broadcast"
-                );
-            }
-            *existing = discovered;
-        } else {
-            info!(
+        // Only log on first discovery, not updates
+        let moved = hosts
+            .iter()
+            .any(|h| h.id == resp.host_id && h.addresses != discovered.addresses);
+        match track_host(&mut hosts, discovered, &state.config.network, active) {
+            Tracked::New => info!(
                 host_id = resp.host_id,
                 ip = %ip_addr,
                 device = %resp.device_name,
                 admin_port = resp.admin_port,
                 "Discovered MIDInet host via broadcast"
-            );
-            hosts.push(discovered);
+            ),
+            Tracked::Updated if moved => info!(
+                host_id = resp.host_id,
+                ip = %ip_addr,
+                device = %resp.device_name,
+                "Discovered MIDInet host via broadcast"
+            ),
+            Tracked::Updated => {}
+            Tracked::Ignored => return,
         }
     }
 
@@ -605,3 +749,88 @@ async fn handle_service_removed(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn seed_list_is_fetched_and_parsed_into_hosts() {
+        let seed = r#"[
+            {"id": 1, "ip": "10.8.0.2", "name": "stage-a", "device_name": "APC40 mkII", "multicast_group": "239.69.83.1", "data_port": 5004},
+            {"id": 2, "ip": "10.8.0.3", "role": "standby"},
+            {"id": 3, "ip": "not-an-ip"}
+        ]"#;
+        let app = axum::Router::new().route("/midinet/hosts.json", axum::routing::get(move || async move { seed }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let http = reqwest::Client::new();
+        let hosts = fetch_seed(&http, &format!("http://{}/midinet/hosts.json", addr)).await.unwrap();
+
+        assert_eq!(hosts.len(), 2, "unparseable IP is skipped");
+        let (_, a) = &hosts[0];
+        // No role listed: not assumed primary, so seeds don't fight over active
+        assert_eq!((a.id, a.name.as_str(), a.role.as_str()), (1, "stage-a", "unknown"));
+        assert_eq!(a.addresses, HashSet::from(["10.8.0.2".parse::<IpAddr>().unwrap()]));
+        assert_eq!(a.device_name, "APC40 mkII");
+        assert!(a.admin_url.is_none());
+        let (_, b) = &hosts[1];
+        assert_eq!((b.id, b.role.as_str()), (2, "standby"));
+        assert_eq!(b.multicast_group, midi_protocol::DEFAULT_PRIMARY_GROUP);
+        assert_eq!(b.data_port, midi_protocol::DEFAULT_DATA_PORT);
+
+        // The admin panel's `{"hosts": [...]}` shape is accepted too
+        let wrapped = parse_seed(r#"{"hosts": [{"id": 4, "ip": "10.8.0.9"}]}"#).unwrap();
+        assert_eq!(wrapped[0].id, 4);
+    }
+
+    fn host(id: u8, name: &str, last_seen: Instant) -> DiscoveredHost {
+        DiscoveredHost {
+            id,
+            name: name.to_string(),
+            role: "primary".to_string(),
+            addresses: HashSet::new(),
+            multicast_group: midi_protocol::DEFAULT_PRIMARY_GROUP.to_string(),
+            data_port: midi_protocol::DEFAULT_DATA_PORT,
+            control_group: None,
+            device_name: String::new(),
+            protocol_version: None,
+            admin_url: None,
+            last_seen,
+        }
+    }
+
+    #[test]
+    fn full_host_list_evicts_least_recently_seen_but_never_allowlisted() {
+        let mut net: NetworkSection = toml::from_str("").unwrap();
+        net.max_discovered_hosts = 3;
+        net.host_allowlist = vec!["stage-a".to_string()];
+        let t0 = Instant::now();
+        let at = |s| t0 + Duration::from_secs(s);
+
+        let mut hosts = Vec::new();
+        // The allowlisted host is the stalest of all
+        assert_eq!(track_host(&mut hosts, host(1, "stage-a._midinet._udp.local.", at(0)), &net, None), Tracked::New);
+        assert_eq!(track_host(&mut hosts, host(2, "other-b", at(1)), &net, None), Tracked::New);
+        assert_eq!(track_host(&mut hosts, host(3, "other-c", at(2)), &net, None), Tracked::New);
+        // Host 2 is seen again, so host 3 is now the least recently seen
+        assert_eq!(track_host(&mut hosts, host(2, "other-b", at(3)), &net, None), Tracked::Updated);
+
+        assert_eq!(track_host(&mut hosts, host(4, "other-d", at(4)), &net, None), Tracked::New);
+        let ids: Vec<u8> = hosts.iter().map(|h| h.id).collect();
+        assert_eq!(ids, vec![1, 2, 4]);
+
+        // The active host is kept too; with nothing else evictable, newcomers are dropped
+        net.max_discovered_hosts = 2;
+        hosts.retain(|h| h.id != 4);
+        assert_eq!(track_host(&mut hosts, host(5, "other-e", at(5)), &net, Some(2)), Tracked::Ignored);
+        assert_eq!(hosts.len(), 2);
+
+        // Allowlist-only ignores everything else outright
+        net.host_allowlist_only = true;
+        net.max_discovered_hosts = 0;
+        assert_eq!(track_host(&mut hosts, host(6, "other-f", at(6)), &net, None), Tracked::Ignored);
+    }
+}
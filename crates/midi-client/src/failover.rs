@@ -1,85 +1,210 @@
 /// Failover monitor for the client.
-/// Tracks heartbeats from both primary and standby hosts.
-/// Switches streams when the active host fails.
+/// Tracks heartbeats from every host on the heartbeat group.
+/// Switches streams when the active host fails, or at once when a host
+/// announces a planned stop, to the live host with the best (lowest)
+/// advertised priority; equal priorities go to the lower host_id.
 
-use std::net::Ipv4Addr;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 use tracing::{error, info, warn};
 
-use midi_protocol::packets::HeartbeatPacket;
+use midi_protocol::failover::switch_target;
+use midi_protocol::health::ConnectionState;
+use midi_protocol::multicast;
+use midi_protocol::packets::{HeartbeatPacket, HostStoppingPacket};
 
-use crate::health::TaskPulse;
-use crate::ClientState;
+use crate::health::{HealthCollector, TaskPulse};
+use crate::virtual_device::VirtualMidiDevice;
+use crate::{ClientState, MAX_DETECTION_WINDOW_MS};
 
 struct HostTracker {
-    _host_id: u8,
+    host_id: u8,
+    /// Priority the host last advertised
+    priority: u8,
     last_heartbeat: Option<Instant>,
     last_sequence: u16,
     miss_count: u32,
+    /// Heartbeat interval the host last advertised
+    interval_ms: u16,
+    /// Advertised intervals the host may miss before it counts as lost
+    miss_threshold: u8,
 }
 
 impl HostTracker {
-    fn new(host_id: u8) -> Self {
+    fn new(host_id: u8, miss_threshold: u8) -> Self {
         Self {
-            _host_id: host_id,
+            host_id,
+            priority: host_id,
             last_heartbeat: None,
             last_sequence: 0,
             miss_count: 0,
+            interval_ms: HeartbeatPacket::LEGACY_INTERVAL_MS,
+            miss_threshold,
         }
     }
 
-    fn record_heartbeat(&mut self, seq: u16) {
+    /// Returns true when the advertised interval changed.
+    fn record_heartbeat(&mut self, hb: &HeartbeatPacket) -> bool {
         self.last_heartbeat = Some(Instant::now());
-        self.last_sequence = seq;
+        self.last_sequence = hb.sequence;
+        self.priority = hb.priority;
         self.miss_count = 0;
+        let interval_ms = hb.effective_interval_ms();
+        let changed = interval_ms != self.interval_ms;
+        self.interval_ms = interval_ms;
+        changed
     }
 
-    fn is_alive(&self, timeout_ms: u64) -> bool {
+    /// Detection window for this host: `miss_threshold × advertised
+    /// interval`, with the configured window as the floor (a host backing
+    /// off under congestion must not look dead).
+    fn window_ms(&self, configured_ms: u64) -> u64 {
+        let advertised = (self.interval_ms as u64 * self.miss_threshold as u64).min(MAX_DETECTION_WINDOW_MS);
+        configured_ms.max(advertised)
+    }
+
+    /// The host said it is stopping: dead until its next heartbeat.
+    fn mark_stopped(&mut self) {
+        self.last_heartbeat = None;
+    }
+
+    /// `timeout_ms` is the configured window; see `window_ms`.
+    fn is_alive_at(&self, now: Instant, timeout_ms: u64) -> bool {
         match self.last_heartbeat {
-            Some(last) => last.elapsed().as_millis() < timeout_ms as u128,
+            Some(last) => now.saturating_duration_since(last).as_millis() < self.window_ms(timeout_ms) as u128,
             None => false,
         }
     }
 }
 
+/// (host_id, priority) of every host alive at `now`.
+fn alive_hosts<'a>(
+    trackers: impl IntoIterator<Item = &'a HostTracker>,
+    now: Instant,
+    timeout_ms: u64,
+) -> Vec<(u8, u8)> {
+    trackers
+        .into_iter()
+        .filter(|t| t.is_alive_at(now, timeout_ms))
+        .map(|t| (t.host_id, t.priority))
+        .collect()
+}
+
+/// Whether every host that has been heard has since gone silent. Feeds the
+/// Disconnected connection state.
+#[derive(Default)]
+struct HostLoss {
+    heard_host: bool,
+}
+
+impl HostLoss {
+    /// Record this check's alive hosts and publish the result to `health`.
+    fn update(&mut self, health: &HealthCollector, any_host_alive: bool) -> bool {
+        self.heard_host |= any_host_alive;
+        let lost = self.heard_host && !any_host_alive;
+        health.hosts_lost.store(lost, Ordering::Relaxed);
+        lost
+    }
+}
+
+/// Silences the virtual device once the client has been Disconnected for
+/// `grace`. Arms only after the client has been Connected, fires once per
+/// outage.
+struct DeadManSwitch {
+    grace: Option<Duration>,
+    was_connected: bool,
+    disconnected_since: Option<Instant>,
+    fired: bool,
+}
+
+impl DeadManSwitch {
+    fn new(grace_ms: u64) -> Self {
+        Self {
+            grace: (grace_ms > 0).then(|| Duration::from_millis(grace_ms)),
+            was_connected: false,
+            disconnected_since: None,
+            fired: false,
+        }
+    }
+
+    /// Whether a grace is configured (0 = never silence)
+    fn enabled(&self) -> bool {
+        self.grace.is_some()
+    }
+
+    /// Returns true when the device should be silenced now.
+    fn update(&mut self, now: Instant, connection: ConnectionState) -> bool {
+        let Some(grace) = self.grace else {
+            return false;
+        };
+        if connection != ConnectionState::Disconnected {
+            self.was_connected |= connection == ConnectionState::Connected;
+            self.disconnected_since = None;
+            self.fired = false;
+            return false;
+        }
+        if !self.was_connected || self.fired {
+            return false;
+        }
+        let since = *self.disconnected_since.get_or_insert(now);
+        if now.saturating_duration_since(since) >= grace {
+            self.fired = true;
+            return true;
+        }
+        false
+    }
+}
+
 pub async fn run(state: Arc<ClientState>, pulse: TaskPulse) -> anyhow::Result<()> {
-    let primary_addr: Ipv4Addr = state.config.network.primary_group.parse()?;
+    let primary_addr = multicast::parse_group(&state.config.network.primary_group)?;
     let heartbeat_port = state.config.network.heartbeat_port;
 
     // Create multicast listener for heartbeats
     let socket = {
-        let s = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let s = multicast::new_socket(primary_addr)?;
         s.set_reuse_address(true)?;
 
         #[cfg(any(target_os = "macos", target_os = "freebsd"))]
         s.set_reuse_port(true)?;
 
-        let addr = std::net::SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, heartbeat_port);
-        s.bind(&addr.into())?;
-        s.join_multicast_v4(&primary_addr, &Ipv4Addr::UNSPECIFIED)?;
+        s.bind(&multicast::bind_addr(primary_addr, heartbeat_port).into())?;
+        multicast::join(&s, primary_addr, state.data_interface)?;
         s.set_nonblocking(true)?;
 
         UdpSocket::from_std(s.into())?
     };
 
     // Also try to join standby group
-    let standby_addr: Result<Ipv4Addr, _> = state.config.network.standby_group.parse();
-    if let Ok(standby) = standby_addr {
-        let join_socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
-        let _ = join_socket.join_multicast_v4(&standby, &Ipv4Addr::UNSPECIFIED);
+    if let Ok(standby) = multicast::parse_group(&state.config.network.standby_group) {
+        let join_socket = multicast::new_socket(standby)?;
+        let _ = multicast::join(&join_socket, standby, state.data_interface);
     }
 
-    let mut primary_tracker = HostTracker::new(1);
-    let mut standby_tracker = HostTracker::new(2);
+    let miss_threshold = state.config.failover.miss_threshold.max(1);
+    let mut trackers: HashMap<u8, HostTracker> = HashMap::new();
 
     let mut buf = [0u8; HeartbeatPacket::SIZE + 16]; // extra space for safety
-    let heartbeat_timeout_ms: u64 = 3 * 3; // miss_threshold * interval = 9ms
+    let configured_window_ms = state.config.failover.detection_window_ms;
+    let heartbeat_timeout_ms = state.config.failover.effective_detection_window_ms();
+    if heartbeat_timeout_ms != configured_window_ms {
+        warn!(
+            configured = configured_window_ms,
+            effective = heartbeat_timeout_ms,
+            "failover.detection_window_ms out of bounds, clamped"
+        );
+    }
+
+    info!(
+        detection_window_ms = heartbeat_timeout_ms,
+        "Failover monitor started, listening for heartbeats"
+    );
 
-    info!("Failover monitor started, listening for heartbeats");
+    let mut host_loss = HostLoss::default();
+    let mut dead_man = DeadManSwitch::new(state.config.failover.silence_on_host_loss_ms);
 
     let mut check_interval = tokio::time::interval(std::time::Duration::from_millis(3));
 
@@ -88,11 +213,26 @@ pub async fn run(state: Arc<ClientState>, pulse: TaskPulse) -> anyhow::Result<()
             result = socket.recv_from(&mut buf) => {
                 match result {
                     Ok((len, _addr)) => {
-                        if let Some(hb) = HeartbeatPacket::deserialize(&buf[..len]) {
-                            match hb.host_id {
-                                1 => primary_tracker.record_heartbeat(hb.sequence),
-                                2 => standby_tracker.record_heartbeat(hb.sequence),
-                                _ => {}
+                        if let Some(hb) = HeartbeatPacket::deserialize(&buf[..len])
+                            .filter(|hb| state.config.failover.accepts_host(hb.host_id))
+                        {
+                            let tracker = trackers
+                                .entry(hb.host_id)
+                                .or_insert_with(|| HostTracker::new(hb.host_id, miss_threshold));
+                            if tracker.record_heartbeat(&hb) {
+                                info!(
+                                    host_id = hb.host_id,
+                                    interval_ms = tracker.interval_ms,
+                                    window_ms = tracker.window_ms(heartbeat_timeout_ms),
+                                    "Host heartbeat interval changed"
+                                );
+                            }
+                        } else if let Some(stop) = HostStoppingPacket::deserialize(&buf[..len])
+                            .filter(|p| state.config.failover.accepts_host(p.host_id))
+                        {
+                            info!(host_id = stop.host_id, "Host announced it is stopping");
+                            if let Some(tracker) = trackers.get_mut(&stop.host_id) {
+                                tracker.mark_stopped();
                             }
                         }
                     }
@@ -108,24 +248,36 @@ pub async fn run(state: Arc<ClientState>, pulse: TaskPulse) -> anyhow::Result<()
                 pulse.tick();
                 let current_active = state.active_host_id.read().await.unwrap_or(1);
 
-                let primary_alive = primary_tracker.is_alive(heartbeat_timeout_ms);
-                let standby_alive = standby_tracker.is_alive(heartbeat_timeout_ms);
+                let now = Instant::now();
+                let alive = alive_hosts(trackers.values(), now, heartbeat_timeout_ms);
+
+                host_loss.update(&state.health, !alive.is_empty());
+                if dead_man.enabled() {
+                    let connection = state.health.connection_state(&state).await;
+                    if dead_man.update(now, connection) {
+                        warn!(
+                            grace_ms = state.config.failover.silence_on_host_loss_ms,
+                            "Disconnected from all hosts, silencing virtual device"
+                        );
+                        send_all_notes_off(&state).await;
+                    }
+                }
 
                 // Failover logic
-                if current_active == 1 && !primary_alive && standby_alive {
-                    warn!("Primary host lost! Switching to standby");
-                    *state.active_host_id.write().await = Some(2);
-                    send_all_notes_off(&state).await;
-                    state.needs_reconciliation.store(true, std::sync::atomic::Ordering::Relaxed);
-                    state.health.failover.record();
-                } else if current_active == 2 && !standby_alive && primary_alive {
-                    info!("Standby host lost, primary available — switching back");
-                    *state.active_host_id.write().await = Some(1);
-                    send_all_notes_off(&state).await;
-                    state.needs_reconciliation.store(true, std::sync::atomic::Ordering::Relaxed);
-                    state.health.failover.record();
-                } else if current_active == 1 && !primary_alive && !standby_alive {
-                    warn!("Both hosts unreachable!");
+                match switch_target(current_active, &alive) {
+                    Some(target) => {
+                        let priority = trackers.get(&target).map(|t| t.priority);
+                        warn!(from = current_active, to = target, priority, "Active host lost! Switching hosts");
+                        *state.active_host_id.write().await = Some(target);
+                        send_all_notes_off(&state).await;
+                        state.needs_reconciliation.store(true, std::sync::atomic::Ordering::Relaxed);
+                        state.health.failover.record();
+                    }
+                    None => {
+                        if current_active == 1 && alive.is_empty() {
+                            warn!("All hosts unreachable!");
+                        }
+                    }
                 }
             }
         }
@@ -140,9 +292,255 @@ async fn send_all_notes_off(state: &Arc<ClientState>) {
         return;
     }
     let vdev = state.virtual_device.read().await;
+    silence(vdev.as_ref());
+    info!("Sent All Notes Off on all channels (failover safety)");
+}
+
+fn silence(vdev: &dyn VirtualMidiDevice) {
     for ch in 0..16u8 {
         let _ = vdev.send(&[0xB0 | ch, 120, 0]); // All Sound Off
         let _ = vdev.send(&[0xB0 | ch, 123, 0]); // All Notes Off
     }
-    info!("Sent All Notes Off on all channels (failover safety)");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use midi_protocol::packets::HostRole;
+
+    use crate::virtual_device::mock::MockDevice;
+    use crate::FailoverSection;
+
+    fn section(detection_window_ms: u64) -> FailoverSection {
+        FailoverSection {
+            jitter_buffer_us: 0,
+            detection_window_ms,
+            silence_on_host_loss_ms: 0,
+            accept_host_ids: Vec::new(),
+            reconcile_max_msgs_per_ms: 0,
+            miss_threshold: 3,
+        }
+    }
+
+    /// Trackers where the primary went quiet `gap` ago and the standby is live.
+    fn trackers_with_gap(now: Instant, gap: Duration) -> Option<(HostTracker, HostTracker)> {
+        let mut primary = HostTracker::new(1, 3);
+        let mut standby = HostTracker::new(2, 3);
+        primary.last_heartbeat = Some(now.checked_sub(gap)?);
+        standby.last_heartbeat = Some(now);
+        Some((primary, standby))
+    }
+
+    fn switch_after_gap(window_ms: u64, gap: Duration) -> Option<u8> {
+        let now = Instant::now();
+        let (primary, standby) = trackers_with_gap(now, gap)?;
+        let timeout = section(window_ms).effective_detection_window_ms();
+        switch_target(1, &alive_hosts([&primary, &standby], now, timeout))
+    }
+
+    fn heartbeat(host_id: u8, priority: u8, interval_ms: u16) -> HeartbeatPacket {
+        HeartbeatPacket {
+            host_id,
+            role: HostRole::Standby,
+            sequence: 0,
+            timestamp_us: 0,
+            interval_ms,
+            priority,
+        }
+    }
+
+    /// Trackers that have each heard one heartbeat, advertising `priority`.
+    fn advertised(hosts: &[(u8, u8)]) -> Vec<HostTracker> {
+        hosts
+            .iter()
+            .map(|&(id, priority)| {
+                let mut tracker = HostTracker::new(id, 3);
+                tracker.record_heartbeat(&heartbeat(id, priority, 3));
+                tracker
+            })
+            .collect()
+    }
+
+    #[test]
+    fn window_controls_when_switch_triggers() {
+        let gap = Duration::from_millis(20);
+        // Default 9ms window: a 20ms gap is a dead primary
+        assert_eq!(switch_after_gap(9, gap), Some(2));
+        // Wider window tolerates the same gap
+        assert_eq!(switch_after_gap(50, gap), None);
+        // ...until the gap exceeds it
+        assert_eq!(switch_after_gap(50, Duration::from_millis(60)), Some(2));
+    }
+
+    #[test]
+    fn stopping_notice_switches_without_waiting_for_timeout() {
+        let now = Instant::now();
+        let (mut primary, standby) = trackers_with_gap(now, Duration::ZERO).unwrap();
+        let timeout = section(5000).effective_detection_window_ms();
+        let target = |primary: &HostTracker| switch_target(1, &alive_hosts([primary, &standby], now, timeout));
+        assert_eq!(target(&primary), None);
+
+        // No need to wait out the window once the primary says it is stopping
+        primary.mark_stopped();
+        assert_eq!(target(&primary), Some(2));
+    }
+
+    #[test]
+    fn selection_prefers_lowest_priority_among_three_hosts() {
+        let now = Instant::now();
+        let timeout = section(9).effective_detection_window_ms();
+        // Priorities run against host_id: host 3 is preferred, then 1, then 2
+        let mut trackers = advertised(&[(1, 20), (2, 30), (3, 10)]);
+
+        // Active host alive: stay put, even on a worse-ranked host
+        assert_eq!(switch_target(2, &alive_hosts(&trackers, now, timeout)), None);
+
+        // Active host gone: the best-priority survivor, not the lowest id
+        trackers[1].mark_stopped();
+        assert_eq!(switch_target(2, &alive_hosts(&trackers, now, timeout)), Some(3));
+
+        // Host 3 gone too: host 1 is all that's left
+        trackers[2].mark_stopped();
+        assert_eq!(switch_target(3, &alive_hosts(&trackers, now, timeout)), Some(1));
+
+        trackers[0].mark_stopped();
+        assert_eq!(switch_target(1, &alive_hosts(&trackers, now, timeout)), None);
+    }
+
+    #[test]
+    fn equal_priorities_go_to_the_lower_host_id() {
+        let now = Instant::now();
+        let timeout = section(9).effective_detection_window_ms();
+        let mut trackers = advertised(&[(4, 50), (2, 10), (3, 10)]);
+        trackers[0].mark_stopped();
+        assert_eq!(switch_target(4, &alive_hosts(&trackers, now, timeout)), Some(2));
+
+        // Hosts that don't advertise a priority rank by host_id
+        let mut legacy: Vec<HostTracker> = [1, 2, 3].map(|id| HostTracker::new(id, 3)).into();
+        for tracker in &mut legacy {
+            tracker.last_heartbeat = Some(now);
+        }
+        legacy[0].mark_stopped();
+        assert_eq!(switch_target(1, &alive_hosts(&legacy, now, timeout)), Some(2));
+    }
+
+    #[test]
+    fn window_follows_the_advertised_interval() {
+        let now = Instant::now();
+        let (mut primary, _) = trackers_with_gap(now, Duration::from_millis(20)).unwrap();
+        let timeout = section(9).effective_detection_window_ms();
+        assert!(!primary.is_alive_at(now, timeout));
+
+        // Host backed off to 12ms heartbeats: 3 missed = 36ms window
+        primary.interval_ms = 12;
+        assert_eq!(primary.window_ms(timeout), 36);
+        assert!(primary.is_alive_at(now, timeout));
+
+        // Back to 3ms: the configured window is the floor again
+        primary.interval_ms = 3;
+        assert_eq!(primary.window_ms(timeout), 9);
+        assert!(!primary.is_alive_at(now, timeout));
+
+        // Huge intervals stay within bounds
+        primary.interval_ms = u16::MAX;
+        assert_eq!(primary.window_ms(timeout), crate::MAX_DETECTION_WINDOW_MS);
+    }
+
+    #[test]
+    fn window_is_miss_threshold_times_advertised_interval() {
+        let floor = crate::MIN_DETECTION_WINDOW_MS;
+        let mut tracker = HostTracker::new(1, 4);
+
+        assert!(tracker.record_heartbeat(&heartbeat(1, 1, 10)));
+        assert_eq!(tracker.window_ms(floor), 40);
+        assert!(!tracker.record_heartbeat(&heartbeat(1, 1, 10)));
+
+        // A v1 host (no interval) is assumed to beat every 3ms
+        assert!(tracker.record_heartbeat(&heartbeat(1, 1, 0)));
+        assert_eq!(tracker.window_ms(floor), 12);
+    }
+
+    #[test]
+    fn window_is_clamped_to_bounds() {
+        assert_eq!(section(0).effective_detection_window_ms(), crate::MIN_DETECTION_WINDOW_MS);
+        assert_eq!(section(60_000).effective_detection_window_ms(), crate::MAX_DETECTION_WINDOW_MS);
+        assert_eq!(section(200).effective_detection_window_ms(), 200);
+    }
+
+    #[test]
+    fn default_window_matches_legacy_threshold() {
+        let parsed: FailoverSection = toml::from_str("jitter_buffer_us = 0").unwrap();
+        assert_eq!(parsed.detection_window_ms, 9);
+    }
+
+    #[test]
+    fn heartbeat_timeout_disconnects_and_silences_past_grace() {
+        let health = HealthCollector::new();
+        let device = MockDevice::default();
+        let mut tracker = HostTracker::new(1, 3);
+        tracker.record_heartbeat(&heartbeat(1, 1, 3));
+        let t0 = tracker.last_heartbeat.unwrap();
+        let timeout = section(9).effective_detection_window_ms();
+
+        let mut host_loss = HostLoss::default();
+        let mut dead_man = DeadManSwitch::new(500);
+        let mut check = |ms| {
+            let now = t0 + Duration::from_millis(ms);
+            host_loss.update(&health, !alive_hosts([&tracker], now, timeout).is_empty());
+            // Host 1 active, device up, host still listed by discovery
+            let connection = health.connection_state_of(Some(1), true, 1);
+            if dead_man.update(now, connection) {
+                silence(&device);
+            }
+            connection
+        };
+
+        assert_eq!(check(0), ConnectionState::Connected);
+        // The heartbeats stop: Disconnected once the window passes
+        assert_eq!(check(5), ConnectionState::Connected);
+        assert_eq!(check(20), ConnectionState::Disconnected);
+        assert!(device.sent.lock().unwrap().is_empty(), "silenced within the grace");
+        check(400);
+        assert!(device.sent.lock().unwrap().is_empty(), "silenced within the grace");
+        // Past the grace: silenced once
+        check(530);
+        check(900);
+
+        let sent = device.sent.lock().unwrap();
+        assert_eq!(sent.len(), 32);
+        for ch in 0..16u8 {
+            assert!(sent.contains(&vec![0xB0 | ch, 120, 0]));
+            assert!(sent.contains(&vec![0xB0 | ch, 123, 0]));
+        }
+    }
+
+    #[test]
+    fn dead_man_switch_rearms_and_respects_zero() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let (connected, disconnected) = (ConnectionState::Connected, ConnectionState::Disconnected);
+
+        let mut switch = DeadManSwitch::new(100);
+        // Never connected: not armed
+        assert!(!switch.update(at(500), disconnected));
+        switch.update(at(600), connected);
+        assert!(!switch.update(at(800), disconnected));
+        assert!(switch.update(at(900), disconnected));
+        // Host returns, then a second outage fires again
+        switch.update(at(1000), connected);
+        assert!(!switch.update(at(1010), disconnected));
+        assert!(switch.update(at(1200), disconnected));
+
+        let mut never = DeadManSwitch::new(0);
+        assert!(!never.enabled());
+        never.update(at(0), connected);
+        assert!(!never.update(at(60_000), disconnected));
+    }
+
+    #[test]
+    fn dead_man_switch_is_off_by_default() {
+        let parsed: FailoverSection = toml::from_str("jitter_buffer_us = 0").unwrap();
+        assert_eq!(parsed.silence_on_host_loss_ms, 0);
+    }
 }
@@ -8,18 +8,23 @@
 ///   4. Host forwards feedback → physical controller (LEDs, faders)
 ///   5. On disconnect or explicit release, focus is released
 
-use std::net::{Ipv4Addr, SocketAddrV4};
+
+use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 use tracing::{debug, error, info, warn};
 
+use midi_protocol::framing::MidiFramer;
+use midi_protocol::multicast;
+use midi_protocol::identity::{identity_reply, is_identity_request, parse_sysex_identity};
 use midi_protocol::packets::{FocusAction, FocusPacket, MidiDataPacket};
+use midi_protocol::pipeline::PipelineConfig;
 
 use crate::health::TaskPulse;
+use crate::virtual_device::answer_identity_request;
 use crate::{ClientState, FocusCommand};
 
 /// Whether this client currently holds focus
@@ -68,35 +73,48 @@ async fn run_inner(
     focus_rx: &mut tokio::sync::mpsc::Receiver<FocusCommand>,
 ) -> anyhow::Result<()> {
 
-    let control_group: Ipv4Addr = state.config.network.control_group.parse()?;
+    let control_group = multicast::parse_group(&state.config.network.control_group)?;
     let control_port = state.config.network.control_port;
 
+    let control_ttl = state.config.network.control_ttl;
+    let control_interface = state.control_interface;
+
     // Create socket for sending focus claims
     let send_socket = {
-        let sock = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
-        sock.set_multicast_ttl_v4(1)?;
+        let sock = multicast::new_socket(control_group)?;
+        multicast::set_outgoing(&sock, control_group, control_interface, control_ttl, true)?;
         sock.set_nonblocking(true)?;
-        let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
-        sock.bind(&addr.into())?;
+        sock.bind(&multicast::bind_addr(control_group, 0).into())?;
         UdpSocket::from_std(sock.into())?
     };
 
     // Create socket for receiving focus acks
     let recv_socket = {
-        let sock = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let sock = multicast::new_socket(control_group)?;
         sock.set_reuse_address(true)?;
         #[cfg(any(target_os = "macos", target_os = "freebsd"))]
         sock.set_reuse_port(true)?;
-        let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, control_port);
-        sock.bind(&addr.into())?;
-        sock.join_multicast_v4(&control_group, &Ipv4Addr::UNSPECIFIED)?;
+        sock.bind(&multicast::bind_addr(control_group, control_port).into())?;
+        multicast::join(&sock, control_group, control_interface)?;
         sock.set_nonblocking(true)?;
         UdpSocket::from_std(sock.into())?
     };
 
-    let dest = SocketAddrV4::new(control_group, control_port);
+    let dest = SocketAddr::new(control_group, control_port);
     let mut sequence: u16 = 0;
 
+    // Optional configured SysEx identity, for controllers whose captured reply is wrong
+    let sysex_identity_override = match state.config.midi.sysex_identity.as_deref() {
+        Some(hex) => {
+            let parsed = parse_sysex_identity(hex);
+            if parsed.is_none() {
+                warn!(value = hex, "Invalid midi.sysex_identity, using host-provided identity");
+            }
+            parsed
+        }
+        None => None,
+    };
+
     // Wait for device to be ready before claiming focus
     loop {
         let ready = *state.device_ready.read().await;
@@ -116,6 +134,7 @@ async fn run_inner(
     let mut last_feedback_check = Instant::now();
     let feedback_interval = Duration::from_millis(5); // Check for feedback every 5ms
     let mut feedback_sequence: u16 = 0;
+    let mut feedback_framer = MidiFramer::new();
 
     // Periodically re-claim focus to survive host auto-release (10s timeout)
     let mut last_claim = Instant::now();
@@ -197,8 +216,10 @@ async fn run_inner(
                     send_focus_claim(&send_socket, dest, state.client_id, &mut sequence).await;
                 }
 
-                // If we have focus, periodically check for and send feedback from virtual device
-                if is_focused() && last_feedback_check.elapsed() >= feedback_interval {
+                // Periodically drain the virtual device: Identity Requests are answered
+                // locally; other feedback goes to the host only while we have focus
+                // (or always with follow_activity, letting the host move focus to us)
+                if last_feedback_check.elapsed() >= feedback_interval {
                     last_feedback_check = Instant::now();
 
                     let vdev = state.virtual_device.read().await;
@@ -206,14 +227,49 @@ async fn run_inner(
                     loop {
                         match vdev.receive() {
                             Ok(Some(midi_data)) => {
-                                let processed = pipeline.process(&midi_data);
-                                let send_data = match processed {
-                                    Some(ref data) => data,
-                                    None => {
-                                        debug!(bytes = midi_data.len(), "Feedback MIDI filtered by pipeline");
-                                        continue;
-                                    }
-                                };
+                                // Drivers may hand back several messages, a partial one or
+                                // running-status data; forward only complete messages
+                                let mut messages = feedback_framer.push(&midi_data);
+                                if messages.is_empty() {
+                                    continue;
+                                }
+
+                                // The identity is only looked at for an actual request
+                                let mut answered = false;
+                                if messages.iter().any(|msg| is_identity_request(msg)) {
+                                    let reply = match &sysex_identity_override {
+                                        Some(bytes) => identity_reply(bytes),
+                                        None => state.identity.read().await.sysex_identity_reply(),
+                                    };
+                                    messages.retain(|msg| match answer_identity_request(vdev.as_ref(), &reply, msg) {
+                                        Ok(true) => {
+                                            answered = true;
+                                            false
+                                        }
+                                        Ok(false) => true,
+                                        Err(e) => {
+                                            warn!("Failed to send SysEx Identity Reply: {}", e);
+                                            false
+                                        }
+                                    });
+                                }
+                                if answered {
+                                    debug!("Answered SysEx Identity Request from app");
+                                }
+                                if messages.is_empty() {
+                                    continue;
+                                }
+
+                                if !is_focused() && !state.config.focus.follow_activity {
+                                    debug!(bytes = midi_data.len(), "Feedback MIDI dropped (not focused)");
+                                    continue;
+                                }
+
+                                let send_data = process_feedback_messages(&pipeline, &messages);
+                                if send_data.is_empty() {
+                                    debug!(bytes = midi_data.len(), "Feedback MIDI filtered by pipeline");
+                                    continue;
+                                }
 
                                 let active_host = state.active_host_id.read().await;
                                 if active_host.is_some() {
@@ -221,7 +277,7 @@ async fn run_inner(
                                         sequence: feedback_sequence,
                                         timestamp_us: now_us(),
                                         host_id: 0,
-                                        midi_data: send_data.clone(),
+                                        midi_data: send_data,
                                         journal: None,
                                     };
                                     feedback_sequence = feedback_sequence.wrapping_add(1);
@@ -234,7 +290,7 @@ async fn run_inner(
                                         error!("Failed to send feedback MIDI: {}", e);
                                     } else {
                                         state.health.counters.midi_out.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                                        debug!(bytes = send_data.len(), seq = packet.sequence, "Sent feedback MIDI to host");
+                                        debug!(bytes = packet.midi_data.len(), seq = packet.sequence, "Sent feedback MIDI to host");
                                     }
                                 } else {
                                     debug!("Feedback MIDI ready but no active host");
@@ -255,9 +311,19 @@ async fn run_inner(
     }
 }
 
+/// Run each complete feedback message through the pipeline and concatenate
+/// what survives into one packet payload.
+fn process_feedback_messages(pipeline: &PipelineConfig, messages: &[Vec<u8>]) -> Vec<u8> {
+    messages
+        .iter()
+        .filter_map(|msg| pipeline.process(msg))
+        .flatten()
+        .collect()
+}
+
 async fn send_focus_claim(
     socket: &UdpSocket,
-    dest: SocketAddrV4,
+    dest: SocketAddr,
     client_id: u32,
     sequence: &mut u16,
 ) {
@@ -282,7 +348,7 @@ async fn send_focus_claim(
 
 async fn send_focus_release(
     socket: &UdpSocket,
-    dest: SocketAddrV4,
+    dest: SocketAddr,
     client_id: u32,
     sequence: &mut u16,
 ) {
@@ -304,3 +370,29 @@ async fn send_focus_release(
 
     *sequence = sequence.wrapping_add(1);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concatenated_and_partial_feedback_is_normalized() {
+        let pipeline = PipelineConfig::default();
+        let mut framer = MidiFramer::new();
+
+        // Two LED updates in one read, the second using running status,
+        // followed by the first half of a CC
+        let messages = framer.push(&[0x90, 36, 127, 37, 0, 0xB0, 20]);
+        assert_eq!(
+            process_feedback_messages(&pipeline, &messages),
+            vec![0x90, 36, 127, 0x90, 37, 0]
+        );
+
+        // The next read completes the CC, then continues it via running status
+        let messages = framer.push(&[64, 21, 0]);
+        assert_eq!(
+            process_feedback_messages(&pipeline, &messages),
+            vec![0xB0, 20, 64, 0xB0, 21, 0]
+        );
+    }
+}
@@ -5,9 +5,12 @@
 /// - Atomic MIDI traffic counters
 /// - Packet-loss estimator (rolling window)
 /// - Failover event tracker
+/// - Virtual device send latency (rolling p99)
+/// - One-way network latency relative to its recent minimum (rolling p99)
 /// - `snapshot()` to build a `ClientHealthSnapshot` on demand
 
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use tokio::sync::watch;
@@ -71,8 +74,20 @@ pub struct TrafficCounters {
     pub midi_out: AtomicU64,
     /// Number of received packets (for loss calculation)
     pub packets_received: AtomicU64,
-    /// Number of detected sequence gaps
+    /// Packets missing from sequence gaps (see `midi_protocol::sequence`)
     pub sequence_gaps: AtomicU64,
+    /// Packets the jitter buffer put back in order (cumulative)
+    pub packets_reordered: AtomicU64,
+    /// Packets dropped for missing their playout window (cumulative)
+    pub packets_late_dropped: AtomicU64,
+    /// Packets dropped for failing decryption/authentication (cumulative)
+    pub packets_auth_failed: AtomicU64,
+    /// Lost packets rebuilt from FEC parity (cumulative)
+    pub packets_fec_recovered: AtomicU64,
+    /// Largest single sequence gap seen (cumulative max)
+    pub max_sequence_gap: AtomicU64,
+    /// Packets that arrived behind a newer one (cumulative)
+    pub packets_out_of_order: AtomicU64,
 }
 
 impl TrafficCounters {
@@ -82,10 +97,17 @@ impl TrafficCounters {
             midi_out: AtomicU64::new(0),
             packets_received: AtomicU64::new(0),
             sequence_gaps: AtomicU64::new(0),
+            packets_reordered: AtomicU64::new(0),
+            packets_late_dropped: AtomicU64::new(0),
+            packets_auth_failed: AtomicU64::new(0),
+            packets_fec_recovered: AtomicU64::new(0),
+            max_sequence_gap: AtomicU64::new(0),
+            packets_out_of_order: AtomicU64::new(0),
         }
     }
 
     /// Snapshot and reset, returning (midi_in, midi_out, packets, gaps).
+    /// The jitter buffer, authentication and FEC totals are not reset.
     pub fn snapshot_and_reset(&self) -> (u64, u64, u64, u64) {
         (
             self.midi_in.swap(0, Ordering::Relaxed),
@@ -122,6 +144,108 @@ impl FailoverTracker {
     }
 }
 
+// ── Device send latency ─────────────────────────────────────────────────
+
+/// Number of recent `device.send` timings kept for the p99.
+const SEND_LATENCY_SAMPLES: usize = 256;
+
+/// Rolling window of virtual device send durations.  Recording is two
+/// relaxed atomic stores, so it is safe to call on every MIDI message;
+/// the p99 is computed only when a snapshot is taken.
+pub struct SendLatencyTracker {
+    samples_us: [AtomicU32; SEND_LATENCY_SAMPLES],
+    next: AtomicUsize,
+}
+
+impl SendLatencyTracker {
+    pub fn new() -> Self {
+        Self {
+            samples_us: std::array::from_fn(|_| AtomicU32::new(0)),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Time a device send and record its duration.
+    pub fn time<T>(&self, send: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = send();
+        self.record_us(start.elapsed().as_micros().min(u32::MAX as u128) as u32);
+        result
+    }
+
+    pub fn record_us(&self, us: u32) {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % SEND_LATENCY_SAMPLES;
+        self.samples_us[idx].store(us, Ordering::Relaxed);
+    }
+
+    /// 99th percentile of the recorded window in microseconds (0 if empty).
+    pub fn p99_us(&self) -> u32 {
+        let count = self.next.load(Ordering::Relaxed).min(SEND_LATENCY_SAMPLES);
+        if count == 0 {
+            return 0;
+        }
+        let mut samples: Vec<u32> = self.samples_us[..count]
+            .iter()
+            .map(|s| s.load(Ordering::Relaxed))
+            .collect();
+        samples.sort_unstable();
+        let rank = (count * 99).div_ceil(100).max(1);
+        samples[rank - 1]
+    }
+}
+
+// ── One-way latency ─────────────────────────────────────────────────────
+
+/// Number of recent data packets the latency estimator looks back over.
+const SKEW_SAMPLES: usize = 512;
+
+/// One-way latency estimator that doesn't need synced clocks.
+///
+/// Each sample is `arrival − host timestamp`, which is the real one-way
+/// delay plus the (unknown) offset between the two clocks. The smallest
+/// sample in the window is taken as "no queueing"; the offset cancels out of
+/// `sample − minimum`, leaving how much later than its best case a packet
+/// arrived. Samples from a different host restart the window, since that
+/// host's clock has its own offset.
+pub struct SkewEstimator {
+    host_id: Option<u8>,
+    delays_us: VecDeque<i64>,
+}
+
+impl SkewEstimator {
+    pub fn new() -> Self {
+        Self {
+            host_id: None,
+            delays_us: VecDeque::with_capacity(SKEW_SAMPLES),
+        }
+    }
+
+    /// Record a packet stamped `sent_us` by `host_id` and received at
+    /// `arrived_us` on the local clock.
+    pub fn record(&mut self, host_id: u8, sent_us: u64, arrived_us: u64) {
+        if self.host_id != Some(host_id) {
+            self.host_id = Some(host_id);
+            self.delays_us.clear();
+        }
+        if self.delays_us.len() == SKEW_SAMPLES {
+            self.delays_us.pop_front();
+        }
+        self.delays_us.push_back(arrived_us as i64 - sent_us as i64);
+    }
+
+    /// 99th percentile of the window's delays above its minimum, in
+    /// microseconds (0 if empty).
+    pub fn relative_latency_p99_us(&self) -> u32 {
+        let Some(&min) = self.delays_us.iter().min() else {
+            return 0;
+        };
+        let mut relative: Vec<i64> = self.delays_us.iter().map(|d| d - min).collect();
+        relative.sort_unstable();
+        let rank = (relative.len() * 99).div_ceil(100).max(1);
+        relative[rank - 1].min(u32::MAX as i64) as u32
+    }
+}
+
 // ── Health collector ────────────────────────────────────────────────────
 
 /// Central health state, shared via `Arc` from `ClientState`.
@@ -129,6 +253,10 @@ pub struct HealthCollector {
     pub start_time: Instant,
     pub counters: TrafficCounters,
     pub failover: FailoverTracker,
+    /// Time spent in `VirtualMidiDevice::send` (driver latency)
+    pub device_send_latency: SendLatencyTracker,
+    /// Data packet one-way latency, fed by the receiver
+    pub one_way_latency: std::sync::Mutex<SkewEstimator>,
     /// Task monitors (populated during startup)
     pub monitors: std::sync::Mutex<Vec<TaskMonitor>>,
     /// Computed rates (updated every 500ms by the health server)
@@ -141,6 +269,8 @@ pub struct HealthCollector {
     pub restart_count: AtomicU32,
     /// Host git hash received via admin heartbeat response
     host_git_hash: std::sync::RwLock<String>,
+    /// Set by the failover monitor while every host it has heard is silent
+    pub hosts_lost: AtomicBool,
 }
 
 impl HealthCollector {
@@ -149,6 +279,8 @@ impl HealthCollector {
             start_time: Instant::now(),
             counters: TrafficCounters::new(),
             failover: FailoverTracker::new(),
+            device_send_latency: SendLatencyTracker::new(),
+            one_way_latency: std::sync::Mutex::new(SkewEstimator::new()),
             monitors: std::sync::Mutex::new(Vec::new()),
             midi_rate_in: AtomicU64::new(0),
             midi_rate_out: AtomicU64::new(0),
@@ -156,6 +288,7 @@ impl HealthCollector {
             memory_mb: AtomicU64::new(0),
             restart_count: AtomicU32::new(0),
             host_git_hash: std::sync::RwLock::new(String::new()),
+            hosts_lost: AtomicBool::new(false),
         }
     }
 
@@ -193,6 +326,24 @@ impl HealthCollector {
             .store(f32::to_bits(loss) as u64, Ordering::Relaxed);
     }
 
+    /// The client's connection state, as reported in the snapshot.
+    pub async fn connection_state(&self, state: &ClientState) -> ConnectionState {
+        let active_host_id = *state.active_host_id.read().await;
+        let device_ready = *state.device_ready.read().await;
+        let hosts_discovered = state.discovered_hosts.read().await.len();
+        self.connection_state_of(active_host_id, device_ready, hosts_discovered)
+    }
+
+    pub(crate) fn connection_state_of(&self, active_host_id: Option<u8>, device_ready: bool, hosts_discovered: usize) -> ConnectionState {
+        connection_state(
+            active_host_id,
+            device_ready,
+            hosts_discovered,
+            self.hosts_lost.load(Ordering::Relaxed),
+            self.failover.count.load(Ordering::Relaxed),
+        )
+    }
+
     /// Build a complete health snapshot by reading shared client state.
     pub async fn snapshot(&self, state: &ClientState) -> ClientHealthSnapshot {
         let now_ms = SystemTime::now()
@@ -204,16 +355,7 @@ impl HealthCollector {
         let active_host_id = *state.active_host_id.read().await;
         let hosts = state.discovered_hosts.read().await;
         let device_ready = *state.device_ready.read().await;
-
-        let connection_state = if active_host_id.is_some() && device_ready {
-            ConnectionState::Connected
-        } else if !hosts.is_empty() {
-            ConnectionState::Discovering
-        } else if self.failover.count.load(Ordering::Relaxed) > 0 {
-            ConnectionState::Reconnecting
-        } else {
-            ConnectionState::Disconnected
-        };
+        let connection_state = self.connection_state_of(active_host_id, device_ready, hosts.len());
 
         let active_host = active_host_id.and_then(|id| {
             hosts.iter().find(|h| h.id == id).map(|h| ActiveHostInfo {
@@ -304,6 +446,119 @@ impl HealthCollector {
             version_mismatch,
             host_git_hash,
             client_git_hash,
+            device_send_latency_us: self.device_send_latency.p99_us(),
+            relative_latency_us: self.one_way_latency.lock().unwrap().relative_latency_p99_us(),
+            packets_reordered: self.counters.packets_reordered.load(Ordering::Relaxed),
+            packets_late_dropped: self.counters.packets_late_dropped.load(Ordering::Relaxed),
+            packets_auth_failed: self.counters.packets_auth_failed.load(Ordering::Relaxed),
+            packets_fec_recovered: self.counters.packets_fec_recovered.load(Ordering::Relaxed),
+            max_sequence_gap: self.counters.max_sequence_gap.load(Ordering::Relaxed),
+            packets_out_of_order: self.counters.packets_out_of_order.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Every host that had been heard going silent counts as Disconnected,
+/// whatever the discovery state says.
+fn connection_state(
+    active_host_id: Option<u8>,
+    device_ready: bool,
+    hosts_discovered: usize,
+    hosts_lost: bool,
+    failovers: u32,
+) -> ConnectionState {
+    if hosts_lost {
+        ConnectionState::Disconnected
+    } else if active_host_id.is_some() && device_ready {
+        ConnectionState::Connected
+    } else if hosts_discovered > 0 {
+        ConnectionState::Discovering
+    } else if failovers > 0 {
+        ConnectionState::Reconnecting
+    } else {
+        ConnectionState::Disconnected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::virtual_device::mock::MockDevice;
+    use crate::virtual_device::VirtualMidiDevice;
+
+    #[test]
+    fn send_latency_reflects_injected_delay() {
+        let tracker = SendLatencyTracker::new();
+        let device = MockDevice::with_send_delay(Duration::from_millis(3));
+
+        for _ in 0..20 {
+            tracker.time(|| device.send(&[0x90, 60, 100])).unwrap();
+        }
+
+        let p99 = tracker.p99_us();
+        assert!(p99 >= 3_000, "p99 {}us should include the 3ms driver delay", p99);
+        assert!(p99 < 1_000_000);
+    }
+
+    #[test]
+    fn p99_picks_the_slow_tail() {
+        let tracker = SendLatencyTracker::new();
+        assert_eq!(tracker.p99_us(), 0);
+
+        for _ in 0..99 {
+            tracker.record_us(10);
+        }
+        tracker.record_us(5_000);
+        assert_eq!(tracker.p99_us(), 10);
+
+        tracker.record_us(5_000);
+        assert_eq!(tracker.p99_us(), 5_000);
+    }
+
+    #[test]
+    fn window_rolls_over_old_samples() {
+        let tracker = SendLatencyTracker::new();
+        for _ in 0..SEND_LATENCY_SAMPLES {
+            tracker.record_us(9_000);
         }
+        for _ in 0..SEND_LATENCY_SAMPLES {
+            tracker.record_us(20);
+        }
+        assert_eq!(tracker.p99_us(), 20);
+    }
+
+    #[test]
+    fn relative_latency_ignores_clock_offset() {
+        // Network delays in µs: a 500µs floor with some queueing on top
+        let delays = [500u64, 700, 500, 1_500, 600, 500, 900];
+        let mut in_sync = SkewEstimator::new();
+        let mut offset_ahead = SkewEstimator::new();
+        let mut offset_behind = SkewEstimator::new();
+        for (i, delay) in delays.iter().enumerate() {
+            let sent = 1_000_000 + i as u64 * 10_000;
+            in_sync.record(1, sent, sent + delay);
+            // Host clock 3s ahead of the client: "arrives before it was sent"
+            offset_ahead.record(1, sent + 3_000_000, sent + delay);
+            offset_behind.record(1, sent, sent + delay + 42_000_000);
+        }
+        assert_eq!(in_sync.relative_latency_p99_us(), 1_000);
+        assert_eq!(offset_ahead.relative_latency_p99_us(), 1_000);
+        assert_eq!(offset_behind.relative_latency_p99_us(), 1_000);
+    }
+
+    #[test]
+    fn relative_latency_restarts_for_a_new_host() {
+        let mut estimator = SkewEstimator::new();
+        assert_eq!(estimator.relative_latency_p99_us(), 0);
+        estimator.record(1, 0, 500);
+        estimator.record(1, 0, 2_500);
+        assert_eq!(estimator.relative_latency_p99_us(), 2_000);
+
+        // Host 2's clock is far off from host 1's; its samples stand alone
+        estimator.record(2, 9_000_000, 600);
+        estimator.record(2, 9_000_000, 700);
+        assert_eq!(estimator.relative_latency_p99_us(), 100);
     }
 }
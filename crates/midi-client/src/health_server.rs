@@ -7,18 +7,25 @@
 ///   WS   /ws       — push snapshot every 500ms
 ///   POST /focus/claim   — tell the daemon to claim focus
 ///   POST /focus/release — tell the daemon to release focus
+///   GET  /replay?seconds=N — recent MIDI forwarded to the virtual device
+///   POST /replay  — re-send the last N seconds to the virtual device,
+///                   or write them to a file if `file` is given
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::extract::State;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::routing::{get, post};
 use axum::Json;
-use tracing::{debug, error, info};
+use serde::Deserialize;
+use tracing::{debug, error, info, warn};
 
 use midi_protocol::health::DEFAULT_HEALTH_PORT;
 
+use crate::message_log;
 use crate::ClientState;
 
 /// Shared state for the health server handlers.
@@ -38,6 +45,7 @@ pub async fn run(state: Arc<ClientState>) {
         .route("/ws", get(ws_handler))
         .route("/focus/claim", post(focus_claim_handler))
         .route("/focus/release", post(focus_release_handler))
+        .route("/replay", get(replay_list_handler).post(replay_handler))
         .route("/shutdown", post(shutdown_handler))
         .with_state(health_state);
 
@@ -140,6 +148,92 @@ async fn shutdown_handler(State(state): State<HealthState>) -> impl IntoResponse
     Json(serde_json::json!({ "status": "ok", "action": "shutdown" }))
 }
 
+// ── Replay handlers ─────────────────────────────────────────────────────
+
+fn default_replay_seconds() -> f64 { 10.0 }
+
+#[derive(Deserialize)]
+struct ReplayQuery {
+    #[serde(default = "default_replay_seconds")]
+    seconds: f64,
+}
+
+#[derive(Deserialize)]
+struct ReplayRequest {
+    #[serde(default = "default_replay_seconds")]
+    seconds: f64,
+    /// Write to this file instead of re-sending to the virtual device
+    #[serde(default)]
+    file: Option<String>,
+}
+
+/// Clamp a requested window to what the log actually keeps.
+fn replay_window(state: &HealthState, seconds: f64) -> Duration {
+    let max = state.client.message_log.retention().as_secs_f64();
+    let secs = if seconds.is_finite() { seconds.clamp(0.0, max) } else { max };
+    Duration::from_secs_f64(secs)
+}
+
+async fn replay_list_handler(
+    State(state): State<HealthState>,
+    Query(query): Query<ReplayQuery>,
+) -> impl IntoResponse {
+    let window = replay_window(&state, query.seconds);
+    let messages = state.client.message_log.recent(window);
+    Json(serde_json::json!({
+        "seconds": window.as_secs_f64(),
+        "count": messages.len(),
+        "messages": messages,
+    }))
+}
+
+async fn replay_handler(
+    State(state): State<HealthState>,
+    Json(req): Json<ReplayRequest>,
+) -> impl IntoResponse {
+    let window = replay_window(&state, req.seconds);
+    let messages = state.client.message_log.recent(window);
+
+    let result = match req.file.clone() {
+        Some(path) => {
+            tokio::task::spawn_blocking(move || message_log::write_to_file(&messages, std::path::Path::new(&path)))
+                .await
+                .unwrap_or_else(|e| Err(e.into()))
+        }
+        None => {
+            if !*state.client.device_ready.read().await {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(serde_json::json!({ "error": "virtual device not ready" })),
+                );
+            }
+            message_log::replay_to_device(&messages, &state.client.virtual_device).await
+        }
+    };
+
+    match result {
+        Ok(count) => {
+            info!(
+                count,
+                seconds = window.as_secs_f64(),
+                file = ?req.file,
+                "Replayed recent MIDI via health API"
+            );
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "status": "ok", "action": "replay", "count": count })),
+            )
+        }
+        Err(e) => {
+            warn!("Replay failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+        }
+    }
+}
+
 async fn handle_tray_command(state: &HealthState, cmd: midi_protocol::health::TrayCommand) {
     match cmd {
         midi_protocol::health::TrayCommand::ClaimFocus => {
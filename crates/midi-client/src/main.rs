@@ -8,8 +8,10 @@ mod failover;
 mod focus;
 mod health;
 mod health_server;
+mod message_log;
 mod platform;
 mod receiver;
+mod subscribe;
 mod virtual_device;
 mod watchdog;
 
@@ -21,16 +23,19 @@ use std::path::PathBuf;
 use std::future::Future;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
 use midi_protocol::identity::DeviceIdentity;
+use midi_protocol::interface::{self, InterfaceFallback};
+use midi_protocol::multicast::MulticastInterface;
 use midi_protocol::pipeline::PipelineConfig;
 
 use crate::health::{task_pulse, HealthCollector, TaskPulse};
-use crate::virtual_device::{create_virtual_device, VirtualMidiDevice};
+use crate::message_log::MessageLog;
+use crate::virtual_device::{create_and_clear, create_virtual_device, IdentityStabilizer, VirtualMidiDevice};
 
 #[derive(Parser, Debug)]
 #[command(name = "midi-client", about = "MIDInet client daemon")]
@@ -63,33 +68,135 @@ pub struct NetworkSection {
     pub control_group: String,
     #[serde(default = "default_control_port")]
     pub control_port: u16,
-    #[serde(default = "default_interface")]
+    /// Interface (name or IPv4 address) to receive data and heartbeats on
+    /// (empty = any; an unknown name falls back to any)
+    #[serde(default)]
     pub interface: String,
+    /// Multicast TTL for control-group sends (focus claims, feedback)
+    #[serde(default = "default_control_ttl")]
+    pub control_ttl: u32,
+    /// Interface (name or IPv4 address) to send/join the control group on
+    /// (empty = OS default); resolved like `interface`
+    #[serde(default)]
+    pub control_interface: String,
     /// Admin panel URL for HTTP-based host discovery (fallback when mDNS unavailable)
     #[serde(default)]
     pub admin_url: Option<String>,
+    /// URL of a static JSON host list, polled for discovery where multicast
+    /// and broadcast can't reach (VPN / routed links)
+    #[serde(default)]
+    pub seed_url: Option<String>,
+    /// Seconds between seed-list fetches
+    #[serde(default = "default_seed_interval_s")]
+    pub seed_interval_s: u64,
+    /// Most hosts kept in the discovered list; the least recently seen one
+    /// is dropped to make room (0 = no limit)
+    #[serde(default = "default_max_discovered_hosts")]
+    pub max_discovered_hosts: usize,
+    /// Host names (mDNS instance or full name) or multicast groups that are
+    /// never evicted from the discovered list
+    #[serde(default)]
+    pub host_allowlist: Vec<String>,
+    /// Ignore every host not on `host_allowlist`
+    #[serde(default)]
+    pub host_allowlist_only: bool,
+    /// Pre-shared key the hosts seal data packets with (empty = plaintext).
+    /// Must match the hosts' `network.psk`.
+    #[serde(default)]
+    pub psk: String,
+    /// Ask every discovered host for unicast delivery at this interval in
+    /// ms, for clients behind NAT (0 = off)
+    #[serde(default)]
+    pub unicast_subscribe_ms: u64,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct MidiSection {
     pub device_name: Option<String>,
+    /// Override the SysEx identity bytes answered to Identity Requests,
+    /// as hex (e.g. "47 73 00 19 00 01 00 00 00"). Default: host-provided.
+    #[serde(default)]
+    pub sysex_identity: Option<String>,
+    /// Consecutive 250ms checks the host identity must stay unchanged before
+    /// the virtual device is created (0 or 1 = create on the first identity)
+    #[serde(default = "default_identity_stable_cycles")]
+    pub identity_stable_cycles: u32,
+    /// Send All Sound Off / All Notes Off once the device is created, so
+    /// notes the app still holds from a previous session are cleared
+    #[serde(default)]
+    pub panic_on_startup: bool,
+}
+
+impl Default for MidiSection {
+    fn default() -> Self {
+        Self {
+            device_name: None,
+            sysex_identity: None,
+            identity_stable_cycles: default_identity_stable_cycles(),
+            panic_on_startup: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct FailoverSection {
+    /// Playout delay for incoming data packets, reordering them by host
+    /// timestamp (0 = forward on arrival)
     #[serde(default)]
     pub jitter_buffer_us: u64,
+    /// How long the active host may go without a heartbeat before switching,
+    /// at least; clamped to `MIN_DETECTION_WINDOW_MS..=MAX_DETECTION_WINDOW_MS`.
+    /// Widened per host to `miss_threshold` of its advertised interval.
+    #[serde(default = "default_detection_window_ms")]
+    pub detection_window_ms: u64,
+    /// Heartbeat intervals (as advertised by the host, 3ms for hosts that
+    /// don't advertise one) that may be missed before switching
+    #[serde(default = "default_miss_threshold")]
+    pub miss_threshold: u8,
+    /// Dead-man switch: after the client has been Disconnected (every host
+    /// silent) this long, send All Notes Off to the virtual device so
+    /// nothing drones (0 = never, the default)
+    #[serde(default)]
+    pub silence_on_host_loss_ms: u64,
+    /// Only accept data/heartbeats from these host IDs (empty = any host).
+    /// Guards against a stray host on a shared multicast group.
+    #[serde(default)]
+    pub accept_host_ids: Vec<u8>,
+    /// Pace the state restore after a failover to at most this many
+    /// messages per millisecond (0 = send the whole burst at once)
+    #[serde(default)]
+    pub reconcile_max_msgs_per_ms: u32,
+}
+
+pub const MIN_DETECTION_WINDOW_MS: u64 = 3;
+pub const MAX_DETECTION_WINDOW_MS: u64 = 5000;
+
+impl FailoverSection {
+    /// The detection window actually used, with bounds applied.
+    pub fn effective_detection_window_ms(&self) -> u64 {
+        self.detection_window_ms
+            .clamp(MIN_DETECTION_WINDOW_MS, MAX_DETECTION_WINDOW_MS)
+    }
+
+    /// Whether packets from `host_id` pass the `accept_host_ids` allowlist.
+    pub fn accepts_host(&self, host_id: u8) -> bool {
+        self.accept_host_ids.is_empty() || self.accept_host_ids.contains(&host_id)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct FocusSection {
     #[serde(default = "default_true")]
     pub auto_claim: bool,
+    /// Send feedback to the host even without focus, so a host with
+    /// `focus_follows_activity` can hand focus to this client
+    #[serde(default)]
+    pub follow_activity: bool,
 }
 
 impl Default for FocusSection {
     fn default() -> Self {
-        Self { auto_claim: true }
+        Self { auto_claim: true, follow_activity: false }
     }
 }
 
@@ -99,8 +206,17 @@ fn default_data_port() -> u16 { midi_protocol::DEFAULT_DATA_PORT }
 fn default_heartbeat_port() -> u16 { midi_protocol::DEFAULT_HEARTBEAT_PORT }
 fn default_control_group() -> String { midi_protocol::DEFAULT_CONTROL_GROUP.to_string() }
 fn default_control_port() -> u16 { midi_protocol::DEFAULT_CONTROL_PORT }
-fn default_interface() -> String { "eth0".to_string() }
+fn default_control_ttl() -> u32 { 1 }
+fn default_seed_interval_s() -> u64 { 30 }
+fn default_max_discovered_hosts() -> usize { 32 }
+fn default_identity_stable_cycles() -> u32 { 2 }
 fn default_true() -> bool { true }
+fn default_detection_window_ms() -> u64 {
+    midi_protocol::DEFAULT_HEARTBEAT_MISS_THRESHOLD as u64 * midi_protocol::DEFAULT_HEARTBEAT_INTERVAL_MS
+}
+fn default_miss_threshold() -> u8 {
+    midi_protocol::DEFAULT_HEARTBEAT_MISS_THRESHOLD
+}
 
 /// Discovered host information from mDNS
 #[derive(Debug, Clone)]
@@ -117,6 +233,8 @@ pub struct DiscoveredHost {
     pub device_name: String,
     pub protocol_version: Option<u8>,
     pub admin_url: Option<String>,
+    /// When any discovery source last reported this host
+    pub last_seen: Instant,
 }
 
 /// Commands the tray or health API can send to the focus task.
@@ -147,12 +265,32 @@ pub struct ClientState {
     pub health: Arc<HealthCollector>,
     /// Set to true after a failover to request journal reconciliation
     pub needs_reconciliation: AtomicBool,
+    /// Recent MIDI forwarded to the virtual device (for replay/debugging)
+    pub message_log: MessageLog,
     /// Channel to send focus commands (claim/release) to the focus task
     pub focus_tx: mpsc::Sender<FocusCommand>,
     /// Receiver end — taken once by the focus task on startup
     pub focus_rx: std::sync::Mutex<Option<mpsc::Receiver<FocusCommand>>>,
     /// Cancellation token for graceful shutdown (set by Ctrl+C or /shutdown API)
     pub cancel: CancellationToken,
+    /// The receiver's data socket, published so unicast subscriptions go out
+    /// from the port the hosts must send back to (None until it is bound)
+    pub data_socket: watch::Sender<Option<Arc<tokio::net::UdpSocket>>>,
+    /// Resolved `network.interface` for the data and heartbeat joins
+    pub data_interface: MulticastInterface,
+    /// Resolved `network.control_interface` for the focus sockets
+    pub control_interface: MulticastInterface,
+}
+
+/// Resolve one of the `[network]` interface settings, falling back to the OS
+/// default (and reporting it) when the interface is missing.
+fn resolve_network_interface(key: &str, name: &str) -> interface::ResolvedInterface {
+    let resolved = interface::resolve(name, InterfaceFallback::Any);
+    match &resolved.error {
+        Some(e) => error!("Network misconfiguration: {} (fix network.{} in the config)", e, key),
+        None => info!(setting = key, interface = %name, addr = %resolved.addr, "Multicast interface resolved"),
+    }
+    resolved
 }
 
 #[tokio::main]
@@ -180,15 +318,34 @@ async fn main() -> anyhow::Result<()> {
                 heartbeat_port: default_heartbeat_port(),
                 control_group: default_control_group(),
                 control_port: default_control_port(),
-                interface: default_interface(),
+                interface: String::new(),
+                control_ttl: default_control_ttl(),
+                control_interface: String::new(),
                 admin_url: None,
+                seed_url: None,
+                seed_interval_s: default_seed_interval_s(),
+                max_discovered_hosts: default_max_discovered_hosts(),
+                host_allowlist: Vec::new(),
+                host_allowlist_only: false,
+                psk: String::new(),
+                unicast_subscribe_ms: 0,
             },
             midi: MidiSection::default(),
-            failover: FailoverSection { jitter_buffer_us: 0 },
+            failover: FailoverSection {
+                jitter_buffer_us: 0,
+                detection_window_ms: default_detection_window_ms(),
+                silence_on_host_loss_ms: 0,
+                accept_host_ids: Vec::new(),
+                reconcile_max_msgs_per_ms: 0,
+                miss_threshold: default_miss_threshold(),
+            },
             focus: FocusSection::default(),
         }
     };
 
+    let data_interface = resolve_network_interface("interface", &config.network.interface);
+    let control_interface = resolve_network_interface("control_interface", &config.network.control_interface);
+
     let client_id: u32 = rand_client_id();
     let virtual_device = create_virtual_device();
     let health = Arc::new(HealthCollector::new());
@@ -220,9 +377,13 @@ async fn main() -> anyhow::Result<()> {
         pipeline_config: RwLock::new(PipelineConfig::default()),
         health: Arc::clone(&health),
         needs_reconciliation: AtomicBool::new(false),
+        message_log: MessageLog::default(),
         focus_tx,
         focus_rx: std::sync::Mutex::new(Some(focus_rx)),
         cancel: cancel.clone(),
+        data_socket: watch::channel(None).0,
+        data_interface: data_interface.multicast(),
+        control_interface: control_interface.multicast(),
     });
 
     info!(client_id = client_id, "MIDInet client starting");
@@ -251,6 +412,7 @@ async fn main() -> anyhow::Result<()> {
         let state = Arc::clone(&state);
         let cancel = cancel.clone();
         tokio::spawn(async move {
+            let mut stabilizer = IdentityStabilizer::new(state.config.midi.identity_stable_cycles);
             loop {
                 if cancel.is_cancelled() {
                     return;
@@ -261,6 +423,11 @@ async fn main() -> anyhow::Result<()> {
                 if !identity.is_valid() {
                     continue;
                 }
+                // Wait out discovery races so a transient host's identity
+                // doesn't create a device that is renamed right after
+                if !stabilizer.observe(&identity) {
+                    continue;
+                }
 
                 let device_identity = if let Some(ref override_name) = state.config.midi.device_name {
                     let mut custom = identity.clone();
@@ -272,7 +439,7 @@ async fn main() -> anyhow::Result<()> {
                 drop(identity);
 
                 let mut vdev = state.virtual_device.write().await;
-                match vdev.create(&device_identity) {
+                match create_and_clear(vdev.as_mut(), &device_identity, state.config.midi.panic_on_startup) {
                     Ok(()) => {
                         let host_count = state.discovered_hosts.read().await.len();
                         let active_id = state.active_host_id.read().await;
@@ -330,6 +497,32 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
+    // Spawn seed-list discovery (if seed_url is configured)
+    let seed_discovery_handle = if let Some(ref seed_url) = config.network.seed_url {
+        let state = Arc::clone(&state);
+        let url = seed_url.clone();
+        let interval = Duration::from_secs(config.network.seed_interval_s.max(1));
+        info!(seed_url = %url, "Seed-list host discovery enabled");
+        Some(tokio::spawn(async move {
+            discovery::run_seed_discovery(state, url, interval).await;
+        }))
+    } else {
+        None
+    };
+
+    // Spawn unicast subscription (if unicast_subscribe_ms is configured)
+    let subscribe_handle = if config.network.unicast_subscribe_ms > 0 {
+        let state = Arc::clone(&state);
+        let interval = Duration::from_millis(config.network.unicast_subscribe_ms);
+        Some(tokio::spawn(async move {
+            if let Err(e) = subscribe::run(state, interval).await {
+                error!("Unicast subscription error: {}", e);
+            }
+        }))
+    } else {
+        None
+    };
+
     // Spawn broadcast discovery (always — zero-config, works on all LANs)
     let broadcast_discovery_handle = {
         let state = Arc::clone(&state);
@@ -376,6 +569,12 @@ async fn main() -> anyhow::Result<()> {
     if let Some(h) = http_discovery_handle {
         h.abort();
     }
+    if let Some(h) = seed_discovery_handle {
+        h.abort();
+    }
+    if let Some(h) = subscribe_handle {
+        h.abort();
+    }
     broadcast_discovery_handle.abort();
 
     Ok(())
@@ -0,0 +1,229 @@
+/// Rolling log of the MIDI recently forwarded to the virtual device.
+///
+/// When a stuck note is reported, the last few seconds of traffic can be
+/// replayed into the virtual device (or dumped to a file) so the exact
+/// sequence that led to it can be reproduced against a test app.
+///
+/// Bounded both by message count and by age; oldest entries are dropped first.
+/// Each entry is one packet's MIDI as forwarded in a single device send, so
+/// the receiver takes the lock once per packet, and evicted entries lend
+/// their buffers to new ones: a full log records without allocating.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::virtual_device::VirtualMidiDevice;
+
+/// Maximum number of messages kept in the log
+pub const DEFAULT_LOG_CAPACITY: usize = 8192;
+
+/// How far back the log reaches
+pub const DEFAULT_LOG_RETENTION: Duration = Duration::from_secs(60);
+
+/// A single logged MIDI message.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggedMessage {
+    #[serde(skip)]
+    pub at: Instant,
+    /// Wall-clock receive time, microseconds since UNIX epoch
+    pub timestamp_us: u64,
+    pub data: Vec<u8>,
+}
+
+pub struct MessageLog {
+    entries: Mutex<VecDeque<LoggedMessage>>,
+    capacity: usize,
+    retention: Duration,
+    /// Wall-clock time (µs since UNIX epoch) at `started`, so entries are
+    /// stamped without reading the system clock each time
+    started: Instant,
+    started_us: u64,
+}
+
+impl MessageLog {
+    pub fn new(capacity: usize, retention: Duration) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            capacity: capacity.max(1),
+            retention,
+            started: Instant::now(),
+            started_us: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_micros() as u64,
+        }
+    }
+
+    /// Record a message that was just forwarded to the virtual device.
+    pub fn record(&self, data: &[u8]) {
+        self.record_at(Instant::now(), data);
+    }
+
+    fn record_at(&self, at: Instant, data: &[u8]) {
+        let timestamp_us = match at.checked_duration_since(self.started) {
+            Some(since) => self.started_us + since.as_micros() as u64,
+            None => self.started_us.saturating_sub(self.started.duration_since(at).as_micros() as u64),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        let mut spare = None;
+        while entries.len() >= self.capacity {
+            spare = entries.pop_front();
+        }
+        while entries
+            .front()
+            .is_some_and(|e| at.saturating_duration_since(e.at) > self.retention)
+        {
+            spare = entries.pop_front();
+        }
+        let mut buf = spare.map(|e| e.data).unwrap_or_default();
+        buf.clear();
+        buf.extend_from_slice(data);
+        entries.push_back(LoggedMessage {
+            at,
+            timestamp_us,
+            data: buf,
+        });
+    }
+
+    /// Messages received within the last `window`, oldest first.
+    pub fn recent(&self, window: Duration) -> Vec<LoggedMessage> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .filter(|e| now.saturating_duration_since(e.at) <= window)
+            .cloned()
+            .collect()
+    }
+
+    pub fn retention(&self) -> Duration {
+        self.retention
+    }
+}
+
+impl Default for MessageLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_CAPACITY, DEFAULT_LOG_RETENTION)
+    }
+}
+
+/// Re-send logged messages to the virtual device in their original order
+/// and with their original spacing. Returns the number of messages sent.
+///
+/// The device lock is taken per message and never held while waiting, so
+/// a long replay can't stall live traffic behind a queued device rebuild.
+pub async fn replay_to_device<D: VirtualMidiDevice + ?Sized>(
+    messages: &[LoggedMessage],
+    device: &RwLock<Box<D>>,
+) -> anyhow::Result<usize> {
+    let (Some(first), start) = (messages.first(), tokio::time::Instant::now()) else {
+        return Ok(0);
+    };
+    for msg in messages {
+        tokio::time::sleep_until(start + msg.at.saturating_duration_since(first.at)).await;
+        device.read().await.send(&msg.data)?;
+    }
+    Ok(messages.len())
+}
+
+/// Write logged messages to a text file, one per line:
+/// `<offset ms from first message> <hex bytes>`.
+/// Returns the number of messages written.
+pub fn write_to_file(messages: &[LoggedMessage], path: &Path) -> anyhow::Result<usize> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let start = messages.first().map(|m| m.at);
+
+    for msg in messages {
+        let offset = start
+            .map(|s| msg.at.saturating_duration_since(s))
+            .unwrap_or_default();
+        let hex: Vec<String> = msg.data.iter().map(|b| format!("{:02X}", b)).collect();
+        writeln!(file, "{:.3} {}", offset.as_secs_f64() * 1000.0, hex.join(" "))?;
+    }
+
+    file.flush()?;
+    Ok(messages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::virtual_device::mock::MockDevice;
+
+    #[tokio::test]
+    async fn replay_re_emits_messages_in_order() {
+        let log = MessageLog::default();
+        let messages: [&[u8]; 4] = [
+            &[0x90, 60, 100],
+            &[0xB0, 7, 90],
+            &[0x90, 64, 80],
+            &[0x80, 60, 0],
+        ];
+        for m in messages {
+            log.record(m);
+        }
+
+        let device = RwLock::new(Box::new(MockDevice::default()));
+        let sent = replay_to_device(&log.recent(Duration::from_secs(10)), &device).await.unwrap();
+
+        assert_eq!(sent, 4);
+        let device = device.read().await;
+        let got = device.sent.lock().unwrap();
+        let expected: Vec<Vec<u8>> = messages.iter().map(|m| m.to_vec()).collect();
+        assert_eq!(*got, expected);
+    }
+
+    #[tokio::test]
+    async fn replay_keeps_the_recorded_spacing() {
+        let log = MessageLog::default();
+        let start = log.started;
+        log.record_at(start, &[0x90, 60, 100]);
+        log.record_at(start + Duration::from_millis(60), &[0x80, 60, 0]);
+        let recent = log.recent(Duration::from_secs(10));
+        assert_eq!(recent[1].timestamp_us - recent[0].timestamp_us, 60_000);
+
+        let device = RwLock::new(Box::new(MockDevice::default()));
+        let began = Instant::now();
+        let replay = replay_to_device(&recent, &device);
+        tokio::pin!(replay);
+        // Between messages the device is free for a rebuild
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut replay).await.is_err());
+        drop(tokio::time::timeout(Duration::from_millis(5), device.write()).await.unwrap());
+        assert_eq!(replay.await.unwrap(), 2);
+        assert!(began.elapsed() >= Duration::from_millis(60), "replayed faster than recorded");
+        assert_eq!(device.read().await.sent.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn recent_excludes_messages_outside_window() {
+        let log = MessageLog::default();
+        let now = Instant::now();
+        let Some(old) = now.checked_sub(Duration::from_secs(20)) else {
+            return;
+        };
+        log.record_at(old, &[0x90, 60, 100]);
+        log.record_at(now, &[0x80, 60, 0]);
+
+        let recent = log.recent(Duration::from_secs(5));
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].data, vec![0x80, 60, 0]);
+    }
+
+    #[test]
+    fn log_is_bounded_by_capacity() {
+        let log = MessageLog::new(3, DEFAULT_LOG_RETENTION);
+        for note in 0u8..5 {
+            log.record(&[0x90, note, 100]);
+        }
+
+        let notes: Vec<u8> = log.recent(Duration::from_secs(10)).iter().map(|m| m.data[1]).collect();
+        assert_eq!(notes, vec![2, 3, 4]);
+    }
+}
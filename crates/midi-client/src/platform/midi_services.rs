@@ -27,111 +27,17 @@ use std::sync::{Arc, Mutex};
 #[cfg(target_os = "windows")]
 use bindings::Windows::Devices::Midi2 as midi2;
 
+#[cfg(target_os = "windows")]
+use super::ump::UmpEncoder;
+
 use crate::virtual_device::VirtualMidiDevice;
 use midi_protocol::identity::DeviceIdentity;
 use tracing::{debug, info, warn};
 
 // ── MIDI 1.0 → UMP Conversion ──
 
-/// Convert MIDI 1.0 bytes to UMP word(s).
-/// Returns a Vec of (word_count, word0, word1) tuples.
-/// Type 2 (Channel Voice) = 1 word. Type 3 (SysEx) = 2 words.
-#[cfg(target_os = "windows")]
-fn midi1_to_ump_messages(data: &[u8]) -> Vec<(u8, u32, u32)> {
-    if data.is_empty() {
-        return Vec::new();
-    }
-
-    let mut result = Vec::new();
-    let mut i = 0;
-
-    while i < data.len() {
-        let status = data[i];
-
-        match status {
-            // Channel Voice Messages (0x80..=0xEF) → UMP Type 2, 1 word
-            0x80..=0xBF | 0xE0..=0xEF => {
-                if i + 2 < data.len() {
-                    let word0 = 0x2000_0000u32
-                        | ((status as u32) << 16)
-                        | ((data[i + 1] as u32) << 8)
-                        | (data[i + 2] as u32);
-                    result.push((1, word0, 0));
-                    i += 3;
-                } else {
-                    break;
-                }
-            }
-            0xC0..=0xDF => {
-                if i + 1 < data.len() {
-                    let word0 = 0x2000_0000u32
-                        | ((status as u32) << 16)
-                        | ((data[i + 1] as u32) << 8);
-                    result.push((1, word0, 0));
-                    i += 2;
-                } else {
-                    break;
-                }
-            }
-            // System Exclusive (0xF0) → UMP Type 3, 2 words per packet
-            0xF0 => {
-                let start = i + 1;
-                let end = data[start..].iter().position(|&b| b == 0xF7)
-                    .map(|p| start + p)
-                    .unwrap_or(data.len());
-                let sysex_data = &data[start..end];
-
-                let chunks: Vec<&[u8]> = sysex_data.chunks(6).collect();
-                let total_chunks = chunks.len().max(1);
-
-                for (idx, chunk) in chunks.iter().enumerate() {
-                    let sysex_status: u32 = if total_chunks == 1 {
-                        0x00 // Complete
-                    } else if idx == 0 {
-                        0x01 // Start
-                    } else if idx == total_chunks - 1 {
-                        0x03 // End
-                    } else {
-                        0x02 // Continue
-                    };
-
-                    let num_bytes = chunk.len() as u32;
-                    // Type 3, group 0, status | num_bytes in upper nibbles
-                    let word0 = 0x3000_0000u32
-                        | (sysex_status << 20)
-                        | (num_bytes << 16);
-                    let mut word1 = 0u32;
-                    for (j, &b) in chunk.iter().enumerate() {
-                        match j {
-                            0 => word1 |= (b as u32) << 24,
-                            1 => word1 |= (b as u32) << 16,
-                            2 => word1 |= (b as u32) << 8,
-                            3 => word1 |= b as u32,
-                            // bytes 4-5 go into lower bits of word0
-                            4 => { /* word0 lower bits - simplified */ }
-                            5 => { /* word0 lower bits - simplified */ }
-                            _ => {}
-                        }
-                    }
-                    result.push((2, word0, word1));
-                }
-
-                i = if end < data.len() { end + 1 } else { end };
-            }
-            // System Real-Time (0xF8..=0xFF) → UMP Type 1, 1 word
-            0xF8..=0xFF => {
-                let word0 = 0x1000_0000u32 | ((status as u32) << 16);
-                result.push((1, word0, 0));
-                i += 1;
-            }
-            _ => {
-                i += 1;
-            }
-        }
-    }
-
-    result
-}
+// MIDI 1.0 bytes → UMP is handled by the stateful `UmpEncoder`
+// (running status, messages split across sends) in `platform::ump`.
 
 /// Convert UMP words (from FillWords) back to MIDI 1.0 bytes.
 #[cfg(target_os = "windows")]
@@ -203,6 +109,9 @@ pub struct MidiServicesDevice {
     _virtual_device: Option<midi2::Endpoints::Virtual::MidiVirtualDevice>,
     #[cfg(target_os = "windows")]
     feedback_buffer: Arc<Mutex<Vec<Vec<u8>>>>,
+    /// MIDI 1.0 → UMP encoder; keeps running status + partial messages between sends
+    #[cfg(target_os = "windows")]
+    encoder: Mutex<UmpEncoder>,
     #[cfg(target_os = "windows")]
     _message_token: Option<windows::Foundation::EventRegistrationToken>,
     /// COM bootstrapper that installs Detours hooks for WinRT class activation.
@@ -237,6 +146,8 @@ impl MidiServicesDevice {
             #[cfg(target_os = "windows")]
             feedback_buffer: Arc::new(Mutex::new(Vec::new())),
             #[cfg(target_os = "windows")]
+            encoder: Mutex::new(UmpEncoder::new()),
+            #[cfg(target_os = "windows")]
             _message_token: None,
             #[cfg(target_os = "windows")]
             _initializer: None,
@@ -473,7 +384,11 @@ impl VirtualMidiDevice for MidiServicesDevice {
                 None => return Ok(()),
             };
 
-            let messages = midi1_to_ump_messages(data);
+            let messages = self
+                .encoder
+                .lock()
+                .map_err(|_| anyhow::anyhow!("UMP encoder lock poisoned"))?
+                .encode(data);
             for &(word_count, word0, word1) in &messages {
                 let _result = match word_count {
                     1 => connection.SendSingleMessageWords(0, word0),
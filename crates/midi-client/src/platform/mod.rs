@@ -10,3 +10,6 @@ pub mod te_virtual_midi;
 pub mod midi_services;
 #[cfg(target_os = "windows")]
 pub mod windows;
+
+#[cfg(any(target_os = "windows", test))]
+pub mod ump;
@@ -0,0 +1,185 @@
+/// MIDI 1.0 byte stream → UMP (Universal MIDI Packet) conversion.
+///
+/// Stateful so it copes with real-world streams: running status, messages
+/// split across `encode()` calls and interleaved Real-Time bytes are all
+/// handled by `midi_protocol::framing::MidiFramer` before conversion.
+///
+/// Platform-independent (only the Windows MIDI Services backend uses it),
+/// so it is unit tested on every platform.
+
+use midi_protocol::framing::{MidiFramer, MAX_SYSEX_LEN};
+
+/// One UMP message: (word_count, word0, word1).
+/// Type 1/2 = 1 word. Type 3 (SysEx) = 2 words per packet.
+pub type UmpMessage = (u8, u32, u32);
+
+#[derive(Debug, Default)]
+pub struct UmpEncoder {
+    /// Reassembles complete MIDI 1.0 messages from the raw byte stream
+    framer: MidiFramer,
+}
+
+impl UmpEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert MIDI 1.0 bytes to UMP messages. Bytes of a message that is
+    /// not yet complete are buffered until the next call.
+    pub fn encode(&mut self, data: &[u8]) -> Vec<UmpMessage> {
+        let discarded = self.framer.discarded_sysex();
+        let mut out = Vec::new();
+        for msg in self.framer.push(data) {
+            message_to_ump(&msg, &mut out);
+        }
+        if self.framer.discarded_sysex() > discarded {
+            tracing::warn!("SysEx exceeds {} bytes without F7, discarding", MAX_SYSEX_LEN);
+        }
+        out
+    }
+
+    /// Bytes currently waiting for the rest of their message.
+    pub fn pending_len(&self) -> usize {
+        self.framer.pending_len()
+    }
+}
+
+/// Convert one complete MIDI 1.0 message (explicit status byte) to UMP.
+fn message_to_ump(msg: &[u8], out: &mut Vec<UmpMessage>) {
+    let status = msg[0];
+    if status == 0xF0 {
+        // Framed SysEx always ends in F7
+        sysex_to_ump(&msg[1..msg.len() - 1], out);
+        return;
+    }
+
+    let d1 = msg.get(1).copied().unwrap_or(0) as u32;
+    let d2 = msg.get(2).copied().unwrap_or(0) as u32;
+    let msg_type = if status >= 0xF0 { 0x1000_0000u32 } else { 0x2000_0000u32 };
+    out.push((1, msg_type | ((status as u32) << 16) | (d1 << 8) | d2, 0));
+}
+
+/// Encode a SysEx payload (without F0/F7) as UMP Type 3 packets. An empty
+/// SysEx (F0 F7) is still one Complete packet, with a byte count of 0.
+fn sysex_to_ump(sysex_data: &[u8], out: &mut Vec<UmpMessage>) {
+    let mut chunks: Vec<&[u8]> = sysex_data.chunks(6).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    let total_chunks = chunks.len();
+
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let sysex_status: u32 = if total_chunks == 1 {
+            0x00 // Complete
+        } else if idx == 0 {
+            0x01 // Start
+        } else if idx == total_chunks - 1 {
+            0x03 // End
+        } else {
+            0x02 // Continue
+        };
+
+        let num_bytes = chunk.len() as u32;
+        // Type 3, group 0, status and byte count, then the first two data
+        // bytes; the remaining four fill the second word
+        let mut bytes = [0u32; 6];
+        for (slot, &b) in bytes.iter_mut().zip(chunk.iter()) {
+            *slot = b as u32;
+        }
+        let word0 = 0x3000_0000u32
+            | (sysex_status << 20)
+            | (num_bytes << 16)
+            | (bytes[0] << 8)
+            | bytes[1];
+        let word1 = (bytes[2] << 24) | (bytes[3] << 16) | (bytes[4] << 8) | bytes[5];
+        out.push((2, word0, word1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cv(status: u8, d1: u8, d2: u8) -> UmpMessage {
+        (1, 0x2000_0000 | ((status as u32) << 16) | ((d1 as u32) << 8) | d2 as u32, 0)
+    }
+
+    #[test]
+    fn complete_messages_convert() {
+        let mut enc = UmpEncoder::new();
+        let out = enc.encode(&[0x90, 60, 100, 0xC1, 5, 0xF8]);
+        assert_eq!(out, vec![cv(0x90, 60, 100), cv(0xC1, 5, 0), (1, 0x10F8_0000, 0)]);
+        assert_eq!(enc.pending_len(), 0);
+    }
+
+    #[test]
+    fn running_status_stream() {
+        let mut enc = UmpEncoder::new();
+        // Note On, then two more notes using running status, then a CC run
+        let out = enc.encode(&[0x90, 60, 100, 64, 90, 67, 80, 0xB0, 7, 100, 10, 64]);
+        assert_eq!(
+            out,
+            vec![
+                cv(0x90, 60, 100),
+                cv(0x90, 64, 90),
+                cv(0x90, 67, 80),
+                cv(0xB0, 7, 100),
+                cv(0xB0, 10, 64),
+            ]
+        );
+    }
+
+    #[test]
+    fn running_status_survives_across_sends() {
+        let mut enc = UmpEncoder::new();
+        assert_eq!(enc.encode(&[0x90, 60, 100]), vec![cv(0x90, 60, 100)]);
+        assert_eq!(enc.encode(&[62, 0]), vec![cv(0x90, 62, 0)]);
+    }
+
+    #[test]
+    fn message_split_across_two_sends() {
+        let mut enc = UmpEncoder::new();
+        assert!(enc.encode(&[0x90, 60, 100, 0xB0, 7]).len() == 1);
+        assert_eq!(enc.pending_len(), 2);
+        assert_eq!(enc.encode(&[127]), vec![cv(0xB0, 7, 127)]);
+        assert_eq!(enc.pending_len(), 0);
+    }
+
+    #[test]
+    fn sysex_split_across_sends() {
+        let mut enc = UmpEncoder::new();
+        assert!(enc.encode(&[0xF0, 0x7E, 0x7F]).is_empty());
+        let out = enc.encode(&[0x06, 0x01, 0xF7]);
+        assert_eq!(out, vec![(2, 0x3004_7E7F, 0x0601_0000)]);
+    }
+
+    #[test]
+    fn long_sysex_packs_six_bytes_per_packet() {
+        let mut enc = UmpEncoder::new();
+        let out = enc.encode(&[0xF0, 1, 2, 3, 4, 5, 6, 7, 8, 0xF7]);
+        assert_eq!(out, vec![(2, 0x3016_0102, 0x0304_0506), (2, 0x3032_0708, 0)]);
+    }
+
+    #[test]
+    fn empty_sysex_is_one_complete_packet() {
+        let mut enc = UmpEncoder::new();
+        let out = enc.encode(&[0xF0, 0xF7, 0xF8]);
+        assert_eq!(out, vec![(2, 0x3000_0000, 0), (1, 0x10F8_0000, 0)]);
+    }
+
+    #[test]
+    fn realtime_interleaved_mid_message() {
+        let mut enc = UmpEncoder::new();
+        let out = enc.encode(&[0x90, 60, 0xF8, 100]);
+        assert_eq!(out, vec![(1, 0x10F8_0000, 0), cv(0x90, 60, 100)]);
+    }
+
+    #[test]
+    fn system_common_cancels_running_status() {
+        let mut enc = UmpEncoder::new();
+        let out = enc.encode(&[0x90, 60, 100, 0xF3, 2, 64, 0]);
+        // 0xF3 (Song Select) clears running status, so 64 0 are stray
+        assert_eq!(out, vec![cv(0x90, 60, 100), (1, 0x10F3_0200, 0)]);
+        assert_eq!(enc.pending_len(), 0);
+    }
+}
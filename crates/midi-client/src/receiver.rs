@@ -1,27 +1,50 @@
 /// UDP multicast receiver for MIDI data.
 /// Listens on the primary multicast group, deserializes packets,
 /// updates MIDI state, and forwards raw MIDI to the virtual device.
+///
+/// Loss is tracked with `SequenceTracker`, which compares the u16 packet
+/// sequence modulo 2^16: the wrap from 65535 to 0 is an ordinary step, a
+/// gap counts only the packets actually missing, and a packet more than
+/// half the sequence space behind is treated as late rather than as loss.
+///
+/// With `failover.jitter_buffer_us` set, packets pass through a
+/// `JitterBuffer` first and are delivered at their playout deadline in
+/// host timestamp order, so the sequence check sees the repaired order.
+///
+/// Data packets are also remembered by a `FecDecoder`; when the host sends
+/// FEC parity (`network.fec_group_size`), a packet lost from a group is
+/// rebuilt from the parity and the rest of the group and then handled as
+/// if it had arrived.
 
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 use tracing::{debug, error, info, warn};
 
+use midi_protocol::clock::{PacketClock, TimestampSource};
+use midi_protocol::crypto::{decode_data_packet, OpenError, PacketCipher};
+use midi_protocol::fec::{FecDecoder, Recovery};
+use midi_protocol::jitter_buffer::{Admit, JitterBuffer};
 use midi_protocol::journal::decode_journal;
 use midi_protocol::midi_state::MidiState;
-use midi_protocol::packets::MidiDataPacket;
+use midi_protocol::multicast::{self, MulticastInterface};
+use midi_protocol::packets::{FecParityPacket, MidiDataPacket, MAGIC_FEC_PARITY};
+use midi_protocol::sequence::{SeqEvent, SequenceTracker};
 
 use crate::health::TaskPulse;
-use crate::ClientState;
+use crate::virtual_device::send_paced;
+use crate::{ClientState, FailoverSection};
 
 /// Create a multicast listener socket that joins the specified group.
 fn create_multicast_listener(
-    multicast_addr: Ipv4Addr,
+    multicast_addr: IpAddr,
     port: u16,
+    interface: MulticastInterface,
 ) -> std::io::Result<std::net::UdpSocket> {
-    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    let socket = multicast::new_socket(multicast_addr)?;
     socket.set_reuse_address(true)?;
 
     // On macOS/BSD, we also need SO_REUSEPORT for multiple listeners on same port
@@ -29,23 +52,157 @@ fn create_multicast_listener(
     socket.set_reuse_port(true)?;
 
     // Bind to the multicast port
-    let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
-    socket.bind(&addr.into())?;
+    socket.bind(&multicast::bind_addr(multicast_addr, port).into())?;
 
-    // Join multicast group on all interfaces
-    socket.join_multicast_v4(&multicast_addr, &Ipv4Addr::UNSPECIFIED)?;
+    // Join multicast group on the configured interface
+    multicast::join(&socket, multicast_addr, interface)?;
 
     socket.set_nonblocking(true)?;
 
     Ok(socket.into())
 }
 
+/// Decode a data packet (opening it first on a sealed stream), dropping it
+/// if its host isn't in `accept_host_ids`. Undecodable packets are `Ok(None)`;
+/// packets that fail authentication are an error so they can be counted.
+fn accept_packet(
+    failover: &FailoverSection,
+    cipher: Option<&PacketCipher>,
+    data: &[u8],
+) -> Result<Option<MidiDataPacket>, OpenError> {
+    match decode_data_packet(cipher, data) {
+        Ok(packet) => Ok(Some(packet).filter(|p| failover.accepts_host(p.host_id))),
+        Err(OpenError::Malformed) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replay the reconciled state onto the virtual device, paced by
+/// `failover.reconcile_max_msgs_per_ms`.
+async fn restore_device_state(state: &ClientState, midi_state: &MidiState) {
+    if !*state.device_ready.read().await {
+        return;
+    }
+    let messages = midi_state.generate_reconciliation();
+    let vdev = state.virtual_device.read().await;
+    let max_per_ms = state.config.failover.reconcile_max_msgs_per_ms;
+    match send_paced(vdev.as_ref(), &messages, max_per_ms).await {
+        Ok(()) => debug!(messages = messages.len(), max_per_ms, "Restored device state after failover"),
+        Err(e) => error!("Failed to restore device state after failover: {}", e),
+    }
+}
+
+/// Sequence-check, reconcile, process and forward one data packet.
+async fn deliver(
+    state: &ClientState,
+    packet: MidiDataPacket,
+    addr: SocketAddr,
+    midi_state: &mut MidiState,
+    sequence: &mut SequenceTracker,
+) {
+    match sequence.observe(packet.sequence) {
+        SeqEvent::InOrder => {}
+        // Skip a packet we already processed (can happen when both
+        // multicast and unicast deliver the same packet), even when a
+        // newer one overtook the second copy
+        SeqEvent::Duplicate => {
+            debug!(seq = packet.sequence, "Duplicate packet skipped");
+            return;
+        }
+        SeqEvent::Gap { lost } => {
+            state.health.counters.sequence_gaps.fetch_add(lost as u64, Ordering::Relaxed);
+            state.health.counters.max_sequence_gap.fetch_max(lost as u64, Ordering::Relaxed);
+            warn!(
+                got = packet.sequence,
+                lost = lost,
+                "Packet sequence gap detected"
+            );
+
+            // If we have a journal, reconcile state from it
+            if let Some(ref journal_data) = packet.journal {
+                if let Some(recovered_state) = decode_journal(journal_data) {
+                    *midi_state = recovered_state;
+                    info!("State recovered from journal after packet loss");
+                }
+            }
+        }
+        // A reordered packet that was counted as lost has turned up
+        SeqEvent::Late => {
+            let _ = state.health.counters.sequence_gaps.fetch_update(
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+                |gaps| Some(gaps.saturating_sub(1)),
+            );
+            state.health.counters.packets_out_of_order.fetch_add(1, Ordering::Relaxed);
+            debug!(seq = packet.sequence, "Late (reordered) packet");
+        }
+    }
+
+    // Check if failover requested state reconciliation
+    if state.needs_reconciliation.swap(false, Ordering::Relaxed) {
+        if let Some(ref journal_data) = packet.journal {
+            if let Some(recovered_state) = decode_journal(journal_data) {
+                *midi_state = recovered_state;
+                info!("State reconciled from journal after failover");
+                restore_device_state(state, midi_state).await;
+            }
+        }
+    }
+
+    // Apply pipeline processing to incoming MIDI
+    let pipeline = state.pipeline_config.read().await;
+    let processed = pipeline.process(&packet.midi_data);
+    drop(pipeline);
+
+    let forward_data = match processed {
+        Some(data) => data,
+        None => {
+            debug!(
+                seq = packet.sequence,
+                bytes = packet.midi_data.len(),
+                "Incoming MIDI filtered by pipeline"
+            );
+            return;
+        }
+    };
+
+    // Track MIDI throughput
+    state.health.counters.midi_in.fetch_add(1, Ordering::Relaxed);
+
+    // Update MIDI state model with processed data
+    midi_state.process_message(&forward_data);
+
+    // Keep a rolling record for replay debugging
+    state.message_log.record(&forward_data);
+
+    // Forward to virtual MIDI device if it's ready
+    let device_ready = *state.device_ready.read().await;
+    if device_ready {
+        let vdev = state.virtual_device.read().await;
+        let sent = state.health.device_send_latency.time(|| vdev.send(&forward_data));
+        if let Err(e) = sent {
+            error!("Failed to send MIDI to virtual device: {}", e);
+        }
+    }
+
+    debug!(
+        seq = packet.sequence,
+        host = packet.host_id,
+        bytes = forward_data.len(),
+        from = %addr,
+        "Received and forwarded MIDI data"
+    );
+}
+
 pub async fn run(state: Arc<ClientState>, pulse: TaskPulse) -> anyhow::Result<()> {
-    let primary_addr: Ipv4Addr = state.config.network.primary_group.parse()?;
+    let primary_addr = multicast::parse_group(&state.config.network.primary_group)?;
     let port = state.config.network.data_port;
 
-    let std_socket = create_multicast_listener(primary_addr, port)?;
-    let socket = UdpSocket::from_std(std_socket)?;
+    let std_socket = create_multicast_listener(primary_addr, port, state.data_interface)?;
+    let socket = Arc::new(UdpSocket::from_std(std_socket)?);
+    // Unicast subscriptions are sent from this socket, so a NAT maps the
+    // port the hosts' unicast copies have to come back to
+    state.data_socket.send_replace(Some(Arc::clone(&socket)));
 
     info!(
         group = %primary_addr,
@@ -55,107 +212,187 @@ pub async fn run(state: Arc<ClientState>, pulse: TaskPulse) -> anyhow::Result<()
 
     let mut buf = [0u8; 1500]; // MTU-sized buffer
     let mut midi_state = MidiState::new();
-    let mut last_sequence: Option<u16> = None;
+    let mut sequence = SequenceTracker::new();
 
-    loop {
-        match socket.recv_from(&mut buf).await {
-            Ok((len, addr)) => {
-                pulse.tick();
-                if let Some(packet) = MidiDataPacket::deserialize(&buf[..len]) {
-                    state.health.counters.packets_received.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-
-                    // Duplicate detection: skip if we already processed this sequence
-                    // (can happen when both multicast and unicast deliver the same packet)
-                    if let Some(last_seq) = last_sequence {
-                        if packet.sequence == last_seq {
-                            debug!(seq = packet.sequence, "Duplicate packet skipped");
-                            continue;
-                        }
-                    }
+    // Playout buffer (None = forward on arrival)
+    let jitter_buffer_us = state.config.failover.jitter_buffer_us;
+    let mut jitter = (jitter_buffer_us > 0).then(|| {
+        info!(delay_us = jitter_buffer_us, "Jitter buffer enabled");
+        JitterBuffer::new(jitter_buffer_us, Instant::now())
+    });
+    let mut buffered_host: Option<u8> = None;
 
-                    // Check for sequence gaps (packet loss detection)
-                    if let Some(last_seq) = last_sequence {
-                        let expected = last_seq.wrapping_add(1);
-                        if packet.sequence != expected {
-                            let gap = packet.sequence.wrapping_sub(last_seq);
-                            state.health.counters.sequence_gaps.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                            warn!(
-                                expected = expected,
-                                got = packet.sequence,
-                                gap = gap,
-                                "Packet sequence gap detected"
-                            );
+    let cipher = PacketCipher::from_psk(&state.config.network.psk);
+    if cipher.is_some() {
+        info!("Data stream encryption enabled");
+    }
+    let mut fec = FecDecoder::new();
+    // Arrival times for the one-way latency estimate; monotonic so a clock
+    // step here doesn't show up as a latency spike
+    let arrival_clock = PacketClock::new(TimestampSource::Monotonic);
+
+    loop {
+        let deadline = jitter.as_ref().and_then(|j| j.next_deadline());
 
-                            // If we have a journal, reconcile state from it
-                            if let Some(ref journal_data) = packet.journal {
-                                if let Some(recovered_state) = decode_journal(journal_data) {
-                                    midi_state = recovered_state;
-                                    info!("State recovered from journal after packet loss");
-                                }
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => match received {
+                Ok((len, addr)) => {
+                    pulse.tick();
+                    let arrived_us = arrival_clock.now_us();
+                    let mut rebuilt = false;
+                    let recovered;
+                    let data: &[u8] = if buf[..len].starts_with(&MAGIC_FEC_PARITY) {
+                        let Some(parity) = FecParityPacket::deserialize(&buf[..len]) else {
+                            continue;
+                        };
+                        match fec.recover(&parity) {
+                            Recovery::Recovered(bytes) => {
+                                state.health.counters.packets_fec_recovered.fetch_add(1, Ordering::Relaxed);
+                                debug!(base = parity.base_sequence, "Lost packet rebuilt from FEC parity");
+                                rebuilt = true;
+                                recovered = bytes;
+                                &recovered
                             }
-                        }
-                    }
-                    last_sequence = Some(packet.sequence);
-
-                    // Check if failover requested state reconciliation
-                    if state.needs_reconciliation.swap(false, std::sync::atomic::Ordering::Relaxed) {
-                        if let Some(ref journal_data) = packet.journal {
-                            if let Some(recovered_state) = decode_journal(journal_data) {
-                                midi_state = recovered_state;
-                                info!("State reconciled from journal after failover");
+                            Recovery::Complete => continue,
+                            Recovery::Unrecoverable { missing } => {
+                                debug!(base = parity.base_sequence, missing, "FEC group lost too many packets");
+                                continue;
                             }
                         }
-                    }
-
-                    // Apply pipeline processing to incoming MIDI
-                    let pipeline = state.pipeline_config.read().await;
-                    let processed = pipeline.process(&packet.midi_data);
-                    drop(pipeline);
-
-                    let forward_data = match processed {
-                        Some(data) => data,
-                        None => {
-                            debug!(
-                                seq = packet.sequence,
-                                bytes = packet.midi_data.len(),
-                                "Incoming MIDI filtered by pipeline"
-                            );
-                            continue;
+                    } else {
+                        fec.record(&buf[..len]);
+                        &buf[..len]
+                    };
+                    let accepted = match accept_packet(&state.config.failover, cipher.as_ref(), data) {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            state.health.counters.packets_auth_failed.fetch_add(1, Ordering::Relaxed);
+                            debug!(from = %addr, error = ?e, "Data packet failed authentication, dropped");
+                            None
                         }
                     };
+                    if let Some(packet) = accepted {
+                        state.health.counters.packets_received.fetch_add(1, Ordering::Relaxed);
+                        // A rebuilt packet's arrival says nothing about its delay
+                        if !rebuilt {
+                            state.health.one_way_latency.lock().unwrap().record(
+                                packet.host_id,
+                                packet.timestamp_us,
+                                arrived_us,
+                            );
+                        }
 
-                    // Track MIDI throughput
-                    state.health.counters.midi_in.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(jitter) = jitter.as_mut() else {
+                            deliver(&state, packet, addr, &mut midi_state, &mut sequence).await;
+                            continue;
+                        };
 
-                    // Update MIDI state model with processed data
-                    midi_state.process_message(&forward_data);
+                        // Another host's clock: play out what's queued and re-learn
+                        if buffered_host.is_some_and(|id| id != packet.host_id) {
+                            for (queued, from) in jitter.drain() {
+                                deliver(&state, queued, from, &mut midi_state, &mut sequence).await;
+                            }
+                            jitter.resync();
+                        }
+                        buffered_host = Some(packet.host_id);
 
-                    // Forward to virtual MIDI device if it's ready
-                    let device_ready = *state.device_ready.read().await;
-                    if device_ready {
-                        let vdev = state.virtual_device.read().await;
-                        if let Err(e) = vdev.send(&forward_data) {
-                            error!("Failed to send MIDI to virtual device: {}", e);
+                        let (seq, timestamp_us) = (packet.sequence, packet.timestamp_us);
+                        match jitter.push(timestamp_us, (packet, addr), Instant::now()) {
+                            Admit::Queued { reordered: false, .. } => {}
+                            Admit::Queued { reordered: true, .. } => {
+                                state.health.counters.packets_reordered.fetch_add(1, Ordering::Relaxed);
+                                debug!(seq, "Reordered packet put back in sequence");
+                            }
+                            Admit::LateDropped => {
+                                state.health.counters.packets_late_dropped.fetch_add(1, Ordering::Relaxed);
+                                debug!(seq, timestamp_us, "Packet arrived after its playout window, dropped");
+                            }
                         }
                     }
-
-                    debug!(
-                        seq = packet.sequence,
-                        host = packet.host_id,
-                        bytes = forward_data.len(),
-                        from = %addr,
-                        "Received and forwarded MIDI data"
-                    );
                 }
-            }
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::WouldBlock {
-                    tokio::time::sleep(std::time::Duration::from_micros(100)).await;
-                    continue;
+                Err(e) => {
+                    if e.kind() == std::io::ErrorKind::WouldBlock {
+                        tokio::time::sleep(Duration::from_micros(100)).await;
+                        continue;
+                    }
+                    error!("Receive error: {}", e);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
                 }
-                error!("Receive error: {}", e);
-                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            },
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into()), if deadline.is_some() => {
+                pulse.tick();
+            }
+        }
+
+        // Release everything whose playout deadline has come
+        if let Some(jitter) = jitter.as_mut() {
+            while let Some((packet, addr)) = jitter.pop_due(Instant::now()) {
+                deliver(&state, packet, addr, &mut midi_state, &mut sequence).await;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet_from(host_id: u8) -> Vec<u8> {
+        let mut buf = Vec::new();
+        MidiDataPacket {
+            sequence: 1,
+            timestamp_us: 0,
+            host_id,
+            midi_data: vec![0x90, 60, 100],
+            journal: None,
+        }
+        .serialize(&mut buf);
+        buf
+    }
+
+    fn failover_accepting(ids: Vec<u8>) -> FailoverSection {
+        FailoverSection {
+            jitter_buffer_us: 0,
+            detection_window_ms: 9,
+            silence_on_host_loss_ms: 0,
+            accept_host_ids: ids,
+            reconcile_max_msgs_per_ms: 0,
+            miss_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn unlisted_host_ids_are_ignored() {
+        let failover = failover_accepting(vec![1, 2]);
+
+        let delivered = accept_packet(&failover, None, &packet_from(2)).unwrap().unwrap();
+        assert_eq!(delivered.host_id, 2);
+        assert_eq!(delivered.midi_data, vec![0x90, 60, 100]);
+
+        assert!(accept_packet(&failover, None, &packet_from(1)).unwrap().is_some());
+        assert!(accept_packet(&failover, None, &packet_from(7)).unwrap().is_none());
+    }
+
+    #[test]
+    fn empty_allowlist_accepts_any_host() {
+        let failover = failover_accepting(Vec::new());
+        assert!(accept_packet(&failover, None, &packet_from(7)).unwrap().is_some());
+    }
+
+    #[test]
+    fn sealed_stream_rejects_plaintext_and_foreign_keys() {
+        let failover = failover_accepting(Vec::new());
+        let cipher = PacketCipher::from_psk("venue-key").unwrap();
+
+        let mut sealed = packet_from(2);
+        cipher.seal(&mut sealed);
+        let opened = accept_packet(&failover, Some(&cipher), &sealed).unwrap().unwrap();
+        assert_eq!(opened.midi_data, vec![0x90, 60, 100]);
+
+        assert_eq!(accept_packet(&failover, Some(&cipher), &packet_from(2)).unwrap_err(), OpenError::NotEncrypted);
+        let other = PacketCipher::from_psk("other-key").unwrap();
+        assert_eq!(accept_packet(&failover, Some(&other), &sealed).unwrap_err(), OpenError::AuthFailed);
+        // Without a key the sealed packet is just undecodable
+        assert!(accept_packet(&failover, None, &sealed).unwrap().is_none());
+    }
+}
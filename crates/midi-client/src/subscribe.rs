@@ -0,0 +1,94 @@
+/// Unicast subscription for clients the hosts can't reach on their own.
+///
+/// With `network.unicast_subscribe_ms` set, the client sends a
+/// `SubscribePacket` to the control port of every discovered host at that
+/// interval. It goes out from the receiver's data socket, so sending it
+/// opens the way back through a NAT for exactly the port the data arrives
+/// on, and the hosts (with `[unicast] enabled = true`) unicast to the
+/// sender's address and port until it has missed about five intervals. Every host is
+/// subscribed, not just the active one, so a standby already unicasts to
+/// this client when it takes over.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use midi_protocol::packets::SubscribePacket;
+use tracing::{debug, info};
+
+use crate::{ClientState, DiscoveredHost};
+
+/// Control-port addresses of `hosts` (IPv4 only: unicast targets are IPv4).
+fn subscribe_targets(hosts: &[DiscoveredHost], control_port: u16) -> Vec<SocketAddr> {
+    let mut targets: Vec<SocketAddr> = hosts
+        .iter()
+        .flat_map(|h| h.addresses.iter())
+        .filter(|ip| matches!(ip, IpAddr::V4(v4) if !v4.is_unspecified()))
+        .map(|&ip| SocketAddr::new(ip, control_port))
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets
+}
+
+/// Send subscribe packets to every discovered host until the client stops.
+pub async fn run(state: Arc<ClientState>, interval: Duration) -> anyhow::Result<()> {
+    let data_socket = state.data_socket.subscribe();
+    let control_port = state.config.network.control_port;
+    let packet = SubscribePacket {
+        client_id: state.client_id,
+        interval_ms: interval.as_millis().min(u16::MAX as u128) as u16,
+    };
+    let mut buf = [0u8; SubscribePacket::SIZE];
+    packet.serialize(&mut buf);
+
+    info!(interval_ms = packet.interval_ms, "Unicast subscription enabled");
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        // Bound by the receiver; a restarted receiver publishes its new one
+        let Some(socket) = data_socket.borrow().clone() else {
+            continue;
+        };
+        let targets = subscribe_targets(&state.discovered_hosts.read().await, control_port);
+        for target in targets {
+            if let Err(e) = socket.send_to(&buf, target).await {
+                debug!(to = %target, "Failed to send unicast subscribe: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    use super::*;
+
+    fn host(id: u8, addresses: &[&str]) -> DiscoveredHost {
+        DiscoveredHost {
+            id,
+            name: format!("host-{id}"),
+            role: "primary".to_string(),
+            addresses: addresses.iter().map(|a| a.parse().unwrap()).collect::<HashSet<IpAddr>>(),
+            multicast_group: "239.69.83.1".to_string(),
+            data_port: 5004,
+            control_group: None,
+            device_name: String::new(),
+            protocol_version: None,
+            admin_url: None,
+            last_seen: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn every_hosts_ipv4_addresses_are_subscribed() {
+        let hosts = [host(1, &["192.168.1.10", "fe80::1"]), host(2, &["192.168.1.11", "0.0.0.0"])];
+        let targets = subscribe_targets(&hosts, 5006);
+        assert_eq!(
+            targets,
+            vec!["192.168.1.10:5006".parse().unwrap(), "192.168.1.11:5006".parse::<SocketAddr>().unwrap()]
+        );
+    }
+}
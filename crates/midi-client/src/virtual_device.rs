@@ -2,7 +2,8 @@
 /// Creates a platform-specific virtual MIDI port that mimics the identity
 /// of the physical controller connected to the host.
 
-use midi_protocol::identity::DeviceIdentity;
+use midi_protocol::identity::{is_identity_request, DeviceIdentity};
+use tracing::{info, warn};
 
 /// Trait for platform-specific virtual MIDI device implementations.
 pub trait VirtualMidiDevice: Send + Sync {
@@ -48,6 +49,92 @@ pub trait VirtualMidiDevice: Send + Sync {
     }
 }
 
+/// Answer a Universal Identity Request from the app with the cloned
+/// identity's `reply`, so DAWs probing the port see the physical controller.
+/// Returns true if `data` was an Identity Request (and has been answered).
+pub fn answer_identity_request(
+    device: &dyn VirtualMidiDevice,
+    reply: &[u8],
+    data: &[u8],
+) -> anyhow::Result<bool> {
+    if !is_identity_request(data) {
+        return Ok(false);
+    }
+    device.send(reply)?;
+    Ok(true)
+}
+
+/// Send a reconciliation burst to the device, at most `max_per_ms` messages
+/// per millisecond (0 = all at once). After a failover or late join the
+/// restore can be hundreds of messages, which overruns slow drivers.
+pub async fn send_paced(
+    device: &dyn VirtualMidiDevice,
+    messages: &[Vec<u8>],
+    max_per_ms: u32,
+) -> anyhow::Result<()> {
+    if max_per_ms == 0 {
+        for msg in messages {
+            device.send(msg)?;
+        }
+        return Ok(());
+    }
+
+    // Schedule against the start time so slow sends don't stretch the burst further
+    let start = tokio::time::Instant::now();
+    for (slot, batch) in messages.chunks(max_per_ms as usize).enumerate() {
+        if slot > 0 {
+            tokio::time::sleep_until(start + std::time::Duration::from_millis(slot as u64)).await;
+        }
+        for msg in batch {
+            device.send(msg)?;
+        }
+    }
+    Ok(())
+}
+
+/// Create the device and, with `panic_on_startup`, silence it before any
+/// live MIDI is forwarded. A failed silence is logged, not fatal: the
+/// device exists and the session can go on.
+pub fn create_and_clear(
+    device: &mut dyn VirtualMidiDevice,
+    identity: &DeviceIdentity,
+    panic_on_startup: bool,
+) -> anyhow::Result<()> {
+    device.create(identity)?;
+    if panic_on_startup {
+        match device.send_all_off() {
+            Ok(()) => info!("Sent startup panic (All Sound Off + All Notes Off)"),
+            Err(e) => warn!("Failed to send startup panic: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Holds back device creation until the host identity has been the same
+/// for `required` consecutive checks.
+pub struct IdentityStabilizer {
+    required: u32,
+    last: Option<DeviceIdentity>,
+    stable_for: u32,
+}
+
+impl IdentityStabilizer {
+    pub fn new(required: u32) -> Self {
+        Self { required: required.max(1), last: None, stable_for: 0 }
+    }
+
+    /// Record this check's identity. Returns true once it is stable.
+    pub fn observe(&mut self, identity: &DeviceIdentity) -> bool {
+        if self.last.as_ref() == Some(identity) {
+            self.stable_for += 1;
+        } else {
+            self.last = Some(identity.clone());
+            self.stable_for = 1;
+        }
+        self.stable_for >= self.required
+    }
+}
+
 /// Create a platform-appropriate virtual MIDI device.
 pub fn create_virtual_device() -> Box<dyn VirtualMidiDevice> {
     #[cfg(target_os = "linux")]
@@ -108,3 +195,157 @@ impl VirtualMidiDevice for StubVirtualDevice {
         &self.name
     }
 }
+
+/// In-memory device for tests: records everything sent, and returns queued
+/// messages from `receive()` as if an app had written them.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct MockDevice {
+        pub sent: Mutex<Vec<Vec<u8>>>,
+        pub incoming: Mutex<VecDeque<Vec<u8>>>,
+        /// Simulated driver latency per send
+        pub send_delay: Option<std::time::Duration>,
+    }
+
+    impl MockDevice {
+        pub fn with_send_delay(delay: std::time::Duration) -> Self {
+            Self {
+                send_delay: Some(delay),
+                ..Self::default()
+            }
+        }
+
+        pub fn feed(&self, data: &[u8]) {
+            self.incoming.lock().unwrap().push_back(data.to_vec());
+        }
+    }
+
+    impl VirtualMidiDevice for MockDevice {
+        fn create(&mut self, _identity: &DeviceIdentity) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn send(&self, data: &[u8]) -> anyhow::Result<()> {
+            if let Some(delay) = self.send_delay {
+                std::thread::sleep(delay);
+            }
+            self.sent.lock().unwrap().push(data.to_vec());
+            Ok(())
+        }
+
+        fn receive(&self) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self.incoming.lock().unwrap().pop_front())
+        }
+
+        fn close(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn device_name(&self) -> &str {
+            "mock"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockDevice;
+    use super::*;
+
+    fn apc40() -> DeviceIdentity {
+        DeviceIdentity {
+            name: "APC40".to_string(),
+            manufacturer: "Akai".to_string(),
+            vendor_id: 0x09E8,
+            product_id: 0x0028,
+            sysex_identity: [0x47, 0x73, 0x00, 0x19, 0x00, 0x01, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0],
+            port_count_in: 1,
+            port_count_out: 1,
+        }
+    }
+
+    #[test]
+    fn identity_request_is_answered_with_identity_reply() {
+        let device = MockDevice::default();
+        let identity = apc40();
+        device.feed(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]);
+        device.feed(&[0x90, 60, 127]);
+
+        let mut forwarded = Vec::new();
+        while let Some(data) = device.receive().unwrap() {
+            if !answer_identity_request(&device, &identity.sysex_identity_reply(), &data).unwrap() {
+                forwarded.push(data);
+            }
+        }
+
+        assert_eq!(*device.sent.lock().unwrap(), vec![identity.sysex_identity_reply()]);
+        // Non-identity feedback continues on to the host
+        assert_eq!(forwarded, vec![vec![0x90, 60, 127]]);
+    }
+
+    #[test]
+    fn changing_identity_defers_creation_until_stable() {
+        let mut other = apc40();
+        other.name = "Launchpad".to_string();
+
+        let mut stabilizer = IdentityStabilizer::new(3);
+        assert!(!stabilizer.observe(&other));
+        // A different host wins the discovery race: the count starts over
+        assert!(!stabilizer.observe(&apc40()));
+        assert!(!stabilizer.observe(&apc40()));
+        assert!(stabilizer.observe(&apc40()));
+
+        // One cycle creates on the first identity seen
+        assert!(IdentityStabilizer::new(1).observe(&other));
+        assert!(IdentityStabilizer::new(0).observe(&other));
+    }
+
+    #[test]
+    fn startup_panic_precedes_forwarded_midi() {
+        let mut device = MockDevice::default();
+        create_and_clear(&mut device, &apc40(), true).unwrap();
+        device.send(&[0x90, 60, 100]).unwrap();
+
+        let sent = device.sent.lock().unwrap();
+        assert_eq!(sent.len(), 33);
+        for ch in 0..16u8 {
+            assert_eq!(sent[ch as usize * 2], vec![0xB0 | ch, 120, 0]);
+            assert_eq!(sent[ch as usize * 2 + 1], vec![0xB0 | ch, 123, 0]);
+        }
+        assert_eq!(sent[32], vec![0x90, 60, 100]);
+    }
+
+    #[test]
+    fn startup_panic_is_opt_in() {
+        let mut device = MockDevice::default();
+        create_and_clear(&mut device, &apc40(), false).unwrap();
+        assert!(device.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reconciliation_burst_is_paced() {
+        let device = MockDevice::default();
+        let messages: Vec<Vec<u8>> = (0..400u32)
+            .map(|i| vec![0xB0 | (i / 128) as u8, (i % 128) as u8, 64])
+            .collect();
+
+        let start = std::time::Instant::now();
+        send_paced(&device, &messages, 20).await.unwrap();
+        let elapsed = start.elapsed();
+
+        // 400 messages at 20/ms occupy 20 one-millisecond slots
+        assert!(elapsed >= std::time::Duration::from_millis(19), "burst took only {:?}", elapsed);
+        assert_eq!(*device.sent.lock().unwrap(), messages);
+
+        // Unpaced sends everything immediately
+        let device = MockDevice::default();
+        send_paced(&device, &messages, 0).await.unwrap();
+        assert_eq!(device.sent.lock().unwrap().len(), 400);
+    }
+}
@@ -4,21 +4,30 @@
 /// Responds with a `DiscoverResponse` containing the host's identity, ports,
 /// and admin panel URL. This enables zero-config client setup on networks
 /// where mDNS multicast doesn't work.
+///
+/// When the device identity changes, the response is re-sent to every client
+/// seen so far so they can recreate their virtual device immediately.
+///
+/// Broadcast is IPv4-only and the response carries the group as IPv4
+/// octets, so a host on an IPv6 group doesn't run the responder; its
+/// clients find it over mDNS or a seed list.
 
 use std::collections::HashSet;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::Arc;
 
 use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 use tracing::{debug, error, info};
 
-use midi_protocol::packets::{DiscoverRequest, DiscoverResponse};
+use midi_protocol::multicast;
+use midi_protocol::packets::{DiscoverRequest, DiscoverResponse, IdentityPacket};
 use midi_protocol::{DEFAULT_DISCOVERY_PORT, PROTOCOL_VERSION};
 
-use crate::SharedState;
+use crate::{NetworkSection, SharedState};
 
 pub async fn run(state: Arc<SharedState>) -> anyhow::Result<()> {
+    announced_group(&state.config.network)?;
     let socket = {
         let s = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
         s.set_reuse_address(true)?;
@@ -31,6 +40,13 @@ pub async fn run(state: Arc<SharedState>) -> anyhow::Result<()> {
 
     info!(port = DEFAULT_DISCOVERY_PORT, "Broadcast discovery responder listening");
 
+    serve(state, socket).await
+}
+
+/// Answer discovery requests on `socket`. When the device identity changes,
+/// the current response is pushed to every client seen so far (and
+/// optionally to the control group) without waiting for their next broadcast.
+async fn serve(state: Arc<SharedState>, socket: UdpSocket) -> anyhow::Result<()> {
     // Parse admin port from config listen address (e.g. "0.0.0.0:8080" → 8080)
     let admin_port: u16 = state
         .config
@@ -41,68 +57,253 @@ pub async fn run(state: Arc<SharedState>) -> anyhow::Result<()> {
         .and_then(|p| p.parse().ok())
         .unwrap_or(8080);
 
-    // Parse multicast group into raw octets
-    let mcast_octets: [u8; 4] = state
-        .config
-        .network
-        .multicast_group
-        .parse::<Ipv4Addr>()
-        .unwrap_or(Ipv4Addr::new(239, 69, 83, 1))
-        .octets();
+    let mcast_octets = announced_group(&state.config.network)?.octets();
+
+    // The responder's socket is IPv4, like the broadcasts it answers
+    let control_dest = match multicast::parse_group(&state.config.network.control_group) {
+        Ok(IpAddr::V4(group)) => Some(SocketAddrV4::new(group, state.config.network.control_port)),
+        _ => None,
+    };
 
     let mut buf = [0u8; 64];
     let mut resp_buf = Vec::with_capacity(128);
     let mut seen_clients: HashSet<SocketAddr> = HashSet::new();
 
+    let mut identity_rx = state.identity_generation.subscribe();
+    let mut readvertise = state.config.discovery.readvertise_on_identity_change;
+
     loop {
-        let (len, src) = match socket.recv_from(&mut buf).await {
-            Ok(r) => r,
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::WouldBlock {
-                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        tokio::select! {
+            result = socket.recv_from(&mut buf) => {
+                let (len, src) = match result {
+                    Ok(r) => r,
+                    Err(e) => {
+                        if e.kind() == std::io::ErrorKind::WouldBlock {
+                            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                            continue;
+                        }
+                        error!("Broadcast discovery recv error: {}", e);
+                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+
+                let Some(req) = DiscoverRequest::deserialize(&buf[..len]) else {
+                    continue;
+                };
+                if state.note_peer_protocol(req.protocol_version) {
+                    info!(
+                        client_id = req.client_id,
+                        version = req.protocol_version,
+                        "Older client on the network, journals downgraded to its protocol"
+                    );
+                }
+
+                if !seen_clients.contains(&src) {
+                    info!(
+                        client_id = req.client_id,
+                        from = %src,
+                        version = req.protocol_version,
+                        "Discovery broadcast received from new client"
+                    );
+                    seen_clients.insert(src);
+                } else {
+                    debug!(client_id = req.client_id, from = %src, "Discovery broadcast (repeat)");
+                }
+
+                let response = build_response(&state, admin_port, mcast_octets).await;
+                response.serialize(&mut resp_buf);
+
+                if let Err(e) = socket.send_to(&resp_buf, src).await {
+                    error!(to = %src, "Failed to send discovery response: {}", e);
+                }
+            }
+            changed = identity_rx.changed(), if readvertise => {
+                if changed.is_err() {
+                    readvertise = false;
                     continue;
                 }
-                error!("Broadcast discovery recv error: {}", e);
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                continue;
+
+                let response = build_response(&state, admin_port, mcast_octets).await;
+                response.serialize(&mut resp_buf);
+
+                info!(
+                    device = %response.device_name,
+                    clients = seen_clients.len(),
+                    "Device identity changed, re-announcing to known clients"
+                );
+
+                for client in &seen_clients {
+                    if let Err(e) = socket.send_to(&resp_buf, client).await {
+                        debug!(to = %client, "Failed to send identity announcement: {}", e);
+                    }
+                }
+
+                if state.config.discovery.notify_control_group {
+                    if let Some(dest) = control_dest {
+                        let identity = state.identity.read().await.clone();
+                        let packet = IdentityPacket {
+                            host_id: state.config.host.id,
+                            device_name: identity.name,
+                            manufacturer: identity.manufacturer,
+                            vendor_id: identity.vendor_id,
+                            product_id: identity.product_id,
+                            sysex_identity: identity.sysex_identity,
+                            port_count_in: identity.port_count_in,
+                            port_count_out: identity.port_count_out,
+                        };
+                        packet.serialize(&mut resp_buf);
+                        if let Err(e) = socket.send_to(&resp_buf, dest).await {
+                            debug!(to = %dest, "Failed to send identity to control group: {}", e);
+                        }
+                    }
+                }
             }
-        };
-
-        let Some(req) = DiscoverRequest::deserialize(&buf[..len]) else {
-            continue;
-        };
-
-        if !seen_clients.contains(&src) {
-            info!(
-                client_id = req.client_id,
-                from = %src,
-                version = req.protocol_version,
-                "Discovery broadcast received from new client"
-            );
-            seen_clients.insert(src);
-        } else {
-            debug!(client_id = req.client_id, from = %src, "Discovery broadcast (repeat)");
         }
+    }
+}
+
+/// The data group as announced in responses. Errors for an IPv6 (or
+/// invalid) group, which a `DiscoverResponse` can't carry.
+fn announced_group(network: &NetworkSection) -> anyhow::Result<Ipv4Addr> {
+    match multicast::parse_group(&network.multicast_group)? {
+        IpAddr::V4(group) => Ok(group),
+        IpAddr::V6(group) => anyhow::bail!(
+            "broadcast discovery is IPv4-only and can't announce {}; clients find this host over mDNS or a seed list",
+            group
+        ),
+    }
+}
 
-        // Build response from current state
-        let role = *state.role.borrow();
-        let device_name = state.identity.read().await.name.clone();
-
-        let response = DiscoverResponse {
-            host_id: state.config.host.id,
-            role,
-            protocol_version: PROTOCOL_VERSION,
-            data_port: state.config.network.data_port,
-            heartbeat_port: state.config.network.heartbeat_port,
-            admin_port,
-            multicast_group: mcast_octets,
-            device_name,
-        };
-
-        response.serialize(&mut resp_buf);
-
-        if let Err(e) = socket.send_to(&resp_buf, src).await {
-            error!(to = %src, "Failed to send discovery response: {}", e);
+/// Build a `DiscoverResponse` from the current role and identity.
+async fn build_response(
+    state: &SharedState,
+    admin_port: u16,
+    multicast_group: [u8; 4],
+) -> DiscoverResponse {
+    let role = *state.role.borrow();
+    let device_name = state.identity.read().await.name.clone();
+
+    DiscoverResponse {
+        host_id: state.config.host.id,
+        role,
+        protocol_version: PROTOCOL_VERSION,
+        data_port: state.config.network.data_port,
+        heartbeat_port: state.config.network.heartbeat_port,
+        admin_port,
+        multicast_group,
+        device_name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use midi_protocol::identity::DeviceIdentity;
+
+    use crate::test_support::test_state;
+
+    async fn recv_response(socket: &UdpSocket, within: Duration) -> Option<DiscoverResponse> {
+        let mut buf = [0u8; 256];
+        let (len, _) = tokio::time::timeout(within, socket.recv_from(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+        DiscoverResponse::deserialize(&buf[..len])
+    }
+
+    #[tokio::test]
+    async fn identity_change_triggers_immediate_announcement() {
+        let state = test_state("APC40 mkII");
+
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let responder_addr = responder.local_addr().unwrap();
+        tokio::spawn(serve(Arc::clone(&state), responder));
+
+        // Register as a client with a single discovery request
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut req_buf = [0u8; DiscoverRequest::SIZE];
+        DiscoverRequest { client_id: 42, protocol_version: PROTOCOL_VERSION }.serialize(&mut req_buf);
+        client.send_to(&req_buf, responder_addr).await.unwrap();
+
+        let first = recv_response(&client, Duration::from_secs(1))
+            .await
+            .expect("initial discovery response");
+        assert_eq!(first.device_name, "APC40 mkII");
+
+        // Swap the device — no new request is sent, so anything that arrives
+        // now must be the out-of-cycle announcement (clients re-broadcast
+        // every 3s, so a 500ms window rules out the regular cycle).
+        let changed = state
+            .set_identity(DeviceIdentity {
+                name: "Launchpad X".to_string(),
+                ..DeviceIdentity::default()
+            })
+            .await;
+        assert!(changed);
+
+        let announced = recv_response(&client, Duration::from_millis(500))
+            .await
+            .expect("identity change should be announced immediately");
+        assert_eq!(announced.device_name, "Launchpad X");
+        assert_eq!(announced.host_id, 1);
+    }
+
+    #[tokio::test]
+    async fn unchanged_identity_is_not_reannounced() {
+        let state = test_state("APC40 mkII");
+        let generation = state.identity_generation.subscribe();
+
+        let changed = state
+            .set_identity(DeviceIdentity {
+                name: "APC40 mkII".to_string(),
+                ..DeviceIdentity::default()
+            })
+            .await;
+
+        assert!(!changed);
+        assert!(!generation.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn older_client_downgrades_journals() {
+        use midi_protocol::journal::{encode_journal, encode_journal_for};
+        use midi_protocol::midi_state::MidiState;
+
+        let state = test_state("APC40 mkII");
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let responder_addr = responder.local_addr().unwrap();
+        tokio::spawn(serve(Arc::clone(&state), responder));
+
+        // A sustained note: v4 journals carry it in a pedal section
+        let mut held = MidiState::new();
+        for msg in [[0xB0, 64, 127], [0x90, 60, 100], [0x80, 60, 0]] {
+            held.process_message(&msg);
+        }
+        assert_eq!(state.encode_journal(&held), encode_journal(&held));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut req_buf = [0u8; DiscoverRequest::SIZE];
+        // Versions this build can't talk to don't change anything
+        for version in [0, PROTOCOL_VERSION + 1, 3] {
+            DiscoverRequest { client_id: 42, protocol_version: version }.serialize(&mut req_buf);
+            client.send_to(&req_buf, responder_addr).await.unwrap();
+            recv_response(&client, Duration::from_secs(1)).await.expect("discovery response");
         }
+        assert_eq!(state.encode_journal(&held), encode_journal_for(&held, 3));
+        assert_ne!(encode_journal_for(&held, 3), encode_journal(&held));
+    }
+
+    #[test]
+    fn ipv6_group_is_refused_not_announced_as_ipv4() {
+        use crate::test_support::host_config;
+
+        let v4 = host_config(1, "239.69.83.7", 0, "");
+        assert_eq!(announced_group(&v4.network).unwrap(), Ipv4Addr::new(239, 69, 83, 7));
+        let v6 = host_config(1, "ff15::4d49:1", 0, "");
+        assert!(announced_group(&v6.network).unwrap_err().to_string().contains("IPv4-only"));
+        assert!(announced_group(&host_config(1, "10.0.0.1", 0, "").network).is_err());
     }
 }
@@ -1,70 +1,163 @@
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{IpAddr, SocketAddr, SocketAddrV4};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
-use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
-use tracing::{debug, error, info};
-
-use midi_protocol::journal::encode_journal;
-use midi_protocol::packets::{HeartbeatPacket, MidiDataPacket};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use midi_protocol::fec::FecEncoder;
+use midi_protocol::midi_state::panic_messages;
+use midi_protocol::mmc;
+use midi_protocol::multicast::{self, MulticastInterface};
+use midi_protocol::packets::{HeartbeatPacket, HostRole, MidiDataPacket};
 use midi_protocol::ringbuf::SLOT_SIZE;
 
 use crate::input_mux::InputMux;
+use crate::midi_clock::{has_clock_tick, CLOCK_TICK};
+use crate::pipeline::{CcThrottle, MergedNotes, MonoVoice, NoteDebouncer, NoteLatch, PipelineConfig};
+use crate::recorder::RecorderCommand;
+use crate::send_retry::{self, send_with_retry};
 use crate::SharedState;
 
-/// Timestamp in microseconds since UNIX epoch
-fn now_us() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_micros() as u64
-}
-
-/// Create a multicast UDP socket bound to the specified port
-fn create_multicast_socket(
-    _multicast_addr: Ipv4Addr,
+/// Create a socket for sending to `group`, bound to the specified port.
+/// The socket's address family follows the group's.
+pub(crate) fn create_multicast_socket(
+    group: IpAddr,
     port: u16,
-    interface: Ipv4Addr,
+    interface: MulticastInterface,
+    ttl: u32,
 ) -> std::io::Result<std::net::UdpSocket> {
-    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    let socket = multicast::new_socket(group)?;
     socket.set_reuse_address(true)?;
 
-    // Set multicast interface
-    socket.set_multicast_if_v4(&interface)?;
-
-    // Set multicast TTL to 1 (LAN only)
-    socket.set_multicast_ttl_v4(1)?;
-
-    // Enable loopback so the admin sniffer on the same host can see packets
-    socket.set_multicast_loop_v4(true)?;
+    // Interface and TTL (default 1 = LAN only); loopback so the admin
+    // sniffer on the same host can see packets
+    multicast::set_outgoing(&socket, group, interface, ttl, true)?;
 
     // Bind to the port
-    let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
-    socket.bind(&addr.into())?;
+    socket.bind(&multicast::bind_addr(group, port).into())?;
 
     socket.set_nonblocking(true)?;
 
     Ok(socket.into())
 }
 
+/// Run each MIDI message in `raw_midi` through the pipeline into `out`.
+/// Harmonized channels expand each note into its chord first.
+/// With `merge_note_refcount` set, Note Offs for merged notes still held by
+/// another source are dropped; mono channels go through the mono voice and
+/// latched channels through the note latch; with `min_note_duration_ms`
+/// set, notes also pass through the debouncer, and with `cc_throttle_hz`
+/// set, Control Changes through the throttle. Releases generated on the
+/// way are normalized like incoming ones (`normalize_note_off`).
+/// MMC transport commands are logged as they arrive and pass through as
+/// the SysEx they are.
+#[allow(clippy::too_many_arguments)]
+fn apply_pipeline(
+    pipeline_config: &PipelineConfig,
+    merged: &mut MergedNotes,
+    mono: &mut MonoVoice,
+    latch: &mut NoteLatch,
+    debouncer: &mut NoteDebouncer,
+    throttle: &mut CcThrottle,
+    raw_midi: &[u8],
+    out: &mut Vec<u8>,
+) {
+    out.clear();
+    let min_note = Duration::from_millis(pipeline_config.min_note_duration_ms);
+    let now = Instant::now();
+
+    let mut offset = 0;
+    while offset < raw_midi.len() {
+        let remaining = &raw_midi[offset..];
+        let (msg_len, _status) = midi_message_length(remaining);
+
+        if msg_len == 0 {
+            offset += 1;
+            continue;
+        }
+
+        let played = &remaining[..msg_len];
+        offset += msg_len;
+
+        if played[0] == 0xF0 {
+            if let Some(mmc) = mmc::parse(played) {
+                info!(command = ?mmc.command, device = mmc.device_id, "MMC {}", mmc.command.name());
+            }
+        }
+
+        // Harmonized chords pass through the stateful stages note by note
+        pipeline_config.harmonize_each(played, |msg| {
+            let Some(processed) = pipeline_config.process(msg) else {
+                return;
+            };
+            if pipeline_config.merge_note_refcount && !merged.forward(msg, &processed) {
+                return; // another merged source still holds this note
+            }
+            if pipeline_config.cc_throttle_hz > 0 && processed[0] & 0xF0 == 0xB0 {
+                for m in throttle.process(&processed, pipeline_config.cc_throttle_hz, now) {
+                    out.extend_from_slice(&m);
+                }
+                return;
+            }
+            mono.process(&pipeline_config.mono, pipeline_config.mono_retrigger, played, &processed, |voiced| {
+                latch.process(&pipeline_config.latch, played, voiced, |latched| {
+                    // The mono voice and latch generate Note Offs of their own
+                    let mut note;
+                    let latched = match latched {
+                        &[status, key, velocity] => {
+                            note = [status, key, velocity];
+                            pipeline_config.normalize_release(&mut note);
+                            &note[..]
+                        }
+                        _ => latched,
+                    };
+                    if min_note.is_zero() {
+                        out.extend_from_slice(latched);
+                    } else {
+                        for m in debouncer.process(latched, min_note, now) {
+                            out.extend_from_slice(&m);
+                        }
+                    }
+                });
+            });
+        });
+    }
+}
+
+async fn sleep_until_due(due: Option<Instant>) {
+    if let Some(due) = due {
+        tokio::time::sleep_until(due.into()).await;
+    }
+}
+
+/// Clear held notes for `channel` (None = all) in the journal state and
+/// return the All Sound Off + All Notes Off bytes to broadcast.
+pub(crate) async fn apply_panic(state: &SharedState, channel: Option<u8>) -> Vec<u8> {
+    state.midi_state.write().await.clear_notes(channel);
+    panic_messages(channel)
+}
+
 /// Run the MIDI data broadcaster.
 /// Reads MIDI from the InputMux (which handles dual-controller failover),
 /// applies the pipeline, sends via UDP multicast (and unicast if enabled).
+/// Panic requests from `panic_rx` are sent in-sequence on the same stream.
 pub async fn run(
     state: Arc<SharedState>,
     mux: Arc<InputMux>,
+    mut panic_rx: mpsc::Receiver<Option<u8>>,
 ) -> anyhow::Result<()> {
-    let multicast_addr: Ipv4Addr = state.config.network.multicast_group.parse()?;
+    let multicast_addr = multicast::parse_group(&state.config.network.multicast_group)?;
     let port = state.config.network.data_port;
 
-    // For now, bind to all interfaces
-    let interface = Ipv4Addr::UNSPECIFIED;
+    let interface = state.data_interface.multicast();
 
-    let std_socket = create_multicast_socket(multicast_addr, 0, interface)?;
+    let std_socket = create_multicast_socket(multicast_addr, 0, interface, state.config.network.data_ttl)?;
     let socket = UdpSocket::from_std(std_socket)?;
 
-    let dest = SocketAddrV4::new(multicast_addr, port);
+    let dest = SocketAddr::new(multicast_addr, port);
 
     // Separate socket for unicast sends (plain UDP, no multicast options)
     let unicast_socket = if state.config.unicast.enabled {
@@ -75,10 +168,34 @@ pub async fn run(
         None
     };
 
+    // Optional parallel senders (per-channel order is kept within a shard)
+    let shard_count = state.config.network.send_shards;
+    let shards = if shard_count > 1 {
+        crate::send_shards::spawn(&state, shard_count, dest)?
+    } else {
+        Vec::new()
+    };
+
+    // Forward error correction (single-socket path only: shards send
+    // independently, so no task sees every packet of a group)
+    let mut fec = FecEncoder::new(state.config.network.fec_group_size);
+    if fec.is_some() && !shards.is_empty() {
+        warn!("network.fec_group_size is ignored with send_shards > 1");
+        fec = None;
+    } else if fec.is_some() {
+        info!(group_size = state.config.network.fec_group_size, "FEC parity enabled");
+    }
+    let mut parity_buf = Vec::with_capacity(512);
+
     let mut sequence: u16 = 0;
     let mut send_buf = Vec::with_capacity(512);
     let mut midi_buf = [0u8; SLOT_SIZE];
     let mut processed_buf = Vec::with_capacity(SLOT_SIZE);
+    let mut merged_notes = MergedNotes::new();
+    let mut mono_voice = MonoVoice::new();
+    let mut note_latch = NoteLatch::new();
+    let mut debouncer = NoteDebouncer::new();
+    let mut cc_throttle = CcThrottle::new();
 
     // Journal is appended periodically (every 100ms) or when state changes significantly
     let mut last_journal_time = Instant::now();
@@ -91,46 +208,96 @@ pub async fn run(
         "MIDI broadcaster started (lock-free ring buffer)"
     );
 
+    let mut shadowing = state.shadowing.subscribe();
+    let mut role = state.role.subscribe();
+
     loop {
-        // Wait for MIDI data from the active input (async, no spin)
-        let len = mux.pop(&mut midi_buf).await;
-        let raw_midi = &midi_buf[..len];
-
-        // Apply the MIDI processing pipeline (filter, remap, velocity curve, etc.)
-        let pipeline_config = state.pipeline_config.read().await;
-        processed_buf.clear();
-
-        // Process each MIDI message through the pipeline
-        let mut offset = 0;
-        while offset < raw_midi.len() {
-            let remaining = &raw_midi[offset..];
-            let (msg_len, _status) = midi_message_length(remaining);
-
-            if msg_len == 0 {
-                offset += 1;
-                continue;
+        // Wait for MIDI data from the active input (async, no spin), or a panic request
+        let mut panicked = false;
+        let mut promoted = false;
+        let held_due = [debouncer.next_due(), cc_throttle.next_due()].into_iter().flatten().min();
+        let clock_due = state.midi_clock.lock().unwrap().next_due();
+        tokio::select! {
+            len = mux.pop(&mut midi_buf) => {
+                if let Some(recorder) = &state.recorder_tx {
+                    let _ = recorder.try_send(RecorderCommand::Midi(midi_buf, len, Instant::now()));
+                }
+
+                // A shadow's state is mirrored from its source; local input waits for promotion
+                if *state.shadowing.borrow() {
+                    continue;
+                }
+
+                // Apply the MIDI processing pipeline (filter, remap, velocity curve, etc.)
+                let pipeline_config = state.pipeline_config.read().await;
+                apply_pipeline(&pipeline_config, &mut merged_notes, &mut mono_voice, &mut note_latch, &mut debouncer, &mut cc_throttle, &midi_buf[..len], &mut processed_buf);
+                drop(pipeline_config);
+
+                // Skip if pipeline filtered everything out
+                if processed_buf.is_empty() {
+                    continue;
+                }
+
+                if has_clock_tick(&processed_buf) {
+                    state.midi_clock.lock().unwrap().observe(Instant::now());
+                }
+
+                // Update MIDI state for journal snapshots
+                let mut midi_state = state.midi_state.write().await;
+                midi_state.process_message(&processed_buf);
             }
-
-            let msg = &remaining[..msg_len];
-
-            if let Some(processed) = pipeline_config.process(msg) {
-                processed_buf.extend_from_slice(&processed);
+            Some(channel) = panic_rx.recv() => {
+                if *state.shadowing.borrow() {
+                    continue;
+                }
+                // Panic bypasses the pipeline: the channel is as the apps see it
+                processed_buf.clear();
+                processed_buf.extend_from_slice(&apply_panic(&state, channel).await);
+                panicked = true;
+                info!(channel = ?channel.map(|c| c + 1), "MIDI panic broadcast");
+            }
+            // Promoted from shadow: go live with a journal of the mirrored state
+            Ok(()) = shadowing.changed(), if *shadowing.borrow() => {
+                if *shadowing.borrow() {
+                    continue;
+                }
+                processed_buf.clear();
+                promoted = true;
+            }
+            // Became the primary (failover or switch-back): send a journal now
+            // so clients re-sync without waiting for the next MIDI, and carry
+            // on the clock the old primary was sending
+            Ok(()) = role.changed() => {
+                if *role.borrow_and_update() != HostRole::Primary {
+                    state.midi_clock.lock().unwrap().stop();
+                    continue;
+                }
+                if *state.shadowing.borrow() {
+                    continue;
+                }
+                state.midi_clock.lock().unwrap().take_over(Instant::now());
+                processed_buf.clear();
+                promoted = true;
+            }
+            // Regenerated clock tick (after a takeover, until the source returns)
+            _ = sleep_until_due(clock_due), if clock_due.is_some() => {
+                state.midi_clock.lock().unwrap().tick_sent();
+                processed_buf.clear();
+                processed_buf.push(CLOCK_TICK);
+            }
+            // Release Note Offs held back by the minimum note duration and
+            // the latest values of throttled controllers
+            _ = sleep_until_due(held_due), if held_due.is_some() => {
+                processed_buf.clear();
+                let now = Instant::now();
+                for m in debouncer.poll(now).into_iter().chain(cc_throttle.poll(now)) {
+                    processed_buf.extend_from_slice(&m);
+                }
+                if processed_buf.is_empty() {
+                    continue;
+                }
+                state.midi_state.write().await.process_message(&processed_buf);
             }
-
-            offset += msg_len;
-        }
-
-        drop(pipeline_config);
-
-        // Skip if pipeline filtered everything out
-        if processed_buf.is_empty() {
-            continue;
-        }
-
-        // Update MIDI state for journal snapshots
-        {
-            let mut midi_state = state.midi_state.write().await;
-            midi_state.process_message(&processed_buf);
         }
 
         // Update metrics
@@ -141,33 +308,54 @@ pub async fn run(
         }
 
         // Attach journal for state recovery — periodically or forced after input switch
-        let force = mux.take_force_journal();
+        let force = mux.take_force_journal() || panicked || promoted;
         let journal = if force || last_journal_time.elapsed() >= journal_interval {
             last_journal_time = Instant::now();
             let midi_state = state.midi_state.read().await;
-            Some(encode_journal(&midi_state))
+            Some(state.encode_journal(&midi_state))
         } else {
             None
         };
 
+        if !shards.is_empty() {
+            crate::send_shards::dispatch(
+                &shards,
+                &processed_buf,
+                &mut sequence,
+                state.packet_clock.now_us(),
+                state.config.host.id,
+                journal,
+            )
+            .await;
+            state.data_sequence.store(sequence, Ordering::Relaxed);
+            mirror_to_osc(&state.osc_mirror_tx, *state.role.borrow(), &processed_buf);
+            continue;
+        }
+
         let packet = MidiDataPacket {
             sequence,
-            timestamp_us: now_us(),
+            timestamp_us: state.packet_clock.now_us(),
             host_id: state.config.host.id,
             midi_data: processed_buf.clone(),
             journal,
         };
 
         packet.serialize(&mut send_buf);
+        if let Some(ref cipher) = state.data_cipher {
+            cipher.seal(&mut send_buf);
+        }
 
-        match socket.send_to(&send_buf, dest).await {
+        let outcome = send_with_retry(&socket, &send_buf, dest, state.config.network.send_retries).await;
+        match &outcome.result {
             Ok(_) => {
-                debug!(seq = sequence, len = send_buf.len(), midi_bytes = processed_buf.len(), "Sent MIDI packet");
+                debug!(seq = sequence, len = send_buf.len(), midi_bytes = processed_buf.len(), retries = outcome.retries, "Sent MIDI packet");
             }
             Err(e) => {
-                error!("Failed to send MIDI packet: {}", e);
+                error!(retries = outcome.retries, "Failed to send MIDI packet: {}", e);
             }
         }
+        send_retry::record(&state, &outcome).await;
+        mirror_to_osc(&state.osc_mirror_tx, *state.role.borrow(), &processed_buf);
 
         // Unicast fan-out: send same packet to each registered client
         if let Some(ref uc_socket) = unicast_socket {
@@ -177,22 +365,105 @@ pub async fn run(
             }
         }
 
+        // Parity after every `fec_group_size` packets, on the same paths
+        if let Some(parity) = fec.as_mut().and_then(|f| f.push(&send_buf)) {
+            parity.serialize(&mut parity_buf);
+            if let Err(e) = socket.send_to(&parity_buf, dest).await {
+                debug!(base_seq = parity.base_sequence, "Failed to send FEC parity: {}", e);
+            }
+            if let Some(ref uc_socket) = unicast_socket {
+                let targets = state.unicast_targets.borrow().clone();
+                for target in &targets {
+                    let _ = uc_socket.send_to(&parity_buf, target).await;
+                }
+            }
+        }
+
         sequence = sequence.wrapping_add(1);
+        state.data_sequence.store(sequence, Ordering::Relaxed);
+    }
+}
+
+/// Hand a copy of sent MIDI to the OSC mirror, which encodes and sends it
+/// off this path. Only the primary mirrors; with both hosts mirroring, OSC
+/// receivers would see every message twice.
+fn mirror_to_osc(mirror: &Option<mpsc::Sender<Vec<u8>>>, role: HostRole, midi: &[u8]) {
+    if role != HostRole::Primary {
+        return;
+    }
+    if let Some(mirror) = mirror {
+        let _ = mirror.try_send(midi.to_vec());
+    }
+}
+
+/// Clean time at a backed-off heartbeat interval before stepping back down
+const HEARTBEAT_RECOVERY: Duration = Duration::from_secs(2);
+
+/// Heartbeat interval for `heartbeat.adaptive`: doubles (up to the max)
+/// whenever a heartbeat send finds the socket buffer full, and halves back
+/// toward the configured interval after `HEARTBEAT_RECOVERY` without one.
+#[derive(Debug)]
+pub(crate) struct HeartbeatPacer {
+    base: Duration,
+    max: Duration,
+    current: Duration,
+    last_change: Instant,
+}
+
+impl HeartbeatPacer {
+    pub fn new(base: Duration, max: Duration, now: Instant) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            current: base,
+            last_change: now,
+        }
+    }
+
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// The interval as advertised in heartbeats.
+    pub fn advertised_ms(&self) -> u16 {
+        self.current.as_millis().min(u16::MAX as u128) as u16
+    }
+
+    /// Feed the outcome of one heartbeat send. Returns the new interval
+    /// when it changes.
+    pub fn record(&mut self, congested: bool, now: Instant) -> Option<Duration> {
+        let next = if congested {
+            // Congestion also restarts the recovery wait
+            self.last_change = now;
+            (self.current * 2).min(self.max)
+        } else if self.current > self.base && now.duration_since(self.last_change) >= HEARTBEAT_RECOVERY {
+            self.last_change = now;
+            (self.current / 2).max(self.base)
+        } else {
+            return None;
+        };
+        if next == self.current {
+            return None;
+        }
+        self.current = next;
+        Some(next)
     }
 }
 
 /// Run the heartbeat broadcaster.
-/// Sends heartbeat packets at the configured interval.
+/// Sends heartbeat packets at the configured interval, or with
+/// `heartbeat.adaptive` at an interval that backs off under congestion.
+/// Each heartbeat advertises the interval it is being sent at.
 pub async fn run_heartbeat(state: Arc<SharedState>) -> anyhow::Result<()> {
-    let multicast_addr: Ipv4Addr = state.config.network.multicast_group.parse()?;
+    let multicast_addr = multicast::parse_group(&state.config.network.multicast_group)?;
     let port = state.config.network.heartbeat_port;
     let interval_ms = state.config.heartbeat.interval_ms;
 
-    let interface = Ipv4Addr::UNSPECIFIED;
-    let std_socket = create_multicast_socket(multicast_addr, 0, interface)?;
+    let interface = state.data_interface.multicast();
+    let std_socket = create_multicast_socket(multicast_addr, 0, interface, state.config.network.data_ttl)?;
     let socket = UdpSocket::from_std(std_socket)?;
 
-    let dest = SocketAddrV4::new(multicast_addr, port);
+    let dest = SocketAddr::new(multicast_addr, port);
 
     // Separate socket for unicast heartbeats
     let unicast_socket = if state.config.unicast.enabled {
@@ -205,29 +476,58 @@ pub async fn run_heartbeat(state: Arc<SharedState>) -> anyhow::Result<()> {
 
     let mut sequence: u16 = 0;
     let mut buf = [0u8; HeartbeatPacket::SIZE];
-    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
+    let mut pacer = HeartbeatPacer::new(
+        Duration::from_millis(interval_ms),
+        Duration::from_millis(state.config.heartbeat.max_interval_ms),
+        Instant::now(),
+    );
+    let adaptive = state.config.heartbeat.adaptive;
+    let mut interval = tokio::time::interval(pacer.current());
 
     info!(
         interval_ms = interval_ms,
+        adaptive,
         "Heartbeat broadcaster started"
     );
 
     loop {
         interval.tick().await;
 
+        // A shadow must not look like a live host to clients
+        if *state.shadowing.borrow() {
+            continue;
+        }
+
         let role = *state.role.borrow();
 
         let packet = HeartbeatPacket {
             host_id: state.config.host.id,
             role,
             sequence,
-            timestamp_us: now_us(),
+            timestamp_us: state.packet_clock.now_us(),
+            interval_ms: pacer.advertised_ms(),
+            priority: state.config.host.effective_priority(),
         };
 
         packet.serialize(&mut buf);
 
+        let mut congested = false;
         if let Err(e) = socket.send_to(&buf, dest).await {
-            error!("Failed to send heartbeat: {}", e);
+            congested = send_retry::is_retryable(&e);
+            if !congested {
+                error!("Failed to send heartbeat: {}", e);
+            }
+        }
+
+        if adaptive {
+            if let Some(next) = pacer.record(congested, Instant::now()) {
+                if congested {
+                    warn!(interval_ms = next.as_millis() as u64, "Heartbeat socket congested, backing off");
+                } else {
+                    info!(interval_ms = next.as_millis() as u64, "Heartbeat congestion cleared, speeding up");
+                }
+                interval = tokio::time::interval_at(tokio::time::Instant::now() + next, next);
+            }
         }
 
         // Unicast heartbeats to each registered client
@@ -246,7 +546,7 @@ pub async fn run_heartbeat(state: Arc<SharedState>) -> anyhow::Result<()> {
 
 /// Determine the length of a MIDI message starting at the given position.
 /// Returns (message_length, status_byte).
-fn midi_message_length(data: &[u8]) -> (usize, u8) {
+pub(crate) fn midi_message_length(data: &[u8]) -> (usize, u8) {
     if data.is_empty() {
         return (0, 0);
     }
@@ -292,3 +592,104 @@ fn midi_message_length(data: &[u8]) -> (usize, u8) {
     // Data byte without status — skip
     (0, 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_state;
+
+    #[test]
+    fn heartbeat_pacer_backs_off_and_recovers() {
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let mut pacer = HeartbeatPacer::new(ms(3), ms(20), t0);
+        assert_eq!(pacer.advertised_ms(), 3);
+
+        assert_eq!(pacer.record(true, t0), Some(ms(6)));
+        assert_eq!(pacer.record(true, t0 + ms(6)), Some(ms(12)));
+        // Capped at the max, and staying congested changes nothing
+        assert_eq!(pacer.record(true, t0 + ms(18)), Some(ms(20)));
+        assert_eq!(pacer.record(true, t0 + ms(38)), None);
+        assert_eq!(pacer.advertised_ms(), 20);
+
+        // Clean sends only step down after the recovery period
+        let clear = t0 + ms(38);
+        assert_eq!(pacer.record(false, clear + ms(100)), None);
+        assert_eq!(pacer.record(false, clear + HEARTBEAT_RECOVERY), Some(ms(10)));
+        assert_eq!(pacer.record(false, clear + HEARTBEAT_RECOVERY * 2), Some(ms(5)));
+        assert_eq!(pacer.record(false, clear + HEARTBEAT_RECOVERY * 3), Some(ms(3)));
+        assert_eq!(pacer.record(false, clear + HEARTBEAT_RECOVERY * 4), None);
+        assert_eq!(pacer.current(), ms(3));
+    }
+
+    #[test]
+    fn only_the_primary_mirrors_to_osc() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mirror = Some(tx);
+        mirror_to_osc(&mirror, HostRole::Standby, &[0x90, 60, 100]);
+        assert!(rx.try_recv().is_err());
+        mirror_to_osc(&mirror, HostRole::Primary, &[0x90, 60, 100]);
+        assert_eq!(rx.try_recv().unwrap(), [0x90, 60, 100]);
+    }
+
+    #[test]
+    fn data_socket_follows_the_group_family() {
+        let v4 = multicast::parse_group("239.69.83.1").unwrap();
+        let socket = create_multicast_socket(v4, 0, MulticastInterface::ANY, 1).unwrap();
+        assert!(socket.local_addr().unwrap().is_ipv4());
+
+        // No IPv6 on this machine: nothing to check
+        let v6 = multicast::parse_group("ff15::6d69").unwrap();
+        if let Ok(socket) = create_multicast_socket(v6, 0, MulticastInterface::ANY, 1) {
+            assert!(socket.local_addr().unwrap().is_ipv6());
+        }
+    }
+
+    #[test]
+    fn harmonized_chord_is_released_by_its_note_off() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.harmonize[0] = Some(vec![0, 4, 7]);
+        let (mut merged, mut mono, mut latch, mut debouncer, mut throttle) =
+            (MergedNotes::new(), MonoVoice::new(), NoteLatch::new(), NoteDebouncer::new(), CcThrottle::new());
+        let mut out = Vec::new();
+
+        // One chunk: a Note On and a CC
+        apply_pipeline(&pipeline, &mut merged, &mut mono, &mut latch, &mut debouncer, &mut throttle, &[0x90, 60, 100, 0xB0, 1, 64], &mut out);
+        assert_eq!(out, vec![0x90, 60, 100, 0x90, 64, 100, 0x90, 67, 100, 0xB0, 1, 64]);
+
+        apply_pipeline(&pipeline, &mut merged, &mut mono, &mut latch, &mut debouncer, &mut throttle, &[0x80, 60, 0], &mut out);
+        assert_eq!(out, vec![0x80, 60, 0, 0x80, 64, 0, 0x80, 67, 0]);
+
+        // Latched, a chord is held as a whole and the next one replaces it
+        pipeline.latch[0] = true;
+        apply_pipeline(&pipeline, &mut merged, &mut mono, &mut latch, &mut debouncer, &mut throttle, &[0x90, 60, 100], &mut out);
+        assert_eq!(out, vec![0x90, 60, 100, 0x90, 64, 100, 0x90, 67, 100]);
+        apply_pipeline(&pipeline, &mut merged, &mut mono, &mut latch, &mut debouncer, &mut throttle, &[0x80, 60, 0], &mut out);
+        assert!(out.is_empty());
+        assert_eq!(latch.held_count(), 3);
+        apply_pipeline(&pipeline, &mut merged, &mut mono, &mut latch, &mut debouncer, &mut throttle, &[0x90, 62, 100], &mut out);
+        assert_eq!(
+            out,
+            vec![0x80, 60, 0, 0x80, 64, 0, 0x80, 67, 0, 0x90, 62, 100, 0x90, 66, 100, 0x90, 69, 100]
+        );
+    }
+
+    #[tokio::test]
+    async fn channel_panic_only_targets_that_channel() {
+        let state = test_state("panic-test");
+        {
+            let mut midi_state = state.midi_state.write().await;
+            midi_state.process_message(&[0x92, 60, 100]);
+            midi_state.process_message(&[0x95, 62, 100]);
+        }
+
+        let bytes = apply_panic(&state, Some(2)).await;
+
+        // Only pedals up + CC 120 / CC 123 on channel 3 (index 2)
+        assert_eq!(bytes, vec![0xB2, 64, 0, 0xB2, 66, 0, 0xB2, 120, 0, 0xB2, 123, 0]);
+
+        let midi_state = state.midi_state.read().await;
+        assert_eq!(midi_state.channels[2].notes[60], 0);
+        assert_eq!(midi_state.channels[5].notes[62], 100);
+    }
+}
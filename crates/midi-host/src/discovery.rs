@@ -9,12 +9,73 @@ use midi_protocol::MDNS_SERVICE_TYPE;
 use crate::SharedState;
 
 /// Run mDNS service advertisement.
-/// Advertises this host as a MIDInet service on the local network.
+/// Advertises this host as a MIDInet service on the local network and
+/// re-registers the service (with a fresh TXT record) when the device
+/// identity changes.
 pub async fn run(state: Arc<SharedState>) -> anyhow::Result<()> {
     let mdns = ServiceDaemon::new()?;
 
     let instance_name = format!("MIDInet {}", state.config.host.name);
 
+    mdns.register(build_service_info(&state, &instance_name).await?)?;
+
+    info!(
+        instance = %instance_name,
+        service_type = MDNS_SERVICE_TYPE,
+        "mDNS service registered"
+    );
+
+    let mut role_rx = state.role.subscribe();
+    let mut identity_rx = state.identity_generation.subscribe();
+    let mut readvertise = state.config.discovery.readvertise_on_identity_change;
+    loop {
+        tokio::select! {
+            result = role_rx.changed() => {
+                match result {
+                    Ok(()) => {
+                        let new_role = *role_rx.borrow();
+                        info!(role = ?new_role, "Role changed, should update mDNS TXT");
+                        // Re-register with updated role would go here
+                    }
+                    Err(_) => break,
+                }
+            }
+            result = identity_rx.changed(), if readvertise => {
+                if result.is_err() {
+                    readvertise = false;
+                    continue;
+                }
+                // Re-registering the same instance replaces the TXT record
+                // and triggers an unsolicited announcement.
+                match build_service_info(&state, &instance_name).await {
+                    Ok(service_info) => {
+                        if let Err(e) = mdns.register(service_info) {
+                            error!("Failed to re-register mDNS service: {}", e);
+                        } else {
+                            info!(instance = %instance_name, "Device identity changed, mDNS service re-advertised");
+                        }
+                    }
+                    Err(e) => error!("Failed to build mDNS service info: {}", e),
+                }
+            }
+            _ = tokio::signal::ctrl_c() => {
+                break;
+            }
+        }
+    }
+
+    // Unregister on shutdown
+    if let Err(e) = mdns.unregister(&format!("{}.{}", instance_name, MDNS_SERVICE_TYPE)) {
+        error!("Failed to unregister mDNS service: {}", e);
+    }
+
+    mdns.shutdown()?;
+
+    Ok(())
+}
+
+/// Build the mDNS service record from the current role and identity.
+async fn build_service_info(state: &SharedState, instance_name: &str) -> anyhow::Result<ServiceInfo> {
     // Build TXT record properties
     let mut properties = HashMap::new();
     properties.insert("id".to_string(), state.config.host.id.to_string());
@@ -48,6 +109,11 @@ pub async fn run(state: Arc<SharedState>) -> anyhow::Result<()> {
         midi_protocol::PROTOCOL_VERSION.to_string(),
     );
 
+    // Surface a network misconfiguration (interface fallback in use)
+    if let Some(ref err) = state.data_interface.error {
+        properties.insert("net_err".to_string(), err.chars().take(200).collect());
+    }
+
     if state.config.admin.enabled {
         properties.insert(
             "admin".to_string(),
@@ -57,49 +123,12 @@ pub async fn run(state: Arc<SharedState>) -> anyhow::Result<()> {
 
     let service_info = ServiceInfo::new(
         MDNS_SERVICE_TYPE,
-        &instance_name,
+        instance_name,
         &format!("{}.local.", state.config.host.name),
         "",
         state.config.network.data_port,
         properties,
     )?;
 
-    mdns.register(service_info)?;
-
-    info!(
-        instance = %instance_name,
-        service_type = MDNS_SERVICE_TYPE,
-        "mDNS service registered"
-    );
-
-    // Keep the mDNS daemon running.
-    // In a full implementation, we'd periodically update the TXT record
-    // when the device identity or role changes.
-    let mut role_rx = state.role.subscribe();
-    loop {
-        tokio::select! {
-            result = role_rx.changed() => {
-                match result {
-                    Ok(()) => {
-                        let new_role = *role_rx.borrow();
-                        info!(role = ?new_role, "Role changed, should update mDNS TXT");
-                        // Re-register with updated role would go here
-                    }
-                    Err(_) => break,
-                }
-            }
-            _ = tokio::signal::ctrl_c() => {
-                break;
-            }
-        }
-    }
-
-    // Unregister on shutdown
-    if let Err(e) = mdns.unregister(&format!("{}.{}", instance_name, MDNS_SERVICE_TYPE)) {
-        error!("Failed to unregister mDNS service: {}", e);
-    }
-
-    mdns.shutdown()?;
-
-    Ok(())
+    Ok(service_info)
 }
@@ -1,35 +1,118 @@
 /// Failover management for the host daemon.
 /// Handles primary/standby negotiation and manual switch triggers.
 /// Thread-safe — uses interior mutability so it can be shared via Arc.
+///
+/// Every switch is recorded as a `FailoverEvent` with its cause, so a
+/// switch can be explained after the fact instead of only counted, and
+/// reported to the admin panel's `/api/failover` history.
+///
+/// With `auto_enabled`, a standby takes over once no host has sent a
+/// Primary heartbeat for a heartbeat-miss window, if it is the host clients
+/// would pick among those still alive (`midi_protocol::failover`).
+///
+/// With `switch_back_policy = "auto"` the host returns to its configured
+/// role once the primary host's heartbeats have been steady for
+/// `switch_back_delay_s`. Any missed heartbeat window restarts the wait,
+/// and the switch back still honours `lockout_seconds`, so a primary that
+/// keeps dropping out can't make the hosts flap.
+///
+/// Each host broadcasts on its own group, so the heartbeat watchers join
+/// the other hosts' groups (`[[failover.peers]]`, or by default the
+/// standard primary/standby groups other than this host's) and drop this
+/// host's own heartbeats. Which host is primary comes from the config:
+/// the best (priority, id) among this host and its peers.
+///
+/// Split-brain resolution: a Primary that keeps hearing another host's
+/// heartbeats claiming Primary for a whole heartbeat-miss window yields if
+/// the other host ranks ahead by (priority, host_id). The loser demotes
+/// regardless of the lockout and stops preferring Primary, so auto
+/// switch-back doesn't hand it straight back. Both raise an alert on the
+/// admin panel until the conflict is over.
+///
+/// An operator can pin one host as primary from the admin panel
+/// (`PUT /api/hosts/:id/promote`). Each host polls the pinned id and moves
+/// to Primary or Standby with `switch_to`, within the lockout like any
+/// other switch — once per pin, so later switches stand.
 
-use std::sync::Mutex;
-use std::time::Instant;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use midi_protocol::packets::HostRole;
+use midi_protocol::failover::{preferred_host, FailoverCause};
+use midi_protocol::multicast;
+use midi_protocol::packets::{HeartbeatPacket, HostRole};
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
 use tokio::sync::watch;
-use tracing::info;
+use tracing::{debug, error, info, warn};
+
+use crate::input_mux::{SwitchBack, SwitchBackTracker};
+use crate::{HostConfig, SharedState};
+
+/// Switches kept in the failover log
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailoverEvent {
+    /// Unix timestamp (seconds)
+    pub timestamp: u64,
+    pub from_host: String,
+    pub to_host: String,
+    pub cause: FailoverCause,
+    /// Free-form context, e.g. "3 heartbeats missed" or the OSC source
+    pub detail: String,
+}
+
+#[derive(Default)]
+struct History {
+    /// Most recent first
+    events: VecDeque<FailoverEvent>,
+    /// Switches recorded since startup
+    recorded: u64,
+}
 
 pub struct FailoverManager {
     lockout_seconds: u64,
     _role_tx: watch::Sender<HostRole>,
     last_switch: Mutex<Option<Instant>>,
+    history: Mutex<History>,
+    /// Role this host holds when nothing has failed (its startup role,
+    /// until it yields Primary to a higher-ranked host)
+    preferred_role: Mutex<HostRole>,
+    switch_back: Mutex<SwitchBackTracker>,
 }
 
 impl FailoverManager {
     pub fn new(lockout_seconds: u64, role_tx: watch::Sender<HostRole>) -> Self {
+        let preferred_role = *role_tx.borrow();
         Self {
             lockout_seconds,
             _role_tx: role_tx,
             last_switch: Mutex::new(None),
+            history: Mutex::new(History::default()),
+            preferred_role: Mutex::new(preferred_role),
+            switch_back: Mutex::new(SwitchBackTracker::new(SwitchBack::Manual, Duration::ZERO)),
         }
     }
 
+    /// Return to the preferred role automatically once the primary has been
+    /// healthy for `delay` (see `poll_switch_back`).
+    pub fn with_switch_back(mut self, policy: SwitchBack, delay: Duration) -> Self {
+        self.switch_back = Mutex::new(SwitchBackTracker::new(policy, delay));
+        self
+    }
+
     /// Check if a switch is allowed based on safety measures (lockout period)
     pub fn can_switch(&self) -> bool {
+        self.can_switch_at(Instant::now())
+    }
+
+    fn can_switch_at(&self, now: Instant) -> bool {
         if let Ok(guard) = self.last_switch.lock() {
             if let Some(last) = *guard {
-                let lockout = std::time::Duration::from_secs(self.lockout_seconds);
-                if last.elapsed() < lockout {
+                let lockout = Duration::from_secs(self.lockout_seconds);
+                if now.saturating_duration_since(last) < lockout {
                     return false;
                 }
             }
@@ -37,10 +120,52 @@ impl FailoverManager {
         true
     }
 
+    /// Feed the primary's heartbeat health; switches back to the preferred
+    /// role once it has been healthy for the whole switch-back delay and
+    /// the lockout has expired. Returns true if the switch was performed.
+    pub fn poll_switch_back(
+        &self,
+        role_tx: &watch::Sender<HostRole>,
+        primary_healthy: bool,
+        now: Instant,
+    ) -> bool {
+        let at_preferred = self.preferred_role.lock().is_ok_and(|p| *role_tx.borrow() == *p);
+        let due = match self.switch_back.lock() {
+            Ok(mut tracker) => tracker.update(at_preferred, primary_healthy, now),
+            Err(_) => false,
+        };
+        // Still locked out: keep waiting, the healthy window stays satisfied
+        if !due || !self.can_switch_at(now) {
+            return false;
+        }
+        let window = self.switch_back.lock().map(|t| t.window()).unwrap_or_default();
+        self.switch_at(
+            role_tx,
+            FailoverCause::SwitchBack,
+            format!("primary healthy for {}s", window.as_secs()),
+            now,
+        )
+    }
+
     /// Trigger a failover switch. Returns true if the switch was performed.
-    pub fn trigger_switch(&self, role_tx: &watch::Sender<HostRole>) -> bool {
-        if !self.can_switch() {
-            info!("Switch blocked by lockout period");
+    pub fn trigger_switch(
+        &self,
+        role_tx: &watch::Sender<HostRole>,
+        cause: FailoverCause,
+        detail: impl Into<String>,
+    ) -> bool {
+        self.switch_at(role_tx, cause, detail, Instant::now())
+    }
+
+    fn switch_at(
+        &self,
+        role_tx: &watch::Sender<HostRole>,
+        cause: FailoverCause,
+        detail: impl Into<String>,
+        now: Instant,
+    ) -> bool {
+        if !self.can_switch_at(now) {
+            info!(cause = cause.as_str(), "Switch blocked by lockout period");
             return false;
         }
 
@@ -49,20 +174,100 @@ impl FailoverManager {
             HostRole::Primary => HostRole::Standby,
             HostRole::Standby => HostRole::Primary,
         };
+        self.record(role_tx, current_role, new_role, cause, detail.into(), now);
+        true
+    }
 
+    /// Move to `target` rather than toggling (the operator pinned which host
+    /// is primary). Honours the lockout; once at `target` it becomes the
+    /// preferred role, so auto switch-back doesn't undo the pin. Returns
+    /// true if the role changed.
+    pub fn switch_to(
+        &self,
+        role_tx: &watch::Sender<HostRole>,
+        target: HostRole,
+        cause: FailoverCause,
+        detail: impl Into<String>,
+    ) -> bool {
+        let now = Instant::now();
+        let current_role = *role_tx.borrow();
+        if current_role != target {
+            if !self.can_switch_at(now) {
+                info!(cause = cause.as_str(), "Switch blocked by lockout period");
+                return false;
+            }
+            self.record(role_tx, current_role, target, cause, detail.into(), now);
+        }
+        if let Ok(mut preferred) = self.preferred_role.lock() {
+            *preferred = target;
+        }
+        current_role != target
+    }
+
+    /// Step down from Primary because a higher-ranked host also claims it.
+    /// Ignores the lockout (two primaries must not wait it out) and makes
+    /// Standby the preferred role from now on. Returns false if this host
+    /// wasn't Primary.
+    pub fn yield_primary(&self, role_tx: &watch::Sender<HostRole>, detail: impl Into<String>) -> bool {
+        if *role_tx.borrow() != HostRole::Primary {
+            return false;
+        }
+        if let Ok(mut preferred) = self.preferred_role.lock() {
+            *preferred = HostRole::Standby;
+        }
+        self.record(role_tx, HostRole::Primary, HostRole::Standby, FailoverCause::SplitBrain, detail.into(), Instant::now());
+        true
+    }
+
+    fn record(
+        &self,
+        role_tx: &watch::Sender<HostRole>,
+        current_role: HostRole,
+        new_role: HostRole,
+        cause: FailoverCause,
+        detail: String,
+        now: Instant,
+    ) {
         role_tx.send(new_role).ok();
 
         if let Ok(mut guard) = self.last_switch.lock() {
-            *guard = Some(Instant::now());
+            *guard = Some(now);
         }
 
+        let event = FailoverEvent {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            from_host: role_name(current_role).to_string(),
+            to_host: role_name(new_role).to_string(),
+            cause,
+            detail,
+        };
+
         info!(
             from = ?current_role,
             to = ?new_role,
+            cause = cause.as_str(),
+            detail = %event.detail,
             "Failover switch triggered"
         );
 
-        true
+        if let Ok(mut history) = self.history.lock() {
+            history.events.push_front(event);
+            history.events.truncate(MAX_HISTORY);
+            history.recorded += 1;
+        }
+    }
+
+    /// Switches recorded after the first `seen`, most recent first, and how
+    /// many have been recorded in all (the `seen` for the next call)
+    pub fn history_since(&self, seen: u64) -> (u64, Vec<FailoverEvent>) {
+        let Ok(history) = self.history.lock() else {
+            return (seen, Vec::new());
+        };
+        let new = history.recorded.saturating_sub(seen).min(history.events.len() as u64) as usize;
+        (history.recorded, history.events.iter().take(new).cloned().collect())
     }
 
     /// Get the current role
@@ -71,3 +276,918 @@ impl FailoverManager {
         *self._role_tx.borrow()
     }
 }
+
+/// Priority of the host that starts as primary when no peers are
+/// configured to rank it against
+pub const PRIMARY_PRIORITY: u8 = 1;
+
+/// Another host in the failover set (`[[failover.peers]]`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct PeerHost {
+    pub id: u8,
+    /// Group the peer broadcasts its data and heartbeats on
+    pub multicast_group: String,
+    /// The peer's `host.priority` (0 = ranked by its id)
+    #[serde(default)]
+    pub priority: u8,
+}
+
+/// This host's view of the failover set: which host the config makes
+/// primary, and which groups carry the other hosts' heartbeats.
+#[derive(Debug, Clone)]
+pub struct FailoverPeers {
+    host_id: u8,
+    priority: u8,
+    /// (host_id, effective priority) of each configured peer
+    peers: Vec<(u8, u8)>,
+    groups: Vec<IpAddr>,
+}
+
+impl FailoverPeers {
+    pub fn from_config(config: &HostConfig) -> anyhow::Result<Self> {
+        let own_group = multicast::parse_group(&config.network.multicast_group)?;
+        let mut peers = Vec::new();
+        let mut groups = Vec::new();
+        for peer in &config.failover.peers {
+            if peer.id == config.host.id {
+                anyhow::bail!("failover.peers lists this host's own id {}", peer.id);
+            }
+            let group = multicast::parse_group(&peer.multicast_group)?;
+            if group.is_ipv4() != own_group.is_ipv4() {
+                anyhow::bail!(
+                    "peer {} group {} is not the same address family as multicast_group {}",
+                    peer.id, group, own_group
+                );
+            }
+            let priority = if peer.priority == 0 { peer.id } else { peer.priority };
+            peers.push((peer.id, priority));
+            groups.push(group);
+        }
+        if config.failover.peers.is_empty() {
+            // Only priority 1 starts as primary without peers to rank
+            // against: 10/20/30 would leave every host in standby
+            if config.host.priority > PRIMARY_PRIORITY {
+                anyhow::bail!(
+                    "host.priority = {} needs [[failover.peers]] listing the other hosts to rank against",
+                    config.host.priority
+                );
+            }
+            for default in [midi_protocol::DEFAULT_PRIMARY_GROUP, midi_protocol::DEFAULT_STANDBY_GROUP] {
+                let group = multicast::parse_group(default)?;
+                if group.is_ipv4() == own_group.is_ipv4() {
+                    groups.push(group);
+                }
+            }
+        }
+        groups.retain(|g| *g != own_group);
+        groups.sort_unstable();
+        groups.dedup();
+
+        Ok(Self {
+            host_id: config.host.id,
+            priority: config.host.effective_priority(),
+            peers,
+            groups,
+        })
+    }
+
+    /// Groups to join to hear the other hosts' heartbeats
+    pub fn groups(&self) -> &[IpAddr] {
+        &self.groups
+    }
+
+    /// Host the config makes primary: the best (priority, id) among this
+    /// host and its peers. Without peers only a priority-1 host knows it is
+    /// primary; the others can't tell which host that is (None).
+    pub fn primary(&self) -> Option<u8> {
+        if self.peers.is_empty() {
+            return (self.priority <= PRIMARY_PRIORITY).then_some(self.host_id);
+        }
+        self.peers
+            .iter()
+            .map(|&(id, priority)| (priority, id))
+            .chain(std::iter::once((self.priority, self.host_id)))
+            .min()
+            .map(|(_, id)| id)
+    }
+
+    /// Whether this host is the configured primary (its own heartbeats
+    /// never count as the primary's)
+    pub fn is_primary(&self) -> bool {
+        self.primary() == Some(self.host_id)
+    }
+
+    /// Whether a stream from `host_id` is the one to follow while this host
+    /// stands by: the configured primary's, or any peer's when this host is
+    /// the configured primary (or can't tell which host is).
+    pub fn is_primary_stream(&self, host_id: u8) -> bool {
+        if host_id == self.host_id {
+            return false;
+        }
+        match self.primary() {
+            Some(id) if id != self.host_id => host_id == id,
+            _ => true,
+        }
+    }
+
+    /// Whether `hb` is the configured primary's heartbeat. Without peers,
+    /// the primary is whichever other host advertises priority 1.
+    pub fn is_primary_heartbeat(&self, hb: &HeartbeatPacket) -> bool {
+        if hb.host_id == self.host_id {
+            return false;
+        }
+        match self.primary() {
+            Some(id) => hb.host_id == id,
+            None => hb.priority <= PRIMARY_PRIORITY,
+        }
+    }
+}
+
+/// After starting, how long to listen for the other hosts before taking
+/// over from a primary that was never heard
+const STARTUP_GRACE: Duration = Duration::from_secs(3);
+
+/// Decides when this host takes over as Primary because no host has sent
+/// a Primary heartbeat for a whole heartbeat-miss window
+/// (`failover.auto_enabled`). Of the standbys still alive only the one
+/// clients would follow, by `preferred_host`, promotes itself.
+pub struct PrimaryWatch {
+    host_id: u8,
+    priority: u8,
+    window: Duration,
+    started: Instant,
+    /// Last Primary heartbeat from another host
+    last_primary: Option<Instant>,
+    /// Other hosts' priority and when each was last heard
+    alive: HashMap<u8, (u8, Instant)>,
+}
+
+impl PrimaryWatch {
+    pub fn new(host_id: u8, priority: u8, window: Duration, now: Instant) -> Self {
+        Self { host_id, priority, window, started: now, last_primary: None, alive: HashMap::new() }
+    }
+
+    /// Feed one heartbeat (this host's own looped-back ones are ignored)
+    pub fn observe(&mut self, hb: &HeartbeatPacket, now: Instant) {
+        if hb.host_id == self.host_id {
+            return;
+        }
+        self.alive.insert(hb.host_id, (hb.priority, now));
+        if hb.role == HostRole::Primary {
+            self.last_primary = Some(now);
+        }
+    }
+
+    /// How long ago the last Primary heartbeat arrived, if that is longer
+    /// than the miss window and this Standby host should take over
+    pub fn takeover_due(&self, own_role: HostRole, now: Instant) -> Option<Duration> {
+        let since = self.last_primary.unwrap_or(self.started + STARTUP_GRACE);
+        let silent = now.saturating_duration_since(since);
+        if own_role != HostRole::Standby || silent <= self.window {
+            return None;
+        }
+        let alive: Vec<(u8, u8)> = self
+            .alive
+            .iter()
+            .filter(|(_, &(_, seen))| now.saturating_duration_since(seen) <= self.window)
+            .map(|(&id, &(priority, _))| (id, priority))
+            .chain(std::iter::once((self.host_id, self.priority)))
+            .collect();
+        (preferred_host(&alive) == Some(self.host_id)).then_some(silent)
+    }
+}
+
+/// Watch the other hosts' heartbeats: take over as Primary when none is
+/// heard (`auto_enabled`, see `PrimaryWatch`) and drive `poll_switch_back`.
+/// The configured primary counts as healthy while its heartbeats arrive
+/// within `heartbeat.miss_threshold` intervals of each other — the
+/// configured `heartbeat.interval_ms` or the primary's advertised interval,
+/// whichever is longer. On the configured primary itself there is nothing
+/// to wait for: it is healthy by definition and returns to its role after
+/// the delay.
+pub async fn run_primary_watch(state: Arc<SharedState>, mgr: Arc<FailoverManager>) -> anyhow::Result<()> {
+    let peers = FailoverPeers::from_config(&state.config)?;
+    let socket = heartbeat_socket(&state, peers.groups())?;
+
+    let heartbeat = &state.config.heartbeat;
+    let misses = heartbeat.miss_threshold.max(1) as u64;
+    let mut health_window = Duration::from_millis(heartbeat.interval_ms * misses);
+    let mut last_primary_heartbeat: Option<Instant> = None;
+    let auto_failover = state.config.failover.auto_enabled;
+    let host = &state.config.host;
+    let mut watch = PrimaryWatch::new(host.id, host.effective_priority(), health_window, Instant::now());
+    let mut ticker = tokio::time::interval(Duration::from_millis(heartbeat.interval_ms.clamp(1, 250)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut buf = [0u8; 64];
+
+    info!(
+        auto_failover,
+        delay_s = state.config.failover.switch_back_delay_s,
+        window_ms = health_window.as_millis() as u64,
+        primary = ?peers.primary(),
+        groups = ?peers.groups(),
+        "Watching the other hosts' heartbeats"
+    );
+
+    loop {
+        tokio::select! {
+            result = socket.recv_from(&mut buf) => match result {
+                Ok((len, _)) => {
+                    let Some(hb) = HeartbeatPacket::deserialize(&buf[..len]) else {
+                        continue;
+                    };
+                    watch.observe(&hb, Instant::now());
+                    if peers.is_primary_heartbeat(&hb) {
+                        let interval_ms = heartbeat.interval_ms.max(hb.interval_ms as u64);
+                        health_window = Duration::from_millis(interval_ms * misses);
+                        watch.window = health_window;
+                        last_primary_heartbeat = Some(Instant::now());
+                    }
+                }
+                Err(e) => {
+                    error!("Heartbeat watch receive error: {}", e);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            },
+            _ = ticker.tick() => {
+                let now = Instant::now();
+                if auto_failover {
+                    if let Some(silent) = watch.takeover_due(*state.role.borrow(), now) {
+                        let detail = format!("no primary heartbeat for {}ms", silent.as_millis());
+                        if mgr.trigger_switch(&state.role, FailoverCause::HeartbeatMiss, detail) {
+                            continue;
+                        }
+                    }
+                }
+                let healthy = peers.is_primary()
+                    || last_primary_heartbeat.is_some_and(|t| now.duration_since(t) <= health_window);
+                mgr.poll_switch_back(&state.role, healthy, now);
+            }
+        }
+    }
+}
+
+/// Report each recorded switch to the admin panel's failover history
+/// (`POST /api/failover/report`). Switches that fail to send are retried
+/// on the next pass, as long as they are still in the log.
+pub async fn report_history(state: Arc<SharedState>, mgr: Arc<FailoverManager>) {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .unwrap_or_default();
+    let url = format!("{}/api/failover/report", state.config.failover.admin_url);
+    let host_id = state.config.host.id;
+    let mut seen = 0;
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+        let (recorded, events) = mgr.history_since(seen);
+        // Oldest first; anything that fell out of the log is skipped
+        seen = recorded - events.len() as u64;
+        for event in events.iter().rev() {
+            let body = serde_json::json!({ "host_id": host_id, "event": event });
+            if let Err(e) = http.post(&url).json(&body).send().await.and_then(|r| r.error_for_status()) {
+                debug!(error = %e, "Failed to report failover event to admin API");
+                break;
+            }
+            seen += 1;
+        }
+    }
+}
+
+/// Role for `host_id` when the admin panel has pinned `designated` as
+/// primary; None leaves the role alone.
+pub fn designated_role(host_id: u8, designated: Option<u8>) -> Option<HostRole> {
+    designated.map(|id| if id == host_id { HostRole::Primary } else { HostRole::Standby })
+}
+
+/// Applies the admin panel's pinned primary once per pin, so switches made
+/// afterwards (OSC, MIDI, a dead pinned host, switch-back) aren't reverted
+/// on the next poll.
+#[derive(Debug, Default)]
+pub struct PinFollower {
+    /// Last pin this host has settled into
+    applied: Option<u8>,
+}
+
+impl PinFollower {
+    /// Move to the role `designated` gives this host if the pin is new.
+    /// A switch held back by the lockout is retried on the next call.
+    /// Returns true if the role changed.
+    pub fn update(
+        &mut self,
+        mgr: &FailoverManager,
+        role_tx: &watch::Sender<HostRole>,
+        host_id: u8,
+        designated: Option<u8>,
+    ) -> bool {
+        if designated == self.applied {
+            return false;
+        }
+        let (Some(target), Some(id)) = (designated_role(host_id, designated), designated) else {
+            // Pin cleared: nothing to undo, roles stay as they are
+            self.applied = None;
+            return false;
+        };
+        let changed = mgr.switch_to(role_tx, target, FailoverCause::Manual, format!("admin pinned host {} as primary", id));
+        if *role_tx.borrow() == target {
+            self.applied = designated;
+        }
+        changed
+    }
+}
+
+/// Poll the admin panel's pinned primary and move this host to match
+/// whenever the operator pins a different host.
+pub async fn follow_designated_primary(state: Arc<SharedState>, mgr: Arc<FailoverManager>) {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .unwrap_or_default();
+
+    let url = format!("{}/api/failover", state.config.failover.admin_url);
+    let host_id = state.config.host.id;
+    let mut follower = PinFollower::default();
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+
+        let body: serde_json::Value = match http.get(&url).send().await {
+            Ok(resp) => match resp.json().await {
+                Ok(v) => v,
+                Err(e) => {
+                    debug!(error = %e, "Failed to parse failover state from admin API");
+                    continue;
+                }
+            },
+            Err(e) => {
+                debug!(error = %e, "Failed to fetch failover state from admin API");
+                continue;
+            }
+        };
+
+        let designated = body["designated_primary"].as_u64().and_then(|id| u8::try_from(id).ok());
+        follower.update(&mgr, &state.role, host_id, designated);
+    }
+}
+
+/// Which side of a split brain this host is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBrain {
+    /// The rival outranks this host: step down to Standby
+    Yield { winner: u8 },
+    /// This host outranks the rival, which is expected to step down
+    Hold { rival: u8 },
+}
+
+/// Detects other hosts claiming Primary while this host is Primary.
+/// A conflict is reported once, after the rival's Primary heartbeats have
+/// kept arriving for `window` — long enough that the overlap of an ordinary
+/// handover doesn't count.
+pub struct SplitBrainDetector {
+    host_id: u8,
+    priority: u8,
+    window: Duration,
+    /// Rival host_id -> (first heard, last heard, reported)
+    conflicts: HashMap<u8, (Instant, Instant, bool)>,
+}
+
+impl SplitBrainDetector {
+    pub fn new(host_id: u8, priority: u8, window: Duration) -> Self {
+        Self { host_id, priority, window, conflicts: HashMap::new() }
+    }
+
+    /// Feed one heartbeat (this host's own looped-back ones are ignored)
+    pub fn observe(&mut self, own_role: HostRole, hb: &HeartbeatPacket, now: Instant) -> Option<SplitBrain> {
+        if hb.host_id == self.host_id {
+            return None;
+        }
+        if own_role != HostRole::Primary || hb.role != HostRole::Primary {
+            self.conflicts.remove(&hb.host_id);
+            return None;
+        }
+
+        let (first, last, reported) = self.conflicts.entry(hb.host_id).or_insert((now, now, false));
+        // Silent for a whole window: a new conflict, not the same one
+        if now.saturating_duration_since(*last) > self.window {
+            (*first, *reported) = (now, false);
+        }
+        *last = now;
+        if *reported || now.saturating_duration_since(*first) < self.window {
+            return None;
+        }
+        *reported = true;
+
+        if (hb.priority, hb.host_id) < (self.priority, self.host_id) {
+            Some(SplitBrain::Yield { winner: hb.host_id })
+        } else {
+            Some(SplitBrain::Hold { rival: hb.host_id })
+        }
+    }
+
+    /// Whether a reported conflict is still going on: this host is Primary
+    /// and the rival's Primary heartbeats haven't gone quiet for a window
+    pub fn in_conflict(&self, own_role: HostRole, now: Instant) -> bool {
+        own_role == HostRole::Primary
+            && self
+                .conflicts
+                .values()
+                .any(|&(_, last, reported)| reported && now.saturating_duration_since(last) <= self.window)
+    }
+}
+
+/// Watch for other hosts claiming Primary while this host is Primary and
+/// resolve the conflict by (priority, host_id): the lower-ranked host yields.
+/// Each host raises a critical `split_brain_host_<id>` alert on the admin
+/// panel (`failover.admin_url`) while it is in a conflict, cleared once the
+/// conflict is over.
+pub async fn run_split_brain(state: Arc<SharedState>, mgr: Arc<FailoverManager>) -> anyhow::Result<()> {
+    let peers = FailoverPeers::from_config(&state.config)?;
+    let socket = heartbeat_socket(&state, peers.groups())?;
+
+    let heartbeat = &state.config.heartbeat;
+    let window = Duration::from_millis(heartbeat.interval_ms * heartbeat.miss_threshold.max(1) as u64);
+    let host = &state.config.host;
+    let mut detector = SplitBrainDetector::new(host.id, host.effective_priority(), window);
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .unwrap_or_default();
+    let mut alerting = false;
+    let mut ticker = tokio::time::interval(Duration::from_millis(250));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut buf = [0u8; 64];
+
+    loop {
+        tokio::select! {
+            result = socket.recv_from(&mut buf) => {
+                let len = match result {
+                    Ok((len, _)) => len,
+                    Err(e) => {
+                        error!("Split-brain heartbeat receive error: {}", e);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        continue;
+                    }
+                };
+                let Some(hb) = HeartbeatPacket::deserialize(&buf[..len]) else {
+                    continue;
+                };
+                let own_role = *state.role.borrow();
+                let message = match detector.observe(own_role, &hb, Instant::now()) {
+                    Some(SplitBrain::Yield { winner }) => {
+                        mgr.yield_primary(&state.role, format!("host {} also primary (priority {})", winner, hb.priority));
+                        format!("Split brain: host {} also claims primary and outranks host {}, which demoted to standby", winner, host.id)
+                    }
+                    Some(SplitBrain::Hold { rival }) => {
+                        format!("Split brain: host {} also claims primary; host {} outranks it and stays primary", rival, host.id)
+                    }
+                    None => continue,
+                };
+                error!(rival = hb.host_id, rival_priority = hb.priority, priority = host.effective_priority(), "ALERT: {}", message);
+                alerting = true;
+                report_split_brain(&http, &state, true, message);
+            }
+            _ = ticker.tick() => {
+                if alerting && !detector.in_conflict(*state.role.borrow(), Instant::now()) {
+                    alerting = false;
+                    info!("Split brain resolved");
+                    report_split_brain(&http, &state, false, String::new());
+                }
+            }
+        }
+    }
+}
+
+/// Set or clear this host's split-brain alert on the admin panel
+fn report_split_brain(http: &reqwest::Client, state: &SharedState, active: bool, message: String) {
+    let admin_url = &state.config.failover.admin_url;
+    if admin_url.is_empty() {
+        return;
+    }
+    let request = http.post(format!("{}/api/alerts/report", admin_url)).json(&serde_json::json!({
+        "source": format!("split_brain_host_{}", state.config.host.id),
+        "active": active,
+        "severity": "Critical",
+        "message": message,
+    }));
+    tokio::spawn(async move {
+        if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+            debug!(error = %e, "Failed to report split brain to admin API");
+        }
+    });
+}
+
+/// Listener for the other hosts' heartbeats on `groups`, sharing the port
+/// with the host's other heartbeat watchers
+fn heartbeat_socket(state: &SharedState, groups: &[IpAddr]) -> anyhow::Result<UdpSocket> {
+    let own_group = multicast::parse_group(&state.config.network.multicast_group)?;
+    let port = state.config.network.heartbeat_port;
+    if groups.is_empty() {
+        warn!("No peer heartbeat groups to join; set [[failover.peers]]");
+    }
+
+    let sock = multicast::new_socket(own_group)?;
+    sock.set_reuse_address(true)?;
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    sock.set_reuse_port(true)?;
+    sock.bind(&multicast::bind_addr(own_group, port).into())?;
+    for &group in groups {
+        multicast::join(&sock, group, state.data_interface.multicast())?;
+    }
+    sock.set_nonblocking(true)?;
+    Ok(UdpSocket::from_std(sock.into())?)
+}
+
+fn role_name(role: HostRole) -> &'static str {
+    match role {
+        HostRole::Primary => "primary",
+        HostRole::Standby => "standby",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host_config;
+
+    #[test]
+    fn switches_record_distinct_causes() {
+        let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+        let mgr = FailoverManager::new(0, role_tx.clone());
+
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::HeartbeatMiss, "3 heartbeats missed"));
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::Manual, "admin API"));
+
+        let history = mgr.history_since(0).1;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].cause, FailoverCause::Manual);
+        assert_eq!((history[0].from_host.as_str(), history[0].to_host.as_str()), ("standby", "primary"));
+        assert_eq!(history[1].cause, FailoverCause::HeartbeatMiss);
+        assert_eq!(history[1].detail, "3 heartbeats missed");
+        assert_eq!((history[1].from_host.as_str(), history[1].to_host.as_str()), ("primary", "standby"));
+    }
+
+    #[test]
+    fn silent_primary_promotes_only_the_preferred_standby() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let window = Duration::from_millis(9);
+        let mut watches = [PrimaryWatch::new(2, 20, window, t0), PrimaryWatch::new(3, 30, window, t0)];
+
+        // Host 1 is primary until 30ms, the standbys keep going
+        for n in (0..60).step_by(3) {
+            for watch in &mut watches {
+                if n < 30 {
+                    watch.observe(&heartbeat(1, 10, HostRole::Primary), ms(n));
+                }
+                watch.observe(&heartbeat(2, 20, HostRole::Standby), ms(n));
+                watch.observe(&heartbeat(3, 30, HostRole::Standby), ms(n));
+            }
+        }
+        assert_eq!(watches[0].takeover_due(HostRole::Standby, ms(35)), None, "inside the miss window");
+        assert_eq!(watches[0].takeover_due(HostRole::Standby, ms(57)), Some(Duration::from_millis(30)));
+        assert_eq!(watches[1].takeover_due(HostRole::Standby, ms(57)), None, "host 2 ranks ahead");
+        assert_eq!(watches[0].takeover_due(HostRole::Primary, ms(57)), None);
+
+        // The takeover is logged as a heartbeat miss, unlike a manual switch
+        let (role_tx, _role_rx) = watch::channel(HostRole::Standby);
+        let mgr = FailoverManager::new(0, role_tx.clone());
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::HeartbeatMiss, "no primary heartbeat for 30ms"));
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::Manual, "admin API"));
+        let (recorded, history) = mgr.history_since(0);
+        assert_eq!(recorded, 2);
+        assert_eq!(history[1].cause, FailoverCause::HeartbeatMiss);
+        assert_eq!(history[0].cause, FailoverCause::Manual);
+        // Only what is new since the last report
+        assert_eq!(mgr.history_since(1).1, history[..1]);
+        assert!(mgr.history_since(2).1.is_empty());
+
+        // A standby that never heard a primary waits out the startup grace
+        let lone = PrimaryWatch::new(2, 20, window, t0);
+        assert_eq!(lone.takeover_due(HostRole::Standby, ms(100)), None);
+        assert!(lone.takeover_due(HostRole::Standby, t0 + STARTUP_GRACE + Duration::from_millis(10)).is_some());
+    }
+
+    #[test]
+    fn blocked_switch_is_not_recorded() {
+        let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+        let mgr = FailoverManager::new(60, role_tx.clone());
+
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::Osc, "from 10.0.0.5"));
+        assert!(!mgr.trigger_switch(&role_tx, FailoverCause::Midi, "trigger note"));
+        assert_eq!(mgr.history_since(0).1.len(), 1);
+    }
+
+    #[test]
+    fn flapping_primary_never_wins_back_and_lockout_holds() {
+        let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+        let mgr = FailoverManager::new(30, role_tx.clone())
+            .with_switch_back(SwitchBack::Auto, Duration::from_secs(10));
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::HeartbeatMiss, "3 heartbeats missed"));
+        assert_eq!(*role_tx.borrow(), HostRole::Standby);
+
+        // Primary comes back for 8s at a time, then drops a heartbeat window
+        for cycle in 0..5 {
+            let start = 40 + cycle * 9;
+            for s in start..start + 8 {
+                assert!(!mgr.poll_switch_back(&role_tx, true, at(s)), "switched back at {s}s");
+            }
+            assert!(!mgr.poll_switch_back(&role_tx, false, at(start + 8)));
+        }
+        assert_eq!(*role_tx.borrow(), HostRole::Standby);
+
+        // Healthy for the full delay, but still within the lockout: wait
+        let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+        let mgr = FailoverManager::new(30, role_tx.clone())
+            .with_switch_back(SwitchBack::Auto, Duration::from_secs(10));
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::Osc, "OSC"));
+        for s in 0..=29 {
+            assert!(!mgr.poll_switch_back(&role_tx, true, at(s)), "switched back at {s}s");
+        }
+        assert!(mgr.poll_switch_back(&role_tx, true, at(31)));
+        assert_eq!(*role_tx.borrow(), HostRole::Primary);
+        assert_eq!(mgr.history_since(0).1[0].cause, FailoverCause::SwitchBack);
+
+        // Back at the preferred role: nothing more to do
+        assert!(!mgr.poll_switch_back(&role_tx, true, at(100)));
+    }
+
+    #[test]
+    fn manual_policy_never_switches_back() {
+        let (role_tx, _role_rx) = watch::channel(HostRole::Standby);
+        let mgr = FailoverManager::new(0, role_tx.clone())
+            .with_switch_back(SwitchBack::Manual, Duration::from_secs(1));
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::Manual, "admin API"));
+        let t0 = Instant::now();
+        for s in 0..60 {
+            assert!(!mgr.poll_switch_back(&role_tx, true, t0 + Duration::from_secs(s)));
+        }
+        assert_eq!(*role_tx.borrow(), HostRole::Primary);
+    }
+
+    #[test]
+    fn two_hosts_on_separate_groups_switch_back() {
+        let configs = [host_config(1, "239.69.83.1", 0, ""), host_config(2, "239.69.83.2", 0, "")];
+        let peers: Vec<_> = configs.iter().map(|c| FailoverPeers::from_config(c).unwrap()).collect();
+        let groups: Vec<IpAddr> = configs.iter().map(|c| c.network.multicast_group.parse().unwrap()).collect();
+        // Each listens on the other's group, never its own
+        assert_eq!(peers[0].groups(), &groups[1..]);
+        assert_eq!(peers[1].groups(), &groups[..1]);
+        assert_eq!((peers[0].primary(), peers[1].primary()), (Some(1), None));
+
+        // An operator swapped the roles; both return on their own
+        let nodes: Vec<_> = [HostRole::Primary, HostRole::Standby]
+            .into_iter()
+            .map(|role| {
+                let (role_tx, role_rx) = watch::channel(role);
+                let mgr = FailoverManager::new(0, role_tx.clone())
+                    .with_switch_back(SwitchBack::Auto, Duration::from_millis(30));
+                assert!(mgr.trigger_switch(&role_tx, FailoverCause::Manual, "admin API"));
+                (role_tx, role_rx, mgr)
+            })
+            .collect();
+
+        let run = |host_1_alive: bool, from_ms: u64| {
+            let t0 = Instant::now() + Duration::from_millis(from_ms);
+            let mut last_primary: [Option<Instant>; 2] = [None, None];
+            for ms in (0..100u64).step_by(3) {
+                let now = t0 + Duration::from_millis(ms);
+                for (sender, (tx, _, _)) in nodes.iter().enumerate() {
+                    if sender == 0 && !host_1_alive {
+                        continue;
+                    }
+                    let hb = heartbeat(sender as u8 + 1, sender as u8 + 1, *tx.borrow());
+                    for (listener, p) in peers.iter().enumerate() {
+                        // Delivered to joined groups, and looped back to the sender
+                        let heard = listener == sender || p.groups().contains(&groups[sender]);
+                        if heard && p.is_primary_heartbeat(&hb) {
+                            last_primary[listener] = Some(now);
+                        }
+                    }
+                }
+                for (i, (tx, _, mgr)) in nodes.iter().enumerate() {
+                    let healthy = peers[i].is_primary()
+                        || last_primary[i].is_some_and(|t| now.duration_since(t) <= Duration::from_millis(9));
+                    mgr.poll_switch_back(tx, healthy, now);
+                }
+            }
+        };
+
+        // Host 1 silent: host 2 hears no primary (not even its own heartbeats)
+        // and stays Primary; host 1 returns only because it is the primary
+        run(false, 0);
+        assert_eq!(*nodes[1].0.borrow(), HostRole::Primary);
+        assert_eq!(*nodes[0].0.borrow(), HostRole::Primary);
+
+        // Host 1's heartbeats reach host 2 on host 1's group: host 2 steps back
+        run(true, 1000);
+        assert_eq!(*nodes[1].0.borrow(), HostRole::Standby);
+        assert_eq!(nodes[1].2.history_since(0).1[0].cause, FailoverCause::SwitchBack);
+    }
+
+    #[test]
+    fn configured_peers_rank_the_primary() {
+        let peers = r#"
+            [[failover.peers]]
+            id = 1
+            multicast_group = "239.69.83.1"
+            priority = 20
+
+            [[failover.peers]]
+            id = 3
+            multicast_group = "239.69.83.3"
+            priority = 30
+        "#;
+        let mut config = host_config(2, "239.69.83.2", 10, peers);
+        let peers = FailoverPeers::from_config(&config).unwrap();
+
+        assert_eq!(peers.groups().len(), 2);
+        assert_eq!(peers.primary(), Some(2));
+        assert!(peers.is_primary());
+        assert!(!peers.is_primary_heartbeat(&heartbeat(2, 10, HostRole::Primary)));
+        assert!(!peers.is_primary_heartbeat(&heartbeat(1, 1, HostRole::Primary)));
+
+        config.failover.peers[0].priority = 5;
+        let peers = FailoverPeers::from_config(&config).unwrap();
+        assert_eq!(peers.primary(), Some(1));
+        assert!(peers.is_primary_heartbeat(&heartbeat(1, 5, HostRole::Primary)));
+    }
+
+    #[test]
+    fn explicit_priorities_start_exactly_one_primary() {
+        let hosts = [(1u8, 20u8), (2, 10), (3, 30)];
+        let group = |id: u8| format!("239.69.83.{id}");
+        let primaries: Vec<u8> = hosts
+            .iter()
+            .filter(|&&(id, priority)| {
+                let peers: String = hosts
+                    .iter()
+                    .filter(|&&(peer, _)| peer != id)
+                    .map(|&(peer, p)| {
+                        format!("[[failover.peers]]\nid = {peer}\nmulticast_group = \"{}\"\npriority = {p}\n", group(peer))
+                    })
+                    .collect();
+                FailoverPeers::from_config(&host_config(id, &group(id), priority, &peers)).unwrap().is_primary()
+            })
+            .map(|&(id, _)| id)
+            .collect();
+        assert_eq!(primaries, [2]);
+
+        // Without peers a priority of 10 can't be ranked: refuse to start
+        assert!(FailoverPeers::from_config(&host_config(2, "239.69.83.2", 10, "")).is_err());
+        assert!(FailoverPeers::from_config(&host_config(1, "239.69.83.1", 1, "")).unwrap().is_primary());
+    }
+
+    fn heartbeat(host_id: u8, priority: u8, role: HostRole) -> HeartbeatPacket {
+        HeartbeatPacket { host_id, role, sequence: 0, timestamp_us: 0, interval_ms: 3, priority }
+    }
+
+    struct Node {
+        id: u8,
+        priority: u8,
+        group: IpAddr,
+        peers: FailoverPeers,
+        role_tx: watch::Sender<HostRole>,
+        _role_rx: watch::Receiver<HostRole>,
+        mgr: FailoverManager,
+        detector: SplitBrainDetector,
+    }
+
+    #[test]
+    fn split_brain_leaves_exactly_one_primary() {
+        let window = Duration::from_millis(9);
+        // Both hosts start as Primary, each on its own group; host 2 is
+        // configured ahead of host 1
+        let hosts = [(1u8, 5u8), (2, 1)];
+        let group = |id: u8| format!("239.69.83.{id}");
+        let mut nodes: Vec<Node> = hosts
+            .iter()
+            .map(|&(id, priority)| {
+                let (peer, peer_priority) = hosts.into_iter().find(|&(peer, _)| peer != id).unwrap();
+                let peers = format!("[[failover.peers]]\nid = {peer}\nmulticast_group = \"{}\"\npriority = {peer_priority}", group(peer));
+                let peers = FailoverPeers::from_config(&host_config(id, &group(id), priority, &peers)).unwrap();
+                let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+                let mgr = FailoverManager::new(30, role_tx.clone())
+                    .with_switch_back(SwitchBack::Auto, Duration::from_millis(1));
+                let detector = SplitBrainDetector::new(id, priority, window);
+                Node { id, priority, group: group(id).parse().unwrap(), peers, role_tx, _role_rx, mgr, detector }
+            })
+            .collect();
+        let primaries = |nodes: &[Node]| nodes.iter().filter(|n| *n.role_tx.borrow() == HostRole::Primary).count();
+
+        let t0 = Instant::now();
+        let mut alerted = [false; 2];
+        for ms in (0..30u64).step_by(3) {
+            let now = t0 + Duration::from_millis(ms);
+            let beats: Vec<_> = nodes.iter().map(|n| (n.group, heartbeat(n.id, n.priority, *n.role_tx.borrow()))).collect();
+            for (sender, (group, hb)) in beats.iter().enumerate() {
+                for (listener, node) in nodes.iter_mut().enumerate() {
+                    // Delivered on the peer groups, and looped back to the sender
+                    if listener != sender && !node.peers.groups().contains(group) {
+                        continue;
+                    }
+                    let own = *node.role_tx.borrow();
+                    match node.detector.observe(own, hb, now) {
+                        Some(SplitBrain::Yield { winner }) => {
+                            alerted[listener] = true;
+                            assert!(node.mgr.yield_primary(&node.role_tx, format!("host {} also primary", winner)));
+                        }
+                        Some(SplitBrain::Hold { .. }) => alerted[listener] = true,
+                        None => {}
+                    }
+                }
+            }
+            if ms < 9 {
+                assert_eq!(primaries(&nodes), 2, "resolved inside the window at {ms}ms");
+            }
+        }
+
+        assert_eq!(primaries(&nodes), 1);
+        assert_eq!(alerted, [true, true], "both hosts raise the alert");
+        let (loser, winner) = (&nodes[0], &nodes[1]);
+        assert_eq!(*winner.role_tx.borrow(), HostRole::Primary);
+        assert_eq!(*loser.role_tx.borrow(), HostRole::Standby);
+        // The conflict is over on both sides, so the alerts clear
+        let end = t0 + Duration::from_millis(30);
+        assert!(!loser.detector.in_conflict(*loser.role_tx.borrow(), end));
+        assert!(!winner.detector.in_conflict(*winner.role_tx.borrow(), end));
+        // The lockout didn't hold the demotion back, and it's on the record
+        assert_eq!(loser.mgr.history_since(0).1[0].cause, FailoverCause::SplitBrain);
+        assert!(winner.mgr.history_since(0).1.is_empty());
+
+        // The loser no longer prefers Primary, so auto switch-back leaves it be
+        assert!(!loser.mgr.poll_switch_back(&loser.role_tx, true, t0 + Duration::from_secs(120)));
+        assert_eq!(*loser.role_tx.borrow(), HostRole::Standby);
+    }
+
+    #[test]
+    fn pinning_host_3_of_3_settles_one_primary() {
+        // Host 1 is primary, host 2 standby, host 3 standby after a failover
+        let nodes: Vec<_> = [(1u8, HostRole::Primary), (2, HostRole::Standby), (3, HostRole::Standby)]
+            .into_iter()
+            .map(|(id, role)| {
+                let (role_tx, role_rx) = watch::channel(role);
+                let mgr = FailoverManager::new(30, role_tx.clone())
+                    .with_switch_back(SwitchBack::Auto, Duration::from_secs(1));
+                (id, role_tx, role_rx, mgr)
+            })
+            .collect();
+
+        for (id, role_tx, _, mgr) in &nodes {
+            let target = designated_role(*id, Some(3)).unwrap();
+            mgr.switch_to(role_tx, target, FailoverCause::Manual, "admin pinned host 3 as primary");
+        }
+        let roles: Vec<_> = nodes.iter().map(|(_, tx, _, _)| *tx.borrow()).collect();
+        assert_eq!(roles, [HostRole::Standby, HostRole::Standby, HostRole::Primary]);
+        assert_eq!(nodes[0].3.history_since(0).1[0].cause, FailoverCause::Manual);
+        assert!(nodes[1].3.history_since(0).1.is_empty(), "already standby: nothing to record");
+
+        // Steady primary heartbeats don't pull host 1 back, and a second
+        // pin inside the lockout waits rather than switching
+        let later = Instant::now() + Duration::from_secs(120);
+        assert!(!nodes[0].3.poll_switch_back(&nodes[0].1, true, later));
+        assert!(!nodes[2].3.switch_to(&nodes[2].1, HostRole::Standby, FailoverCause::Manual, "re-pin"));
+        assert_eq!(*nodes[2].1.borrow(), HostRole::Primary);
+
+        // No pin: roles are left alone
+        assert_eq!(designated_role(1, None), None);
+    }
+
+    #[test]
+    fn pin_is_applied_once_and_later_switches_stand() {
+        let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+        let mgr = FailoverManager::new(0, role_tx.clone());
+        let mut follower = PinFollower::default();
+
+        // Host 2 follows the pin on host 3
+        assert!(follower.update(&mgr, &role_tx, 2, Some(3)));
+        assert_eq!(*role_tx.borrow(), HostRole::Standby);
+
+        // Host 3 dies and host 2 takes over; the unchanged pin doesn't
+        // demote it again on the next polls
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::HeartbeatMiss, "3 heartbeats missed"));
+        for _ in 0..5 {
+            assert!(!follower.update(&mgr, &role_tx, 2, Some(3)));
+        }
+        assert_eq!(*role_tx.borrow(), HostRole::Primary);
+
+        // A new pin is followed, and clearing it changes nothing
+        assert!(follower.update(&mgr, &role_tx, 2, Some(1)));
+        assert_eq!(*role_tx.borrow(), HostRole::Standby);
+        assert!(!follower.update(&mgr, &role_tx, 2, None));
+        assert_eq!(*role_tx.borrow(), HostRole::Standby);
+
+        // A pin held back by the lockout is retried until it lands
+        let (role_tx, _role_rx) = watch::channel(HostRole::Primary);
+        let mgr = FailoverManager::new(60, role_tx.clone());
+        let mut follower = PinFollower::default();
+        assert!(mgr.trigger_switch(&role_tx, FailoverCause::Osc, "OSC"));
+        assert!(!follower.update(&mgr, &role_tx, 2, Some(2)));
+        assert_eq!(follower.applied, None);
+    }
+}
@@ -4,21 +4,57 @@
 ///
 /// In dual-controller mode, feedback MIDI is sent to BOTH controllers
 /// simultaneously so LED state, displays, and motorized faders stay in sync.
+///
+/// With `focus.focus_follows_activity`, focus moves to whichever client last
+/// sent feedback MIDI once the current holder has been idle for
+/// `focus.activity_idle_ms`. Clients are recognised by the address their
+/// focus claims came from.
+///
+/// With `focus.gate_output`, upstream MIDI from clients that do not hold
+/// focus is dropped on `focus.gated_channels` (all channels when empty), so
+/// a shared instrument only hears the focused client there.
+///
+/// With `focus.reset_on_focus_loss` set, that MIDI is written to the
+/// controllers whenever a client loses focus (transfer, release or
+/// timeout), so LEDs left lit by its app return to a neutral state.
+///
+/// With `[[focus.indicators]]` configured for the controller, a "focused"
+/// or "unfocused" MIDI message (SysEx or CC bytes, per device) is written
+/// to the controllers whenever the focus holder changes, so their LEDs can
+/// show whether a client has focus. A holder's periodic re-claim is not a
+/// change and sends nothing.
+///
+/// Clients asking for unicast delivery send their `SubscribePacket`s
+/// straight to this port; they are handed to the unicast relay's
+/// subscriber list.
 
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use socket2::{Domain, Protocol, Socket, Type};
+use serde::Deserialize;
 use tokio::net::UdpSocket;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-use midi_protocol::packets::{FocusAction, FocusPacket, MidiDataPacket, MAGIC_FOCUS, MAGIC_MIDI};
+use midi_protocol::multicast::{self, MulticastInterface};
+use midi_protocol::packets::{
+    FocusAction, FocusPacket, InputEnablePacket, MidiDataPacket, PanicPacket, RecordPacket,
+    SubscribePacket, MAGIC_FOCUS, MAGIC_INPUT_ENABLE, MAGIC_MIDI, MAGIC_PANIC, MAGIC_RECORD,
+    MAGIC_SUBSCRIBE,
+};
 
+use crate::broadcaster::midi_message_length;
+use crate::input_mux::InputMux;
 use crate::midi_output::platform::MidiOutputWriter;
+use crate::recorder::RecorderCommand;
 use crate::SharedState;
 
+/// How long a client address is remembered without a claim or feedback
+/// from it (clients re-claim every 5s)
+const CLIENT_ADDR_TTL: Duration = Duration::from_secs(30);
+
 /// Tracks the current focus holder
 pub struct FocusState {
     /// Client ID of the current focus holder (None = no focus)
@@ -29,6 +65,9 @@ pub struct FocusState {
     pub claimed_at: Option<Instant>,
     /// Focus auto-release timeout (10s without feedback → release)
     pub last_feedback: Option<Instant>,
+    /// Client ID behind each address that has sent a focus claim, and
+    /// when that address was last heard from
+    pub client_addrs: HashMap<SocketAddr, (u32, Instant)>,
 }
 
 impl Default for FocusState {
@@ -38,10 +77,129 @@ impl Default for FocusState {
             last_claim_seq: 0,
             claimed_at: None,
             last_feedback: None,
+            client_addrs: HashMap::new(),
         }
     }
 }
 
+impl FocusState {
+    /// Focus-follows-activity: feedback from `client_id` takes focus when
+    /// nobody holds it or the holder has sent nothing for `idle`.
+    /// Returns true if focus moved to `client_id`.
+    pub fn follow_activity(&mut self, client_id: u32, idle: Duration, now: Instant) -> bool {
+        if self.holder == Some(client_id) {
+            return false;
+        }
+        let holder_idle = self
+            .last_feedback
+            .is_none_or(|last| now.saturating_duration_since(last) >= idle);
+        if self.holder.is_some() && !holder_idle {
+            return false;
+        }
+        self.holder = Some(client_id);
+        self.claimed_at = Some(now);
+        self.last_feedback = Some(now);
+        true
+    }
+
+    /// Client ID behind the focus claims from `source`.
+    pub fn client_id(&self, source: &SocketAddr) -> Option<u32> {
+        self.client_addrs.get(source).map(|&(id, _)| id)
+    }
+
+    /// True if the focus claims from `source` were made by the holder.
+    pub fn is_holder(&self, source: &SocketAddr) -> bool {
+        self.holder.is_some() && self.client_id(source) == self.holder
+    }
+
+    /// Record a claim by `client_id` from `source`. A client that came back
+    /// on another port is only known by the new one.
+    pub fn note_client(&mut self, source: SocketAddr, client_id: u32, now: Instant) {
+        self.client_addrs.retain(|addr, (id, _)| *id != client_id || *addr == source);
+        self.client_addrs.insert(source, (client_id, now));
+    }
+
+    /// Mark a known client address as still alive.
+    pub fn touch(&mut self, source: &SocketAddr, now: Instant) {
+        if let Some((_, seen)) = self.client_addrs.get_mut(source) {
+            *seen = now;
+        }
+    }
+
+    /// Forget client addresses silent for `CLIENT_ADDR_TTL`, except the
+    /// holder's. Returns true if any were dropped.
+    pub fn prune_clients(&mut self, now: Instant) -> bool {
+        let before = self.client_addrs.len();
+        let holder = self.holder;
+        self.client_addrs
+            .retain(|_, &mut (id, seen)| Some(id) == holder || now.saturating_duration_since(seen) < CLIENT_ADDR_TTL);
+        self.client_addrs.len() != before
+    }
+}
+
+/// `[[focus.indicators]]`: what the controllers are sent when the focus
+/// holder changes
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FocusIndicator {
+    /// Controller this applies to (`midi.device`); empty = any device
+    #[serde(default)]
+    pub device: String,
+    /// Sent when a client takes focus (or it moves to another client)
+    #[serde(default)]
+    pub focused: Vec<u8>,
+    /// Sent when no client holds focus any more
+    #[serde(default)]
+    pub unfocused: Vec<u8>,
+}
+
+impl FocusIndicator {
+    /// The entry for `device`: an exact match, else one with no device
+    pub fn for_device<'a>(indicators: &'a [FocusIndicator], device: &str) -> Option<&'a FocusIndicator> {
+        indicators
+            .iter()
+            .find(|i| i.device == device)
+            .or_else(|| indicators.iter().find(|i| i.device.is_empty()))
+    }
+
+    /// The message to write for `holder`, if it differs from `announced`
+    /// (the holder last announced, updated here).
+    pub fn on_change(&self, announced: &mut Option<u32>, holder: Option<u32>) -> Option<&[u8]> {
+        if *announced == holder {
+            return None;
+        }
+        *announced = holder;
+        let midi = if holder.is_some() { &self.focused } else { &self.unfocused };
+        (!midi.is_empty()).then_some(midi.as_slice())
+    }
+}
+
+/// The reset feedback to write when focus moves away from `lost`, if any.
+pub fn focus_loss_reset(lost: Option<u32>, reset: &[u8]) -> Option<&[u8]> {
+    lost.filter(|_| !reset.is_empty()).map(|_| reset)
+}
+
+/// Drop the messages in `midi` that fall under the focus gate: channel
+/// messages on `gated_channels` (1-16), or everything when that is empty.
+/// Used for upstream MIDI from clients that do not hold focus.
+pub fn gate_non_holder(midi: &[u8], gated_channels: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(midi.len());
+    let mut offset = 0;
+    while offset < midi.len() {
+        let (len, status) = midi_message_length(&midi[offset..]);
+        if len == 0 {
+            offset += 1;
+            continue;
+        }
+        let gated = gated_channels.is_empty()
+            || ((0x80..0xF0).contains(&status) && gated_channels.contains(&((status & 0x0F) + 1)));
+        if !gated {
+            out.extend_from_slice(&midi[offset..offset + len]);
+        }
+        offset += len;
+    }
+    out
+}
+
 fn now_us() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -49,51 +207,85 @@ fn now_us() -> u64 {
         .as_micros() as u64
 }
 
+/// Create the socket used to send on the control multicast group.
+/// TTL and interface come from the control settings, independent of the
+/// data-group socket options in the broadcaster.
+pub(crate) fn create_control_send_socket(
+    group: IpAddr,
+    ttl: u32,
+    interface: MulticastInterface,
+) -> std::io::Result<std::net::UdpSocket> {
+    let sock = multicast::new_socket(group)?;
+    multicast::set_outgoing(&sock, group, interface, ttl, true)?;
+    sock.set_nonblocking(true)?;
+    sock.bind(&multicast::bind_addr(group, 0).into())?;
+    Ok(sock.into())
+}
+
 /// Run the feedback receiver and focus manager.
 /// Uses proper async recv_from for minimal latency (no polling).
 /// `midi_output` writes feedback MIDI to all connected controllers.
+/// `mux` takes input enable/disable requests (None in single-controller mode).
 pub async fn run(
     state: Arc<SharedState>,
     focus_state: Arc<RwLock<FocusState>>,
     midi_output: Arc<MidiOutputWriter>,
+    mux: Option<Arc<InputMux>>,
 ) -> anyhow::Result<()> {
-    let control_group: Ipv4Addr = state.config.network.control_group.parse()?;
+    let control_group = multicast::parse_group(&state.config.network.control_group)?;
     let control_port = state.config.network.control_port;
 
+    let control_ttl = state.config.network.control_ttl;
+    let control_interface = state.control_interface.multicast();
+
     // Socket for receiving focus + feedback packets
     let recv_socket = {
-        let sock = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let sock = multicast::new_socket(control_group)?;
         sock.set_reuse_address(true)?;
         #[cfg(any(target_os = "macos", target_os = "freebsd"))]
         sock.set_reuse_port(true)?;
-        let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, control_port);
-        sock.bind(&addr.into())?;
-        sock.join_multicast_v4(&control_group, &Ipv4Addr::UNSPECIFIED)?;
+        sock.bind(&multicast::bind_addr(control_group, control_port).into())?;
+        multicast::join(&sock, control_group, control_interface)?;
         sock.set_nonblocking(true)?;
         UdpSocket::from_std(sock.into())?
     };
 
     // Socket for sending focus acks back on the control multicast
-    let send_socket = {
-        let sock = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
-        sock.set_multicast_ttl_v4(1)?;
-        sock.set_nonblocking(true)?;
-        let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
-        sock.bind(&addr.into())?;
-        UdpSocket::from_std(sock.into())?
-    };
+    let send_socket =
+        UdpSocket::from_std(create_control_send_socket(control_group, control_ttl, control_interface)?)?;
 
-    let dest = SocketAddrV4::new(control_group, control_port);
+    let dest = SocketAddr::new(control_group, control_port);
 
     info!(
         group = %control_group,
         port = control_port,
+        ttl = control_ttl,
+        interface = %state.control_interface.addr,
         output_devices = midi_output.device_count(),
         "Focus/feedback receiver started"
     );
 
     let mut buf = [0u8; 512];
     let focus_timeout = Duration::from_secs(10);
+    let follows_activity = state.config.focus.focus_follows_activity;
+    let activity_idle = Duration::from_millis(state.config.focus.activity_idle_ms);
+    let gate_output = state.config.focus.gate_output;
+    let gated_channels = state.config.focus.gated_channels.clone();
+    let reset_on_focus_loss = state.config.focus.reset_on_focus_loss.clone();
+    let reset_lost_focus = |lost: Option<u32>| {
+        if let Some(reset) = focus_loss_reset(lost, &reset_on_focus_loss) {
+            info!(client_id = ?lost, "Resetting controller feedback after focus loss");
+            midi_output.write_all(reset);
+        }
+    };
+    let indicator = FocusIndicator::for_device(&state.config.focus.indicators, &state.config.midi.device);
+    let mut announced_holder = None;
+    let mut announce_focus = |holder: Option<u32>| {
+        if let Some(midi) = indicator.and_then(|i| i.on_change(&mut announced_holder, holder)) {
+            debug!(holder = ?holder, "Writing focus indicator to controllers");
+            midi_output.write_all(midi);
+        }
+    };
     let mut focus_check_interval = tokio::time::interval(Duration::from_secs(1));
     focus_check_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
@@ -106,7 +298,7 @@ pub async fn run(
                         if len >= 4 {
                             if &buf[0..4] == &MAGIC_FOCUS {
                                 if let Some(packet) = FocusPacket::deserialize(&buf[..len]) {
-                                    handle_focus_packet(
+                                    let lost = handle_focus_packet(
                                         &packet,
                                         &focus_state,
                                         &send_socket,
@@ -114,24 +306,91 @@ pub async fn run(
                                         &addr,
                                     )
                                     .await;
+                                    reset_lost_focus(lost);
+                                    announce_focus(focus_state.read().await.holder);
                                 }
                             } else if &buf[0..4] == &MAGIC_MIDI {
                                 if let Some(packet) = MidiDataPacket::deserialize(&buf[..len]) {
-                                    let fs = focus_state.read().await;
-                                    if fs.holder.is_some() {
+                                    if follows_activity {
+                                        if let Some(lost) = follow_feedback_activity(&focus_state, &send_socket, dest, &addr, activity_idle).await {
+                                            reset_lost_focus(lost);
+                                            announce_focus(focus_state.read().await.holder);
+                                        }
+                                    }
+                                    let mut fs = focus_state.write().await;
+                                    fs.touch(&addr, Instant::now());
+                                    let is_holder = fs.is_holder(&addr);
+                                    // Following activity, only the holder's feedback reaches
+                                    // the controllers (and keeps its focus alive)
+                                    let from_holder = !follows_activity || is_holder;
+                                    if fs.holder.is_some() && from_holder {
+                                        drop(fs);
+                                        let upstream = if gate_output && !is_holder {
+                                            gate_non_holder(&packet.midi_data, &gated_channels)
+                                        } else {
+                                            packet.midi_data
+                                        };
+                                        if upstream.is_empty() {
+                                            debug!(from = %addr, "Upstream MIDI gated (sender does not hold focus)");
+                                            continue;
+                                        }
                                         debug!(
                                             from = %addr,
-                                            midi_bytes = packet.midi_data.len(),
+                                            midi_bytes = upstream.len(),
                                             "Forwarding feedback MIDI to controllers"
                                         );
+                                        let midi = state.pipeline_config.read().await.process_feedback(&upstream);
                                         // Write to ALL connected controllers (primary + secondary)
-                                        midi_output.write_all(&packet.midi_data);
+                                        midi_output.write_all(&midi);
                                         // Update last feedback timestamp
-                                        drop(fs);
                                         let mut fs_w = focus_state.write().await;
                                         fs_w.last_feedback = Some(Instant::now());
                                     }
                                 }
+                            } else if &buf[0..4] == &MAGIC_PANIC {
+                                if let Some(packet) = PanicPacket::deserialize(&buf[..len]) {
+                                    info!(
+                                        from = %addr,
+                                        channel = ?packet.channel.map(|c| c + 1),
+                                        "MIDI panic requested"
+                                    );
+                                    if state.panic_tx.try_send(packet.channel).is_err() {
+                                        warn!("Panic request dropped (broadcaster busy)");
+                                    }
+                                }
+                            } else if &buf[0..4] == &MAGIC_RECORD {
+                                if let Some(packet) = RecordPacket::deserialize(&buf[..len]) {
+                                    let Some(recorder) = &state.recorder_tx else {
+                                        debug!(from = %addr, "Record request ignored (recording.enabled = false)");
+                                        continue;
+                                    };
+                                    info!(from = %addr, start = packet.start, "Record request");
+                                    let command = if packet.start { RecorderCommand::Start } else { RecorderCommand::Stop };
+                                    if recorder.try_send(command).is_err() {
+                                        warn!("Record request dropped (recorder busy)");
+                                    }
+                                }
+                            } else if buf[0..4] == MAGIC_INPUT_ENABLE {
+                                if let Some(packet) = InputEnablePacket::deserialize(&buf[..len]) {
+                                    apply_input_enable(&packet, mux.as_deref(), addr);
+                                }
+                            } else if buf[0..4] == MAGIC_SUBSCRIBE {
+                                if let Some(packet) = SubscribePacket::deserialize(&buf[..len]) {
+                                    if !state.config.unicast.enabled {
+                                        debug!(from = %addr, "Unicast subscribe ignored (unicast.enabled = false)");
+                                        continue;
+                                    }
+                                    // The source port is the client's data socket (or
+                                    // its NAT mapping): unicast goes back to exactly it
+                                    let SocketAddr::V4(from) = addr else {
+                                        debug!(from = %addr, "Unicast subscribe ignored (IPv4 only)");
+                                        continue;
+                                    };
+                                    let interval = Duration::from_millis(packet.interval_ms as u64);
+                                    if state.unicast_subscribers.lock().unwrap().subscribe(from, interval, Instant::now()) {
+                                        info!(from = %addr, client_id = packet.client_id, "Client subscribed to unicast delivery");
+                                    }
+                                }
                             }
                         }
                     }
@@ -144,6 +403,9 @@ pub async fn run(
             // Periodic focus timeout check (1s interval instead of every packet)
             _ = focus_check_interval.tick() => {
                 let mut fs = focus_state.write().await;
+                if fs.prune_clients(Instant::now()) {
+                    debug!(clients = fs.client_addrs.len(), "Forgot silent focus clients");
+                }
                 if let (Some(holder_id), Some(last_fb)) = (fs.holder, fs.last_feedback) {
                     if last_fb.elapsed() > focus_timeout {
                         info!(
@@ -153,6 +415,9 @@ pub async fn run(
                         fs.holder = None;
                         fs.claimed_at = None;
                         fs.last_feedback = None;
+                        drop(fs);
+                        reset_lost_focus(Some(holder_id));
+                        announce_focus(None);
                     }
                 }
             }
@@ -160,16 +425,76 @@ pub async fn run(
     }
 }
 
+/// Mute or unmute an input controller as the admin panel asked.
+/// Returns whether the mux took the request.
+fn apply_input_enable(packet: &InputEnablePacket, mux: Option<&InputMux>, from: SocketAddr) -> bool {
+    match mux {
+        Some(mux) if mux.set_enabled(packet.index, packet.enabled) => {
+            info!(from = %from, input = packet.index, enabled = packet.enabled, "Input enable changed via admin");
+            true
+        }
+        Some(_) => {
+            warn!(from = %from, input = packet.index, "Input enable request: no such input");
+            false
+        }
+        None => {
+            debug!(from = %from, "Input enable request ignored (single-controller mode)");
+            false
+        }
+    }
+}
+
+/// Tell the clients who holds focus now.
+async fn send_focus_ack(send_socket: &UdpSocket, dest: SocketAddr, client_id: u32, sequence: u16) {
+    let ack = FocusPacket {
+        action: FocusAction::Ack,
+        client_id,
+        sequence,
+        timestamp_us: now_us(),
+    };
+    let mut ack_buf = [0u8; FocusPacket::SIZE];
+    ack.serialize(&mut ack_buf);
+    if let Err(e) = send_socket.send_to(&ack_buf, dest).await {
+        error!("Failed to send focus ack: {}", e);
+    }
+}
+
+/// Move focus to the client that sent feedback from `source`, if the
+/// holder has gone idle, and announce the change.
+/// Returns None if focus stayed put, else the client that lost it (if any).
+async fn follow_feedback_activity(
+    focus_state: &RwLock<FocusState>,
+    send_socket: &UdpSocket,
+    dest: SocketAddr,
+    source: &SocketAddr,
+    idle: Duration,
+) -> Option<Option<u32>> {
+    let mut fs = focus_state.write().await;
+    let client_id = fs.client_id(source)?;
+    let old_holder = fs.holder;
+    if !fs.follow_activity(client_id, idle, Instant::now()) {
+        return None;
+    }
+    let sequence = fs.last_claim_seq;
+    drop(fs);
+
+    info!(client_id, old_holder = ?old_holder, from = %source, "Focus follows activity");
+    send_focus_ack(send_socket, dest, client_id, sequence).await;
+    Some(old_holder)
+}
+
+/// Apply a focus claim or release. Returns the client that lost focus, if any.
 async fn handle_focus_packet(
     packet: &FocusPacket,
     focus_state: &RwLock<FocusState>,
     send_socket: &UdpSocket,
-    dest: SocketAddrV4,
+    dest: SocketAddr,
     source: &std::net::SocketAddr,
-) {
+) -> Option<u32> {
     match packet.action {
         FocusAction::Claim => {
             let mut fs = focus_state.write().await;
+            fs.note_client(*source, packet.client_id, Instant::now());
 
             // Last-writer-wins: accept the claim if the sequence is newer.
             // Uses wrapping comparison: new_seq is "newer" if the forward distance
@@ -198,19 +523,11 @@ async fn handle_focus_packet(
                     "Focus granted"
                 );
 
-                // Send ack
-                let ack = FocusPacket {
-                    action: FocusAction::Ack,
-                    client_id: packet.client_id,
-                    sequence: packet.sequence,
-                    timestamp_us: now_us(),
-                };
-                let mut ack_buf = [0u8; FocusPacket::SIZE];
-                ack.serialize(&mut ack_buf);
-                if let Err(e) = send_socket.send_to(&ack_buf, dest).await {
-                    error!("Failed to send focus ack: {}", e);
-                }
+                drop(fs);
+                send_focus_ack(send_socket, dest, packet.client_id, packet.sequence).await;
+                return old_holder.filter(|&old| old != packet.client_id);
             }
+            None
         }
         FocusAction::Release => {
             let mut fs = focus_state.write().await;
@@ -219,10 +536,213 @@ async fn handle_focus_packet(
                 fs.holder = None;
                 fs.claimed_at = None;
                 fs.last_feedback = None;
+                return Some(packet.client_id);
             }
+            None
         }
         FocusAction::Ack => {
             // Host doesn't process acks — only clients do
+            None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcaster::create_multicast_socket;
+    use crate::NetworkSection;
+    use midi_protocol::interface;
+
+    #[test]
+    fn activity_moves_focus_only_after_holder_goes_idle() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let idle = Duration::from_millis(2_000);
+
+        let mut fs = FocusState::default();
+        assert!(fs.follow_activity(1, idle, at(0)));
+        assert_eq!(fs.holder, Some(1));
+
+        // Client 2 plays while client 1 is still active: focus stays
+        fs.last_feedback = Some(at(1_000));
+        assert!(!fs.follow_activity(2, idle, at(2_500)));
+        assert_eq!(fs.holder, Some(1));
+
+        // Client 1 idle past the threshold: client 2's activity takes focus
+        assert!(fs.follow_activity(2, idle, at(3_000)));
+        assert_eq!(fs.holder, Some(2));
+        assert_eq!(fs.last_feedback, Some(at(3_000)));
+        assert!(!fs.follow_activity(2, idle, at(3_100)));
+    }
+
+    #[test]
+    fn gate_passes_only_the_focus_holders_upstream_midi() {
+        let holder: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        let other: SocketAddr = "10.0.0.3:40000".parse().unwrap();
+        let mut fs = FocusState::default();
+        fs.note_client(holder, 1, Instant::now());
+        fs.note_client(other, 2, Instant::now());
+        fs.holder = Some(1);
+        assert!(fs.is_holder(&holder));
+        assert!(!fs.is_holder(&other));
+
+        // Note On ch1, CC ch2, clock
+        let midi = [0x90, 60, 100, 0xB1, 7, 64, 0xF8];
+        // Gating every channel drops the non-holder entirely
+        assert!(gate_non_holder(&midi, &[]).is_empty());
+        // Gating channel 1 only lets the ch2 CC and system message through
+        assert_eq!(gate_non_holder(&midi, &[1]), vec![0xB1, 7, 64, 0xF8]);
+    }
+
+    #[test]
+    fn admin_input_enable_reaches_the_mux() {
+        let (_primary_tx, primary_rx) = midi_protocol::ringbuf::midi_ring_buffer(16);
+        let (_secondary_tx, secondary_rx) = midi_protocol::ringbuf::midi_ring_buffer(16);
+        let mux = InputMux::new(primary_rx, secondary_rx);
+        let from: SocketAddr = "10.0.0.5:5005".parse().unwrap();
+        let request = |index, enabled| InputEnablePacket { index, enabled, timestamp_us: 0 };
+
+        assert!(apply_input_enable(&request(1, false), Some(&mux), from));
+        assert!(!mux.is_enabled(1));
+        assert!(mux.is_enabled(0));
+        assert!(apply_input_enable(&request(1, true), Some(&mux), from));
+        assert!(mux.is_enabled(1));
+
+        assert!(!apply_input_enable(&request(2, false), Some(&mux), from));
+        assert!(!apply_input_enable(&request(0, false), None, from));
+    }
+
+    #[test]
+    fn silent_clients_are_forgotten_but_never_the_holder() {
+        let t0 = Instant::now();
+        let a: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        let b: SocketAddr = "10.0.0.3:40000".parse().unwrap();
+        let mut fs = FocusState::default();
+        fs.note_client(a, 1, t0);
+        fs.note_client(b, 2, t0);
+        fs.holder = Some(1);
+
+        // Client 2 restarts on a new port: only the new address is kept
+        let b2: SocketAddr = "10.0.0.3:40001".parse().unwrap();
+        fs.note_client(b2, 2, t0);
+        assert_eq!(fs.client_id(&b), None);
+        assert_eq!(fs.client_id(&b2), Some(2));
+
+        assert!(!fs.prune_clients(t0 + CLIENT_ADDR_TTL / 2));
+        assert!(fs.prune_clients(t0 + CLIENT_ADDR_TTL));
+        assert!(fs.is_holder(&a));
+        assert_eq!(fs.client_id(&b2), None);
+    }
+
+    #[tokio::test]
+    async fn focus_transfer_resets_the_previous_holders_feedback() {
+        let focus_state = RwLock::new(FocusState::default());
+        let send_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dest: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let reset = [0xB0, 123, 0, 0x90, 0, 0];
+        let claim = |client_id, sequence| FocusPacket {
+            action: FocusAction::Claim,
+            client_id,
+            sequence,
+            timestamp_us: 0,
+        };
+        let a: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        let b: SocketAddr = "10.0.0.3:40000".parse().unwrap();
+
+        // First grant and a re-claim by the holder lose nobody focus
+        let lost = handle_focus_packet(&claim(1, 1), &focus_state, &send_socket, dest, &a).await;
+        assert_eq!(focus_loss_reset(lost, &reset), None);
+        let lost = handle_focus_packet(&claim(1, 2), &focus_state, &send_socket, dest, &a).await;
+        assert_eq!(focus_loss_reset(lost, &reset), None);
+
+        // Client 2 takes over: client 1's controller feedback is reset
+        let lost = handle_focus_packet(&claim(2, 3), &focus_state, &send_socket, dest, &b).await;
+        assert_eq!(lost, Some(1));
+        assert_eq!(focus_loss_reset(lost, &reset), Some(&reset[..]));
+        // ...unless no reset pattern is configured
+        assert_eq!(focus_loss_reset(lost, &[]), None);
+    }
+
+    #[test]
+    fn indicator_is_written_only_when_the_holder_changes() {
+        let indicators = [
+            FocusIndicator { device: "hw:2,0,0".to_string(), focused: vec![0xB0, 1, 1], unfocused: vec![0xB0, 1, 2] },
+            FocusIndicator {
+                device: "hw:1,0,0".to_string(),
+                focused: vec![0xF0, 0x7D, 0x01, 0xF7],
+                unfocused: vec![0xF0, 0x7D, 0x00, 0xF7],
+            },
+        ];
+        let indicator = FocusIndicator::for_device(&indicators, "hw:1,0,0").unwrap();
+        let mut announced = None;
+
+        // Nobody held focus to begin with: nothing to say
+        assert_eq!(indicator.on_change(&mut announced, None), None);
+        // Client 1 is granted focus; its periodic re-claims change nothing
+        assert_eq!(indicator.on_change(&mut announced, Some(1)), Some(&[0xF0, 0x7D, 0x01, 0xF7][..]));
+        assert_eq!(indicator.on_change(&mut announced, Some(1)), None);
+        // Client 2 takes over, then focus times out
+        assert_eq!(indicator.on_change(&mut announced, Some(2)), Some(&[0xF0, 0x7D, 0x01, 0xF7][..]));
+        assert_eq!(indicator.on_change(&mut announced, None), Some(&[0xF0, 0x7D, 0x00, 0xF7][..]));
+        assert_eq!(indicator.on_change(&mut announced, None), None);
+
+        // No entry for the device and no catch-all: no indicator
+        assert!(FocusIndicator::for_device(&indicators, "hw:3,0,0").is_none());
+    }
+
+    fn network(extra: &str) -> NetworkSection {
+        let toml_str = format!(
+            r#"
+            multicast_group = "239.69.83.1"
+            data_port = 5004
+            heartbeat_port = 5005
+            control_group = "239.69.83.100"
+            control_port = 5006
+            {extra}
+            "#
+        );
+        toml::from_str(&toml_str).unwrap()
+    }
+
+    #[test]
+    fn ttls_default_to_lan_only() {
+        let net = network("");
+        assert_eq!(net.data_ttl, 1);
+        assert_eq!(net.control_ttl, 1);
+        assert!(net.control_interface.is_empty());
+    }
+
+    #[test]
+    fn control_socket_uses_its_own_ttl() {
+        let net = network("data_ttl = 1\ncontrol_ttl = 8\ncontrol_interface = \"127.0.0.1\"");
+        let group = multicast::parse_group(&net.multicast_group).unwrap();
+        let control_group = multicast::parse_group(&net.control_group).unwrap();
+
+        let data = create_multicast_socket(group, 0, MulticastInterface::ANY, net.data_ttl).unwrap();
+        let control = create_control_send_socket(
+            control_group,
+            net.control_ttl,
+            interface::resolve(&net.control_interface, net.interface_fallback).multicast(),
+        )
+        .unwrap();
+
+        assert_eq!(data.multicast_ttl_v4().unwrap(), 1);
+        assert_eq!(control.multicast_ttl_v4().unwrap(), 8);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn control_interface_accepts_names() {
+        let net = network("control_interface = \"lo\"");
+        let resolved = interface::resolve(&net.control_interface, net.interface_fallback);
+        assert_eq!(resolved.addr, std::net::Ipv4Addr::LOCALHOST);
+        assert!(resolved.error.is_none());
+
+        // A missing interface falls back instead of failing the task
+        let net = network("control_interface = \"no-such-if0\"");
+        let resolved = interface::resolve(&net.control_interface, net.interface_fallback);
+        assert_eq!(resolved.addr, std::net::Ipv4Addr::UNSPECIFIED);
+        assert!(resolved.error.is_some());
+    }
+}
@@ -6,12 +6,26 @@
 ///
 /// The inactive controller's ring buffer is periodically drained to prevent
 /// stale data accumulation.
+///
+/// Either input can be disabled at runtime (e.g. a malfunctioning pad):
+/// it stays configured and its reader keeps running, but its messages are
+/// discarded and it is never chosen as a switch target.
+///
+/// After a failover the mux stays on the secondary unless
+/// `midi.input_switch_back = "auto"`, in which case it returns to the primary
+/// once the primary has been healthy for `input_switch_back_window_s` without
+/// interruption — a controller that keeps dropping out never wins back.
+///
+/// A third, optional queue carries MIDI injected by show control (OSC). It
+/// is read whichever controller is active, and doesn't count as controller
+/// activity, so injected cues can't mask a dead controller.
 
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use midi_protocol::ringbuf::{MidiConsumer, SLOT_SIZE};
+use serde::Deserialize;
 use tokio::sync::{mpsc, Notify};
 use tracing::{info, warn};
 
@@ -34,6 +48,55 @@ pub struct InputMux {
     /// Timestamp of the last MIDI data received (any input).
     /// Used for activity-timeout failover.
     last_active_data: AtomicU64,
+    /// Per-input enable flag; a disabled input is read and discarded
+    enabled: [AtomicBool; 2],
+    /// Timestamp of the last MIDI data received on the standby input.
+    /// Used to tell whether a recovered primary is actually sending.
+    last_standby_data: AtomicU64,
+    /// Injected MIDI, independent of the active input
+    injected: Option<MidiConsumer>,
+}
+
+/// Whether the mux returns to the primary input on its own after a failover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchBack {
+    /// Stay on the secondary until an operator switches back
+    #[default]
+    Manual,
+    /// Return to the primary once it has been healthy for the window
+    Auto,
+}
+
+/// Hysteresis for switching back to the primary input.
+#[derive(Debug)]
+pub struct SwitchBackTracker {
+    policy: SwitchBack,
+    window: Duration,
+    /// When the primary was first seen healthy while not active
+    healthy_since: Option<Instant>,
+}
+
+impl SwitchBackTracker {
+    pub fn new(policy: SwitchBack, window: Duration) -> Self {
+        Self { policy, window, healthy_since: None }
+    }
+
+    /// Feed the current situation; returns true when it is time to switch
+    /// back. Any moment the primary is unhealthy (or already active)
+    /// restarts the window.
+    pub fn update(&mut self, on_primary: bool, primary_healthy: bool, now: Instant) -> bool {
+        if on_primary || !primary_healthy {
+            self.healthy_since = None;
+            return false;
+        }
+        let since = *self.healthy_since.get_or_insert(now);
+        self.policy == SwitchBack::Auto && now.duration_since(since) >= self.window
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
 }
 
 impl InputMux {
@@ -44,15 +107,27 @@ impl InputMux {
             force_journal: AtomicBool::new(false),
             switch_notify: Arc::new(Notify::new()),
             last_active_data: AtomicU64::new(now_nanos()),
+            enabled: [AtomicBool::new(true), AtomicBool::new(true)],
+            last_standby_data: AtomicU64::new(0),
+            injected: None,
         }
     }
 
+    /// Also read MIDI injected through `injected`'s producer.
+    pub fn with_injected(mut self, injected: MidiConsumer) -> Self {
+        self.injected = Some(injected);
+        self
+    }
+
     /// Read the next MIDI message from the active input.
-    /// Drains the inactive input's buffer to prevent stale data buildup.
+    /// Discards the inactive input's data as it arrives (noting when it was
+    /// last heard) to prevent stale data buildup.
     /// Returns the number of bytes read into `buf`.
     pub async fn pop(&self, buf: &mut [u8; SLOT_SIZE]) -> usize {
         // Drain any pending data from the inactive consumer
         self.drain_inactive();
+        let mut injected = [0u8; SLOT_SIZE];
+        let mut standby = [0u8; SLOT_SIZE];
 
         // Race: wait for data on active consumer OR a switch event.
         // On switch, we loop back and read from the new active consumer.
@@ -61,10 +136,23 @@ impl InputMux {
 
             tokio::select! {
                 len = self.consumers[active_idx].pop(buf) => {
+                    if !self.enabled[active_idx].load(Ordering::Acquire) {
+                        continue; // disabled input: drop its data
+                    }
                     // Record data timestamp for activity-timeout tracking
                     self.last_active_data.store(now_nanos(), Ordering::Relaxed);
                     return len;
                 }
+                len = pop_injected(self.injected.as_ref(), &mut injected) => {
+                    buf[..len].copy_from_slice(&injected[..len]);
+                    return len;
+                }
+                _ = self.consumers[1 - active_idx].pop(&mut standby) => {
+                    // Standby data is discarded, but its arrival shows the
+                    // input is alive even while the active one is silent
+                    self.last_standby_data.store(now_nanos().max(1), Ordering::Relaxed);
+                    continue;
+                }
                 _ = self.switch_notify.notified() => {
                     // Active input changed — drain the now-inactive buffer
                     // and retry on the new active consumer
@@ -75,19 +163,43 @@ impl InputMux {
         }
     }
 
-    /// Switch to the other input. Returns the new active index.
+    /// Switch to the other input. Returns the new active index, or None if
+    /// the other input is disabled.
     /// Can be called by the health monitor (automatic) or externally (manual).
-    pub fn switch(&self) -> u8 {
+    pub fn switch(&self) -> Option<u8> {
         let current = self.active.load(Ordering::Acquire);
         let new = if current == INPUT_PRIMARY {
             INPUT_SECONDARY
         } else {
             INPUT_PRIMARY
         };
+        if !self.is_enabled(new) {
+            return None;
+        }
         self.active.store(new, Ordering::Release);
         self.force_journal.store(true, Ordering::Release);
         self.switch_notify.notify_one();
-        new
+        Some(new)
+    }
+
+    /// Enable or disable an input (0 = primary, 1 = secondary).
+    /// Returns false for an out-of-range index.
+    pub fn set_enabled(&self, index: u8, enabled: bool) -> bool {
+        let Some(flag) = self.enabled.get(index as usize) else {
+            return false;
+        };
+        let was = flag.swap(enabled, Ordering::AcqRel);
+        if was != enabled {
+            info!(input = index, enabled, "Input controller {}", if enabled { "enabled" } else { "disabled" });
+        }
+        true
+    }
+
+    /// Whether an input is enabled (out-of-range indices are not).
+    pub fn is_enabled(&self, index: u8) -> bool {
+        self.enabled
+            .get(index as usize)
+            .is_some_and(|f| f.load(Ordering::Acquire))
     }
 
     /// Check and clear the force-journal flag.
@@ -108,12 +220,34 @@ impl InputMux {
         now_nanos().saturating_sub(last)
     }
 
+    /// Elapsed nanoseconds since MIDI data was last received on the standby
+    /// input (u64::MAX if it has never sent any).
+    pub fn standby_idle_nanos(&self) -> u64 {
+        match self.last_standby_data.load(Ordering::Relaxed) {
+            0 => u64::MAX,
+            last => now_nanos().saturating_sub(last),
+        }
+    }
+
     /// Drain all pending messages from the inactive consumer.
     fn drain_inactive(&self) {
         let active = self.active.load(Ordering::Relaxed) as usize;
         let inactive = 1 - active;
         let mut discard = [0u8; SLOT_SIZE];
-        while self.consumers[inactive].try_pop(&mut discard).is_some() {}
+        let mut drained = false;
+        while self.consumers[inactive].try_pop(&mut discard).is_some() {
+            drained = true;
+        }
+        if drained {
+            self.last_standby_data.store(now_nanos().max(1), Ordering::Relaxed);
+        }
+    }
+}
+
+async fn pop_injected(injected: Option<&MidiConsumer>, buf: &mut [u8; SLOT_SIZE]) -> usize {
+    match injected {
+        Some(consumer) => consumer.pop(buf).await,
+        None => std::future::pending().await,
     }
 }
 
@@ -135,6 +269,9 @@ fn now_nanos() -> u64 {
 ///
 /// `activity_timeout` triggers a switch if the active input produces no
 /// MIDI data for the given duration (Duration::ZERO = disabled).
+/// With an activity timeout set, a recovered primary must also be sending
+/// data before `switch_back` counts it as healthy.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_health_monitor(
     mux: Arc<InputMux>,
     mut health_rx: mpsc::Receiver<(u8, InputHealth)>,
@@ -143,6 +280,7 @@ pub async fn run_health_monitor(
     dual_input_enabled: bool,
     activity_timeout: Duration,
     auto_switch_enabled: Arc<AtomicBool>,
+    mut switch_back: SwitchBackTracker,
 ) {
     let activity_check_enabled = dual_input_enabled && !activity_timeout.is_zero();
     let activity_check_interval = if activity_check_enabled {
@@ -156,6 +294,10 @@ pub async fn run_health_monitor(
     // Don't pile up ticks if processing is slow
     activity_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
+    let switch_back_check_enabled = dual_input_enabled && switch_back.policy == SwitchBack::Auto;
+    let mut switch_back_ticker = tokio::time::interval(Duration::from_millis(250));
+    switch_back_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
     // Track per-input health for the admin state reporting
     let mut input_health: [InputHealthState; 2] = [InputHealthState::Unknown, InputHealthState::Unknown];
 
@@ -188,7 +330,7 @@ pub async fn run_health_monitor(
                         if index == active && dual_input_enabled {
                             // Check if the other input is healthy enough to switch to
                             let other = 1 - index;
-                            if input_health[other as usize] == InputHealthState::Active {
+                            if input_health[other as usize] == InputHealthState::Active && mux.is_enabled(other) {
                                 if auto_switch_enabled.load(Ordering::Relaxed) {
                                     warn!(
                                         input = index,
@@ -209,7 +351,8 @@ pub async fn run_health_monitor(
                                     input = index,
                                     error = %msg,
                                     other_health = ?input_health[other as usize],
-                                    "Active input failed — other input not healthy, staying put"
+                                    other_enabled = mux.is_enabled(other),
+                                    "Active input failed — other input not healthy or disabled, staying put"
                                 );
                             }
                         } else if index == active {
@@ -237,8 +380,8 @@ pub async fn run_health_monitor(
                     let active = mux.active_input();
                     let other = 1 - active;
 
-                    // Only switch if the other input is healthy and auto-switch is enabled
-                    if input_health[other as usize] == InputHealthState::Active {
+                    // Only switch if the other input is healthy, enabled and auto-switch is on
+                    if input_health[other as usize] == InputHealthState::Active && mux.is_enabled(other) {
                         if auto_switch_enabled.load(Ordering::Relaxed) {
                             warn!(
                                 active_input = active,
@@ -258,6 +401,25 @@ pub async fn run_health_monitor(
                     }
                 }
             }
+
+            _ = switch_back_ticker.tick(), if switch_back_check_enabled => {
+                let on_primary = mux.active_input() == INPUT_PRIMARY;
+                let primary_sending = activity_timeout.is_zero()
+                    || Duration::from_nanos(mux.standby_idle_nanos()) < activity_timeout;
+                let primary_healthy = input_health[INPUT_PRIMARY as usize] == InputHealthState::Active
+                    && mux.is_enabled(INPUT_PRIMARY)
+                    && primary_sending;
+
+                if switch_back.update(on_primary, primary_healthy, Instant::now())
+                    && auto_switch_enabled.load(Ordering::Relaxed)
+                {
+                    info!(
+                        window_secs = switch_back.window.as_secs_f32(),
+                        "Primary input healthy for the switch-back window — returning to primary"
+                    );
+                    do_switch(&mux, &input_switch_count, &shared_input_active);
+                }
+            }
         }
     }
 }
@@ -267,7 +429,10 @@ fn do_switch(
     input_switch_count: &Arc<AtomicU64>,
     shared_input_active: &Arc<AtomicU8>,
 ) {
-    let new = mux.switch();
+    let Some(new) = mux.switch() else {
+        warn!("Input failover skipped — other input is disabled");
+        return;
+    };
     input_switch_count.fetch_add(1, Ordering::Relaxed);
     shared_input_active.store(new, Ordering::Relaxed);
     info!(new_active = new, "Input failover complete");
@@ -282,3 +447,119 @@ enum InputHealthState {
     Error,
     Disconnected,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midi_protocol::ringbuf::midi_ring_buffer;
+
+    #[tokio::test]
+    async fn disabled_input_is_muted_and_not_a_switch_target() {
+        let (primary_tx, primary_rx) = midi_ring_buffer(16);
+        let (secondary_tx, secondary_rx) = midi_ring_buffer(16);
+        let mux = InputMux::new(primary_rx, secondary_rx);
+        let mut buf = [0u8; SLOT_SIZE];
+
+        // Secondary disabled: it can't be switched to
+        assert!(mux.set_enabled(INPUT_SECONDARY, false));
+        assert_eq!(mux.switch(), None);
+        assert_eq!(mux.active_input(), INPUT_PRIMARY);
+        assert!(!mux.set_enabled(2, false));
+
+        // Primary disabled while active: its messages never reach the broadcaster
+        mux.set_enabled(INPUT_PRIMARY, false);
+        primary_tx.push(&[0x90, 60, 100]);
+        let muted = tokio::time::timeout(Duration::from_millis(50), mux.pop(&mut buf)).await;
+        assert!(muted.is_err(), "disabled input was forwarded");
+
+        // Re-enabled: new messages flow again, the muted one is gone
+        mux.set_enabled(INPUT_PRIMARY, true);
+        primary_tx.push(&[0x90, 62, 100]);
+        let len = mux.pop(&mut buf).await;
+        assert_eq!(&buf[..len], &[0x90, 62, 100]);
+
+        // Secondary re-enabled: eligible again
+        mux.set_enabled(INPUT_SECONDARY, true);
+        assert_eq!(mux.switch(), Some(INPUT_SECONDARY));
+        secondary_tx.push(&[0xB0, 7, 1]);
+        let len = mux.pop(&mut buf).await;
+        assert_eq!(&buf[..len], &[0xB0, 7, 1]);
+    }
+
+    #[tokio::test]
+    async fn injected_midi_is_read_without_counting_as_activity() {
+        let (_primary_tx, primary_rx) = midi_ring_buffer(16);
+        let (_secondary_tx, secondary_rx) = midi_ring_buffer(16);
+        let (inject_tx, inject_rx) = midi_ring_buffer(16);
+        let mux = InputMux::new(primary_rx, secondary_rx).with_injected(inject_rx);
+        let mut buf = [0u8; SLOT_SIZE];
+
+        let idle_before = mux.active_idle_nanos();
+        inject_tx.push(&[0xB0, 20, 127]);
+        let len = mux.pop(&mut buf).await;
+        assert_eq!(&buf[..len], &[0xB0, 20, 127]);
+        assert!(mux.active_idle_nanos() >= idle_before);
+    }
+
+    #[test]
+    fn primary_recovery_switches_back_only_in_auto_after_window() {
+        let window = Duration::from_secs(10);
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+
+        let mut auto = SwitchBackTracker::new(SwitchBack::Auto, window);
+        // Primary recovers at t=0 but drops out again at t=6: window restarts
+        assert!(!auto.update(false, true, at(0)));
+        assert!(!auto.update(false, true, at(5)));
+        assert!(!auto.update(false, false, at(6)));
+        assert!(!auto.update(false, true, at(7)));
+        assert!(!auto.update(false, true, at(16)));
+        assert!(auto.update(false, true, at(17)));
+        // Back on primary: nothing more to do
+        assert!(!auto.update(true, true, at(18)));
+
+        let mut manual = SwitchBackTracker::new(SwitchBack::Manual, window);
+        for s in 0..60 {
+            assert!(!manual.update(false, true, at(s)));
+        }
+    }
+
+    #[tokio::test]
+    async fn standby_activity_is_tracked_while_draining() {
+        let (primary_tx, primary_rx) = midi_ring_buffer(16);
+        let (secondary_tx, secondary_rx) = midi_ring_buffer(16);
+        let mux = InputMux::new(primary_rx, secondary_rx);
+        let mut buf = [0u8; SLOT_SIZE];
+        assert_eq!(mux.switch(), Some(INPUT_SECONDARY));
+        assert_eq!(mux.standby_idle_nanos(), u64::MAX);
+
+        // The recovered primary sends while the secondary is live
+        primary_tx.push(&[0x90, 60, 100]);
+        secondary_tx.push(&[0x90, 62, 100]);
+        let len = mux.pop(&mut buf).await;
+        assert_eq!(&buf[..len], &[0x90, 62, 100]);
+        assert!(Duration::from_nanos(mux.standby_idle_nanos()) < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn standby_activity_is_tracked_while_active_input_is_silent() {
+        let (_primary_tx, primary_rx) = midi_ring_buffer(16);
+        let (secondary_tx, secondary_rx) = midi_ring_buffer(16);
+        let mux = Arc::new(InputMux::new(primary_rx, secondary_rx));
+
+        // The broadcaster is parked on the silent primary
+        let reader = mux.clone();
+        let pop = tokio::spawn(async move {
+            let mut buf = [0u8; SLOT_SIZE];
+            reader.pop(&mut buf).await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(mux.standby_idle_nanos(), u64::MAX);
+
+        secondary_tx.push(&[0x90, 62, 100]);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(Duration::from_nanos(mux.standby_idle_nanos()) < Duration::from_secs(1));
+        assert!(!pop.is_finished(), "standby data was forwarded");
+        pop.abort();
+    }
+}
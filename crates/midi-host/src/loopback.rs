@@ -0,0 +1,141 @@
+/// Synthetic MIDI input for rehearsing without hardware (`--loopback`).
+///
+/// Replaces the primary `usb_reader` with a generator that pushes a fixed
+/// pattern into the primary ring buffer: a C major scale up and down on
+/// channel 1, one note per period, with a mod wheel (CC 1) sweep alongside.
+/// Everything downstream — input mux, pipeline, broadcaster, failover — is
+/// the same as with a real controller, so clients receive the pattern as
+/// ordinary MIDI.
+///
+/// Like the real reader the generator reports `InputHealth::Active` when it
+/// starts and exits once its health channel closes; on Ctrl+C it is aborted
+/// with the readers and the shutdown silence follows its last note.
+
+use std::time::Duration;
+
+use midi_protocol::ringbuf::MidiProducer;
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::usb_reader::InputHealth;
+
+/// C4 to C5
+const SCALE: [u8; 8] = [60, 62, 64, 65, 67, 69, 71, 72];
+const VELOCITY: u8 = 100;
+const MOD_WHEEL: u8 = 1;
+/// Steps for the mod wheel to sweep 0 → 127 → 0
+const SWEEP_STEPS: u32 = 32;
+
+/// Step-by-step source of the loopback pattern.
+#[derive(Debug, Default)]
+pub struct LoopbackPattern {
+    step: u32,
+    sounding: Option<u8>,
+}
+
+impl LoopbackPattern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scale note for `step`: up the scale, then back down without
+    /// repeating the top and bottom notes.
+    fn note(step: u32) -> u8 {
+        let cycle = (SCALE.len() as u32 - 1) * 2;
+        let pos = (step % cycle) as usize;
+        if pos < SCALE.len() {
+            SCALE[pos]
+        } else {
+            SCALE[cycle as usize - pos]
+        }
+    }
+
+    fn mod_wheel(step: u32) -> u8 {
+        let half = SWEEP_STEPS / 2;
+        let pos = step % SWEEP_STEPS;
+        let rising = if pos <= half { pos } else { SWEEP_STEPS - pos };
+        (rising * 127 / half) as u8
+    }
+
+    /// Messages for the next step: release the previous note, move the
+    /// mod wheel, strike the next note.
+    pub fn next_step(&mut self) -> Vec<[u8; 3]> {
+        let mut messages = Vec::with_capacity(3);
+        if let Some(note) = self.sounding.take() {
+            messages.push([0x80, note, 0]);
+        }
+        messages.push([0xB0, MOD_WHEEL, Self::mod_wheel(self.step)]);
+        let note = Self::note(self.step);
+        messages.push([0x90, note, VELOCITY]);
+        self.sounding = Some(note);
+        self.step = self.step.wrapping_add(1);
+        messages
+    }
+}
+
+/// Feed the loopback pattern into `producer`, one step every `period`.
+pub async fn run_generator(
+    producer: MidiProducer,
+    health_tx: mpsc::Sender<InputHealth>,
+    period: Duration,
+) -> anyhow::Result<()> {
+    info!(period_ms = period.as_millis() as u64, "Loopback pattern generator running");
+    let _ = health_tx.send(InputHealth::Active).await;
+
+    let mut pattern = LoopbackPattern::new();
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if health_tx.is_closed() {
+            info!("Health channel closed — loopback generator exiting");
+            return Ok(());
+        }
+        for message in pattern.next_step() {
+            producer.push_overwrite(&message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_goes_up_and_down_with_one_note_at_a_time() {
+        let mut pattern = LoopbackPattern::new();
+        let mut notes = Vec::new();
+        let mut sounding = None;
+        for _ in 0..15 {
+            for message in pattern.next_step() {
+                match message {
+                    [0x80, note, _] => {
+                        assert_eq!(sounding.take(), Some(note), "released a note that wasn't held");
+                    }
+                    [0x90, note, velocity] => {
+                        assert!(sounding.is_none(), "two notes held at once");
+                        assert_eq!(velocity, VELOCITY);
+                        sounding = Some(note);
+                        notes.push(note);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        assert_eq!(notes, vec![60, 62, 64, 65, 67, 69, 71, 72, 71, 69, 67, 65, 64, 62, 60]);
+    }
+
+    #[test]
+    fn mod_wheel_sweeps_the_full_range() {
+        let mut pattern = LoopbackPattern::new();
+        let values: Vec<u8> = (0..=SWEEP_STEPS)
+            .flat_map(|_| pattern.next_step())
+            .filter(|m| m[0] == 0xB0 && m[1] == MOD_WHEEL)
+            .map(|m| m[2])
+            .collect();
+        assert_eq!(values[0], 0);
+        assert_eq!(values[SWEEP_STEPS as usize / 2], 127);
+        assert_eq!(values[SWEEP_STEPS as usize], 0);
+        assert!(values.windows(2).take(SWEEP_STEPS as usize / 2).all(|w| w[0] < w[1]));
+    }
+}
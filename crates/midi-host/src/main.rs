@@ -4,10 +4,21 @@ mod discovery;
 mod failover;
 mod feedback;
 mod input_mux;
+mod loopback;
 mod metrics;
+mod midi_clock;
+mod midi_file;
 mod midi_output;
 mod osc_listener;
+mod osc_mirror;
 mod pipeline;
+mod recorder;
+mod send_retry;
+mod send_shards;
+mod shadow;
+mod shutdown;
+#[cfg(test)]
+mod test_support;
 mod unicast_relay;
 mod usb_detector;
 mod usb_reader;
@@ -16,13 +27,17 @@ use clap::Parser;
 use serde::Deserialize;
 use std::path::PathBuf;
 use std::net::SocketAddrV4;
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, watch, RwLock};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use midi_protocol::clock::{PacketClock, TimestampSource};
+use midi_protocol::crypto::PacketCipher;
 use midi_protocol::identity::DeviceIdentity;
+use midi_protocol::interface;
+use midi_protocol::journal;
 use midi_protocol::midi_state::MidiState;
 use midi_protocol::packets::HostRole;
 use midi_protocol::ringbuf;
@@ -36,6 +51,15 @@ struct Args {
     /// Path to configuration file
     #[arg(short, long, default_value = "config/host.toml")]
     config: PathBuf,
+
+    /// Replace the primary MIDI input with a generated test pattern
+    /// (C major scale plus a mod wheel sweep) for rehearsal without hardware
+    #[arg(long)]
+    loopback: bool,
+
+    /// Time between loopback pattern steps, in milliseconds
+    #[arg(long, default_value_t = 500, value_parser = clap::value_parser!(u64).range(1..))]
+    loopback_period_ms: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -50,13 +74,43 @@ pub struct HostConfig {
     #[serde(default)]
     pub osc: OscSection,
     #[serde(default)]
+    pub osc_mirror: osc_mirror::OscMirrorSection,
+    #[serde(default)]
     pub unicast: UnicastSection,
+    #[serde(default)]
+    pub discovery: DiscoverySection,
+    /// The shared `[pipeline]` section, as saved by the admin panel
+    /// (`PUT /api/pipeline`) and loaded here at boot
+    #[serde(default)]
+    pub pipeline: PipelineSection,
+    #[serde(default)]
+    pub shadow: shadow::ShadowSection,
+    #[serde(default)]
+    pub focus: FocusSection,
+    #[serde(default)]
+    pub recording: recorder::RecordingSection,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct HostSection {
     pub id: u8,
     pub name: String,
+    /// Failover rank advertised in heartbeats: clients prefer the live host
+    /// with the lowest priority, ties going to the lower id. 0 (unset)
+    /// ranks the host by its id. Values above 1 need `[[failover.peers]]`
+    /// so the host can tell whether it ranks first.
+    #[serde(default)]
+    pub priority: u8,
+}
+
+impl HostSection {
+    pub fn effective_priority(&self) -> u8 {
+        if self.priority == 0 {
+            self.id
+        } else {
+            self.priority
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -68,6 +122,39 @@ pub struct NetworkSection {
     pub control_port: u16,
     #[serde(default = "default_interface")]
     pub interface: String,
+    /// What to use if `interface` doesn't exist: "any" (0.0.0.0) or "default_route"
+    #[serde(default)]
+    pub interface_fallback: interface::InterfaceFallback,
+    /// Multicast TTL for the data + heartbeat sockets (1 = LAN only)
+    #[serde(default = "default_multicast_ttl")]
+    pub data_ttl: u32,
+    /// Multicast TTL for the control group (focus, feedback). Control traffic
+    /// is low-rate, so it can be routed across subnets without affecting data.
+    #[serde(default = "default_multicast_ttl")]
+    pub control_ttl: u32,
+    /// Interface (name or IPv4 address) to send/join the control group on
+    /// (empty = OS default); resolved like `interface`
+    #[serde(default)]
+    pub control_interface: String,
+    /// Packet timestamps: "wall_clock" (cross-machine correlation) or
+    /// "monotonic" (immune to NTP steps)
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
+    /// Parallel data sender sockets/tasks, split by MIDI channel (1 = single socket)
+    #[serde(default = "default_send_shards")]
+    pub send_shards: usize,
+    /// Retries for a data packet that hits a transient send error
+    /// (socket buffer full); 0 = drop on the first failure
+    #[serde(default = "default_send_retries")]
+    pub send_retries: u32,
+    /// Pre-shared key sealing data packets with AES-256-GCM (empty = plaintext).
+    /// Clients need the same key.
+    #[serde(default)]
+    pub psk: String,
+    /// Send an XOR parity packet after every this many data packets so
+    /// clients can rebuild one lost packet per group (0 = off)
+    #[serde(default)]
+    pub fec_group_size: u8,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -76,6 +163,11 @@ pub struct HeartbeatSection {
     pub interval_ms: u64,
     #[serde(default = "default_miss_threshold")]
     pub miss_threshold: u8,
+    /// Back off toward `max_interval_ms` while the heartbeat socket is congested
+    #[serde(default)]
+    pub adaptive: bool,
+    #[serde(default = "default_max_heartbeat_interval")]
+    pub max_interval_ms: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -87,20 +179,44 @@ pub struct MidiSection {
     /// Activity timeout in seconds for input failover (0 = disabled)
     #[serde(default)]
     pub input_failover_timeout_s: u64,
+    /// Return to the primary input after a failover: "manual" or "auto"
+    #[serde(default)]
+    pub input_switch_back: input_mux::SwitchBack,
+    /// Seconds the primary must stay healthy before an auto switch-back
+    #[serde(default = "default_input_switch_back_window")]
+    pub input_switch_back_window_s: u64,
+    /// Loop `file:` devices (MIDI file playback) instead of playing once
+    #[serde(default = "default_true")]
+    pub file_loop: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct FailoverSection {
+    /// Take over as Primary when no host's Primary heartbeats are heard
     #[serde(default = "default_true")]
     pub auto_enabled: bool,
     #[serde(default = "default_switch_back_policy")]
     pub switch_back_policy: String,
     #[serde(default = "default_lockout")]
     pub lockout_seconds: u64,
+    /// With `switch_back_policy = "auto"`: how long the primary's heartbeats
+    /// must be steady before returning to it
+    #[serde(default = "default_switch_back_delay")]
+    pub switch_back_delay_s: u64,
     #[serde(default = "default_confirmation_mode")]
     pub confirmation_mode: String,
     #[serde(default)]
     pub triggers: FailoverTriggers,
+    /// Admin panel polled for the operator's pinned primary
+    /// (`PUT /api/hosts/:id/promote`) and sent each failover switch;
+    /// empty = neither
+    #[serde(default = "default_unicast_admin_url")]
+    pub admin_url: String,
+    /// The other hosts in the failover set, for hearing their heartbeats
+    /// and ranking this host among them (empty = the default primary/standby
+    /// groups, with priority 1 starting as primary)
+    #[serde(default)]
+    pub peers: Vec<failover::PeerHost>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -187,12 +303,20 @@ impl Default for AdminSection {
 pub struct OscSection {
     #[serde(default = "default_osc_port")]
     pub listen_port: u16,
+    /// Alert if no /midinet/ping arrives for this long (0 = keepalives not expected)
+    #[serde(default)]
+    pub keepalive_timeout_ms: u64,
+    /// Accept /midinet/midi/note and /midinet/midi/cc as injected input
+    #[serde(default)]
+    pub midi_inject: bool,
 }
 
 impl Default for OscSection {
     fn default() -> Self {
         Self {
             listen_port: 5588,
+            keepalive_timeout_ms: 0,
+            midi_inject: false,
         }
     }
 }
@@ -201,26 +325,292 @@ impl Default for OscSection {
 pub struct UnicastSection {
     #[serde(default)]
     pub enabled: bool,
+    /// "all" = every client, "hybrid" = only clients not receiving multicast
+    #[serde(default)]
+    pub mode: unicast_relay::UnicastMode,
     #[serde(default = "default_unicast_admin_url")]
     pub admin_url: String,
+    /// Most clients that may subscribe for unicast at once (set to the
+    /// expected client count)
+    #[serde(default = "default_max_unicast_subscribers")]
+    pub max_subscribers: usize,
 }
 
 impl Default for UnicastSection {
     fn default() -> Self {
         Self {
             enabled: false,
+            mode: unicast_relay::UnicastMode::default(),
             admin_url: "http://127.0.0.1:8080".to_string(),
+            max_subscribers: default_max_unicast_subscribers(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoverySection {
+    /// Send an out-of-cycle announcement as soon as the device identity changes
+    #[serde(default = "default_true")]
+    pub readvertise_on_identity_change: bool,
+    /// Also multicast an IdentityPacket on the control group on identity change
+    #[serde(default)]
+    pub notify_control_group: bool,
+}
+
+impl Default for DiscoverySection {
+    fn default() -> Self {
+        Self {
+            readvertise_on_identity_change: true,
+            notify_control_group: false,
         }
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct FocusSection {
+    /// Hand feedback focus to whichever client last sent feedback MIDI
+    #[serde(default)]
+    pub focus_follows_activity: bool,
+    /// How long the holder must be silent before activity elsewhere takes focus
+    #[serde(default = "default_focus_activity_idle_ms")]
+    pub activity_idle_ms: u64,
+    /// Only the focus holder's upstream MIDI reaches the controllers on the
+    /// gated channels; other clients are dropped there
+    #[serde(default)]
+    pub gate_output: bool,
+    /// Channels (1-16) covered by `gate_output`; empty gates every channel
+    /// and system messages
+    #[serde(default)]
+    pub gated_channels: Vec<u8>,
+    /// Raw MIDI written to the controllers when a client loses focus, e.g.
+    /// CCs/notes that turn its LEDs off (empty = leave them as they are)
+    #[serde(default)]
+    pub reset_on_focus_loss: Vec<u8>,
+    /// "You have / don't have focus" MIDI sent to each client's device on
+    /// focus changes, per controller (`[[focus.indicators]]`)
+    #[serde(default)]
+    pub indicators: Vec<feedback::FocusIndicator>,
+}
+
+impl Default for FocusSection {
+    fn default() -> Self {
+        Self {
+            focus_follows_activity: false,
+            activity_idle_ms: default_focus_activity_idle_ms(),
+            gate_output: false,
+            gated_channels: Vec::new(),
+            reset_on_focus_loss: Vec::new(),
+            indicators: Vec::new(),
+        }
+    }
+}
+
+/// `[pipeline.message_filter]` as the admin panel writes it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MessageFilterSection {
+    pub note_on: bool,
+    pub note_off: bool,
+    pub control_change: bool,
+    pub program_change: bool,
+    pub pitch_bend: bool,
+    /// Both kinds of aftertouch; `channel_pressure` / `poly_aftertouch`
+    /// block just one
+    pub aftertouch: bool,
+    pub channel_pressure: bool,
+    pub poly_aftertouch: bool,
+    pub sysex: bool,
+    pub clock: bool,
+    /// Active sensing and reset
+    pub realtime: bool,
+}
+
+impl Default for MessageFilterSection {
+    fn default() -> Self {
+        Self {
+            note_on: true,
+            note_off: true,
+            control_change: true,
+            program_change: true,
+            pitch_bend: true,
+            aftertouch: true,
+            channel_pressure: true,
+            poly_aftertouch: true,
+            sysex: true,
+            clock: true,
+            realtime: true,
+        }
+    }
+}
+
+impl MessageFilterSection {
+    fn to_filter(&self) -> pipeline::MessageFilter {
+        pipeline::MessageFilter {
+            // The pipeline filters notes as a pair: blocking either half
+            // blocks both, so no Note On is left without its Note Off
+            note_on_off: self.note_on && self.note_off,
+            control_change: self.control_change,
+            program_change: self.program_change,
+            pitch_bend: self.pitch_bend,
+            channel_pressure: self.aftertouch && self.channel_pressure,
+            poly_aftertouch: self.aftertouch && self.poly_aftertouch,
+            sysex: self.sysex,
+            clock: self.clock,
+            realtime: self.realtime,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PipelineSection {
+    /// Channels let through (index 0-15)
+    #[serde(default = "default_channel_filter")]
+    pub channel_filter: [bool; 16],
+    #[serde(default)]
+    pub message_filter: MessageFilterSection,
+    /// Destination channel per source channel (index 0-15, 0xFF = no remap)
+    #[serde(default = "default_channel_remap")]
+    pub channel_remap: [u8; 16],
+    /// Note transpose per channel in semitones
+    #[serde(default)]
+    pub transpose: [i8; 16],
+    #[serde(default)]
+    pub velocity_curve: pipeline::VelocityCurve,
+    #[serde(default = "default_true")]
+    pub sysex_passthrough: bool,
+    /// Keyboard split zones (note range on a source channel → target channel)
+    #[serde(default)]
+    pub zones: Vec<pipeline::Zone>,
+    /// Controller number remaps, e.g. a fader board's CC 21-28 onto 71-78
+    #[serde(default)]
+    pub cc_remap: Vec<pipeline::CcRemap>,
+    /// Debounce: minimum Note On → Note Off time in ms (0 = off)
+    #[serde(default)]
+    pub min_note_duration_ms: u64,
+    /// Velocity curve for feedback MIDI returning to the controllers
+    #[serde(default)]
+    pub feedback_velocity_curve: pipeline::VelocityCurve,
+    /// Note On velocities are rescaled onto `velocity_min..=velocity_max`
+    #[serde(default = "default_velocity_min")]
+    pub velocity_min: u8,
+    #[serde(default = "default_velocity_max")]
+    pub velocity_max: u8,
+    /// Rewrite every note release into `note_off_form`
+    #[serde(default)]
+    pub normalize_note_off: bool,
+    #[serde(default)]
+    pub note_off_form: pipeline::NoteOffForm,
+    /// Hold notes merged by channel remap until every source releases them
+    #[serde(default)]
+    pub merge_note_refcount: bool,
+    /// Max Control Changes per second per controller (0 = off)
+    #[serde(default)]
+    pub cc_throttle_hz: u16,
+    /// Note latch per channel (index 0-15): notes sustain until the next Note On
+    #[serde(default)]
+    pub latch: [bool; 16],
+    /// Monophonic mode per channel (index 0-15), last-note priority
+    #[serde(default)]
+    pub mono: [bool; 16],
+    /// On a mono channel, releasing the sounding key returns to the last one still held
+    #[serde(default)]
+    pub mono_retrigger: bool,
+    /// Chord offsets (semitones) per channel, keyed "1"-"16", e.g. `1 = [0, 4, 7]`
+    #[serde(default)]
+    pub harmonize: std::collections::BTreeMap<String, Vec<i8>>,
+    /// MPE zone (channel index 0-15) passed through without remap/filtering
+    #[serde(default)]
+    pub mpe_zone: Option<pipeline::MpeZone>,
+}
+
+impl Default for PipelineSection {
+    fn default() -> Self {
+        Self {
+            channel_filter: default_channel_filter(),
+            message_filter: MessageFilterSection::default(),
+            channel_remap: default_channel_remap(),
+            transpose: [0; 16],
+            velocity_curve: pipeline::VelocityCurve::default(),
+            sysex_passthrough: true,
+            zones: Vec::new(),
+            cc_remap: Vec::new(),
+            min_note_duration_ms: 0,
+            feedback_velocity_curve: pipeline::VelocityCurve::default(),
+            velocity_min: default_velocity_min(),
+            velocity_max: default_velocity_max(),
+            normalize_note_off: false,
+            note_off_form: pipeline::NoteOffForm::default(),
+            merge_note_refcount: false,
+            cc_throttle_hz: 0,
+            latch: [false; 16],
+            mono: [false; 16],
+            mono_retrigger: false,
+            harmonize: Default::default(),
+            mpe_zone: None,
+        }
+    }
+}
+
+impl PipelineSection {
+    /// The pipeline this section configures.
+    fn to_config(&self) -> pipeline::PipelineConfig {
+        pipeline::PipelineConfig {
+            channel_filter: self.channel_filter,
+            message_filter: self.message_filter.to_filter(),
+            channel_remap: self.channel_remap,
+            transpose: self.transpose,
+            velocity_curve: self.velocity_curve,
+            sysex_passthrough: self.sysex_passthrough,
+            zones: self.zones.clone(),
+            cc_remap: self.cc_remap.clone(),
+            min_note_duration_ms: self.min_note_duration_ms,
+            feedback_velocity_curve: self.feedback_velocity_curve,
+            velocity_min: self.velocity_min,
+            velocity_max: self.velocity_max,
+            normalize_note_off: self.normalize_note_off,
+            note_off_form: self.note_off_form,
+            merge_note_refcount: self.merge_note_refcount,
+            cc_throttle_hz: self.cc_throttle_hz,
+            latch: self.latch,
+            mono: self.mono,
+            mono_retrigger: self.mono_retrigger,
+            harmonize: self.harmonize_channels(),
+            mpe_zone: self.mpe_zone,
+        }
+    }
+
+    /// `harmonize` as the pipeline's per-channel array. Keys that aren't a
+    /// channel 1-16 are skipped with a warning.
+    fn harmonize_channels(&self) -> [Option<Vec<i8>>; 16] {
+        let mut channels: [Option<Vec<i8>>; 16] = Default::default();
+        for (key, offsets) in &self.harmonize {
+            match key.trim().parse::<usize>() {
+                Ok(ch @ 1..=16) => channels[ch - 1] = Some(offsets.clone()),
+                _ => warn!(channel = %key, "pipeline.harmonize: not a channel 1-16, ignored"),
+            }
+        }
+        channels
+    }
+}
+
 // Default value functions
 fn default_interface() -> String { "eth0".to_string() }
+fn default_multicast_ttl() -> u32 { 1 }
+fn default_input_switch_back_window() -> u64 { 10 }
+fn default_send_shards() -> usize { 1 }
+fn default_send_retries() -> u32 { 3 }
+fn default_focus_activity_idle_ms() -> u64 { 2000 }
 fn default_heartbeat_interval() -> u64 { 3 }
 fn default_miss_threshold() -> u8 { 3 }
+fn default_max_heartbeat_interval() -> u64 { 48 }
 fn default_true() -> bool { true }
+fn default_velocity_min() -> u8 { 1 }
+fn default_velocity_max() -> u8 { 127 }
+fn default_channel_filter() -> [bool; 16] { [true; 16] }
+fn default_channel_remap() -> [u8; 16] { [0xFF; 16] }
 fn default_switch_back_policy() -> String { "manual".to_string() }
 fn default_lockout() -> u64 { 5 }
+fn default_switch_back_delay() -> u64 { 10 }
 fn default_confirmation_mode() -> String { "immediate".to_string() }
 fn default_trigger_channel() -> u8 { 16 }
 fn default_trigger_note() -> u8 { 127 }
@@ -231,11 +621,14 @@ fn default_admin_listen() -> String { "0.0.0.0:8080".to_string() }
 fn default_admin_user() -> String { "admin".to_string() }
 fn default_admin_pass() -> String { "midinet".to_string() }
 fn default_unicast_admin_url() -> String { "http://127.0.0.1:8080".to_string() }
+fn default_max_unicast_subscribers() -> usize { 64 }
 
 /// Shared state accessible across all tasks
 pub struct SharedState {
     pub config: HostConfig,
     pub identity: RwLock<DeviceIdentity>,
+    /// Bumped every time `identity` changes (wakes the discovery announcers)
+    pub identity_generation: watch::Sender<u64>,
     pub role: watch::Sender<HostRole>,
     pub metrics: RwLock<metrics::HostMetrics>,
     /// Pipeline config (hot-reloadable via admin API)
@@ -251,6 +644,64 @@ pub struct SharedState {
     pub input_redundancy_enabled: bool,
     /// Unicast relay target addresses (populated by unicast_relay task)
     pub unicast_targets: watch::Receiver<Vec<SocketAddrV4>>,
+    /// Clients that registered for unicast with SubscribePackets
+    pub unicast_subscribers: Arc<std::sync::Mutex<unicast_relay::Subscribers>>,
+    /// Panic requests for the broadcaster: Some(channel 0-15) or None for all
+    pub panic_tx: mpsc::Sender<Option<u8>>,
+    /// Resolved `network.interface` for the data/heartbeat sockets
+    /// (carries the misconfiguration if a fallback is in use)
+    pub data_interface: interface::ResolvedInterface,
+    /// Resolved `network.control_interface` for the focus/feedback sockets
+    pub control_interface: interface::ResolvedInterface,
+    /// Timestamp source for data + heartbeat packets (one anchor for both)
+    pub packet_clock: PacketClock,
+    /// True while mirroring another host as a shadow: no data or heartbeats
+    /// are sent until promotion clears it
+    pub shadowing: watch::Sender<bool>,
+    /// Sequence number of the next data packet, published by the broadcaster
+    /// so the shutdown silence continues the stream
+    pub data_sequence: AtomicU16,
+    /// Observed MIDI clock, regenerated by the broadcaster after a takeover
+    pub midi_clock: std::sync::Mutex<midi_clock::ClockTracker>,
+    /// Seals outgoing data packets when `network.psk` is set
+    pub data_cipher: Option<PacketCipher>,
+    /// Input recorder commands (None unless `recording.enabled`)
+    pub recorder_tx: Option<mpsc::Sender<recorder::RecorderCommand>>,
+    /// Outgoing MIDI for the OSC mirror (None unless `osc_mirror.enabled`)
+    pub osc_mirror_tx: Option<mpsc::Sender<Vec<u8>>>,
+    /// Oldest protocol version a client has announced in a discovery
+    /// request; journals are encoded so it can read them
+    pub peer_protocol: AtomicU8,
+}
+
+impl SharedState {
+    /// Replace the device identity. Returns true and notifies subscribers of
+    /// `identity_generation` only if the identity actually changed.
+    pub async fn set_identity(&self, identity: DeviceIdentity) -> bool {
+        {
+            let mut current = self.identity.write().await;
+            if *current == identity {
+                return false;
+            }
+            *current = identity;
+        }
+        self.identity_generation.send_modify(|g| *g = g.wrapping_add(1));
+        true
+    }
+
+    /// Note a client speaking protocol `version`. Returns true if it is
+    /// older than every client seen so far (journals now target it).
+    pub fn note_peer_protocol(&self, version: u8) -> bool {
+        if !(midi_protocol::MIN_PROTOCOL_VERSION..=midi_protocol::PROTOCOL_VERSION).contains(&version) {
+            return false;
+        }
+        self.peer_protocol.fetch_min(version, Ordering::Relaxed) > version
+    }
+
+    /// Journal of `midi_state` that every client seen so far can decode.
+    pub fn encode_journal(&self, midi_state: &MidiState) -> Vec<u8> {
+        journal::encode_journal_for(midi_state, self.peer_protocol.load(Ordering::Relaxed))
+    }
 }
 
 /// Adapter that tags InputHealth events with an input index
@@ -279,6 +730,45 @@ impl TaggedHealthTx {
     }
 }
 
+/// Run the reader for one input: a MIDI file player for `file:PATH`
+/// devices, the ALSA reader otherwise.
+async fn run_input(
+    device: &str,
+    file_loop: bool,
+    producer: ringbuf::MidiProducer,
+    health_tx: mpsc::Sender<usb_reader::InputHealth>,
+) -> anyhow::Result<()> {
+    match midi_file::file_path(device) {
+        Some(path) => midi_file::run_player(path, file_loop, producer, health_tx).await,
+        None => usb_reader::platform::run_midi_reader(device, producer, health_tx).await,
+    }
+}
+
+/// Parse the host config, including the `[pipeline]` section the admin
+/// panel saves, and reject a pipeline the host can't apply.
+fn parse_config(contents: &str) -> anyhow::Result<HostConfig> {
+    let config: HostConfig = toml::from_str(contents)?;
+    if let Err(e) = config.pipeline.to_config().validate() {
+        anyhow::bail!("invalid [pipeline] config: {}", e);
+    }
+    Ok(config)
+}
+
+/// Resolve one of the `[network]` interface settings. Never exits over a
+/// missing interface: falls back and reports it.
+fn resolve_network_interface(
+    key: &str,
+    name: &str,
+    fallback: interface::InterfaceFallback,
+) -> interface::ResolvedInterface {
+    let resolved = interface::resolve(name, fallback);
+    match &resolved.error {
+        Some(e) => error!("Network misconfiguration: {} (fix network.{} in the config)", e, key),
+        None => info!(setting = key, interface = %name, addr = %resolved.addr, "Multicast interface resolved"),
+    }
+    resolved
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing
@@ -297,20 +787,29 @@ async fn main() -> anyhow::Result<()> {
         e
     })?;
 
-    let config: HostConfig = toml::from_str(&config_str).map_err(|e| {
-        error!("Failed to parse config: {}", e);
+    let config = parse_config(&config_str).map_err(|e| {
+        error!("Failed to load config: {}", e);
         e
     })?;
 
     info!(
         host_id = config.host.id,
         name = %config.host.name,
+        priority = config.host.effective_priority(),
         multicast = %config.network.multicast_group,
         "MIDInet host starting"
     );
 
-    // Determine initial role based on host ID (lower ID = primary)
-    let initial_role = if config.host.id == 1 {
+    let fallback = config.network.interface_fallback;
+    let data_interface = resolve_network_interface("interface", &config.network.interface, fallback);
+    let control_interface = resolve_network_interface("control_interface", &config.network.control_interface, fallback);
+
+    // The host ranked first among its peers starts as primary
+    let peers = failover::FailoverPeers::from_config(&config).map_err(|e| {
+        error!("Invalid [failover] config: {}", e);
+        e
+    })?;
+    let initial_role = if peers.is_primary() {
         HostRole::Primary
     } else {
         HostRole::Standby
@@ -348,20 +847,71 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Read device identity from ALSA before creating shared state
-    let device_identity = usb_detector::read_device_identity(&resolved_device);
+    let device_identity = if args.loopback {
+        DeviceIdentity {
+            name: "MIDInet Loopback".to_string(),
+            ..Default::default()
+        }
+    } else if let Some(path) = midi_file::file_path(&resolved_device) {
+        DeviceIdentity {
+            name: format!("MIDI file {}", path.file_name().unwrap_or_default().to_string_lossy()),
+            ..Default::default()
+        }
+    } else {
+        usb_detector::read_device_identity(&resolved_device)
+    };
     info!(device_name = %device_identity.name, "Device identity loaded");
 
+    // Panic requests (control group → broadcaster)
+    let (panic_tx, panic_rx) = mpsc::channel::<Option<u8>>(16);
+
+    // Input recorder (started and stopped from the control group)
+    let (recorder_tx, recorder_handle) = if config.recording.enabled {
+        let (tx, rx) = mpsc::channel::<recorder::RecorderCommand>(1024);
+        let handle = tokio::spawn(recorder::run(config.recording.clone(), config.host.id, rx));
+        (Some(tx), Some(handle))
+    } else {
+        (None, None)
+    };
+
+    // OSC mirror of the outgoing stream
+    let osc_mirror_tx = if config.osc_mirror.enabled {
+        let (tx, rx) = mpsc::channel::<Vec<u8>>(1024);
+        let mirror_config = config.osc_mirror.clone();
+        tokio::spawn(async move {
+            if let Err(e) = osc_mirror::run(mirror_config, rx).await {
+                error!("OSC mirror error: {}", e);
+            }
+        });
+        Some(tx)
+    } else {
+        None
+    };
+
     let state = Arc::new(SharedState {
         config: config.clone(),
         identity: RwLock::new(device_identity),
+        identity_generation: watch::channel(0).0,
         role: role_tx,
         metrics: RwLock::new(metrics::HostMetrics::default()),
-        pipeline_config: RwLock::new(pipeline::PipelineConfig::default()),
+        pipeline_config: RwLock::new(config.pipeline.to_config()),
         midi_state: RwLock::new(MidiState::new()),
         input_active: Arc::clone(&input_active),
         input_switch_count: Arc::clone(&input_switch_count),
         input_redundancy_enabled: dual_input,
         unicast_targets: unicast_rx,
+        unicast_subscribers: Arc::new(std::sync::Mutex::new(unicast_relay::Subscribers::new(config.unicast.max_subscribers))),
+        panic_tx,
+        data_interface,
+        control_interface,
+        packet_clock: PacketClock::new(config.network.timestamp_source),
+        shadowing: watch::channel(config.shadow.enabled).0,
+        data_sequence: AtomicU16::new(0),
+        midi_clock: std::sync::Mutex::new(midi_clock::ClockTracker::new()),
+        data_cipher: PacketCipher::from_psk(&config.network.psk),
+        recorder_tx,
+        osc_mirror_tx,
+        peer_protocol: AtomicU8::new(midi_protocol::PROTOCOL_VERSION),
     });
 
     // --- Dual-controller input setup ---
@@ -373,14 +923,25 @@ async fn main() -> anyhow::Result<()> {
     // Health channel: readers report (input_index, health) events
     let (health_tx, health_rx) = mpsc::channel::<(u8, usb_reader::InputHealth)>(16);
 
-    // Spawn primary MIDI reader
-    let reader_primary_handle = {
+    // Spawn primary MIDI reader (or the loopback generator in its place)
+    let reader_primary_handle = if args.loopback {
+        let period = Duration::from_millis(args.loopback_period_ms);
+        let tx = health_tx.clone();
+        warn!(period_ms = args.loopback_period_ms, "Loopback mode — primary input replaced by a test pattern");
+        tokio::spawn(async move {
+            let tagged_tx = TaggedHealthTx::new(0, tx);
+            if let Err(e) = loopback::run_generator(primary_producer, tagged_tx.into_sender(), period).await {
+                error!("Loopback generator error: {}", e);
+            }
+        })
+    } else {
         let device = resolved_device.clone();
+        let file_loop = config.midi.file_loop;
         let tx = health_tx.clone();
         tokio::spawn(async move {
             let tagged_tx = TaggedHealthTx::new(0, tx);
-            if let Err(e) = usb_reader::platform::run_midi_reader(
-                &device, primary_producer, tagged_tx.into_sender(),
+            if let Err(e) = run_input(
+                &device, file_loop, primary_producer, tagged_tx.into_sender(),
             ).await {
                 error!("Primary MIDI reader error: {}", e);
             }
@@ -390,12 +951,13 @@ async fn main() -> anyhow::Result<()> {
     // Spawn secondary MIDI reader (only if configured)
     let reader_secondary_handle = if dual_input {
         let device = resolved_secondary.clone();
+        let file_loop = config.midi.file_loop;
         let tx = health_tx.clone();
         info!(device = %device, "Input redundancy enabled — spawning secondary MIDI reader");
         Some(tokio::spawn(async move {
             let tagged_tx = TaggedHealthTx::new(1, tx);
-            if let Err(e) = usb_reader::platform::run_midi_reader(
-                &device, secondary_producer, tagged_tx.into_sender(),
+            if let Err(e) = run_input(
+                &device, file_loop, secondary_producer, tagged_tx.into_sender(),
             ).await {
                 error!("Secondary MIDI reader error: {}", e);
             }
@@ -405,8 +967,17 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
-    // Create InputMux (handles dual-controller failover)
-    let mux = Arc::new(input_mux::InputMux::new(primary_consumer, secondary_consumer));
+    // Create InputMux (handles dual-controller failover), with a queue for
+    // MIDI injected over OSC
+    let mut mux = input_mux::InputMux::new(primary_consumer, secondary_consumer);
+    let midi_inject = if config.osc.midi_inject {
+        let (producer, consumer) = ringbuf::midi_ring_buffer(256);
+        mux = mux.with_injected(consumer);
+        Some(producer)
+    } else {
+        None
+    };
+    let mux = Arc::new(mux);
 
     // Auto-switch flag — shared between health monitor, OSC listener, and admin API
     let auto_switch_enabled = Arc::new(AtomicBool::new(true));
@@ -422,6 +993,10 @@ async fn main() -> anyhow::Result<()> {
         } else {
             Duration::ZERO
         };
+        let switch_back = input_mux::SwitchBackTracker::new(
+            config.midi.input_switch_back,
+            Duration::from_secs(config.midi.input_switch_back_window_s),
+        );
         tokio::spawn(async move {
             input_mux::run_health_monitor(
                 mux,
@@ -431,6 +1006,7 @@ async fn main() -> anyhow::Result<()> {
                 dual_input,
                 activity_timeout,
                 auto_switch,
+                switch_back,
             ).await;
         })
     };
@@ -440,7 +1016,7 @@ async fn main() -> anyhow::Result<()> {
         let state = Arc::clone(&state);
         let mux = Arc::clone(&mux);
         tokio::spawn(async move {
-            if let Err(e) = broadcaster::run(state, mux).await {
+            if let Err(e) = broadcaster::run(state, mux, panic_rx).await {
                 error!("Broadcaster error: {}", e);
             }
         })
@@ -468,10 +1044,61 @@ async fn main() -> anyhow::Result<()> {
 
     // Create FailoverManager for manual switch triggers (OSC, MIDI, API)
     let (failover_role_tx, _) = watch::channel(initial_role);
-    let failover_mgr = Arc::new(FailoverManager::new(
-        config.failover.lockout_seconds,
-        failover_role_tx,
-    ));
+    let switch_back_policy = if config.failover.switch_back_policy == "auto" {
+        input_mux::SwitchBack::Auto
+    } else {
+        input_mux::SwitchBack::Manual
+    };
+    let failover_mgr = Arc::new(
+        FailoverManager::new(config.failover.lockout_seconds, failover_role_tx)
+            .with_switch_back(switch_back_policy, Duration::from_secs(config.failover.switch_back_delay_s)),
+    );
+
+    // Learn the primary's MIDI clock while in standby (for regeneration on takeover)
+    let clock_observer_handle = {
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = midi_clock::run_observer(state).await {
+                error!("Clock observer error: {}", e);
+            }
+        })
+    };
+
+    // Auto failover (take over when no primary is heard) and auto switch-back
+    // (return to the recovered primary once its heartbeats are steady)
+    let primary_watch_handle = if config.failover.auto_enabled || switch_back_policy == input_mux::SwitchBack::Auto {
+        let state = Arc::clone(&state);
+        let mgr = Arc::clone(&failover_mgr);
+        Some(tokio::spawn(async move {
+            if let Err(e) = failover::run_primary_watch(state, mgr).await {
+                error!("Primary heartbeat watch error: {}", e);
+            }
+        }))
+    } else {
+        None
+    };
+
+    // Split-brain resolution: yield Primary to a higher-ranked host that also claims it
+    let split_brain_handle = {
+        let state = Arc::clone(&state);
+        let mgr = Arc::clone(&failover_mgr);
+        tokio::spawn(async move {
+            if let Err(e) = failover::run_split_brain(state, mgr).await {
+                error!("Split-brain monitor error: {}", e);
+            }
+        })
+    };
+
+    // Each switch and its cause goes to the admin panel's failover history
+    let history_handle = (!config.failover.admin_url.is_empty())
+        .then(|| tokio::spawn(failover::report_history(Arc::clone(&state), Arc::clone(&failover_mgr))));
+
+    // Follow the admin panel's pinned primary (`midinet promote <id>`)
+    let promotion_handle = (!config.failover.admin_url.is_empty()).then(|| {
+        let state = Arc::clone(&state);
+        let mgr = Arc::clone(&failover_mgr);
+        tokio::spawn(failover::follow_designated_primary(state, mgr))
+    });
 
     // Spawn OSC listener (always — handles both host failover and input switching)
     let osc_handle = {
@@ -481,6 +1108,7 @@ async fn main() -> anyhow::Result<()> {
             mux: if dual_input { Some(Arc::clone(&mux)) } else { None },
             input_switch_count: Arc::clone(&input_switch_count),
             shared_input_active: Arc::clone(&input_active),
+            midi_inject,
         });
         info!(port = config.osc.listen_port, "Spawning OSC listener");
         Some(tokio::spawn(async move {
@@ -494,11 +1122,13 @@ async fn main() -> anyhow::Result<()> {
     let focus_state = Arc::new(RwLock::new(FocusState::default()));
 
     // Create MIDI output writer — sends feedback to ALL connected controllers
+    // (MIDI file inputs have nothing to send feedback to)
     let midi_output = {
         let mut devices: Vec<&str> = vec![&resolved_device];
         if dual_input {
             devices.push(&resolved_secondary);
         }
+        devices.retain(|d| midi_file::file_path(d).is_none());
         Arc::new(midi_output::platform::MidiOutputWriter::open(&devices))
     };
 
@@ -507,8 +1137,9 @@ async fn main() -> anyhow::Result<()> {
         let state = Arc::clone(&state);
         let focus_state = Arc::clone(&focus_state);
         let midi_output = Arc::clone(&midi_output);
+        let mux = if dual_input { Some(Arc::clone(&mux)) } else { None };
         tokio::spawn(async move {
-            if let Err(e) = feedback::run(state, focus_state, midi_output).await {
+            if let Err(e) = feedback::run(state, focus_state, midi_output, mux).await {
                 error!("Feedback receiver error: {}", e);
             }
         })
@@ -517,10 +1148,24 @@ async fn main() -> anyhow::Result<()> {
     // Spawn unicast relay target fetcher (if enabled)
     let unicast_handle = if config.unicast.enabled {
         let admin_url = config.unicast.admin_url.clone();
+        let mode = config.unicast.mode;
         let data_port = config.network.data_port;
-        info!(admin_url = %admin_url, "Unicast relay enabled, fetching client targets from admin API");
+        let subscribers = Arc::clone(&state.unicast_subscribers);
+        info!(admin_url = %admin_url, ?mode, "Unicast relay enabled, fetching client targets from admin API");
         Some(tokio::spawn(async move {
-            unicast_relay::run(admin_url, data_port, unicast_tx).await;
+            unicast_relay::run(admin_url, mode, data_port, subscribers, unicast_tx).await;
+        }))
+    } else {
+        None
+    };
+
+    // Spawn identity monitor — re-reads the identity when the active
+    // controller changes so discovery can re-advertise the new device
+    let identity_monitor_handle = if dual_input {
+        let state = Arc::clone(&state);
+        let devices = [resolved_device.clone(), resolved_secondary.clone()];
+        Some(tokio::spawn(async move {
+            usb_detector::run_identity_monitor(state, devices).await;
         }))
     } else {
         None
@@ -536,21 +1181,72 @@ async fn main() -> anyhow::Result<()> {
         })
     };
 
+    // Shadow mode: mirror the source host and wait for promotion
+    let shadow_handles = if config.shadow.enabled {
+        info!(
+            source = config.shadow.source_host_id,
+            admin_url = %config.shadow.admin_url,
+            "Starting as shadow host, silent until promoted"
+        );
+        let mirror_state = Arc::clone(&state);
+        let poll_state = Arc::clone(&state);
+        Some((
+            tokio::spawn(async move {
+                if let Err(e) = shadow::run_mirror(mirror_state).await {
+                    error!("Shadow mirror error: {}", e);
+                }
+            }),
+            tokio::spawn(shadow::poll_promotion(poll_state)),
+        ))
+    } else {
+        None
+    };
+
     info!(role = ?initial_role, "Host daemon running");
 
     // Wait for shutdown signal
     tokio::signal::ctrl_c().await?;
     info!("Shutting down...");
 
-    // Abort all tasks
+    // Stop everything that feeds the stream first, so the silence is last
     reader_primary_handle.abort();
     if let Some(handle) = reader_secondary_handle {
         handle.abort();
     }
     health_monitor_handle.abort();
+    if let Some(handle) = primary_watch_handle {
+        handle.abort();
+    }
+    if let Some(handle) = history_handle {
+        handle.abort();
+    }
+    split_brain_handle.abort();
+    if let Some(handle) = promotion_handle {
+        handle.abort();
+    }
+    clock_observer_handle.abort();
     broadcaster_handle.abort();
-    discovery_handle.abort();
     heartbeat_handle.abort();
+    if let Some((mirror, poll)) = shadow_handles {
+        mirror.abort();
+        poll.abort();
+    }
+
+    // Tell clients: All Notes Off, then "host stopping" so they switch now
+    if let Err(e) = shutdown::run(&state).await {
+        error!("Failed to announce shutdown: {}", e);
+    }
+
+    // Close an open recording cleanly
+    if let (Some(tx), Some(handle)) = (&state.recorder_tx, recorder_handle) {
+        let _ = tx.send(recorder::RecorderCommand::Shutdown).await;
+        if tokio::time::timeout(Duration::from_secs(2), handle).await.is_err() {
+            warn!("Recorder did not finish in time; recording may be incomplete");
+        }
+    }
+
+    // Abort the remaining tasks
+    discovery_handle.abort();
     if let Some(handle) = osc_handle {
         handle.abort();
     }
@@ -558,7 +1254,58 @@ async fn main() -> anyhow::Result<()> {
     if let Some(handle) = unicast_handle {
         handle.abort();
     }
+    if let Some(handle) = identity_monitor_handle {
+        handle.abort();
+    }
     broadcast_discovery_handle.abort();
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::TEST_CONFIG as BASE;
+
+    #[test]
+    fn pipeline_saved_by_the_admin_loads_at_boot() {
+        // The [pipeline] section as the admin panel writes it
+        let saved = r#"
+            [pipeline]
+            transpose = [12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+            velocity_curve = "SCurve"
+            note_off_form = "note_off"
+
+            [pipeline.message_filter]
+            note_on = true
+            note_off = false
+            aftertouch = true
+            poly_aftertouch = false
+
+            [pipeline.feedback_velocity_curve.Compressor]
+            threshold = 90
+            ratio = 3.0
+            makeup = 4
+
+            [pipeline.harmonize]
+            1 = [0, 4, 7]
+        "#;
+        let config = parse_config(&format!("{}{}", BASE, saved)).unwrap().pipeline.to_config();
+        assert_eq!(config.transpose[0], 12);
+        assert_eq!(config.velocity_curve, pipeline::VelocityCurve::SCurve);
+        assert_eq!(
+            config.feedback_velocity_curve,
+            pipeline::VelocityCurve::Compressor { threshold: 90, ratio: 3.0, makeup: 4 }
+        );
+        assert!(!config.message_filter.note_on_off, "blocking Note Off blocks the pair");
+        assert!(config.message_filter.channel_pressure && !config.message_filter.poly_aftertouch);
+        assert_eq!(config.harmonize[0], Some(vec![0, 4, 7]));
+
+        // Parsed, but not something the pipeline can apply
+        let bad = "[pipeline.velocity_curve.Compressor]\nthreshold = 90\nratio = 0.0\nmakeup = 0\n";
+        let err = parse_config(&format!("{}{}", BASE, bad)).unwrap_err().to_string();
+        assert!(err.contains("velocity_curve"), "{}", err);
+        assert!(parse_config(&format!("{}[pipeline]\nvelocity_curve = \"wobbly\"\n", BASE)).is_err());
+    }
+}
@@ -26,6 +26,10 @@ pub struct HostMetrics {
     pub input_switch_count: u64,
     /// Whether input redundancy is configured
     pub input_redundancy_enabled: bool,
+    /// Data packet send attempts repeated after a transient error
+    pub send_retries: u64,
+    /// Data packets dropped after a send error (retries exhausted or permanent)
+    pub send_drops: u64,
 }
 
 /// Metrics collector that accumulates data from the hot path
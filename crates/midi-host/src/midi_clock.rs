@@ -0,0 +1,285 @@
+/// MIDI clock (0xF8) tracking and regeneration across failover.
+///
+/// Lighting desks and drum machines downstream lock to the 24 PPQN clock in
+/// the stream. When the primary host dies its clock dies with it, and the
+/// standby has nothing to send until the clock source reaches it again —
+/// a tempo glitch at exactly the wrong moment.
+///
+/// `ClockTracker` watches clock ticks (from the local input and, while in
+/// standby, from the primary's stream), measures the tick interval, and
+/// remembers the phase of the last tick. It only generates ticks after
+/// `take_over`, i.e. once this host becomes the primary; generated ticks
+/// continue the observed grid (last tick + n × interval). As soon as a
+/// real tick is observed again generation stops and the source wins.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use midi_protocol::multicast;
+use midi_protocol::crypto::decode_data_packet;
+use midi_protocol::packets::HostRole;
+use tokio::net::UdpSocket;
+use tracing::{debug, error, info};
+
+use crate::failover::FailoverPeers;
+use crate::SharedState;
+
+/// MIDI Timing Clock status byte
+pub const CLOCK_TICK: u8 = 0xF8;
+
+/// Clock ticks per quarter note
+const PPQN: f64 = 24.0;
+
+/// Tick intervals outside this range aren't a running clock (300–20 BPM);
+/// a longer gap means the clock stopped and the measurement restarts.
+const MIN_INTERVAL: Duration = Duration::from_micros(8_333);
+const MAX_INTERVAL: Duration = Duration::from_micros(125_000);
+
+/// Weight of a new sample in the interval average (1/8): smooths network
+/// and USB jitter while following tempo changes within a beat or two.
+const SMOOTHING: f64 = 0.125;
+
+/// Don't regenerate a clock that was last seen this many intervals ago
+const MAX_STALE_TICKS: u32 = 48;
+
+#[derive(Debug, Default)]
+pub struct ClockTracker {
+    last_tick: Option<Instant>,
+    /// Smoothed tick interval in seconds
+    interval_s: Option<f64>,
+    /// Next generated tick while generating
+    next_due: Option<Instant>,
+}
+
+impl ClockTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a real clock tick. Stops any generation: the source is back.
+    pub fn observe(&mut self, now: Instant) {
+        if let Some(last) = self.last_tick {
+            let dt = now.saturating_duration_since(last);
+            if (MIN_INTERVAL..=MAX_INTERVAL).contains(&dt) {
+                let dt = dt.as_secs_f64();
+                self.interval_s = Some(match self.interval_s {
+                    Some(avg) => avg + (dt - avg) * SMOOTHING,
+                    None => dt,
+                });
+            } else if dt > MAX_INTERVAL {
+                self.interval_s = None;
+            }
+        }
+        self.last_tick = Some(now);
+        if self.next_due.take().is_some() {
+            debug!("Clock source back, stopped regenerating");
+        }
+    }
+
+    /// Measured tick interval, once at least two ticks have been seen
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval_s.map(Duration::from_secs_f64)
+    }
+
+    pub fn bpm(&self) -> Option<f64> {
+        self.interval_s.map(|s| 60.0 / (s * PPQN))
+    }
+
+    /// This host became the primary: generate ticks on the observed grid,
+    /// starting with the first grid point after `now`. Returns false if no
+    /// recent clock was measured.
+    pub fn take_over(&mut self, now: Instant) -> bool {
+        let (Some(last), Some(interval)) = (self.last_tick, self.interval()) else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(last);
+        let missed = (elapsed.as_secs_f64() / interval.as_secs_f64()).floor() as u32;
+        if missed > MAX_STALE_TICKS {
+            return false;
+        }
+        self.next_due = Some(last + interval * (missed + 1));
+        info!(bpm = format!("{:.1}", self.bpm().unwrap_or_default()), "Regenerating MIDI clock");
+        true
+    }
+
+    /// Back to standby: leave the clock to the primary.
+    pub fn stop(&mut self) {
+        self.next_due = None;
+    }
+
+    /// When the next generated tick is due (None = not generating)
+    pub fn next_due(&self) -> Option<Instant> {
+        self.next_due
+    }
+
+    /// A generated tick went out; schedule the next one.
+    pub fn tick_sent(&mut self) {
+        if let (Some(due), Some(interval)) = (self.next_due, self.interval()) {
+            self.last_tick = Some(due);
+            self.next_due = Some(due + interval);
+        }
+    }
+}
+
+/// Whether `midi` contains a clock tick. Real-time bytes may sit anywhere
+/// in a message and no data byte has the high bit set, so any 0xF8 counts.
+pub fn has_clock_tick(midi: &[u8]) -> bool {
+    midi.contains(&CLOCK_TICK)
+}
+
+/// While in standby, latch onto the clock in the primary's data stream, on
+/// the primary's group, so `take_over` can continue it.
+pub async fn run_observer(state: Arc<SharedState>) -> anyhow::Result<()> {
+    let own_group = multicast::parse_group(&state.config.network.multicast_group)?;
+    let port = state.config.network.data_port;
+    let peers = FailoverPeers::from_config(&state.config)?;
+
+    // The primary broadcasts on its own group, not this host's
+    let socket = {
+        let sock = multicast::new_socket(own_group)?;
+        sock.set_reuse_address(true)?;
+        #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+        sock.set_reuse_port(true)?;
+        sock.bind(&multicast::bind_addr(own_group, port).into())?;
+        for &group in peers.groups() {
+            multicast::join(&sock, group, state.data_interface.multicast())?;
+        }
+        sock.set_nonblocking(true)?;
+        UdpSocket::from_std(sock.into())?
+    };
+
+    info!(groups = ?peers.groups(), port, primary = ?peers.primary(), "Watching the primary's MIDI clock");
+
+    let mut buf = [0u8; 2048];
+    loop {
+        match socket.recv_from(&mut buf).await {
+            Ok((len, _)) => {
+                if *state.role.borrow() != HostRole::Standby {
+                    continue;
+                }
+                let Ok(packet) = decode_data_packet(state.data_cipher.as_ref(), &buf[..len]) else {
+                    continue;
+                };
+                if peers.is_primary_stream(packet.host_id) && has_clock_tick(&packet.midi_data) {
+                    state.midi_clock.lock().unwrap().observe(Instant::now());
+                }
+            }
+            Err(e) => {
+                error!("Clock observer receive error: {}", e);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host_config;
+
+    /// 120 BPM at 24 PPQN
+    const TICK_120_BPM: Duration = Duration::from_nanos(20_833_333);
+
+    fn feed_120_bpm(start: Instant, ticks: u32) -> ClockTracker {
+        let mut tracker = ClockTracker::new();
+        for i in 0..ticks {
+            tracker.observe(start + TICK_120_BPM * i);
+        }
+        tracker
+    }
+
+    fn assert_within_1ms(actual: Duration, expected: Duration) {
+        let diff = actual.abs_diff(expected);
+        assert!(diff < Duration::from_millis(1), "{actual:?} vs {expected:?}");
+    }
+
+    #[test]
+    fn regenerated_clock_keeps_120_bpm_and_phase() {
+        let start = Instant::now();
+        let tracker_ticks = 48; // two beats
+        let mut tracker = feed_120_bpm(start, tracker_ticks);
+        assert!((tracker.bpm().unwrap() - 120.0).abs() < 0.5);
+
+        // Standby learns the clock but doesn't generate it
+        assert_eq!(tracker.next_due(), None);
+
+        // Primary dies 5ms after its last tick; we take over
+        let last = start + TICK_120_BPM * (tracker_ticks - 1);
+        assert!(tracker.take_over(last + Duration::from_millis(5)));
+
+        let mut prev = last;
+        for _ in 0..24 {
+            let due = tracker.next_due().unwrap();
+            assert_within_1ms(due - prev, TICK_120_BPM);
+            tracker.tick_sent();
+            prev = due;
+        }
+        // Still on the original grid a beat later
+        assert_within_1ms(prev - last, TICK_120_BPM * 24);
+    }
+
+    #[test]
+    fn take_over_skips_grid_points_already_past() {
+        let start = Instant::now();
+        let mut tracker = feed_120_bpm(start, 24);
+        let last = start + TICK_120_BPM * 23;
+
+        // Failover took 50ms: two ticks were lost, the third is next
+        assert!(tracker.take_over(last + Duration::from_millis(50)));
+        assert_within_1ms(tracker.next_due().unwrap() - last, TICK_120_BPM * 3);
+    }
+
+    #[test]
+    fn real_ticks_stop_generation() {
+        let start = Instant::now();
+        let mut tracker = feed_120_bpm(start, 24);
+        let last = start + TICK_120_BPM * 23;
+        assert!(tracker.take_over(last));
+        assert!(tracker.next_due().is_some());
+
+        tracker.observe(last + TICK_120_BPM);
+        assert_eq!(tracker.next_due(), None);
+    }
+
+    #[test]
+    fn no_take_over_without_a_running_clock() {
+        let start = Instant::now();
+        let mut tracker = ClockTracker::new();
+        assert!(!tracker.take_over(start));
+
+        let mut tracker = feed_120_bpm(start, 24);
+        // Clock stopped long ago
+        assert!(!tracker.take_over(start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn standby_follows_the_primary_stream_on_its_group() {
+        let primary_group: std::net::IpAddr = "239.69.83.1".parse().unwrap();
+        let standby = FailoverPeers::from_config(&host_config(2, "239.69.83.2", 0, "")).unwrap();
+        assert_eq!(standby.groups(), [primary_group]);
+
+        // Ticks from host 1 on its group are followed, our own looped-back
+        // ones aren't
+        let start = Instant::now();
+        let mut tracker = ClockTracker::new();
+        for i in 0..24 {
+            let at = start + TICK_120_BPM * i;
+            for host_id in [1, 2] {
+                if standby.is_primary_stream(host_id) {
+                    tracker.observe(at);
+                }
+            }
+        }
+        assert!(tracker.take_over(start + TICK_120_BPM * 23 + Duration::from_millis(1)));
+        assert_within_1ms(tracker.next_due().unwrap() - start, TICK_120_BPM * 24);
+
+        // With three hosts, another standby's stream isn't the primary's
+        let peers = "[[failover.peers]]\nid = 1\nmulticast_group = \"239.69.83.1\"\n\
+                     [[failover.peers]]\nid = 2\nmulticast_group = \"239.69.83.2\"";
+        let third = FailoverPeers::from_config(&host_config(3, "239.69.83.3", 0, peers)).unwrap();
+        assert_eq!(third.groups().len(), 2);
+        assert!(third.is_primary_stream(1));
+        assert!(!third.is_primary_stream(2));
+        assert!(!third.is_primary_stream(3));
+    }
+}
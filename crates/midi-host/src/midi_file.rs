@@ -0,0 +1,399 @@
+/// Standard MIDI File playback as a MIDI input (`midi.device = "file:PATH"`).
+///
+/// A drop-in for `usb_reader` for automated QA and demos: the file is
+/// parsed up front into one timeline of events with wall-clock offsets,
+/// then played into the input's ring buffer, so the input mux, pipeline and
+/// broadcaster treat it like a controller. Playback loops unless
+/// `midi.file_loop = false`.
+///
+/// Formats 0 and 1 are supported (format 2 holds independent sequences and
+/// has no single timeline). Tracks are merged by tick; tempo changes from
+/// any track form the tempo map (default 120 BPM), and SMPTE time
+/// divisions are honoured. All other meta events are ignored. SysEx is
+/// played as F0 … F7, escape (F7) events as their raw bytes.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use midi_protocol::ringbuf::{MidiProducer, SLOT_SIZE};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::usb_reader::InputHealth;
+
+/// Prefix of `midi.device` values that name a MIDI file
+const FILE_PREFIX: &str = "file:";
+
+/// 120 BPM until the first tempo event
+const DEFAULT_TEMPO_US: u64 = 500_000;
+
+/// The file path of a `file:PATH` device, or None for a hardware device.
+pub fn file_path(device: &str) -> Option<&Path> {
+    device.strip_prefix(FILE_PREFIX).map(Path::new)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedEvent {
+    /// Offset from the start of the file
+    pub at_us: u64,
+    pub data: Vec<u8>,
+}
+
+/// A parsed file: every track's MIDI events on one timeline.
+#[derive(Debug, Clone)]
+pub struct MidiFile {
+    pub events: Vec<TimedEvent>,
+    /// Time of the last end-of-track, i.e. one loop's length
+    pub length_us: u64,
+}
+
+enum Division {
+    /// Ticks per quarter note; tick length follows the tempo map
+    Metrical(u64),
+    /// Fixed tick length (SMPTE frames × ticks per frame), in ns
+    Timecode(u64),
+}
+
+enum TrackEvent {
+    Midi(Vec<u8>),
+    Tempo(u64),
+    End,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.bytes.len());
+        let end = end.ok_or_else(|| anyhow!("unexpected end of data at byte {}", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Variable-length quantity: 7 bits per byte, at most 4 bytes
+    fn vlq(&mut self) -> anyhow::Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let b = self.u8()?;
+            value = (value << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("variable-length quantity longer than 4 bytes at byte {}", self.pos)
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+}
+
+/// One MTrk chunk as (absolute tick, event).
+fn parse_track(data: &[u8]) -> anyhow::Result<Vec<(u64, TrackEvent)>> {
+    let mut r = Reader { bytes: data, pos: 0 };
+    let mut events = Vec::new();
+    let mut tick = 0u64;
+    let mut running: Option<u8> = None;
+
+    while !r.done() {
+        tick += r.vlq()? as u64;
+        let first = r.u8()?;
+        match first {
+            0xFF => {
+                running = None;
+                let kind = r.u8()?;
+                let len = r.vlq()? as usize;
+                let body = r.take(len)?;
+                match kind {
+                    0x51 if len == 3 => {
+                        let tempo = u32::from_be_bytes([0, body[0], body[1], body[2]]) as u64;
+                        events.push((tick, TrackEvent::Tempo(tempo)));
+                    }
+                    0x2F => {
+                        events.push((tick, TrackEvent::End));
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                running = None;
+                let len = r.vlq()? as usize;
+                let body = r.take(len)?;
+                let mut message = Vec::with_capacity(len + 1);
+                if first == 0xF0 {
+                    message.push(0xF0);
+                }
+                message.extend_from_slice(body);
+                events.push((tick, TrackEvent::Midi(message)));
+            }
+            _ => {
+                // Channel message, possibly under running status
+                let (status, first_data) = if first & 0x80 != 0 {
+                    (first, None)
+                } else {
+                    let status = running.ok_or_else(|| anyhow!("data byte without running status"))?;
+                    (status, Some(first))
+                };
+                running = Some(status);
+                let len = match status & 0xF0 {
+                    0xC0 | 0xD0 => 1,
+                    _ => 2,
+                };
+                let mut message = vec![status];
+                if let Some(b) = first_data {
+                    message.push(b);
+                }
+                while message.len() < len + 1 {
+                    message.push(r.u8()?);
+                }
+                events.push((tick, TrackEvent::Midi(message)));
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Parse a Standard MIDI File (format 0 or 1).
+pub fn parse(bytes: &[u8]) -> anyhow::Result<MidiFile> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(4)? != b"MThd" {
+        bail!("not a Standard MIDI File (missing MThd)");
+    }
+    let header_len = r.u32()? as usize;
+    let header = r.take(header_len)?;
+    if header.len() < 6 {
+        bail!("MThd chunk too short");
+    }
+    let format = u16::from_be_bytes([header[0], header[1]]);
+    let division = u16::from_be_bytes([header[4], header[5]]);
+    if format > 1 {
+        bail!("MIDI file format {} is not supported (only 0 and 1)", format);
+    }
+    let division = if division & 0x8000 != 0 {
+        let fps = match (division >> 8) as u8 as i8 {
+            -29 => 29.97,
+            fps => -(fps as f64),
+        };
+        let ticks_per_frame = (division & 0xFF) as f64;
+        Division::Timecode((1e9 / (fps * ticks_per_frame)) as u64)
+    } else {
+        Division::Metrical((division as u64).max(1))
+    };
+
+    // Concatenated tracks, then a stable sort merges them by tick with
+    // ties kept in track order
+    let mut merged = Vec::new();
+    while !r.done() {
+        let id = r.take(4)?;
+        let len = r.u32()? as usize;
+        let data = r.take(len)?;
+        if id == b"MTrk" {
+            merged.extend(parse_track(data)?);
+        }
+    }
+    merged.sort_by_key(|(tick, _)| *tick);
+
+    // Tempo map: time of the current tempo segment's start + ticks since
+    let mut events = Vec::new();
+    let mut length_us = 0;
+    let (mut seg_tick, mut seg_us, mut tempo) = (0u64, 0u64, DEFAULT_TEMPO_US);
+    let time_of = |tick: u64, seg_tick: u64, seg_us: u64, tempo: u64| -> u64 {
+        let ticks = (tick - seg_tick) as u128;
+        seg_us
+            + match division {
+                Division::Metrical(ppq) => (ticks * tempo as u128 / ppq as u128) as u64,
+                Division::Timecode(ns) => (ticks * ns as u128 / 1000) as u64,
+            }
+    };
+    for (tick, event) in merged {
+        let at_us = time_of(tick, seg_tick, seg_us, tempo);
+        match event {
+            TrackEvent::Midi(data) => events.push(TimedEvent { at_us, data }),
+            TrackEvent::Tempo(t) => {
+                (seg_tick, seg_us, tempo) = (tick, at_us, t);
+            }
+            TrackEvent::End => {}
+        }
+        length_us = length_us.max(at_us);
+    }
+
+    Ok(MidiFile { events, length_us })
+}
+
+/// Play `path` into `producer` at the file's timing, looping if `looped`.
+pub async fn run_player(
+    path: &Path,
+    looped: bool,
+    producer: MidiProducer,
+    health_tx: mpsc::Sender<InputHealth>,
+) -> anyhow::Result<()> {
+    let loaded = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read MIDI file {:?}", path))
+        .and_then(|bytes| parse(&bytes).with_context(|| format!("Failed to parse MIDI file {:?}", path)));
+    let file = match loaded {
+        Ok(file) => file,
+        Err(e) => {
+            let _ = health_tx.send(InputHealth::Disconnected(format!("{:#}", e))).await;
+            return Err(e);
+        }
+    };
+
+    info!(
+        path = ?path,
+        events = file.events.len(),
+        length_s = format!("{:.1}", file.length_us as f64 / 1e6),
+        looped,
+        "Playing MIDI file"
+    );
+    let _ = health_tx.send(InputHealth::Active).await;
+
+    loop {
+        let start = Instant::now();
+        for event in &file.events {
+            tokio::time::sleep_until(start + Duration::from_micros(event.at_us)).await;
+            if health_tx.is_closed() {
+                info!("Health channel closed — MIDI file player exiting");
+                return Ok(());
+            }
+            if event.data.len() > SLOT_SIZE {
+                debug!(bytes = event.data.len(), "SysEx too long for the input buffer, skipped");
+                continue;
+            }
+            producer.push_overwrite(&event.data);
+        }
+        tokio::time::sleep_until(start + Duration::from_micros(file.length_us)).await;
+
+        if !looped {
+            info!(path = ?path, "MIDI file finished");
+            return Ok(());
+        }
+        // Everything at time zero: looping would replay it back to back
+        // without ever yielding
+        if file.length_us == 0 {
+            warn!(path = ?path, "MIDI file has zero length, played once instead of looping");
+            return Ok(());
+        }
+        debug!(path = ?path, "MIDI file looping");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn smf(format: u16, division: u16, tracks: &[&[u8]]) -> Vec<u8> {
+        let mut header = format.to_be_bytes().to_vec();
+        header.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        header.extend_from_slice(&division.to_be_bytes());
+        let mut out = chunk(b"MThd", &header);
+        for track in tracks {
+            out.extend(chunk(b"MTrk", track));
+        }
+        out
+    }
+
+    #[test]
+    fn two_track_format_1_follows_the_tempo_change() {
+        // Conductor track: 120 BPM, then 60 BPM from beat 2, a name in between
+        let conductor: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // tempo 500000
+            0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', // track name (ignored)
+            0x83, 0x60, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // +480: tempo 1000000
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        // Notes: one per beat, the second beat under running status
+        let notes: &[u8] = &[
+            0x00, 0x90, 60, 100,
+            0x83, 0x60, 0x80, 60, 0, // +480: beat 2
+            0x00, 0x90, 62, 100,
+            0x83, 0x60, 62, 0, // +480 running status (Note On vel 0)
+            0x00, 0xC1, 5, // program change: one data byte
+            0x83, 0x60, 0xFF, 0x2F, 0x00, // +480: end
+        ];
+        let file = parse(&smf(1, 480, &[conductor, notes])).unwrap();
+
+        let timeline: Vec<(u64, Vec<u8>)> =
+            file.events.iter().map(|e| (e.at_us, e.data.clone())).collect();
+        assert_eq!(
+            timeline,
+            vec![
+                (0, vec![0x90, 60, 100]),
+                (500_000, vec![0x80, 60, 0]),
+                (500_000, vec![0x90, 62, 100]),
+                (1_500_000, vec![0x90, 62, 0]),
+                (1_500_000, vec![0xC1, 5]),
+            ]
+        );
+        assert_eq!(file.length_us, 2_500_000);
+    }
+
+    #[test]
+    fn format_0_with_sysex_and_smpte_division() {
+        // 25 fps × 40 ticks per frame = 1ms per tick
+        let division = (((-25i8) as u8 as u16) << 8) | 40;
+        let track: &[u8] = &[
+            0x00, 0xF0, 0x04, 0x7E, 0x7F, 0x09, 0xF7, // GM reset, F0 implied
+            0x0A, 0xB0, 7, 90, // +10 ticks
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let file = parse(&smf(0, division, &[track])).unwrap();
+        assert_eq!(file.events[0], TimedEvent { at_us: 0, data: vec![0xF0, 0x7E, 0x7F, 0x09, 0xF7] });
+        assert_eq!(file.events[1], TimedEvent { at_us: 10_000, data: vec![0xB0, 7, 90] });
+    }
+
+    #[tokio::test]
+    async fn zero_length_file_plays_once_instead_of_spinning() {
+        let track: &[u8] = &[0x00, 0x90, 60, 100, 0x00, 0x80, 60, 0, 0x00, 0xFF, 0x2F, 0x00];
+        let path = std::env::temp_dir().join(format!("midinet-zero-length-{}.mid", std::process::id()));
+        std::fs::write(&path, smf(0, 480, &[track])).unwrap();
+
+        let (producer, consumer) = midi_protocol::ringbuf::midi_ring_buffer(16);
+        let (health_tx, _health_rx) = mpsc::channel(4);
+        let played = tokio::time::timeout(Duration::from_secs(1), run_player(&path, true, producer, health_tx)).await;
+        std::fs::remove_file(&path).unwrap();
+        assert!(played.expect("looped a zero-length file").is_ok());
+
+        let mut buf = [0u8; SLOT_SIZE];
+        assert_eq!(consumer.try_pop(&mut buf), Some(3));
+        assert_eq!(consumer.try_pop(&mut buf), Some(3));
+        assert_eq!(consumer.try_pop(&mut buf), None);
+    }
+
+    #[test]
+    fn unsupported_or_truncated_files_are_errors() {
+        let track: &[u8] = &[0x00, 0xFF, 0x2F, 0x00];
+        assert!(parse(&smf(2, 480, &[track])).is_err());
+        assert!(parse(b"RIFF....").is_err());
+
+        let mut truncated = smf(1, 480, &[&[0x00, 0x90, 60, 100, 0x00, 0xFF, 0x2F, 0x00]]);
+        truncated.truncate(truncated.len() - 6);
+        assert!(parse(&truncated).is_err());
+
+        assert_eq!(file_path("file:/tmp/song.mid"), Some(Path::new("/tmp/song.mid")));
+        assert_eq!(file_path("hw:1,0,0"), None);
+    }
+}
@@ -4,11 +4,29 @@
 /// Supported OSC addresses:
 ///   /midinet/failover/switch   — Trigger manual failover to the other host
 ///   /midinet/input/switch      — Switch active input controller (toggle or target 0/1)
+///   /midinet/input/enable      — Enable/disable an input: <index 0/1> <enabled 0/1>
+///   /midinet/ping              — Keepalive; answered with /midinet/pong <host_id> <role>
+///   /midinet/midi/note         — Inject a note: <channel 1-16> <note> <velocity> (0 = Note Off)
+///   /midinet/midi/cc           — Inject a Control Change: <channel 1-16> <controller> <value>
+///
+/// MIDI injection is off unless `osc.midi_inject = true`. Injected messages
+/// enter the input mux as if the controller had sent them, so they go
+/// through the pipeline and out to the clients. Senders are checked against
+/// the failover trigger's `allowed_sources`, and arguments must be OSC
+/// int32s in range; anything else is dropped with a warning.
+///
+/// With `osc.keepalive_timeout_ms` set, the show-control system is expected
+/// to ping periodically. If pings stop for longer than the timeout the host
+/// raises an alert, so a broken OSC link is noticed before the show rather
+/// than when a failover cue is fired into the void.
 
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use midi_protocol::failover::FailoverCause;
+use midi_protocol::ringbuf::MidiProducer;
 use rosc::{OscMessage, OscPacket, OscType};
 use tokio::net::UdpSocket;
 use tracing::{debug, error, info, warn};
@@ -17,6 +35,43 @@ use crate::failover::FailoverManager;
 use crate::input_mux::InputMux;
 use crate::SharedState;
 
+pub const PING_ADDRESS: &str = "/midinet/ping";
+pub const PONG_ADDRESS: &str = "/midinet/pong";
+pub const NOTE_INJECT_ADDRESS: &str = "/midinet/midi/note";
+pub const CC_INJECT_ADDRESS: &str = "/midinet/midi/cc";
+
+/// Watches for keepalive pings from the show-control system.
+pub struct KeepaliveMonitor {
+    timeout: Duration,
+    last_ping: Instant,
+    alerting: bool,
+}
+
+impl KeepaliveMonitor {
+    /// `started` counts as the first ping, so a link that never comes up
+    /// alerts after one timeout too.
+    pub fn new(timeout: Duration, started: Instant) -> Self {
+        Self { timeout, last_ping: started, alerting: false }
+    }
+
+    /// Record a ping. Returns true if this clears an active alert.
+    pub fn record(&mut self, now: Instant) -> bool {
+        self.last_ping = now;
+        std::mem::take(&mut self.alerting)
+    }
+
+    /// Returns the silence duration when the alert is newly raised
+    /// (once per outage).
+    pub fn check(&mut self, now: Instant) -> Option<Duration> {
+        let silent = now.saturating_duration_since(self.last_ping);
+        if self.alerting || silent <= self.timeout {
+            return None;
+        }
+        self.alerting = true;
+        Some(silent)
+    }
+}
+
 /// Context passed to the OSC message handler.
 pub struct OscContext {
     pub state: Arc<SharedState>,
@@ -24,6 +79,8 @@ pub struct OscContext {
     pub mux: Option<Arc<InputMux>>,
     pub input_switch_count: Arc<AtomicU64>,
     pub shared_input_active: Arc<AtomicU8>,
+    /// Injected MIDI into the input mux (None unless `osc.midi_inject`)
+    pub midi_inject: Option<MidiProducer>,
 }
 
 /// Run the OSC listener on the configured port.
@@ -45,14 +102,41 @@ pub async fn run(ctx: Arc<OscContext>) -> anyhow::Result<()> {
         info!("OSC failover trigger disabled — listener running but ignoring failover commands");
     }
 
+    let keepalive_timeout = ctx.state.config.osc.keepalive_timeout_ms;
+    let mut keepalive = (keepalive_timeout > 0)
+        .then(|| KeepaliveMonitor::new(Duration::from_millis(keepalive_timeout), Instant::now()));
+    if keepalive.is_some() {
+        info!(timeout_ms = keepalive_timeout, "Expecting OSC keepalives on {}", PING_ADDRESS);
+    }
+    let mut keepalive_check = tokio::time::interval(Duration::from_millis(keepalive_timeout.clamp(100, 1000)));
+
     let mut buf = [0u8; 1500];
 
     loop {
-        match socket.recv_from(&mut buf).await {
+        let received = tokio::select! {
+            r = socket.recv_from(&mut buf) => r,
+            _ = keepalive_check.tick(), if keepalive.is_some() => {
+                if let Some(silent) = keepalive.as_mut().and_then(|k| k.check(Instant::now())) {
+                    error!(
+                        silent_ms = silent.as_millis() as u64,
+                        timeout_ms = keepalive_timeout,
+                        "ALERT: OSC keepalives from show control stopped — check the OSC link"
+                    );
+                }
+                continue;
+            }
+        };
+
+        match received {
             Ok((len, source)) => {
                 match rosc::decoder::decode_udp(&buf[..len]) {
                     Ok((_, packet)) => {
-                        handle_osc_packet(&packet, source, &ctx).await;
+                        if let Some(ref mut k) = keepalive {
+                            if contains_address(&packet, PING_ADDRESS) && k.record(Instant::now()) {
+                                info!(from = %source, "OSC keepalives resumed");
+                            }
+                        }
+                        handle_osc_packet(&packet, source, &socket, &ctx).await;
                     }
                     Err(e) => {
                         debug!(from = %source, "Invalid OSC packet: {:?}", e);
@@ -67,19 +151,51 @@ pub async fn run(ctx: Arc<OscContext>) -> anyhow::Result<()> {
     }
 }
 
+/// Whether `packet` (or any message in a bundle) is addressed to `addr`.
+fn contains_address(packet: &OscPacket, addr: &str) -> bool {
+    match packet {
+        OscPacket::Message(msg) => msg.addr == addr,
+        OscPacket::Bundle(bundle) => bundle.content.iter().any(|p| contains_address(p, addr)),
+    }
+}
+
+/// Reply to a keepalive ping: /midinet/pong <host_id> <role>
+async fn send_pong(socket: &UdpSocket, source: SocketAddr, ctx: &OscContext) {
+    let role = match *ctx.state.role.borrow() {
+        midi_protocol::packets::HostRole::Primary => "primary",
+        midi_protocol::packets::HostRole::Standby => "standby",
+    };
+    let pong = OscPacket::Message(OscMessage {
+        addr: PONG_ADDRESS.to_string(),
+        args: vec![
+            OscType::Int(ctx.state.config.host.id as i32),
+            OscType::String(role.to_string()),
+        ],
+    });
+    match rosc::encoder::encode(&pong) {
+        Ok(bytes) => {
+            if let Err(e) = socket.send_to(&bytes, source).await {
+                debug!(to = %source, "Failed to send OSC pong: {}", e);
+            }
+        }
+        Err(e) => debug!("Failed to encode OSC pong: {:?}", e),
+    }
+}
+
 fn handle_osc_packet<'a>(
     packet: &'a OscPacket,
     source: SocketAddr,
+    socket: &'a UdpSocket,
     ctx: &'a OscContext,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'a>> {
     Box::pin(async move {
         match packet {
             OscPacket::Message(msg) => {
-                handle_osc_message(msg, source, ctx).await;
+                handle_osc_message(msg, source, socket, ctx).await;
             }
             OscPacket::Bundle(bundle) => {
                 for item in &bundle.content {
-                    handle_osc_packet(item, source, ctx).await;
+                    handle_osc_packet(item, source, socket, ctx).await;
                 }
             }
         }
@@ -89,6 +205,7 @@ fn handle_osc_packet<'a>(
 async fn handle_osc_message(
     msg: &OscMessage,
     source: SocketAddr,
+    socket: &UdpSocket,
     ctx: &OscContext,
 ) {
     let trigger = &ctx.state.config.failover.triggers.osc;
@@ -100,6 +217,12 @@ async fn handle_osc_message(
         "Received OSC message"
     );
 
+    // ── Keepalive (/midinet/ping) ──
+    if msg.addr == PING_ADDRESS {
+        send_pong(socket, source, ctx).await;
+        return;
+    }
+
     // ── Host failover switch (/midinet/failover/switch) ──
     if msg.addr == trigger.address {
         if !trigger.enabled {
@@ -108,32 +231,18 @@ async fn handle_osc_message(
         }
 
         // Validate source IP against whitelist
-        if !trigger.allowed_sources.is_empty() {
-            let source_ip = source.ip().to_string();
-            let allowed = trigger.allowed_sources.iter().any(|allowed| {
-                if let Some(prefix) = allowed.split('/').next() {
-                    let prefix_parts: Vec<&str> = prefix.split('.').collect();
-                    let source_parts: Vec<&str> = source_ip.split('.').collect();
-                    if allowed.contains("/24") && prefix_parts.len() == 4 && source_parts.len() == 4 {
-                        return prefix_parts[..3] == source_parts[..3];
-                    }
-                }
-                source_ip == *allowed
-            });
-
-            if !allowed {
-                warn!(
-                    from = %source,
-                    "OSC failover command rejected — source not in whitelist"
-                );
-                return;
-            }
+        if !source_allowed(&trigger.allowed_sources, source) {
+            warn!(
+                from = %source,
+                "OSC failover command rejected — source not in whitelist"
+            );
+            return;
         }
 
         info!(from = %source, "OSC failover switch triggered");
 
         if ctx.failover_mgr.can_switch() {
-            ctx.failover_mgr.trigger_switch(&ctx.state.role);
+            ctx.failover_mgr.trigger_switch(&ctx.state.role, FailoverCause::Osc, format!("OSC {} from {}", msg.addr, source));
             info!("Failover switch executed via OSC");
         } else {
             warn!("Failover switch blocked by lockout period");
@@ -162,7 +271,10 @@ async fn handle_osc_message(
                 }
             }
 
-            let new = mux.switch();
+            let Some(new) = mux.switch() else {
+                warn!(from = %source, current = current, "OSC input switch: other input is disabled");
+                return;
+            };
             ctx.input_switch_count.fetch_add(1, Ordering::Relaxed);
             ctx.shared_input_active.store(new, Ordering::Relaxed);
 
@@ -179,5 +291,156 @@ async fn handle_osc_message(
         return;
     }
 
+    // ── Input enable/disable (/midinet/input/enable <index> <enabled>) ──
+    if msg.addr == "/midinet/input/enable" {
+        let arg = |i: usize| {
+            msg.args.get(i).and_then(|a| match a {
+                OscType::Int(v) => Some(*v),
+                OscType::Float(v) => Some(*v as i32),
+                OscType::Bool(v) => Some(*v as i32),
+                _ => None,
+            })
+        };
+        let (Some(index), Some(enabled)) = (arg(0), arg(1)) else {
+            warn!(from = %source, "OSC input enable: expected <index> <enabled>");
+            return;
+        };
+        match ctx.mux {
+            Some(ref mux) if mux.set_enabled(index as u8, enabled != 0) => {
+                info!(from = %source, input = index, enabled = enabled != 0, "Input enable changed via OSC");
+            }
+            Some(_) => warn!(from = %source, input = index, "OSC input enable: no such input"),
+            None => warn!(from = %source, "OSC input enable: no InputMux (single-controller mode)"),
+        }
+        return;
+    }
+
+    // ── MIDI injection (/midinet/midi/note, /midinet/midi/cc) ──
+    let status = match msg.addr.as_str() {
+        NOTE_INJECT_ADDRESS => Some(0x90),
+        CC_INJECT_ADDRESS => Some(0xB0),
+        _ => None,
+    };
+    if let Some(status) = status {
+        let Some(ref producer) = ctx.midi_inject else {
+            debug!(from = %source, "OSC MIDI injection disabled, ignoring");
+            return;
+        };
+        if !source_allowed(&trigger.allowed_sources, source) {
+            warn!(from = %source, "OSC MIDI injection rejected — source not in whitelist");
+            return;
+        }
+        match injected_midi(status, &msg.args) {
+            Ok(midi) => {
+                producer.push_overwrite(&midi);
+                debug!(from = %source, midi = ?midi, "MIDI injected via OSC");
+            }
+            Err(e) => warn!(from = %source, addr = %msg.addr, "OSC MIDI injection dropped: {}", e),
+        }
+        return;
+    }
+
     debug!(addr = %msg.addr, "Unhandled OSC address");
 }
+
+/// Whether `source` may send commands: an empty list allows everyone,
+/// entries are single IPs or `a.b.c.0/24` networks.
+fn source_allowed(allowed_sources: &[String], source: SocketAddr) -> bool {
+    if allowed_sources.is_empty() {
+        return true;
+    }
+    let source_ip = source.ip().to_string();
+    allowed_sources.iter().any(|allowed| {
+        if let Some(prefix) = allowed.split('/').next() {
+            let prefix_parts: Vec<&str> = prefix.split('.').collect();
+            let source_parts: Vec<&str> = source_ip.split('.').collect();
+            if allowed.contains("/24") && prefix_parts.len() == 4 && source_parts.len() == 4 {
+                return prefix_parts[..3] == source_parts[..3];
+            }
+        }
+        source_ip == *allowed
+    })
+}
+
+/// MIDI bytes for an injection message: `status` on <channel 1-16> with two
+/// data bytes. Only int32 arguments are accepted.
+fn injected_midi(status: u8, args: &[OscType]) -> Result<[u8; 3], String> {
+    let [channel, data1, data2] = args else {
+        return Err(format!("expected 3 int arguments, got {}", args.len()));
+    };
+    let int = |arg: &OscType, range: std::ops::RangeInclusive<i32>| match arg {
+        OscType::Int(v) if range.contains(v) => Ok(*v as u8),
+        OscType::Int(v) => Err(format!("{} out of range {}-{}", v, range.start(), range.end())),
+        other => Err(format!("expected int, got {:?}", other)),
+    };
+    let channel = int(channel, 1..=16)? - 1;
+    Ok([status | channel, int(data1, 0..=127)?, int(data2, 0..=127)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_keepalive_past_threshold_raises_alert_once() {
+        let start = Instant::now();
+        let mut k = KeepaliveMonitor::new(Duration::from_millis(500), start);
+
+        k.record(start + Duration::from_millis(300));
+        assert_eq!(k.check(start + Duration::from_millis(700)), None);
+
+        // 600ms since the last ping: alert, but only once for this outage
+        let silent = k.check(start + Duration::from_millis(900)).unwrap();
+        assert_eq!(silent, Duration::from_millis(600));
+        assert_eq!(k.check(start + Duration::from_millis(2_000)), None);
+
+        // Pings resume: alert clears, and a new outage alerts again
+        assert!(k.record(start + Duration::from_millis(2_100)));
+        assert!(!k.record(start + Duration::from_millis(2_200)));
+        assert!(k.check(start + Duration::from_millis(2_800)).is_some());
+    }
+
+    #[test]
+    fn never_started_link_alerts_after_timeout() {
+        let start = Instant::now();
+        let mut k = KeepaliveMonitor::new(Duration::from_millis(500), start);
+        assert_eq!(k.check(start + Duration::from_millis(400)), None);
+        assert!(k.check(start + Duration::from_millis(501)).is_some());
+    }
+
+    #[test]
+    fn injection_args_must_be_ints_in_range() {
+        use OscType::{Float, Int};
+        assert_eq!(injected_midi(0x90, &[Int(1), Int(60), Int(100)]), Ok([0x90, 60, 100]));
+        assert_eq!(injected_midi(0xB0, &[Int(16), Int(7), Int(0)]), Ok([0xBF, 7, 0]));
+
+        // A float where an int is expected drops the message
+        assert!(injected_midi(0x90, &[Int(1), Float(60.0), Int(100)]).is_err());
+        assert!(injected_midi(0x90, &[Int(0), Int(60), Int(100)]).is_err());
+        assert!(injected_midi(0xB0, &[Int(1), Int(7), Int(128)]).is_err());
+        assert!(injected_midi(0xB0, &[Int(1), Int(7)]).is_err());
+    }
+
+    #[test]
+    fn allowlist_accepts_listed_ips_and_slash_24_networks() {
+        let source = |ip: &str| SocketAddr::new(ip.parse().unwrap(), 9000);
+        let allowed = vec!["10.0.0.5".to_string(), "192.168.1.0/24".to_string()];
+        assert!(source_allowed(&[], source("172.16.0.1")));
+        assert!(source_allowed(&allowed, source("10.0.0.5")));
+        assert!(source_allowed(&allowed, source("192.168.1.77")));
+        assert!(!source_allowed(&allowed, source("10.0.0.6")));
+        assert!(!source_allowed(&allowed, source("192.168.2.77")));
+    }
+
+    #[test]
+    fn ping_is_found_inside_bundles() {
+        let ping = OscPacket::Message(OscMessage { addr: PING_ADDRESS.to_string(), args: vec![] });
+        let other = OscPacket::Message(OscMessage { addr: "/cue/go".to_string(), args: vec![] });
+        let bundle = OscPacket::Bundle(rosc::OscBundle {
+            timetag: rosc::OscTime { seconds: 0, fractional: 1 },
+            content: vec![other.clone(), ping],
+        });
+        assert!(contains_address(&bundle, PING_ADDRESS));
+        assert!(!contains_address(&other, PING_ADDRESS));
+    }
+}
@@ -0,0 +1,201 @@
+/// OSC mirror: re-sends the outgoing MIDI stream as OSC messages for
+/// media servers and show-control software that prefer OSC.
+///
+/// Enabled with `[osc_mirror] enabled = true`. The broadcaster hands each
+/// post-pipeline chunk to the mirror task after it is built, so encoding
+/// and the extra UDP send never sit on the MIDI path; if the task falls
+/// behind, chunks are dropped rather than delaying the broadcast.
+///
+/// Mirrored messages (`{ch}` in a template is the MIDI channel, 1-16):
+///   Note On (velocity > 0) — `note_address` <note> <velocity>
+///   Control Change         — `cc_address` <controller> <value>
+///   MMC (with `mmc = true`) — `mmc_address` <command> [<hr> <mn> <sc> <fr>]
+/// Arguments are OSC int32s, apart from the MMC command name ("play",
+/// "locate", ...); Locate adds its target timecode. Everything else is not
+/// mirrored.
+
+use std::net::SocketAddr;
+
+use midi_protocol::framing::MidiFramer;
+use midi_protocol::mmc::{self, MmcCommand};
+use rosc::{OscMessage, OscPacket, OscType};
+use serde::Deserialize;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OscMirrorSection {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where to send the OSC messages ("host:port")
+    #[serde(default = "default_target")]
+    pub target: String,
+    /// Address template for Note Ons
+    #[serde(default = "default_note_address")]
+    pub note_address: String,
+    /// Address template for Control Changes
+    #[serde(default = "default_cc_address")]
+    pub cc_address: String,
+    /// Also mirror MMC transport commands
+    #[serde(default)]
+    pub mmc: bool,
+    /// Address for MMC commands
+    #[serde(default = "default_mmc_address")]
+    pub mmc_address: String,
+}
+
+impl Default for OscMirrorSection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: default_target(),
+            note_address: default_note_address(),
+            cc_address: default_cc_address(),
+            mmc: false,
+            mmc_address: default_mmc_address(),
+        }
+    }
+}
+
+fn default_target() -> String {
+    "127.0.0.1:9000".to_string()
+}
+
+fn default_note_address() -> String {
+    "/midi/note/{ch}".to_string()
+}
+
+fn default_cc_address() -> String {
+    "/midi/cc/{ch}".to_string()
+}
+
+fn default_mmc_address() -> String {
+    "/midi/mmc".to_string()
+}
+
+fn mmc_to_osc(config: &OscMirrorSection, msg: &[u8]) -> Option<OscMessage> {
+    let mmc = mmc::parse(msg)?;
+    let mut args = vec![OscType::String(mmc.command.name().to_string())];
+    if let MmcCommand::Locate(tc) = mmc.command {
+        args.extend([tc.hours, tc.minutes, tc.seconds, tc.frames].map(|v| OscType::Int(v as i32)));
+    }
+    Some(OscMessage {
+        addr: config.mmc_address.clone(),
+        args,
+    })
+}
+
+/// The OSC message mirroring one complete MIDI message, if it is mirrored.
+pub fn to_osc(config: &OscMirrorSection, msg: &[u8]) -> Option<OscMessage> {
+    if msg.first() == Some(&0xF0) {
+        return if config.mmc { mmc_to_osc(config, msg) } else { None };
+    }
+    if msg.len() < 3 {
+        return None;
+    }
+    let template = match msg[0] & 0xF0 {
+        0x90 if msg[2] > 0 => &config.note_address,
+        0xB0 => &config.cc_address,
+        _ => return None,
+    };
+    let channel = (msg[0] & 0x0F) + 1;
+    Some(OscMessage {
+        addr: template.replace("{ch}", &channel.to_string()),
+        args: vec![OscType::Int(msg[1] as i32), OscType::Int(msg[2] as i32)],
+    })
+}
+
+/// Mirror task: frames the chunks from `rx` and sends the OSC messages.
+pub async fn run(config: OscMirrorSection, mut rx: mpsc::Receiver<Vec<u8>>) -> anyhow::Result<()> {
+    let target: SocketAddr = tokio::net::lookup_host(&config.target)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("osc_mirror.target '{}' did not resolve", config.target))?;
+    let bind: SocketAddr = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse()?;
+    let socket = UdpSocket::bind(bind).await?;
+    info!(target = %target, "OSC mirror started");
+
+    let mut framer = MidiFramer::new();
+    while let Some(chunk) = rx.recv().await {
+        for msg in framer.push(&chunk) {
+            let Some(osc) = to_osc(&config, &msg) else {
+                continue;
+            };
+            let bytes = match rosc::encoder::encode(&OscPacket::Message(osc)) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!("Failed to encode OSC mirror message: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = socket.send_to(&bytes, target).await {
+                debug!("OSC mirror send failed: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_on_encodes_to_the_expected_osc_bytes() {
+        let config = OscMirrorSection::default();
+        let osc = to_osc(&config, &[0x92, 60, 100]).unwrap();
+        let bytes = rosc::encoder::encode(&OscPacket::Message(osc)).unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(b"/midi/note/3\0\0\0\0"); // padded to 4 bytes
+        expected.extend_from_slice(b",ii\0");
+        expected.extend_from_slice(&60i32.to_be_bytes());
+        expected.extend_from_slice(&100i32.to_be_bytes());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn only_note_ons_and_ccs_are_mirrored() {
+        let config = OscMirrorSection {
+            cc_address: "/show/fader{ch}".to_string(),
+            ..OscMirrorSection::default()
+        };
+        let cc = to_osc(&config, &[0xBF, 7, 127]).unwrap();
+        assert_eq!(cc.addr, "/show/fader16");
+        assert_eq!(cc.args, vec![OscType::Int(7), OscType::Int(127)]);
+
+        assert!(to_osc(&config, &[0x90, 60, 0]).is_none(), "velocity 0 is a Note Off");
+        assert!(to_osc(&config, &[0x80, 60, 0]).is_none());
+        assert!(to_osc(&config, &[0xE0, 0, 64]).is_none());
+        assert!(to_osc(&config, &[0xF8]).is_none());
+        assert!(to_osc(&config, &[0xF0, 0x7F, 0x7F, 0x06, 0x02, 0xF7]).is_none(), "MMC is off by default");
+    }
+
+    #[test]
+    fn mmc_commands_are_mirrored_by_name() {
+        let config = OscMirrorSection {
+            mmc: true,
+            ..OscMirrorSection::default()
+        };
+        let play = to_osc(&config, &[0xF0, 0x7F, 0x7F, 0x06, 0x02, 0xF7]).unwrap();
+        assert_eq!(play.addr, "/midi/mmc");
+        assert_eq!(play.args, vec![OscType::String("play".to_string())]);
+
+        let locate = [0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x21, 0x02, 0x03, 0x04, 0x00, 0xF7];
+        let locate = to_osc(&config, &locate).unwrap();
+        assert_eq!(
+            locate.args,
+            vec![
+                OscType::String("locate".to_string()),
+                OscType::Int(1),
+                OscType::Int(2),
+                OscType::Int(3),
+                OscType::Int(4),
+            ]
+        );
+
+        // Other SysEx is still not mirrored
+        assert!(to_osc(&config, &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]).is_none());
+    }
+}
@@ -0,0 +1,302 @@
+/// Input recorder: captures what arrives through the InputMux to a
+/// format-0 Standard MIDI File ("capture last set").
+///
+/// Enabled with `[recording] enabled = true`; recordings are started and
+/// stopped with a `RecordPacket` on the control group (`POST /api/record`
+/// on the admin panel). The broadcaster hands each popped input chunk to
+/// the recorder task with its arrival time, so file I/O never sits on the
+/// send path. Chunks are framed into complete messages and written with
+/// delta times from the arrival timestamps, at 960 ticks per quarter note
+/// under a fixed 120 BPM tempo (one tick ≈ 0.52ms).
+///
+/// Stopping (or Ctrl+C while recording) writes the end-of-track event,
+/// patches the track length into the MTrk header and syncs the file.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use midi_protocol::framing::MidiFramer;
+use midi_protocol::ringbuf::SLOT_SIZE;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+const TICKS_PER_QUARTER: u16 = 960;
+const TEMPO_US: u32 = 500_000;
+
+/// Byte offset of the MTrk length field: MThd (14 bytes) + "MTrk"
+const TRACK_LEN_OFFSET: u64 = 18;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingSection {
+    /// Accept start/stop requests from the control group
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory for recorded `.mid` files (created if missing)
+    #[serde(default = "default_recording_dir")]
+    pub dir: PathBuf,
+}
+
+impl Default for RecordingSection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_recording_dir(),
+        }
+    }
+}
+
+fn default_recording_dir() -> PathBuf {
+    PathBuf::from("recordings")
+}
+
+#[derive(Debug)]
+pub enum RecorderCommand {
+    /// Raw input as popped from the mux (slot and length), with its
+    /// arrival time. A fixed slot, so the send path never allocates.
+    Midi([u8; SLOT_SIZE], usize, Instant),
+    Start,
+    Stop,
+    /// Close any open recording and end the task
+    Shutdown,
+}
+
+fn write_vlq(out: &mut impl Write, mut value: u32) -> io::Result<usize> {
+    let mut bytes = [0u8; 4];
+    let mut n = 0;
+    loop {
+        bytes[3 - n] = (value & 0x7F) as u8 | if n > 0 { 0x80 } else { 0 };
+        n += 1;
+        value >>= 7;
+        if value == 0 || n == 4 {
+            break;
+        }
+    }
+    out.write_all(&bytes[4 - n..])?;
+    Ok(n)
+}
+
+/// A format-0 SMF being written.
+pub struct SmfWriter {
+    out: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+    last_tick: u64,
+    /// Bytes written to the track chunk so far
+    track_len: u32,
+    framer: MidiFramer,
+}
+
+impl SmfWriter {
+    /// Create `path` and write the header and tempo. Delta times count
+    /// from `started`.
+    pub fn create(path: &Path, started: Instant) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"MThd")?;
+        out.write_all(&6u32.to_be_bytes())?;
+        out.write_all(&0u16.to_be_bytes())?; // format 0
+        out.write_all(&1u16.to_be_bytes())?; // one track
+        out.write_all(&TICKS_PER_QUARTER.to_be_bytes())?;
+        out.write_all(b"MTrk")?;
+        out.write_all(&0u32.to_be_bytes())?; // patched in finish()
+
+        let mut writer = Self {
+            out,
+            path: path.to_path_buf(),
+            started,
+            last_tick: 0,
+            track_len: 0,
+            framer: MidiFramer::new(),
+        };
+        let tempo = TEMPO_US.to_be_bytes();
+        writer.event(0, &[0xFF, 0x51, 0x03, tempo[1], tempo[2], tempo[3]])?;
+        Ok(writer)
+    }
+
+    fn tick_at(&self, at: Instant) -> u64 {
+        let us = at.saturating_duration_since(self.started).as_micros() as u64;
+        us * TICKS_PER_QUARTER as u64 / TEMPO_US as u64
+    }
+
+    fn event(&mut self, tick: u64, bytes: &[u8]) -> io::Result<()> {
+        self.framed_event(tick, &[], bytes)
+    }
+
+    /// Write an event whose bytes are `head` followed by `body`.
+    fn framed_event(&mut self, tick: u64, head: &[u8], body: &[u8]) -> io::Result<()> {
+        // Ticks are derived from absolute time, so rounding never drifts
+        let delta = tick.saturating_sub(self.last_tick).min(0x0FFF_FFFF) as u32;
+        self.last_tick = self.last_tick.max(tick);
+        self.track_len += write_vlq(&mut self.out, delta)? as u32;
+        self.out.write_all(head)?;
+        self.out.write_all(body)?;
+        self.track_len += (head.len() + body.len()) as u32;
+        Ok(())
+    }
+
+    fn message(&mut self, tick: u64, message: &[u8]) -> io::Result<()> {
+        // Event type byte plus the VLQ length
+        let mut head = [0u8; 5];
+        let (body, length) = match message[0] {
+            0x80..=0xEF => return self.event(tick, message),
+            // SysEx: F0 <length> <bytes after F0, F7 included>
+            0xF0 => {
+                head[0] = 0xF0;
+                (&message[1..], message.len() as u32 - 1)
+            }
+            // System Common / Real-Time as escape events
+            _ => {
+                head[0] = 0xF7;
+                (message, message.len() as u32)
+            }
+        };
+        let n = write_vlq(&mut &mut head[1..], length)?;
+        self.framed_event(tick, &head[..1 + n], body)
+    }
+
+    /// Append input that arrived at `at`.
+    pub fn write(&mut self, midi: &[u8], at: Instant) -> io::Result<()> {
+        let tick = self.tick_at(at);
+        // Taken out so messages can be written while it frames them
+        let mut framer = std::mem::take(&mut self.framer);
+        let mut result = Ok(());
+        framer.push_each(midi, |message| {
+            if result.is_ok() {
+                result = self.message(tick, message);
+            }
+        });
+        self.framer = framer;
+        result
+    }
+
+    /// Write the end-of-track at `at`, patch the track length and sync.
+    pub fn finish(mut self, at: Instant) -> io::Result<PathBuf> {
+        let tick = self.tick_at(at);
+        self.event(tick, &[0xFF, 0x2F, 0x00])?;
+        self.out.seek(SeekFrom::Start(TRACK_LEN_OFFSET))?;
+        self.out.write_all(&self.track_len.to_be_bytes())?;
+        self.out.flush()?;
+        self.out.get_ref().sync_all()?;
+        Ok(self.path)
+    }
+}
+
+fn new_recording_path(dir: &Path, host_id: u8) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    dir.join(format!("host{}-{}.mid", host_id, secs))
+}
+
+fn finish(writer: SmfWriter) {
+    match writer.finish(Instant::now()) {
+        Ok(path) => info!(path = ?path, "Recording saved"),
+        Err(e) => error!("Failed to close recording: {}", e),
+    }
+}
+
+/// Recorder task: owns the open file and serves `RecorderCommand`s.
+pub async fn run(config: RecordingSection, host_id: u8, mut rx: mpsc::Receiver<RecorderCommand>) {
+    info!(dir = ?config.dir, "Input recorder ready (start with POST /api/record)");
+    let mut writer: Option<SmfWriter> = None;
+
+    while let Some(command) = rx.recv().await {
+        match command {
+            RecorderCommand::Midi(midi, len, at) => {
+                if let Some(w) = writer.as_mut() {
+                    if let Err(e) = w.write(&midi[..len], at) {
+                        error!("Recording write failed, stopping: {}", e);
+                        writer = None;
+                    }
+                }
+            }
+            RecorderCommand::Start => {
+                if let Some(w) = writer.take() {
+                    finish(w);
+                }
+                let path = new_recording_path(&config.dir, host_id);
+                let created = std::fs::create_dir_all(&config.dir)
+                    .and_then(|_| SmfWriter::create(&path, Instant::now()));
+                match created {
+                    Ok(w) => {
+                        info!(path = ?path, "Recording started");
+                        writer = Some(w);
+                    }
+                    Err(e) => error!(path = ?path, "Failed to start recording: {}", e),
+                }
+            }
+            RecorderCommand::Stop => match writer.take() {
+                Some(w) => finish(w),
+                None => warn!("Stop requested but nothing is being recorded"),
+            },
+            RecorderCommand::Shutdown => break,
+        }
+    }
+
+    if let Some(w) = writer.take() {
+        finish(w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn recorded_file_reparses_in_order_and_on_time() {
+        let dir = std::env::temp_dir().join(format!("midinet-recorder-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("take.mid");
+
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut writer = SmfWriter::create(&path, start).unwrap();
+        writer.write(&[0x90, 60, 100], at(0)).unwrap();
+        // Two messages in one chunk, then a note split across chunks
+        writer.write(&[0xB0, 7, 90, 0x80, 60, 0], at(250)).unwrap();
+        writer.write(&[0x90, 64], at(500)).unwrap();
+        writer.write(&[80], at(500)).unwrap();
+        writer.write(&[0xF0, 0x7E, 0x7F, 0x09, 0xF7], at(750)).unwrap();
+        writer.write(&[0x80, 64, 0], at(1_000)).unwrap();
+        let saved = writer.finish(at(1_200)).unwrap();
+
+        let file = crate::midi_file::parse(&std::fs::read(&saved).unwrap()).unwrap();
+        let expected: [(u64, &[u8]); 6] = [
+            (0, &[0x90, 60, 100]),
+            (250, &[0xB0, 7, 90]),
+            (250, &[0x80, 60, 0]),
+            (500, &[0x90, 64, 80]),
+            (750, &[0xF0, 0x7E, 0x7F, 0x09, 0xF7]),
+            (1_000, &[0x80, 64, 0]),
+        ];
+        assert_eq!(file.events.len(), expected.len());
+        for (event, (ms, data)) in file.events.iter().zip(expected) {
+            assert_eq!(event.data, data);
+            let expected_us = ms * 1_000;
+            assert!(event.at_us.abs_diff(expected_us) < 1_000, "{:?} at {}us, expected {}us", data, event.at_us, expected_us);
+        }
+        assert!(file.length_us.abs_diff(1_200_000) < 1_000);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn vlq_encoding_matches_the_smf_spec() {
+        for (value, bytes) in [
+            (0u32, vec![0x00]),
+            (0x7F, vec![0x7F]),
+            (0x80, vec![0x81, 0x00]),
+            (0x3FFF, vec![0xFF, 0x7F]),
+            (0x0FFF_FFFF, vec![0xFF, 0xFF, 0xFF, 0x7F]),
+        ] {
+            let mut out = Vec::new();
+            assert_eq!(write_vlq(&mut out, value).unwrap(), bytes.len());
+            assert_eq!(out, bytes, "{value:#x}");
+        }
+    }
+}
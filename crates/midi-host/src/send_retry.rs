@@ -0,0 +1,152 @@
+/// Bounded retry for transient data-packet send failures.
+///
+/// `send_to` on a busy interface can fail with ENOBUFS (or EAGAIN) while the
+/// kernel's socket buffer is momentarily full. Those errors are retried up
+/// to `network.send_retries` times with a short doubling backoff; any other
+/// error, or running out of attempts, drops the packet. Retries and drops
+/// are counted in `HostMetrics` so lost MIDI is visible rather than silent.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+
+use crate::SharedState;
+
+/// Wait before the first retry; doubled for each further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_micros(200);
+
+#[cfg(target_os = "linux")]
+const ENOBUFS: i32 = 105;
+#[cfg(target_os = "windows")]
+const ENOBUFS: i32 = 10055; // WSAENOBUFS
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+const ENOBUFS: i32 = 55;
+
+/// Anything that can send a datagram (a `UdpSocket`, or a mock in tests).
+pub trait DatagramSink {
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> impl Future<Output = io::Result<usize>> + Send;
+}
+
+impl DatagramSink for UdpSocket {
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> impl Future<Output = io::Result<usize>> + Send {
+        UdpSocket::send_to(self, buf, target)
+    }
+}
+
+/// Errors that mean "try again shortly" rather than "this will never work".
+pub fn is_retryable(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted)
+        || e.raw_os_error() == Some(ENOBUFS)
+}
+
+#[derive(Debug)]
+pub struct SendOutcome {
+    /// Attempts made after the first one
+    pub retries: u32,
+    pub result: io::Result<usize>,
+}
+
+/// Send `buf`, retrying retryable errors up to `max_retries` times.
+pub async fn send_with_retry<S: DatagramSink>(
+    sink: &S,
+    buf: &[u8],
+    target: SocketAddr,
+    max_retries: u32,
+) -> SendOutcome {
+    let mut retries = 0;
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        match sink.send_to(buf, target).await {
+            Err(e) if retries < max_retries && is_retryable(&e) => {
+                retries += 1;
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            result => return SendOutcome { retries, result },
+        }
+    }
+}
+
+/// Add a send's retries (and its drop, if it failed) to the host metrics.
+pub async fn record(state: &SharedState, outcome: &SendOutcome) {
+    if outcome.retries == 0 && outcome.result.is_ok() {
+        return;
+    }
+    let mut metrics = state.metrics.write().await;
+    metrics.send_retries += outcome.retries as u64;
+    if outcome.result.is_err() {
+        metrics.send_drops += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use crate::test_support::test_state;
+
+    /// Fails with the queued errors first, then succeeds.
+    #[derive(Default)]
+    struct FlakySink {
+        failures: Mutex<Vec<io::Error>>,
+        sent: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl DatagramSink for FlakySink {
+        async fn send_to(&self, buf: &[u8], _target: SocketAddr) -> io::Result<usize> {
+            if let Some(e) = self.failures.lock().unwrap().pop() {
+                return Err(e);
+            }
+            self.sent.lock().unwrap().push(buf.to_vec());
+            Ok(buf.len())
+        }
+    }
+
+    fn target() -> SocketAddr {
+        "127.0.0.1:5004".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn buffer_full_is_retried_until_sent() {
+        let state = test_state("retry-test");
+        let sink = FlakySink::default();
+        sink.failures.lock().unwrap().push(io::Error::from_raw_os_error(ENOBUFS));
+
+        let outcome = send_with_retry(&sink, &[0x90, 60, 100], target(), 3).await;
+        record(&state, &outcome).await;
+
+        assert_eq!(outcome.result.unwrap(), 3);
+        assert_eq!(outcome.retries, 1);
+        assert_eq!(*sink.sent.lock().unwrap(), vec![vec![0x90, 60, 100]]);
+        let metrics = state.metrics.read().await;
+        assert_eq!((metrics.send_retries, metrics.send_drops), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn exhausted_or_permanent_errors_are_dropped_and_counted() {
+        let state = test_state("retry-test");
+        let sink = FlakySink::default();
+        for _ in 0..5 {
+            sink.failures.lock().unwrap().push(io::ErrorKind::WouldBlock.into());
+        }
+        let outcome = send_with_retry(&sink, &[0xF8], target(), 2).await;
+        assert_eq!(outcome.retries, 2);
+        assert!(outcome.result.is_err());
+        record(&state, &outcome).await;
+
+        // Not a transient condition: no retry at all
+        let sink = FlakySink::default();
+        sink.failures.lock().unwrap().push(io::ErrorKind::PermissionDenied.into());
+        let outcome = send_with_retry(&sink, &[0xF8], target(), 2).await;
+        assert_eq!(outcome.retries, 0);
+        record(&state, &outcome).await;
+
+        let metrics = state.metrics.read().await;
+        assert_eq!((metrics.send_retries, metrics.send_drops), (2, 2));
+        assert!(sink.sent.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+/// Sender sharding for very high message rates.
+///
+/// With `network.send_shards > 1` the broadcaster hands packets to that many
+/// sender tasks, each with its own socket, so serialization and sends run in
+/// parallel. Outgoing MIDI is split by channel and a channel always maps to
+/// the same shard; shards are FIFO, so messages on one channel never overtake
+/// each other. Messages on *different* channels (and packet sequence numbers)
+/// may arrive reordered, which is why a single socket stays the default.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use midi_protocol::packets::MidiDataPacket;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
+
+use crate::broadcaster::{create_multicast_socket, midi_message_length};
+use crate::send_retry::{self, send_with_retry};
+use crate::SharedState;
+
+/// Packets queued per shard before the broadcaster waits
+const SHARD_QUEUE: usize = 1024;
+
+/// Shard for one MIDI message. Channel messages go by channel; system
+/// messages (SysEx, clock, ...) all go to shard 0.
+pub fn shard_for(msg: &[u8], shards: usize) -> usize {
+    match msg.first() {
+        Some(&status) if (0x80..0xF0).contains(&status) => (status & 0x0F) as usize % shards,
+        _ => 0,
+    }
+}
+
+/// Split a MIDI byte stream into per-shard chunks, keeping message order
+/// within each chunk. Chunks are returned in order of first appearance.
+pub fn split_by_shard(midi: &[u8], shards: usize) -> Vec<(usize, Vec<u8>)> {
+    let shards = shards.max(1);
+    let mut out: Vec<(usize, Vec<u8>)> = Vec::new();
+
+    let mut offset = 0;
+    while offset < midi.len() {
+        let (len, _) = midi_message_length(&midi[offset..]);
+        if len == 0 {
+            offset += 1;
+            continue;
+        }
+        let msg = &midi[offset..offset + len];
+        let shard = shard_for(msg, shards);
+        match out.iter_mut().find(|(s, _)| *s == shard) {
+            Some((_, bytes)) => bytes.extend_from_slice(msg),
+            None => out.push((shard, msg.to_vec())),
+        }
+        offset += len;
+    }
+    out
+}
+
+/// Queue `midi` on the shards, one packet per shard touched, each with its
+/// own sequence number. The journal rides on the first packet.
+pub async fn dispatch(
+    shards: &[mpsc::Sender<MidiDataPacket>],
+    midi: &[u8],
+    sequence: &mut u16,
+    timestamp_us: u64,
+    host_id: u8,
+    mut journal: Option<Vec<u8>>,
+) {
+    let mut chunks = split_by_shard(midi, shards.len());
+    if chunks.is_empty() && journal.is_some() {
+        // Journal-only packet (e.g. a promoted shadow going live)
+        chunks.push((0, Vec::new()));
+    }
+    for (shard, midi_data) in chunks {
+        let packet = MidiDataPacket {
+            sequence: *sequence,
+            timestamp_us,
+            host_id,
+            midi_data,
+            journal: journal.take(),
+        };
+        *sequence = sequence.wrapping_add(1);
+        if shards[shard].send(packet).await.is_err() {
+            error!(shard, "Send shard stopped, dropping MIDI packet");
+        }
+    }
+}
+
+/// Spawn `count` sender tasks. Returns one queue per shard.
+pub fn spawn(
+    state: &Arc<SharedState>,
+    count: usize,
+    dest: SocketAddr,
+) -> anyhow::Result<Vec<mpsc::Sender<MidiDataPacket>>> {
+    let mut senders = Vec::with_capacity(count);
+    for index in 0..count {
+        let socket = UdpSocket::from_std(create_multicast_socket(
+            dest.ip(),
+            0,
+            state.data_interface.multicast(),
+            state.config.network.data_ttl,
+        )?)?;
+        let unicast_socket = if state.config.unicast.enabled {
+            let std_sock = std::net::UdpSocket::bind("0.0.0.0:0")?;
+            std_sock.set_nonblocking(true)?;
+            Some(UdpSocket::from_std(std_sock)?)
+        } else {
+            None
+        };
+
+        let (tx, rx) = mpsc::channel(SHARD_QUEUE);
+        tokio::spawn(run_shard(index, Arc::clone(state), socket, unicast_socket, dest, rx));
+        senders.push(tx);
+    }
+    info!(shards = count, "Broadcaster send sharding enabled (per-channel order preserved)");
+    Ok(senders)
+}
+
+async fn run_shard(
+    index: usize,
+    state: Arc<SharedState>,
+    socket: UdpSocket,
+    unicast_socket: Option<UdpSocket>,
+    dest: SocketAddr,
+    mut rx: mpsc::Receiver<MidiDataPacket>,
+) {
+    let mut send_buf = Vec::with_capacity(512);
+    while let Some(packet) = rx.recv().await {
+        packet.serialize(&mut send_buf);
+        if let Some(ref cipher) = state.data_cipher {
+            cipher.seal(&mut send_buf);
+        }
+
+        let outcome = send_with_retry(&socket, &send_buf, dest, state.config.network.send_retries).await;
+        match &outcome.result {
+            Ok(_) => debug!(shard = index, seq = packet.sequence, len = send_buf.len(), "Sent MIDI packet"),
+            Err(e) => error!(shard = index, retries = outcome.retries, "Failed to send MIDI packet: {}", e),
+        }
+        send_retry::record(&state, &outcome).await;
+
+        if let Some(ref uc_socket) = unicast_socket {
+            let targets = state.unicast_targets.borrow().clone();
+            for target in &targets {
+                let _ = uc_socket.send_to(&send_buf, target).await;
+            }
+        }
+    }
+    debug!(shard = index, "Send shard stopped");
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn split_groups_messages_by_channel_shard() {
+        // ch1 note, ch2 CC, clock, ch5 note (ch1 and ch5 share a shard with 4 shards)
+        let midi = [0x90, 60, 100, 0xB1, 7, 90, 0xF8, 0x94, 62, 80];
+        assert_eq!(
+            split_by_shard(&midi, 4),
+            vec![(0, vec![0x90, 60, 100, 0xF8, 0x94, 62, 80]), (1, vec![0xB1, 7, 90])]
+        );
+        assert_eq!(split_by_shard(&midi, 1), vec![(0, midi.to_vec())]);
+    }
+
+    #[tokio::test]
+    async fn sharded_sends_preserve_per_channel_order() {
+        const SHARDS: usize = 4;
+        let wire: Arc<Mutex<Vec<Vec<u8>>>> = Arc::default();
+
+        // Shards run concurrently at different speeds, so packets from
+        // different shards interleave arbitrarily on the "wire"
+        let mut senders = Vec::new();
+        let mut tasks = Vec::new();
+        for index in 0..SHARDS {
+            let (tx, mut rx) = mpsc::channel::<MidiDataPacket>(SHARD_QUEUE);
+            let wire = Arc::clone(&wire);
+            tasks.push(tokio::spawn(async move {
+                while let Some(packet) = rx.recv().await {
+                    tokio::time::sleep(Duration::from_micros(50 * (SHARDS - index) as u64)).await;
+                    wire.lock().await.push(packet.midi_data);
+                }
+            }));
+            senders.push(tx);
+        }
+
+        // Every packet carries one CC per channel; the value counts up per channel
+        let mut sequence = 0;
+        for value in 0..50u8 {
+            let midi: Vec<u8> = (0..16u8).flat_map(|ch| [0xB0 | ch, 1, value]).collect();
+            dispatch(&senders, &midi, &mut sequence, 0, 1, None).await;
+        }
+        assert_eq!(sequence, 50 * SHARDS as u16);
+        drop(senders);
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let mut per_channel: Vec<Vec<u8>> = vec![Vec::new(); 16];
+        for midi in wire.lock().await.iter() {
+            for msg in midi.chunks(3) {
+                per_channel[(msg[0] & 0x0F) as usize].push(msg[2]);
+            }
+        }
+        let expected: Vec<u8> = (0..50).collect();
+        for (ch, values) in per_channel.iter().enumerate() {
+            assert_eq!(values, &expected, "channel {} out of order", ch + 1);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+/// Shadow host mode for zero-downtime host upgrades.
+///
+/// A shadow host joins another host's data stream and mirrors it into its
+/// own `MidiState` — journals replace the state, MIDI between journals is
+/// applied on top — while sending no data or heartbeats of its own. When
+/// the admin panel marks it for promotion (`POST /api/shadow/:host_id/promote`)
+/// it stops mirroring and the broadcaster goes live, leading with a journal
+/// of the mirrored state so clients continue from where the source left off.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::net::UdpSocket;
+use tracing::{debug, error, info};
+
+use midi_protocol::crypto::decode_data_packet;
+use midi_protocol::journal::decode_journal;
+use midi_protocol::midi_state::MidiState;
+use midi_protocol::multicast;
+use midi_protocol::packets::MidiDataPacket;
+
+use crate::SharedState;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShadowSection {
+    /// Start as a shadow of `source_host_id` instead of broadcasting
+    #[serde(default)]
+    pub enabled: bool,
+    /// Host whose stream is mirrored
+    #[serde(default = "default_source_host_id")]
+    pub source_host_id: u8,
+    /// Multicast group the source broadcasts on (empty = network.multicast_group)
+    #[serde(default)]
+    pub source_group: String,
+    /// Admin panel polled for the promotion request
+    #[serde(default = "crate::default_unicast_admin_url")]
+    pub admin_url: String,
+}
+
+impl Default for ShadowSection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source_host_id: default_source_host_id(),
+            source_group: String::new(),
+            admin_url: crate::default_unicast_admin_url(),
+        }
+    }
+}
+
+fn default_source_host_id() -> u8 { 1 }
+
+/// Apply one packet from the mirrored stream to `state`.
+/// Returns false if the packet isn't from `source_host_id`.
+pub fn mirror_packet(state: &mut MidiState, source_host_id: u8, packet: &MidiDataPacket) -> bool {
+    if packet.host_id != source_host_id {
+        return false;
+    }
+    // The source snapshots its journal after applying the packet's MIDI
+    match packet.journal.as_deref().and_then(decode_journal) {
+        Some(snapshot) => *state = snapshot,
+        None => {
+            state.process_message(&packet.midi_data);
+        }
+    }
+    true
+}
+
+/// Leave shadow mode. Returns false if the host was not shadowing.
+pub fn promote(state: &SharedState) -> bool {
+    let was_shadowing = state.shadowing.send_replace(false);
+    if was_shadowing {
+        info!(
+            source = state.config.shadow.source_host_id,
+            "Shadow host promoted, broadcasting from mirrored state"
+        );
+    }
+    was_shadowing
+}
+
+/// Mirror the source host's data stream into `state.midi_state` until promoted.
+pub async fn run_mirror(state: Arc<SharedState>) -> anyhow::Result<()> {
+    let group = if state.config.shadow.source_group.is_empty() {
+        multicast::parse_group(&state.config.network.multicast_group)?
+    } else {
+        multicast::parse_group(&state.config.shadow.source_group)?
+    };
+    let port = state.config.network.data_port;
+    let source = state.config.shadow.source_host_id;
+
+    let socket = {
+        let sock = multicast::new_socket(group)?;
+        sock.set_reuse_address(true)?;
+        #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+        sock.set_reuse_port(true)?;
+        sock.bind(&multicast::bind_addr(group, port).into())?;
+        multicast::join(&sock, group, state.data_interface.multicast())?;
+        sock.set_nonblocking(true)?;
+        UdpSocket::from_std(sock.into())?
+    };
+
+    info!(group = %group, port, source, "Shadowing host stream");
+
+    let mut shadowing = state.shadowing.subscribe();
+    let mut buf = [0u8; 2048];
+    while *shadowing.borrow_and_update() {
+        tokio::select! {
+            result = socket.recv_from(&mut buf) => match result {
+                Ok((len, _)) => {
+                    if let Ok(packet) = decode_data_packet(state.data_cipher.as_ref(), &buf[..len]) {
+                        let mut midi_state = state.midi_state.write().await;
+                        if mirror_packet(&mut midi_state, source, &packet) {
+                            debug!(seq = packet.sequence, journal = packet.journal.is_some(), "Mirrored packet");
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Shadow receive error: {}", e);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            },
+            changed = shadowing.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    info!("Stopped mirroring host stream");
+    Ok(())
+}
+
+/// Poll the admin panel until it asks this host to be promoted.
+pub async fn poll_promotion(state: Arc<SharedState>) {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .unwrap_or_default();
+
+    let url = format!("{}/api/shadow/{}", state.config.shadow.admin_url, state.config.host.id);
+    let mut interval = tokio::time::interval(Duration::from_millis(500));
+
+    while *state.shadowing.borrow() {
+        interval.tick().await;
+
+        let body: serde_json::Value = match http.get(&url).send().await {
+            Ok(resp) => match resp.json().await {
+                Ok(v) => v,
+                Err(e) => {
+                    debug!(error = %e, "Failed to parse shadow status from admin API");
+                    continue;
+                }
+            },
+            Err(e) => {
+                debug!(error = %e, "Failed to fetch shadow status from admin API");
+                continue;
+            }
+        };
+
+        if body["promote"].as_bool() == Some(true) {
+            promote(&state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_state;
+    use midi_protocol::journal::encode_journal;
+
+    fn packet(host_id: u8, sequence: u16, midi_data: Vec<u8>, journal: Option<Vec<u8>>) -> MidiDataPacket {
+        MidiDataPacket { sequence, timestamp_us: 0, host_id, midi_data, journal }
+    }
+
+    #[tokio::test]
+    async fn shadow_tracks_source_and_goes_live_from_mirrored_state() {
+        let state = test_state("shadow-test");
+        state.shadowing.send_replace(true);
+
+        // The source host's own view of its state as it broadcasts
+        let mut source = MidiState::new();
+        let mut stream = Vec::new();
+        for (seq, msg) in [vec![0x90, 60, 100], vec![0xB0, 7, 90], vec![0x91, 64, 80]].into_iter().enumerate() {
+            source.process_message(&msg);
+            let journal = (seq == 1).then(|| encode_journal(&source));
+            stream.push(packet(1, seq as u16, msg, journal));
+        }
+        source.process_message(&[0x80, 60, 0]);
+        stream.push(packet(1, 3, vec![0x80, 60, 0], None));
+
+        {
+            let mut mirrored = state.midi_state.write().await;
+            for p in &stream {
+                assert!(mirror_packet(&mut mirrored, 1, p));
+            }
+            // Another host on the same group is ignored
+            assert!(!mirror_packet(&mut mirrored, 1, &packet(3, 0, vec![0x92, 10, 10], None)));
+        }
+        assert_eq!(
+            encode_journal(&*state.midi_state.read().await),
+            encode_journal(&source)
+        );
+
+        // Promotion ends shadowing once; the state the broadcaster journals is the source's
+        assert!(promote(&state));
+        assert!(!promote(&state));
+        assert!(!*state.shadowing.borrow());
+        let live = state.midi_state.read().await;
+        assert_eq!(live.channels[1].notes[64], 80);
+        assert_eq!(live.channels[0].notes[60], 0);
+        assert_eq!(live.channels[0].cc[7], 90);
+    }
+}
@@ -0,0 +1,166 @@
+/// Planned-stop sequence for the host.
+///
+/// On Ctrl+C the input readers, broadcaster and heartbeat are stopped first
+/// so nothing new enters the stream. The host then sends one last data
+/// packet of All Sound Off + All Notes Off on every channel (continuing the
+/// broadcaster's sequence, with a journal of the silenced state) followed by
+/// a `HostStoppingPacket` on the heartbeat port. Clients silence held notes
+/// and switch hosts straight away instead of waiting out the heartbeat
+/// timeout. The remaining tasks are aborted after that.
+
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+
+use tokio::net::UdpSocket;
+use tracing::{error, info};
+
+use midi_protocol::multicast;
+use midi_protocol::packets::{HostStoppingPacket, MidiDataPacket};
+
+use crate::broadcaster::{apply_panic, create_multicast_socket};
+use crate::send_retry::{send_with_retry, DatagramSink};
+use crate::SharedState;
+
+/// Send the silence packet and the stopping notice to `data_dest` /
+/// `heartbeat_dest` and every unicast target. A shadow host was never live
+/// and sends nothing.
+pub async fn announce_stop<S: DatagramSink>(
+    state: &SharedState,
+    sink: &S,
+    data_dest: SocketAddr,
+    heartbeat_dest: SocketAddr,
+) {
+    if *state.shadowing.borrow() {
+        return;
+    }
+
+    let silence = apply_panic(state, None).await;
+    let journal = state.encode_journal(&*state.midi_state.read().await);
+    let mut data_buf = Vec::with_capacity(512);
+    MidiDataPacket {
+        sequence: state.data_sequence.load(Ordering::Relaxed),
+        timestamp_us: state.packet_clock.now_us(),
+        host_id: state.config.host.id,
+        midi_data: silence,
+        journal: Some(journal),
+    }
+    .serialize(&mut data_buf);
+    if let Some(ref cipher) = state.data_cipher {
+        cipher.seal(&mut data_buf);
+    }
+
+    let mut stopping_buf = [0u8; HostStoppingPacket::SIZE];
+    HostStoppingPacket {
+        host_id: state.config.host.id,
+        timestamp_us: state.packet_clock.now_us(),
+    }
+    .serialize(&mut stopping_buf);
+
+    // Targets are stored with data_port — swap to heartbeat port for the notice
+    let unicast = state.unicast_targets.borrow().clone();
+    let retries = state.config.network.send_retries;
+
+    for dest in std::iter::once(data_dest).chain(unicast.iter().map(|&t| t.into())) {
+        if let Err(e) = send_with_retry(sink, &data_buf, dest, retries).await.result {
+            error!(%dest, "Failed to send shutdown silence: {}", e);
+        }
+    }
+    let heartbeat_targets = unicast
+        .iter()
+        .map(|t| SocketAddr::new((*t.ip()).into(), heartbeat_dest.port()));
+    for dest in std::iter::once(heartbeat_dest).chain(heartbeat_targets) {
+        if let Err(e) = send_with_retry(sink, &stopping_buf, dest, retries).await.result {
+            error!(%dest, "Failed to send host stopping notice: {}", e);
+        }
+    }
+
+    info!(unicast_targets = unicast.len(), "Sent shutdown silence and host stopping notice");
+}
+
+/// Open a socket on the data interface and run `announce_stop`.
+pub async fn run(state: &SharedState) -> anyhow::Result<()> {
+    let multicast_addr = multicast::parse_group(&state.config.network.multicast_group)?;
+    let std_socket = create_multicast_socket(
+        multicast_addr,
+        0,
+        state.data_interface.multicast(),
+        state.config.network.data_ttl,
+    )?;
+    let socket = UdpSocket::from_std(std_socket)?;
+
+    announce_stop(
+        state,
+        &socket,
+        SocketAddr::new(multicast_addr, state.config.network.data_port),
+        SocketAddr::new(multicast_addr, state.config.network.heartbeat_port),
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::Mutex;
+
+    use midi_protocol::journal::decode_journal;
+    use midi_protocol::packets::HeartbeatPacket;
+
+    use crate::test_support::test_state;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        sent: Mutex<Vec<(SocketAddr, Vec<u8>)>>,
+    }
+
+    impl DatagramSink for RecordingSink {
+        async fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
+            self.sent.lock().unwrap().push((target, buf.to_vec()));
+            Ok(buf.len())
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_sends_silence_then_stopping_notice() {
+        let state = test_state("shutdown-test");
+        state.midi_state.write().await.process_message(&[0x90, 60, 100]);
+        state.data_sequence.store(41, Ordering::Relaxed);
+
+        let data: SocketAddr = "239.69.83.1:5004".parse().unwrap();
+        let heartbeat: SocketAddr = "239.69.83.1:5005".parse().unwrap();
+        let sink = RecordingSink::default();
+        announce_stop(&state, &sink, data, heartbeat).await;
+
+        let sent = sink.sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+
+        // Silence first, continuing the stream, with the held note cleared
+        let (dest, bytes) = &sent[0];
+        assert_eq!(*dest, data);
+        let packet = MidiDataPacket::deserialize(bytes).unwrap();
+        assert_eq!(packet.sequence, 41);
+        for ch in 0..16u8 {
+            assert!(packet.midi_data.windows(3).any(|m| m == [0xB0 | ch, 120, 0]));
+            assert!(packet.midi_data.windows(3).any(|m| m == [0xB0 | ch, 123, 0]));
+        }
+        let journaled = decode_journal(packet.journal.as_deref().unwrap()).unwrap();
+        assert_eq!(journaled.channels[0].notes[60], 0);
+
+        // Then the stopping notice on the heartbeat port
+        let (dest, bytes) = &sent[1];
+        assert_eq!(*dest, heartbeat);
+        assert_eq!(HostStoppingPacket::deserialize(bytes).unwrap().host_id, 1);
+        assert!(HeartbeatPacket::deserialize(bytes).is_none());
+    }
+
+    #[tokio::test]
+    async fn shadow_host_stops_silently() {
+        let state = test_state("shutdown-test");
+        state.shadowing.send_replace(true);
+        let sink = RecordingSink::default();
+        let dest: SocketAddr = "239.69.83.1:5004".parse().unwrap();
+        announce_stop(&state, &sink, dest, dest).await;
+        assert!(sink.sent.lock().unwrap().is_empty());
+    }
+}
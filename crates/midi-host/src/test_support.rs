@@ -0,0 +1,103 @@
+//! Shared fixtures for host unit tests.
+
+use std::sync::atomic::{AtomicU16, AtomicU64, AtomicU8};
+use std::sync::Arc;
+
+use midi_protocol::clock::{PacketClock, TimestampSource};
+use midi_protocol::identity::DeviceIdentity;
+use midi_protocol::interface;
+use midi_protocol::midi_state::MidiState;
+use midi_protocol::packets::HostRole;
+use tokio::sync::{mpsc, watch, RwLock};
+
+use crate::{metrics, pipeline, HostConfig, SharedState};
+
+/// Minimal config with every required section
+pub const TEST_CONFIG: &str = r#"
+    [host]
+    id = 1
+    name = "test-host"
+
+    [network]
+    multicast_group = "239.69.83.1"
+    data_port = 5004
+    heartbeat_port = 5005
+    control_group = "239.69.83.100"
+    control_port = 5006
+
+    [heartbeat]
+
+    [midi]
+    device = "hw:1,0,0"
+
+    [failover]
+"#;
+
+pub fn test_state(device_name: &str) -> Arc<SharedState> {
+    let config: HostConfig = toml::from_str(TEST_CONFIG).unwrap();
+    Arc::new(SharedState {
+        config,
+        identity: RwLock::new(DeviceIdentity {
+            name: device_name.to_string(),
+            ..DeviceIdentity::default()
+        }),
+        identity_generation: watch::channel(0).0,
+        role: watch::channel(HostRole::Primary).0,
+        metrics: RwLock::new(metrics::HostMetrics::default()),
+        pipeline_config: RwLock::new(pipeline::PipelineConfig::default()),
+        midi_state: RwLock::new(MidiState::new()),
+        input_active: Arc::new(AtomicU8::new(0)),
+        input_switch_count: Arc::new(AtomicU64::new(0)),
+        input_redundancy_enabled: false,
+        unicast_targets: watch::channel(Vec::new()).1,
+        unicast_subscribers: Arc::new(std::sync::Mutex::new(crate::unicast_relay::Subscribers::new(64))),
+        panic_tx: mpsc::channel(16).0,
+        data_interface: interface::ResolvedInterface {
+            addr: std::net::Ipv4Addr::UNSPECIFIED,
+            index: 0,
+            error: None,
+        },
+        control_interface: interface::ResolvedInterface {
+            addr: std::net::Ipv4Addr::UNSPECIFIED,
+            index: 0,
+            error: None,
+        },
+        packet_clock: PacketClock::new(TimestampSource::WallClock),
+        shadowing: watch::channel(false).0,
+        data_sequence: AtomicU16::new(0),
+        midi_clock: std::sync::Mutex::new(crate::midi_clock::ClockTracker::new()),
+        data_cipher: None,
+        recorder_tx: None,
+        osc_mirror_tx: None,
+        peer_protocol: AtomicU8::new(midi_protocol::PROTOCOL_VERSION),
+    })
+}
+
+/// Config for host `id` broadcasting on `group`; `failover` is appended to
+/// the `[failover]` section (e.g. `[[failover.peers]]` entries).
+pub fn host_config(id: u8, group: &str, priority: u8, failover: &str) -> HostConfig {
+    toml::from_str(&format!(
+        r#"
+        [host]
+        id = {id}
+        name = "host-{id}"
+        priority = {priority}
+
+        [network]
+        multicast_group = "{group}"
+        data_port = 5004
+        heartbeat_port = 5005
+        control_group = "239.69.83.100"
+        control_port = 5006
+
+        [heartbeat]
+
+        [midi]
+        device = "hw:1,0,0"
+
+        [failover]
+        {failover}
+        "#
+    ))
+    .unwrap()
+}
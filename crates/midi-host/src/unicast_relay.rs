@@ -4,18 +4,189 @@
 /// client IP addresses. The broadcaster tasks subscribe to the resulting
 /// `watch` channel and send MIDI data + heartbeats to each target via
 /// UDP unicast, bypassing multicast.
+///
+/// In `hybrid` mode only clients whose loss reports show they get next to
+/// nothing over multicast, for `MULTICAST_BLOCKED_HOLD` in a row, are
+/// unicast to; everyone else stays on multicast. A promoted client stays on
+/// unicast while it is registered, since its loss drops to zero as soon as
+/// the unicast copy reaches it.
+///
+/// Clients the host can't learn about from the admin panel (behind NAT)
+/// can register themselves instead: they send a `SubscribePacket` to the
+/// host's control port every few seconds from their data socket, and are
+/// unicast to at that source address and port — the one the NAT mapped —
+/// until they have missed `SUBSCRIBE_MISSED_INTERVALS` of them. Subscribed
+/// clients are targeted in every mode, merged with the admin list, up to
+/// `unicast.max_subscribers` of them; further subscribes are refused until
+/// one expires.
 
+use std::collections::{HashMap, HashSet};
 use std::net::{Ipv4Addr, SocketAddrV4};
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use serde::Deserialize;
+use serde_json::Value;
 use tokio::sync::watch;
 use tracing::{debug, info, warn};
 
-/// Poll the admin API for registered clients and publish their addresses
-/// as unicast targets for the broadcaster.
+/// Reported packet loss at which a client counts as multicast-blocked.
+/// Below 100% because a blocked client still counts the odd unicast-era or
+/// stray packet, and its loss window rarely reads exactly 100.
+const MULTICAST_BLOCKED_LOSS_PERCENT: f64 = 95.0;
+
+/// How long a client must stay above that loss before it is promoted, so
+/// one bad reporting window (a Wi-Fi dropout) doesn't switch it to unicast
+const MULTICAST_BLOCKED_HOLD: Duration = Duration::from_secs(5);
+
+/// Subscribe intervals a client may miss before it is dropped
+const SUBSCRIBE_MISSED_INTERVALS: u32 = 5;
+
+/// Shortest subscribe interval honoured, so a bogus 0 can't expire at once
+const MIN_SUBSCRIBE_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicastMode {
+    /// Unicast to every registered client (in addition to multicast)
+    #[default]
+    All,
+    /// Unicast only to clients that aren't receiving multicast
+    Hybrid,
+}
+
+/// Hybrid-mode bookkeeping carried between polls.
+#[derive(Debug, Default)]
+pub struct Promotions {
+    /// Clients on unicast
+    promoted: HashSet<Ipv4Addr>,
+    /// When each not-yet-promoted client first reported blocked-level loss
+    blocked_since: HashMap<Ipv4Addr, Instant>,
+}
+
+/// Pick unicast targets from the admin's `/api/clients` list, polled at `now`.
+pub fn select_targets(
+    clients: &[Value],
+    mode: UnicastMode,
+    data_port: u16,
+    promotions: &mut Promotions,
+    now: Instant,
+) -> Vec<SocketAddrV4> {
+    let registered: Vec<(Ipv4Addr, f64)> = clients
+        .iter()
+        .filter_map(|c| {
+            let ip: Ipv4Addr = c["ip"].as_str()?.parse().ok()?;
+            // Skip loopback and unspecified addresses
+            if ip.is_loopback() || ip.is_unspecified() {
+                return None;
+            }
+            Some((ip, c["packet_loss_percent"].as_f64().unwrap_or(0.0)))
+        })
+        .collect();
+
+    // Forget clients that went away
+    let Promotions { promoted, blocked_since } = promotions;
+    promoted.retain(|ip| registered.iter().any(|(r, _)| r == ip));
+    blocked_since.retain(|ip, _| registered.iter().any(|(r, _)| r == ip));
+
+    registered
+        .into_iter()
+        .filter(|&(ip, loss)| match mode {
+            UnicastMode::All => true,
+            UnicastMode::Hybrid => {
+                if promoted.contains(&ip) {
+                    return true;
+                }
+                if loss < MULTICAST_BLOCKED_LOSS_PERCENT {
+                    blocked_since.remove(&ip);
+                    return false;
+                }
+                let since = *blocked_since.entry(ip).or_insert(now);
+                if now.duration_since(since) < MULTICAST_BLOCKED_HOLD {
+                    return false;
+                }
+                blocked_since.remove(&ip);
+                promoted.insert(ip);
+                info!(client = %ip, loss, "Client gets no multicast, adding unicast delivery");
+                true
+            }
+        })
+        .map(|(ip, _)| SocketAddrV4::new(ip, data_port))
+        .collect()
+}
+
+/// Clients that asked for unicast with `SubscribePacket`s.
+#[derive(Debug)]
+pub struct Subscribers {
+    /// When each client's subscription runs out, by the address and port
+    /// its subscribes come from
+    expires: HashMap<SocketAddrV4, Instant>,
+    /// Most clients subscribed at once; every one costs a unicast copy of
+    /// the stream, and subscribes are unauthenticated
+    capacity: usize,
+    /// Set once a full table has been reported, until there is room again
+    full_logged: bool,
+}
+
+impl Subscribers {
+    pub fn new(capacity: usize) -> Self {
+        Self { expires: HashMap::new(), capacity, full_logged: false }
+    }
+
+    /// Record a subscribe from `addr`, which re-sends every `interval`.
+    /// Returns true for a client that wasn't subscribed. A new client is
+    /// refused (false) while `capacity` others are subscribed.
+    pub fn subscribe(&mut self, addr: SocketAddrV4, interval: Duration, now: Instant) -> bool {
+        if !self.expires.contains_key(&addr) && self.expires.len() >= self.capacity {
+            self.expires.retain(|_, expires| *expires > now);
+            if self.expires.len() >= self.capacity {
+                if !self.full_logged {
+                    warn!(client = %addr, max = self.capacity, "Unicast subscribe refused: subscriber limit reached");
+                    self.full_logged = true;
+                }
+                return false;
+            }
+        }
+        self.full_logged = false;
+        let ttl = interval.max(MIN_SUBSCRIBE_INTERVAL) * SUBSCRIBE_MISSED_INTERVALS;
+        self.expires.insert(addr, now + ttl).is_none_or(|expired| expired <= now)
+    }
+
+    /// Clients still subscribed at `now`, in address order. Expired ones
+    /// are forgotten.
+    pub fn active(&mut self, now: Instant) -> Vec<SocketAddrV4> {
+        self.expires.retain(|addr, expires| {
+            let live = *expires > now;
+            if !live {
+                info!(client = %addr, "Unicast subscription expired");
+            }
+            live
+        });
+        let mut addrs: Vec<SocketAddrV4> = self.expires.keys().copied().collect();
+        addrs.sort_unstable();
+        addrs
+    }
+}
+
+/// The admin-selected targets plus every subscribed address not already in them.
+fn merge_targets(admin: &[SocketAddrV4], subscribed: &[SocketAddrV4]) -> Vec<SocketAddrV4> {
+    let mut targets = admin.to_vec();
+    for addr in subscribed {
+        if !targets.contains(addr) {
+            targets.push(*addr);
+        }
+    }
+    targets
+}
+
+/// Poll the admin API for registered clients and publish their addresses,
+/// together with the subscribed clients, as unicast targets for the
+/// broadcaster.
 pub async fn run(
     admin_url: String,
+    mode: UnicastMode,
     data_port: u16,
+    subscribers: Arc<Mutex<Subscribers>>,
     targets_tx: watch::Sender<Vec<SocketAddrV4>>,
 ) {
     let http = reqwest::Client::builder()
@@ -26,43 +197,21 @@ pub async fn run(
     let url = format!("{}/api/clients", admin_url);
     let mut interval = tokio::time::interval(Duration::from_secs(2));
     let mut last_count: usize = 0;
+    let mut promotions = Promotions::default();
+    // Last good admin list, kept while the admin panel is unreachable
+    let mut admin_targets = Vec::new();
 
     loop {
         interval.tick().await;
 
-        let resp = match http.get(&url).send().await {
-            Ok(r) => r,
-            Err(e) => {
-                debug!(error = %e, "Failed to fetch client list from admin API");
-                continue;
+        match fetch_clients(&http, &url).await {
+            Some(clients) => {
+                admin_targets = select_targets(&clients, mode, data_port, &mut promotions, Instant::now())
             }
-        };
-
-        let body: serde_json::Value = match resp.json().await {
-            Ok(v) => v,
-            Err(e) => {
-                debug!(error = %e, "Failed to parse admin API response");
-                continue;
-            }
-        };
-
-        let clients = match body["clients"].as_array() {
-            Some(arr) => arr,
-            None => continue,
-        };
-
-        let addrs: Vec<SocketAddrV4> = clients
-            .iter()
-            .filter_map(|c| {
-                let ip_str = c["ip"].as_str()?;
-                let ip: Ipv4Addr = ip_str.parse().ok()?;
-                // Skip loopback and unspecified addresses
-                if ip.is_loopback() || ip.is_unspecified() {
-                    return None;
-                }
-                Some(SocketAddrV4::new(ip, data_port))
-            })
-            .collect();
+            None => debug!("Keeping the previous admin client list"),
+        }
+        let subscribed = subscribers.lock().unwrap().active(Instant::now());
+        let addrs = merge_targets(&admin_targets, &subscribed);
 
         if addrs.len() != last_count {
             if addrs.is_empty() {
@@ -83,3 +232,123 @@ pub async fn run(
         }
     }
 }
+
+/// The admin's `/api/clients` list, or None if it couldn't be fetched.
+async fn fetch_clients(http: &reqwest::Client, url: &str) -> Option<Vec<Value>> {
+    let resp = match http.get(url).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            debug!(error = %e, "Failed to fetch client list from admin API");
+            return None;
+        }
+    };
+
+    let mut body: serde_json::Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            debug!(error = %e, "Failed to parse admin API response");
+            return None;
+        }
+    };
+
+    match body["clients"].take() {
+        Value::Array(clients) => Some(clients),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn clients(loss_b: f64) -> Vec<Value> {
+        vec![
+            json!({ "ip": "192.168.1.10", "packet_loss_percent": 0.0 }),
+            json!({ "ip": "192.168.1.11", "packet_loss_percent": loss_b }),
+            json!({ "ip": "127.0.0.1", "packet_loss_percent": 100.0 }),
+        ]
+    }
+
+    #[test]
+    fn hybrid_promotes_client_blocked_for_the_hold_time() {
+        let mut promotions = Promotions::default();
+        let b = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 11), 5004);
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+        let mut poll = |loss: f64, s: u64| select_targets(&clients(loss), UnicastMode::Hybrid, 5004, &mut promotions, at(s));
+
+        // Partial loss is a network problem, not a blocked multicast
+        assert!(poll(40.0, 0).is_empty());
+        // One blocked-looking window that recovers: not promoted
+        assert!(poll(97.0, 2).is_empty());
+        assert!(poll(10.0, 4).is_empty());
+
+        // Blocked for the whole hold time: promoted, short of 100%
+        assert!(poll(97.0, 6).is_empty());
+        assert!(poll(100.0, 8).is_empty());
+        assert_eq!(poll(96.0, 11), vec![b]);
+        // Unicast now reaches it, so its loss recovers; it stays promoted
+        assert_eq!(poll(0.0, 13), vec![b]);
+
+        // Gone from the client list: promotion dropped
+        let only_a = &clients(0.0)[..1];
+        assert!(select_targets(only_a, UnicastMode::Hybrid, 5004, &mut promotions, at(15)).is_empty());
+        assert!(promotions.promoted.is_empty());
+    }
+
+    #[test]
+    fn subscription_expires_after_five_missed_intervals() {
+        let mut subscribers = Subscribers::new(8);
+        let ip = SocketAddrV4::new(Ipv4Addr::new(10, 8, 0, 2), 40123);
+        let interval = Duration::from_secs(1);
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+
+        assert!(subscribers.subscribe(ip, interval, at(0)));
+        assert!(!subscribers.subscribe(ip, interval, at(1)), "refresh is not a new subscription");
+        assert_eq!(subscribers.active(at(5)), vec![ip]);
+        // Last subscribe at t=1: gone once five intervals have passed
+        assert!(subscribers.active(at(6)).is_empty());
+        assert!(subscribers.subscribe(ip, interval, at(7)));
+    }
+
+    #[test]
+    fn subscribed_clients_are_merged_with_the_admin_list() {
+        let a = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 5004);
+        // Behind NAT: unicast to the port the subscribe came from, not the data port
+        let nat = SocketAddrV4::new(Ipv4Addr::new(10, 8, 0, 2), 40123);
+        let mut subscribers = Subscribers::new(8);
+        let now = Instant::now();
+        subscribers.subscribe(a, Duration::from_secs(1), now);
+        subscribers.subscribe(nat, Duration::from_secs(1), now);
+        let merged = merge_targets(&[a], &subscribers.active(now));
+        assert_eq!(merged, vec![a, nat]);
+    }
+
+    #[test]
+    fn subscribes_beyond_the_limit_are_refused_until_one_expires() {
+        let client = |port| SocketAddrV4::new(Ipv4Addr::new(10, 8, 0, 2), port);
+        let interval = Duration::from_secs(1);
+        let t0 = Instant::now();
+        let mut subscribers = Subscribers::new(2);
+
+        assert!(subscribers.subscribe(client(1), interval, t0));
+        assert!(subscribers.subscribe(client(2), interval, t0 + Duration::from_secs(3)));
+        assert!(!subscribers.subscribe(client(3), interval, t0 + Duration::from_secs(3)));
+        assert_eq!(subscribers.active(t0 + Duration::from_secs(3)), vec![client(1), client(2)]);
+
+        // Subscribed clients can still refresh while the table is full
+        assert!(!subscribers.subscribe(client(2), interval, t0 + Duration::from_secs(4)));
+
+        // client(1) expired at t=5: its slot goes to the waiting client
+        assert!(subscribers.subscribe(client(3), interval, t0 + Duration::from_secs(6)));
+        assert_eq!(subscribers.active(t0 + Duration::from_secs(6)), vec![client(2), client(3)]);
+    }
+
+    #[test]
+    fn all_mode_targets_every_client() {
+        let targets = select_targets(&clients(0.0), UnicastMode::All, 5004, &mut Promotions::default(), Instant::now());
+        assert_eq!(targets.len(), 2);
+    }
+}
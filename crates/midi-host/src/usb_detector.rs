@@ -1,6 +1,10 @@
 /// USB MIDI device detection and enumeration.
 /// Discovers connected MIDI controllers and reads their identity.
 
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
 use midi_protocol::identity::DeviceIdentity;
 #[cfg(target_os = "linux")]
 use tracing::{info, warn};
@@ -229,3 +233,28 @@ pub fn read_device_identity(device: &str) -> DeviceIdentity {
         ..DeviceIdentity::default()
     }
 }
+
+/// Watch the active input and refresh `SharedState.identity` whenever it
+/// switches to a controller with a different identity (e.g. a secondary
+/// of another model). `devices` is indexed by input (0 = primary).
+pub async fn run_identity_monitor(state: Arc<crate::SharedState>, devices: [String; 2]) {
+    let mut interval = tokio::time::interval(Duration::from_millis(250));
+    let mut last_active = state.input_active.load(Ordering::Relaxed);
+
+    loop {
+        interval.tick().await;
+
+        let active = state.input_active.load(Ordering::Relaxed);
+        if active == last_active {
+            continue;
+        }
+        last_active = active;
+
+        let device = &devices[(active as usize).min(1)];
+        let identity = read_device_identity(device);
+        let name = identity.name.clone();
+        if state.set_identity(identity).await {
+            tracing::info!(input = active, device = %name, "Device identity changed after input switch");
+        }
+    }
+}
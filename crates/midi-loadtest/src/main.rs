@@ -5,33 +5,42 @@
 ///
 /// Usage:
 ///   midi-loadtest latency              Measure send→receive latency over loopback multicast
-///   midi-loadtest throughput            Saturate the link and measure max sustained throughput
+///   midi-loadtest throughput            Saturate the link and measure max sustained throughput (--shards N)
 ///   midi-loadtest burst                 Send realistic MIDI burst patterns (drum rolls, chord stabs)
 ///   midi-loadtest heartbeat             Verify heartbeat timing accuracy at 3ms intervals
 ///   midi-loadtest failover              Simulate primary failure and measure failover time
+///   midi-loadtest failover-chain        Kill N hosts in turn, check priority order and no dual-active
 ///   midi-loadtest soak                  Long-duration soak test (packet loss, jitter, memory)
 ///   midi-loadtest pipeline              Benchmark pipeline processing throughput
 ///   midi-loadtest journal               Benchmark journal encode/decode + state reconciliation
+///   midi-loadtest journal-loss          Verify journal + reconciliation heals state after packet loss
+///   midi-loadtest hot-path              End-to-end host hot path: pipeline + state + journal + packet
+///   midi-loadtest jitter                Impaired link (delay, jitter, loss): jitter buffer + FEC recovery
 ///   midi-loadtest all                   Run all tests sequentially with a final report
 
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use clap::{Parser, Subcommand};
-use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 
+use midi_protocol::failover::{preferred_host, switch_target};
+use midi_protocol::fec::{FecDecoder, FecEncoder, Recovery};
+use midi_protocol::jitter_buffer::{Admit, JitterBuffer};
 use midi_protocol::journal::{decode_journal, encode_journal};
 use midi_protocol::midi_state::MidiState;
-use midi_protocol::packets::{HeartbeatPacket, HostRole, MidiDataPacket};
+use midi_protocol::multicast;
+use midi_protocol::packets::{FecParityPacket, HeartbeatPacket, HostRole, MidiDataPacket, MAGIC_FEC_PARITY};
 use midi_protocol::pipeline::PipelineConfig;
+use midi_protocol::sequence::{seq_distance, SeqEvent, SequenceTracker};
 
 // ── Test Configuration ───────────────────────────────────────
 
 /// Dedicated test multicast group (avoids interfering with live traffic)
-const TEST_MCAST_GROUP: Ipv4Addr = Ipv4Addr::new(239, 69, 83, 250);
+const TEST_MCAST_GROUP: IpAddr = IpAddr::V4(Ipv4Addr::new(239, 69, 83, 250));
 const TEST_DATA_PORT: u16 = 15004;
 const TEST_HB_PORT: u16 = 15005;
 
@@ -68,6 +77,9 @@ enum Command {
         /// Test duration in seconds
         #[arg(short, long, default_value = "10")]
         duration: u64,
+        /// Parallel sender sockets, one MIDI channel each (mirrors network.send_shards)
+        #[arg(short, long, default_value = "1")]
+        shards: usize,
     },
     /// Realistic MIDI burst patterns (drum rolls, chord stabs, CC sweeps)
     Burst,
@@ -79,6 +91,12 @@ enum Command {
     },
     /// Simulate primary failure and measure client-side failover time
     Failover,
+    /// Cascading failover across N hosts with distinct priorities
+    FailoverChain {
+        /// Number of hosts (2-16)
+        #[arg(short = 'n', long, default_value = "3")]
+        hosts: u8,
+    },
     /// Long-duration soak test (packet loss, jitter, memory stability)
     Soak {
         /// Soak duration in seconds
@@ -92,34 +110,72 @@ enum Command {
     Pipeline,
     /// Benchmark journal encode/decode and state reconciliation
     Journal,
+    /// Verify journal recovery restores state when updates are lost mid-stream
+    JournalLoss {
+        /// Percentage of incremental updates to drop
+        #[arg(short, long, default_value = "10")]
+        drop_percent: f64,
+        /// Number of independent trials
+        #[arg(short, long, default_value = "100")]
+        trials: u64,
+    },
+    /// Benchmark the host hot path end to end (pipeline, state, journal, packet round trip)
+    HotPath {
+        /// Number of messages to push through
+        #[arg(short, long, default_value = "1000000")]
+        count: u64,
+        /// Embed a journal every Nth packet
+        #[arg(short, long, default_value = "500")]
+        journal_every: u64,
+    },
+    /// Impair the send path (delay, jitter, loss) and measure jitter buffer and FEC recovery
+    Jitter {
+        /// Number of data packets to send
+        #[arg(short, long, default_value = "10000")]
+        count: u64,
+        /// Packets per second
+        #[arg(short, long, default_value = "1000")]
+        rate: u64,
+        /// Mean added one-way delay in microseconds
+        #[arg(long, default_value = "2000")]
+        delay_mean_us: u64,
+        /// Standard deviation of the added delay in microseconds
+        #[arg(long, default_value = "500")]
+        delay_stddev_us: u64,
+        /// Percentage of packets (data and parity) to drop
+        #[arg(short, long, default_value = "1")]
+        loss_percent: f64,
+        /// Client jitter buffer delay in microseconds (mirrors failover.jitter_buffer_us)
+        #[arg(short, long, default_value = "8000")]
+        buffer_us: u64,
+        /// FEC group size, 0 to disable (mirrors network.fec_group_size)
+        #[arg(short, long, default_value = "4")]
+        fec_group: u8,
+    },
     /// Run all tests sequentially
     All,
 }
 
 // ── Socket Helpers ───────────────────────────────────────────
 
-fn create_sender(interface: Ipv4Addr) -> anyhow::Result<std::net::UdpSocket> {
-    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+fn create_sender(group: IpAddr, interface: Ipv4Addr) -> anyhow::Result<std::net::UdpSocket> {
+    let socket = multicast::new_socket(group)?;
     socket.set_reuse_address(true)?;
     #[cfg(target_os = "macos")]
     socket.set_reuse_port(true)?;
-    socket.set_multicast_if_v4(&interface)?;
-    socket.set_multicast_ttl_v4(1)?;
-    socket.set_multicast_loop_v4(true)?; // Loopback enabled for self-test
-    let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
-    socket.bind(&addr.into())?;
+    multicast::set_outgoing(&socket, group, interface.into(), 1, true)?; // Loopback enabled for self-test
+    socket.bind(&multicast::bind_addr(group, 0).into())?;
     socket.set_nonblocking(true)?;
     Ok(socket.into())
 }
 
-fn create_receiver(mcast: Ipv4Addr, port: u16, interface: Ipv4Addr) -> anyhow::Result<std::net::UdpSocket> {
-    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+fn create_receiver(mcast: IpAddr, port: u16, interface: Ipv4Addr) -> anyhow::Result<std::net::UdpSocket> {
+    let socket = multicast::new_socket(mcast)?;
     socket.set_reuse_address(true)?;
     #[cfg(target_os = "macos")]
     socket.set_reuse_port(true)?;
-    let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
-    socket.bind(&addr.into())?;
-    socket.join_multicast_v4(&mcast, &interface)?;
+    socket.bind(&multicast::bind_addr(mcast, port).into())?;
+    multicast::join(&socket, mcast, interface.into())?;
     socket.set_nonblocking(true)?;
     // Increase receive buffer for burst tests
     socket.set_recv_buffer_size(4 * 1024 * 1024)?;
@@ -176,9 +232,9 @@ async fn test_latency(count: u64, interface: Ipv4Addr) -> anyhow::Result<bool> {
     println!("\n=== LATENCY TEST ===");
     println!("  Sending {count} MIDI packets via loopback multicast...\n");
 
-    let sender = UdpSocket::from_std(create_sender(interface)?)?;
+    let sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
     let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_DATA_PORT, interface)?)?;
-    let dest = SocketAddrV4::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
 
     let mut stats = LatencyStats::default();
     let mut send_buf = Vec::with_capacity(128);
@@ -246,50 +302,26 @@ async fn test_latency(count: u64, interface: Ipv4Addr) -> anyhow::Result<bool> {
 
 // ── Test: Throughput ─────────────────────────────────────────
 
-async fn test_throughput(duration_secs: u64, interface: Ipv4Addr) -> anyhow::Result<bool> {
-    println!("\n=== THROUGHPUT TEST ===");
-    println!("  Saturating link for {duration_secs}s...\n");
-
-    let sender = UdpSocket::from_std(create_sender(interface)?)?;
-    let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_DATA_PORT, interface)?)?;
-    let dest = SocketAddrV4::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
-
-    let sent = Arc::new(AtomicU64::new(0));
-    let received = Arc::new(AtomicU64::new(0));
-    let bytes_sent = Arc::new(AtomicU64::new(0));
-    let running = Arc::new(AtomicBool::new(true));
-
-    // Receiver task
-    let recv_count = Arc::clone(&received);
-    let recv_running = Arc::clone(&running);
-    let recv_handle = tokio::spawn(async move {
-        let mut buf = [0u8; 1024];
-        while recv_running.load(Ordering::Relaxed) {
-            match tokio::time::timeout(Duration::from_millis(10), receiver.recv_from(&mut buf)).await {
-                Ok(Ok((len, _))) => {
-                    if MidiDataPacket::deserialize(&buf[..len]).is_some() {
-                        recv_count.fetch_add(1, Ordering::Relaxed);
-                    }
-                }
-                _ => {}
-            }
-        }
-    });
-
-    // Sender: fire as fast as possible
+/// Send data packets on `channel` as fast as the socket accepts them.
+async fn saturate(
+    sender: UdpSocket,
+    dest: SocketAddr,
+    deadline: Instant,
+    channel: u8,
+    sent: Arc<AtomicU64>,
+    bytes_sent: Arc<AtomicU64>,
+) {
     let mut send_buf = Vec::with_capacity(128);
     let mut seq: u16 = 0;
-    let start = Instant::now();
-    let deadline = start + Duration::from_secs(duration_secs);
 
     // Typical MIDI messages: 3-byte Note On/Off, CC, with occasional journal
     let midi_messages: Vec<Vec<u8>> = vec![
-        vec![0x90, 60, 127],           // Note On
-        vec![0x80, 60, 0],             // Note Off
-        vec![0xB0, 1, 64],             // CC Mod Wheel
-        vec![0xB0, 7, 100],            // CC Volume
-        vec![0xE0, 0, 64],             // Pitch Bend
-        vec![0xC0, 5],                 // Program Change
+        vec![0x90 | channel, 60, 127], // Note On
+        vec![0x80 | channel, 60, 0],   // Note Off
+        vec![0xB0 | channel, 1, 64],   // CC Mod Wheel
+        vec![0xB0 | channel, 7, 100],  // CC Volume
+        vec![0xE0 | channel, 0, 64],   // Pitch Bend
+        vec![0xC0 | channel, 5],       // Program Change
     ];
 
     while Instant::now() < deadline {
@@ -316,6 +348,56 @@ async fn test_throughput(duration_secs: u64, interface: Ipv4Addr) -> anyhow::Res
 
         seq = seq.wrapping_add(1);
     }
+}
+
+async fn test_throughput(duration_secs: u64, shards: usize, interface: Ipv4Addr) -> anyhow::Result<bool> {
+    let shards = shards.max(1);
+    println!("\n=== THROUGHPUT TEST ===");
+    println!("  Saturating link for {duration_secs}s with {shards} sender socket(s)...\n");
+
+    let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_DATA_PORT, interface)?)?;
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
+
+    let sent = Arc::new(AtomicU64::new(0));
+    let received = Arc::new(AtomicU64::new(0));
+    let bytes_sent = Arc::new(AtomicU64::new(0));
+    let running = Arc::new(AtomicBool::new(true));
+
+    // Receiver task
+    let recv_count = Arc::clone(&received);
+    let recv_running = Arc::clone(&running);
+    let recv_handle = tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        while recv_running.load(Ordering::Relaxed) {
+            match tokio::time::timeout(Duration::from_millis(10), receiver.recv_from(&mut buf)).await {
+                Ok(Ok((len, _))) => {
+                    if MidiDataPacket::deserialize(&buf[..len]).is_some() {
+                        recv_count.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+
+    // Senders: fire as fast as possible, one task + socket per shard
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(duration_secs);
+    let mut senders = Vec::with_capacity(shards);
+    for shard in 0..shards {
+        let sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
+        senders.push(tokio::spawn(saturate(
+            sender,
+            dest,
+            deadline,
+            shard as u8 % 16,
+            Arc::clone(&sent),
+            Arc::clone(&bytes_sent),
+        )));
+    }
+    for sender in senders {
+        sender.await?;
+    }
 
     // Let receiver catch up
     tokio::time::sleep(Duration::from_millis(100)).await;
@@ -355,9 +437,9 @@ async fn test_burst(interface: Ipv4Addr) -> anyhow::Result<bool> {
     println!("\n=== BURST TEST ===");
     println!("  Simulating real-world MIDI patterns...\n");
 
-    let sender = UdpSocket::from_std(create_sender(interface)?)?;
+    let sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
     let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_DATA_PORT, interface)?)?;
-    let dest = SocketAddrV4::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
 
     let mut send_buf = Vec::with_capacity(256);
     let mut recv_buf = [0u8; 1024];
@@ -503,9 +585,9 @@ async fn test_heartbeat(count: u64, interface: Ipv4Addr) -> anyhow::Result<bool>
     println!("\n=== HEARTBEAT TIMING TEST ===");
     println!("  Measuring {count} heartbeat intervals at 3ms target...\n");
 
-    let sender = UdpSocket::from_std(create_sender(interface)?)?;
+    let sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
     let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_HB_PORT, interface)?)?;
-    let dest = SocketAddrV4::new(TEST_MCAST_GROUP, TEST_HB_PORT);
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_HB_PORT);
 
     let running = Arc::new(AtomicBool::new(true));
     let send_running = Arc::clone(&running);
@@ -522,6 +604,8 @@ async fn test_heartbeat(count: u64, interface: Ipv4Addr) -> anyhow::Result<bool>
                 role: HostRole::Primary,
                 sequence: seq,
                 timestamp_us: now_us(),
+                interval_ms: 3,
+                priority: 1,
             };
             pkt.serialize(&mut buf);
             let _ = sender.send_to(&buf, dest).await;
@@ -591,10 +675,10 @@ async fn test_failover(interface: Ipv4Addr) -> anyhow::Result<bool> {
     println!("\n=== FAILOVER SIMULATION TEST ===");
     println!("  Simulating primary failure, measuring detection and switch time...\n");
 
-    let primary_sender = UdpSocket::from_std(create_sender(interface)?)?;
-    let standby_sender = UdpSocket::from_std(create_sender(interface)?)?;
+    let primary_sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
+    let standby_sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
     let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_HB_PORT, interface)?)?;
-    let dest = SocketAddrV4::new(TEST_MCAST_GROUP, TEST_HB_PORT);
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_HB_PORT);
 
     let primary_alive = Arc::new(AtomicBool::new(true));
     let test_running = Arc::new(AtomicBool::new(true));
@@ -614,6 +698,8 @@ async fn test_failover(interface: Ipv4Addr) -> anyhow::Result<bool> {
                     role: HostRole::Primary,
                     sequence: seq,
                     timestamp_us: now_us(),
+                    interval_ms: 3,
+                    priority: 1,
                 };
                 pkt.serialize(&mut buf);
                 let _ = primary_sender.send_to(&buf, dest).await;
@@ -635,6 +721,8 @@ async fn test_failover(interface: Ipv4Addr) -> anyhow::Result<bool> {
                 role: HostRole::Standby,
                 sequence: seq,
                 timestamp_us: now_us(),
+                interval_ms: 3,
+                priority: 2,
             };
             pkt.serialize(&mut buf);
             let _ = standby_sender.send_to(&buf, dest).await;
@@ -759,15 +847,252 @@ async fn test_failover(interface: Ipv4Addr) -> anyhow::Result<bool> {
     Ok(pass)
 }
 
+// ── Test: Failover Chain ─────────────────────────────────────
+
+/// Heartbeat interval of the simulated hosts
+const CHAIN_HB_INTERVAL: Duration = Duration::from_millis(3);
+/// A host is lost after 3 missed heartbeats (client and hosts alike)
+const CHAIN_MISS_WINDOW: Duration = Duration::from_millis(9);
+/// Two hosts claiming Primary this close together are both active
+const CHAIN_DUAL_WINDOW: Duration = Duration::from_millis(6);
+
+/// Latest heartbeat from each host, as one listener (client or host) sees it.
+#[derive(Default)]
+struct PeerView {
+    last_seen: HashMap<u8, (Instant, HeartbeatPacket)>,
+}
+
+impl PeerView {
+    fn record(&mut self, hb: &HeartbeatPacket) {
+        self.last_seen.insert(hb.host_id, (Instant::now(), *hb));
+    }
+
+    fn alive(&self) -> impl Iterator<Item = &HeartbeatPacket> + '_ {
+        self.last_seen
+            .values()
+            .filter(|(at, _)| at.elapsed() < CHAIN_MISS_WINDOW)
+            .map(|(_, hb)| hb)
+    }
+
+    /// (host_id, priority) of every live host
+    fn ranked(&self) -> Vec<(u8, u8)> {
+        self.alive().map(|hb| (hb.host_id, hb.priority)).collect()
+    }
+}
+
+/// One simulated host. Starts as Primary or Standby; a standby promotes
+/// itself once no Primary has been heard for the miss window and it is
+/// the best-priority host still alive. Stops sending for good when
+/// `alive` is cleared.
+async fn run_chain_host(
+    host_id: u8,
+    priority: u8,
+    mut role: HostRole,
+    alive: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+    interface: Ipv4Addr,
+) -> anyhow::Result<()> {
+    let sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
+    let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_HB_PORT, interface)?)?;
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_HB_PORT);
+
+    let started = Instant::now();
+    let mut peers = PeerView::default();
+    let mut interval = tokio::time::interval(CHAIN_HB_INTERVAL);
+    let mut recv_buf = [0u8; 64];
+    let mut buf = [0u8; HeartbeatPacket::SIZE];
+    let mut seq: u16 = 0;
+
+    while running.load(Ordering::Relaxed) {
+        tokio::select! {
+            received = receiver.recv_from(&mut recv_buf) => {
+                if let Ok((len, _)) = received {
+                    if let Some(hb) = HeartbeatPacket::deserialize(&recv_buf[..len]).filter(|hb| hb.host_id != host_id) {
+                        peers.record(&hb);
+                    }
+                }
+            }
+            _ = interval.tick() => {
+                if !alive.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let primary_heard = peers.alive().any(|hb| hb.role == HostRole::Primary);
+                let mut ranked = peers.ranked();
+                ranked.push((host_id, priority));
+                let best = preferred_host(&ranked);
+                if role == HostRole::Standby
+                    && started.elapsed() > CHAIN_MISS_WINDOW
+                    && !primary_heard
+                    && best == Some(host_id)
+                {
+                    role = HostRole::Primary;
+                }
+                let pkt = HeartbeatPacket {
+                    host_id,
+                    role,
+                    sequence: seq,
+                    timestamp_us: now_us(),
+                    interval_ms: CHAIN_HB_INTERVAL.as_millis() as u16,
+                    priority,
+                };
+                pkt.serialize(&mut buf);
+                let _ = sender.send_to(&buf, dest).await;
+                seq = seq.wrapping_add(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn test_failover_chain(hosts: u8, interface: Ipv4Addr) -> anyhow::Result<bool> {
+    let hosts = hosts.clamp(2, 16);
+    println!("\n=== FAILOVER CHAIN TEST ===");
+    println!("  {hosts} hosts, killing the active host until one is left...\n");
+
+    // Priorities run opposite to host_id so selection can't fall back on ID order
+    let priorities: HashMap<u8, u8> = (1..=hosts).map(|id| (id, (hosts - id) * 10 + 1)).collect();
+    let first = preferred_host(&priorities.iter().map(|(&id, &p)| (id, p)).collect::<Vec<_>>()).unwrap_or(1);
+    for id in 1..=hosts {
+        println!("    host {id}: priority {}{}", priorities[&id], if id == first { " (primary)" } else { "" });
+    }
+    println!();
+
+    let running = Arc::new(AtomicBool::new(true));
+    let mut alive_flags = HashMap::new();
+    let mut handles = Vec::new();
+    for id in 1..=hosts {
+        let alive = Arc::new(AtomicBool::new(true));
+        alive_flags.insert(id, Arc::clone(&alive));
+        let role = if id == first { HostRole::Primary } else { HostRole::Standby };
+        handles.push(tokio::spawn(run_chain_host(
+            id,
+            priorities[&id],
+            role,
+            alive,
+            Arc::clone(&running),
+            interface,
+        )));
+    }
+
+    // Client-side view: follows the best-priority live host and watches
+    // for two hosts claiming Primary at once
+    let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_HB_PORT, interface)?)?;
+    let mut recv_buf = [0u8; 64];
+    let mut peers = PeerView::default();
+    let mut last_primary: Option<(u8, Instant)> = None;
+    let mut dual_active = 0u64;
+
+    let mut observe = |peers: &mut PeerView, last_primary: &mut Option<(u8, Instant)>, data: &[u8]| {
+        let hb = HeartbeatPacket::deserialize(data)?;
+        peers.record(&hb);
+        if hb.role == HostRole::Primary {
+            if let Some((other, at)) = *last_primary {
+                if other != hb.host_id && at.elapsed() < CHAIN_DUAL_WINDOW {
+                    dual_active += 1;
+                }
+            }
+            *last_primary = Some((hb.host_id, Instant::now()));
+        }
+        Some(hb)
+    };
+    let name = |host: Option<u8>| host.map_or("none".to_string(), |id| format!("host {id}"));
+
+    println!("  Warmup: all hosts healthy (1s)...");
+    let warmup_end = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < warmup_end {
+        if let Ok(Ok((len, _))) = tokio::time::timeout(Duration::from_millis(10), receiver.recv_from(&mut recv_buf)).await {
+            observe(&mut peers, &mut last_primary, &recv_buf[..len]);
+        }
+    }
+    let mut active = preferred_host(&peers.ranked());
+    let seen = peers.alive().count();
+    println!("    {seen}/{hosts} hosts heard, client on {}", name(active));
+    let mut pass = seen == hosts as usize && active == Some(first);
+
+    let mut survivors: Vec<u8> = (1..=hosts).collect();
+    let mut failover_times = Vec::new();
+    for step in 1..hosts {
+        let Some(victim) = active else { break };
+        survivors.retain(|&id| id != victim);
+        let expected = preferred_host(&survivors.iter().map(|&id| (id, priorities[&id])).collect::<Vec<_>>());
+
+        let kill_time = Instant::now();
+        alive_flags[&victim].store(false, Ordering::Relaxed);
+
+        // Client switch (victim missed) and promotion (new Primary heard)
+        let mut switched: Option<Duration> = None;
+        let mut promoted: Option<Duration> = None;
+        let deadline = kill_time + Duration::from_millis(200);
+        while Instant::now() < deadline && (switched.is_none() || promoted.is_none()) {
+            if let Ok(Ok((len, _))) = tokio::time::timeout(Duration::from_millis(1), receiver.recv_from(&mut recv_buf)).await {
+                observe(&mut peers, &mut last_primary, &recv_buf[..len]);
+            }
+            // The client's own failover rule decides where it lands
+            if switched.is_none() {
+                if let Some(target) = switch_target(victim, &peers.ranked()) {
+                    active = Some(target);
+                    switched = Some(kill_time.elapsed());
+                }
+            }
+            if promoted.is_none() && last_primary.is_some_and(|(id, _)| id != victim) {
+                promoted = Some(kill_time.elapsed());
+            }
+        }
+
+        let ms = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.2}ms", d.as_secs_f64() * 1000.0));
+        let landed = active == expected;
+        let note = if landed { String::new() } else { format!(", expected {}", name(expected)) };
+        println!(
+            "  Step {step}: killed host {victim} → client on {}{note} (switch {}, promotion {})",
+            name(active),
+            ms(switched),
+            ms(promoted),
+        );
+        match switched {
+            Some(t) => failover_times.push(t.as_secs_f64() * 1000.0),
+            None => pass = false,
+        }
+        pass &= landed && promoted.is_some();
+    }
+
+    // The last host standing keeps streaming as the only Primary
+    println!("  Settle: last survivor streaming (500ms)...");
+    let settle_end = Instant::now() + Duration::from_millis(500);
+    let mut survivor_primaries = 0u64;
+    while Instant::now() < settle_end {
+        if let Ok(Ok((len, _))) = tokio::time::timeout(Duration::from_millis(10), receiver.recv_from(&mut recv_buf)).await {
+            let hb = observe(&mut peers, &mut last_primary, &recv_buf[..len]);
+            if hb.is_some_and(|hb| hb.role == HostRole::Primary && Some(hb.host_id) == active) {
+                survivor_primaries += 1;
+            }
+        }
+    }
+    println!("    Primary heartbeats from {}: {survivor_primaries}", name(active));
+
+    running.store(false, Ordering::Relaxed);
+    for handle in handles {
+        handle.await??;
+    }
+
+    let worst = failover_times.iter().cloned().fold(0.0, f64::max);
+    println!("\n  Worst failover:  {worst:.2}ms over {} steps", failover_times.len());
+    println!("  Dual-active:     {dual_active} overlapping Primary heartbeats");
+
+    pass &= dual_active == 0 && worst < 20.0 && survivor_primaries > 100;
+    println!("\n  RESULT: {}", if pass { "PASS" } else { "FAIL" });
+    println!("  Criteria: every step lands on the best-priority survivor within 20ms, no dual-active window");
+    Ok(pass)
+}
+
 // ── Test: Soak ───────────────────────────────────────────────
 
 async fn test_soak(duration_secs: u64, rate: u64, interface: Ipv4Addr) -> anyhow::Result<bool> {
     println!("\n=== SOAK TEST ===");
     println!("  Running for {duration_secs}s at {rate} msg/s...\n");
 
-    let sender = UdpSocket::from_std(create_sender(interface)?)?;
+    let sender = UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?;
     let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_DATA_PORT, interface)?)?;
-    let dest = SocketAddrV4::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
 
     let running = Arc::new(AtomicBool::new(true));
     let total_recv = Arc::new(AtomicU64::new(0));
@@ -1032,6 +1357,473 @@ async fn test_journal() -> anyhow::Result<bool> {
     Ok(pass)
 }
 
+// ── Test: Hot Path Benchmark ─────────────────────────────────
+
+/// Per-message cost of everything the host does between reading a MIDI
+/// message and a client decoding the packet: pipeline, state tracking,
+/// periodic journal, serialize, deserialize. The other benchmarks measure
+/// these pieces in isolation.
+async fn test_hot_path(count: u64, journal_every: u64) -> anyhow::Result<bool> {
+    println!("\n=== HOT PATH BENCHMARK ===");
+    println!("  pipeline → MidiState → journal (every {journal_every}) → serialize → deserialize\n");
+
+    let config = PipelineConfig {
+        transpose: [2; 16],
+        velocity_curve: midi_protocol::pipeline::VelocityCurve::Logarithmic,
+        ..Default::default()
+    };
+
+    let messages: Vec<Vec<u8>> = (0..1000).map(|i| {
+        let ch = (i % 4) as u8;
+        match i % 5 {
+            0 => vec![0x90 | ch, 36 + (i as u8 % 48), 100], // Note On
+            1 => vec![0x80 | ch, 36 + (i as u8 % 48), 0],   // Note Off
+            2 => vec![0xB0 | ch, (i as u8) % 120, 64],      // CC
+            3 => vec![0xE0 | ch, 0, 64],                    // Pitch bend
+            _ => vec![0xC0 | ch, (i as u8) % 128],          // Program change
+        }
+    }).collect();
+
+    let journal_every = journal_every.max(1);
+    let mut state = MidiState::new();
+    let mut buf = Vec::with_capacity(1500);
+    let mut sequence = 0u16;
+    let mut journals = 0u64;
+    let mut sent = 0u64;
+    let mut decoded = 0u64;
+
+    let start = Instant::now();
+    for i in 0..count {
+        let msg = &messages[i as usize % messages.len()];
+        let Some(processed) = config.process(msg) else { continue };
+        state.process_message(&processed);
+
+        let journal = if i % journal_every == 0 {
+            journals += 1;
+            Some(encode_journal(&state))
+        } else {
+            None
+        };
+        let pkt = MidiDataPacket {
+            sequence,
+            timestamp_us: now_us(),
+            host_id: 1,
+            midi_data: processed,
+            journal,
+        };
+        sequence = sequence.wrapping_add(1);
+        pkt.serialize(&mut buf);
+        sent += 1;
+
+        if let Some(rx) = MidiDataPacket::deserialize(std::hint::black_box(&buf)) {
+            std::hint::black_box(&rx);
+            decoded += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let ns_per_msg = elapsed.as_nanos() as f64 / count as f64;
+    let rate = count as f64 / elapsed.as_secs_f64();
+    println!("  Messages:      {count} ({sent} packets, {journals} with journal)");
+    println!("  Decoded:       {decoded}/{sent}");
+    println!("  Cost:          {ns_per_msg:.0}ns/msg");
+    println!("  Throughput:    {rate:.0} msg/s");
+
+    // Same bar as the pipeline alone: heavy shows peak around 5k msg/s
+    let pass = rate > 100_000.0 && sent > 0 && decoded == sent;
+    println!("\n  RESULT: {}", if pass { "PASS" } else { "FAIL" });
+    println!("  Criteria: full hot path >100k msg/s, every packet decodes");
+    Ok(pass)
+}
+
+// ── Test: Journal Recovery Under Loss ────────────────────────
+
+/// Deterministic xorshift PRNG so failing trials can be reproduced.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// Uniform in (0, 1]
+    fn unit(&mut self) -> f64 {
+        ((self.next() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Normally distributed sample (Box-Muller)
+    fn gaussian(&mut self, mean: f64, stddev: f64) -> f64 {
+        let (u1, u2) = (self.unit(), self.unit());
+        mean + stddev * (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+}
+
+/// Random channel voice message biased towards notes and CCs.
+fn random_update(rng: &mut XorShift) -> Vec<u8> {
+    let ch = rng.below(16) as u8;
+    let data1 = rng.below(128) as u8;
+    let data2 = rng.below(128) as u8;
+    match rng.below(10) {
+        0..=2 => vec![0x90 | ch, data1, data2.max(1)],
+        3..=4 => vec![0x80 | ch, data1, 0],
+        5..=7 => vec![0xB0 | ch, data1 % 120, data2],
+        8 => match rng.below(3) {
+            0 => vec![0xC0 | ch, data1],
+            1 => vec![0xD0 | ch, data1],
+            _ => vec![0xE0 | ch, data1, data2],
+        },
+        _ => vec![0x90 | ch, data1, 0], // Note On vel 0 = Note Off
+    }
+}
+
+/// Count fields that differ between two states (notes, CCs, program, bend, pressure).
+fn state_diff_count(a: &MidiState, b: &MidiState) -> usize {
+    let mut diffs = 0;
+    for (x, y) in a.channels.iter().zip(b.channels.iter()) {
+        diffs += x.notes.iter().zip(y.notes.iter()).filter(|(p, q)| p != q).count();
+        diffs += x.cc.iter().zip(y.cc.iter()).filter(|(p, q)| p != q).count();
+        diffs += (x.program != y.program) as usize;
+        diffs += (x.pitch_bend != y.pitch_bend) as usize;
+        diffs += (x.channel_pressure != y.channel_pressure) as usize;
+    }
+    diffs
+}
+
+async fn test_journal_loss(drop_percent: f64, trials: u64) -> anyhow::Result<bool> {
+    println!("\n=== JOURNAL RECOVERY UNDER LOSS ===");
+    println!("  {trials} trials, 2000 updates each, dropping {drop_percent:.1}% of updates...\n");
+
+    let updates_per_trial = 2000u64;
+    let drop_threshold = (drop_percent.clamp(0.0, 100.0) * 10.0) as u64; // per-mille
+
+    let mut total_dropped = 0u64;
+    let mut diverged_trials = 0u64;
+    let mut state_failures = 0u64;
+    let mut note_failures = 0u64;
+    let mut restore_failures = 0u64;
+    let mut stale_defaults = 0u64;
+
+    for trial in 0..trials {
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15 ^ (trial + 1));
+        let mut source = MidiState::new();
+        let mut receiver = MidiState::new(); // client's state model
+        let mut device = MidiState::new(); // what the virtual device has seen
+
+        for _ in 0..updates_per_trial {
+            let msg = random_update(&mut rng);
+            source.process_message(&msg);
+            if rng.below(1000) < drop_threshold {
+                total_dropped += 1;
+                continue;
+            }
+            receiver.process_message(&msg);
+            device.process_message(&msg);
+        }
+
+        if state_diff_count(&source, &receiver) > 0 {
+            diverged_trials += 1;
+        }
+
+        // Latest journal arrives: replace the state model, replay reconciliation
+        let journal = encode_journal(&source);
+        receiver = match decode_journal(&journal) {
+            Some(s) => s,
+            None => {
+                state_failures += 1;
+                continue;
+            }
+        };
+        for msg in receiver.generate_reconciliation() {
+            device.process_message(&msg);
+        }
+
+        if state_diff_count(&source, &receiver) != 0 {
+            state_failures += 1;
+        }
+
+        let mut notes_ok = true;
+        let mut restored_ok = true;
+        for (src, dev) in source.channels.iter().zip(device.channels.iter()) {
+            if src.notes != dev.notes {
+                notes_ok = false;
+            }
+            // Everything the source holds away from its default must be restored
+            for cc in 0..120 {
+                if src.cc[cc] != 0 && src.cc[cc] != dev.cc[cc] {
+                    restored_ok = false;
+                } else if src.cc[cc] == 0 && dev.cc[cc] != 0 {
+                    stale_defaults += 1;
+                }
+            }
+            if src.program != 0 && src.program != dev.program {
+                restored_ok = false;
+            }
+            if src.pitch_bend != 8192 && src.pitch_bend != dev.pitch_bend {
+                restored_ok = false;
+            }
+            if src.channel_pressure != 0 && src.channel_pressure != dev.channel_pressure {
+                restored_ok = false;
+            }
+            stale_defaults += (src.program == 0 && dev.program != 0) as u64
+                + (src.pitch_bend == 8192 && dev.pitch_bend != 8192) as u64
+                + (src.channel_pressure == 0 && dev.channel_pressure != 0) as u64;
+        }
+        if !notes_ok {
+            note_failures += 1;
+        }
+        if !restored_ok {
+            restore_failures += 1;
+        }
+    }
+
+    println!("  Dropped updates:      {total_dropped}/{}", trials * updates_per_trial);
+    println!("  Diverged before heal: {diverged_trials}/{trials} trials");
+    println!("  State model mismatch: {state_failures}/{trials} trials");
+    println!("  Device note mismatch: {note_failures}/{trials} trials");
+    println!("  Device value missing: {restore_failures}/{trials} trials");
+    println!("  Stale default values: {stale_defaults} (reconciliation only re-sends non-default values)");
+
+    let pass = state_failures == 0 && note_failures == 0 && restore_failures == 0;
+    println!("\n  RESULT: {}", if pass { "PASS" } else { "FAIL" });
+    println!("  Criteria: journal restores the exact source state, device notes and non-default values match");
+    Ok(pass)
+}
+
+// ── Test: Jitter & Loss Impairment ───────────────────────────
+
+/// Network impairment applied to every packet on the send path.
+#[derive(Clone, Copy)]
+struct Impairment {
+    delay_mean_us: f64,
+    delay_stddev_us: f64,
+    loss_percent: f64,
+}
+
+impl Impairment {
+    /// None if the packet is lost, otherwise how long to hold it back.
+    fn apply(&self, rng: &mut XorShift) -> Option<Duration> {
+        if rng.unit() * 100.0 <= self.loss_percent {
+            return None;
+        }
+        let delay_us = rng.gaussian(self.delay_mean_us, self.delay_stddev_us).max(0.0);
+        Some(Duration::from_micros(delay_us as u64))
+    }
+}
+
+/// What the receiver saw, before and after the jitter buffer.
+#[derive(Default)]
+struct JitterReport {
+    /// Data packets that came off the wire or out of FEC
+    received: u64,
+    /// Arrived behind a packet with a later sequence
+    reordered_arrivals: u64,
+    /// Furthest a packet arrived behind the newest one, in packets
+    max_reorder_depth: i32,
+    fec_recovered: u64,
+    /// Parity that found two or more of its group missing, lost or
+    /// simply still in flight behind it
+    fec_unrecoverable: u64,
+    /// Arrived past their deadline but inside the late window, played at once
+    late_played: u64,
+    /// Arrived after their playout window, dropped by the buffer
+    late_dropped: u64,
+    /// Rebuilt from parity and then also arrived
+    duplicates: u64,
+    played: u64,
+    /// Played behind a later sequence (the buffer failed to reorder)
+    played_out_of_order: u64,
+    /// Host timestamp to playout
+    playout_latency: LatencyStats,
+}
+
+async fn test_jitter(
+    count: u64,
+    rate: u64,
+    impairment: Impairment,
+    buffer_us: u64,
+    fec_group: u8,
+    interface: Ipv4Addr,
+) -> anyhow::Result<bool> {
+    // Sequences are tracked in a set, so stay within one u16 cycle
+    let count = count.clamp(1, 65_536);
+    println!("\n=== JITTER & LOSS IMPAIRMENT TEST ===");
+    println!(
+        "  {count} packets at {rate} pkt/s, delay {:.0}±{:.0}us, {:.1}% loss, buffer {buffer_us}us, FEC group {}\n",
+        impairment.delay_mean_us,
+        impairment.delay_stddev_us,
+        impairment.loss_percent,
+        if fec_group >= 2 { fec_group.to_string() } else { "off".to_string() },
+    );
+
+    let sender = Arc::new(UdpSocket::from_std(create_sender(TEST_MCAST_GROUP, interface)?)?);
+    let receiver = UdpSocket::from_std(create_receiver(TEST_MCAST_GROUP, TEST_DATA_PORT, interface)?)?;
+    let dest = SocketAddr::new(TEST_MCAST_GROUP, TEST_DATA_PORT);
+    let running = Arc::new(AtomicBool::new(true));
+
+    // Receiver: the client's FEC → jitter buffer → playout path
+    let recv_running = Arc::clone(&running);
+    let jitter = JitterBuffer::<MidiDataPacket>::new(buffer_us, Instant::now());
+    let late_window = jitter.late_window();
+    let recv_handle = tokio::spawn(async move {
+        let mut report = JitterReport::default();
+        let mut jitter = jitter;
+        let mut fec = FecDecoder::new();
+        let mut newest: Option<u16> = None;
+        let mut playout = SequenceTracker::new();
+        let mut played = HashSet::new();
+        let mut buf = [0u8; 1500];
+
+        while recv_running.load(Ordering::Relaxed) || jitter.next_deadline().is_some() {
+            let wake = jitter.next_deadline().unwrap_or_else(|| Instant::now() + Duration::from_millis(10));
+            tokio::select! {
+                received = receiver.recv_from(&mut buf) => {
+                    let Ok((len, _)) = received else { continue };
+                    let recovered;
+                    let data: &[u8] = if buf[..len].starts_with(&MAGIC_FEC_PARITY) {
+                        let Some(parity) = FecParityPacket::deserialize(&buf[..len]) else { continue };
+                        match fec.recover(&parity) {
+                            Recovery::Recovered(bytes) => {
+                                report.fec_recovered += 1;
+                                recovered = bytes;
+                                &recovered
+                            }
+                            Recovery::Complete => continue,
+                            Recovery::Unrecoverable { .. } => {
+                                report.fec_unrecoverable += 1;
+                                continue;
+                            }
+                        }
+                    } else {
+                        fec.record(&buf[..len]);
+                        &buf[..len]
+                    };
+                    let Some(packet) = MidiDataPacket::deserialize(data) else { continue };
+                    report.received += 1;
+
+                    let behind = newest.map_or(0, |n| -seq_distance(n, packet.sequence));
+                    if behind > 0 {
+                        report.max_reorder_depth = report.max_reorder_depth.max(behind);
+                    } else {
+                        newest = Some(packet.sequence);
+                    }
+
+                    let timestamp_us = packet.timestamp_us;
+                    match jitter.push(timestamp_us, packet, Instant::now()) {
+                        Admit::Queued { reordered, late } => {
+                            report.reordered_arrivals += reordered as u64;
+                            report.late_played += late as u64;
+                        }
+                        Admit::LateDropped => report.late_dropped += 1,
+                    }
+                }
+                _ = tokio::time::sleep_until(wake.into()) => {}
+            }
+
+            while let Some(packet) = jitter.pop_due(Instant::now()) {
+                if !played.insert(packet.sequence) {
+                    report.duplicates += 1;
+                    continue;
+                }
+                if playout.observe(packet.sequence) == SeqEvent::Late {
+                    report.played_out_of_order += 1;
+                }
+                report.played += 1;
+                report.playout_latency.add(now_us().saturating_sub(packet.timestamp_us) as f64);
+            }
+        }
+        report
+    });
+
+    // Sender: every packet (data and parity) goes through the impairment
+    let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+    let mut encoder = FecEncoder::new(fec_group);
+    let mut data_lost = 0u64;
+    let mut parity_sent = 0u64;
+    let mut parity_lost = 0u64;
+    let mut ticker = tokio::time::interval(Duration::from_micros(1_000_000 / rate.max(1)));
+
+    for i in 0..count {
+        ticker.tick().await;
+        let pkt = MidiDataPacket {
+            sequence: i as u16,
+            timestamp_us: now_us(),
+            host_id: 1,
+            midi_data: vec![0x90 | (i % 16) as u8, 36 + (i % 48) as u8, 100],
+            journal: None,
+        };
+        let mut wire = Vec::with_capacity(64);
+        pkt.serialize(&mut wire);
+
+        let mut outgoing = Vec::with_capacity(2);
+        if let Some(parity) = encoder.as_mut().and_then(|e| e.push(&wire)) {
+            let mut parity_wire = Vec::new();
+            parity.serialize(&mut parity_wire);
+            outgoing.push((true, parity_wire));
+        }
+        outgoing.insert(0, (false, wire));
+
+        for (is_parity, bytes) in outgoing {
+            parity_sent += is_parity as u64;
+            let Some(delay) = impairment.apply(&mut rng) else {
+                if is_parity { parity_lost += 1 } else { data_lost += 1 }
+                continue;
+            };
+            let sender = Arc::clone(&sender);
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ = sender.send_to(&bytes, dest).await;
+            });
+        }
+    }
+
+    // Wait out the slowest delayed packets, then let the buffer drain
+    let tail_us = impairment.delay_mean_us + 6.0 * impairment.delay_stddev_us + 2.0 * buffer_us as f64;
+    tokio::time::sleep(Duration::from_micros(tail_us as u64) + Duration::from_millis(100)).await;
+    running.store(false, Ordering::Relaxed);
+    let mut report = recv_handle.await?;
+
+    let residual_lost = count - report.played;
+    let injected_pct = data_lost as f64 / count as f64 * 100.0;
+    let residual_pct = residual_lost as f64 / count as f64 * 100.0;
+
+    println!("  Impairment:");
+    println!("    data lost:          {data_lost}/{count} ({injected_pct:.2}%)");
+    println!("    parity lost:        {parity_lost}/{parity_sent}");
+    println!("  Arrival:");
+    println!("    received:           {}", report.received);
+    println!("    reordered:          {} (max depth {} packets)", report.reordered_arrivals, report.max_reorder_depth);
+    println!("    FEC recovered:      {} ({} groups incomplete at parity)", report.fec_recovered, report.fec_unrecoverable);
+    println!("  Jitter buffer:");
+    println!("    late window:        {}us past deadline", late_window.as_micros());
+    println!("    late, played:       {}", report.late_played);
+    println!("    late dropped:       {}", report.late_dropped);
+    println!("    duplicates skipped: {}", report.duplicates);
+    println!("    played:             {}/{count}", report.played);
+    println!("    played out of order: {}", report.played_out_of_order);
+    println!("    residual loss:      {residual_lost} ({residual_pct:.2}%)");
+    report.playout_latency.report("Playout latency (host timestamp → playout)");
+
+    // FEC must win back at least half the injected loss (reordering makes
+    // parity overtake its group, so it can't win back all of it); without
+    // FEC the buffer may only add its own late drops on top
+    let allowed_pct = if encoder.is_some() {
+        impairment.loss_percent / 2.0 + 0.1
+    } else {
+        impairment.loss_percent + 0.5
+    };
+    let pass = report.played > 0 && report.played_out_of_order == 0 && residual_pct <= allowed_pct;
+    println!("\n  RESULT: {}", if pass { "PASS" } else { "FAIL" });
+    println!("  Criteria: playout in sequence order, residual loss <= {allowed_pct:.2}%");
+    Ok(pass)
+}
+
 // ── Run All ──────────────────────────────────────────────────
 
 async fn run_all(interface: Ipv4Addr) -> anyhow::Result<()> {
@@ -1043,12 +1835,17 @@ async fn run_all(interface: Ipv4Addr) -> anyhow::Result<()> {
 
     results.push(("Pipeline Benchmark", test_pipeline().await?));
     results.push(("Journal Benchmark", test_journal().await?));
+    results.push(("Journal Recovery (10% loss)", test_journal_loss(10.0, 100).await?));
+    results.push(("Hot Path (1M msgs)", test_hot_path(1_000_000, 500).await?));
     results.push(("Latency (10k pkts)", test_latency(10_000, interface).await?));
     results.push(("Heartbeat Timing (3k)", test_heartbeat(3_000, interface).await?));
     results.push(("Burst Patterns", test_burst(interface).await?));
-    results.push(("Throughput (10s)", test_throughput(10, interface).await?));
+    results.push(("Throughput (10s)", test_throughput(10, 1, interface).await?));
     results.push(("Failover Simulation", test_failover(interface).await?));
+    results.push(("Failover Chain (3 hosts)", test_failover_chain(3, interface).await?));
     results.push(("Soak Test (30s)", test_soak(30, 1000, interface).await?));
+    let impairment = Impairment { delay_mean_us: 2000.0, delay_stddev_us: 500.0, loss_percent: 1.0 };
+    results.push(("Jitter & Loss (1%, FEC 4)", test_jitter(10_000, 1000, impairment, 8000, 4, interface).await?));
 
     println!("\n\n╔═══════════════════════════════════════════════════╗");
     println!("║                  FINAL REPORT                     ║");
@@ -1085,13 +1882,24 @@ async fn main() -> anyhow::Result<()> {
 
     match args.command {
         Command::Latency { count } => { test_latency(count, interface).await?; }
-        Command::Throughput { duration } => { test_throughput(duration, interface).await?; }
+        Command::Throughput { duration, shards } => { test_throughput(duration, shards, interface).await?; }
         Command::Burst => { test_burst(interface).await?; }
         Command::Heartbeat { count } => { test_heartbeat(count, interface).await?; }
         Command::Failover => { test_failover(interface).await?; }
+        Command::FailoverChain { hosts } => { test_failover_chain(hosts, interface).await?; }
         Command::Soak { duration, rate } => { test_soak(duration, rate, interface).await?; }
         Command::Pipeline => { test_pipeline().await?; }
         Command::Journal => { test_journal().await?; }
+        Command::JournalLoss { drop_percent, trials } => { test_journal_loss(drop_percent, trials).await?; }
+        Command::HotPath { count, journal_every } => { test_hot_path(count, journal_every).await?; }
+        Command::Jitter { count, rate, delay_mean_us, delay_stddev_us, loss_percent, buffer_us, fec_group } => {
+            let impairment = Impairment {
+                delay_mean_us: delay_mean_us as f64,
+                delay_stddev_us: delay_stddev_us as f64,
+                loss_percent,
+            };
+            test_jitter(count, rate, impairment, buffer_us, fec_group, interface).await?;
+        }
         Command::All => { run_all(interface).await?; }
     }
 
@@ -0,0 +1,145 @@
+/// Per-channel MIDI activity meters.
+///
+/// Counts channel messages (Note On/Off, CC, Program Change, ...) per MIDI
+/// channel and turns them into messages-per-second the same way the
+/// aggregate rate is computed: messages counted since the window started,
+/// divided by the window's elapsed time, then a new window begins.
+/// `record()` runs on the hot path; `roll()` is called by a periodic task.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How often the owner of a `ChannelActivity` should `roll()` it
+/// (matches the admin sniffer's aggregate rate window).
+pub const RATE_WINDOW: Duration = Duration::from_millis(250);
+
+/// One channel's meter, as reported by the APIs.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChannelMeter {
+    /// 1-16
+    pub channel: u8,
+    /// Messages seen on this channel since start
+    pub messages: u64,
+    /// Messages per second over the last window
+    pub per_sec: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChannelActivity {
+    totals: [u64; 16],
+    window: [u32; 16],
+    rates: [f32; 16],
+}
+
+impl ChannelActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the channel messages in `data`. A message under running
+    /// status (data bytes with no status of their own) counts against the
+    /// last channel status. System messages (0xF0-0xFF) belong to no
+    /// channel and are skipped; SysEx and System Common cancel running
+    /// status, real-time bytes leave it alone.
+    pub fn record(&mut self, data: &[u8]) {
+        let mut running: Option<u8> = None;
+        let mut remaining = 0usize;
+        for &byte in data {
+            let status = match byte {
+                0x80..=0xEF => byte,
+                0xF8..=0xFF => continue,
+                0xF0..=0xF7 => {
+                    running = None;
+                    remaining = 0;
+                    continue;
+                }
+                _ if remaining > 0 => {
+                    remaining -= 1;
+                    continue;
+                }
+                _ => match running {
+                    Some(status) => status,
+                    None => continue,
+                },
+            };
+            let ch = (status & 0x0F) as usize;
+            self.totals[ch] += 1;
+            self.window[ch] = self.window[ch].saturating_add(1);
+            let len = if matches!(status & 0xF0, 0xC0 | 0xD0) { 1 } else { 2 };
+            // The data byte that stood in for a status is the message's first
+            remaining = if byte < 0x80 { len - 1 } else { len };
+            running = Some(status);
+        }
+    }
+
+    /// Close the current window after `elapsed`: each channel's rate becomes
+    /// its window count divided by the elapsed seconds, and counting starts over.
+    pub fn roll(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f32().max(0.001);
+        for ch in 0..16 {
+            self.rates[ch] = self.window[ch] as f32 / secs;
+            self.window[ch] = 0;
+        }
+    }
+
+    /// All 16 channels' meters, channel 1 first.
+    pub fn meters(&self) -> Vec<ChannelMeter> {
+        (0..16)
+            .map(|ch| ChannelMeter {
+                channel: ch as u8 + 1,
+                messages: self.totals[ch],
+                per_sec: self.rates[ch],
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_channel_messages_per_channel() {
+        let mut activity = ChannelActivity::new();
+        // Note On ch1, CC ch2, clock, SysEx, Program Change ch16
+        activity.record(&[0x90, 60, 100, 0xB1, 7, 64, 0xF8, 0xF0, 0x7E, 0x7F, 0xF7, 0xCF, 3]);
+        let meters = activity.meters();
+        assert_eq!(meters.len(), 16);
+        assert_eq!(meters[0], ChannelMeter { channel: 1, messages: 1, per_sec: 0.0 });
+        assert_eq!(meters[1].messages, 1);
+        assert_eq!(meters[15].messages, 1);
+        assert_eq!(meters.iter().map(|m| m.messages).sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn running_status_counts_every_message() {
+        let mut activity = ChannelActivity::new();
+        // Three Note Ons on ch1 under one status (clock in between), two
+        // Program Changes on ch3, then SysEx cancels running status
+        activity.record(&[0x90, 60, 100, 62, 0xF8, 100, 64, 100, 0xC2, 5, 6, 0xF0, 0x7E, 0xF7, 1, 2]);
+        let meters = activity.meters();
+        assert_eq!(meters[0].messages, 3);
+        assert_eq!(meters[2].messages, 2);
+        assert_eq!(meters.iter().map(|m| m.messages).sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn rate_covers_only_the_last_window() {
+        let mut activity = ChannelActivity::new();
+        for _ in 0..10 {
+            activity.record(&[0x90, 60, 100]);
+        }
+        activity.roll(Duration::from_millis(250));
+        assert_eq!(activity.meters()[0].per_sec, 40.0);
+
+        // A quiet window brings the rate back to zero; the total stays
+        activity.roll(Duration::from_millis(250));
+        assert_eq!(activity.meters()[0].per_sec, 0.0);
+        assert_eq!(activity.meters()[0].messages, 10);
+
+        activity.record(&[0x80, 60, 0]);
+        activity.roll(Duration::from_millis(500));
+        assert_eq!(activity.meters()[0].per_sec, 2.0);
+    }
+}
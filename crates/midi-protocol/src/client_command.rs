@@ -0,0 +1,65 @@
+/// Commands the admin panel sends to clients.
+///
+/// Commands are queued by the admin and handed to each client in the
+/// response to its next heartbeat. The client executes them and returns a
+/// `CommandAck` per command with its following heartbeat.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::PipelineConfig;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// Send All Sound Off + All Notes Off on every channel of the virtual device
+    AllNotesOff,
+    /// Replace the client's MIDI processing pipeline
+    SetPipeline { pipeline: Box<PipelineConfig> },
+}
+
+/// A command addressed to a single client, tagged with the admin's command id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedCommand {
+    pub id: u64,
+    pub command: ClientCommand,
+}
+
+/// Result of executing a `QueuedCommand` on a client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandAck {
+    pub id: u64,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CommandAck {
+    pub fn ok(id: u64) -> Self {
+        Self { id, success: true, error: None }
+    }
+
+    pub fn failed(id: u64, error: impl Into<String>) -> Self {
+        Self { id, success: false, error: Some(error.into()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_wire_format() {
+        let json = serde_json::to_value(ClientCommand::AllNotesOff).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "all_notes_off" }));
+
+        let cmd: ClientCommand = serde_json::from_value(serde_json::json!({
+            "type": "set_pipeline",
+            "pipeline": { "sysex_passthrough": false },
+        }))
+        .unwrap();
+        match cmd {
+            ClientCommand::SetPipeline { pipeline } => assert!(!pipeline.sysex_passthrough),
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+}
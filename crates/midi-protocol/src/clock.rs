@@ -0,0 +1,103 @@
+/// Packet timestamp source.
+///
+/// Wall-clock timestamps (`SystemTime`) can be correlated across machines
+/// but jump whenever NTP steps the clock, which corrupts latency/jitter
+/// figures mid-show. Monotonic timestamps are anchored to the wall clock
+/// once at startup and then advance with `Instant`, so intervals between
+/// packets stay correct regardless of clock adjustments.
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSource {
+    /// Microseconds since UNIX epoch, read on every packet
+    #[default]
+    WallClock,
+    /// Startup wall-clock time plus monotonic elapsed time
+    Monotonic,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PacketClock {
+    source: TimestampSource,
+    anchor: Instant,
+    anchor_wall_us: u64,
+}
+
+impl PacketClock {
+    pub fn new(source: TimestampSource) -> Self {
+        Self::with_anchor(source, Instant::now(), wall_clock_us())
+    }
+
+    pub fn with_anchor(source: TimestampSource, anchor: Instant, anchor_wall_us: u64) -> Self {
+        Self { source, anchor, anchor_wall_us }
+    }
+
+    /// Timestamp for a packet sent now, in microseconds.
+    pub fn now_us(&self) -> u64 {
+        match self.source {
+            TimestampSource::WallClock => wall_clock_us(),
+            TimestampSource::Monotonic => self.monotonic_us(Instant::now()),
+        }
+    }
+
+    /// Timestamp given an explicit monotonic instant and wall-clock reading.
+    pub fn timestamp_at(&self, now: Instant, wall_us: u64) -> u64 {
+        match self.source {
+            TimestampSource::WallClock => wall_us,
+            TimestampSource::Monotonic => self.monotonic_us(now),
+        }
+    }
+
+    fn monotonic_us(&self, now: Instant) -> u64 {
+        self.anchor_wall_us + now.saturating_duration_since(self.anchor).as_micros() as u64
+    }
+}
+
+/// Microseconds since UNIX epoch
+pub fn wall_clock_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn monotonic_timing_survives_wall_clock_jump() {
+        let start = Instant::now();
+        let wall_start = 1_700_000_000_000_000u64;
+        let mono = PacketClock::with_anchor(TimestampSource::Monotonic, start, wall_start);
+        let wall = PacketClock::with_anchor(TimestampSource::WallClock, start, wall_start);
+
+        // Packets every 10ms; NTP steps the wall clock back 2s before the third
+        let sends = [
+            (start, wall_start),
+            (start + Duration::from_millis(10), wall_start + 10_000),
+            (start + Duration::from_millis(20), wall_start + 20_000 - 2_000_000),
+        ];
+
+        let mono_ts: Vec<u64> = sends.iter().map(|&(i, w)| mono.timestamp_at(i, w)).collect();
+        assert_eq!(mono_ts[0], wall_start);
+        assert_eq!(mono_ts[1] - mono_ts[0], 10_000);
+        assert_eq!(mono_ts[2] - mono_ts[1], 10_000);
+
+        // Wall clock goes backwards, which is what the monotonic source avoids
+        let wall_ts: Vec<u64> = sends.iter().map(|&(i, w)| wall.timestamp_at(i, w)).collect();
+        assert!(wall_ts[2] < wall_ts[1]);
+    }
+
+    #[test]
+    fn monotonic_is_anchored_near_wall_clock() {
+        let clock = PacketClock::new(TimestampSource::Monotonic);
+        let diff = clock.now_us().abs_diff(wall_clock_us());
+        assert!(diff < 1_000_000, "monotonic drifted {}us from wall clock", diff);
+    }
+}
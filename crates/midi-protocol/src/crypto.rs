@@ -0,0 +1,183 @@
+/// Optional AES-256-GCM sealing of the MIDI data stream.
+///
+/// With `network.psk` set, the host seals every serialized `MidiDataPacket`
+/// before sending and clients open it straight after receiving; with no PSK
+/// the stream stays plaintext. The 256-bit key is the SHA-256 of the PSK
+/// string, so any passphrase works but it must match on every machine.
+///
+/// Sealed layout: the first 16 header bytes (magic, sequence, timestamp,
+/// host_id, flags with `FLAG_ENCRYPTED` set) stay readable and are
+/// authenticated as associated data; everything after them (midi_len, MIDI,
+/// journal) is ciphertext, followed by the 16-byte GCM tag. The nonce is
+/// host_id, sequence and timestamp_us: host_id keeps hosts sharing a key
+/// apart, and the timestamp keeps nonces unique after the u16 sequence
+/// wraps.
+
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce, Tag};
+use sha2::{Digest, Sha256};
+
+use crate::packets::{MidiDataPacket, FLAG_ENCRYPTED, MAGIC_MIDI};
+
+/// Plaintext header bytes: magic(4) + seq(2) + timestamp(8) + host_id(1) + flags(1)
+const AAD_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenError {
+    /// Not a data packet, or too short to hold a header and tag
+    Malformed,
+    /// A plaintext packet on a stream that requires encryption
+    NotEncrypted,
+    /// Wrong key, or the packet was modified in transit
+    AuthFailed,
+}
+
+#[derive(Clone)]
+pub struct PacketCipher {
+    cipher: Aes256Gcm,
+}
+
+impl std::fmt::Debug for PacketCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PacketCipher")
+    }
+}
+
+impl PacketCipher {
+    /// Cipher for `psk`, or None for an empty PSK (plaintext stream).
+    pub fn from_psk(psk: &str) -> Option<Self> {
+        if psk.is_empty() {
+            return None;
+        }
+        let key = Sha256::digest(psk.as_bytes());
+        Some(Self { cipher: Aes256Gcm::new(&key) })
+    }
+
+    fn nonce(header: &[u8]) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[0] = header[14]; // host_id
+        nonce[2..4].copy_from_slice(&header[4..6]); // sequence
+        nonce[4..12].copy_from_slice(&header[6..14]); // timestamp_us
+        nonce
+    }
+
+    /// Seal a serialized data packet in place.
+    pub fn seal(&self, buf: &mut Vec<u8>) {
+        if buf.len() < MidiDataPacket::HEADER_SIZE || buf[0..4] != MAGIC_MIDI {
+            return;
+        }
+        buf[15] |= FLAG_ENCRYPTED;
+        let nonce = Self::nonce(buf);
+        let (header, body) = buf.split_at_mut(AAD_LEN);
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), header, body)
+            .expect("data packets are far below the AES-GCM length limit");
+        buf.extend_from_slice(&tag);
+    }
+
+    /// Verify and decrypt a sealed data packet, returning the plaintext
+    /// serialization for `MidiDataPacket::deserialize`.
+    pub fn open(&self, data: &[u8]) -> Result<Vec<u8>, OpenError> {
+        if data.len() < AAD_LEN || data[0..4] != MAGIC_MIDI {
+            return Err(OpenError::Malformed);
+        }
+        if data[15] & FLAG_ENCRYPTED == 0 {
+            return Err(OpenError::NotEncrypted);
+        }
+        if data.len() < MidiDataPacket::HEADER_SIZE + TAG_LEN {
+            return Err(OpenError::Malformed);
+        }
+        let nonce = Self::nonce(data);
+        let (sealed, tag) = data.split_at(data.len() - TAG_LEN);
+        let mut plain = sealed.to_vec();
+        let (header, body) = plain.split_at_mut(AAD_LEN);
+        self.cipher
+            .decrypt_in_place_detached(Nonce::from_slice(&nonce), header, body, Tag::from_slice(tag))
+            .map_err(|_| OpenError::AuthFailed)?;
+        plain[15] &= !FLAG_ENCRYPTED;
+        Ok(plain)
+    }
+}
+
+/// Decode a received data packet, opening it first on a sealed stream.
+pub fn decode_data_packet(cipher: Option<&PacketCipher>, data: &[u8]) -> Result<MidiDataPacket, OpenError> {
+    match cipher {
+        Some(cipher) => MidiDataPacket::deserialize(&cipher.open(data)?),
+        None => MidiDataPacket::deserialize(data),
+    }
+    .ok_or(OpenError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet() -> MidiDataPacket {
+        MidiDataPacket {
+            sequence: 0xFFFF,
+            timestamp_us: 1_234_567,
+            host_id: 2,
+            midi_data: vec![0x90, 60, 100],
+            journal: Some(vec![0x00, 0x01, 0x01, 0x01, 60, 100]),
+        }
+    }
+
+    #[test]
+    fn sealed_packet_roundtrips() {
+        let cipher = PacketCipher::from_psk("venue-vlan-key").unwrap();
+        let mut buf = Vec::new();
+        packet().serialize(&mut buf);
+        let plain = buf.clone();
+
+        cipher.seal(&mut buf);
+        assert_eq!(buf.len(), plain.len() + TAG_LEN);
+        // MIDI bytes are not readable on the wire
+        assert!(!buf.windows(3).any(|w| w == [0x90, 60, 100]));
+        // Receivers without the key don't misread it as a packet
+        assert!(MidiDataPacket::deserialize(&buf).is_none());
+
+        let opened = cipher.open(&buf).unwrap();
+        assert_eq!(opened, plain);
+        let decoded = MidiDataPacket::deserialize(&opened).unwrap();
+        assert_eq!(decoded.sequence, 0xFFFF);
+        assert_eq!(decoded.midi_data, vec![0x90, 60, 100]);
+        assert_eq!(decoded.journal, packet().journal);
+    }
+
+    #[test]
+    fn tampering_or_wrong_key_fails_authentication() {
+        let cipher = PacketCipher::from_psk("venue-vlan-key").unwrap();
+        let mut sealed = Vec::new();
+        packet().serialize(&mut sealed);
+        cipher.seal(&mut sealed);
+
+        // Flipped ciphertext bit
+        let mut tampered = sealed.clone();
+        tampered[AAD_LEN + 3] ^= 0x01;
+        assert_eq!(cipher.open(&tampered), Err(OpenError::AuthFailed));
+
+        // Rewritten header (replayed under another sequence number)
+        let mut tampered = sealed.clone();
+        tampered[5] ^= 0x01;
+        assert_eq!(cipher.open(&tampered), Err(OpenError::AuthFailed));
+
+        let other = PacketCipher::from_psk("another-key").unwrap();
+        assert_eq!(other.open(&sealed), Err(OpenError::AuthFailed));
+
+        let mut plain = Vec::new();
+        packet().serialize(&mut plain);
+        assert_eq!(cipher.open(&plain), Err(OpenError::NotEncrypted));
+        assert_eq!(cipher.open(&sealed[..10]), Err(OpenError::Malformed));
+    }
+
+    #[test]
+    fn empty_psk_means_plaintext() {
+        assert!(PacketCipher::from_psk("").is_none());
+
+        let mut buf = Vec::new();
+        packet().serialize(&mut buf);
+        assert_eq!(decode_data_packet(None, &buf).unwrap().host_id, 2);
+    }
+}
@@ -0,0 +1,69 @@
+/// Failover cause, shared by the host's failover log and the admin
+/// panel's failover history so post-incident analysis reads the same
+/// everywhere, and the rule clients use to pick the host to follow.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverCause {
+    /// The active host stopped sending heartbeats
+    HeartbeatMiss,
+    /// Operator action (admin panel / API)
+    #[default]
+    Manual,
+    /// OSC trigger from show control
+    Osc,
+    /// MIDI trigger note
+    Midi,
+    /// The active host was overloaded
+    HostLoad,
+    /// Automatic return to the recovered primary (`switch_back_policy = "auto"`)
+    SwitchBack,
+    /// Another host also claimed Primary and outranked this one
+    SplitBrain,
+}
+
+impl FailoverCause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HeartbeatMiss => "heartbeat_miss",
+            Self::Manual => "manual",
+            Self::Osc => "osc",
+            Self::Midi => "midi",
+            Self::HostLoad => "host_load",
+            Self::SwitchBack => "switch_back",
+            Self::SplitBrain => "split_brain",
+        }
+    }
+}
+
+/// The host clients should follow among the live `(host_id, priority)`
+/// pairs: the lowest priority, ties going to the lower host_id.
+pub fn preferred_host(alive: &[(u8, u8)]) -> Option<u8> {
+    alive.iter().min_by_key(|&&(id, priority)| (priority, id)).map(|&(id, _)| id)
+}
+
+/// Decide whether a client on `current_active` switches hosts. Returns
+/// the host to switch to, if any: nothing while the active host is alive,
+/// otherwise the `preferred_host` among the live ones.
+pub fn switch_target(current_active: u8, alive: &[(u8, u8)]) -> Option<u8> {
+    if alive.iter().any(|&(id, _)| id == current_active) {
+        return None;
+    }
+    preferred_host(alive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_stay_put_then_switch_to_the_best_priority() {
+        let alive = [(1, 30), (2, 10), (3, 10)];
+        assert_eq!(preferred_host(&alive), Some(2), "ties go to the lower host_id");
+        assert_eq!(switch_target(1, &alive), None, "a live active host is kept");
+        assert_eq!(switch_target(4, &alive), Some(2));
+        assert_eq!(switch_target(1, &[]), None);
+    }
+}
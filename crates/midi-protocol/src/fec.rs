@@ -0,0 +1,270 @@
+/// XOR forward error correction for the MIDI data stream.
+///
+/// With `network.fec_group_size = K` the host sends one `FecParityPacket`
+/// after every K data packets. The parity is the XOR of the K packets'
+/// wire bytes (after sealing, if the stream is encrypted), so a receiver
+/// that got K-1 of them can XOR the parity with the survivors and get the
+/// missing packet back byte for byte. Losing two or more packets of one
+/// group leaves it unrecoverable; bursts longer than a group need a
+/// smaller K (more parity overhead) or the journal to catch up.
+///
+/// Groups are runs of consecutive sequence numbers. If the host skips a
+/// sequence the encoder starts a new group, so parity always describes
+/// packets that were actually sent.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::packets::{FecParityPacket, MidiDataPacket, MAGIC_MIDI};
+
+/// Received packets remembered for recovery: more than any group can span
+const DECODER_WINDOW: usize = 512;
+
+fn xor_into(parity: &mut Vec<u8>, bytes: &[u8]) {
+    if parity.len() < bytes.len() {
+        parity.resize(bytes.len(), 0);
+    }
+    for (p, b) in parity.iter_mut().zip(bytes) {
+        *p ^= b;
+    }
+}
+
+/// (host_id, sequence) of a data packet on the wire, readable even when
+/// the body is sealed.
+fn wire_id(wire: &[u8]) -> Option<(u8, u16)> {
+    if wire.len() < MidiDataPacket::HEADER_SIZE || wire[0..4] != MAGIC_MIDI {
+        return None;
+    }
+    Some((wire[14], u16::from_be_bytes([wire[4], wire[5]])))
+}
+
+/// Host side: accumulates parity over each group of sent packets.
+#[derive(Debug, Clone)]
+pub struct FecEncoder {
+    group_size: u8,
+    base_sequence: u16,
+    count: u8,
+    length_xor: u16,
+    parity: Vec<u8>,
+}
+
+impl FecEncoder {
+    /// None for a group size below 2 (FEC off)
+    pub fn new(group_size: u8) -> Option<Self> {
+        (group_size >= 2).then(|| Self {
+            group_size,
+            base_sequence: 0,
+            count: 0,
+            length_xor: 0,
+            parity: Vec::new(),
+        })
+    }
+
+    /// Add a sent data packet. Returns the parity packet once the group is
+    /// complete.
+    pub fn push(&mut self, wire: &[u8]) -> Option<FecParityPacket> {
+        let (host_id, sequence) = wire_id(wire)?;
+        if self.count > 0 && sequence != self.base_sequence.wrapping_add(self.count as u16) {
+            self.count = 0;
+        }
+        if self.count == 0 {
+            self.base_sequence = sequence;
+            self.length_xor = 0;
+            self.parity.clear();
+        }
+
+        xor_into(&mut self.parity, wire);
+        self.length_xor ^= wire.len() as u16;
+        self.count += 1;
+
+        if self.count < self.group_size {
+            return None;
+        }
+        self.count = 0;
+        Some(FecParityPacket {
+            host_id,
+            base_sequence: self.base_sequence,
+            group_size: self.group_size,
+            length_xor: self.length_xor,
+            parity: std::mem::take(&mut self.parity),
+        })
+    }
+}
+
+/// What a parity packet did for its group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// Every packet of the group arrived
+    Complete,
+    /// The one missing packet, as its wire bytes
+    Recovered(Vec<u8>),
+    /// Too many packets of the group were lost
+    Unrecoverable { missing: usize },
+}
+
+/// Client side: remembers recent data packets and rebuilds a lost one
+/// when its group's parity arrives.
+#[derive(Debug, Default)]
+pub struct FecDecoder {
+    received: HashMap<(u8, u16), Vec<u8>>,
+    order: VecDeque<(u8, u16)>,
+}
+
+impl FecDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a received data packet (wire bytes).
+    pub fn record(&mut self, wire: &[u8]) {
+        let Some(id) = wire_id(wire) else {
+            return;
+        };
+        if self.received.insert(id, wire.to_vec()).is_none() {
+            self.order.push_back(id);
+        }
+        while self.order.len() > DECODER_WINDOW {
+            if let Some(old) = self.order.pop_front() {
+                self.received.remove(&old);
+            }
+        }
+    }
+
+    /// Apply a parity packet. A recovered packet is also recorded, so a
+    /// duplicated parity doesn't recover it twice.
+    pub fn recover(&mut self, parity: &FecParityPacket) -> Recovery {
+        let mut bytes = parity.parity.clone();
+        let mut length = parity.length_xor;
+        let mut missing = Vec::new();
+        for i in 0..parity.group_size as u16 {
+            let sequence = parity.base_sequence.wrapping_add(i);
+            match self.received.get(&(parity.host_id, sequence)) {
+                Some(wire) => {
+                    xor_into(&mut bytes, wire);
+                    length ^= wire.len() as u16;
+                }
+                None => missing.push(sequence),
+            }
+        }
+
+        match missing.as_slice() {
+            [] => Recovery::Complete,
+            [sequence] => {
+                bytes.truncate(length as usize);
+                // A corrupt or mismatched parity rebuilds garbage
+                if wire_id(&bytes) != Some((parity.host_id, *sequence)) {
+                    return Recovery::Unrecoverable { missing: 1 };
+                }
+                self.record(&bytes);
+                Recovery::Recovered(bytes)
+            }
+            _ => Recovery::Unrecoverable { missing: missing.len() },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUP: u8 = 4;
+
+    /// Packets of varying length, as a wireless bridge would see them
+    fn wire(sequence: u16) -> Vec<u8> {
+        let mut buf = Vec::new();
+        MidiDataPacket {
+            sequence,
+            timestamp_us: 1_000 + sequence as u64,
+            host_id: 1,
+            midi_data: (0..=(sequence % 5) as u8).flat_map(|n| [0x90, 60 + n, 100]).collect(),
+            journal: sequence.is_multiple_of(3).then(|| vec![0x00, 0x00]),
+        }
+        .serialize(&mut buf);
+        buf
+    }
+
+    fn send(
+        encoder: &mut FecEncoder,
+        sequences: impl IntoIterator<Item = u16>,
+    ) -> Vec<(Vec<u8>, Option<FecParityPacket>)> {
+        sequences
+            .into_iter()
+            .map(|s| {
+                let w = wire(s);
+                let parity = encoder.push(&w);
+                (w, parity)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_loss_per_group_is_fully_reconstructed() {
+        let mut encoder = FecEncoder::new(GROUP).unwrap();
+        let mut decoder = FecDecoder::new();
+        let mut delivered = Vec::new();
+
+        // Four groups; the second one spans the sequence wrap
+        for (i, (w, parity)) in send(&mut encoder, (65530..=65535).chain(0..10)).into_iter().enumerate() {
+            // Drop the third packet of every group
+            if i % GROUP as usize != 2 {
+                decoder.record(&w);
+                delivered.push(w);
+            }
+            if let Some(parity) = parity {
+                // The parity itself goes over the wire too
+                let mut buf = Vec::new();
+                parity.serialize(&mut buf);
+                let parity = FecParityPacket::deserialize(&buf).unwrap();
+                match decoder.recover(&parity) {
+                    Recovery::Recovered(bytes) => delivered.push(bytes),
+                    other => panic!("group at {} not recovered: {:?}", parity.base_sequence, other),
+                }
+            }
+        }
+
+        let mut sequences: Vec<u16> = delivered
+            .iter()
+            .map(|w| MidiDataPacket::deserialize(w).unwrap().sequence)
+            .collect();
+        sequences.sort_by_key(|&s| s.wrapping_add(6));
+        let expected: Vec<u16> = (65530..=65535).chain(0..10).collect();
+        assert_eq!(sequences, expected);
+        for w in &delivered {
+            let seq = MidiDataPacket::deserialize(w).unwrap().sequence;
+            assert_eq!(*w, wire(seq), "seq {seq} differs from what was sent");
+        }
+    }
+
+    #[test]
+    fn two_losses_in_a_group_cannot_be_reconstructed() {
+        let mut encoder = FecEncoder::new(GROUP).unwrap();
+        let mut decoder = FecDecoder::new();
+        let mut parity = None;
+        for (i, (w, p)) in send(&mut encoder, 100..104).into_iter().enumerate() {
+            if i != 1 && i != 2 {
+                decoder.record(&w);
+            }
+            parity = p;
+        }
+        let parity = parity.expect("group of four completes at the fourth packet");
+        assert_eq!(decoder.recover(&parity), Recovery::Unrecoverable { missing: 2 });
+    }
+
+    #[test]
+    fn complete_group_needs_no_recovery_and_skips_restart_groups() {
+        let mut encoder = FecEncoder::new(GROUP).unwrap();
+        let mut decoder = FecDecoder::new();
+        for (w, _) in send(&mut encoder, 0..2) {
+            decoder.record(&w);
+        }
+        // Sequence jumps: the group restarts at 10
+        let out = send(&mut encoder, 10..14);
+        for (w, _) in &out {
+            decoder.record(w);
+        }
+        let parity = out.last().unwrap().1.clone().unwrap();
+        assert_eq!(parity.base_sequence, 10);
+        assert_eq!(decoder.recover(&parity), Recovery::Complete);
+
+        assert!(FecEncoder::new(1).is_none());
+    }
+}
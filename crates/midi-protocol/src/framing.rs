@@ -0,0 +1,147 @@
+/// MIDI 1.0 byte stream → complete messages.
+///
+/// Virtual devices and drivers hand back raw byte chunks that may hold
+/// several messages, half a message, or running-status data. `MidiFramer`
+/// reassembles them into complete messages with an explicit status byte.
+/// Data bytes without a status reuse the last channel status (running
+/// status), an incomplete trailing message is kept and completed by the
+/// next `push()` call, and Real-Time bytes (0xF8..=0xFF) may interleave
+/// anywhere, even mid-message.
+
+/// Upper bound on a buffered SysEx message; anything larger is discarded.
+pub const MAX_SYSEX_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Default)]
+pub struct MidiFramer {
+    /// Last Channel Voice status, reused for running-status data bytes
+    running_status: Option<u8>,
+    /// Partial message carried over between `push()` calls
+    pending: Vec<u8>,
+    /// Oversize SysEx messages thrown away so far
+    discarded_sysex: u64,
+}
+
+impl MidiFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed raw bytes; returns the messages they complete, in order.
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        self.push_each(data, |msg| out.push(msg.to_vec()));
+        out
+    }
+
+    /// Feed raw bytes, calling `f` with each message they complete, in
+    /// order. Nothing is allocated per message.
+    pub fn push_each(&mut self, data: &[u8], mut f: impl FnMut(&[u8])) {
+        for &b in data {
+            self.push_byte(b, &mut f);
+        }
+    }
+
+    /// Bytes currently waiting for the rest of their message.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Number of SysEx messages discarded for exceeding `MAX_SYSEX_LEN`.
+    pub fn discarded_sysex(&self) -> u64 {
+        self.discarded_sysex
+    }
+
+    fn push_byte(&mut self, b: u8, out: &mut impl FnMut(&[u8])) {
+        match b {
+            // System Real-Time; never disturbs a message in progress
+            0xF8..=0xFF => out(&[b]),
+            0xF0 => {
+                self.running_status = None;
+                self.pending.clear();
+                self.pending.push(b);
+            }
+            0xF7 => {
+                if self.pending.first() == Some(&0xF0) {
+                    self.pending.push(b);
+                    out(&self.pending);
+                }
+                self.pending.clear();
+            }
+            // Channel Voice status
+            0x80..=0xEF => {
+                self.running_status = Some(b);
+                self.pending.clear();
+                self.pending.push(b);
+            }
+            // System Common cancels running status
+            0xF1..=0xF6 => {
+                self.running_status = None;
+                self.pending.clear();
+                self.pending.push(b);
+                self.flush_if_complete(out);
+            }
+            // Data byte
+            _ => {
+                if self.pending.is_empty() {
+                    match self.running_status {
+                        Some(status) => self.pending.push(status),
+                        None => return, // stray data byte, no status to attach to
+                    }
+                }
+                if self.pending[0] == 0xF0 {
+                    if self.pending.len() >= MAX_SYSEX_LEN {
+                        self.discarded_sysex += 1;
+                        self.pending.clear();
+                        return;
+                    }
+                    self.pending.push(b);
+                    return;
+                }
+                self.pending.push(b);
+                self.flush_if_complete(out);
+            }
+        }
+    }
+
+    fn flush_if_complete(&mut self, out: &mut impl FnMut(&[u8])) {
+        if self.pending.len() >= message_len(self.pending[0]) {
+            out(&self.pending);
+            self.pending.clear();
+        }
+    }
+}
+
+/// Total length of a non-SysEx message (status + data bytes).
+fn message_len(status: u8) -> usize {
+    match status {
+        0xC0..=0xDF | 0xF1 | 0xF3 => 2,
+        0x80..=0xEF | 0xF2 => 3,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concatenated_messages_are_split() {
+        let mut framer = MidiFramer::new();
+        let out = framer.push(&[0x90, 60, 100, 0xB0, 7, 90, 0xF0, 0x7E, 0x7F, 0xF7, 0xC1, 5]);
+        assert_eq!(
+            out,
+            vec![vec![0x90, 60, 100], vec![0xB0, 7, 90], vec![0xF0, 0x7E, 0x7F, 0xF7], vec![0xC1, 5]]
+        );
+        assert_eq!(framer.pending_len(), 0);
+    }
+
+    #[test]
+    fn partial_and_running_status_messages_are_completed() {
+        let mut framer = MidiFramer::new();
+        assert_eq!(framer.push(&[0x91, 36]), Vec::<Vec<u8>>::new());
+        assert_eq!(framer.pending_len(), 2);
+        // Rest of the note, a running-status note, and half of the next
+        assert_eq!(framer.push(&[127, 38, 0, 40]), vec![vec![0x91, 36, 127], vec![0x91, 38, 0]]);
+        assert_eq!(framer.push(&[0xF8, 64]), vec![vec![0xF8], vec![0x91, 40, 64]]);
+    }
+}
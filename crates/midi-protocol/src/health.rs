@@ -51,13 +51,42 @@ pub struct ClientHealthSnapshot {
     /// Git hash of this client binary (compiled in)
     #[serde(default)]
     pub client_git_hash: String,
+    /// Rolling p99 of virtual device send time in microseconds.
+    /// A spike here points at the OS MIDI driver rather than the network.
+    #[serde(default)]
+    pub device_send_latency_us: u32,
+    /// Rolling p99 of data packet one-way latency above its recent
+    /// minimum, in microseconds. Independent of host/client clock offset,
+    /// so it measures network jitter without NTP.
+    #[serde(default)]
+    pub relative_latency_us: u32,
+    /// Packets the jitter buffer reordered back into timestamp order
+    #[serde(default)]
+    pub packets_reordered: u64,
+    /// Packets dropped for arriving after their jitter buffer deadline
+    #[serde(default)]
+    pub packets_late_dropped: u64,
+    /// Packets dropped for failing decryption/authentication (wrong PSK,
+    /// tampering, or plaintext on an encrypted stream)
+    #[serde(default)]
+    pub packets_auth_failed: u64,
+    /// Lost data packets rebuilt from FEC parity packets
+    #[serde(default)]
+    pub packets_fec_recovered: u64,
+    /// Largest sequence gap (data packets missing in a row) since startup
+    #[serde(default)]
+    pub max_sequence_gap: u64,
+    /// Data packets that arrived behind a newer one, since startup
+    #[serde(default)]
+    pub packets_out_of_order: u64,
 }
 
 /// High-level connection state for the tray icon color.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ConnectionState {
-    /// No hosts discovered, never connected
+    /// No hosts discovered and never connected, or every host that had
+    /// been heard has stopped heartbeating
     Disconnected,
     /// mDNS browsing, waiting for first host
     Discovering,
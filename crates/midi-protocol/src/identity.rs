@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 /// Captured identity of a physical MIDI controller.
 /// This is what gets cloned on each client to make the virtual device
 /// appear identical to the original controller.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DeviceIdentity {
     /// Display name (e.g., "Akai APC40")
     pub name: String,
@@ -42,16 +42,7 @@ impl DeviceIdentity {
     /// Universal Device Inquiry response:
     /// F0 7E <device_id> 06 02 <mfr_id> <family_lsb> <family_msb> <model_lsb> <model_msb> <ver1> <ver2> <ver3> <ver4> F7
     pub fn sysex_identity_reply(&self) -> Vec<u8> {
-        let mut msg = vec![0xF0, 0x7E, 0x7F, 0x06, 0x02];
-        // Append identity bytes (manufacturer + family + model + version)
-        for &b in &self.sysex_identity {
-            if b == 0xF7 {
-                break; // stop before end-of-sysex if embedded
-            }
-            msg.push(b);
-        }
-        msg.push(0xF7);
-        msg
+        identity_reply(&self.sysex_identity)
     }
 
     /// Check if this is a valid (non-default) identity
@@ -60,6 +51,44 @@ impl DeviceIdentity {
     }
 }
 
+/// Identity Reply for the given identity bytes, e.g. a configured override
+/// for a controller whose captured reply is missing or wrong.
+pub fn identity_reply(sysex_identity: &[u8; 15]) -> Vec<u8> {
+    let mut msg = vec![0xF0, 0x7E, 0x7F, 0x06, 0x02];
+    // Append identity bytes (manufacturer + family + model + version)
+    for &b in sysex_identity {
+        if b == 0xF7 {
+            break; // stop before end-of-sysex if embedded
+        }
+        msg.push(b);
+    }
+    msg.push(0xF7);
+    msg
+}
+
+/// Check whether `msg` is a Universal Identity Request
+/// (F0 7E <device_id> 06 01 F7), as sent by DAWs probing a port.
+pub fn is_identity_request(msg: &[u8]) -> bool {
+    matches!(msg, [0xF0, 0x7E, device_id, 0x06, 0x01, 0xF7] if *device_id <= 0x7F)
+}
+
+/// Parse a space-separated hex string (e.g. "47 73 00 19 00 01 00 00 00")
+/// into a `sysex_identity` payload. Returns None if it is empty, longer than
+/// 15 bytes, or contains non-7-bit data bytes.
+pub fn parse_sysex_identity(hex: &str) -> Option<[u8; 15]> {
+    let mut out = [0u8; 15];
+    let mut len = 0;
+    for tok in hex.split_whitespace() {
+        let b = u8::from_str_radix(tok.trim_start_matches("0x"), 16).ok()?;
+        if b > 0x7F || len == out.len() {
+            return None;
+        }
+        out[len] = b;
+        len += 1;
+    }
+    (len > 0).then_some(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,4 +125,32 @@ mod tests {
         assert_eq!(*reply.last().unwrap(), 0xF7); // SysEx end
         assert!(id.is_valid());
     }
+
+    #[test]
+    fn test_identity_request_detection() {
+        assert!(is_identity_request(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]));
+        assert!(is_identity_request(&[0xF0, 0x7E, 0x00, 0x06, 0x01, 0xF7]));
+        // Identity Reply is not a request
+        assert!(!is_identity_request(&[0xF0, 0x7E, 0x7F, 0x06, 0x02, 0xF7]));
+        // Note On is not a request
+        assert!(!is_identity_request(&[0x90, 60, 100]));
+    }
+
+    #[test]
+    fn test_parse_sysex_identity() {
+        let parsed = parse_sysex_identity("47 73 00 19 00 01 00 00 00").unwrap();
+        assert_eq!(&parsed[..9], &[0x47, 0x73, 0x00, 0x19, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(&parsed[9..], &[0; 6]);
+
+        assert!(parse_sysex_identity("").is_none());
+        assert!(parse_sysex_identity("47 F7").is_none());
+        assert!(parse_sysex_identity("zz").is_none());
+        assert!(parse_sysex_identity(&"01 ".repeat(16)).is_none());
+    }
+
+    #[test]
+    fn test_identity_reply_from_override() {
+        let bytes = parse_sysex_identity("47 73").unwrap();
+        assert_eq!(identity_reply(&bytes), vec![0xF0, 0x7E, 0x7F, 0x06, 0x02, 0x47, 0x73, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xF7]);
+    }
 }
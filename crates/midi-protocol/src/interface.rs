@@ -0,0 +1,223 @@
+/// Network interface resolution for multicast sockets, shared by the host
+/// (data/heartbeat sockets), the client (receive sockets) and the admin
+/// panel (sniffers).
+///
+/// `network.interface` may be an interface name ("eth0") or an IPv4 address.
+/// IPv6 groups select the interface by index, which is looked up from the
+/// name; a literal address or "any" leaves the choice to the OS.
+/// Interfaces get renamed (eth0 → enp3s0) and boxes get re-cabled, so an
+/// unknown name does not stop the host: it logs the interfaces that do exist,
+/// falls back according to `network.interface_fallback`, and keeps the
+/// misconfiguration as `ResolvedInterface::error` for status reporting.
+
+use std::net::Ipv4Addr;
+
+use serde::Deserialize;
+
+use crate::multicast::MulticastInterface;
+
+/// What to use when the configured interface can't be found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceFallback {
+    /// Let the OS pick (0.0.0.0)
+    #[default]
+    Any,
+    /// The address of the interface holding the default route
+    DefaultRoute,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedInterface {
+    pub addr: Ipv4Addr,
+    /// Interface index for IPv6 groups (0 = OS picks)
+    pub index: u32,
+    /// Set when the configured interface was not found and a fallback is in use
+    pub error: Option<String>,
+}
+
+/// Resolve `name` against the `available` (name, IPv4) interfaces.
+/// `default_route` is only consulted for `InterfaceFallback::DefaultRoute`.
+pub fn resolve_interface(
+    name: &str,
+    fallback: InterfaceFallback,
+    available: &[(String, Ipv4Addr)],
+    default_route: impl FnOnce() -> Option<Ipv4Addr>,
+) -> ResolvedInterface {
+    let name = name.trim();
+    if name.is_empty() || name == "any" {
+        return ResolvedInterface { addr: Ipv4Addr::UNSPECIFIED, index: 0, error: None };
+    }
+    if let Ok(addr) = name.parse::<Ipv4Addr>() {
+        return ResolvedInterface { addr, index: 0, error: None };
+    }
+    if let Some((_, addr)) = available.iter().find(|(n, _)| n == name) {
+        return ResolvedInterface { addr: *addr, index: 0, error: None };
+    }
+
+    let addr = match fallback {
+        InterfaceFallback::Any => Ipv4Addr::UNSPECIFIED,
+        InterfaceFallback::DefaultRoute => default_route().unwrap_or(Ipv4Addr::UNSPECIFIED),
+    };
+    let names: Vec<String> = available
+        .iter()
+        .map(|(n, a)| format!("{} ({})", n, a))
+        .collect();
+    let listing = if names.is_empty() {
+        "none found".to_string()
+    } else {
+        names.join(", ")
+    };
+    ResolvedInterface {
+        addr,
+        index: 0,
+        error: Some(format!(
+            "interface '{}' not found (available: {}); using {}",
+            name, listing, addr
+        )),
+    }
+}
+
+/// Resolve the configured interface on this machine. Callers log
+/// `ResolvedInterface::error` when it is set.
+pub fn resolve(name: &str, fallback: InterfaceFallback) -> ResolvedInterface {
+    let mut resolved = resolve_interface(name, fallback, &list_interfaces(), default_route_ipv4);
+    if resolved.error.is_none() {
+        resolved.index = interface_index(name.trim());
+    }
+    resolved
+}
+
+impl ResolvedInterface {
+    /// The interface for multicast sockets of either address family
+    pub fn multicast(&self) -> MulticastInterface {
+        MulticastInterface { v4: self.addr, v6_index: self.index }
+    }
+}
+
+/// Index of the interface called `name`, 0 if there is none (or `name` is
+/// an address).
+fn interface_index(name: &str) -> u32 {
+    #[cfg(target_os = "linux")]
+    {
+        if name.is_empty() || name.contains('/') {
+            return 0;
+        }
+        std::fs::read_to_string(format!("/sys/class/net/{}/ifindex", name))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = name;
+        0
+    }
+}
+
+/// IPv4 interfaces on this machine as (name, address).
+pub fn list_interfaces() -> Vec<(String, Ipv4Addr)> {
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("ip")
+            .args(["-4", "-o", "addr", "show"])
+            .output()
+            .map(|o| parse_ip_addr_output(&String::from_utf8_lossy(&o.stdout)))
+            .unwrap_or_default()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Parse `ip -4 -o addr show` lines: "2: eth0    inet 192.168.2.23/24 brd ...".
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_ip_addr_output(stdout: &str) -> Vec<(String, Ipv4Addr)> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.nth(1)?;
+            let addr = parts
+                .skip_while(|p| *p != "inet")
+                .nth(1)?
+                .split('/')
+                .next()?
+                .parse()
+                .ok()?;
+            Some((name.to_string(), addr))
+        })
+        .collect()
+}
+
+/// Local address the OS would use for the default route. Connecting a UDP
+/// socket only selects a route; nothing is sent.
+fn default_route_ipv4() -> Option<Ipv4Addr> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available() -> Vec<(String, Ipv4Addr)> {
+        vec![
+            ("lo".to_string(), Ipv4Addr::LOCALHOST),
+            ("enp3s0".to_string(), Ipv4Addr::new(192, 168, 1, 20)),
+        ]
+    }
+
+    #[test]
+    fn known_name_and_literal_address_resolve() {
+        let r = resolve_interface("enp3s0", InterfaceFallback::Any, &available(), || None);
+        assert_eq!(r, ResolvedInterface { addr: Ipv4Addr::new(192, 168, 1, 20), index: 0, error: None });
+
+        let r = resolve_interface("10.0.0.5", InterfaceFallback::Any, &[], || None);
+        assert_eq!(r.addr, Ipv4Addr::new(10, 0, 0, 5));
+        assert!(r.error.is_none());
+    }
+
+    #[test]
+    fn unknown_interface_falls_back_with_descriptive_error() {
+        let r = resolve_interface("eth0", InterfaceFallback::Any, &available(), || None);
+        assert_eq!(r.addr, Ipv4Addr::UNSPECIFIED);
+        let err = r.error.unwrap();
+        assert!(err.contains("'eth0' not found"), "{}", err);
+        assert!(err.contains("enp3s0 (192.168.1.20)"), "{}", err);
+        assert!(err.contains("using 0.0.0.0"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_interface_can_fall_back_to_default_route() {
+        let route = Ipv4Addr::new(192, 168, 1, 20);
+        let r = resolve_interface("eth0", InterfaceFallback::DefaultRoute, &available(), || Some(route));
+        assert_eq!(r.addr, route);
+        assert!(r.error.unwrap().contains("using 192.168.1.20"));
+
+        // No default route either: still no crash, OS picks
+        let r = resolve_interface("eth0", InterfaceFallback::DefaultRoute, &[], || None);
+        assert_eq!(r.addr, Ipv4Addr::UNSPECIFIED);
+        assert!(r.error.unwrap().contains("none found"));
+    }
+
+    #[test]
+    fn parses_ip_addr_listing() {
+        let out = "1: lo    inet 127.0.0.1/8 scope host lo\\       valid_lft forever\n\
+                   2: enp3s0    inet 192.168.1.20/24 brd 192.168.1.255 scope global enp3s0\n";
+        assert_eq!(parse_ip_addr_output(out), available());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn loopback_has_an_interface_index() {
+        assert_eq!(interface_index("lo"), 1);
+        assert_eq!(interface_index("127.0.0.1"), 0);
+        assert_eq!(interface_index("no-such-if0"), 0);
+    }
+}
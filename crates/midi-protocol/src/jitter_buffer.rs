@@ -0,0 +1,206 @@
+/// Playout buffer for incoming MIDI data packets.
+///
+/// Packets are held in a queue ordered by their host `timestamp_us` and
+/// released `delay_us` after the moment they would have arrived over an
+/// ideal link, so packets reordered on the wire come out in send order.
+///
+/// Host and client clocks are unrelated, so the buffer learns the mapping:
+/// `offset` is the smallest transit (local arrival − host timestamp) seen,
+/// i.e. the fastest path through the network. Each packet's deadline is
+/// `timestamp + offset + delay`. The offset is allowed to creep up by
+/// `DRIFT_PPM` so a host clock running slow doesn't shrink the buffer to
+/// nothing, and it is re-learned after `RESYNC_AFTER_DROPS` consecutive
+/// late drops (the path got slower for good, or the host clock stepped).
+///
+/// A packet whose deadline has already passed by more than one buffer
+/// width is dropped: its notes would land too far off the beat to be
+/// worth playing. A packet that is late by less than that is released
+/// immediately, so the window a packet can still be played in runs to one
+/// buffer width past its deadline.
+///
+/// Shared by the client's receiver and the load test's jitter run, so the
+/// load test measures the same buffer the client plays through.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// How fast the learned offset may grow, in parts per million of elapsed
+/// time. Covers host/client crystal drift with a wide margin.
+const DRIFT_PPM: u64 = 1_000;
+
+/// Consecutive late drops after which the offset is re-learned.
+const RESYNC_AFTER_DROPS: u32 = 8;
+
+/// What happened to a pushed packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admit {
+    /// Queued for playout. `reordered` is set when a packet with a later
+    /// timestamp had already arrived; `late` when its deadline had already
+    /// passed, so it is released at once.
+    Queued { reordered: bool, late: bool },
+    /// Its deadline passed more than a buffer width ago
+    LateDropped,
+}
+
+pub struct JitterBuffer<T> {
+    delay_us: u64,
+    /// Reference point for local microsecond readings
+    epoch: Instant,
+    /// Learned (local − host) offset and the local time it was last lowered
+    offset: Option<(i64, i64)>,
+    /// Keyed on (host timestamp, arrival order) so equal timestamps keep
+    /// their arrival order
+    queue: BTreeMap<(u64, u64), T>,
+    arrivals: u64,
+    newest_timestamp_us: Option<u64>,
+    consecutive_drops: u32,
+}
+
+impl<T> JitterBuffer<T> {
+    pub fn new(delay_us: u64, now: Instant) -> Self {
+        Self {
+            delay_us,
+            epoch: now,
+            offset: None,
+            queue: BTreeMap::new(),
+            arrivals: 0,
+            newest_timestamp_us: None,
+            consecutive_drops: 0,
+        }
+    }
+
+    fn local_us(&self, now: Instant) -> i64 {
+        now.saturating_duration_since(self.epoch).as_micros() as i64
+    }
+
+    /// Add a packet that arrived at `now`.
+    pub fn push(&mut self, timestamp_us: u64, item: T, now: Instant) -> Admit {
+        let local = self.local_us(now);
+        let transit = local - timestamp_us as i64;
+
+        let offset = match self.offset {
+            Some((offset, since)) => {
+                let allowance = ((local - since).max(0) as u64 * DRIFT_PPM / 1_000_000) as i64;
+                offset + allowance
+            }
+            None => transit,
+        };
+
+        if transit - offset > 2 * self.delay_us as i64 {
+            self.consecutive_drops += 1;
+            if self.consecutive_drops >= RESYNC_AFTER_DROPS {
+                self.resync();
+            }
+            return Admit::LateDropped;
+        }
+        self.consecutive_drops = 0;
+        self.offset = Some((offset.min(transit), local));
+        let late = transit - offset > self.delay_us as i64;
+
+        let reordered = self.newest_timestamp_us.is_some_and(|newest| timestamp_us < newest);
+        self.newest_timestamp_us = Some(self.newest_timestamp_us.map_or(timestamp_us, |n| n.max(timestamp_us)));
+
+        self.queue.insert((timestamp_us, self.arrivals), item);
+        self.arrivals += 1;
+        Admit::Queued { reordered, late }
+    }
+
+    /// How long past its deadline a packet may arrive and still be played.
+    pub fn late_window(&self) -> Duration {
+        Duration::from_micros(self.delay_us)
+    }
+
+    /// When the earliest queued packet is due (None if the queue is empty).
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let (&(timestamp_us, _), _) = self.queue.first_key_value()?;
+        let (offset, _) = self.offset?;
+        let due = (timestamp_us as i64 + offset + self.delay_us as i64).max(0);
+        Some(self.epoch + Duration::from_micros(due as u64))
+    }
+
+    /// Take the next packet if its deadline has been reached.
+    pub fn pop_due(&mut self, now: Instant) -> Option<T> {
+        if self.next_deadline()? > now {
+            return None;
+        }
+        self.queue.pop_first().map(|(_, item)| item)
+    }
+
+    /// Empty the queue in timestamp order, e.g. before switching hosts.
+    pub fn drain(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.queue).into_values().collect()
+    }
+
+    /// Forget the learned clock offset; the next packet re-establishes it.
+    pub fn resync(&mut self) {
+        self.offset = None;
+        self.newest_timestamp_us = None;
+        self.consecutive_drops = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: u64 = 2_000;
+
+    fn at(start: Instant, us: u64) -> Instant {
+        start + Duration::from_micros(us)
+    }
+
+    #[test]
+    fn reordered_arrivals_play_out_in_timestamp_order() {
+        let start = Instant::now();
+        let mut buffer = JitterBuffer::new(DELAY, start);
+
+        // Host sends at 0, 100, 200us; the middle packet is overtaken
+        assert_eq!(buffer.push(1_000, "a", at(start, 50)), Admit::Queued { reordered: false, late: false });
+        assert_eq!(buffer.push(1_200, "c", at(start, 250)), Admit::Queued { reordered: false, late: false });
+        assert_eq!(buffer.push(1_100, "b", at(start, 400)), Admit::Queued { reordered: true, late: false });
+
+        // Nothing is due before the buffer delay has elapsed
+        assert_eq!(buffer.pop_due(at(start, 1_000)), None);
+        assert_eq!(buffer.next_deadline(), Some(at(start, 50 + DELAY)));
+
+        let due = at(start, 250 + DELAY);
+        let mut played = Vec::new();
+        while let Some(item) = buffer.pop_due(due) {
+            played.push(item);
+        }
+        assert_eq!(played, ["a", "b", "c"]);
+        assert_eq!(buffer.next_deadline(), None);
+    }
+
+    #[test]
+    fn packets_later_than_one_buffer_width_are_dropped() {
+        let start = Instant::now();
+        let mut buffer = JitterBuffer::new(DELAY, start);
+        buffer.push(0, 1, at(start, 0));
+
+        // Deadline passed, but by less than a buffer width: play it now
+        assert_eq!(buffer.push(100, 2, at(start, 100 + DELAY + 1_500)), Admit::Queued { reordered: false, late: true });
+        // Deadline passed by more than a buffer width
+        assert_eq!(buffer.push(200, 3, at(start, 200 + 2 * DELAY + 100)), Admit::LateDropped);
+
+        let now = at(start, 200 + 2 * DELAY + 100);
+        assert_eq!(buffer.pop_due(now), Some(1));
+        assert_eq!(buffer.pop_due(now), Some(2));
+        assert_eq!(buffer.pop_due(now), None);
+    }
+
+    #[test]
+    fn sustained_lateness_relearns_the_offset() {
+        let start = Instant::now();
+        let mut buffer = JitterBuffer::new(DELAY, start);
+        buffer.push(0, 0, at(start, 0));
+
+        // The path suddenly takes 20ms longer and stays that way
+        let mut admitted = Vec::new();
+        for i in 1..=RESYNC_AFTER_DROPS as u64 + 1 {
+            admitted.push(buffer.push(i * 100, i, at(start, i * 100 + 20_000)));
+        }
+        assert!(admitted[..RESYNC_AFTER_DROPS as usize].iter().all(|a| *a == Admit::LateDropped));
+        assert_eq!(admitted.last(), Some(&Admit::Queued { reordered: false, late: false }));
+    }
+}
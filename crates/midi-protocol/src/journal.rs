@@ -16,15 +16,35 @@ use crate::midi_state::{MidiState, NUM_CHANNELS, NUM_CCS, NUM_NOTES};
 ///   [program: 1 byte] — if flag set
 ///   [pitch_bend: 2 bytes] — if flag set (and not center)
 ///   [channel_pressure: 1 byte] — if flag set
+///   [pedal_held: variable] — released notes still held by sustain/sostenuto (v4)
+///   [sostenuto_latched: variable] — [count] + note numbers latched by CC66 (v4)
+///
+/// Receivers older than protocol v4 don't know the last two sections and
+/// would misread everything after them, so they are only emitted for v4
+/// peers; `encode_journal_for` folds pedal-held notes into the active notes
+/// for older ones, which keeps those notes sounding on reconciliation.
 
 const FLAG_HAS_NOTES: u8 = 0x01;
 const FLAG_HAS_CC: u8 = 0x02;
 const FLAG_HAS_PROGRAM: u8 = 0x04;
 const FLAG_HAS_PITCH_BEND: u8 = 0x08;
 const FLAG_HAS_PRESSURE: u8 = 0x10;
+const FLAG_HAS_PEDAL_HELD: u8 = 0x20;
+const FLAG_HAS_SOSTENUTO: u8 = 0x40;
+
+/// First protocol version whose journals carry pedal-held notes and
+/// sostenuto latches
+pub const JOURNAL_PEDAL_VERSION: u8 = 4;
 
 /// Encode the current MIDI state into a compact journal.
 pub fn encode_journal(state: &MidiState) -> Vec<u8> {
+    encode_journal_for(state, crate::PROTOCOL_VERSION)
+}
+
+/// Encode the current MIDI state into a journal that receivers speaking
+/// `peer_version` can decode.
+pub fn encode_journal_for(state: &MidiState, peer_version: u8) -> Vec<u8> {
+    let pedal_sections = peer_version >= JOURNAL_PEDAL_VERSION;
     let mut buf = Vec::with_capacity(256);
 
     // Channel mask: which channels have non-default state
@@ -46,9 +66,14 @@ pub fn encode_journal(state: &MidiState) -> Vec<u8> {
         let mut flags: u8 = 0;
 
         // Determine what state to encode
+        // Older peers get pedal-held notes as plain active notes
+        let sounding = |n: usize| match channel.notes[n] {
+            0 if !pedal_sections => channel.pedal_held[n],
+            vel => vel,
+        };
         let active_notes: Vec<(u8, u8)> = (0..NUM_NOTES)
-            .filter(|&n| channel.notes[n] > 0)
-            .map(|n| (n as u8, channel.notes[n]))
+            .filter(|&n| sounding(n) > 0)
+            .map(|n| (n as u8, sounding(n)))
             .collect();
 
         let non_zero_cc: Vec<(u8, u8)> = (0..NUM_CCS)
@@ -56,6 +81,16 @@ pub fn encode_journal(state: &MidiState) -> Vec<u8> {
             .map(|c| (c as u8, channel.cc[c]))
             .collect();
 
+        let pedal_held: Vec<(u8, u8)> = (0..NUM_NOTES)
+            .filter(|&n| channel.pedal_held[n] > 0)
+            .map(|n| (n as u8, channel.pedal_held[n]))
+            .collect();
+
+        let latched: Vec<u8> = (0..NUM_NOTES)
+            .filter(|&n| channel.sostenuto_latched[n])
+            .map(|n| n as u8)
+            .collect();
+
         if !active_notes.is_empty() {
             flags |= FLAG_HAS_NOTES;
         }
@@ -71,6 +106,12 @@ pub fn encode_journal(state: &MidiState) -> Vec<u8> {
         if channel.channel_pressure != 0 {
             flags |= FLAG_HAS_PRESSURE;
         }
+        if pedal_sections && !pedal_held.is_empty() {
+            flags |= FLAG_HAS_PEDAL_HELD;
+        }
+        if pedal_sections && !latched.is_empty() {
+            flags |= FLAG_HAS_SOSTENUTO;
+        }
 
         buf.push(flags);
 
@@ -103,6 +144,21 @@ pub fn encode_journal(state: &MidiState) -> Vec<u8> {
         if flags & FLAG_HAS_PRESSURE != 0 {
             buf.push(channel.channel_pressure);
         }
+
+        // Encode pedal-held notes: [count(1)] [note, velocity] pairs
+        if flags & FLAG_HAS_PEDAL_HELD != 0 {
+            buf.push(pedal_held.len() as u8);
+            for (note, vel) in &pedal_held {
+                buf.push(*note);
+                buf.push(*vel);
+            }
+        }
+
+        // Encode sostenuto latches: [count(1)] [note] list
+        if flags & FLAG_HAS_SOSTENUTO != 0 {
+            buf.push(latched.len() as u8);
+            buf.extend_from_slice(&latched);
+        }
     }
 
     buf
@@ -197,6 +253,44 @@ pub fn decode_journal(data: &[u8]) -> Option<MidiState> {
             state.channels[ch].channel_pressure = data[offset];
             offset += 1;
         }
+
+        // Decode pedal-held notes
+        if flags & FLAG_HAS_PEDAL_HELD != 0 {
+            if offset >= data.len() {
+                return None;
+            }
+            let count = data[offset] as usize;
+            offset += 1;
+            if offset + count * 2 > data.len() {
+                return None;
+            }
+            for i in 0..count {
+                let note = data[offset + i * 2] as usize;
+                let vel = data[offset + i * 2 + 1];
+                if note < NUM_NOTES {
+                    state.channels[ch].pedal_held[note] = vel;
+                }
+            }
+            offset += count * 2;
+        }
+
+        // Decode sostenuto latches
+        if flags & FLAG_HAS_SOSTENUTO != 0 {
+            if offset >= data.len() {
+                return None;
+            }
+            let count = data[offset] as usize;
+            offset += 1;
+            if offset + count > data.len() {
+                return None;
+            }
+            for &note in &data[offset..offset + count] {
+                if (note as usize) < NUM_NOTES {
+                    state.channels[ch].sostenuto_latched[note as usize] = true;
+                }
+            }
+            offset += count;
+        }
     }
 
     Some(state)
@@ -208,6 +302,8 @@ fn has_state(ch: &crate::midi_state::ChannelState) -> bool {
         || ch.program != 0
         || ch.pitch_bend != 8192
         || ch.channel_pressure != 0
+        || ch.pedal_held.iter().any(|&v| v > 0)
+        || ch.sostenuto_latched.iter().any(|&l| l)
 }
 
 #[cfg(test)]
@@ -1,13 +1,30 @@
+pub mod activity;
+pub mod client_command;
+pub mod clock;
+pub mod crypto;
+pub mod failover;
+pub mod fec;
+pub mod framing;
 pub mod health;
 pub mod identity;
+pub mod interface;
+pub mod jitter_buffer;
 pub mod journal;
 pub mod midi_state;
+pub mod mmc;
+pub mod multicast;
 pub mod packets;
 pub mod pipeline;
 pub mod ringbuf;
+pub mod sequence;
 
-/// Protocol version
-pub const PROTOCOL_VERSION: u8 = 1;
+/// Protocol version (2: heartbeats advertise the host's interval;
+/// 3: heartbeats carry the host's failover priority; 4: journals carry
+/// pedal-held notes and sostenuto latches)
+pub const PROTOCOL_VERSION: u8 = 4;
+
+/// Oldest protocol version this build still interoperates with
+pub const MIN_PROTOCOL_VERSION: u8 = 1;
 
 /// Build info (set by build.rs from git)
 pub const GIT_HASH: &str = env!("MIDINET_GIT_HASH");
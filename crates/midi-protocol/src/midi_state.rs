@@ -4,6 +4,10 @@ pub const NUM_CHANNELS: usize = 16;
 pub const NUM_NOTES: usize = 128;
 /// Number of MIDI CCs
 pub const NUM_CCS: usize = 128;
+/// Sustain (damper) pedal
+pub const CC_SUSTAIN: usize = 64;
+/// Sostenuto pedal
+pub const CC_SOSTENUTO: usize = 66;
 
 /// Complete MIDI state model for all 16 channels.
 /// Serialization is handled by the journal module's custom binary format.
@@ -24,6 +28,60 @@ pub struct ChannelState {
     pub pitch_bend: u16,
     /// Channel pressure (aftertouch)
     pub channel_pressure: u8,
+    /// Notes whose key is up but that keep sounding under the sustain or
+    /// sostenuto pedal: velocity > 0 means held
+    pub pedal_held: [u8; NUM_NOTES],
+    /// Notes latched by the sostenuto pedal when it went down
+    pub sostenuto_latched: [bool; NUM_NOTES],
+}
+
+impl ChannelState {
+    pub fn sustain_down(&self) -> bool {
+        self.cc[CC_SUSTAIN] >= 64
+    }
+
+    pub fn sostenuto_down(&self) -> bool {
+        self.cc[CC_SOSTENUTO] >= 64
+    }
+
+    fn held_by_pedal(&self, note: usize) -> bool {
+        self.sustain_down() || (self.sostenuto_down() && self.sostenuto_latched[note])
+    }
+
+    fn note_off(&mut self, note: usize) {
+        let velocity = self.notes[note];
+        self.notes[note] = 0;
+        if velocity > 0 && self.held_by_pedal(note) {
+            self.pedal_held[note] = velocity;
+        }
+    }
+
+    /// Apply a CC64/CC66 move: latch on sostenuto press, release the notes
+    /// no pedal holds any more. `cc` already has the new value.
+    fn pedal_moved(&mut self, cc_num: usize, was_down: bool) {
+        let down = self.cc[cc_num] >= 64;
+        if cc_num == CC_SOSTENUTO && down && !was_down {
+            for note in 0..NUM_NOTES {
+                self.sostenuto_latched[note] = self.notes[note] > 0 || self.pedal_held[note] > 0;
+            }
+        }
+        if cc_num == CC_SOSTENUTO && !down {
+            self.sostenuto_latched = [false; NUM_NOTES];
+        }
+        if was_down && !down {
+            for note in 0..NUM_NOTES {
+                if !self.held_by_pedal(note) {
+                    self.pedal_held[note] = 0;
+                }
+            }
+        }
+    }
+
+    fn clear_sounding(&mut self) {
+        self.notes = [0; NUM_NOTES];
+        self.pedal_held = [0; NUM_NOTES];
+        self.sostenuto_latched = [false; NUM_NOTES];
+    }
 }
 
 impl Default for ChannelState {
@@ -34,6 +92,8 @@ impl Default for ChannelState {
             program: 0,
             pitch_bend: 8192, // center position
             channel_pressure: 0,
+            pedal_held: [0; NUM_NOTES],
+            sostenuto_latched: [false; NUM_NOTES],
         }
     }
 }
@@ -69,12 +129,12 @@ impl MidiState {
         let channel = (status & 0x0F) as usize;
 
         match msg_type {
-            // Note Off
+            // Note Off (the pedals may keep it sounding)
             0x80 => {
                 if data.len() >= 3 {
                     let note = data[1] as usize;
                     if note < NUM_NOTES {
-                        self.channels[channel].notes[note] = 0;
+                        self.channels[channel].note_off(note);
                         return true;
                     }
                 }
@@ -86,7 +146,13 @@ impl MidiState {
                     let velocity = data[2];
                     if note < NUM_NOTES {
                         // Velocity 0 = Note Off
-                        self.channels[channel].notes[note] = velocity;
+                        let state = &mut self.channels[channel];
+                        if velocity == 0 {
+                            state.note_off(note);
+                        } else {
+                            state.notes[note] = velocity;
+                            state.pedal_held[note] = 0;
+                        }
                         return true;
                     }
                 }
@@ -99,18 +165,17 @@ impl MidiState {
                     let cc_num = data[1] as usize;
                     let value = data[2];
                     if cc_num < NUM_CCS {
-                        self.channels[channel].cc[cc_num] = value;
+                        let state = &mut self.channels[channel];
+                        let was_down = state.cc[cc_num] >= 64;
+                        state.cc[cc_num] = value;
 
                         // Handle special CCs
                         match cc_num {
+                            CC_SUSTAIN | CC_SOSTENUTO => state.pedal_moved(cc_num, was_down),
                             // All Sound Off
-                            120 => {
-                                self.channels[channel].notes = [0; NUM_NOTES];
-                            }
+                            120 => state.clear_sounding(),
                             // All Notes Off
-                            123 => {
-                                self.channels[channel].notes = [0; NUM_NOTES];
-                            }
+                            123 => state.clear_sounding(),
                             _ => {}
                         }
                         return true;
@@ -149,6 +214,11 @@ impl MidiState {
     /// Generate MIDI messages to reconcile state after failover.
     /// Sends: All Notes Off on all channels, then restores CCs, programs,
     /// pitch bends, and re-triggers active notes.
+    ///
+    /// Pedals are restored around the notes so the synth ends up holding
+    /// the same notes: sustain goes down first, sostenuto goes down after
+    /// the notes it latched are struck, and notes held only by a pedal are
+    /// struck and released again for the pedal to keep.
     pub fn generate_reconciliation(&self) -> Vec<Vec<u8>> {
         let mut messages = Vec::new();
 
@@ -159,9 +229,9 @@ impl MidiState {
             // First: All Sound Off (CC 120) to immediately silence
             messages.push(vec![0xB0 | ch_byte, 120, 0]);
 
-            // Restore CC values (skip special CCs 120-127)
+            // Restore CC values (skip special CCs 120-127; pedals come with the notes)
             for cc in 0..120 {
-                if channel.cc[cc] != 0 {
+                if channel.cc[cc] != 0 && cc != CC_SUSTAIN && cc != CC_SOSTENUTO {
                     messages.push(vec![0xB0 | ch_byte, cc as u8, channel.cc[cc]]);
                 }
             }
@@ -183,12 +253,36 @@ impl MidiState {
                 messages.push(vec![0xD0 | ch_byte, channel.channel_pressure]);
             }
 
+            let sounding = |note: usize| channel.notes[note].max(channel.pedal_held[note]);
+
+            // Sustain before any note, so released notes can be held again
+            if channel.cc[CC_SUSTAIN] != 0 {
+                messages.push(vec![0xB0 | ch_byte, CC_SUSTAIN as u8, channel.cc[CC_SUSTAIN]]);
+            }
+
+            // Sostenuto latches what sounds when it goes down: strike its notes first
+            let latched = |note: usize| channel.sostenuto_down() && channel.sostenuto_latched[note];
+            for note in (0..NUM_NOTES).filter(|&n| latched(n) && sounding(n) > 0) {
+                messages.push(vec![0x90 | ch_byte, note as u8, sounding(note)]);
+            }
+            if channel.cc[CC_SOSTENUTO] != 0 {
+                messages.push(vec![0xB0 | ch_byte, CC_SOSTENUTO as u8, channel.cc[CC_SOSTENUTO]]);
+            }
+
             // Re-trigger active notes
             for note in 0..NUM_NOTES {
-                if channel.notes[note] > 0 {
+                if channel.notes[note] > 0 && !latched(note) {
                     messages.push(vec![0x90 | ch_byte, note as u8, channel.notes[note]]);
                 }
             }
+
+            // Notes held only by a pedal: strike, then let the pedal hold them
+            for note in (0..NUM_NOTES).filter(|&n| channel.pedal_held[n] > 0) {
+                if !latched(note) {
+                    messages.push(vec![0x90 | ch_byte, note as u8, channel.pedal_held[note]]);
+                }
+                messages.push(vec![0x80 | ch_byte, note as u8, 0]);
+            }
         }
 
         messages
@@ -207,6 +301,40 @@ impl MidiState {
     pub fn reset(&mut self) {
         *self = Self::default();
     }
+
+    /// Clear held notes on one channel (or all with None), as a panic does.
+    /// The panic also lifts the sustain and sostenuto pedals; other
+    /// controller, program and bend values are kept — the device still has them.
+    pub fn clear_notes(&mut self, channel: Option<u8>) {
+        for (ch, state) in self.channels.iter_mut().enumerate() {
+            if channel.is_none_or(|c| c as usize == ch) {
+                state.clear_sounding();
+                state.cc[CC_SUSTAIN] = 0;
+                state.cc[CC_SOSTENUTO] = 0;
+            }
+        }
+    }
+}
+
+/// MIDI panic bytes: sustain + sostenuto up (CC 64/66), then All Sound Off
+/// (CC 120) + All Notes Off (CC 123) on one channel (0-15), or on all 16
+/// channels with None. Lifting the pedals first means notes released by
+/// All Notes Off actually stop instead of being held by a stuck pedal.
+pub fn panic_messages(channel: Option<u8>) -> Vec<u8> {
+    let channels: Vec<u8> = match channel {
+        Some(ch) => vec![ch & 0x0F],
+        None => (0..NUM_CHANNELS as u8).collect(),
+    };
+    let mut out = Vec::with_capacity(channels.len() * 12);
+    for ch in channels {
+        out.extend_from_slice(&[
+            0xB0 | ch, CC_SUSTAIN as u8, 0,
+            0xB0 | ch, CC_SOSTENUTO as u8, 0,
+            0xB0 | ch, 120, 0,
+            0xB0 | ch, 123, 0,
+        ]);
+    }
+    out
 }
 
 #[cfg(test)]
@@ -309,6 +437,84 @@ mod tests {
         assert!(messages.contains(&vec![0x90, 60, 100]));
     }
 
+    #[test]
+    fn test_mpe_per_channel_bend_is_reconciled() {
+        // MPE: each sounding note sits on its own channel with its own bend
+        let mut live = MidiState::new();
+        live.process_message(&[0xE1, 0, 72]);
+        live.process_message(&[0xD1, 50]);
+        live.process_message(&[0x91, 60, 100]);
+        live.process_message(&[0xE2, 0, 40]);
+        live.process_message(&[0x92, 64, 90]);
+
+        // Through the journal, as a client rejoining after failover sees it
+        let state = crate::journal::decode_journal(&crate::journal::encode_journal(&live)).unwrap();
+        let messages = state.generate_reconciliation();
+
+        for (bend, note) in [(vec![0xE1, 0, 72], vec![0x91, 60, 100]), (vec![0xE2, 0, 40], vec![0x92, 64, 90])] {
+            let bend_at = messages.iter().position(|m| *m == bend).expect("bend restored");
+            let note_at = messages.iter().position(|m| *m == note).expect("note restored");
+            // Bend lands before the note so it doesn't start unbent
+            assert!(bend_at < note_at);
+        }
+        assert!(messages.contains(&vec![0xD1, 50]));
+    }
+
+    #[test]
+    fn test_sustained_note_survives_journal_and_reconciliation() {
+        let mut live = MidiState::new();
+        live.process_message(&[0x90, 60, 100]);
+        live.process_message(&[0xB0, 64, 127]); // sustain down
+        live.process_message(&[0x80, 60, 0]); // key up: the pedal holds it
+        assert_eq!(live.channels[0].notes[60], 0);
+        assert_eq!(live.channels[0].pedal_held[60], 100);
+
+        let state = crate::journal::decode_journal(&crate::journal::encode_journal(&live)).unwrap();
+        assert_eq!(state.channels[0].pedal_held[60], 100);
+
+        let messages = state.generate_reconciliation();
+        let at = |msg: &[u8]| messages.iter().position(|m| m == msg).unwrap();
+        assert!(at(&[0xB0, 64, 127]) < at(&[0x90, 60, 100]));
+        assert!(at(&[0x90, 60, 100]) < at(&[0x80, 60, 0]));
+
+        // Replaying the burst leaves the note sounding under the pedal
+        let mut standby = MidiState::new();
+        for msg in &messages {
+            standby.process_message(msg);
+        }
+        assert_eq!(standby.channels[0].pedal_held[60], 100);
+        assert!(standby.channels[0].sustain_down());
+
+        // ...until the pedal comes up
+        standby.process_message(&[0xB0, 64, 0]);
+        assert_eq!(standby.channels[0].pedal_held[60], 0);
+    }
+
+    #[test]
+    fn test_sostenuto_holds_only_latched_notes() {
+        let mut live = MidiState::new();
+        live.process_message(&[0x90, 48, 90]);
+        live.process_message(&[0xB0, 66, 127]); // sostenuto latches C3
+        live.process_message(&[0x90, 60, 100]);
+        live.process_message(&[0x80, 48, 0]);
+        live.process_message(&[0x80, 60, 0]);
+        assert_eq!(live.channels[0].pedal_held[48], 90);
+        assert_eq!(live.channels[0].pedal_held[60], 0);
+
+        let messages = live.generate_reconciliation();
+        let at = |msg: &[u8]| messages.iter().position(|m| m == msg).unwrap();
+        // The latched note sounds before sostenuto goes down, then is released to it
+        assert!(at(&[0x90, 48, 90]) < at(&[0xB0, 66, 127]));
+        assert!(at(&[0xB0, 66, 127]) < at(&[0x80, 48, 0]));
+
+        let mut standby = MidiState::new();
+        for msg in &messages {
+            standby.process_message(msg);
+        }
+        assert_eq!(standby.channels[0].pedal_held[48], 90);
+        assert!(standby.channels[0].sostenuto_latched[48]);
+    }
+
     #[test]
     fn test_multichannel() {
         let mut state = MidiState::new();
@@ -322,4 +528,60 @@ mod tests {
         assert_eq!(state.channels[15].notes[48], 60);
         assert_eq!(state.active_note_count(), 3);
     }
+
+    #[test]
+    fn test_channel_panic_targets_one_channel() {
+        let msgs = panic_messages(Some(4));
+        assert_eq!(msgs, vec![0xB4, 64, 0, 0xB4, 66, 0, 0xB4, 120, 0, 0xB4, 123, 0]);
+        // Every CC status byte is on channel 4 only
+        assert!(msgs.chunks(3).all(|m| m[0] == 0xB4));
+
+        assert_eq!(panic_messages(None).len(), 16 * 12);
+    }
+
+    #[test]
+    fn test_panic_releases_pedal_held_notes() {
+        let mut state = MidiState::new();
+        state.process_message(&[0x90, 60, 100]);
+        state.process_message(&[0xB0, 64, 127]);
+        state.process_message(&[0x80, 60, 0]);
+        assert_eq!(state.channels[0].pedal_held[60], 100);
+
+        state.clear_notes(None);
+        assert_eq!(state.channels[0].pedal_held[60], 0);
+        assert!(!state.channels[0].sustain_down());
+
+        // A device replaying the panic bytes ends up the same way
+        let mut device = MidiState::new();
+        device.process_message(&[0xB0, 64, 127]);
+        device.process_message(&[0x90, 62, 90]);
+        device.process_message(&[0x80, 62, 0]);
+        for msg in panic_messages(Some(0)).chunks(3) {
+            device.process_message(msg);
+        }
+        assert_eq!(device.channels[0].pedal_held[62], 0);
+        assert!(!device.channels[0].sustain_down());
+
+        // The next note released isn't held by a stuck pedal
+        device.process_message(&[0x90, 64, 80]);
+        device.process_message(&[0x80, 64, 0]);
+        assert_eq!(device.channels[0].pedal_held[64], 0);
+    }
+
+    #[test]
+    fn test_clear_notes_only_affects_target_channel() {
+        let mut state = MidiState::new();
+        state.process_message(&[0x94, 60, 100]);
+        state.process_message(&[0x94, 64, 90]);
+        state.process_message(&[0x90, 60, 100]);
+        state.process_message(&[0xB4, 7, 80]);
+
+        state.clear_notes(Some(4));
+        assert!(state.channels[4].notes.iter().all(|&v| v == 0));
+        assert_eq!(state.channels[4].cc[7], 80);
+        assert_eq!(state.channels[0].notes[60], 100);
+
+        state.clear_notes(None);
+        assert_eq!(state.active_note_count(), 0);
+    }
 }
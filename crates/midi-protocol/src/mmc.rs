@@ -0,0 +1,163 @@
+/// MIDI Machine Control (MMC) transport commands.
+///
+/// MMC travels as Universal Real-Time SysEx: `F0 7F <device> 06 <command> F7`,
+/// with Locate carrying a target timecode:
+/// `F0 7F <device> 06 44 06 01 <hr> <mn> <sc> <fr> <ff> F7`, where the top
+/// bits of `<hr>` hold the frame rate. The frames themselves are forwarded
+/// as ordinary SysEx; this module only decodes them for logging and the
+/// OSC mirror.
+
+/// Universal Real-Time SysEx ID
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+/// Sub-ID #1 for MMC commands
+const MMC_COMMAND: u8 = 0x06;
+/// Locate command and its TARGET sub-command
+const LOCATE: u8 = 0x44;
+const LOCATE_TARGET: u8 = 0x01;
+
+/// Device ID addressing every device
+pub const ALL_DEVICES: u8 = 0x7F;
+
+/// Timecode rate, from bits 5-6 of the hours byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameRate {
+    Fps24,
+    Fps25,
+    Fps30Drop,
+    Fps30,
+}
+
+/// Position a Locate command moves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmcTimecode {
+    pub rate: FrameRate,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub subframes: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmcCommand {
+    Stop,
+    Play,
+    DeferredPlay,
+    FastForward,
+    Rewind,
+    /// Punch in (start recording)
+    RecordStrobe,
+    /// Punch out
+    RecordExit,
+    Pause,
+    Locate(MmcTimecode),
+}
+
+impl MmcCommand {
+    /// Short lowercase name, e.g. "play" or "locate".
+    pub fn name(&self) -> &'static str {
+        match self {
+            MmcCommand::Stop => "stop",
+            MmcCommand::Play => "play",
+            MmcCommand::DeferredPlay => "deferred_play",
+            MmcCommand::FastForward => "fast_forward",
+            MmcCommand::Rewind => "rewind",
+            MmcCommand::RecordStrobe => "record_strobe",
+            MmcCommand::RecordExit => "record_exit",
+            MmcCommand::Pause => "pause",
+            MmcCommand::Locate(_) => "locate",
+        }
+    }
+}
+
+/// A decoded MMC command and the device it is addressed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmcMessage {
+    /// Target device ID (`ALL_DEVICES` = broadcast)
+    pub device_id: u8,
+    pub command: MmcCommand,
+}
+
+/// Decode one complete SysEx message (F0 ... F7) as an MMC command.
+/// Returns None for any other SysEx and for MMC commands not listed in
+/// `MmcCommand`.
+pub fn parse(sysex: &[u8]) -> Option<MmcMessage> {
+    let [0xF0, UNIVERSAL_REAL_TIME, device_id, MMC_COMMAND, body @ .., 0xF7] = sysex else {
+        return None;
+    };
+    let command = match body {
+        [0x01] => MmcCommand::Stop,
+        [0x02] => MmcCommand::Play,
+        [0x03] => MmcCommand::DeferredPlay,
+        [0x04] => MmcCommand::FastForward,
+        [0x05] => MmcCommand::Rewind,
+        [0x06] => MmcCommand::RecordStrobe,
+        [0x07] => MmcCommand::RecordExit,
+        [0x09] => MmcCommand::Pause,
+        [LOCATE, 0x06, LOCATE_TARGET, hr, mn, sc, fr, ff] => {
+            let rate = match (hr >> 5) & 0x03 {
+                0 => FrameRate::Fps24,
+                1 => FrameRate::Fps25,
+                2 => FrameRate::Fps30Drop,
+                _ => FrameRate::Fps30,
+            };
+            MmcCommand::Locate(MmcTimecode {
+                rate,
+                hours: hr & 0x1F,
+                minutes: *mn & 0x3F,
+                seconds: *sc & 0x3F,
+                frames: *fr & 0x1F,
+                subframes: *ff & 0x7F,
+            })
+        }
+        _ => return None,
+    };
+    Some(MmcMessage {
+        device_id: *device_id,
+        command,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_decodes_with_its_device_id() {
+        let msg = parse(&[0xF0, 0x7F, 0x7F, 0x06, 0x02, 0xF7]).unwrap();
+        assert_eq!(msg.device_id, ALL_DEVICES);
+        assert_eq!(msg.command, MmcCommand::Play);
+        assert_eq!(msg.command.name(), "play");
+
+        assert_eq!(parse(&[0xF0, 0x7F, 0x10, 0x06, 0x01, 0xF7]).unwrap().command, MmcCommand::Stop);
+        assert_eq!(parse(&[0xF0, 0x7F, 0x10, 0x06, 0x06, 0xF7]).unwrap().command, MmcCommand::RecordStrobe);
+    }
+
+    #[test]
+    fn locate_decodes_the_target_timecode() {
+        // 25 fps, 01:02:03:04.05 ("hr" = 0b0_01_00001)
+        let msg = parse(&[0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x21, 0x02, 0x03, 0x04, 0x05, 0xF7]).unwrap();
+        assert_eq!(
+            msg.command,
+            MmcCommand::Locate(MmcTimecode {
+                rate: FrameRate::Fps25,
+                hours: 1,
+                minutes: 2,
+                seconds: 3,
+                frames: 4,
+                subframes: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn other_sysex_is_not_mmc() {
+        // Identity request (non-MMC Universal Non-Real-Time)
+        assert_eq!(parse(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]), None);
+        // MIDI Time Code full frame (Real-Time, sub-ID 01)
+        assert_eq!(parse(&[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03, 0x04, 0xF7]), None);
+        // Truncated Locate, unterminated frame
+        assert_eq!(parse(&[0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x21, 0xF7]), None);
+        assert_eq!(parse(&[0xF0, 0x7F, 0x7F, 0x06, 0x02]), None);
+    }
+}
@@ -0,0 +1,170 @@
+/// Address-family-aware multicast socket helpers.
+///
+/// Multicast groups are configured as strings and may be IPv4
+/// ("239.69.83.1") or IPv6 ("ff15::6d69:6469"). Everything that opens a
+/// group socket parses the group with `parse_group` and lets the group's
+/// family pick the socket domain, bind address, join call and outgoing
+/// interface options.
+///
+/// IPv4 selects an interface by address, IPv6 by interface index, so an
+/// interface is carried as `MulticastInterface` holding both; the one that
+/// doesn't match the group's family is ignored. `0.0.0.0` / index 0 lets
+/// the OS pick.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticastInterface {
+    /// Interface address for IPv4 groups
+    pub v4: Ipv4Addr,
+    /// Interface index for IPv6 groups
+    pub v6_index: u32,
+}
+
+impl MulticastInterface {
+    /// Let the OS pick in both families
+    pub const ANY: Self = Self { v4: Ipv4Addr::UNSPECIFIED, v6_index: 0 };
+}
+
+impl Default for MulticastInterface {
+    fn default() -> Self {
+        Self::ANY
+    }
+}
+
+impl From<Ipv4Addr> for MulticastInterface {
+    fn from(v4: Ipv4Addr) -> Self {
+        Self { v4, v6_index: 0 }
+    }
+}
+
+/// Parse a configured multicast group. Rejects unicast addresses, which
+/// would otherwise fail much later with a confusing join error.
+pub fn parse_group(group: &str) -> io::Result<IpAddr> {
+    let addr: IpAddr = group.trim().parse().map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("invalid multicast group '{}'", group))
+    })?;
+    if !addr.is_multicast() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a multicast address", group),
+        ));
+    }
+    Ok(addr)
+}
+
+/// Socket domain for a group's address family
+pub fn domain(group: IpAddr) -> Domain {
+    match group {
+        IpAddr::V4(_) => Domain::IPV4,
+        IpAddr::V6(_) => Domain::IPV6,
+    }
+}
+
+/// The wildcard address of the group's family, bound to `port`
+pub fn bind_addr(group: IpAddr, port: u16) -> SocketAddr {
+    match group {
+        IpAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
+        IpAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port),
+    }
+}
+
+/// A UDP socket of the group's family. IPv6 sockets are v6-only so an
+/// IPv4 socket on the same port can coexist.
+pub fn new_socket(group: IpAddr) -> io::Result<Socket> {
+    let socket = Socket::new(domain(group), Type::DGRAM, Some(Protocol::UDP))?;
+    if group.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    Ok(socket)
+}
+
+/// Join `group` on `interface`.
+pub fn join(socket: &Socket, group: IpAddr, interface: MulticastInterface) -> io::Result<()> {
+    match group {
+        IpAddr::V4(group) => socket.join_multicast_v4(&group, &interface.v4),
+        IpAddr::V6(group) => socket.join_multicast_v6(&group, interface.v6_index),
+    }
+}
+
+/// Outgoing interface, TTL / hop limit and loopback for sends to `group`.
+pub fn set_outgoing(
+    socket: &Socket,
+    group: IpAddr,
+    interface: MulticastInterface,
+    ttl: u32,
+    loopback: bool,
+) -> io::Result<()> {
+    match group {
+        IpAddr::V4(_) => {
+            socket.set_multicast_if_v4(&interface.v4)?;
+            socket.set_multicast_ttl_v4(ttl)?;
+            socket.set_multicast_loop_v4(loopback)
+        }
+        IpAddr::V6(_) => {
+            socket.set_multicast_if_v6(interface.v6_index)?;
+            socket.set_multicast_hops_v6(ttl)?;
+            socket.set_multicast_loop_v6(loopback)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn family_is_taken_from_the_group() {
+        let v4 = parse_group("239.69.83.1").unwrap();
+        assert!(v4.is_ipv4());
+        assert_eq!(domain(v4), Domain::IPV4);
+        assert_eq!(bind_addr(v4, 5004), "0.0.0.0:5004".parse().unwrap());
+
+        let v6 = parse_group(" ff15::6d69:6469 ").unwrap();
+        assert!(v6.is_ipv6());
+        assert_eq!(domain(v6), Domain::IPV6);
+        assert_eq!(bind_addr(v6, 5004), "[::]:5004".parse().unwrap());
+    }
+
+    #[test]
+    fn unicast_and_garbage_groups_are_rejected() {
+        for bad in ["192.168.1.10", "fe80::1", "eth0", ""] {
+            let err = parse_group(bad).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{bad}");
+        }
+    }
+
+    #[test]
+    fn ipv4_socket_takes_interface_address_and_ttl() {
+        let group = parse_group("239.69.83.250").unwrap();
+        let socket = new_socket(group).unwrap();
+        set_outgoing(&socket, group, Ipv4Addr::LOCALHOST.into(), 4, true).unwrap();
+        assert_eq!(socket.multicast_if_v4().unwrap(), Ipv4Addr::LOCALHOST);
+        assert_eq!(socket.multicast_ttl_v4().unwrap(), 4);
+        assert!(socket.multicast_loop_v4().unwrap());
+
+        socket.bind(&bind_addr(group, 0).into()).unwrap();
+        assert!(socket.local_addr().unwrap().as_socket().unwrap().is_ipv4());
+    }
+
+    #[test]
+    fn ipv6_socket_takes_interface_index_and_hop_limit() {
+        let group = parse_group("ff15::1").unwrap();
+        // Hosts without IPv6 can't create the socket at all
+        let Ok(socket) = new_socket(group) else {
+            return;
+        };
+        assert!(socket.only_v6().unwrap());
+        let interface = MulticastInterface { v4: Ipv4Addr::LOCALHOST, v6_index: 0 };
+        set_outgoing(&socket, group, interface, 4, false).unwrap();
+        assert_eq!(socket.multicast_if_v6().unwrap(), 0);
+        assert_eq!(socket.multicast_hops_v6().unwrap(), 4);
+        assert!(!socket.multicast_loop_v6().unwrap());
+
+        socket.bind(&bind_addr(group, 0).into()).unwrap();
+        assert!(socket.local_addr().unwrap().as_socket().unwrap().is_ipv6());
+    }
+}
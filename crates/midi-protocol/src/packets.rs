@@ -8,6 +8,12 @@ pub const MAGIC_IDENTITY: [u8; 4] = *b"MDID";
 pub const MAGIC_FOCUS: [u8; 4] = *b"MDFC";
 pub const MAGIC_DISCOVER_REQ: [u8; 4] = *b"MDDS";
 pub const MAGIC_DISCOVER_RESP: [u8; 4] = *b"MDDR";
+pub const MAGIC_PANIC: [u8; 4] = *b"MDPN";
+pub const MAGIC_HOST_STOPPING: [u8; 4] = *b"MDBY";
+pub const MAGIC_FEC_PARITY: [u8; 4] = *b"MDFE";
+pub const MAGIC_RECORD: [u8; 4] = *b"MDRC";
+pub const MAGIC_SUBSCRIBE: [u8; 4] = *b"MDSB";
+pub const MAGIC_INPUT_ENABLE: [u8; 4] = *b"MDIE";
 
 // -- Host roles --
 
@@ -31,6 +37,11 @@ impl HostRole {
 // -- MIDI Data Packet --
 // Lightweight RTP-inspired framing for MIDI over UDP.
 
+/// Flags byte: a journal follows the MIDI data
+pub const FLAG_JOURNAL: u8 = 0x01;
+/// Flags byte: the body is sealed (see `crypto`)
+pub const FLAG_ENCRYPTED: u8 = 0x02;
+
 #[derive(Debug, Clone)]
 pub struct MidiDataPacket {
     pub sequence: u16,
@@ -52,7 +63,7 @@ impl MidiDataPacket {
         buf.extend_from_slice(&self.timestamp_us.to_be_bytes());
         buf.push(self.host_id);
 
-        let flags: u8 = if self.journal.is_some() { 0x01 } else { 0x00 };
+        let flags: u8 = if self.journal.is_some() { FLAG_JOURNAL } else { 0x00 };
         buf.push(flags);
 
         let midi_len = self.midi_data.len() as u16;
@@ -80,6 +91,10 @@ impl MidiDataPacket {
         ]);
         let host_id = data[14];
         let flags = data[15];
+        // Sealed: must go through `PacketCipher::open` first
+        if flags & FLAG_ENCRYPTED != 0 {
+            return None;
+        }
         let midi_len = u16::from_be_bytes([data[16], data[17]]) as usize;
 
         if data.len() < Self::HEADER_SIZE + midi_len {
@@ -88,7 +103,7 @@ impl MidiDataPacket {
 
         let midi_data = data[Self::HEADER_SIZE..Self::HEADER_SIZE + midi_len].to_vec();
 
-        let journal = if flags & 0x01 != 0 {
+        let journal = if flags & FLAG_JOURNAL != 0 {
             let journal_offset = Self::HEADER_SIZE + midi_len;
             if data.len() < journal_offset + 2 {
                 return None;
@@ -113,7 +128,7 @@ impl MidiDataPacket {
     }
 }
 
-// -- Heartbeat Packet (16 bytes) --
+// -- Heartbeat Packet (19 bytes) --
 
 #[derive(Debug, Clone, Copy)]
 pub struct HeartbeatPacket {
@@ -121,10 +136,31 @@ pub struct HeartbeatPacket {
     pub role: HostRole,
     pub sequence: u16,
     pub timestamp_us: u64,
+    /// The host's current heartbeat interval, so clients can size their
+    /// failover window to it (it grows while an adaptive host backs off).
+    /// 0 = not advertised (protocol v1 hosts send 16-byte heartbeats).
+    pub interval_ms: u16,
+    /// Failover preference: clients fail over to the live host with the
+    /// lowest priority, ties going to the lower host_id. Hosts that don't
+    /// advertise one (18-byte heartbeats and older) rank by host_id.
+    pub priority: u8,
 }
 
 impl HeartbeatPacket {
-    pub const SIZE: usize = 16;
+    pub const SIZE: usize = 19;
+    /// Protocol v1 heartbeats end after the timestamp
+    pub const LEGACY_SIZE: usize = 16;
+    /// The fixed interval of hosts that don't advertise one
+    pub const LEGACY_INTERVAL_MS: u16 = 3;
+
+    /// The advertised interval, or the legacy 3ms when there is none.
+    pub fn effective_interval_ms(&self) -> u16 {
+        if self.interval_ms == 0 {
+            Self::LEGACY_INTERVAL_MS
+        } else {
+            self.interval_ms
+        }
+    }
 
     pub fn serialize(&self, buf: &mut [u8; Self::SIZE]) {
         buf[0..4].copy_from_slice(&MAGIC_HEARTBEAT);
@@ -132,10 +168,12 @@ impl HeartbeatPacket {
         buf[5] = self.role as u8;
         buf[6..8].copy_from_slice(&self.sequence.to_be_bytes());
         buf[8..16].copy_from_slice(&self.timestamp_us.to_be_bytes());
+        buf[16..18].copy_from_slice(&self.interval_ms.to_be_bytes());
+        buf[18] = self.priority;
     }
 
     pub fn deserialize(data: &[u8]) -> Option<Self> {
-        if data.len() < Self::SIZE {
+        if data.len() < Self::LEGACY_SIZE {
             return None;
         }
         if &data[0..4] != &MAGIC_HEARTBEAT {
@@ -149,6 +187,11 @@ impl HeartbeatPacket {
             timestamp_us: u64::from_be_bytes([
                 data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15],
             ]),
+            interval_ms: match data.get(16..18) {
+                Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+                None => 0,
+            },
+            priority: data.get(18).copied().unwrap_or(data[4]),
         })
     }
 }
@@ -311,6 +354,259 @@ impl FocusPacket {
     }
 }
 
+// -- Panic Packets --
+
+/// Sent by the admin panel on the control group to make hosts broadcast
+/// All Notes Off + All Sound Off and clear the affected channel state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicPacket {
+    /// MIDI channel 0-15, or None for all channels
+    pub channel: Option<u8>,
+    pub timestamp_us: u64,
+}
+
+impl PanicPacket {
+    pub const SIZE: usize = 13; // magic(4) + channel(1, 0xFF = all) + timestamp(8)
+
+    pub fn serialize(&self, buf: &mut [u8; Self::SIZE]) {
+        buf[0..4].copy_from_slice(&MAGIC_PANIC);
+        buf[4] = self.channel.unwrap_or(0xFF);
+        buf[5..13].copy_from_slice(&self.timestamp_us.to_be_bytes());
+    }
+
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+        if data[0..4] != MAGIC_PANIC {
+            return None;
+        }
+
+        let channel = match data[4] {
+            0xFF => None,
+            ch if ch < 16 => Some(ch),
+            _ => return None,
+        };
+
+        Some(Self {
+            channel,
+            timestamp_us: u64::from_be_bytes([
+                data[5], data[6], data[7], data[8], data[9], data[10], data[11], data[12],
+            ]),
+        })
+    }
+}
+
+// -- Record Packet (13 bytes) --
+
+/// Sent by the admin panel on the control group to start or stop the
+/// hosts' input recorders (`POST /api/record`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordPacket {
+    /// true = start a new recording, false = stop and close the file
+    pub start: bool,
+    pub timestamp_us: u64,
+}
+
+impl RecordPacket {
+    pub const SIZE: usize = 13; // magic(4) + action(1, 1 = start, 0 = stop) + timestamp(8)
+
+    pub fn serialize(&self, buf: &mut [u8; Self::SIZE]) {
+        buf[0..4].copy_from_slice(&MAGIC_RECORD);
+        buf[4] = self.start as u8;
+        buf[5..13].copy_from_slice(&self.timestamp_us.to_be_bytes());
+    }
+
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+        if data[0..4] != MAGIC_RECORD {
+            return None;
+        }
+
+        let start = match data[4] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+
+        Some(Self {
+            start,
+            timestamp_us: u64::from_be_bytes([
+                data[5], data[6], data[7], data[8], data[9], data[10], data[11], data[12],
+            ]),
+        })
+    }
+}
+
+// -- Input Enable Packet (14 bytes) --
+
+/// Sent by the admin panel on the control group to mute or unmute one of a
+/// host's input controllers (`PUT /api/input-redundancy/:index/enable`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEnablePacket {
+    /// 0 = primary controller, 1 = secondary
+    pub index: u8,
+    pub enabled: bool,
+    pub timestamp_us: u64,
+}
+
+impl InputEnablePacket {
+    pub const SIZE: usize = 14; // magic(4) + index(1) + enabled(1) + timestamp(8)
+
+    pub fn serialize(&self, buf: &mut [u8; Self::SIZE]) {
+        buf[0..4].copy_from_slice(&MAGIC_INPUT_ENABLE);
+        buf[4] = self.index;
+        buf[5] = self.enabled as u8;
+        buf[6..14].copy_from_slice(&self.timestamp_us.to_be_bytes());
+    }
+
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+        if data[0..4] != MAGIC_INPUT_ENABLE {
+            return None;
+        }
+
+        let enabled = match data[5] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+
+        Some(Self {
+            index: data[4],
+            enabled,
+            timestamp_us: u64::from_be_bytes([
+                data[6], data[7], data[8], data[9], data[10], data[11], data[12], data[13],
+            ]),
+        })
+    }
+}
+
+// -- Subscribe Packet (10 bytes) --
+
+/// Sent by a client straight to its host's control port to ask for unicast
+/// delivery, for clients the host can't learn about any other way (behind
+/// NAT). Repeated every `interval_ms`; the host drops a client that stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribePacket {
+    pub client_id: u32,
+    /// How often the client re-sends this packet
+    pub interval_ms: u16,
+}
+
+impl SubscribePacket {
+    pub const SIZE: usize = 10; // magic(4) + client_id(4) + interval_ms(2)
+
+    pub fn serialize(&self, buf: &mut [u8; Self::SIZE]) {
+        buf[0..4].copy_from_slice(&MAGIC_SUBSCRIBE);
+        buf[4..8].copy_from_slice(&self.client_id.to_be_bytes());
+        buf[8..10].copy_from_slice(&self.interval_ms.to_be_bytes());
+    }
+
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+        if data[0..4] != MAGIC_SUBSCRIBE {
+            return None;
+        }
+
+        Some(Self {
+            client_id: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            interval_ms: u16::from_be_bytes([data[8], data[9]]),
+        })
+    }
+}
+
+// -- Host Stopping Packet (13 bytes) --
+
+/// Sent on the heartbeat port when a host is stopped on purpose, so clients
+/// switch away at once instead of waiting out the heartbeat timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostStoppingPacket {
+    pub host_id: u8,
+    pub timestamp_us: u64,
+}
+
+impl HostStoppingPacket {
+    pub const SIZE: usize = 13; // magic(4) + host_id(1) + timestamp(8)
+
+    pub fn serialize(&self, buf: &mut [u8; Self::SIZE]) {
+        buf[0..4].copy_from_slice(&MAGIC_HOST_STOPPING);
+        buf[4] = self.host_id;
+        buf[5..13].copy_from_slice(&self.timestamp_us.to_be_bytes());
+    }
+
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+        if data[0..4] != MAGIC_HOST_STOPPING {
+            return None;
+        }
+
+        Some(Self {
+            host_id: data[4],
+            timestamp_us: u64::from_be_bytes([
+                data[5], data[6], data[7], data[8], data[9], data[10], data[11], data[12],
+            ]),
+        })
+    }
+}
+
+// -- FEC Parity Packet (data port, see `fec`) --
+
+/// XOR parity over `group_size` consecutive data packets of one host,
+/// starting at `base_sequence`. Any one lost packet of the group can be
+/// rebuilt from the parity and the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FecParityPacket {
+    pub host_id: u8,
+    pub base_sequence: u16,
+    pub group_size: u8,
+    /// XOR of the wire lengths of the group's packets
+    pub length_xor: u16,
+    /// XOR of the group's wire bytes, each zero-padded to the longest
+    pub parity: Vec<u8>,
+}
+
+impl FecParityPacket {
+    /// magic(4) + host_id(1) + base_sequence(2) + group_size(1) + length_xor(2) + parity_len(2)
+    pub const HEADER_SIZE: usize = 12;
+
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.extend_from_slice(&MAGIC_FEC_PARITY);
+        buf.push(self.host_id);
+        buf.extend_from_slice(&self.base_sequence.to_be_bytes());
+        buf.push(self.group_size);
+        buf.extend_from_slice(&self.length_xor.to_be_bytes());
+        buf.extend_from_slice(&(self.parity.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.parity);
+    }
+
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::HEADER_SIZE || data[0..4] != MAGIC_FEC_PARITY {
+            return None;
+        }
+        let parity_len = u16::from_be_bytes([data[10], data[11]]) as usize;
+        if data.len() < Self::HEADER_SIZE + parity_len {
+            return None;
+        }
+        Some(Self {
+            host_id: data[4],
+            base_sequence: u16::from_be_bytes([data[5], data[6]]),
+            group_size: data[7],
+            length_xor: u16::from_be_bytes([data[8], data[9]]),
+            parity: data[Self::HEADER_SIZE..Self::HEADER_SIZE + parity_len].to_vec(),
+        })
+    }
+}
+
 // -- Discovery Packets (UDP broadcast) --
 
 /// Sent by clients as a broadcast to find hosts on the LAN.
@@ -464,6 +760,8 @@ mod tests {
             role: HostRole::Primary,
             sequence: 1000,
             timestamp_us: 5555555,
+            interval_ms: 24,
+            priority: 30,
         };
 
         let mut buf = [0u8; HeartbeatPacket::SIZE];
@@ -474,6 +772,51 @@ mod tests {
         assert_eq!(decoded.role, HostRole::Primary);
         assert_eq!(decoded.sequence, 1000);
         assert_eq!(decoded.timestamp_us, 5555555);
+        assert_eq!(decoded.interval_ms, 24);
+        assert_eq!(decoded.priority, 30);
+    }
+
+    #[test]
+    fn test_legacy_heartbeat_uses_default_interval() {
+        let packet = HeartbeatPacket {
+            host_id: 2,
+            role: HostRole::Standby,
+            sequence: 7,
+            timestamp_us: 42,
+            interval_ms: 24,
+            priority: 10,
+        };
+        let mut buf = [0u8; HeartbeatPacket::SIZE];
+        packet.serialize(&mut buf);
+
+        // A v1 host sends the same bytes without the interval
+        let decoded = HeartbeatPacket::deserialize(&buf[..HeartbeatPacket::LEGACY_SIZE]).unwrap();
+        assert_eq!(decoded.sequence, 7);
+        assert_eq!(decoded.interval_ms, 0);
+        assert_eq!(decoded.effective_interval_ms(), HeartbeatPacket::LEGACY_INTERVAL_MS);
+        assert_eq!(packet.effective_interval_ms(), 24);
+
+        assert!(HeartbeatPacket::deserialize(&buf[..HeartbeatPacket::LEGACY_SIZE - 1]).is_none());
+    }
+
+    #[test]
+    fn test_heartbeat_without_priority_ranks_by_host_id() {
+        let packet = HeartbeatPacket {
+            host_id: 2,
+            role: HostRole::Standby,
+            sequence: 7,
+            timestamp_us: 42,
+            interval_ms: 24,
+            priority: 10,
+        };
+        let mut buf = [0u8; HeartbeatPacket::SIZE];
+        packet.serialize(&mut buf);
+
+        // A host from before priorities sends 18 bytes
+        let decoded = HeartbeatPacket::deserialize(&buf[..HeartbeatPacket::SIZE - 1]).unwrap();
+        assert_eq!(decoded.interval_ms, 24);
+        assert_eq!(decoded.priority, 2);
+        assert_eq!(HeartbeatPacket::deserialize(&buf).unwrap().priority, 10);
     }
 
     #[test]
@@ -518,6 +861,74 @@ mod tests {
         assert_eq!(decoded.sequence, 7);
     }
 
+    #[test]
+    fn test_panic_roundtrip() {
+        for channel in [Some(0), Some(9), None] {
+            let packet = PanicPacket { channel, timestamp_us: 42 };
+            let mut buf = [0u8; PanicPacket::SIZE];
+            packet.serialize(&mut buf);
+            assert_eq!(PanicPacket::deserialize(&buf), Some(packet));
+        }
+
+        // Out-of-range channel byte is rejected
+        let mut buf = [0u8; PanicPacket::SIZE];
+        PanicPacket { channel: Some(0), timestamp_us: 0 }.serialize(&mut buf);
+        buf[4] = 16;
+        assert!(PanicPacket::deserialize(&buf).is_none());
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        for start in [true, false] {
+            let packet = RecordPacket { start, timestamp_us: 42 };
+            let mut buf = [0u8; RecordPacket::SIZE];
+            packet.serialize(&mut buf);
+            assert_eq!(RecordPacket::deserialize(&buf), Some(packet));
+        }
+
+        let mut buf = [0u8; RecordPacket::SIZE];
+        RecordPacket { start: true, timestamp_us: 0 }.serialize(&mut buf);
+        buf[4] = 2;
+        assert!(RecordPacket::deserialize(&buf).is_none());
+        assert!(RecordPacket::deserialize(&[0u8; PanicPacket::SIZE]).is_none());
+    }
+
+    #[test]
+    fn test_input_enable_roundtrip() {
+        for enabled in [true, false] {
+            let packet = InputEnablePacket { index: 1, enabled, timestamp_us: 42 };
+            let mut buf = [0u8; InputEnablePacket::SIZE];
+            packet.serialize(&mut buf);
+            assert_eq!(InputEnablePacket::deserialize(&buf), Some(packet));
+        }
+
+        let mut buf = [0u8; InputEnablePacket::SIZE];
+        InputEnablePacket { index: 0, enabled: true, timestamp_us: 0 }.serialize(&mut buf);
+        buf[5] = 2;
+        assert!(InputEnablePacket::deserialize(&buf).is_none());
+        assert!(InputEnablePacket::deserialize(&[0u8; RecordPacket::SIZE]).is_none());
+    }
+
+    #[test]
+    fn test_subscribe_roundtrip() {
+        let packet = SubscribePacket { client_id: 0xDEAD_BEEF, interval_ms: 1_000 };
+        let mut buf = [0u8; SubscribePacket::SIZE];
+        packet.serialize(&mut buf);
+        assert_eq!(SubscribePacket::deserialize(&buf), Some(packet));
+        assert!(SubscribePacket::deserialize(&buf[..9]).is_none());
+        assert!(SubscribePacket::deserialize(&[0u8; RecordPacket::SIZE]).is_none());
+    }
+
+    #[test]
+    fn test_host_stopping_roundtrip() {
+        let packet = HostStoppingPacket { host_id: 2, timestamp_us: 1_700_000_000_000_000 };
+        let mut buf = [0u8; HostStoppingPacket::SIZE];
+        packet.serialize(&mut buf);
+        assert_eq!(HostStoppingPacket::deserialize(&buf), Some(packet));
+        // A heartbeat is not a stopping notice
+        assert!(HostStoppingPacket::deserialize(&[0u8; HeartbeatPacket::SIZE]).is_none());
+    }
+
     #[test]
     fn test_reject_invalid_magic() {
         let bad_data = [0xFF; 20];
@@ -525,6 +936,8 @@ mod tests {
         assert!(HeartbeatPacket::deserialize(&bad_data).is_none());
         assert!(IdentityPacket::deserialize(&bad_data).is_none());
         assert!(FocusPacket::deserialize(&bad_data).is_none());
+        assert!(PanicPacket::deserialize(&bad_data).is_none());
+        assert!(HostStoppingPacket::deserialize(&bad_data).is_none());
     }
 
     #[test]
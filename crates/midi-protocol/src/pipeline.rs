@@ -2,6 +2,9 @@
 /// Applies filters, remaps, velocity curves, and transforms to MIDI data.
 /// Shared between host (outbound) and client (inbound + feedback) paths.
 
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,7 +13,8 @@ pub struct PipelineConfig {
     #[serde(default = "default_channels")]
     pub channel_filter: [bool; 16],
 
-    /// Message type filter
+    /// Message type filter, e.g. to keep a controller's SysEx and
+    /// real-time messages off the network
     #[serde(default)]
     pub message_filter: MessageFilter,
 
@@ -22,24 +26,171 @@ pub struct PipelineConfig {
     #[serde(default)]
     pub transpose: [i8; 16],
 
+    /// Controller number remaps for Control Changes, matched on the source
+    /// channel (before `channel_remap`). Empty (the default) leaves every
+    /// controller as it is; the first matching entry wins.
+    #[serde(default)]
+    pub cc_remap: Vec<CcRemap>,
+
     /// Velocity curve type
     #[serde(default)]
     pub velocity_curve: VelocityCurve,
 
+    /// Velocity curve for the feedback (return) path, independent of
+    /// `velocity_curve`. Applied by `process_feedback()`.
+    #[serde(default)]
+    pub feedback_velocity_curve: VelocityCurve,
+
+    /// Note On velocities 1-127 are rescaled linearly onto
+    /// `velocity_min..=velocity_max` after the velocity curve (1 and 127 =
+    /// unchanged). Velocity 0 stays a Note Off. See `validate_velocity_range`.
+    #[serde(default = "default_velocity_min")]
+    pub velocity_min: u8,
+    #[serde(default = "default_velocity_max")]
+    pub velocity_max: u8,
+
+    /// Rewrite every note release into `note_off_form`, whether it came in
+    /// as Note Off (0x80) or as Note On with velocity 0.
+    #[serde(default)]
+    pub normalize_note_off: bool,
+    #[serde(default)]
+    pub note_off_form: NoteOffForm,
+
     /// SysEx passthrough
     #[serde(default = "default_true")]
     pub sysex_passthrough: bool,
+
+    /// Minimum note length in ms (0 = off). Note Offs arriving sooner after
+    /// their Note On are held back, and a re-trigger during that time is
+    /// merged into the held note, so contact bounce plays as one note.
+    /// Stateful, host only: applied through a `NoteDebouncer`; clients ignore it
+    /// and refuse a pipeline that sets it (`validate_for_client`).
+    #[serde(default)]
+    pub min_note_duration_ms: u64,
+
+    /// When `channel_remap` merges several source channels onto one, hold
+    /// a merged note until every source that played it has released it,
+    /// instead of letting the first Note Off cut the others short.
+    /// Stateful, host only: applied through a `MergedNotes`; clients ignore it
+    /// and refuse a pipeline that sets it (`validate_for_client`).
+    #[serde(default)]
+    pub merge_note_refcount: bool,
+
+    /// Rate limit for Control Change floods, per (channel, controller), in
+    /// messages per second (0 = off). Values arriving faster are coalesced
+    /// to the latest one, which is sent when the interval is up, so the
+    /// final position of a fader always gets through. Switch, selector and
+    /// channel-mode controllers (see `cc_is_throttled`) always pass.
+    /// Stateful, host only: applied through a `CcThrottle`; clients ignore it
+    /// and refuse a pipeline that sets it (`validate_for_client`).
+    #[serde(default)]
+    pub cc_throttle_hz: u16,
+
+    /// Note latch per source channel: Note Offs are dropped, and each Note
+    /// On first releases the note latched before it, so notes sustain
+    /// until the next one (ambient pads).
+    /// Stateful, host only: applied through a `NoteLatch`; clients ignore it
+    /// and refuse a pipeline that sets it (`validate_for_client`).
+    #[serde(default)]
+    pub latch: [bool; 16],
+
+    /// Monophonic mode per source channel, last-note priority: a Note On
+    /// first releases the note sounding before it, and Note Offs for keys
+    /// that aren't sounding are dropped.
+    /// Stateful, host only: applied through a `MonoVoice`; clients ignore it
+    /// and refuse a pipeline that sets it (`validate_for_client`).
+    #[serde(default)]
+    pub mono: [bool; 16],
+
+    /// On a mono channel, releasing the sounding key re-triggers the most
+    /// recent key still held (legato lines without gaps). Host only, like
+    /// `mono`.
+    #[serde(default)]
+    pub mono_retrigger: bool,
+
+    /// Chord generation per source channel: each Note On/Off is replaced
+    /// by one note per semitone offset, e.g. `[0, 4, 7]` for a major triad
+    /// (leave out 0 to drop the played note). Offsets that take a note
+    /// outside 0-127 are skipped. Applied by `harmonize()` /
+    /// `process_multi()`, before the rest of the pipeline. Host only:
+    /// clients run `process()` alone.
+    #[serde(default)]
+    pub harmonize: [Option<Vec<i8>>; 16],
+
+    /// MPE zone: channels that carry per-note expression (ROLI,
+    /// LinnStrument). Inside the zone the channel filter, channel remap and
+    /// the pitch bend / aftertouch filters are ignored so the per-note
+    /// channel rotation survives, and every channel uses the transpose of
+    /// the zone's first channel.
+    #[serde(default)]
+    pub mpe_zone: Option<MpeZone>,
+
+    /// Keyboard split zones: Note On/Off and poly aftertouch on a zone's
+    /// source channel within its note range are sent to its target channel
+    /// instead of `channel_remap`. Matched on the incoming note (before
+    /// transpose); the first matching zone wins, notes outside every zone
+    /// pass through untouched.
+    #[serde(default)]
+    pub zones: Vec<Zone>,
+}
+
+/// A keyboard split zone (channels are index 0-15, notes inclusive).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Zone {
+    pub low: u8,
+    pub high: u8,
+    pub source_channel: u8,
+    pub target_channel: u8,
+}
+
+impl Zone {
+    fn matches(&self, channel: u8, note: u8) -> bool {
+        self.source_channel == channel && (self.low..=self.high).contains(&note)
+    }
 }
 
+/// One controller remap: CC `from` on `channel` (index 0-15) becomes CC `to`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CcRemap {
+    pub channel: u8,
+    pub from: u8,
+    pub to: u8,
+}
+
+/// A contiguous channel range (index 0-15) treated as one MPE zone.
+/// For the MPE lower zone this is the master channel followed by its
+/// member channels, e.g. 0..=15.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MpeZone {
+    pub first_channel: u8,
+    pub last_channel: u8,
+}
+
+impl MpeZone {
+    pub fn contains(&self, channel: u8) -> bool {
+        (self.first_channel..=self.last_channel).contains(&channel)
+    }
+}
+
+/// Message categories let through (true = pass). Inside an MPE zone
+/// pitch bend and both kinds of aftertouch always pass; System Common
+/// messages (MTC, song position, ...) are never filtered.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MessageFilter {
+    /// Note On / Note Off, filtered as a pair
     pub note_on_off: bool,
     pub control_change: bool,
     pub program_change: bool,
     pub pitch_bend: bool,
-    pub aftertouch: bool,
+    pub channel_pressure: bool,
+    pub poly_aftertouch: bool,
+    /// SysEx, on top of `sysex_passthrough`
     pub sysex: bool,
+    /// Clock and Start/Continue/Stop
     pub clock: bool,
+    /// The other System Real-Time messages: active sensing, reset
+    pub realtime: bool,
 }
 
 impl Default for MessageFilter {
@@ -49,9 +200,11 @@ impl Default for MessageFilter {
             control_change: true,
             program_change: true,
             pitch_bend: true,
-            aftertouch: true,
+            channel_pressure: true,
+            poly_aftertouch: true,
             sysex: true,
             clock: true,
+            realtime: true,
         }
     }
 }
@@ -59,10 +212,30 @@ impl Default for MessageFilter {
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
 pub enum VelocityCurve {
     #[default]
+    #[serde(alias = "linear")]
     Linear,
+    #[serde(alias = "logarithmic")]
     Logarithmic,
+    #[serde(alias = "exponential")]
     Exponential,
+    #[serde(alias = "s_curve", alias = "scurve")]
     SCurve,
+    /// Dynamics like an audio compressor: the part of a velocity above
+    /// `threshold` is divided by `ratio` (below 1.0 expands instead), then
+    /// `makeup` is added to every velocity.
+    #[serde(alias = "compressor")]
+    Compressor { threshold: u8, ratio: f32, makeup: u8 },
+}
+
+/// How `normalize_note_off` writes a note release.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteOffForm {
+    /// Note Off (0x80) with release velocity 64
+    #[default]
+    NoteOff,
+    /// Note On (0x90) with velocity 0
+    NoteOnZero,
 }
 
 impl Default for PipelineConfig {
@@ -72,8 +245,23 @@ impl Default for PipelineConfig {
             message_filter: MessageFilter::default(),
             channel_remap: [0xFF; 16],
             transpose: [0; 16],
+            cc_remap: Vec::new(),
             velocity_curve: VelocityCurve::default(),
+            feedback_velocity_curve: VelocityCurve::default(),
+            velocity_min: 1,
+            velocity_max: 127,
+            normalize_note_off: false,
+            note_off_form: NoteOffForm::default(),
             sysex_passthrough: true,
+            min_note_duration_ms: 0,
+            merge_note_refcount: false,
+            cc_throttle_hz: 0,
+            latch: [false; 16],
+            mono: [false; 16],
+            mono_retrigger: false,
+            harmonize: Default::default(),
+            mpe_zone: None,
+            zones: Vec::new(),
         }
     }
 }
@@ -90,7 +278,146 @@ fn default_true() -> bool {
     true
 }
 
+fn default_velocity_min() -> u8 {
+    1
+}
+
+fn default_velocity_max() -> u8 {
+    127
+}
+
+/// Check a `velocity_min`/`velocity_max` pair: both within 1-127 (a Note
+/// On must not be rescaled into a Note Off) and `min <= max`.
+pub fn validate_velocity_range(min: u8, max: u8) -> Result<(), String> {
+    if !(1..=127).contains(&min) || !(1..=127).contains(&max) {
+        return Err(format!("velocity_min/velocity_max must be 1-127 (got {}, {})", min, max));
+    }
+    if min > max {
+        return Err(format!("velocity_min ({}) is greater than velocity_max ({})", min, max));
+    }
+    Ok(())
+}
+
+/// Check a velocity curve's parameters: a compressor's threshold and
+/// makeup within 0-127 and a positive, finite ratio.
+pub fn validate_velocity_curve(curve: &VelocityCurve) -> Result<(), String> {
+    if let VelocityCurve::Compressor { threshold, ratio, makeup } = *curve {
+        if threshold > 127 || makeup > 127 {
+            return Err(format!("compressor threshold/makeup must be 0-127 (got {}, {})", threshold, makeup));
+        }
+        if !(ratio.is_finite() && ratio > 0.0) {
+            return Err(format!("compressor ratio must be greater than 0 (got {})", ratio));
+        }
+    }
+    Ok(())
+}
+
 impl PipelineConfig {
+    /// Reject settings `process()` can't apply meaningfully.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_velocity_range(self.velocity_min, self.velocity_max)?;
+        validate_velocity_curve(&self.velocity_curve).map_err(|e| format!("velocity_curve: {}", e))?;
+        validate_velocity_curve(&self.feedback_velocity_curve).map_err(|e| format!("feedback_velocity_curve: {}", e))
+    }
+
+    /// The host-only settings (stateful stages and chord generation) that
+    /// are switched on. A client's `process()` does not apply them.
+    pub fn host_only_settings(&self) -> Vec<&'static str> {
+        let mut set = Vec::new();
+        if self.min_note_duration_ms > 0 {
+            set.push("min_note_duration_ms");
+        }
+        if self.merge_note_refcount {
+            set.push("merge_note_refcount");
+        }
+        if self.cc_throttle_hz > 0 {
+            set.push("cc_throttle_hz");
+        }
+        if self.latch.contains(&true) {
+            set.push("latch");
+        }
+        if self.mono.contains(&true) {
+            set.push("mono");
+        }
+        if self.mono_retrigger {
+            set.push("mono_retrigger");
+        }
+        if self.harmonize.iter().any(Option::is_some) {
+            set.push("harmonize");
+        }
+        set
+    }
+
+    /// `validate()` for a pipeline a client will run: also refuses the
+    /// host-only settings rather than ignoring them silently.
+    pub fn validate_for_client(&self) -> Result<(), String> {
+        self.validate()?;
+        let host_only = self.host_only_settings();
+        if !host_only.is_empty() {
+            return Err(format!("{} only apply on the host; clear them in a client pipeline", host_only.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// With `normalize_note_off` set, rewrite `msg` in place if it is a
+    /// note release. Used by `process()`, and by the host for the Note Offs
+    /// its stateful stages generate.
+    pub fn normalize_release(&self, msg: &mut [u8]) {
+        if !self.normalize_note_off || msg.len() < 3 {
+            return;
+        }
+        let channel = msg[0] & 0x0F;
+        let is_release = match msg[0] & 0xF0 {
+            0x80 => true,
+            0x90 => msg[2] == 0,
+            _ => false,
+        };
+        if is_release {
+            let (status, velocity) = match self.note_off_form {
+                NoteOffForm::NoteOff => (0x80, 64),
+                NoteOffForm::NoteOnZero => (0x90, 0),
+            };
+            msg[0] = status | channel;
+            msg[2] = velocity;
+        }
+    }
+
+    /// Expand one incoming message into the notes `harmonize` plays for
+    /// it, still in their pre-pipeline form. Anything that isn't a Note
+    /// On/Off on a harmonized channel comes back unchanged.
+    pub fn harmonize(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        self.harmonize_each(data, |msg| out.push(msg.to_vec()));
+        out
+    }
+
+    /// `harmonize()` without the allocations: calls `f` with each message.
+    pub fn harmonize_each(&self, data: &[u8], mut f: impl FnMut(&[u8])) {
+        let offsets = match data.first() {
+            Some(&status) if data.len() >= 3 && matches!(status & 0xF0, 0x80 | 0x90) => {
+                self.harmonize[(status & 0x0F) as usize].as_deref()
+            }
+            _ => None,
+        };
+        let Some(offsets) = offsets else {
+            return f(data);
+        };
+
+        for &offset in offsets {
+            let note = data[1] as i16 + offset as i16;
+            if (0..=127).contains(&note) {
+                f(&[data[0], note as u8, data[2]]);
+            }
+        }
+    }
+
+    /// `harmonize()` followed by `process()` on each resulting message.
+    pub fn process_multi(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        self.harmonize_each(data, |msg| out.extend(self.process(msg)));
+        out
+    }
+
     /// Process a MIDI message through the pipeline.
     /// Returns None if the message should be filtered out.
     /// Returns Some(processed_data) if the message should be forwarded.
@@ -108,9 +435,10 @@ impl PipelineConfig {
 
         let msg_type = status & 0xF0;
         let channel = (status & 0x0F) as usize;
+        let mpe_zone = self.mpe_zone.filter(|zone| zone.contains(channel as u8));
 
-        // Channel filter
-        if !self.channel_filter[channel] {
+        // Channel filter (never splits an MPE zone)
+        if mpe_zone.is_none() && !self.channel_filter[channel] {
             return None;
         }
 
@@ -121,8 +449,14 @@ impl PipelineConfig {
                     return None;
                 }
             }
+            // Per-note expression in an MPE zone always passes
             0xA0 | 0xD0 => {
-                if !self.message_filter.aftertouch {
+                let pass = if msg_type == 0xA0 {
+                    self.message_filter.poly_aftertouch
+                } else {
+                    self.message_filter.channel_pressure
+                };
+                if mpe_zone.is_none() && !pass {
                     return None;
                 }
             }
@@ -137,7 +471,7 @@ impl PipelineConfig {
                 }
             }
             0xE0 => {
-                if !self.message_filter.pitch_bend {
+                if mpe_zone.is_none() && !self.message_filter.pitch_bend {
                     return None;
                 }
             }
@@ -146,20 +480,41 @@ impl PipelineConfig {
 
         let mut result = data.to_vec();
 
-        // Channel remap
-        let dest_channel = if self.channel_remap[channel] != 0xFF {
+        // Keyboard split zones, then channel remap (MPE zone channels keep
+        // their per-note channel)
+        let split_channel = match msg_type {
+            0x80 | 0x90 | 0xA0 if mpe_zone.is_none() && data.len() >= 2 => self
+                .zones
+                .iter()
+                .find(|zone| zone.matches(channel as u8, data[1]))
+                .map(|zone| zone.target_channel & 0x0F),
+            _ => None,
+        };
+        let dest_channel = if let Some(target) = split_channel {
+            target
+        } else if mpe_zone.is_none() && self.channel_remap[channel] != 0xFF {
             self.channel_remap[channel] & 0x0F
         } else {
             channel as u8
         };
         result[0] = msg_type | dest_channel;
 
+        // Controller remap, on the source channel
+        if msg_type == 0xB0 && result.len() >= 2 {
+            let remap = self.cc_remap.iter().find(|r| r.channel == channel as u8 && r.from == data[1]);
+            if let Some(remap) = remap {
+                result[1] = remap.to & 0x7F;
+            }
+        }
+
         // Apply note processing (transpose, velocity curve)
         match msg_type {
             0x80 | 0x90 => {
                 if result.len() >= 3 {
-                    // Transpose
-                    let transpose = self.transpose[channel];
+                    // Transpose (one setting for the whole MPE zone)
+                    let transpose_channel =
+                        mpe_zone.map_or(channel, |zone| zone.first_channel as usize);
+                    let transpose = self.transpose[transpose_channel];
                     if transpose != 0 {
                         let note = result[1] as i16 + transpose as i16;
                         if !(0..=127).contains(&note) {
@@ -168,10 +523,13 @@ impl PipelineConfig {
                         result[1] = note as u8;
                     }
 
-                    // Velocity curve (only for Note On with velocity > 0)
+                    // Velocity curve, then range (only for Note On with velocity > 0)
                     if msg_type == 0x90 && result[2] > 0 {
-                        result[2] = apply_velocity_curve(result[2], self.velocity_curve);
+                        let curved = apply_velocity_curve(result[2], self.velocity_curve);
+                        result[2] = rescale_velocity(curved, self.velocity_min, self.velocity_max);
                     }
+
+                    self.normalize_release(&mut result);
                 }
             }
             _ => {}
@@ -180,11 +538,45 @@ impl PipelineConfig {
         Some(result)
     }
 
+    /// Apply the feedback-path velocity curve to a returning MIDI byte
+    /// stream (may hold several messages, with running status). Everything
+    /// other than Note On velocities is passed through untouched.
+    pub fn process_feedback(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        if self.feedback_velocity_curve == VelocityCurve::Linear {
+            return out;
+        }
+
+        let mut status = 0u8;
+        let mut data_index = 0;
+        for b in out.iter_mut() {
+            match *b {
+                // Real-Time may interleave anywhere and doesn't affect status
+                0xF8..=0xFF => {}
+                0x80..=0xF7 => {
+                    status = *b;
+                    data_index = 0;
+                }
+                _ => {
+                    if status & 0xF0 == 0x90 && data_index == 1 && *b > 0 {
+                        *b = apply_velocity_curve(*b, self.feedback_velocity_curve);
+                    }
+                    data_index = match status & 0xF0 {
+                        0xC0 | 0xD0 => 0,
+                        0x80 | 0x90 | 0xA0 | 0xB0 | 0xE0 => (data_index + 1) % 2,
+                        _ => data_index + 1,
+                    };
+                }
+            }
+        }
+        out
+    }
+
     fn process_system_message(&self, data: &[u8]) -> Option<Vec<u8>> {
         match data[0] {
-            0xF0 => {
+            0xF0 | 0xF7 => {
                 // SysEx
-                if self.sysex_passthrough {
+                if self.sysex_passthrough && self.message_filter.sysex {
                     Some(data.to_vec())
                 } else {
                     None
@@ -206,12 +598,433 @@ impl PipelineConfig {
                     None
                 }
             }
+            0xF9 | 0xFD..=0xFF => {
+                // Active Sensing, Reset (and the undefined ones)
+                if self.message_filter.realtime {
+                    Some(data.to_vec())
+                } else {
+                    None
+                }
+            }
             _ => Some(data.to_vec()),
         }
     }
 }
 
+/// Stateful half of `min_note_duration_ms`, tracked per (channel, note).
+///
+/// Feed every processed message through `process()`; Note Offs that were
+/// held back come out of `process()` or `poll()` once they are due, so the
+/// caller should also call `poll()` by `next_due()`.
+#[derive(Debug, Default)]
+pub struct NoteDebouncer {
+    /// Note On time of each sounding note
+    started: HashMap<(u8, u8), Instant>,
+    /// Held Note Offs: due time and the original message
+    deferred: HashMap<(u8, u8), (Instant, Vec<u8>)>,
+}
+
+impl NoteDebouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Debounce one MIDI message. Returns the messages to send now, in order:
+    /// any held Note Offs that have become due, then `msg` unless suppressed.
+    pub fn process(&mut self, msg: &[u8], min_duration: Duration, now: Instant) -> Vec<Vec<u8>> {
+        let mut out = self.poll(now);
+
+        let Some((key, is_on)) = note_key(msg) else {
+            out.push(msg.to_vec());
+            return out;
+        };
+        if min_duration.is_zero() {
+            self.started.remove(&key);
+            out.push(msg.to_vec());
+            return out;
+        }
+
+        if is_on {
+            // Re-trigger while the previous Note Off is held: a bounce, keep the note
+            if self.deferred.remove(&key).is_some() {
+                return out;
+            }
+            self.started.insert(key, now);
+            out.push(msg.to_vec());
+        } else {
+            match self.started.get(&key) {
+                Some(&start) if now.saturating_duration_since(start) < min_duration => {
+                    self.deferred.insert(key, (start + min_duration, msg.to_vec()));
+                }
+                _ => {
+                    self.started.remove(&key);
+                    out.push(msg.to_vec());
+                }
+            }
+        }
+        out
+    }
+
+    /// Release held Note Offs that are due.
+    pub fn poll(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        if self.deferred.is_empty() {
+            return Vec::new();
+        }
+        let mut due: Vec<(Instant, (u8, u8))> = self
+            .deferred
+            .iter()
+            .filter(|(_, (at, _))| *at <= now)
+            .map(|(key, (at, _))| (*at, *key))
+            .collect();
+        due.sort_unstable();
+
+        due.into_iter()
+            .filter_map(|(_, key)| {
+                self.started.remove(&key);
+                self.deferred.remove(&key).map(|(_, msg)| msg)
+            })
+            .collect()
+    }
+
+    /// When the next held Note Off becomes due, if any.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.deferred.values().map(|(at, _)| *at).min()
+    }
+}
+
+/// Whether `cc_throttle_hz` applies to controller `cc`. Only continuous
+/// controllers are coalesced: a sustain release, a bank or RPN/NRPN
+/// selection, data entry for it, or All Notes Off must arrive as sent and
+/// in order.
+pub fn cc_is_throttled(cc: u8) -> bool {
+    !matches!(
+        cc,
+        0 | 32          // Bank Select MSB/LSB
+        | 6 | 38        // Data Entry MSB/LSB
+        | 64..=69       // Sustain, Portamento, Sostenuto, Soft, Legato, Hold 2
+        | 96..=101      // Data Increment/Decrement, NRPN/RPN selectors
+        | 120..=127     // Channel mode (All Sound/Notes Off, Reset, Local, Omni, Mono/Poly)
+    )
+}
+
+/// Stateful half of `cc_throttle_hz`, tracked per (channel, controller).
+///
+/// Feed every processed Control Change through `process()`; the latest
+/// value held back in each interval comes out of `process()` or `poll()`
+/// once the interval is up, so the caller should also call `poll()` by
+/// `next_due()`.
+#[derive(Debug, Default)]
+pub struct CcThrottle {
+    /// Time each controller was last sent
+    sent: HashMap<(u8, u8), Instant>,
+    /// Coalesced values: due time and the latest message
+    pending: HashMap<(u8, u8), (Instant, Vec<u8>)>,
+}
+
+impl CcThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Throttle one MIDI message. Returns the messages to send now, in order:
+    /// any coalesced values that have become due, then `msg` unless held back.
+    /// Messages other than Control Change, and controllers exempt under
+    /// `cc_is_throttled`, pass straight through.
+    pub fn process(&mut self, msg: &[u8], rate_hz: u16, now: Instant) -> Vec<Vec<u8>> {
+        let mut out = self.poll(now);
+
+        if rate_hz == 0 || msg.len() < 3 || msg[0] & 0xF0 != 0xB0 || !cc_is_throttled(msg[1]) {
+            out.push(msg.to_vec());
+            return out;
+        }
+        let key = (msg[0] & 0x0F, msg[1]);
+        let interval = Duration::from_micros(1_000_000 / rate_hz as u64);
+
+        match self.sent.get(&key) {
+            Some(&last) if now.saturating_duration_since(last) < interval => {
+                self.pending.insert(key, (last + interval, msg.to_vec()));
+            }
+            _ => {
+                self.sent.insert(key, now);
+                self.pending.remove(&key);
+                out.push(msg.to_vec());
+            }
+        }
+        out
+    }
+
+    /// Send coalesced values that are due.
+    pub fn poll(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        let mut due: Vec<(Instant, (u8, u8))> = self
+            .pending
+            .iter()
+            .filter(|(_, (at, _))| *at <= now)
+            .map(|(key, (at, _))| (*at, *key))
+            .collect();
+        due.sort_unstable();
+
+        due.into_iter()
+            .filter_map(|(at, key)| {
+                self.sent.insert(key, at);
+                self.pending.remove(&key).map(|(_, msg)| msg)
+            })
+            .collect()
+    }
+
+    /// When the next coalesced value becomes due, if any.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|(at, _)| *at).min()
+    }
+}
+
+/// Stateful half of `merge_note_refcount`: which source notes currently
+/// hold each merged (channel, note).
+///
+/// Feed each message as it was before the pipeline alongside the processed
+/// result; a Note Off is only forwarded once no other source holds the note.
+#[derive(Debug, Default)]
+pub struct MergedNotes {
+    /// Merged (channel, note) → source (channel, note)s holding it
+    held: HashMap<(u8, u8), HashSet<(u8, u8)>>,
+}
+
+impl MergedNotes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `processed` (the pipeline output for `original`) should be sent.
+    pub fn forward(&mut self, original: &[u8], processed: &[u8]) -> bool {
+        let (Some((source, _)), Some((merged, is_on))) = (note_key(original), note_key(processed)) else {
+            return true;
+        };
+        if is_on {
+            self.held.entry(merged).or_default().insert(source);
+            return true;
+        }
+
+        let Some(sources) = self.held.get_mut(&merged) else {
+            return true;
+        };
+        sources.remove(&source);
+        if sources.is_empty() {
+            self.held.remove(&merged);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Number of merged notes currently held.
+    pub fn held_count(&self) -> usize {
+        self.held.len()
+    }
+}
+
+/// A played note and the (output channel, note)s it latched; nothing is
+/// latched while `outputs` is empty. Kept per channel and reused, so
+/// latching a note never allocates once the chord size has been seen.
+#[derive(Debug, Default)]
+struct LatchedChord {
+    played: u8,
+    outputs: Vec<(u8, u8)>,
+}
+
+/// Stateful half of `latch`: the notes currently latched on each source
+/// channel, as they left the pipeline.
+///
+/// Feed each message as it was before the pipeline alongside the processed
+/// result; the messages passed to `emit` replace the processed one. A
+/// harmonized note gives several processed notes for one original: they
+/// are latched together and released together.
+#[derive(Debug, Default)]
+pub struct NoteLatch {
+    /// Per source channel
+    held: [LatchedChord; 16],
+}
+
+impl NoteLatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emit the messages to send for `processed` (the pipeline output for
+    /// `original`), in order.
+    pub fn process(&mut self, latch: &[bool; 16], original: &[u8], processed: &[u8], mut emit: impl FnMut(&[u8])) {
+        let (Some(((source, played), _)), Some((output, is_on))) = (note_key(original), note_key(processed)) else {
+            emit(processed);
+            return;
+        };
+        let source = source as usize;
+
+        if !latch[source] {
+            // Latch switched off: let go of the notes it was still holding
+            self.release(source, &mut emit);
+            emit(processed);
+            return;
+        }
+        if !is_on {
+            return;
+        }
+
+        // Another note of the same chord joins the group; anything else
+        // (including a re-strike) replaces it
+        let held = &self.held[source];
+        if held.outputs.is_empty() || held.played != played || held.outputs.contains(&output) {
+            self.release(source, &mut emit);
+            self.held[source].played = played;
+        }
+        self.held[source].outputs.push(output);
+        emit(processed);
+    }
+
+    /// Note Offs for the notes latched on `source`.
+    fn release(&mut self, source: usize, emit: &mut impl FnMut(&[u8])) {
+        let held = &mut self.held[source];
+        for &(channel, note) in &held.outputs {
+            emit(&[0x80 | channel, note, 0]);
+        }
+        held.outputs.clear();
+    }
+
+    /// Number of notes currently latched, over all channels.
+    pub fn held_count(&self) -> usize {
+        self.held.iter().map(|chord| chord.outputs.len()).sum()
+    }
+}
+
+/// Stateful half of `mono`: the keys held on each source channel, most
+/// recent last, and whether that one is sounding.
+///
+/// Fed like `NoteLatch`: each message as it was before the pipeline
+/// alongside the processed result; the messages passed to `emit` replace
+/// the processed one. A harmonized chord is one voice.
+#[derive(Debug, Default)]
+pub struct MonoVoice {
+    /// Per source channel: (played key, a Note On it produced), one entry
+    /// per output note (several for a harmonized chord), grouped by key.
+    /// Reused, so holding keys doesn't allocate once warmed up.
+    held: [Vec<(u8, [u8; 3])>; 16],
+    sounding: [bool; 16],
+}
+
+impl MonoVoice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emit the messages to send for `processed` (the pipeline output for
+    /// `original`), in order.
+    pub fn process(
+        &mut self,
+        mono: &[bool; 16],
+        retrigger: bool,
+        original: &[u8],
+        processed: &[u8],
+        mut emit: impl FnMut(&[u8]),
+    ) {
+        let (Some(((source, played), _)), Some(((channel, note), is_on))) = (note_key(original), note_key(processed))
+        else {
+            emit(processed);
+            return;
+        };
+        let source = source as usize;
+
+        if !mono[source] {
+            // Mono switched off: the held keys' own Note Offs release them
+            self.held[source].clear();
+            self.sounding[source] = false;
+            emit(processed);
+            return;
+        }
+
+        let held = &mut self.held[source];
+        let is_output = |on: &[u8; 3]| (on[0] & 0x0F, on[1]) == (channel, note);
+        let top = top_key(held);
+        let sounding = self.sounding[source] && top < held.len();
+        if is_on {
+            // Another note of the sounding chord joins it; anything else
+            // cuts the sounding key off
+            let joins = sounding && held[top].0 == played && !held[top..].iter().any(|(_, on)| is_output(on));
+            if !joins {
+                if sounding {
+                    for (_, on) in &held[top..] {
+                        emit(&[0x80 | (on[0] & 0x0F), on[1], 0]);
+                    }
+                }
+                held.retain(|&(key, _)| key != played);
+                self.sounding[source] = true;
+            }
+            held.push((played, [processed[0], processed[1], processed[2]]));
+            emit(processed);
+            return;
+        }
+
+        // A key pressed before mono was switched on
+        if !held.iter().any(|&(key, _)| key == played) {
+            emit(processed);
+            return;
+        }
+        let current = sounding && held[top].0 == played;
+        held.retain(|(key, on)| *key != played || !is_output(on));
+        if !current {
+            return;
+        }
+        emit(processed);
+        if held.last().is_some_and(|&(key, _)| key == played) {
+            return; // other notes of the chord still sound
+        }
+        self.sounding[source] = false;
+        if retrigger && !held.is_empty() {
+            for (_, on) in &held[top_key(held)..] {
+                emit(on);
+            }
+            self.sounding[source] = true;
+        }
+    }
+
+    /// Number of keys currently held, over all mono channels.
+    pub fn held_count(&self) -> usize {
+        self.held.iter().map(|held| held.chunk_by(|a, b| a.0 == b.0).count()).sum()
+    }
+}
+
+/// Where the most recent key's entries start in a `MonoVoice` channel
+/// (the length of `held` if nothing is held).
+fn top_key(held: &[(u8, [u8; 3])]) -> usize {
+    match held.last() {
+        Some(&(last, _)) => held.iter().rposition(|&(key, _)| key != last).map_or(0, |i| i + 1),
+        None => 0,
+    }
+}
+
+/// (channel, note) of a Note On/Off, and whether it is a Note On.
+/// Note On with velocity 0 counts as Note Off.
+fn note_key(msg: &[u8]) -> Option<((u8, u8), bool)> {
+    if msg.len() < 3 {
+        return None;
+    }
+    let key = (msg[0] & 0x0F, msg[1]);
+    match msg[0] & 0xF0 {
+        0x90 => Some((key, msg[2] > 0)),
+        0x80 => Some((key, false)),
+        _ => None,
+    }
+}
+
 fn apply_velocity_curve(velocity: u8, curve: VelocityCurve) -> u8 {
+    if let VelocityCurve::Compressor { threshold, ratio, makeup } = curve {
+        let v = velocity as f32;
+        let threshold = threshold as f32;
+        // A zero, negative or NaN ratio would invert or blow up; treat as 1:1
+        let ratio = if ratio > 0.0 { ratio } else { 1.0 };
+        let compressed = if v > threshold { threshold + (v - threshold) / ratio } else { v };
+        return (compressed + makeup as f32).round().clamp(1.0, 127.0) as u8;
+    }
+
     let v = velocity as f32 / 127.0;
     let result = match curve {
         VelocityCurve::Linear => v,
@@ -222,10 +1035,22 @@ fn apply_velocity_curve(velocity: u8, curve: VelocityCurve) -> u8 {
             let t = v.clamp(0.0, 1.0);
             t * t * (3.0 - 2.0 * t)
         }
+        VelocityCurve::Compressor { .. } => unreachable!("handled above"),
     };
     (result * 127.0).round().clamp(1.0, 127.0) as u8
 }
 
+/// Map a Note On velocity 1-127 linearly onto `min..=max`.
+fn rescale_velocity(velocity: u8, min: u8, max: u8) -> u8 {
+    if (min, max) == (1, 127) {
+        return velocity;
+    }
+    let span = max.saturating_sub(min) as u32;
+    let scaled = (velocity.clamp(1, 127) as u32 - 1) * span;
+    // Rounded to the nearest step; never below 1 so it stays a Note On
+    (min as u32 + (scaled + 63) / 126).clamp(1, 127) as u8
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,6 +1097,61 @@ mod tests {
         assert_eq!(result[0], 0x95); // Channel 5 (6th channel, 0-indexed)
     }
 
+    #[test]
+    fn test_mpe_zone_channels_survive_remap_and_filters() {
+        let mut pipeline = PipelineConfig::default();
+        // A remap/filter setup that would collapse the per-note channels
+        pipeline.channel_remap = [0; 16];
+        pipeline.channel_filter[3] = false;
+        pipeline.message_filter.pitch_bend = false;
+        pipeline.transpose[1] = 12;
+        pipeline.mpe_zone = Some(MpeZone { first_channel: 1, last_channel: 7 });
+
+        // Member channels keep their channel, bend and pressure
+        assert_eq!(pipeline.process(&[0x93, 60, 100]), Some(vec![0x93, 72, 100]));
+        assert_eq!(pipeline.process(&[0xE3, 0, 80]), Some(vec![0xE3, 0, 80]));
+        assert_eq!(pipeline.process(&[0xD5, 40]), Some(vec![0xD5, 40]));
+
+        // Outside the zone the usual remap and filters apply
+        assert_eq!(pipeline.process(&[0x99, 60, 100]), Some(vec![0x90, 60, 100]));
+        assert!(pipeline.process(&[0xE9, 0, 80]).is_none());
+    }
+
+    #[test]
+    fn test_zones_split_by_note_range() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.zones = vec![
+            Zone { low: 21, high: 59, source_channel: 0, target_channel: 1 },
+            Zone { low: 60, high: 108, source_channel: 0, target_channel: 2 },
+            // Overlaps the first zone: never reached
+            Zone { low: 40, high: 50, source_channel: 0, target_channel: 9 },
+        ];
+
+        // Boundary notes land in their own zone
+        assert_eq!(pipeline.process(&[0x90, 21, 100]), Some(vec![0x91, 21, 100]));
+        assert_eq!(pipeline.process(&[0x80, 59, 0]), Some(vec![0x81, 59, 0]));
+        assert_eq!(pipeline.process(&[0x90, 60, 100]), Some(vec![0x92, 60, 100]));
+        assert_eq!(pipeline.process(&[0xA0, 108, 30]), Some(vec![0xA2, 108, 30]));
+        // First match wins for overlapping zones
+        assert_eq!(pipeline.process(&[0x90, 45, 100]), Some(vec![0x91, 45, 100]));
+
+        // Outside every zone, other channels and non-note messages are untouched
+        assert_eq!(pipeline.process(&[0x90, 20, 100]), Some(vec![0x90, 20, 100]));
+        assert_eq!(pipeline.process(&[0x90, 109, 100]), Some(vec![0x90, 109, 100]));
+        assert_eq!(pipeline.process(&[0x93, 60, 100]), Some(vec![0x93, 60, 100]));
+        assert_eq!(pipeline.process(&[0xB0, 64, 127]), Some(vec![0xB0, 64, 127]));
+    }
+
+    #[test]
+    fn test_zones_match_before_transpose() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.transpose[0] = 12;
+        pipeline.zones = vec![Zone { low: 0, high: 59, source_channel: 0, target_channel: 1 }];
+
+        // C3 (59) is in the zone even though it is transposed out of it
+        assert_eq!(pipeline.process(&[0x90, 59, 100]), Some(vec![0x91, 71, 100]));
+    }
+
     #[test]
     fn test_transpose() {
         let mut pipeline = PipelineConfig::default();
@@ -290,6 +1170,148 @@ mod tests {
         assert!(pipeline.process(&[0x90, 100, 100]).is_none());
     }
 
+    #[test]
+    fn velocity_range_rescales_note_ons() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.velocity_min = 20;
+        pipeline.velocity_max = 100;
+
+        assert_eq!(pipeline.process(&[0x90, 60, 1]), Some(vec![0x90, 60, 20]));
+        assert_eq!(pipeline.process(&[0x90, 60, 127]), Some(vec![0x90, 60, 100]));
+        assert_eq!(pipeline.process(&[0x90, 60, 64]), Some(vec![0x90, 60, 60]));
+
+        // Note Offs, including Note On velocity 0, are left alone
+        assert_eq!(pipeline.process(&[0x90, 60, 0]), Some(vec![0x90, 60, 0]));
+        assert_eq!(pipeline.process(&[0x80, 60, 64]), Some(vec![0x80, 60, 64]));
+
+        // A fixed velocity
+        pipeline.velocity_min = 90;
+        pipeline.velocity_max = 90;
+        assert_eq!(pipeline.process(&[0x90, 60, 5]), Some(vec![0x90, 60, 90]));
+        assert_eq!(pipeline.process(&[0x90, 60, 0]), Some(vec![0x90, 60, 0]));
+    }
+
+    #[test]
+    fn velocity_range_is_validated() {
+        assert!(PipelineConfig::default().validate().is_ok());
+        assert!(validate_velocity_range(20, 20).is_ok());
+        assert!(validate_velocity_range(100, 20).is_err());
+        assert!(validate_velocity_range(0, 127).is_err(), "min 0 would turn Note Ons into Note Offs");
+        assert!(validate_velocity_range(1, 128).is_err());
+
+        let mut config = PipelineConfig {
+            feedback_velocity_curve: VelocityCurve::Compressor { threshold: 90, ratio: 0.0, makeup: 0 },
+            ..PipelineConfig::default()
+        };
+        assert!(config.validate().unwrap_err().starts_with("feedback_velocity_curve"));
+        config.feedback_velocity_curve = VelocityCurve::Compressor { threshold: 200, ratio: 2.0, makeup: 0 };
+        assert!(config.validate().is_err());
+        config.feedback_velocity_curve = VelocityCurve::Compressor { threshold: 90, ratio: 0.5, makeup: 10 };
+        assert!(config.validate().is_ok(), "a ratio below 1 expands");
+    }
+
+    #[test]
+    fn client_pipelines_refuse_host_only_settings() {
+        let mut config = PipelineConfig::default();
+        assert!(config.host_only_settings().is_empty());
+        assert!(config.validate_for_client().is_ok());
+
+        config.latch[3] = true;
+        config.cc_throttle_hz = 50;
+        assert_eq!(config.host_only_settings(), vec!["cc_throttle_hz", "latch"]);
+        let err = config.validate_for_client().unwrap_err();
+        assert!(err.starts_with("cc_throttle_hz, latch only apply on the host"), "{}", err);
+        assert!(config.validate().is_ok(), "still fine for the host");
+    }
+
+    #[test]
+    fn cc_remap_rewrites_the_controller_on_one_channel() {
+        let mut pipeline = PipelineConfig::default();
+        // Default: every controller on every channel is left alone
+        for status in 0xB0..=0xBF {
+            for cc in [0, 21, 71, 127] {
+                assert_eq!(pipeline.process(&[status, cc, 64]), Some(vec![status, cc, 64]));
+            }
+        }
+
+        pipeline.cc_remap = (0..8).map(|i| CcRemap { channel: 0, from: 21 + i, to: 71 + i }).collect();
+        assert_eq!(pipeline.process(&[0xB0, 21, 10]), Some(vec![0xB0, 71, 10]));
+        assert_eq!(pipeline.process(&[0xB0, 28, 127]), Some(vec![0xB0, 78, 127]));
+        assert_eq!(pipeline.process(&[0xB0, 29, 5]), Some(vec![0xB0, 29, 5]));
+        // Other channels and other message types keep their data bytes
+        assert_eq!(pipeline.process(&[0xB1, 21, 10]), Some(vec![0xB1, 21, 10]));
+        assert_eq!(pipeline.process(&[0x90, 21, 10]), Some(vec![0x90, 21, 10]));
+    }
+
+    #[test]
+    fn cc_remap_matches_the_source_channel_before_channel_remap() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.channel_remap[0] = 3;
+        pipeline.cc_remap = vec![
+            CcRemap { channel: 0, from: 21, to: 71 },
+            CcRemap { channel: 3, from: 21, to: 99 },
+        ];
+
+        // Channel 1's CC 21 takes its own entry, then moves to channel 4
+        assert_eq!(pipeline.process(&[0xB0, 21, 10]), Some(vec![0xB3, 71, 10]));
+        // Played on channel 4 directly, the channel 4 entry applies
+        assert_eq!(pipeline.process(&[0xB3, 21, 10]), Some(vec![0xB3, 99, 10]));
+    }
+
+    #[test]
+    fn message_filter_drops_only_the_filtered_category() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.message_filter.program_change = false;
+
+        assert_eq!(pipeline.process(&[0xC0, 5]), None);
+        assert_eq!(pipeline.process(&[0xCF, 127]), None);
+        assert_eq!(pipeline.process(&[0x90, 60, 100]), Some(vec![0x90, 60, 100]));
+        assert_eq!(pipeline.process(&[0xB0, 7, 100]), Some(vec![0xB0, 7, 100]));
+        assert_eq!(pipeline.process(&[0xD0, 40]), Some(vec![0xD0, 40]));
+        assert_eq!(pipeline.process(&[0xF8]), Some(vec![0xF8]));
+        assert_eq!(pipeline.process(&[0xF0, 0x7E, 0xF7]), Some(vec![0xF0, 0x7E, 0xF7]));
+
+        // Aftertouch kinds are separate
+        pipeline.message_filter.poly_aftertouch = false;
+        assert_eq!(pipeline.process(&[0xA0, 60, 50]), None);
+        assert_eq!(pipeline.process(&[0xD0, 40]), Some(vec![0xD0, 40]));
+
+        // Real-time and SysEx for safety; System Common still passes
+        pipeline.message_filter.clock = false;
+        pipeline.message_filter.realtime = false;
+        pipeline.message_filter.sysex = false;
+        for msg in [&[0xF8][..], &[0xFA], &[0xFE], &[0xFF], &[0xF0, 0x7E, 0xF7]] {
+            assert_eq!(pipeline.process(msg), None, "{:02X?}", msg);
+        }
+        assert_eq!(pipeline.process(&[0xF2, 0, 8]), Some(vec![0xF2, 0, 8]));
+    }
+
+    #[test]
+    fn normalize_note_off_unifies_both_release_encodings() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.normalize_note_off = true;
+
+        // Default form: Note Off with velocity 64
+        assert_eq!(pipeline.process(&[0x83, 60, 0]), Some(vec![0x83, 60, 64]));
+        assert_eq!(pipeline.process(&[0x83, 60, 112]), Some(vec![0x83, 60, 64]));
+        assert_eq!(pipeline.process(&[0x93, 60, 0]), Some(vec![0x83, 60, 64]));
+
+        pipeline.note_off_form = NoteOffForm::NoteOnZero;
+        assert_eq!(pipeline.process(&[0x83, 60, 40]), Some(vec![0x93, 60, 0]));
+        assert_eq!(pipeline.process(&[0x93, 60, 0]), Some(vec![0x93, 60, 0]));
+
+        // Note Ons and everything else are untouched
+        assert_eq!(pipeline.process(&[0x93, 60, 100]), Some(vec![0x93, 60, 100]));
+        assert_eq!(pipeline.process(&[0xB3, 7, 0]), Some(vec![0xB3, 7, 0]));
+        assert_eq!(pipeline.process(&[0xA3, 60, 0]), Some(vec![0xA3, 60, 0]));
+        assert_eq!(pipeline.process(&[0xC3, 5]), Some(vec![0xC3, 5]));
+
+        // Off by default
+        let pipeline = PipelineConfig::default();
+        assert_eq!(pipeline.process(&[0x93, 60, 0]), Some(vec![0x93, 60, 0]));
+        assert_eq!(pipeline.process(&[0x83, 60, 112]), Some(vec![0x83, 60, 112]));
+    }
+
     #[test]
     fn test_velocity_curves() {
         // Linear: input = output
@@ -305,6 +1327,267 @@ mod tests {
         assert!(s > 0 && s <= 127);
     }
 
+    #[test]
+    fn test_compressor_passes_velocities_below_threshold() {
+        let curve = VelocityCurve::Compressor { threshold: 80, ratio: 4.0, makeup: 0 };
+        for v in [1, 40, 79, 80] {
+            assert_eq!(apply_velocity_curve(v, curve), v);
+        }
+    }
+
+    #[test]
+    fn test_compressor_scales_velocities_above_threshold() {
+        let curve = VelocityCurve::Compressor { threshold: 80, ratio: 4.0, makeup: 0 };
+        // 80 + (120 - 80) / 4
+        assert_eq!(apply_velocity_curve(120, curve), 90);
+        assert_eq!(apply_velocity_curve(127, curve), 92);
+
+        // Makeup gain lifts everything after compression
+        let curve = VelocityCurve::Compressor { threshold: 80, ratio: 4.0, makeup: 10 };
+        assert_eq!(apply_velocity_curve(120, curve), 100);
+        assert_eq!(apply_velocity_curve(50, curve), 60);
+
+        // Ratio below 1 expands
+        let curve = VelocityCurve::Compressor { threshold: 64, ratio: 0.5, makeup: 0 };
+        assert_eq!(apply_velocity_curve(80, curve), 96);
+    }
+
+    #[test]
+    fn test_compressor_clamps_and_only_touches_note_on() {
+        let curve = VelocityCurve::Compressor { threshold: 100, ratio: 2.0, makeup: 40 };
+        assert_eq!(apply_velocity_curve(127, curve), 127);
+        assert_eq!(apply_velocity_curve(120, curve), 127);
+        let expand = VelocityCurve::Compressor { threshold: 10, ratio: 0.1, makeup: 0 };
+        assert_eq!(apply_velocity_curve(100, expand), 127);
+        let bad_ratio = VelocityCurve::Compressor { threshold: 10, ratio: 0.0, makeup: 0 };
+        assert_eq!(apply_velocity_curve(100, bad_ratio), 100);
+
+        let mut pipeline = PipelineConfig::default();
+        pipeline.velocity_curve = curve;
+        assert_eq!(pipeline.process(&[0x90, 60, 120]), Some(vec![0x90, 60, 127]));
+        assert_eq!(pipeline.process(&[0x90, 60, 0]), Some(vec![0x90, 60, 0]));
+        assert_eq!(pipeline.process(&[0x80, 60, 120]), Some(vec![0x80, 60, 120]));
+        assert_eq!(pipeline.process(&[0xB0, 7, 120]), Some(vec![0xB0, 7, 120]));
+    }
+
+    #[test]
+    fn test_compressor_survives_the_pipeline_config_api() {
+        let config: PipelineConfig = serde_json::from_value(serde_json::json!({
+            "velocity_curve": { "compressor": { "threshold": 90, "ratio": 3.0, "makeup": 5 } },
+        }))
+        .unwrap();
+        assert_eq!(config.velocity_curve, VelocityCurve::Compressor { threshold: 90, ratio: 3.0, makeup: 5 });
+
+        let json = serde_json::to_value(&config).unwrap();
+        let back: PipelineConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(back.velocity_curve, config.velocity_curve);
+    }
+
+    /// Pipeline + latch, the way the host applies them
+    fn latched(pipeline: &PipelineConfig, latch: &mut NoteLatch, msg: &[u8]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        if let Some(processed) = pipeline.process(msg) {
+            latch.process(&pipeline.latch, msg, &processed, |m| out.push(m.to_vec()));
+        }
+        out
+    }
+
+    #[test]
+    fn test_latch_replaces_the_previous_note() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.latch[0] = true;
+        pipeline.channel_remap[0] = 3;
+        let mut latch = NoteLatch::new();
+
+        assert_eq!(latched(&pipeline, &mut latch, &[0x90, 60, 100]), vec![vec![0x93, 60, 100]]);
+        // Note Off (and Note On velocity 0) are swallowed: the note sustains
+        assert!(latched(&pipeline, &mut latch, &[0x80, 60, 0]).is_empty());
+        assert!(latched(&pipeline, &mut latch, &[0x90, 60, 0]).is_empty());
+        assert_eq!(latch.held_count(), 1);
+
+        // The next note releases the latched one first (on the output channel)
+        assert_eq!(
+            latched(&pipeline, &mut latch, &[0x90, 64, 90]),
+            vec![vec![0x83, 60, 0], vec![0x93, 64, 90]]
+        );
+        // Other messages on the channel pass untouched
+        assert_eq!(latched(&pipeline, &mut latch, &[0xB0, 1, 64]), vec![vec![0xB3, 1, 64]]);
+
+        // Turning the latch off lets go of the held note
+        pipeline.latch[0] = false;
+        assert_eq!(
+            latched(&pipeline, &mut latch, &[0x90, 67, 80]),
+            vec![vec![0x83, 64, 0], vec![0x93, 67, 80]]
+        );
+        assert_eq!(latch.held_count(), 0);
+    }
+
+    #[test]
+    fn test_latch_leaves_other_channels_alone() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.latch[0] = true;
+        let mut latch = NoteLatch::new();
+
+        latched(&pipeline, &mut latch, &[0x90, 60, 100]);
+        assert_eq!(latched(&pipeline, &mut latch, &[0x91, 48, 100]), vec![vec![0x91, 48, 100]]);
+        assert_eq!(latched(&pipeline, &mut latch, &[0x81, 48, 0]), vec![vec![0x81, 48, 0]]);
+        assert_eq!(latched(&pipeline, &mut latch, &[0x91, 50, 100]), vec![vec![0x91, 50, 100]]);
+        // Channel 1's latched note is still held
+        assert_eq!(latch.held_count(), 1);
+    }
+
+    /// Pipeline + mono voice, the way the host applies them
+    fn voiced(pipeline: &PipelineConfig, mono: &mut MonoVoice, msg: &[u8]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        if let Some(processed) = pipeline.process(msg) {
+            mono.process(&pipeline.mono, pipeline.mono_retrigger, msg, &processed, |m| out.push(m.to_vec()));
+        }
+        out
+    }
+
+    #[test]
+    fn test_mono_last_note_priority() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.mono[0] = true;
+        let mut mono = MonoVoice::new();
+
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x90, 57, 100]), vec![vec![0x90, 57, 100]]);
+        // B cuts A off before it sounds
+        assert_eq!(
+            voiced(&pipeline, &mut mono, &[0x90, 59, 90]),
+            vec![vec![0x80, 57, 0], vec![0x90, 59, 90]]
+        );
+        // A's own release is dropped: it was already silenced
+        assert!(voiced(&pipeline, &mut mono, &[0x80, 57, 0]).is_empty());
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x80, 59, 0]), vec![vec![0x80, 59, 0]]);
+        assert_eq!(mono.held_count(), 0);
+
+        // Other channels stay polyphonic
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x91, 60, 100]), vec![vec![0x91, 60, 100]]);
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x91, 64, 100]), vec![vec![0x91, 64, 100]]);
+    }
+
+    #[test]
+    fn test_mono_overlap_without_retrigger_leaves_a_silent() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.mono[0] = true;
+        let mut mono = MonoVoice::new();
+
+        voiced(&pipeline, &mut mono, &[0x90, 57, 100]);
+        voiced(&pipeline, &mut mono, &[0x90, 59, 90]);
+        // Release B while A is held: nothing sounds, A stays silent
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x80, 59, 0]), vec![vec![0x80, 59, 0]]);
+        assert!(voiced(&pipeline, &mut mono, &[0x90, 57, 0]).is_empty());
+        // The next note starts clean, with no stray Note Off
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x90, 62, 80]), vec![vec![0x90, 62, 80]]);
+    }
+
+    #[test]
+    fn test_mono_retrigger_returns_to_the_held_note() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.mono[0] = true;
+        pipeline.mono_retrigger = true;
+        pipeline.channel_remap[0] = 2;
+        let mut mono = MonoVoice::new();
+
+        voiced(&pipeline, &mut mono, &[0x90, 57, 100]);
+        voiced(&pipeline, &mut mono, &[0x90, 59, 90]);
+        voiced(&pipeline, &mut mono, &[0x90, 60, 80]);
+        // Release C: back to B (most recent still held), at B's velocity
+        assert_eq!(
+            voiced(&pipeline, &mut mono, &[0x80, 60, 0]),
+            vec![vec![0x82, 60, 0], vec![0x92, 59, 90]]
+        );
+        // Releasing A (not sounding) changes nothing
+        assert!(voiced(&pipeline, &mut mono, &[0x80, 57, 0]).is_empty());
+        // Release B: nothing left to return to
+        assert_eq!(voiced(&pipeline, &mut mono, &[0x80, 59, 0]), vec![vec![0x82, 59, 0]]);
+        assert_eq!(mono.held_count(), 0);
+    }
+
+    #[test]
+    fn test_mono_keeps_a_harmonized_chord_as_one_voice() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.mono[0] = true;
+        pipeline.harmonize[0] = Some(vec![0, 7]);
+        let mut mono = MonoVoice::new();
+        // Each chord note is fed with the played key as its original
+        let mut play = |msg: &[u8]| -> Vec<Vec<u8>> {
+            let mut out = Vec::new();
+            for processed in pipeline.process_multi(msg) {
+                mono.process(&pipeline.mono, false, msg, &processed, |m| out.push(m.to_vec()));
+            }
+            out
+        };
+
+        assert_eq!(play(&[0x90, 60, 100]), vec![vec![0x90, 60, 100], vec![0x90, 67, 100]]);
+        assert_eq!(
+            play(&[0x90, 62, 100]),
+            vec![vec![0x80, 60, 0], vec![0x80, 67, 0], vec![0x90, 62, 100], vec![0x90, 69, 100]]
+        );
+        assert_eq!(play(&[0x80, 62, 0]), vec![vec![0x80, 62, 0], vec![0x80, 69, 0]]);
+    }
+
+    #[test]
+    fn test_harmonize_mirrors_chords_on_note_off() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.harmonize[0] = Some(vec![0, 4, 7]);
+        pipeline.transpose[0] = 12;
+
+        // The whole chord goes through the rest of the pipeline
+        assert_eq!(
+            pipeline.process_multi(&[0x90, 60, 100]),
+            vec![vec![0x90, 72, 100], vec![0x90, 76, 100], vec![0x90, 79, 100]]
+        );
+        // Note Off (and Note On velocity 0) releases every generated note
+        assert_eq!(
+            pipeline.process_multi(&[0x80, 60, 0]),
+            vec![vec![0x80, 72, 0], vec![0x80, 76, 0], vec![0x80, 79, 0]]
+        );
+        assert_eq!(
+            pipeline.process_multi(&[0x90, 60, 0]),
+            vec![vec![0x90, 72, 0], vec![0x90, 76, 0], vec![0x90, 79, 0]]
+        );
+
+        // Other messages and other channels are untouched
+        assert_eq!(pipeline.process_multi(&[0xB0, 64, 127]), vec![vec![0xB0, 64, 127]]);
+        assert_eq!(pipeline.process_multi(&[0x91, 60, 100]), vec![vec![0x91, 60, 100]]);
+    }
+
+    #[test]
+    fn test_harmonize_skips_notes_out_of_range() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.harmonize[2] = Some(vec![-12, 0, 12]);
+
+        assert_eq!(pipeline.harmonize(&[0x92, 120, 90]), vec![vec![0x92, 108, 90], vec![0x92, 120, 90]]);
+        assert_eq!(pipeline.harmonize(&[0x82, 5, 0]), vec![vec![0x82, 5, 0], vec![0x82, 17, 0]]);
+
+        // Without 0 the played note itself is replaced
+        pipeline.harmonize[2] = Some(vec![3, 7]);
+        assert_eq!(pipeline.harmonize(&[0x92, 60, 90]), vec![vec![0x92, 63, 90], vec![0x92, 67, 90]]);
+    }
+
+    #[test]
+    fn test_feedback_curve_is_independent_of_forward_curve() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.velocity_curve = VelocityCurve::Exponential;
+        pipeline.feedback_velocity_curve = VelocityCurve::SCurve;
+
+        let forward = pipeline.process(&[0x90, 60, 40]).unwrap();
+        assert_eq!(forward[2], apply_velocity_curve(40, VelocityCurve::Exponential));
+
+        // Returning Note On, a running-status Note On, a CC and a Note Off
+        let feedback = pipeline.process_feedback(&[0x90, 60, 40, 62, 100, 0xB0, 7, 40, 0x80, 60, 40]);
+        let s40 = apply_velocity_curve(40, VelocityCurve::SCurve);
+        let s100 = apply_velocity_curve(100, VelocityCurve::SCurve);
+        assert_ne!(s40, forward[2]);
+        assert_eq!(feedback, vec![0x90, 60, s40, 62, s100, 0xB0, 7, 40, 0x80, 60, 40]);
+
+        // Feedback curve off: returning MIDI is untouched even with a forward curve
+        pipeline.feedback_velocity_curve = VelocityCurve::Linear;
+        assert_eq!(pipeline.process_feedback(&[0x90, 60, 40]), vec![0x90, 60, 40]);
+    }
+
     #[test]
     fn test_sysex_filter() {
         let mut pipeline = PipelineConfig::default();
@@ -317,4 +1600,152 @@ mod tests {
         pipeline.sysex_passthrough = true;
         assert!(pipeline.process(&[0xF0, 0x7E, 0x7F, 0xF7]).is_some());
     }
+
+    #[test]
+    fn test_debounce_collapses_bounce_into_one_note() {
+        let mut debouncer = NoteDebouncer::new();
+        let min = Duration::from_millis(30);
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        // On / Off / On / Off within 30ms of the first Note On
+        let mut sent = Vec::new();
+        sent.extend(debouncer.process(&[0x90, 60, 100], min, at(0)));
+        sent.extend(debouncer.process(&[0x80, 60, 0], min, at(4)));
+        sent.extend(debouncer.process(&[0x90, 60, 90], min, at(8)));
+        sent.extend(debouncer.process(&[0x80, 60, 0], min, at(12)));
+        assert_eq!(sent, vec![vec![0x90, 60, 100]]);
+
+        // The single Note Off is released once the minimum duration has passed
+        assert_eq!(debouncer.next_due(), Some(at(30)));
+        assert!(debouncer.poll(at(20)).is_empty());
+        assert_eq!(debouncer.poll(at(30)), vec![vec![0x80, 60, 0]]);
+        assert_eq!(debouncer.next_due(), None);
+    }
+
+    #[test]
+    fn test_debounce_is_per_note_and_passes_long_notes() {
+        let mut debouncer = NoteDebouncer::new();
+        let min = Duration::from_millis(30);
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        debouncer.process(&[0x90, 60, 100], min, at(0));
+        debouncer.process(&[0x91, 60, 100], min, at(0));
+        // Same note on another channel is held independently
+        assert!(debouncer.process(&[0x91, 60, 0], min, at(5)).is_empty());
+        // A note held past the threshold is released immediately
+        assert_eq!(debouncer.process(&[0x80, 60, 0], min, at(50)), vec![vec![0x91, 60, 0], vec![0x80, 60, 0]]);
+        // Non-note messages pass straight through
+        assert_eq!(debouncer.process(&[0xB0, 7, 100], min, at(51)), vec![vec![0xB0, 7, 100]]);
+    }
+
+    #[test]
+    fn test_debounce_disabled_passes_everything() {
+        let mut debouncer = NoteDebouncer::new();
+        let t0 = Instant::now();
+        assert_eq!(debouncer.process(&[0x90, 60, 100], Duration::ZERO, t0).len(), 1);
+        assert_eq!(debouncer.process(&[0x80, 60, 0], Duration::ZERO, t0).len(), 1);
+        assert_eq!(debouncer.next_due(), None);
+    }
+
+    #[test]
+    fn test_cc_throttle_limits_a_sweep_and_keeps_the_last_value() {
+        let mut throttle = CcThrottle::new();
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        // One second of mod wheel at 1kHz, throttled to 50Hz
+        let mut sent = Vec::new();
+        for ms in 0..1_000u64 {
+            let value = (ms * 127 / 999) as u8;
+            for m in throttle.process(&[0xB0, 1, value], 50, at(ms)) {
+                sent.push((ms, m));
+            }
+        }
+        let due = throttle.next_due().expect("last value is held back");
+        assert_eq!(due, at(1_000));
+        sent.extend(throttle.poll(due).into_iter().map(|m| (1_000, m)));
+
+        // At most one message per 20ms interval, plus the trailing value
+        assert_eq!(sent.len(), 51);
+        assert!(sent.windows(2).all(|w| w[1].0 - w[0].0 >= 20));
+        assert_eq!(sent.last().unwrap().1, vec![0xB0, 1, 127]);
+        assert_eq!(throttle.next_due(), None);
+    }
+
+    #[test]
+    fn test_cc_throttle_is_per_controller_and_passes_other_messages() {
+        let mut throttle = CcThrottle::new();
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        assert_eq!(throttle.process(&[0xB0, 1, 10], 10, at(0)).len(), 1);
+        // Other controller and other channel are not held back
+        assert_eq!(throttle.process(&[0xB0, 7, 10], 10, at(1)).len(), 1);
+        assert_eq!(throttle.process(&[0xB1, 1, 10], 10, at(2)).len(), 1);
+        // Repeats within 100ms coalesce to the latest value
+        assert!(throttle.process(&[0xB0, 1, 20], 10, at(3)).is_empty());
+        assert!(throttle.process(&[0xB0, 1, 30], 10, at(4)).is_empty());
+        // Notes are never throttled
+        assert_eq!(throttle.process(&[0x90, 60, 100], 10, at(5)), vec![vec![0x90, 60, 100]]);
+        // The next message after the interval brings the coalesced value first
+        assert_eq!(
+            throttle.process(&[0x90, 62, 100], 10, at(100)),
+            vec![vec![0xB0, 1, 30], vec![0x90, 62, 100]]
+        );
+        // Off: everything passes
+        assert_eq!(throttle.process(&[0xB0, 1, 40], 0, at(101)).len(), 1);
+    }
+
+    #[test]
+    fn test_cc_throttle_passes_switch_and_mode_controllers() {
+        let mut throttle = CcThrottle::new();
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        // Pedal down/up, bank change and All Notes Off in quick succession
+        for (ms, msg) in [
+            (0, [0xB0, 64, 127]),
+            (1, [0xB0, 64, 0]),
+            (2, [0xB0, 0, 1]),
+            (3, [0xB0, 32, 2]),
+            (4, [0xB0, 0, 3]),
+            (5, [0xB0, 123, 0]),
+            (6, [0xB0, 123, 0]),
+        ] {
+            assert_eq!(throttle.process(&msg, 10, at(ms)), vec![msg.to_vec()]);
+        }
+        assert_eq!(throttle.next_due(), None);
+        // Continuous controllers are still throttled
+        assert_eq!(throttle.process(&[0xB0, 7, 10], 10, at(7)).len(), 1);
+        assert!(throttle.process(&[0xB0, 7, 20], 10, at(8)).is_empty());
+        let exempt: Vec<u8> = (0..=127).filter(|&cc| !cc_is_throttled(cc)).collect();
+        assert_eq!(exempt, [0, 6, 32, 38, 64, 65, 66, 67, 68, 69, 96, 97, 98, 99, 100, 101, 120, 121, 122, 123, 124, 125, 126, 127]);
+    }
+
+    #[test]
+    fn test_merged_note_needs_every_source_released() {
+        let mut pipeline = PipelineConfig::default();
+        pipeline.channel_remap[1] = 0; // Merge channel 2 onto channel 1
+        let mut merged = MergedNotes::new();
+        let run = |merged: &mut MergedNotes, msg: &[u8]| {
+            let processed = pipeline.process(msg).unwrap();
+            merged.forward(msg, &processed).then_some(processed)
+        };
+
+        // Both sources hold C4, which lands on channel 1 twice
+        assert_eq!(run(&mut merged, &[0x90, 60, 100]), Some(vec![0x90, 60, 100]));
+        assert_eq!(run(&mut merged, &[0x91, 60, 90]), Some(vec![0x90, 60, 90]));
+
+        // The first release is swallowed while channel 2 still holds the note
+        assert_eq!(run(&mut merged, &[0x80, 60, 0]), None);
+        assert_eq!(run(&mut merged, &[0x91, 60, 0]), Some(vec![0x90, 60, 0]));
+        assert_eq!(merged.held_count(), 0);
+
+        // Unmerged notes and non-note messages are unaffected
+        assert_eq!(run(&mut merged, &[0x92, 64, 100]), Some(vec![0x92, 64, 100]));
+        assert_eq!(run(&mut merged, &[0x82, 64, 0]), Some(vec![0x82, 64, 0]));
+        assert_eq!(run(&mut merged, &[0xB1, 7, 100]), Some(vec![0xB0, 7, 100]));
+    }
 }
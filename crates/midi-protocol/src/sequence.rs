@@ -0,0 +1,142 @@
+/// Sequence-number arithmetic for `MidiDataPacket.sequence`.
+///
+/// The sequence is a u16 and wraps every 65536 packets — within seconds at
+/// high packet rates — so it must never be compared with plain `<`/`-`.
+/// Distances are taken modulo 2^16: a forward distance of up to 32768 is
+/// "ahead", anything further is "behind" (a late or reordered packet).
+/// 65535 → 0 is therefore one step forward, not 65535 packets of loss.
+
+/// Signed distance from `from` to `to`, in -32767..=32768.
+pub fn seq_distance(from: u16, to: u16) -> i32 {
+    let forward = to.wrapping_sub(from);
+    if forward <= 0x8000 {
+        forward as i32
+    } else {
+        forward as i32 - 0x1_0000
+    }
+}
+
+/// How one received packet relates to the stream so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqEvent {
+    /// The next expected packet (or the first one seen)
+    InOrder,
+    /// Ahead of the expected packet: `lost` packets are missing before it
+    Gap { lost: u16 },
+    /// Already received (e.g. delivered over both multicast and unicast),
+    /// or too far behind the newest packet to tell
+    Duplicate,
+    /// Behind the newest packet and not received before: a packet that was
+    /// counted lost in a `Gap` has turned up out of order
+    Late,
+}
+
+/// How many packets behind the newest one the tracker remembers
+pub const RECEIVE_WINDOW: u16 = 64;
+
+/// Loss/reorder tracker for one packet stream. Remembers the newest
+/// sequence seen and which of the `RECEIVE_WINDOW` packets before it have
+/// arrived; late packets don't move it back.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceTracker {
+    newest: Option<u16>,
+    /// Bit n set: packet `newest - n` has been received
+    received: u64,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Classify `seq` and advance the tracker past it.
+    pub fn observe(&mut self, seq: u16) -> SeqEvent {
+        let Some(newest) = self.newest else {
+            self.newest = Some(seq);
+            self.received = 1;
+            return SeqEvent::InOrder;
+        };
+        match seq_distance(newest, seq) {
+            0 => SeqEvent::Duplicate,
+            d if d < 0 => {
+                let behind = d.unsigned_abs();
+                if behind >= RECEIVE_WINDOW as u32 || self.received & (1 << behind) != 0 {
+                    return SeqEvent::Duplicate;
+                }
+                self.received |= 1 << behind;
+                SeqEvent::Late
+            }
+            d => {
+                self.newest = Some(seq);
+                self.received = if d >= RECEIVE_WINDOW as i32 { 0 } else { self.received << d };
+                self.received |= 1;
+                if d == 1 {
+                    SeqEvent::InOrder
+                } else {
+                    SeqEvent::Gap { lost: (d - 1) as u16 }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_wraps_modulo_u16() {
+        assert_eq!(seq_distance(65535, 0), 1);
+        assert_eq!(seq_distance(0, 65535), -1);
+        assert_eq!(seq_distance(10, 10 + 0x8000), 0x8000);
+        assert_eq!(seq_distance(10, 11 + 0x8000), -0x7FFF);
+    }
+
+    #[test]
+    fn normal_wrap_is_not_loss() {
+        let mut tracker = SequenceTracker::new();
+        for seq in [65533, 65534, 65535, 0, 1, 2] {
+            assert_eq!(tracker.observe(seq), SeqEvent::InOrder, "seq {seq}");
+        }
+    }
+
+    #[test]
+    fn gap_spanning_the_wrap_counts_only_missing_packets() {
+        let mut tracker = SequenceTracker::new();
+        tracker.observe(65533);
+        // 65534, 65535, 0 and 1 never arrive
+        assert_eq!(tracker.observe(2), SeqEvent::Gap { lost: 4 });
+        assert_eq!(tracker.observe(3), SeqEvent::InOrder);
+    }
+
+    #[test]
+    fn reorder_across_the_wrap_is_late_not_loss() {
+        let mut tracker = SequenceTracker::new();
+        tracker.observe(65534);
+        // 65535 is overtaken by 0
+        assert_eq!(tracker.observe(0), SeqEvent::Gap { lost: 1 });
+        assert_eq!(tracker.observe(65535), SeqEvent::Late);
+        // The late packet doesn't rewind the stream
+        assert_eq!(tracker.observe(1), SeqEvent::InOrder);
+        assert_eq!(tracker.observe(1), SeqEvent::Duplicate);
+    }
+
+    #[test]
+    fn redelivered_packet_behind_the_newest_is_a_duplicate_not_late() {
+        let mut tracker = SequenceTracker::new();
+        for seq in [10, 11, 12] {
+            tracker.observe(seq);
+        }
+        // Multicast and unicast both deliver 11: it was never missing
+        assert_eq!(tracker.observe(11), SeqEvent::Duplicate);
+
+        // 14 overtakes 13; 13 turns up once, then again
+        assert_eq!(tracker.observe(14), SeqEvent::Gap { lost: 1 });
+        assert_eq!(tracker.observe(13), SeqEvent::Late);
+        assert_eq!(tracker.observe(13), SeqEvent::Duplicate);
+
+        // Further back than the window: can't tell, treated as a duplicate
+        tracker.observe(14 + RECEIVE_WINDOW);
+        assert_eq!(tracker.observe(14), SeqEvent::Duplicate);
+    }
+}
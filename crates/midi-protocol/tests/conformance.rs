@@ -0,0 +1,406 @@
+//! Wire-format conformance vectors for the midi-protocol crate.
+//!
+//! Each fixture is the frozen byte encoding of one packet or journal. The
+//! tests assert both directions: serializing the value produces exactly
+//! these bytes, and deserializing these bytes gives the value back. A
+//! failure here means the wire format changed — bump `PROTOCOL_VERSION`
+//! and add a new fixture rather than editing an old one.
+
+use midi_protocol::journal::{decode_journal, encode_journal, encode_journal_for};
+use midi_protocol::midi_state::MidiState;
+use midi_protocol::packets::{
+    FecParityPacket, FocusAction, FocusPacket, HeartbeatPacket, HostRole, HostStoppingPacket,
+    IdentityPacket, MidiDataPacket, PanicPacket,
+};
+
+// ---------------------------------------------------------------------------
+// Fixtures
+// ---------------------------------------------------------------------------
+
+/// ch1: Note On C4 vel 100, CC7 = 100.
+/// ch2: program 5, pitch bend 12288, channel pressure 40.
+const JOURNAL: &[u8] = &[
+    0x00, 0x03, // channel mask: ch1 + ch2
+    0x03, // ch1 flags: notes | cc
+    0x01, 0x3C, 0x64, // 1 note: C4 vel 100
+    0x01, 0x07, 0x64, // 1 cc: CC7 = 100
+    0x1C, // ch2 flags: program | pitch bend | pressure
+    0x05, // program 5
+    0x30, 0x00, // pitch bend 12288
+    0x28, // pressure 40
+];
+
+/// Protocol v4: pedal sections. ch1 with sustain down: C4 (vel 100)
+/// released but held by the pedal, E4 (vel 90) sounding, then sostenuto
+/// pressed, latching both.
+const JOURNAL_V4_PEDAL: &[u8] = &[
+    0x00, 0x01, // channel mask: ch1
+    0x63, // flags: notes | cc | pedal held | sostenuto
+    0x01, 0x40, 0x5A, // 1 note: E4 vel 90
+    0x02, 0x40, 0x7F, 0x42, 0x7F, // 2 ccs: CC64 = 127, CC66 = 127
+    0x01, 0x3C, 0x64, // 1 pedal-held note: C4 vel 100
+    0x02, 0x3C, 0x40, // 2 sostenuto latches: C4, E4
+];
+
+/// The same state for a pre-v4 peer: the pedal-held note is sent as an
+/// active note and the pedal sections are left out.
+const JOURNAL_V4_PEDAL_FOR_V3: &[u8] = &[
+    0x00, 0x01, // channel mask: ch1
+    0x03, // flags: notes | cc
+    0x02, 0x3C, 0x64, 0x40, 0x5A, // 2 notes: C4 vel 100, E4 vel 90
+    0x02, 0x40, 0x7F, 0x42, 0x7F, // 2 ccs: CC64 = 127, CC66 = 127
+];
+
+const MIDI_DATA_NO_JOURNAL: &[u8] = &[
+    0x4D, 0x44, 0x4D, 0x49, // "MDMI"
+    0x12, 0x34, // sequence
+    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // timestamp_us
+    0x01, // host_id
+    0x00, // flags
+    0x00, 0x03, // midi_len
+    0x90, 0x3C, 0x64, // Note On C4 vel 100
+];
+
+const MIDI_DATA_WITH_JOURNAL_HEADER: &[u8] = &[
+    0x4D, 0x44, 0x4D, 0x49, // "MDMI"
+    0xFF, 0xFF, // sequence
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x27, 0x10, // timestamp_us
+    0x02, // host_id
+    0x01, // flags: journal present
+    0x00, 0x03, // midi_len
+    0xB0, 0x07, 0x64, // CC7 = 100
+    0x00, 0x0E, // journal_len (followed by JOURNAL)
+];
+
+/// Protocol v1: no advertised interval.
+const HEARTBEAT_V1: &[u8] = &[
+    0x4D, 0x44, 0x48, 0x42, // "MDHB"
+    0x02, // host_id
+    0x02, // role: standby
+    0xAB, 0xCD, // sequence
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x27, 0x10, // timestamp_us
+];
+
+/// Protocol v2: advertised interval, no priority.
+const HEARTBEAT_V2: &[u8] = &[
+    0x4D, 0x44, 0x48, 0x42, // "MDHB"
+    0x02, // host_id
+    0x02, // role: standby
+    0xAB, 0xCD, // sequence
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x27, 0x10, // timestamp_us
+    0x00, 0x18, // interval_ms
+];
+
+/// Protocol v3: advertised interval and failover priority.
+const HEARTBEAT_V3: &[u8] = &[
+    0x4D, 0x44, 0x48, 0x42, // "MDHB"
+    0x02, // host_id
+    0x02, // role: standby
+    0xAB, 0xCD, // sequence
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x27, 0x10, // timestamp_us
+    0x00, 0x18, // interval_ms
+    0x14, // priority
+];
+
+const IDENTITY: &[u8] = &[
+    0x4D, 0x44, 0x49, 0x44, // "MDID"
+    0x01, // host_id
+    0x05, b'A', b'P', b'C', b'4', b'0', // device_name
+    0x04, b'A', b'k', b'a', b'i', // manufacturer
+    0x09, 0xE8, // vendor_id
+    0x00, 0x28, // product_id
+    0x47, 0x73, 0x00, 0x19, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // sysex_identity
+    0x01, // port_count_in
+    0x02, // port_count_out
+];
+
+const FOCUS_CLAIM: &[u8] = &[
+    0x4D, 0x44, 0x46, 0x43, // "MDFC"
+    0x01, // action: claim
+    0xDE, 0xAD, 0xBE, 0xEF, // client_id
+    0x00, 0x07, // sequence
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2A, // timestamp_us
+];
+
+const PANIC_CHANNEL_5: &[u8] = &[
+    0x4D, 0x44, 0x50, 0x4E, // "MDPN"
+    0x04, // channel 5 (0xFF = all)
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2A, // timestamp_us
+];
+
+const HOST_STOPPING: &[u8] = &[
+    0x4D, 0x44, 0x42, 0x59, // "MDBY"
+    0x01, // host_id
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2A, // timestamp_us
+];
+
+const FEC_PARITY: &[u8] = &[
+    0x4D, 0x44, 0x46, 0x45, // "MDFE"
+    0x01, // host_id
+    0xFF, 0xFE, // base_sequence
+    0x04, // group_size
+    0x00, 0x08, // length_xor
+    0x00, 0x03, // parity_len
+    0xAA, 0x55, 0x0F, // parity
+];
+
+fn journal_state() -> MidiState {
+    let mut state = MidiState::new();
+    state.process_message(&[0x90, 0x3C, 0x64]);
+    state.process_message(&[0xB0, 0x07, 0x64]);
+    state.process_message(&[0xC1, 0x05]);
+    state.process_message(&[0xE1, 0x00, 0x60]);
+    state.process_message(&[0xD1, 0x28]);
+    state
+}
+
+fn pedal_state() -> MidiState {
+    let mut state = MidiState::new();
+    state.process_message(&[0xB0, 0x40, 0x7F]);
+    state.process_message(&[0x90, 0x3C, 0x64]);
+    state.process_message(&[0x80, 0x3C, 0x00]);
+    state.process_message(&[0x90, 0x40, 0x5A]);
+    state.process_message(&[0xB0, 0x42, 0x7F]);
+    state
+}
+
+fn with_journal_bytes() -> Vec<u8> {
+    [MIDI_DATA_WITH_JOURNAL_HEADER, JOURNAL].concat()
+}
+
+// ---------------------------------------------------------------------------
+// Journal
+// ---------------------------------------------------------------------------
+
+#[test]
+fn journal_encodes_to_fixture() {
+    assert_eq!(encode_journal(&journal_state()), JOURNAL);
+}
+
+#[test]
+fn journal_fixture_decodes() {
+    let state = decode_journal(JOURNAL).expect("fixture should decode");
+    assert_eq!(state.channels[0].notes[0x3C], 0x64);
+    assert_eq!(state.channels[0].cc[7], 0x64);
+    assert_eq!(state.channels[1].program, 5);
+    assert_eq!(state.channels[1].pitch_bend, 12288);
+    assert_eq!(state.channels[1].channel_pressure, 40);
+    assert_eq!(state.active_note_count(), 1);
+}
+
+#[test]
+fn journal_v4_pedal_encodes_to_fixture() {
+    assert_eq!(encode_journal(&pedal_state()), JOURNAL_V4_PEDAL);
+    assert_eq!(encode_journal_for(&pedal_state(), 4), JOURNAL_V4_PEDAL);
+}
+
+#[test]
+fn journal_v4_pedal_fixture_decodes() {
+    let state = decode_journal(JOURNAL_V4_PEDAL).expect("fixture should decode");
+    assert_eq!(state.channels[0].notes[0x3C], 0);
+    assert_eq!(state.channels[0].pedal_held[0x3C], 0x64);
+    assert_eq!(state.channels[0].notes[0x40], 0x5A);
+    assert!(state.channels[0].sostenuto_latched[0x3C] && state.channels[0].sostenuto_latched[0x40]);
+}
+
+#[test]
+fn journal_for_v3_peer_leaves_out_pedal_sections() {
+    assert_eq!(encode_journal_for(&pedal_state(), 3), JOURNAL_V4_PEDAL_FOR_V3);
+    let state = decode_journal(JOURNAL_V4_PEDAL_FOR_V3).expect("fixture should decode");
+    assert_eq!(state.channels[0].notes[0x3C], 0x64, "held note keeps sounding");
+    assert_eq!(state.active_note_count(), 2);
+}
+
+// ---------------------------------------------------------------------------
+// MidiDataPacket
+// ---------------------------------------------------------------------------
+
+#[test]
+fn midi_data_without_journal_serializes_to_fixture() {
+    let packet = MidiDataPacket {
+        sequence: 0x1234,
+        timestamp_us: 0x0102_0304_0506_0708,
+        host_id: 1,
+        midi_data: vec![0x90, 0x3C, 0x64],
+        journal: None,
+    };
+    let mut buf = Vec::new();
+    packet.serialize(&mut buf);
+    assert_eq!(buf, MIDI_DATA_NO_JOURNAL);
+}
+
+#[test]
+fn midi_data_without_journal_fixture_deserializes() {
+    let packet = MidiDataPacket::deserialize(MIDI_DATA_NO_JOURNAL).expect("fixture should parse");
+    assert_eq!(packet.sequence, 0x1234);
+    assert_eq!(packet.timestamp_us, 0x0102_0304_0506_0708);
+    assert_eq!(packet.host_id, 1);
+    assert_eq!(packet.midi_data, vec![0x90, 0x3C, 0x64]);
+    assert!(packet.journal.is_none());
+}
+
+#[test]
+fn midi_data_with_journal_serializes_to_fixture() {
+    let packet = MidiDataPacket {
+        sequence: 0xFFFF,
+        timestamp_us: 10_000,
+        host_id: 2,
+        midi_data: vec![0xB0, 0x07, 0x64],
+        journal: Some(JOURNAL.to_vec()),
+    };
+    let mut buf = Vec::new();
+    packet.serialize(&mut buf);
+    assert_eq!(buf, with_journal_bytes());
+}
+
+#[test]
+fn midi_data_with_journal_fixture_deserializes() {
+    let bytes = with_journal_bytes();
+    let packet = MidiDataPacket::deserialize(&bytes).expect("fixture should parse");
+    assert_eq!(packet.sequence, 0xFFFF);
+    assert_eq!(packet.timestamp_us, 10_000);
+    assert_eq!(packet.host_id, 2);
+    assert_eq!(packet.midi_data, vec![0xB0, 0x07, 0x64]);
+    assert_eq!(packet.journal.as_deref(), Some(JOURNAL));
+}
+
+// ---------------------------------------------------------------------------
+// HeartbeatPacket
+// ---------------------------------------------------------------------------
+
+#[test]
+fn heartbeat_v3_serializes_to_fixture() {
+    let packet = HeartbeatPacket {
+        host_id: 2,
+        role: HostRole::Standby,
+        sequence: 0xABCD,
+        timestamp_us: 10_000,
+        interval_ms: 24,
+        priority: 20,
+    };
+    let mut buf = [0u8; HeartbeatPacket::SIZE];
+    packet.serialize(&mut buf);
+    assert_eq!(buf, HEARTBEAT_V3);
+}
+
+#[test]
+fn heartbeat_v3_fixture_deserializes() {
+    let packet = HeartbeatPacket::deserialize(HEARTBEAT_V3).expect("v3 fixture should parse");
+    assert_eq!(packet.host_id, 2);
+    assert_eq!(packet.role, HostRole::Standby);
+    assert_eq!(packet.sequence, 0xABCD);
+    assert_eq!(packet.timestamp_us, 10_000);
+    assert_eq!(packet.interval_ms, 24);
+    assert_eq!(packet.priority, 20);
+}
+
+#[test]
+fn heartbeat_v2_fixture_deserializes_with_host_id_priority() {
+    let packet = HeartbeatPacket::deserialize(HEARTBEAT_V2).expect("v2 fixture should parse");
+    assert_eq!(packet.host_id, 2);
+    assert_eq!(packet.interval_ms, 24);
+    assert_eq!(packet.priority, 2);
+}
+
+#[test]
+fn heartbeat_v1_fixture_deserializes_with_legacy_interval() {
+    let packet = HeartbeatPacket::deserialize(HEARTBEAT_V1).expect("v1 fixture should parse");
+    assert_eq!(packet.host_id, 2);
+    assert_eq!(packet.sequence, 0xABCD);
+    assert_eq!(packet.timestamp_us, 10_000);
+    assert_eq!(packet.interval_ms, 0);
+    assert_eq!(packet.effective_interval_ms(), 3);
+    assert_eq!(packet.priority, 2);
+}
+
+// ---------------------------------------------------------------------------
+// IdentityPacket
+// ---------------------------------------------------------------------------
+
+#[test]
+fn identity_serializes_to_fixture() {
+    let packet = IdentityPacket {
+        host_id: 1,
+        device_name: "APC40".to_string(),
+        manufacturer: "Akai".to_string(),
+        vendor_id: 0x09E8,
+        product_id: 0x0028,
+        sysex_identity: [0x47, 0x73, 0x00, 0x19, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        port_count_in: 1,
+        port_count_out: 2,
+    };
+    let mut buf = Vec::new();
+    packet.serialize(&mut buf);
+    assert_eq!(buf, IDENTITY);
+}
+
+#[test]
+fn identity_fixture_deserializes() {
+    let packet = IdentityPacket::deserialize(IDENTITY).expect("fixture should parse");
+    assert_eq!(packet.host_id, 1);
+    assert_eq!(packet.device_name, "APC40");
+    assert_eq!(packet.manufacturer, "Akai");
+    assert_eq!(packet.vendor_id, 0x09E8);
+    assert_eq!(packet.product_id, 0x0028);
+    assert_eq!(packet.sysex_identity[..6], [0x47, 0x73, 0x00, 0x19, 0x00, 0x01]);
+    assert_eq!(packet.port_count_in, 1);
+    assert_eq!(packet.port_count_out, 2);
+}
+
+// ---------------------------------------------------------------------------
+// Control packets
+// ---------------------------------------------------------------------------
+
+#[test]
+fn focus_claim_matches_fixture() {
+    let packet = FocusPacket {
+        action: FocusAction::Claim,
+        client_id: 0xDEAD_BEEF,
+        sequence: 7,
+        timestamp_us: 42,
+    };
+    let mut buf = [0u8; FocusPacket::SIZE];
+    packet.serialize(&mut buf);
+    assert_eq!(buf, FOCUS_CLAIM);
+
+    let decoded = FocusPacket::deserialize(FOCUS_CLAIM).expect("fixture should parse");
+    assert_eq!(decoded.action, FocusAction::Claim);
+    assert_eq!(decoded.client_id, 0xDEAD_BEEF);
+    assert_eq!(decoded.sequence, 7);
+    assert_eq!(decoded.timestamp_us, 42);
+}
+
+#[test]
+fn panic_matches_fixture() {
+    let packet = PanicPacket { channel: Some(4), timestamp_us: 42 };
+    let mut buf = [0u8; PanicPacket::SIZE];
+    packet.serialize(&mut buf);
+    assert_eq!(buf, PANIC_CHANNEL_5);
+    assert_eq!(PanicPacket::deserialize(PANIC_CHANNEL_5), Some(packet));
+}
+
+#[test]
+fn host_stopping_matches_fixture() {
+    let packet = HostStoppingPacket { host_id: 1, timestamp_us: 42 };
+    let mut buf = [0u8; HostStoppingPacket::SIZE];
+    packet.serialize(&mut buf);
+    assert_eq!(buf, HOST_STOPPING);
+    assert_eq!(HostStoppingPacket::deserialize(HOST_STOPPING), Some(packet));
+}
+
+#[test]
+fn fec_parity_matches_fixture() {
+    let packet = FecParityPacket {
+        host_id: 1,
+        base_sequence: 0xFFFE,
+        group_size: 4,
+        length_xor: 8,
+        parity: vec![0xAA, 0x55, 0x0F],
+    };
+    let mut buf = Vec::new();
+    packet.serialize(&mut buf);
+    assert_eq!(buf, FEC_PARITY);
+    assert_eq!(FecParityPacket::deserialize(FEC_PARITY), Some(packet));
+    // Never mistaken for a data packet
+    assert!(MidiDataPacket::deserialize(FEC_PARITY).is_none());
+}
@@ -97,6 +97,8 @@ fn heartbeat_packet_roundtrip_primary() {
         role: HostRole::Primary,
         sequence: 12345,
         timestamp_us: 7_777_777,
+        interval_ms: 3,
+        priority: 1,
     };
 
     let mut buf = [0u8; HeartbeatPacket::SIZE];
@@ -118,6 +120,8 @@ fn heartbeat_packet_roundtrip_standby() {
         role: HostRole::Standby,
         sequence: 0,
         timestamp_us: 0,
+        interval_ms: 0,
+        priority: 0,
     };
 
     let mut buf = [0u8; HeartbeatPacket::SIZE];
@@ -139,6 +143,8 @@ fn heartbeat_packet_boundary_values() {
         role: HostRole::Primary,
         sequence: u16::MAX,
         timestamp_us: u64::MAX,
+        interval_ms: u16::MAX,
+        priority: u8::MAX,
     };
 
     let mut buf = [0u8; HeartbeatPacket::SIZE];
@@ -148,6 +154,8 @@ fn heartbeat_packet_boundary_values() {
     assert_eq!(decoded.host_id, 255);
     assert_eq!(decoded.sequence, u16::MAX);
     assert_eq!(decoded.timestamp_us, u64::MAX);
+    assert_eq!(decoded.interval_ms, u16::MAX);
+    assert_eq!(decoded.priority, u8::MAX);
 }
 
 // ---------------------------------------------------------------------------
@@ -43,7 +43,7 @@ use crate::menu::{
 };
 #[cfg(target_os = "windows")]
 use crate::process_manager::ProcessStatus;
-use crate::ws_client::{send_command, spawn_ws_thread, WsEvent};
+use crate::ws_client::{send_command, spawn_ws_thread, Keepalive, WsEvent};
 
 
 // ── Windows: atomic flag for system shutdown/logoff signals ──
@@ -489,7 +489,7 @@ fn main() {
     };
 
     // Start the WebSocket background thread
-    let ws_rx = spawn_ws_thread();
+    let ws_rx = spawn_ws_thread(Keepalive::from_env());
 
     // Pre-generate all icon variants (no runtime pixel computation)
     let icon_cache = IconCache::new();
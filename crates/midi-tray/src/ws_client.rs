@@ -3,10 +3,16 @@
 /// Runs on a background thread (sync tungstenite, not async) because the
 /// main thread is occupied by the native GUI event loop. All TCP operations
 /// use explicit timeouts to prevent thread hangs.
+///
+/// A daemon whose health server is wedged can keep the socket open while
+/// sending nothing, so the tray pings it and reports `Disconnected` when
+/// pongs stop arriving. Override the timing with `MIDINET_TRAY_PING_MS` and
+/// `MIDINET_TRAY_PONG_TIMEOUT_MS`.
 
+use std::io;
 use std::net::{SocketAddr, TcpStream};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tungstenite::Message;
 use tracing::{debug, info, warn};
@@ -23,17 +29,83 @@ pub enum WsEvent {
     Connected,
 }
 
+/// Ping/pong timing for the daemon connection.
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    /// How often to ping the daemon
+    pub ping_interval: Duration,
+    /// No pong for this long = treat the daemon as gone
+    pub pong_timeout: Duration,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(2),
+            pong_timeout: Duration::from_secs(6),
+        }
+    }
+}
+
+impl Keepalive {
+    /// Defaults, overridden by `MIDINET_TRAY_PING_MS` / `MIDINET_TRAY_PONG_TIMEOUT_MS`.
+    pub fn from_env() -> Self {
+        let ms = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|&v| v > 0)
+                .map(Duration::from_millis)
+        };
+        let defaults = Self::default();
+        Self {
+            ping_interval: ms("MIDINET_TRAY_PING_MS").unwrap_or(defaults.ping_interval),
+            pong_timeout: ms("MIDINET_TRAY_PONG_TIMEOUT_MS").unwrap_or(defaults.pong_timeout),
+        }
+    }
+}
+
+/// Keepalive bookkeeping for one connection.
+struct KeepaliveTimer {
+    config: Keepalive,
+    last_ping: Instant,
+    last_pong: Instant,
+}
+
+impl KeepaliveTimer {
+    fn new(config: Keepalive, now: Instant) -> Self {
+        Self { config, last_ping: now, last_pong: now }
+    }
+
+    fn pong_received(&mut self, now: Instant) {
+        self.last_pong = now;
+    }
+
+    /// True (and the ping is counted as sent) when the next ping is due.
+    fn ping_due(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.last_ping) < self.config.ping_interval {
+            return false;
+        }
+        self.last_ping = now;
+        true
+    }
+
+    fn timed_out(&self, now: Instant) -> bool {
+        now.duration_since(self.last_pong) >= self.config.pong_timeout
+    }
+}
+
 /// Spawn the WebSocket client thread. Returns a receiver for events.
-pub fn spawn_ws_thread() -> mpsc::Receiver<WsEvent> {
+pub fn spawn_ws_thread(keepalive: Keepalive) -> mpsc::Receiver<WsEvent> {
     let (tx, rx) = mpsc::channel();
     std::thread::Builder::new()
         .name("midinet-ws".into())
-        .spawn(move || ws_loop(tx))
+        .spawn(move || ws_loop(tx, keepalive))
         .expect("failed to spawn WS thread");
     rx
 }
 
-fn ws_loop(tx: mpsc::Sender<WsEvent>) {
+fn ws_loop(tx: mpsc::Sender<WsEvent>, keepalive: Keepalive) {
     let url = format!("ws://127.0.0.1:{}/ws", DEFAULT_HEALTH_PORT);
     let addr: SocketAddr = format!("127.0.0.1:{}", DEFAULT_HEALTH_PORT)
         .parse()
@@ -56,7 +128,7 @@ fn ws_loop(tx: mpsc::Sender<WsEvent>) {
             }
         };
 
-        // Set read timeout so socket.read() doesn't block forever if daemon freezes
+        // Set read timeout so the handshake doesn't block forever if daemon freezes
         let _ = tcp.set_read_timeout(Some(Duration::from_secs(10)));
 
         // Upgrade TCP to WebSocket
@@ -64,8 +136,26 @@ fn ws_loop(tx: mpsc::Sender<WsEvent>) {
             Ok((mut socket, _response)) => {
                 let _ = tx.send(WsEvent::Connected);
                 backoff = Duration::from_secs(1); // reset on success
+                // Short read timeout from here on, so the loop wakes up to ping
+                // and check for a frozen daemon even when nothing arrives
+                let _ = socket.get_ref().set_read_timeout(Some(
+                    (keepalive.ping_interval / 2).clamp(Duration::from_millis(100), Duration::from_secs(1)),
+                ));
+                let mut timer = KeepaliveTimer::new(keepalive, Instant::now());
 
                 loop {
+                    let now = Instant::now();
+                    if timer.timed_out(now) {
+                        warn!(
+                            timeout_ms = keepalive.pong_timeout.as_millis() as u64,
+                            "Daemon stopped answering pings — treating as disconnected"
+                        );
+                        break;
+                    }
+                    if timer.ping_due(now) && socket.send(Message::Ping(Vec::new())).is_err() {
+                        break;
+                    }
+
                     match socket.read() {
                         Ok(Message::Text(text)) => {
                             match serde_json::from_str::<ClientHealthSnapshot>(&text) {
@@ -86,7 +176,13 @@ fn ws_loop(tx: mpsc::Sender<WsEvent>) {
                             info!("Daemon closed WebSocket connection");
                             break;
                         }
-                        Ok(_) => {} // Binary, Pong, Frame
+                        Ok(Message::Pong(_)) => timer.pong_received(Instant::now()),
+                        Ok(_) => {} // Binary, Frame
+                        Err(tungstenite::Error::Io(e))
+                            if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) =>
+                        {
+                            // Read timeout: nothing arrived, go round to the keepalive checks
+                        }
                         Err(e) => {
                             warn!("WebSocket read error: {}", e);
                             break;
@@ -136,3 +232,34 @@ pub fn send_command(cmd: &midi_protocol::health::TrayCommand) {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_pongs_time_out_the_connection() {
+        let config = Keepalive {
+            ping_interval: Duration::from_secs(2),
+            pong_timeout: Duration::from_secs(6),
+        };
+        let t0 = Instant::now();
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+        let mut timer = KeepaliveTimer::new(config, t0);
+
+        // Healthy daemon: pings go out on schedule and pongs keep it alive
+        assert!(!timer.ping_due(at(1_000)));
+        assert!(timer.ping_due(at(2_000)));
+        assert!(!timer.ping_due(at(2_500)));
+        timer.pong_received(at(2_010));
+        assert!(timer.ping_due(at(4_000)));
+        timer.pong_received(at(4_010));
+        assert!(!timer.timed_out(at(9_000)));
+
+        // Daemon wedges: pings continue, pongs stop, and the timeout fires
+        assert!(timer.ping_due(at(6_000)));
+        assert!(timer.ping_due(at(8_000)));
+        assert!(!timer.timed_out(at(10_009)));
+        assert!(timer.timed_out(at(10_010)));
+    }
+}